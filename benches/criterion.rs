@@ -32,6 +32,7 @@ fn parse_glsl(stage: naga::ShaderStage, inputs: &[Box<[u8]>]) {
     let options = naga::front::glsl::Options {
         stage,
         defines: Default::default(),
+        include_resolver: None,
     };
     for input in inputs.iter() {
         let string = std::str::from_utf8(input).unwrap();
@@ -187,6 +188,20 @@ fn backends(c: &mut Criterion) {
         });
     });
     #[cfg(feature = "spv-out")]
+    group.bench_function("spv-reuse", |b| {
+        // Like "spv", but with one writer for all the modules, as clients
+        // translating many modules should.
+        let options = naga::back::spv::Options::default();
+        let mut writer = naga::back::spv::Writer::new(&options).unwrap();
+        b.iter(|| {
+            let mut data = Vec::new();
+            for &(ref module, ref info) in inputs.iter() {
+                writer.write(module, info, None, &None, &mut data).unwrap();
+                data.clear();
+            }
+        });
+    });
+    #[cfg(feature = "spv-out")]
     group.bench_function("spv-separate", |b| {
         b.iter(|| {
             let mut data = Vec::new();
@@ -245,6 +260,7 @@ fn backends(c: &mut Criterion) {
                 writer_flags: naga::back::glsl::WriterFlags::empty(),
                 binding_map: Default::default(),
                 zero_initialize_workgroup_memory: true,
+                push_constant_binding: None,
             };
             for &(ref module, ref info) in inputs.iter() {
                 for ep in module.entry_points.iter() {
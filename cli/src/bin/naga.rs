@@ -1,7 +1,15 @@
 #![allow(clippy::manual_strip)]
 #[allow(unused_imports)]
 use std::fs;
-use std::{error::Error, fmt, io::Read, path::Path, str::FromStr};
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt,
+    io::Read,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
 
 /// Translate shaders to different formats.
 #[derive(argh::FromArgs, Debug, Clone)]
@@ -56,17 +64,75 @@ struct Args {
     #[argh(option)]
     profile: Option<GlslProfileArg>,
 
+    /// when writing GLSL, fail if a uniform or storage block's layout puts
+    /// a member somewhere other than its offset in the module, rather than
+    /// just warning
+    #[argh(switch)]
+    check_glsl_block_layouts: bool,
+
     /// the shader model to use if targeting HLSL
     ///
-    /// May be `50`, 51`, or `60`
+    /// May be `50`, `51`, or `60` through `67`
     #[argh(option)]
     shader_model: Option<ShaderModelArg>,
 
-    /// if the selected frontends/backends support coordinate space conversions,
-    /// disable them
+    /// the shader model to use if targeting HLSL, the same as `--shader-model`
+    #[argh(option)]
+    hlsl_shader_model: Option<ShaderModelArg>,
+
+    /// the SPIR-V version to write, for example `1.3`
+    #[argh(option)]
+    spv_version: Option<VersionArg>,
+
+    /// the Metal Shading Language version to write, for example `2.4`
+    #[argh(option)]
+    msl_version: Option<VersionArg>,
+
+    /// the environment the output will run in, choosing the options its
+    /// backend needs.
+    ///
+    /// May be `vulkan1.0` through `vulkan1.3`, `webgl2`, `d3d12-sm<model>`
+    /// (for example `d3d12-sm6.0`), or `metal<version>` (for example
+    /// `metal2.4`). Each sets the backend's version, and rejects modules
+    /// using capabilities that version lacks, and outputs in formats the
+    /// environment doesn't run; `webgl2` also writes `gl_PointSize`, which
+    /// WebGL needs to draw points. The other version options may choose an
+    /// older version than this, but not a newer one.
+    #[argh(option)]
+    target_env: Option<TargetEnvArg>,
+
+    /// the clip-space convention to convert vertex positions for.
+    ///
+    /// May be `wgpu` (the default), `vulkan`, or `gl`:
+    ///
+    /// - `wgpu` does what wgpu expects: reading SPIR-V negates the Y
+    ///   coordinate of positions, writing SPIR-V negates it back, and writing
+    ///   GLSL negates Y and maps Z from [0, 1] to [-1, 1].
+    ///
+    /// - `vulkan` passes positions through SPIR-V, in either direction,
+    ///   unchanged, and writing GLSL negates Y and maps Z as for `wgpu`.
+    ///
+    /// - `gl` leaves positions alone everywhere.
+    ///
+    /// Other frontends and backends never change positions.
+    #[argh(option)]
+    coordinate_space: Option<CoordinateSpaceArg>,
+
+    /// leave positions read from or written to SPIR-V alone, the same as
+    /// `--coordinate-space vulkan`
     #[argh(switch)]
     keep_coordinate_space: bool,
 
+    /// when reading SPIR-V, accept input attachments by turning them into
+    /// sampled 2D textures read at the fragment's position
+    #[argh(switch)]
+    input_attachments_as_textures: bool,
+
+    /// when reading SPIR-V, skip instructions from `NonSemantic.*` extended
+    /// instruction sets, like debug info
+    #[argh(switch)]
+    ignore_non_semantic_instructions: bool,
+
     /// in dot output, include only the control flow graph
     #[argh(switch)]
     dot_cfg_only: bool,
@@ -75,6 +141,22 @@ struct Args {
     #[argh(option)]
     stdin_file_path: Option<String>,
 
+    /// the format of the input, instead of guessing it from the input file's
+    /// extension: one of `wgsl`, `spv`, `bin`, `vert`, `frag` or `comp`.
+    ///
+    /// Without an input file, this reads the input from stdin.
+    #[argh(option)]
+    input_format: Option<String>,
+
+    /// the format to write all outputs in, instead of guessing it from their
+    /// extensions: one of `wgsl`, `spv`, `spvasm` (or `spv-asm`), `metal`,
+    /// `hlsl`, `vert`, `frag`, `comp`, `dot`, `txt` or `bin`.
+    ///
+    /// This is needed to write to stdout. With `--input-format` too, a lone
+    /// `-` reads stdin and writes stdout.
+    #[argh(option)]
+    output_format: Option<String>,
+
     /// generate debug symbols, only works for spv-out for now
     #[argh(switch, short = 'g')]
     generate_debug_symbols: bool,
@@ -93,18 +175,77 @@ struct Args {
     #[argh(option)]
     before_compaction: Option<String>,
 
+    /// apply these math functions to vectors one component at a time, for
+    /// drivers that miscompile their vector forms.
+    ///
+    /// The functions are named as in WGSL and separated by commas, like
+    /// `sin,cos,pow`, and must be componentwise.
+    #[argh(option)]
+    scalarize_math: Option<MathFunctionsArg>,
+
+    /// apply these math functions to whole vectors where the input applies
+    /// them to each component, for targets that prefer the vector forms.
+    ///
+    /// The functions are given as for `--scalarize-math`.
+    #[argh(option)]
+    vectorize_math: Option<MathFunctionsArg>,
+
+    /// clamp the operands of float-to-integer conversions, so that they
+    /// saturate on every backend, as WGSL requires.
+    #[argh(switch)]
+    saturate_float_to_int: bool,
+
+    /// rename all identifiers other than entry points to short meaningless
+    /// names, and drop source locations, before writing output.
+    ///
+    /// This is incompatible with `--generate-debug-symbols`, which embeds the
+    /// input in the output.
+    #[argh(switch)]
+    obfuscate: bool,
+
+    /// print static cost estimates for each entry point to stderr: ALU
+    /// operations, texture samples, branches and loop nesting depth.
+    #[argh(switch)]
+    stats: bool,
+
+    /// an output file, in addition to any given after the input file.
+    ///
+    /// May be repeated. The input is parsed and validated once, and then
+    /// written to every output. A failure to write one output is reported,
+    /// and doesn't stop the others from being written.
+    #[argh(option, short = 'o')]
+    output: Vec<String>,
+
+    /// a directory of shaders to watch, instead of an input file.
+    ///
+    /// Every shader in the directory and its subdirectories is translated
+    /// once, and again whenever it changes, until naga is interrupted. Each
+    /// round ends with a summary of what was translated and what failed.
+    #[argh(option)]
+    watch: Option<String>,
+
+    /// the directory to write translated shaders to, in `--watch` mode.
+    ///
+    /// Each output has the same path relative to this directory as its input
+    /// has to the watched one, with the extension of `--output-format`. If
+    /// omitted, shaders are only validated.
+    #[argh(option)]
+    out_dir: Option<String>,
+
     /// show version
     #[argh(switch)]
     version: bool,
 
     /// the input and output files.
     ///
-    /// First positional argument is the input file. If not specified, the
-    /// input will be read from stdin. In the case, --stdin-file-path must also
-    /// be specified.
+    /// First positional argument is the input file. If not specified, or
+    /// `-`, the input will be read from stdin. In the case, --stdin-file-path
+    /// or --input-format must also be specified.
     ///
-    /// The rest arguments are the output files. If not specified, only
-    /// validation will be performed.
+    /// The rest arguments are the output files, to which `--output` adds more.
+    /// An output of `-` is written to stdout, in the format given by
+    /// --output-format. If there are no outputs, only validation will be
+    /// performed.
     #[argh(positional)]
     files: Vec<String>,
 }
@@ -144,6 +285,13 @@ impl FromStr for ShaderModelArg {
             "50" => ShaderModel::V5_0,
             "51" => ShaderModel::V5_1,
             "60" => ShaderModel::V6_0,
+            "61" => ShaderModel::V6_1,
+            "62" => ShaderModel::V6_2,
+            "63" => ShaderModel::V6_3,
+            "64" => ShaderModel::V6_4,
+            "65" => ShaderModel::V6_5,
+            "66" => ShaderModel::V6_6,
+            "67" => ShaderModel::V6_7,
             _ => return Err(format!("Invalid value for --shader-model: {s}")),
         }))
     }
@@ -168,12 +316,289 @@ impl FromStr for GlslProfileArg {
     }
 }
 
+/// Newtype so we can implement [`FromStr`] for a `(major, minor)` version.
+#[derive(Clone, Copy, Debug)]
+struct VersionArg((u8, u8));
+
+impl FromStr for VersionArg {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (major, minor) = s
+            .split_once('.')
+            .ok_or_else(|| format!("Version should be `major.minor`: {s}"))?;
+        let parse = |n: &str| {
+            n.parse::<u8>()
+                .map_err(|_| format!("Invalid version number: {s}"))
+        };
+        Ok(Self((parse(major)?, parse(minor)?)))
+    }
+}
+
+/// An environment that `--target-env` can choose options for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum TargetEnvArg {
+    /// Vulkan with the given SPIR-V version.
+    Vulkan((u8, u8)),
+    WebGl2,
+    D3d12(naga::back::hlsl::ShaderModel),
+    /// Metal with the given Metal Shading Language version.
+    Metal((u8, u8)),
+}
+
+impl FromStr for TargetEnvArg {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.to_lowercase();
+        Ok(match s.as_str() {
+            "vulkan1.0" => Self::Vulkan((1, 0)),
+            "vulkan1.1" => Self::Vulkan((1, 3)),
+            "vulkan1.2" => Self::Vulkan((1, 5)),
+            "vulkan1.3" => Self::Vulkan((1, 6)),
+            "webgl2" => Self::WebGl2,
+            _ => {
+                if let Some(model) = s.strip_prefix("d3d12-sm") {
+                    Self::D3d12(ShaderModelArg::from_str(&model.replace('.', ""))?.0)
+                } else if let Some(version) = s.strip_prefix("metal") {
+                    Self::Metal(VersionArg::from_str(version)?.0)
+                } else {
+                    return Err(format!("Unknown target environment: {s}"));
+                }
+            }
+        })
+    }
+}
+
+/// Newtype so we can implement [`FromStr`] for a list of componentwise
+/// [`naga::MathFunction`]s.
+#[derive(Clone, Debug)]
+struct MathFunctionsArg(Vec<naga::MathFunction>);
+
+impl FromStr for MathFunctionsArg {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |name: &str| match naga::MathFunction::from_wgsl_ident(name.trim()) {
+            Some(fun) if fun.is_componentwise() => Ok(fun),
+            Some(_) => Err(format!("Math function is not componentwise: {name}")),
+            None => Err(format!("Unknown math function: {name}")),
+        };
+        s.split(',').map(parse).collect::<Result<_, _>>().map(Self)
+    }
+}
+
+/// The GLSL version WebGL 2 takes.
+const WEBGL2: naga::back::glsl::Version = naga::back::glsl::Version::Embedded {
+    version: 300,
+    is_webgl: true,
+};
+
+impl TargetEnvArg {
+    /// Set up `params` for this environment.
+    fn apply(self, params: &mut Parameters) {
+        match self {
+            Self::Vulkan(version) => {
+                params.spv_out.lang_version = version;
+                params.spv_out.target_environment = naga::back::spv::TargetEnvironment::Vulkan;
+            }
+            Self::WebGl2 => {
+                params.glsl.version = WEBGL2;
+                params
+                    .glsl
+                    .writer_flags
+                    .insert(naga::back::glsl::WriterFlags::FORCE_POINT_SIZE);
+            }
+            Self::D3d12(model) => params.hlsl.shader_model = model,
+            Self::Metal(version) => params.msl.lang_version = version,
+        }
+    }
+
+    /// Check that the version options haven't chosen versions newer than
+    /// the environment supports.
+    fn check(self, params: &Parameters) -> Result<(), CliError> {
+        let newer = match self {
+            Self::Vulkan(version) => params.spv_out.lang_version > version,
+            Self::WebGl2 => {
+                if params.glsl.version != WEBGL2 {
+                    return Err(CliError(
+                        "`--profile` can't be used with `--target-env webgl2`",
+                    ));
+                }
+                false
+            }
+            Self::D3d12(model) => params.hlsl.shader_model > model,
+            Self::Metal(version) => params.msl.lang_version > version,
+        };
+        if newer {
+            return Err(CliError(
+                "The version options chose a version newer than `--target-env` supports",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Whether this environment runs output in `format`.
+    ///
+    /// Dumps of the IR are always allowed.
+    fn runs(self, format: &str) -> bool {
+        let formats: &[&str] = match self {
+            Self::Vulkan(_) => &["spv", "spvasm", "spv-asm"],
+            // WebGL 2 has no compute shaders.
+            Self::WebGl2 => &["vert", "frag"],
+            Self::D3d12(_) => &["hlsl"],
+            Self::Metal(_) => &["metal"],
+        };
+        formats.contains(&format) || ["txt", "dot", "bin"].contains(&format)
+    }
+
+    /// The capabilities this environment doesn't have.
+    ///
+    /// These follow the versions at which the backends start supporting
+    /// each capability.
+    fn unsupported_capabilities(self) -> naga::valid::Capabilities {
+        use naga::back::hlsl::ShaderModel as Sm;
+        use naga::valid::Capabilities as C;
+
+        let mut unsupported = C::empty();
+        let mut unsupported_before = |supported: bool, caps| {
+            if !supported {
+                unsupported |= caps;
+            }
+        };
+        match self {
+            Self::Vulkan(version) => {
+                // `OpGroupNonUniform*` arrived in SPIR-V 1.3.
+                unsupported_before(version >= (1, 3), C::SUBGROUP);
+            }
+            // WebGL 2 has none of the optional capabilities, and only allows
+            // constant indices into uniform arrays.
+            Self::WebGl2 => return C::all(),
+            Self::D3d12(model) => {
+                unsupported_before(model >= Sm::V6_0, C::SUBGROUP | C::SHADER_INT64);
+                unsupported_before(model >= Sm::V6_1, C::MULTIVIEW);
+                unsupported_before(model >= Sm::V6_2, C::FLOAT16);
+                unsupported_before(model >= Sm::V6_5, C::RAY_QUERY);
+                unsupported_before(
+                    model >= Sm::V6_6,
+                    C::SHADER_INT64_ATOMIC_MIN_MAX | C::SHADER_INT64_ATOMIC_ALL_OPS,
+                );
+            }
+            Self::Metal(version) => {
+                unsupported_before(version >= (2, 1), C::SUBGROUP);
+                unsupported_before(version >= (2, 2), C::PRIMITIVE_INDEX);
+                unsupported_before(
+                    version >= (2, 4),
+                    C::RAY_QUERY | C::SHADER_INT64_ATOMIC_MIN_MAX | C::SHADER_INT64_ATOMIC_ALL_OPS,
+                );
+                unsupported_before(version >= (3, 1), C::TEXTURE_ATOMIC);
+            }
+        }
+        unsupported
+    }
+}
+
+/// A clip-space convention that `--coordinate-space` can convert for.
+#[derive(Clone, Copy, Debug, Default)]
+enum CoordinateSpaceArg {
+    #[default]
+    Wgpu,
+    Vulkan,
+    Gl,
+}
+
+impl CoordinateSpaceArg {
+    /// Whether positions are converted when reading or writing SPIR-V.
+    const fn adjusts_spv(self) -> bool {
+        match self {
+            Self::Wgpu => true,
+            Self::Vulkan | Self::Gl => false,
+        }
+    }
+
+    /// Whether positions are converted when writing GLSL.
+    const fn adjusts_glsl(self) -> bool {
+        match self {
+            Self::Wgpu | Self::Vulkan => true,
+            Self::Gl => false,
+        }
+    }
+
+    /// The convention `--coordinate-space` and `--keep-coordinate-space`
+    /// choose between them.
+    fn from_args(space: Option<Self>, keep: bool) -> Result<Self, CliError> {
+        match (space, keep) {
+            (None, false) => Ok(Self::default()),
+            (None, true) | (Some(Self::Vulkan), true) => Ok(Self::Vulkan),
+            (Some(space), false) => Ok(space),
+            (Some(_), true) => Err(CliError(
+                "--keep-coordinate-space conflicts with the given --coordinate-space",
+            )),
+        }
+    }
+
+    /// Set the backend flags in `params` that convert positions.
+    fn apply(self, params: &mut Parameters) {
+        params.spv_out.flags.set(
+            naga::back::spv::WriterFlags::ADJUST_COORDINATE_SPACE,
+            self.adjusts_spv(),
+        );
+        params.glsl.writer_flags.set(
+            naga::back::glsl::WriterFlags::ADJUST_COORDINATE_SPACE,
+            self.adjusts_glsl(),
+        );
+    }
+}
+
+impl FromStr for CoordinateSpaceArg {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.to_lowercase().as_str() {
+            "wgpu" => Self::Wgpu,
+            "vulkan" => Self::Vulkan,
+            "gl" => Self::Gl,
+            _ => return Err(format!("Invalid value for --coordinate-space: {s}")),
+        })
+    }
+}
+
+/// The IR passes to run on a validated module, before writing it out.
+#[derive(Default)]
+struct Passes {
+    scalarize_math: Vec<naga::MathFunction>,
+    vectorize_math: Vec<naga::MathFunction>,
+    saturate_float_to_int: bool,
+}
+
+impl Passes {
+    fn is_empty(&self) -> bool {
+        self.scalarize_math.is_empty()
+            && self.vectorize_math.is_empty()
+            && !self.saturate_float_to_int
+    }
+
+    /// Run the passes on `module`, which must have passed validation.
+    fn run(&self, module: &mut naga::Module) {
+        if !self.scalarize_math.is_empty() {
+            naga::proc::scalarize_math(module, &self.scalarize_math);
+        }
+        if !self.vectorize_math.is_empty() {
+            naga::proc::vectorize_math(module, &self.vectorize_math);
+        }
+        if self.saturate_float_to_int {
+            naga::proc::saturate_float_to_int(module);
+        }
+    }
+}
+
 #[derive(Default)]
 struct Parameters<'a> {
     validation_flags: naga::valid::ValidationFlags,
+    passes: Passes,
     bounds_check_policies: naga::proc::BoundsCheckPolicies,
     entry_point: Option<String>,
-    keep_coordinate_space: bool,
+    coordinate_space: CoordinateSpaceArg,
     spv_in: naga::front::spv::Options,
     spv_out: naga::back::spv::Options<'a>,
     dot: naga::back::dot::Options,
@@ -182,11 +607,6 @@ struct Parameters<'a> {
     hlsl: naga::back::hlsl::Options,
 }
 
-trait PrettyResult {
-    type Target;
-    fn unwrap_pretty(self) -> Self::Target;
-}
-
 fn print_err(error: &dyn Error) {
     eprint!("{error}");
 
@@ -203,19 +623,6 @@ fn print_err(error: &dyn Error) {
     }
 }
 
-impl<T, E: Error> PrettyResult for Result<T, E> {
-    type Target = T;
-    fn unwrap_pretty(self) -> T {
-        match self {
-            Result::Ok(value) => value,
-            Result::Err(error) => {
-                print_err(&error);
-                std::process::exit(1);
-            }
-        }
-    }
-}
-
 fn main() {
     if let Err(e) = run() {
         print_err(e.as_ref());
@@ -246,17 +653,21 @@ fn run() -> Result<(), Box<dyn std::error::Error>> {
         println!("{}", env!("CARGO_PKG_VERSION"));
         return Ok(());
     }
-    let (input_path, input) = if let Some(path) = args.files.first() {
-        let path = Path::new(path);
-        (path, fs::read(path)?)
-    } else if let Some(path) = &args.stdin_file_path {
-        let mut input = vec![];
-        std::io::stdin().lock().read_to_end(&mut input)?;
-        (Path::new(path), input)
+    // A lone `-` with both formats given is a pipeline, reading stdin and
+    // writing stdout.
+    let is_pipeline = args.files.len() == 1
+        && args.files[0] == "-"
+        && args.input_format.is_some()
+        && args.output_format.is_some();
+    let output_paths: Vec<&str> = if is_pipeline {
+        &args.files[..]
     } else {
-        return Err(CliError("Input file path is not specified").into());
-    };
-    let output_paths = args.files.get(1..).unwrap_or(&[]);
+        args.files.get(1..).unwrap_or(&[])
+    }
+    .iter()
+    .chain(&args.output)
+    .map(String::as_str)
+    .collect();
 
     // Update parameters from commandline arguments
     if let Some(bits) = args.validate {
@@ -279,93 +690,127 @@ fn run() -> Result<(), Box<dyn std::error::Error>> {
         None => params.bounds_check_policies.index,
     };
 
+    params.coordinate_space =
+        CoordinateSpaceArg::from_args(args.coordinate_space, args.keep_coordinate_space)?;
+
     params.spv_in = naga::front::spv::Options {
-        adjust_coordinate_space: !args.keep_coordinate_space,
+        adjust_coordinate_space: params.coordinate_space.adjusts_spv(),
         strict_capabilities: false,
         block_ctx_dump_prefix: args.block_ctx_dir.map(std::path::PathBuf::from),
+        input_attachments_as_textures: args.input_attachments_as_textures,
+        ignore_non_semantic_instructions: args.ignore_non_semantic_instructions,
+    };
+
+    params.passes = Passes {
+        scalarize_math: args.scalarize_math.map_or_else(Vec::new, |arg| arg.0),
+        vectorize_math: args.vectorize_math.map_or_else(Vec::new, |arg| arg.0),
+        saturate_float_to_int: args.saturate_float_to_int,
     };
 
     params.entry_point = args.entry_point;
+    if let Some(env) = args.target_env {
+        env.apply(&mut params);
+    }
     if let Some(version) = args.profile {
         params.glsl.version = version.0;
     }
-    if let Some(model) = args.shader_model {
+    if let Some(model) = args.hlsl_shader_model.or(args.shader_model) {
         params.hlsl.shader_model = model.0;
     }
-    params.keep_coordinate_space = args.keep_coordinate_space;
-
+    if let Some(version) = args.spv_version {
+        params.spv_out.lang_version = version.0;
+    }
+    if let Some(version) = args.msl_version {
+        params.msl.lang_version = version.0;
+    }
+    if let Some(env) = args.target_env {
+        env.check(&params)?;
+        let formats = output_paths
+            .iter()
+            .filter_map(|path| output_format(path, args.output_format.as_deref()).ok())
+            .chain(args.output_format.as_deref());
+        for format in formats {
+            if !env.runs(format) {
+                return Err(CliError("An output format doesn't run in the `--target-env`").into());
+            }
+        }
+    }
+    // The target environment limits the capabilities the module may use.
+    let env_caps = match args.target_env {
+        Some(env) => !env.unsupported_capabilities(),
+        None => naga::valid::Capabilities::all(),
+    };
     params.dot.cfg_only = args.dot_cfg_only;
 
     params.spv_out.bounds_check_policies = params.bounds_check_policies;
-    params.spv_out.flags.set(
-        naga::back::spv::WriterFlags::ADJUST_COORDINATE_SPACE,
-        !params.keep_coordinate_space,
+    params.hlsl.bounds_check_policies = params.bounds_check_policies;
+    params.coordinate_space.apply(&mut params);
+    params.glsl.writer_flags.set(
+        naga::back::glsl::WriterFlags::CHECK_BLOCK_LAYOUTS,
+        args.check_glsl_block_layouts,
     );
 
-    let (mut module, input_text) = match Path::new(&input_path)
-        .extension()
-        .ok_or(CliError("Input filename has no extension"))?
-        .to_str()
-        .ok_or(CliError("Input filename not valid unicode"))?
-    {
-        "bin" => (bincode::deserialize(&input)?, None),
-        "spv" => naga::front::spv::parse_u8_slice(&input, &params.spv_in).map(|m| (m, None))?,
-        "wgsl" => {
-            let input = String::from_utf8(input)?;
-            let result = naga::front::wgsl::parse_str(&input);
-            match result {
-                Ok(v) => (v, Some(input)),
-                Err(ref e) => {
-                    let path = input_path.to_string_lossy();
-                    e.emit_to_stderr_with_path(&input, &path);
-                    return Err(CliError("Could not parse WGSL").into());
-                }
+    if let Some(ref dir) = args.watch {
+        if !args.files.is_empty() || !args.output.is_empty() {
+            return Err(CliError("`--watch` can't be used with input or output files").into());
+        }
+        if args.generate_debug_symbols {
+            return Err(CliError("`--watch` can't be used with `--generate-debug-symbols`").into());
+        }
+        if args.input_format.is_some() {
+            // Each shader is read in the format its extension names.
+            return Err(CliError("`--watch` can't be used with `--input-format`").into());
+        }
+        let output_format = args.output_format.as_deref();
+        if args.out_dir.is_some() && output_format.is_none() {
+            return Err(CliError("`--out-dir` requires --output-format").into());
+        }
+        if let Some(ref out_dir) = args.out_dir {
+            // Outputs written there could overwrite their inputs, or be
+            // translated again as inputs themselves.
+            if is_within(Path::new(out_dir), Path::new(dir))? {
+                return Err(
+                    CliError("`--out-dir` can't be the `--watch` directory or inside it").into(),
+                );
             }
         }
-        ext @ ("vert" | "frag" | "comp" | "glsl") => {
-            let input = String::from_utf8(input)?;
-            let mut parser = naga::front::glsl::Frontend::default();
-
-            (
-                parser
-                    .parse(
-                        &naga::front::glsl::Options {
-                            stage: match ext {
-                                "vert" => naga::ShaderStage::Vertex,
-                                "frag" => naga::ShaderStage::Fragment,
-                                "comp" => naga::ShaderStage::Compute,
-                                "glsl" => {
-                                    let internal_name = input_path.to_string_lossy();
-                                    match Path::new(&internal_name[..internal_name.len()-5])
-                                        .extension()
-                                        .ok_or(CliError("Input filename ending with .glsl has no internal extension"))?
-                                        .to_str()
-                                        .ok_or(CliError("Input filename not valid unicode"))?
-                                    {
-                                        "vert" => naga::ShaderStage::Vertex,
-                                        "frag" => naga::ShaderStage::Fragment,
-                                        "comp" => naga::ShaderStage::Compute,
-                                        _ => unreachable!(),
-                                    }
-                                },
-                                _ => unreachable!(),
-                            },
-                            defines: Default::default(),
-                        },
-                        &input,
-                    )
-                    .unwrap_or_else(|errors| {
-                        let filename = input_path.file_name().and_then(std::ffi::OsStr::to_str);
-                        emit_glsl_parser_error(errors, filename.unwrap_or("glsl"), &input);
-                        std::process::exit(1);
-                    }),
-                Some(input),
-            )
+        let caps = env_caps & !unsupported_capabilities(output_format);
+        return Watch {
+            dir: Path::new(dir),
+            out_dir: args.out_dir.as_deref().map(Path::new),
+            output_format,
+            compact: args.compact,
+            obfuscate: args.obfuscate,
+            validator: naga::valid::Validator::new(params.validation_flags, caps),
+            params: &params,
         }
-        _ => return Err(CliError("Unknown input file extension").into()),
+        .run();
+    }
+
+    let reads_stdin = match args.files.first() {
+        Some(path) => path == "-",
+        None => args.stdin_file_path.is_some() || args.input_format.is_some(),
+    };
+    let (input_path, input) = if reads_stdin {
+        let mut input = vec![];
+        std::io::stdin().lock().read_to_end(&mut input)?;
+        (
+            Path::new(args.stdin_file_path.as_deref().unwrap_or("-")),
+            input,
+        )
+    } else if let Some(path) = args.files.first() {
+        let path = Path::new(path);
+        (path, fs::read(path)?)
+    } else {
+        return Err(CliError("Input file path is not specified").into());
     };
+    let input_format = input_format(input_path, args.input_format.as_deref())?;
+    let (mut module, input_text) = parse_input(input_path, input, input_format, &params)?;
 
     // Include debugging information if requested.
+    if args.generate_debug_symbols && args.obfuscate {
+        return Err(CliError("`--generate-debug-symbols` can't be used with `--obfuscate`").into());
+    }
     if args.generate_debug_symbols {
         if let Some(ref input_text) = input_text {
             params
@@ -386,42 +831,55 @@ fn run() -> Result<(), Box<dyn std::error::Error>> {
     }
 
     // Decide which capabilities our output formats can support.
-    let validation_caps =
-        output_paths
-            .iter()
-            .fold(naga::valid::Capabilities::all(), |caps, path| {
-                use naga::valid::Capabilities as C;
-                let missing = match Path::new(path).extension().and_then(|ex| ex.to_str()) {
-                    Some("wgsl") => C::CLIP_DISTANCE | C::CULL_DISTANCE,
-                    Some("metal") => C::CULL_DISTANCE,
-                    _ => C::empty(),
-                };
-                caps & !missing
-            });
+    let validation_caps = output_paths.iter().fold(env_caps, |caps, path| {
+        let format = output_format(path, args.output_format.as_deref()).ok();
+        caps & !unsupported_capabilities(format)
+    });
 
     // Validate the IR before compaction.
     let info = match naga::valid::Validator::new(params.validation_flags, validation_caps)
         .validate(&module)
     {
-        Ok(info) => Some(info),
+        Ok(info) => {
+            emit_validation_warnings(&module, &info, input_text.as_deref(), input_path);
+            Some(info)
+        }
         Err(error) => {
             // Validation failure is not fatal. Just report the error.
-            if let Some(input) = &input_text {
-                let filename = input_path.file_name().and_then(std::ffi::OsStr::to_str);
-                emit_annotated_error(&error, filename.unwrap_or("input"), input);
-            }
-            print_err(&error);
+            emit_validation_error(&error, input_text.as_deref(), input_path);
             None
         }
     };
 
+    // Run the requested passes, and revalidate.
+    let info = if params.passes.is_empty() {
+        info
+    } else if info.is_some() {
+        params.passes.run(&mut module);
+        match naga::valid::Validator::new(params.validation_flags, validation_caps)
+            .validate(&module)
+        {
+            Ok(info) => Some(info),
+            Err(error) => {
+                // Validation failure is not fatal. Just report the error.
+                eprintln!("Error validating transformed module:");
+                emit_validation_error(&error, input_text.as_deref(), input_path);
+                None
+            }
+        }
+    } else {
+        eprintln!("Skipping passes due to validation failure.");
+        None
+    };
+
     // Compact the module, if requested.
     let info = if args.compact || args.before_compaction.is_some() {
         // Compact only if validation succeeded. Otherwise, compaction may panic.
         if info.is_some() {
             // Write out the module state before compaction, if requested.
             if let Some(ref before_compaction) = args.before_compaction {
-                write_output(&module, &info, &params, before_compaction)?;
+                let format = output_format(before_compaction, None)?;
+                write_output(&module, &info, &params, before_compaction, format)?;
             }
 
             naga::compact::compact(&mut module);
@@ -434,11 +892,7 @@ fn run() -> Result<(), Box<dyn std::error::Error>> {
                 Err(error) => {
                     // Validation failure is not fatal. Just report the error.
                     eprintln!("Error validating compacted module:");
-                    if let Some(input) = &input_text {
-                        let filename = input_path.file_name().and_then(std::ffi::OsStr::to_str);
-                        emit_annotated_error(&error, filename.unwrap_or("input"), input);
-                    }
-                    print_err(&error);
+                    emit_validation_error(&error, input_text.as_deref(), input_path);
                     None
                 }
             }
@@ -450,42 +904,377 @@ fn run() -> Result<(), Box<dyn std::error::Error>> {
         info
     };
 
+    // Obfuscation leaves handles alone, so `info` still applies.
+    if args.obfuscate {
+        naga::proc::obfuscate(&mut module);
+    }
+
+    if args.stats {
+        if let Some(ref info) = info {
+            for (index, ep) in module.entry_points.iter().enumerate() {
+                let cost = info.cost(index);
+                eprintln!(
+                    "{:?} entry point `{}`: {} ALU ops, {} texture samples, {} branches, \
+                     loop depth {}",
+                    ep.stage,
+                    ep.name,
+                    cost.alu_ops,
+                    cost.texture_samples,
+                    cost.branches,
+                    cost.loop_depth
+                );
+            }
+        }
+    }
+
     // If no output was requested, then report validation results and stop here.
     //
     // If the user asked for output, don't stop: some output formats (".txt",
     // ".dot", ".bin") can be generated even without a `ModuleInfo`.
     if output_paths.is_empty() {
         if info.is_some() {
-            println!("Validation successful");
+            eprintln!("Validation successful");
             return Ok(());
         } else {
             std::process::exit(-1);
         }
     }
 
+    // Write every output we can, even if some fail.
+    let mut failures = 0;
     for output_path in output_paths {
-        write_output(&module, &info, &params, output_path)?;
+        let result = output_format(output_path, args.output_format.as_deref())
+            .map_err(Into::into)
+            .and_then(|format| write_output(&module, &info, &params, output_path, format));
+        if let Err(error) = result {
+            eprint!("{output_path}: ");
+            print_err(error.as_ref());
+            failures += 1;
+        }
+    }
+    if failures != 0 {
+        return Err(CliError("Failed to write some outputs").into());
+    }
+
+    Ok(())
+}
+
+/// Return the format to read `path` in: `format`, if given, or else the
+/// path's extension.
+fn input_format<'a>(path: &'a Path, format: Option<&'a str>) -> Result<&'a str, CliError> {
+    if let Some(format) = format {
+        return Ok(format);
+    }
+    path.extension()
+        .ok_or(CliError("Input filename has no extension"))?
+        .to_str()
+        .ok_or(CliError("Input filename not valid unicode"))
+}
+
+/// Parse `input`, read from `input_path`, as a module in `input_format`.
+///
+/// Also return the input as text, if it is human-readable. Parse errors are
+/// reported on stderr before being returned.
+fn parse_input(
+    input_path: &Path,
+    input: Vec<u8>,
+    input_format: &str,
+    params: &Parameters,
+) -> Result<(naga::Module, Option<String>), Box<dyn Error>> {
+    Ok(match input_format {
+        "bin" => (bincode::deserialize(&input)?, None),
+        "spv" => (
+            naga::front::spv::parse_u8_slice(&input, &params.spv_in)?,
+            None,
+        ),
+        "wgsl" => {
+            let input = String::from_utf8(input)?;
+            match naga::front::wgsl::parse_str(&input) {
+                Ok(module) => (module, Some(input)),
+                Err(ref e) => {
+                    let path = input_path.to_string_lossy();
+                    e.emit_to_stderr_with_path(&input, &path);
+                    return Err(CliError("Could not parse WGSL").into());
+                }
+            }
+        }
+        ext @ ("vert" | "frag" | "comp" | "glsl") => {
+            let input = String::from_utf8(input)?;
+            let stage = match ext {
+                "glsl" => Path::new(input_path.file_stem().unwrap_or_default())
+                    .extension()
+                    .ok_or(CliError(
+                        "Input filename ending with .glsl has no internal extension",
+                    ))?
+                    .to_str()
+                    .ok_or(CliError("Input filename not valid unicode"))?,
+                _ => ext,
+            };
+            let options = naga::front::glsl::Options {
+                stage: match stage {
+                    "vert" => naga::ShaderStage::Vertex,
+                    "frag" => naga::ShaderStage::Fragment,
+                    "comp" => naga::ShaderStage::Compute,
+                    _ => return Err(CliError("Unknown GLSL shader stage").into()),
+                },
+                defines: Default::default(),
+                include_resolver: None,
+            };
+            let mut parser = naga::front::glsl::Frontend::default();
+            match parser.parse(&options, &input) {
+                Ok(module) => (module, Some(input)),
+                Err(errors) => {
+                    let filename = input_path.file_name().and_then(std::ffi::OsStr::to_str);
+                    emit_glsl_parser_error(errors, filename.unwrap_or("glsl"), &input);
+                    return Err(CliError("Could not parse GLSL").into());
+                }
+            }
+        }
+        _ => return Err(CliError("Unknown input format").into()),
+    })
+}
+
+/// Report a validation `error`, against the input text if there is one.
+fn emit_validation_error(
+    error: &naga::WithSpan<naga::valid::ValidationError>,
+    input_text: Option<&str>,
+    input_path: &Path,
+) {
+    if let Some(input) = input_text {
+        let filename = input_path.file_name().and_then(std::ffi::OsStr::to_str);
+        error.emit_to_stderr_with_path(input, filename.unwrap_or("input"));
+    } else {
+        print_err(error);
     }
+}
+
+/// Report the warnings validating `module` found, against the input text if
+/// there is one.
+fn emit_validation_warnings(
+    module: &naga::Module,
+    info: &naga::valid::ModuleInfo,
+    input_text: Option<&str>,
+    input_path: &Path,
+) {
+    let functions = module.functions.iter().map(|(handle, _)| &info[handle]);
+    let entry_points = (0..module.entry_points.len()).map(|index| info.get_entry_point(index));
+    for warning in functions
+        .chain(entry_points)
+        .flat_map(|info| info.warnings())
+    {
+        if let Some(input) = input_text {
+            let filename = input_path.file_name().and_then(std::ffi::OsStr::to_str);
+            eprint!(
+                "{}",
+                warning.emit_to_string_with_options(
+                    input,
+                    filename.unwrap_or("input"),
+                    &naga::EmitOptions::default()
+                )
+            );
+        } else {
+            print_err(warning);
+        }
+    }
+}
+
+/// Return the capabilities that output `format` can't express.
+fn unsupported_capabilities(format: Option<&str>) -> naga::valid::Capabilities {
+    use naga::valid::Capabilities as C;
+    match format {
+        Some("wgsl") => C::CLIP_DISTANCE | C::CULL_DISTANCE,
+        Some("metal") => C::CULL_DISTANCE,
+        _ => C::empty(),
+    }
+}
+
+/// How often `--watch` checks for changed shaders.
+const WATCH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The extensions of the shaders `--watch` translates.
+const WATCH_EXTENSIONS: &[&str] = &["wgsl", "spv", "vert", "frag", "comp", "glsl"];
+
+/// The state of `--watch` mode.
+struct Watch<'a> {
+    dir: &'a Path,
+    out_dir: Option<&'a Path>,
+    output_format: Option<&'a str>,
+    compact: bool,
+    obfuscate: bool,
+    /// Kept across rounds, so that its allocations are reused.
+    validator: naga::valid::Validator,
+    params: &'a Parameters<'a>,
+}
+
+impl Watch<'_> {
+    /// Translate shaders whenever they change, forever.
+    fn run(mut self) -> Result<(), Box<dyn Error>> {
+        println!(
+            "Watching {} for changes, press Ctrl-C to stop",
+            self.dir.display()
+        );
+        let mut modified_times = HashMap::new();
+        loop {
+            let mut inputs = Vec::new();
+            collect_shaders(self.dir, &mut inputs)?;
+
+            let mut changed = Vec::new();
+            let mut new_times = HashMap::with_capacity(inputs.len());
+            for path in inputs {
+                // The file may be gone already, if an editor is replacing it.
+                let Ok(modified) = fs::metadata(&path).and_then(|meta| meta.modified()) else {
+                    continue;
+                };
+                if modified_times.get(&path) != Some(&modified) {
+                    changed.push(path.clone());
+                }
+                new_times.insert(path, modified);
+            }
+            modified_times = new_times;
+
+            if !changed.is_empty() {
+                let mut failed = Vec::new();
+                for path in changed.iter() {
+                    if let Err(error) = self.translate(path) {
+                        eprint!("{}: ", path.display());
+                        print_err(error.as_ref());
+                        failed.push(path);
+                    }
+                }
+                println!(
+                    "{} shaders translated, {} failed",
+                    changed.len() - failed.len(),
+                    failed.len()
+                );
+                for path in failed {
+                    println!("  failed: {}", path.display());
+                }
+            }
+
+            std::thread::sleep(WATCH_INTERVAL);
+        }
+    }
+
+    /// Parse, validate and write out the shader at `path`.
+    fn translate(&mut self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let input = fs::read(path)?;
+        let format = input_format(path, None)?;
+        let (mut module, input_text) = parse_input(path, input, format, self.params)?;
+
+        let mut info = match self.validator.validate(&module) {
+            Ok(info) => {
+                emit_validation_warnings(&module, &info, input_text.as_deref(), path);
+                info
+            }
+            Err(error) => {
+                emit_validation_error(&error, input_text.as_deref(), path);
+                return Err(CliError("Validation failed").into());
+            }
+        };
+        if !self.params.passes.is_empty() {
+            self.params.passes.run(&mut module);
+            info = self.validator.validate(&module).map_err(|error| {
+                emit_validation_error(&error, input_text.as_deref(), path);
+                CliError("Validation of the transformed module failed")
+            })?;
+        }
+        if self.compact {
+            naga::compact::compact(&mut module);
+            info = self.validator.validate(&module).map_err(|error| {
+                emit_validation_error(&error, input_text.as_deref(), path);
+                CliError("Validation of the compacted module failed")
+            })?;
+        }
+        if self.obfuscate {
+            naga::proc::obfuscate(&mut module);
+        }
+
+        let (Some(out_dir), Some(format)) = (self.out_dir, self.output_format) else {
+            return Ok(());
+        };
+        let output_path = out_dir.join(path.strip_prefix(self.dir)?.with_extension(format));
+        if let Some(parent) = output_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let output_path = output_path
+            .to_str()
+            .ok_or(CliError("Output filename not valid unicode"))?;
+        write_output(&module, &Some(info), self.params, output_path, format)
+    }
+}
 
+/// Whether `path`, which needn't exist yet, is `dir` or inside it.
+fn is_within(path: &Path, dir: &Path) -> std::io::Result<bool> {
+    let path = std::env::current_dir()?.join(path);
+    // Resolve the part of `path` that exists, so that links don't hide where
+    // it is.
+    let existing = path
+        .ancestors()
+        .find(|ancestor| ancestor.exists())
+        .unwrap_or(&path);
+    let rest = path.strip_prefix(existing).unwrap_or(Path::new(""));
+    Ok(existing
+        .canonicalize()?
+        .join(rest)
+        .starts_with(dir.canonicalize()?))
+}
+
+/// Add the paths of the shaders under `dir` to `paths`, in a stable order.
+fn collect_shaders(dir: &Path, paths: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    let mut entries = fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(fs::DirEntry::path);
+    for entry in entries {
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            collect_shaders(&path, paths)?;
+        } else if path
+            .extension()
+            .and_then(std::ffi::OsStr::to_str)
+            .map_or(false, |ext| WATCH_EXTENSIONS.contains(&ext))
+        {
+            paths.push(path);
+        }
+    }
     Ok(())
 }
 
+/// Return the format to write `path` in: `format`, if given, or else the
+/// path's extension.
+fn output_format<'a>(path: &'a str, format: Option<&'a str>) -> Result<&'a str, CliError> {
+    if let Some(format) = format {
+        return Ok(format);
+    }
+    if path == "-" {
+        return Err(CliError("Writing to stdout requires --output-format"));
+    }
+    Path::new(path)
+        .extension()
+        .ok_or(CliError("Output filename has no extension"))?
+        .to_str()
+        .ok_or(CliError("Output filename not valid unicode"))
+}
+
+/// Open `path` for writing, or stdout if it is `-`.
+fn create_output(path: &str) -> std::io::Result<Box<dyn std::io::Write>> {
+    Ok(if path == "-" {
+        Box::new(std::io::stdout().lock())
+    } else {
+        Box::new(fs::File::create(path)?)
+    })
+}
+
 fn write_output(
     module: &naga::Module,
     info: &Option<naga::valid::ModuleInfo>,
     params: &Parameters,
     output_path: &str,
+    format: &str,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    match Path::new(&output_path)
-        .extension()
-        .ok_or(CliError("Output filename has no extension"))?
-        .to_str()
-        .ok_or(CliError("Output filename not valid unicode"))?
-    {
-        "txt" => {
-            use std::io::Write;
+    use std::io::Write as _;
 
-            let mut file = fs::File::create(output_path)?;
+    match format {
+        "txt" => {
+            let mut file = create_output(output_path)?;
             writeln!(file, "{module:#?}")?;
             if let Some(ref info) = *info {
                 writeln!(file)?;
@@ -493,7 +1282,7 @@ fn write_output(
             }
         }
         "bin" => {
-            let file = fs::File::create(output_path)?;
+            let file = create_output(output_path)?;
             bincode::serialize_into(file, module)?;
         }
         "metal" => {
@@ -511,11 +1300,10 @@ fn write_output(
                 ))?,
                 &options,
                 &pipeline_options,
-            )
-            .unwrap_pretty();
-            fs::write(output_path, msl)?;
+            )?;
+            create_output(output_path)?.write_all(msl.as_bytes())?;
         }
-        "spv" => {
+        "spv" | "spvasm" | "spv-asm" => {
             use naga::back::spv;
 
             let pipeline_options_owned;
@@ -543,16 +1331,23 @@ fn write_output(
                 ))?,
                 &params.spv_out,
                 pipeline_options,
-            )
-            .unwrap_pretty();
-            let bytes = spv
-                .iter()
-                .fold(Vec::with_capacity(spv.len() * 4), |mut v, w| {
-                    v.extend_from_slice(&w.to_le_bytes());
-                    v
-                });
-
-            fs::write(output_path, bytes.as_slice())?;
+            )?;
+            if format == "spv" {
+                let bytes = spv
+                    .iter()
+                    .fold(Vec::with_capacity(spv.len() * 4), |mut v, w| {
+                        v.extend_from_slice(&w.to_le_bytes());
+                        v
+                    });
+                create_output(output_path)?.write_all(&bytes)?;
+            } else {
+                use rspirv::binary::Disassemble;
+
+                let dis = rspirv::dr::load_words(spv)
+                    .map_err(|_| CliError("Produced invalid SPIR-V"))?
+                    .disassemble();
+                writeln!(create_output(output_path)?, "{dis}")?;
+            }
         }
         stage @ ("vert" | "frag" | "comp") => {
             use naga::back::glsl;
@@ -571,42 +1366,40 @@ fn write_output(
                 multiview: None,
             };
 
+            let info = info.as_ref().ok_or(CliError(
+                "Generating glsl output requires validation to \
+                 succeed, and it failed in a previous step",
+            ))?;
+            // Write into memory first, so that a failure doesn't leave a
+            // partial file behind.
             let mut buffer = String::new();
             let mut writer = glsl::Writer::new(
                 &mut buffer,
                 module,
-                info.as_ref().ok_or(CliError(
-                    "Generating glsl output requires validation to \
-                     succeed, and it failed in a previous step",
-                ))?,
+                info,
                 &params.glsl,
                 &pipeline_options,
                 params.bounds_check_policies,
-            )
-            .unwrap_pretty();
+            )?;
             writer.write()?;
-            fs::write(output_path, buffer)?;
+            create_output(output_path)?.write_all(buffer.as_bytes())?;
         }
         "dot" => {
             use naga::back::dot;
 
             let output = dot::write(module, info.as_ref(), params.dot.clone())?;
-            fs::write(output_path, output)?;
+            create_output(output_path)?.write_all(output.as_bytes())?;
         }
         "hlsl" => {
             use naga::back::hlsl;
+            let info = info.as_ref().ok_or(CliError(
+                "Generating hlsl output requires validation to \
+                 succeed, and it failed in a previous step",
+            ))?;
             let mut buffer = String::new();
             let mut writer = hlsl::Writer::new(&mut buffer, &params.hlsl);
-            writer
-                .write(
-                    module,
-                    info.as_ref().ok_or(CliError(
-                        "Generating hlsl output requires validation to \
-                         succeed, and it failed in a previous step",
-                    ))?,
-                )
-                .unwrap_pretty();
-            fs::write(output_path, buffer)?;
+            writer.write(module, info)?;
+            create_output(output_path)?.write_all(buffer.as_bytes())?;
         }
         "wgsl" => {
             use naga::back::wgsl;
@@ -618,12 +1411,11 @@ fn write_output(
                      succeed, and it failed in a previous step",
                 ))?,
                 wgsl::WriterFlags::empty(),
-            )
-            .unwrap_pretty();
-            fs::write(output_path, wgsl)?;
+            )?;
+            create_output(output_path)?.write_all(wgsl.as_bytes())?;
         }
         other => {
-            println!("Unknown output extension: {other}");
+            return Err(format!("Unknown output format: {other}").into());
         }
     }
 
@@ -638,7 +1430,6 @@ use codespan_reporting::{
         termcolor::{ColorChoice, StandardStream},
     },
 };
-use naga::WithSpan;
 
 pub fn emit_glsl_parser_error(errors: Vec<naga::front::glsl::Error>, filename: &str, source: &str) {
     let files = SimpleFile::new(filename, source);
@@ -656,19 +1447,113 @@ pub fn emit_glsl_parser_error(errors: Vec<naga::front::glsl::Error>, filename: &
     }
 }
 
-pub fn emit_annotated_error<E: Error>(ann_err: &WithSpan<E>, filename: &str, source: &str) {
-    let files = SimpleFile::new(filename, source);
-    let config = codespan_reporting::term::Config::default();
-    let writer = StandardStream::stderr(ColorChoice::Auto);
+#[cfg(test)]
+mod tests {
+    use super::{is_within, CoordinateSpaceArg, Parameters, TargetEnvArg, WEBGL2};
+    use naga::back::hlsl::ShaderModel;
+    use naga::valid::Capabilities as C;
+
+    #[test]
+    fn target_env_presets() {
+        let env: TargetEnvArg = "Vulkan1.2".parse().unwrap();
+        assert_eq!(env, TargetEnvArg::Vulkan((1, 5)));
+        let mut params = Parameters::default();
+        env.apply(&mut params);
+        assert_eq!(params.spv_out.lang_version, (1, 5));
+        assert!(env.check(&params).is_ok());
+        // `--spv-version` may choose an older version, but not a newer one.
+        params.spv_out.lang_version = (1, 3);
+        assert!(env.check(&params).is_ok());
+        params.spv_out.lang_version = (1, 6);
+        assert!(env.check(&params).is_err());
+        assert!(env.runs("spvasm") && env.runs("txt") && !env.runs("metal"));
+        assert_eq!(env.unsupported_capabilities(), C::empty());
+        let env: TargetEnvArg = "vulkan1.0".parse().unwrap();
+        assert_eq!(env.unsupported_capabilities(), C::SUBGROUP);
+
+        let env: TargetEnvArg = "webgl2".parse().unwrap();
+        let mut params = Parameters::default();
+        env.apply(&mut params);
+        assert_eq!(params.glsl.version, WEBGL2);
+        assert!(params
+            .glsl
+            .writer_flags
+            .contains(naga::back::glsl::WriterFlags::FORCE_POINT_SIZE));
+        assert!(env.check(&params).is_ok());
+        params.glsl.version = naga::back::glsl::Version::new_gles(300);
+        assert!(env.check(&params).is_err());
+        assert!(env.runs("frag") && !env.runs("comp") && !env.runs("spv"));
+        assert_eq!(env.unsupported_capabilities(), C::all());
+
+        let env: TargetEnvArg = "d3d12-sm6.0".parse().unwrap();
+        assert_eq!(env, TargetEnvArg::D3d12(ShaderModel::V6_0));
+        let mut params = Parameters::default();
+        env.apply(&mut params);
+        assert_eq!(params.hlsl.shader_model, ShaderModel::V6_0);
+        params.hlsl.shader_model = ShaderModel::V6_6;
+        assert!(env.check(&params).is_err());
+        let unsupported = env.unsupported_capabilities();
+        assert!(unsupported.contains(C::FLOAT16 | C::RAY_QUERY));
+        assert!(!unsupported.intersects(C::SUBGROUP | C::SHADER_INT64));
+        let env: TargetEnvArg = "d3d12-sm5.1".parse().unwrap();
+        assert!(env.unsupported_capabilities().contains(C::SUBGROUP));
+
+        let env: TargetEnvArg = "metal2.4".parse().unwrap();
+        assert_eq!(env, TargetEnvArg::Metal((2, 4)));
+        let mut params = Parameters::default();
+        env.apply(&mut params);
+        assert_eq!(params.msl.lang_version, (2, 4));
+        let unsupported = env.unsupported_capabilities();
+        assert_eq!(unsupported, C::TEXTURE_ATOMIC);
+        assert!(env.runs("metal") && !env.runs("hlsl"));
+
+        assert!("vulkan2.0".parse::<TargetEnvArg>().is_err());
+    }
 
-    let diagnostic = Diagnostic::error().with_labels(
-        ann_err
-            .spans()
-            .map(|(span, desc)| {
-                Label::primary((), span.to_range().unwrap()).with_message(desc.to_owned())
-            })
-            .collect(),
-    );
+    #[test]
+    fn coordinate_spaces() {
+        use naga::back::{glsl, spv};
 
-    term::emit(&mut writer.lock(), &config, &files, &diagnostic).expect("cannot write error");
+        let flags = |space: CoordinateSpaceArg| {
+            let mut params = Parameters::default();
+            space.apply(&mut params);
+            (
+                params
+                    .spv_out
+                    .flags
+                    .contains(spv::WriterFlags::ADJUST_COORDINATE_SPACE),
+                params
+                    .glsl
+                    .writer_flags
+                    .contains(glsl::WriterFlags::ADJUST_COORDINATE_SPACE),
+            )
+        };
+        let chosen = |space: Option<&str>, keep| {
+            let space = space.map(|space| space.parse().unwrap());
+            CoordinateSpaceArg::from_args(space, keep).map(&flags)
+        };
+
+        assert_eq!(chosen(None, false).unwrap(), (true, true));
+        assert_eq!(chosen(Some("Vulkan"), false).unwrap(), (false, true));
+        assert_eq!(chosen(Some("gl"), false).unwrap(), (false, false));
+        // `--keep-coordinate-space` only ever affected SPIR-V.
+        assert_eq!(chosen(None, true).unwrap(), (false, true));
+        assert_eq!(chosen(Some("vulkan"), true).unwrap(), (false, true));
+        assert!(chosen(Some("gl"), true).is_err());
+        assert!(chosen(Some("wgpu"), true).is_err());
+        assert!("metal".parse::<CoordinateSpaceArg>().is_err());
+    }
+
+    #[test]
+    fn out_dir_within_watched_dir() {
+        let dir = std::env::temp_dir().join(format!("naga-watch-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("shaders")).unwrap();
+        let shaders = dir.join("shaders");
+        assert!(is_within(&shaders, &shaders).unwrap());
+        assert!(is_within(&shaders.join("out"), &shaders).unwrap());
+        assert!(is_within(&shaders.join("out/nested"), &shaders).unwrap());
+        assert!(!is_within(&dir.join("out"), &shaders).unwrap());
+        assert!(!is_within(&dir, &shaders).unwrap());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
 }
@@ -34,6 +34,7 @@ impl From<OptionsProxy> for Options {
         Options {
             stage: proxy.stage.into(),
             defines: proxy.defines,
+            include_resolver: None,
         }
     }
 }
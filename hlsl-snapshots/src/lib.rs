@@ -50,6 +50,15 @@ pub struct Config {
     pub vertex: Vec<ConfigItem>,
     pub fragment: Vec<ConfigItem>,
     pub compute: Vec<ConfigItem>,
+    /// Whether the shader was written for FXC, with Naga's
+    /// `Options::fxc_compat` set. FXC validation may skip other shaders'
+    /// entry points when it doesn't support their shader model, but not
+    /// these.
+    ///
+    /// This is an `Option` so that it's left out of the files of shaders
+    /// that aren't, which is what `None` means.
+    #[nserde(default)]
+    pub fxc_compatible: Option<bool>,
 }
 
 impl Config {
@@ -58,6 +67,7 @@ impl Config {
             vertex: Default::default(),
             fragment: Default::default(),
             compute: Default::default(),
+            fxc_compatible: None,
         }
     }
 
@@ -78,14 +88,49 @@ impl Config {
         fs::write(path, &s).map_err(|e| anyhow!("failed to write to {}: {e}", path.display()))
     }
 
+    /// Add `entry_point` to the list for `stage`, targeting the profile for
+    /// `stage` in `shader_model`, given as `{major}_{minor}` (e.g., `5_1`).
+    ///
+    /// Fails if [`TARGET_PROFILES`] doesn't list `shader_model`.
+    pub fn push(
+        &mut self,
+        stage: ShaderStage,
+        entry_point: String,
+        shader_model: &str,
+    ) -> anyhow::Result<()> {
+        let target_profile = target_profile(stage, shader_model)
+            .ok_or_else(|| anyhow!("no target profiles for shader model {shader_model:?}"))?;
+        let Self {
+            vertex,
+            fragment,
+            compute,
+            fxc_compatible: _,
+        } = self;
+        let list = match stage {
+            ShaderStage::Vertex => vertex,
+            ShaderStage::Fragment => fragment,
+            ShaderStage::Compute => compute,
+        };
+        list.push(ConfigItem {
+            entry_point,
+            target_profile: target_profile.to_owned(),
+        });
+        Ok(())
+    }
+
     pub fn is_empty(&self) -> bool {
         let Self {
             vertex,
             fragment,
             compute,
+            fxc_compatible: _,
         } = self;
         vertex.is_empty() && fragment.is_empty() && compute.is_empty()
     }
+
+    pub fn is_fxc_compatible(&self) -> bool {
+        self.fxc_compatible == Some(true)
+    }
 }
 
 #[derive(Debug, DeRon, SerRon)]
@@ -95,3 +140,55 @@ pub struct ConfigItem {
     /// <https://learn.microsoft.com/en-us/windows/win32/direct3dtools/dx-graphics-tools-fxc-using>.
     pub target_profile: String,
 }
+
+impl ConfigItem {
+    /// The major version of the shader model in [`Self::target_profile`], if
+    /// it has the form `{stage}_{major}_{minor}`.
+    pub fn shader_model_major_version(&self) -> Option<u8> {
+        self.shader_model_version().map(|(major, _)| major)
+    }
+
+    /// The major and minor versions of the shader model in
+    /// [`Self::target_profile`], if it has the form `{stage}_{major}_{minor}`.
+    pub fn shader_model_version(&self) -> Option<(u8, u8)> {
+        let mut parts = self.target_profile.split('_').skip(1);
+        let major = parts.next()?.parse().ok()?;
+        let minor = parts.next()?.parse().ok()?;
+        Some((major, minor))
+    }
+}
+
+/// The shader stages a [`Config`] lists entry points for.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ShaderStage {
+    Vertex,
+    Fragment,
+    Compute,
+}
+
+/// The target profile of each [`ShaderStage`], in order, for each shader
+/// model Naga can write HLSL for.
+///
+/// Shader models are given as `{major}_{minor}`, which is how Naga's
+/// `ShaderModel::to_str` spells them.
+pub const TARGET_PROFILES: &[(&str, [&str; 3])] = &[
+    ("5_0", ["vs_5_0", "ps_5_0", "cs_5_0"]),
+    ("5_1", ["vs_5_1", "ps_5_1", "cs_5_1"]),
+    ("6_0", ["vs_6_0", "ps_6_0", "cs_6_0"]),
+    ("6_1", ["vs_6_1", "ps_6_1", "cs_6_1"]),
+    ("6_2", ["vs_6_2", "ps_6_2", "cs_6_2"]),
+    ("6_3", ["vs_6_3", "ps_6_3", "cs_6_3"]),
+    ("6_4", ["vs_6_4", "ps_6_4", "cs_6_4"]),
+    ("6_5", ["vs_6_5", "ps_6_5", "cs_6_5"]),
+    ("6_6", ["vs_6_6", "ps_6_6", "cs_6_6"]),
+    ("6_7", ["vs_6_7", "ps_6_7", "cs_6_7"]),
+];
+
+/// The target profile for `stage` in `shader_model`, if
+/// [`TARGET_PROFILES`] lists it.
+pub fn target_profile(stage: ShaderStage, shader_model: &str) -> Option<&'static str> {
+    let &(_, profiles) = TARGET_PROFILES
+        .iter()
+        .find(|&&(model, _)| model == shader_model)?;
+    Some(profiles[stage as usize])
+}
@@ -23,6 +23,67 @@ impl BadHandle {
     }
 }
 
+/// The error returned when an arena has no room for another item.
+///
+/// A [`Handle`] holds a non-zero `u32`, so an arena can hold at most
+/// `u32::MAX` items.
+#[derive(Clone, Copy, Debug, thiserror::Error, PartialEq)]
+#[error("Arena of {kind} is full; it can hold at most {} items", u32::MAX)]
+pub struct ArenaFull {
+    pub kind: &'static str,
+}
+
+impl ArenaFull {
+    fn new<T>() -> Self {
+        Self {
+            kind: std::any::type_name::<T>(),
+        }
+    }
+}
+
+/// How much memory a container holds, as reported by [`Arena::memory_usage`]
+/// and its cousins.
+///
+/// `bytes` counts the container's own allocations, but not what its items
+/// allocate in turn, like the names of types or the statements of
+/// functions. For [`UniqueArena`], whose hash table has a layout of its
+/// own, it is an estimate.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ArenaUsage {
+    /// The number of items stored.
+    pub len: usize,
+    /// The number of items there is room for without reallocating.
+    pub capacity: usize,
+    /// The number of bytes allocated.
+    pub bytes: usize,
+}
+
+impl ArenaUsage {
+    pub(crate) fn of_vec<T>(vec: &Vec<T>) -> Self {
+        Self {
+            len: vec.len(),
+            capacity: vec.capacity(),
+            bytes: vec.capacity() * std::mem::size_of::<T>(),
+        }
+    }
+
+    pub(crate) fn of_slice<T>(slice: &[T]) -> Self {
+        Self {
+            len: slice.len(),
+            capacity: slice.len(),
+            bytes: slice.len() * std::mem::size_of::<T>(),
+        }
+    }
+}
+
+impl ops::AddAssign for ArenaUsage {
+    fn add_assign(&mut self, other: Self) {
+        self.len += other.len;
+        self.capacity += other.capacity;
+        self.bytes += other.bytes;
+    }
+}
+
 /// A strongly typed reference to an arena item.
 ///
 /// A `Handle` value can be used as an index into an [`Arena`] or [`UniqueArena`].
@@ -101,11 +162,17 @@ impl<T> Handle<T> {
 
     /// Convert a `usize` index into a `Handle<T>`.
     fn from_usize(index: usize) -> Self {
-        let handle_index = u32::try_from(index + 1)
+        Self::try_from_usize(index).expect("Failed to insert into arena. Handle overflows")
+    }
+
+    /// Convert a `usize` index into a `Handle<T>`, or return an error if
+    /// `index` is too large for a handle to represent.
+    fn try_from_usize(index: usize) -> Result<Self, ArenaFull> {
+        u32::try_from(index + 1)
             .ok()
             .and_then(Index::new)
-            .expect("Failed to insert into arena. Handle overflows");
-        Handle::new(handle_index)
+            .map(Handle::new)
+            .ok_or_else(ArenaFull::new::<T>)
     }
 
     /// Convert a `usize` index into a `Handle<T>`, without range checks.
@@ -309,14 +376,30 @@ impl<T> Arena<T> {
     }
 
     /// Adds a new value to the arena, returning a typed handle.
+    ///
+    /// # Panics
+    ///
+    /// If the arena already holds `u32::MAX` items. Use [`try_append`] to
+    /// get an error instead.
+    ///
+    /// [`try_append`]: Arena::try_append
     pub fn append(&mut self, value: T, span: Span) -> Handle<T> {
+        self.try_append(value, span)
+            .expect("Failed to insert into arena. Handle overflows")
+    }
+
+    /// Adds a new value to the arena, returning a typed handle, or an error
+    /// if the arena is full.
+    ///
+    /// If this returns an error, the arena is left unchanged.
+    pub fn try_append(&mut self, value: T, span: Span) -> Result<Handle<T>, ArenaFull> {
         #[cfg(not(feature = "span"))]
         let _ = span;
-        let index = self.data.len();
+        let handle = Handle::try_from_usize(self.data.len())?;
         self.data.push(value);
         #[cfg(feature = "span")]
         self.span_info.push(span);
-        Handle::from_usize(index)
+        Ok(handle)
     }
 
     /// Fetch a handle to an existing type.
@@ -373,7 +456,34 @@ impl<T> Arena<T> {
 
     /// Clears the arena keeping all allocations
     pub fn clear(&mut self) {
-        self.data.clear()
+        self.data.clear();
+        #[cfg(feature = "span")]
+        self.span_info.clear();
+    }
+
+    /// Return how much memory the arena holds.
+    pub fn memory_usage(&self) -> ArenaUsage {
+        #[allow(unused_mut)]
+        let mut usage = ArenaUsage::of_vec(&self.data);
+        #[cfg(feature = "span")]
+        {
+            usage.bytes += ArenaUsage::of_vec(&self.span_info).bytes;
+        }
+        usage
+    }
+
+    /// Free the memory the arena holds beyond what its items need.
+    pub fn shrink_to_fit(&mut self) {
+        self.data.shrink_to_fit();
+        #[cfg(feature = "span")]
+        self.span_info.shrink_to_fit();
+    }
+
+    /// Forget the spans of all elements, as if they had been appended with
+    /// [`Span::default`].
+    pub(crate) fn clear_spans(&mut self) {
+        #[cfg(feature = "span")]
+        self.span_info.fill(Span::default());
     }
 
     pub fn get_span(&self, handle: Handle<T>) -> Span {
@@ -391,6 +501,25 @@ impl<T> Arena<T> {
         }
     }
 
+    /// The spans of all entries, in handle order.
+    #[cfg(feature = "span")]
+    pub(crate) fn spans_mut(&mut self) -> impl Iterator<Item = &mut Span> {
+        self.span_info.iter_mut()
+    }
+
+    /// Set the span of `handle`'s entry, if the `span` feature is enabled.
+    #[cfg(feature = "binary")]
+    pub(crate) fn set_span(&mut self, handle: Handle<T>, span: Span) {
+        #[cfg(feature = "span")]
+        {
+            self.span_info[handle.index()] = span;
+        }
+        #[cfg(not(feature = "span"))]
+        {
+            let _ = (handle, span);
+        }
+    }
+
     /// Assert that `handle` is valid for this arena.
     pub fn check_contains_handle(&self, handle: Handle<T>) -> Result<(), BadHandle> {
         if handle.index() < self.data.len() {
@@ -430,11 +559,23 @@ impl<T> Arena<T> {
         P: FnMut(Handle<T>, &mut T) -> bool,
     {
         let mut index = 0;
+        #[cfg(feature = "span")]
+        let mut retained = 0;
         self.data.retain_mut(|elt| {
             index += 1;
             let handle = Handle::new(Index::new(index).unwrap());
-            predicate(handle, elt)
-        })
+            let keep = predicate(handle, elt);
+            // `predicate` mutates the elements, so it can't be called again
+            // to retain the spans: move them down alongside.
+            #[cfg(feature = "span")]
+            if keep {
+                self.span_info[retained] = self.span_info[handle.index()];
+                retained += 1;
+            }
+            keep
+        });
+        #[cfg(feature = "span")]
+        self.span_info.truncate(retained);
     }
 }
 
@@ -520,6 +661,32 @@ mod tests {
         assert!(t1 != t2);
         assert!(arena[t1] != arena[t2]);
     }
+
+    #[test]
+    fn handle_niche() {
+        use std::mem::size_of;
+        assert_eq!(size_of::<Option<Handle<u8>>>(), size_of::<Handle<u8>>());
+        assert_eq!(size_of::<Handle<u8>>(), size_of::<u32>());
+    }
+
+    #[test]
+    fn handle_overflow() {
+        assert!(Handle::<u8>::try_from_usize(u32::MAX as usize - 1).is_ok());
+        assert_eq!(
+            Handle::<u8>::try_from_usize(u32::MAX as usize),
+            Err(ArenaFull::new::<u8>())
+        );
+    }
+
+    #[test]
+    fn try_insert_unique() {
+        let mut arena: UniqueArena<u8> = UniqueArena::new();
+        let t1 = arena.try_insert(0, Default::default()).unwrap();
+        let t2 = arena.try_insert(1, Default::default()).unwrap();
+        assert_eq!(arena.try_insert(0, Default::default()), Ok(t1));
+        assert!(t1 != t2);
+        assert_eq!(arena.len(), 2);
+    }
 }
 
 /// An arena whose elements are guaranteed to be unique.
@@ -577,6 +744,26 @@ impl<T> UniqueArena<T> {
         self.span_info.clear();
     }
 
+    /// Return how much memory the arena holds.
+    ///
+    /// Each slot of the underlying hash table holds an item, its hash and
+    /// its index, so this counts that much for each item there is room for.
+    pub fn memory_usage(&self) -> ArenaUsage {
+        let capacity = self.set.capacity();
+        let slot = std::mem::size_of::<T>() + 2 * std::mem::size_of::<usize>();
+        #[allow(unused_mut)]
+        let mut usage = ArenaUsage {
+            len: self.set.len(),
+            capacity,
+            bytes: capacity * slot,
+        };
+        #[cfg(feature = "span")]
+        {
+            usage.bytes += ArenaUsage::of_vec(&self.span_info).bytes;
+        }
+        usage
+    }
+
     /// Return the span associated with `handle`.
     ///
     /// If a value has been inserted multiple times, the span returned is the
@@ -599,6 +786,25 @@ impl<T> UniqueArena<T> {
         }
     }
 
+    /// The spans of all entries, in handle order.
+    #[cfg(feature = "span")]
+    pub(crate) fn spans_mut(&mut self) -> impl Iterator<Item = &mut Span> {
+        self.span_info.iter_mut()
+    }
+
+    /// Set the span of `handle`'s entry, if the `span` feature is enabled.
+    #[cfg(feature = "binary")]
+    pub(crate) fn set_span(&mut self, handle: Handle<T>, span: Span) {
+        #[cfg(feature = "span")]
+        {
+            self.span_info[handle.index()] = span;
+        }
+        #[cfg(not(feature = "span"))]
+        {
+            let _ = (handle, span);
+        }
+    }
+
     #[cfg(feature = "compact")]
     pub(crate) fn drain_all(&mut self) -> UniqueArenaDrain<T> {
         UniqueArenaDrain {
@@ -661,9 +867,30 @@ impl<T: Eq + hash::Hash> UniqueArena<T> {
     /// arena, associate `span` with it. An element's span can be retrieved with
     /// the [`get_span`] method.
     ///
+    /// # Panics
+    ///
+    /// If `value` is new and the arena already holds `u32::MAX` items. Use
+    /// [`try_insert`] to get an error instead.
+    ///
     /// [`Handle<T>`]: Handle
     /// [`get_span`]: UniqueArena::get_span
+    /// [`try_insert`]: UniqueArena::try_insert
     pub fn insert(&mut self, value: T, span: Span) -> Handle<T> {
+        self.try_insert(value, span)
+            .expect("Failed to insert into arena. Handle overflows")
+    }
+
+    /// Insert a new value into the arena, like [`insert`], or return an
+    /// error if `value` is new and the arena is full.
+    ///
+    /// If this returns an error, the arena is left unchanged.
+    ///
+    /// [`insert`]: UniqueArena::insert
+    pub fn try_insert(&mut self, value: T, span: Span) -> Result<Handle<T>, ArenaFull> {
+        if let Some(index) = self.set.get_index_of(&value) {
+            return Ok(Handle::from_usize(index));
+        }
+        Handle::<T>::try_from_usize(self.set.len())?;
         let (index, added) = self.set.insert_full(value);
 
         #[cfg(feature = "span")]
@@ -679,7 +906,7 @@ impl<T: Eq + hash::Hash> UniqueArena<T> {
         #[cfg(not(feature = "span"))]
         let _ = (span, added);
 
-        Handle::from_usize(index)
+        Ok(Handle::from_usize(index))
     }
 
     /// Replace an old value with a new value.
@@ -720,6 +947,13 @@ impl<T: Eq + hash::Hash> UniqueArena<T> {
             Err(BadHandle::new(handle))
         }
     }
+
+    /// Free the memory the arena holds beyond what its items need.
+    pub fn shrink_to_fit(&mut self) {
+        self.set.shrink_to_fit();
+        #[cfg(feature = "span")]
+        self.span_info.shrink_to_fit();
+    }
 }
 
 impl<T> Default for UniqueArena<T> {
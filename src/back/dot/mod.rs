@@ -224,6 +224,21 @@ impl StatementGraph {
                     self.dependencies.push((id, value, "value"));
                     "ImageStore"
                 }
+                S::ImageAtomic {
+                    image,
+                    coordinate,
+                    array_index,
+                    fun: _,
+                    value,
+                } => {
+                    self.dependencies.push((id, image, "image"));
+                    self.dependencies.push((id, coordinate, "coordinate"));
+                    if let Some(expr) = array_index {
+                        self.dependencies.push((id, expr, "array_index"));
+                    }
+                    self.dependencies.push((id, value, "value"));
+                    "ImageAtomic"
+                }
                 S::Call {
                     function,
                     ref arguments,
@@ -409,6 +424,10 @@ fn write_function_expressions(
                 payload = Some(Payload::Arguments(components));
                 ("Compose".into(), 3)
             }
+            E::Repeat { value, .. } => {
+                edges.insert("value", value);
+                ("Repeat".into(), 3)
+            }
             E::Access { base, index } => {
                 edges.insert("base", base);
                 edges.insert("index", index);
@@ -586,6 +605,26 @@ fn write_function_expressions(
                 let ty = if committed { "Committed" } else { "Candidate" };
                 (format!("rayQueryGet{}Intersection", ty).into(), 4)
             }
+            E::NonUniform(value) => {
+                edges.insert("", value);
+                ("NonUniform".into(), 7)
+            }
+            E::SubgroupOperation { op, argument } => {
+                use crate::SubgroupOperation as So;
+                edges.insert("arg", argument);
+                if let Some(index) = op.index() {
+                    edges.insert("index", index);
+                }
+                let string: String = match op {
+                    So::Broadcast(_) => "Broadcast".into(),
+                    So::Shuffle(_) => "Shuffle".into(),
+                    So::ShuffleDown(_) => "ShuffleDown".into(),
+                    So::ShuffleUp(_) => "ShuffleUp".into(),
+                    So::ShuffleXor(_) => "ShuffleXor".into(),
+                    _ => format!("{op:?}"),
+                };
+                (format!("Subgroup{string}").into(), 6)
+            }
         };
 
         // give uniform expressions an outline
@@ -1,7 +1,7 @@
-use super::{BackendResult, Error, Version, Writer};
+use super::{BackendResult, Error, Version, Writer, WriterFlags};
 use crate::{
-    AddressSpace, Binding, Bytes, Expression, Handle, ImageClass, ImageDimension, Interpolation,
-    Sampling, ScalarKind, ShaderStage, StorageFormat, Type, TypeInner,
+    back, AddressSpace, Binding, Bytes, Expression, Handle, ImageClass, ImageDimension,
+    Interpolation, Sampling, ScalarKind, ShaderStage, StorageFormat, Type, TypeInner,
 };
 use std::fmt::Write;
 
@@ -43,6 +43,12 @@ bitflags::bitflags! {
         const IMAGE_SIZE = 1 << 20;
         /// Dual source blending
         const DUAL_SOURCE_BLENDING = 1 << 21;
+        /// 2 byte floats.
+        const HALF_TYPE = 1 << 22;
+        /// Subgroup operations and built-ins.
+        const SUBGROUP_OPERATIONS = 1 << 23;
+        /// Atomic operations on storage images.
+        const IMAGE_ATOMICS = 1 << 24;
     }
 }
 
@@ -105,6 +111,8 @@ impl FeaturesManager {
         check_feature!(SAMPLE_VARIABLES, 400, 300);
         check_feature!(DYNAMIC_ARRAY_SIZE, 430, 310);
         check_feature!(DUAL_SOURCE_BLENDING, 330, 300 /* with extension */);
+        check_feature!(SUBGROUP_OPERATIONS, 430, 310 /* with extension */);
+        check_feature!(IMAGE_ATOMICS, 420, 310 /* with extension */);
         match version {
             Version::Embedded { is_webgl: true, .. } => check_feature!(MULTI_VIEW, 140, 300),
             _ => check_feature!(MULTI_VIEW, 140, 310),
@@ -148,6 +156,15 @@ impl FeaturesManager {
             writeln!(out, "#extension GL_ARB_gpu_shader_fp64 : require")?;
         }
 
+        if self.0.contains(Features::HALF_TYPE) {
+            // No version of GLSL has 16-bit floats in core.
+            // https://github.com/KhronosGroup/GLSL/blob/master/extensions/ext/GL_EXT_shader_explicit_arithmetic_types.txt
+            writeln!(
+                out,
+                "#extension GL_EXT_shader_explicit_arithmetic_types_float16 : require"
+            )?;
+        }
+
         if self.0.contains(Features::CUBE_TEXTURES_ARRAY) {
             if version.is_es() {
                 // https://www.khronos.org/registry/OpenGL/extensions/EXT/EXT_texture_cube_map_array.txt
@@ -183,6 +200,14 @@ impl FeaturesManager {
             }
         }
 
+        if self.0.contains(Features::IMAGE_ATOMICS)
+            && version.is_es()
+            && version < Version::new_gles(320)
+        {
+            // https://registry.khronos.org/OpenGL/extensions/OES/OES_shader_image_atomic.txt
+            writeln!(out, "#extension GL_OES_shader_image_atomic : require")?;
+        }
+
         if self.0.contains(Features::CONSERVATIVE_DEPTH) {
             if version.is_es() {
                 // https://www.khronos.org/registry/OpenGL/extensions/EXT/EXT_conservative_depth.txt
@@ -234,6 +259,24 @@ impl FeaturesManager {
             writeln!(out, "#extension GL_EXT_blend_func_extended : require")?;
         }
 
+        if self.0.contains(Features::SUBGROUP_OPERATIONS) {
+            // No version of GLSL has subgroup operations in core.
+            // https://github.com/KhronosGroup/GLSL/blob/master/extensions/khr/GL_KHR_shader_subgroup.txt
+            for extension in [
+                "basic",
+                "vote",
+                "ballot",
+                "arithmetic",
+                "shuffle",
+                "shuffle_relative",
+            ] {
+                writeln!(
+                    out,
+                    "#extension GL_KHR_shader_subgroup_{extension} : require"
+                )?;
+            }
+        }
+
         Ok(())
     }
 }
@@ -247,15 +290,17 @@ impl<'a, W> Writer<'a, W> {
     pub(super) fn collect_required_features(&mut self) -> BackendResult {
         let ep_info = self.info.get_entry_point(self.entry_point_idx as usize);
 
-        if let Some(depth_test) = self.entry_point.early_depth_test {
-            // If IMAGE_LOAD_STORE is supported for this version of GLSL
-            if self.options.version.supports_early_depth_test() {
-                self.features.request(Features::IMAGE_LOAD_STORE);
+        match self.entry_point.early_depth(ep_info) {
+            Some(back::EarlyDepth::Force) => {
+                // If IMAGE_LOAD_STORE is supported for this version of GLSL
+                if self.options.version.supports_early_depth_test() {
+                    self.features.request(Features::IMAGE_LOAD_STORE);
+                }
             }
-
-            if depth_test.conservative.is_some() {
+            Some(back::EarlyDepth::Conservative(_)) => {
                 self.features.request(Features::CONSERVATIVE_DEPTH);
             }
+            None => {}
         }
 
         for arg in self.entry_point.function.arguments.iter() {
@@ -340,31 +385,36 @@ impl<'a, W> Writer<'a, W> {
                                 self.features.request(Features::MULTISAMPLED_TEXTURE_ARRAYS);
                             }
                         }
-                        ImageClass::Storage { format, .. } => match format {
-                            StorageFormat::R8Unorm
-                            | StorageFormat::R8Snorm
-                            | StorageFormat::R8Uint
-                            | StorageFormat::R8Sint
-                            | StorageFormat::R16Uint
-                            | StorageFormat::R16Sint
-                            | StorageFormat::R16Float
-                            | StorageFormat::Rg8Unorm
-                            | StorageFormat::Rg8Snorm
-                            | StorageFormat::Rg8Uint
-                            | StorageFormat::Rg8Sint
-                            | StorageFormat::Rg16Uint
-                            | StorageFormat::Rg16Sint
-                            | StorageFormat::Rg16Float
-                            | StorageFormat::Rgb10a2Uint
-                            | StorageFormat::Rgb10a2Unorm
-                            | StorageFormat::Rg11b10Float
-                            | StorageFormat::Rg32Uint
-                            | StorageFormat::Rg32Sint
-                            | StorageFormat::Rg32Float => {
-                                self.features.request(Features::FULL_IMAGE_FORMATS)
+                        ImageClass::Storage { format, access } => {
+                            if access.contains(crate::StorageAccess::ATOMIC) {
+                                self.features.request(Features::IMAGE_ATOMICS);
                             }
-                            _ => {}
-                        },
+                            match format {
+                                StorageFormat::R8Unorm
+                                | StorageFormat::R8Snorm
+                                | StorageFormat::R8Uint
+                                | StorageFormat::R8Sint
+                                | StorageFormat::R16Uint
+                                | StorageFormat::R16Sint
+                                | StorageFormat::R16Float
+                                | StorageFormat::Rg8Unorm
+                                | StorageFormat::Rg8Snorm
+                                | StorageFormat::Rg8Uint
+                                | StorageFormat::Rg8Sint
+                                | StorageFormat::Rg16Uint
+                                | StorageFormat::Rg16Sint
+                                | StorageFormat::Rg16Float
+                                | StorageFormat::Rgb10a2Uint
+                                | StorageFormat::Rgb10a2Unorm
+                                | StorageFormat::Rg11b10Float
+                                | StorageFormat::Rg32Uint
+                                | StorageFormat::Rg32Sint
+                                | StorageFormat::Rg32Float => {
+                                    self.features.request(Features::FULL_IMAGE_FORMATS)
+                                }
+                                _ => {}
+                            }
+                        }
                         ImageClass::Sampled { multi: false, .. }
                         | ImageClass::Depth { multi: false } => {}
                     }
@@ -381,7 +431,11 @@ impl<'a, W> Writer<'a, W> {
             }
             match global.space {
                 AddressSpace::WorkGroup => self.features.request(Features::COMPUTE_SHADER),
-                AddressSpace::Storage { .. } => self.features.request(Features::BUFFER_STORAGE),
+                AddressSpace::Storage { access } => {
+                    if !self.storage_as_uniform(global.ty, access) {
+                        self.features.request(Features::BUFFER_STORAGE)
+                    }
+                }
                 AddressSpace::PushConstant => {
                     if push_constant_used {
                         return Err(Error::MultiplePushConstants);
@@ -402,8 +456,12 @@ impl<'a, W> Writer<'a, W> {
             entry_point,
             entry_point_idx,
             ref policies,
+            options,
             ..
         } = self;
+        let shadow_lod = options
+            .writer_flags
+            .contains(WriterFlags::TEXTURE_SHADOW_LOD);
 
         // Loop trough all expressions in both functions and the entry point
         // to check for needed features
@@ -418,6 +476,13 @@ impl<'a, W> Writer<'a, W> {
         {
             for (_, expr) in expressions.iter() {
                 match *expr {
+                // Literals may use types the module's arena doesn't have
+                Expression::Literal(crate::Literal::F16(_)) => {
+                    features.request(Features::HALF_TYPE);
+                }
+                Expression::SubgroupOperation { .. } => {
+                    features.request(Features::SUBGROUP_OPERATIONS);
+                }
                 // Check for queries that neeed aditonal features
                 Expression::ImageQuery {
                     image,
@@ -442,7 +507,7 @@ impl<'a, W> Writer<'a, W> {
                 // Check for image loads that needs bound checking on the sample
                 // or level argument since this requires a feature
                 Expression::ImageLoad {
-                    sample, level, ..
+                    image, sample, level, ..
                 } => {
                     if policies.image_load != crate::proc::BoundsCheckPolicy::Unchecked {
                         if sample.is_some() {
@@ -452,6 +517,27 @@ impl<'a, W> Writer<'a, W> {
                         if level.is_some() {
                             features.request(Features::TEXTURE_LEVELS)
                         }
+
+                        // Storage images are checked against `imageSize`.
+                        if let TypeInner::Image {
+                            class: crate::ImageClass::Storage { .. }, ..
+                        } = *info[image].ty.inner_with(&module.types) {
+                            features.request(Features::IMAGE_SIZE)
+                        }
+                    }
+                }
+                // Reject depth comparisons that have no GLSL function up front,
+                // rather than partway through writing the expression
+                Expression::ImageSample {
+                    image,
+                    gather: None,
+                    array_index,
+                    level,
+                    depth_ref: Some(_),
+                    ..
+                } => {
+                    if let TypeInner::Image { dim, .. } = *info[image].ty.inner_with(&module.types) {
+                        check_shadow_sample(dim, array_index.is_some(), level, shadow_lod)?;
                     }
                 }
                 _ => {}
@@ -464,8 +550,12 @@ impl<'a, W> Writer<'a, W> {
 
     /// Helper method that checks the [`Features`] needed by a scalar
     fn scalar_required_features(&mut self, kind: ScalarKind, width: Bytes) {
-        if kind == ScalarKind::Float && width == 8 {
-            self.features.request(Features::DOUBLE_TYPE);
+        if kind == ScalarKind::Float {
+            match width {
+                2 => self.features.request(Features::HALF_TYPE),
+                8 => self.features.request(Features::DOUBLE_TYPE),
+                _ => {}
+            }
         }
     }
 
@@ -492,6 +582,9 @@ impl<'a, W> Writer<'a, W> {
                             crate::BuiltIn::ViewIndex => {
                                 self.features.request(Features::MULTI_VIEW)
                             }
+                            crate::BuiltIn::SubgroupSize | crate::BuiltIn::SubgroupInvocationId => {
+                                self.features.request(Features::SUBGROUP_OPERATIONS)
+                            }
                             _ => {}
                         },
                         Binding::Location {
@@ -516,3 +609,185 @@ impl<'a, W> Writer<'a, W> {
         }
     }
 }
+
+/// Checks that a depth comparison sample can be written as a GLSL texture function.
+///
+/// `shadow_lod` is whether [`WriterFlags::TEXTURE_SHADOW_LOD`] is set.
+fn check_shadow_sample(
+    dim: ImageDimension,
+    arrayed: bool,
+    level: crate::SampleLevel,
+    shadow_lod: bool,
+) -> BackendResult {
+    use crate::SampleLevel as Sl;
+
+    let (sampler, function, needs_shadow_lod) = match (dim, arrayed, level) {
+        (ImageDimension::Cube, true, Sl::Gradient { .. }) => {
+            ("samplerCubeArrayShadow", "textureGrad", false)
+        }
+        (ImageDimension::Cube, true, Sl::Zero | Sl::Exact(_)) if !shadow_lod => {
+            ("samplerCubeArrayShadow", "textureLod", true)
+        }
+        (ImageDimension::Cube, true, Sl::Bias(_)) if !shadow_lod => {
+            ("samplerCubeArrayShadow", "texture with bias", true)
+        }
+        (ImageDimension::D2, true, Sl::Bias(_)) if !shadow_lod => {
+            ("sampler2DArrayShadow", "texture with bias", true)
+        }
+        _ => return Ok(()),
+    };
+
+    Err(Error::UnsupportedShadowSample {
+        sampler,
+        function,
+        needs_shadow_lod,
+    })
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod tests {
+    use crate::back::glsl::{Error, Options, PipelineOptions, WriterFlags};
+    use crate::test_util::{Fixture, Output};
+
+    fn write(fixture: &Fixture, writer_flags: WriterFlags) -> Result<Output, Error> {
+        let options = Options {
+            writer_flags,
+            ..Options::default()
+        };
+        let pipeline_options = PipelineOptions {
+            shader_stage: crate::ShaderStage::Fragment,
+            entry_point: "main".to_string(),
+            multiview: None,
+        };
+        fixture.try_to_glsl(&options, &pipeline_options, Default::default())
+    }
+
+    #[test]
+    fn cube_array_shadow_level() {
+        let fixture = Fixture::wgsl(
+            "
+            @group(0) @binding(0) var t: texture_depth_cube_array;
+            @group(0) @binding(1) var s: sampler_comparison;
+            @fragment
+            fn main() -> @location(0) f32 {
+                return textureSampleCompareLevel(t, s, vec3(1.0), 0, 0.5);
+            }
+            ",
+        );
+
+        assert!(matches!(
+            write(&fixture, WriterFlags::empty()),
+            Err(Error::UnsupportedShadowSample {
+                sampler: "samplerCubeArrayShadow",
+                function: "textureLod",
+                needs_shadow_lod: true,
+            })
+        ));
+        assert!(write(&fixture, WriterFlags::TEXTURE_SHADOW_LOD).is_ok());
+    }
+
+    #[test]
+    fn cube_array_shadow_auto() {
+        let fixture = Fixture::wgsl(
+            "
+            @group(0) @binding(0) var t: texture_depth_cube_array;
+            @group(0) @binding(1) var s: sampler_comparison;
+            @fragment
+            fn main() -> @location(0) f32 {
+                return textureSampleCompare(t, s, vec3(1.0), 0, 0.5);
+            }
+            ",
+        );
+
+        assert!(write(&fixture, WriterFlags::empty()).is_ok());
+    }
+
+    #[test]
+    fn doubles_in_es() {
+        // The default options target GLSL ES, which has no doubles.
+        let fixture = Fixture::wgsl_with_capabilities(
+            "
+            @fragment
+            fn main() -> @location(0) vec4<f32> {
+                let half = f64(0.5);
+                return vec4(f32(half * f64(2.0)));
+            }
+            ",
+            crate::valid::Capabilities::FLOAT64,
+        );
+
+        assert!(matches!(
+            write(&fixture, WriterFlags::empty()),
+            Err(Error::MissingFeatures(_))
+        ));
+    }
+
+    #[test]
+    fn early_depth_test() {
+        let write = |source| {
+            let capabilities = crate::valid::Capabilities::EARLY_DEPTH_TEST;
+            write(
+                &Fixture::wgsl_with_capabilities(source, capabilities),
+                WriterFlags::empty(),
+            )
+            .unwrap()
+        };
+        let forced = write(
+            "
+            @fragment @early_depth_test(less_equal)
+            fn main() -> @location(0) vec4<f32> {
+                return vec4(1.0);
+            }
+            ",
+        );
+        forced.assert_contains("layout(early_fragment_tests) in;");
+
+        // A shader that writes depth only describes how it changes it.
+        let conservative = write(
+            "
+            @fragment @early_depth_test(greater_equal)
+            fn main(@builtin(position) position: vec4<f32>) -> @builtin(frag_depth) f32 {
+                return position.z;
+            }
+            ",
+        );
+        conservative
+            .assert_contains("#extension GL_EXT_conservative_depth : require")
+            .assert_contains("layout (depth_greater) out float gl_FragDepth;")
+            .assert_not_contains("early_fragment_tests");
+    }
+
+    #[test]
+    fn storage_stores() {
+        let fixture = Fixture::wgsl(
+            "
+            @group(0) @binding(0) var rgba: texture_storage_2d<rgba8unorm, write>;
+            @group(0) @binding(1) var red: texture_storage_2d<r16float, write>;
+            @group(0) @binding(2) var rgb: texture_storage_2d<rg11b10float, write>;
+            @group(0) @binding(3) var rg: texture_storage_2d<rg8snorm, write>;
+
+            @fragment
+            fn main(@builtin(position) position: vec4<f32>) {
+                let value = vec4(position.xyz, 1.0);
+                textureStore(rgba, vec2(0), value);
+                textureStore(red, vec2(0), value);
+                textureStore(rgb, vec2(0), value);
+                textureStore(rg, vec2(0), value);
+            }
+            ",
+        );
+        let output = write(&fixture, WriterFlags::empty()).unwrap();
+
+        // The layout qualifiers say how to pack the four components.
+        for layout in ["rgba8", "r16f", "r11f_g11f_b10f", "rg8_snorm"] {
+            output.assert_contains(&format!("layout({layout}) writeonly"));
+        }
+        let stores: Vec<_> = output
+            .as_str()
+            .lines()
+            .filter(|line| line.contains("imageStore("))
+            .collect();
+        assert_eq!(stores.len(), 4);
+        assert!(stores.iter().all(|line| line.ends_with(", value);")));
+    }
+}
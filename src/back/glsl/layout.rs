@@ -0,0 +1,265 @@
+/*!
+Checking that GLSL's block layout rules agree with the module's own layout.
+
+GLSL gives no way to spell out the offset of each member of a block, or the
+strides of its arrays and matrices: they all follow from the block's `std140`
+or `std430` layout. If the module places something elsewhere, the shader and
+the host disagree about the buffer's contents.
+*/
+
+use super::{BlockLayout, Error};
+use crate::{Handle, Module, TypeInner, VectorSize};
+use std::fmt::Write;
+
+/// Check that `layout` places everything in a value of type `ty` where the
+/// module does.
+///
+/// `path` names the value, and is extended to name the first member,
+/// element or matrix column placed differently, in the error.
+pub(super) fn check(
+    module: &Module,
+    ty: Handle<crate::Type>,
+    layout: BlockLayout,
+    path: &mut String,
+) -> Result<(), Error> {
+    check_at(module, ty, layout, path, 0)
+}
+
+fn check_at(
+    module: &Module,
+    ty: Handle<crate::Type>,
+    layout: BlockLayout,
+    path: &mut String,
+    offset: u32,
+) -> Result<(), Error> {
+    let mismatch = |path: &mut String, suffix: &str, module_offset: u32, glsl_offset: u32| {
+        path.push_str(suffix);
+        Err(Error::BlockLayoutMismatch {
+            layout,
+            member: std::mem::take(path),
+            offset: offset + module_offset,
+            glsl_offset: offset + glsl_offset,
+        })
+    };
+
+    match module.types[ty].inner {
+        TypeInner::Matrix { rows, width, .. } => {
+            // Matrices have at least two columns, so the stride always matters.
+            let module_stride = vector_alignment(rows, width);
+            let glsl_stride = layout.round(module_stride);
+            if module_stride != glsl_stride {
+                return mismatch(path, "[1]", module_stride, glsl_stride);
+            }
+        }
+        TypeInner::Array { base, size, stride } => {
+            let (glsl_stride, _) = array_stride(module, base, layout);
+            let single = matches!(size, crate::ArraySize::Constant(count) if count.get() == 1);
+            if !single && stride != glsl_stride {
+                return mismatch(path, "[1]", stride, glsl_stride);
+            }
+            let len = path.len();
+            path.push_str("[0]");
+            check_at(module, base, layout, path, offset)?;
+            path.truncate(len);
+        }
+        TypeInner::Struct { ref members, .. } => {
+            let mut end = 0;
+            for (index, member) in members.iter().enumerate() {
+                let (size, alignment) = size_alignment(module, member.ty, layout);
+                let glsl_offset = round_up(end, alignment);
+                let len = path.len();
+                match member.name {
+                    Some(ref name) => write!(path, ".{name}").unwrap(),
+                    None => write!(path, ".{index}").unwrap(),
+                }
+                if member.offset != glsl_offset {
+                    return mismatch(path, "", member.offset, glsl_offset);
+                }
+                check_at(module, member.ty, layout, path, offset + member.offset)?;
+                path.truncate(len);
+                end = glsl_offset + size;
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Return the size and alignment `layout` gives a value of type `ty`.
+///
+/// Runtime-sized arrays are given the size of a single element; they can
+/// only come last, so their size doesn't affect any offsets.
+fn size_alignment(module: &Module, ty: Handle<crate::Type>, layout: BlockLayout) -> (u32, u32) {
+    match module.types[ty].inner {
+        TypeInner::Scalar { width, .. } | TypeInner::Atomic { width, .. } => {
+            (width as u32, width as u32)
+        }
+        TypeInner::Vector { size, width, .. } => {
+            (size as u32 * width as u32, vector_alignment(size, width))
+        }
+        TypeInner::Matrix {
+            columns,
+            rows,
+            width,
+        } => {
+            let stride = layout.round(vector_alignment(rows, width));
+            (columns as u32 * stride, stride)
+        }
+        TypeInner::Array { base, size, .. } => {
+            let (stride, alignment) = array_stride(module, base, layout);
+            let count = match size {
+                crate::ArraySize::Constant(count) => count.get(),
+                crate::ArraySize::Dynamic => 1,
+            };
+            (count * stride, alignment)
+        }
+        TypeInner::Struct { ref members, .. } => {
+            let mut end = 0;
+            let mut alignment = 1;
+            for member in members {
+                let (size, member_alignment) = size_alignment(module, member.ty, layout);
+                end = round_up(end, member_alignment) + size;
+                alignment = alignment.max(member_alignment);
+            }
+            let alignment = layout.round(alignment);
+            (round_up(end, alignment), alignment)
+        }
+        _ => (0, 1),
+    }
+}
+
+/// Return the stride and alignment `layout` gives an array of `base`.
+fn array_stride(module: &Module, base: Handle<crate::Type>, layout: BlockLayout) -> (u32, u32) {
+    let (size, alignment) = size_alignment(module, base, layout);
+    let alignment = layout.round(alignment);
+    (round_up(size, alignment), alignment)
+}
+
+/// The alignment of a vector in both layouts: that of a `vec4` for `vec3`.
+const fn vector_alignment(size: VectorSize, width: crate::Bytes) -> u32 {
+    match size {
+        VectorSize::Bi => 2 * width as u32,
+        VectorSize::Tri | VectorSize::Quad => 4 * width as u32,
+    }
+}
+
+const fn round_up(value: u32, alignment: u32) -> u32 {
+    (value + alignment - 1) / alignment * alignment
+}
+
+impl BlockLayout {
+    /// Round up the alignment of an array element, matrix column or struct,
+    /// as `std140` does.
+    const fn round(self, alignment: u32) -> u32 {
+        match self {
+            Self::Std140 => round_up(alignment, 16),
+            Self::Std430 => alignment,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod tests {
+    use crate::back::glsl::{
+        BlockLayout, Error, Options, PipelineOptions, ReflectionInfo, Version, Writer, WriterFlags,
+    };
+
+    #[test]
+    fn block_layouts() {
+        let fixture = crate::test_util::Fixture::wgsl(
+            "
+            struct Params {
+                scale: vec4<f32>,
+                transform: mat3x2<f32>,
+            }
+            @group(0) @binding(0) var<uniform> params: Params;
+            @group(0) @binding(1) var<storage, read_write> weights: array<f32, 4>;
+
+            @compute @workgroup_size(1)
+            fn main() {
+                weights[0] = params.scale.x * params.transform[0].x;
+            }
+
+            @compute @workgroup_size(1)
+            fn store() {
+                weights[1] = 2.0;
+            }
+            ",
+        );
+        let write =
+            |entry_point: &str, options: &Options| -> Result<(String, ReflectionInfo), Error> {
+                let mut text = String::new();
+                Writer::new(
+                    &mut text,
+                    &fixture.module,
+                    &fixture.info,
+                    options,
+                    &PipelineOptions {
+                        shader_stage: crate::ShaderStage::Compute,
+                        entry_point: entry_point.to_string(),
+                        multiview: None,
+                    },
+                    crate::proc::BoundsCheckPolicies::default(),
+                )
+                .and_then(|mut writer| writer.write())
+                .map(|reflection| (text, reflection))
+            };
+        let mut globals = fixture.module.global_variables.iter();
+        let (params, _) = globals.next().unwrap();
+        let (weights, _) = globals.next().unwrap();
+
+        // Without the check, mismatched blocks are still written.
+        let (text, reflection) = write(
+            "main",
+            &Options {
+                version: Version::Desktop(430),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!(text.contains("layout(std140) uniform Params_block_0Compute"));
+        assert!(text.contains("layout(std430) buffer type_"));
+        assert_eq!(reflection.block_layouts[&params], BlockLayout::Std140);
+        assert_eq!(reflection.block_layouts[&weights], BlockLayout::Std430);
+
+        let checked = |version, storage_block_layout| Options {
+            version,
+            writer_flags: WriterFlags::CHECK_BLOCK_LAYOUTS,
+            storage_block_layout,
+            ..Default::default()
+        };
+        let desktop = Version::Desktop(430);
+
+        // `std140` pads matrix columns to 16 bytes.
+        match write("main", &checked(desktop, None)) {
+            Err(Error::BlockLayoutMismatch {
+                layout: BlockLayout::Std140,
+                ref member,
+                offset: 24,
+                glsl_offset: 32,
+            }) if member == "params.transform[1]" => {}
+            other => panic!("unexpected result: {other:?}"),
+        }
+
+        // ... and array elements.
+        assert!(write("store", &checked(desktop, None)).is_ok());
+        match write("store", &checked(desktop, Some(BlockLayout::Std140))) {
+            Err(Error::BlockLayoutMismatch {
+                layout: BlockLayout::Std140,
+                ref member,
+                offset: 4,
+                glsl_offset: 16,
+            }) if member == "weights[1]" => {}
+            other => panic!("unexpected result: {other:?}"),
+        }
+
+        assert!(matches!(
+            write(
+                "store",
+                &checked(Version::Desktop(410), Some(BlockLayout::Std430))
+            ),
+            Err(Error::VersionNotSupported)
+        ));
+    }
+}
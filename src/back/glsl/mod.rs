@@ -17,6 +17,13 @@ to output a [`Module`](crate::Module) into glsl
 - 300
 - 310
 
+# Entry point inputs and outputs
+
+GLSL entry points take no parameters and return nothing: inputs and outputs
+are always written as global `in` and `out` variables, with struct members
+flattened into variables of their own, and built-ins become `gl_*` variables.
+Unlike the HLSL backend, there is no other form to choose.
+
 [glsl]: https://www.khronos.org/registry/OpenGL/index_gl.php
 */
 
@@ -63,6 +70,10 @@ use thiserror::Error;
 mod features;
 /// Contains a constant with a slice of all the reserved keywords RESERVED_KEYWORDS
 mod keywords;
+/// Checks that block layouts place everything where the module does
+mod layout;
+#[cfg(all(test, feature = "test-util"))]
+mod tests;
 
 /// List of supported `core` GLSL versions.
 pub const SUPPORTED_CORE_VERSIONS: &[u16] = &[140, 150, 330, 400, 410, 420, 430, 440, 450, 460];
@@ -234,6 +245,13 @@ bitflags::bitflags! {
         const ADJUST_COORDINATE_SPACE = 0x1;
         /// Supports GL_EXT_texture_shadow_lod on the host, which provides
         /// additional functions on shadows and arrays of shadows.
+        ///
+        /// Without it, explicit-level comparisons on `sampler2DArrayShadow` and
+        /// `samplerCubeShadow` fall back to `textureGrad` with zero gradients,
+        /// which always samples the base level. Biased comparisons on
+        /// `sampler2DArrayShadow`, and anything but an automatic-level
+        /// comparison on `samplerCubeArrayShadow`, are rejected with
+        /// [`Error::UnsupportedShadowSample`].
         const TEXTURE_SHADOW_LOD = 0x2;
         /// Include unused global variables, constants and functions. By default the output will exclude
         /// global variables that are not used in the specified entrypoint (including indirect use),
@@ -246,6 +264,22 @@ bitflags::bitflags! {
         /// The variable gl_PointSize is intended for a shader to write the size of the point to be rasterized. It is measured in pixels.
         /// If gl_PointSize is not written to, its value is undefined in subsequent pipe stages.
         const FORCE_POINT_SIZE = 0x10;
+        /// Write output that OpenGL 3.3 and macOS's OpenGL 4.1 accept.
+        ///
+        /// No `binding =` qualifiers are written for resources, even when
+        /// the version has them: the user must assign each texture unit and
+        /// uniform block binding by name, using the names in
+        /// [`ReflectionInfo`]. Read-only storage buffers whose type has the
+        /// same layout in the `uniform` address space are written as uniform
+        /// blocks, so they don't need shader storage buffer objects.
+        const DESKTOP_COMPATIBILITY = 0x20;
+        /// Fail with [`Error::BlockLayoutMismatch`] when a uniform or storage
+        /// block's [`BlockLayout`] places a member, array element or matrix
+        /// column elsewhere than the module does.
+        ///
+        /// Without it, such blocks are still written, and only a warning is
+        /// logged.
+        const CHECK_BLOCK_LAYOUTS = 0x40;
     }
 }
 
@@ -261,7 +295,24 @@ pub struct Options {
     /// Map of resources association to binding locations.
     pub binding_map: BindingMap,
     /// Should workgroup variables be zero initialized (by polyfilling)?
+    ///
+    /// Turn this off for modules that have been through
+    /// [`zero_initialize_workgroup_memory`], which does the same in the IR.
+    ///
+    /// [`zero_initialize_workgroup_memory`]: crate::proc::zero_initialize_workgroup_memory
     pub zero_initialize_workgroup_memory: bool,
+    /// If set, write push constants as a `std140` uniform block at this
+    /// binding, which the user must leave free for them.
+    ///
+    /// Otherwise, push constants are written as a plain `uniform`, and
+    /// [`ReflectionInfo::push_constant_items`] lists the uniforms to set.
+    pub push_constant_binding: Option<u32>,
+    /// The layout to declare storage blocks with.
+    ///
+    /// By default, this is `std430` if the version supports it, and `std140`
+    /// otherwise. Uniform blocks, including push constant blocks, are always
+    /// `std140`.
+    pub storage_block_layout: Option<BlockLayout>,
 }
 
 impl Default for Options {
@@ -271,10 +322,39 @@ impl Default for Options {
             writer_flags: WriterFlags::ADJUST_COORDINATE_SPACE,
             binding_map: BindingMap::default(),
             zero_initialize_workgroup_memory: true,
+            push_constant_binding: None,
+            storage_block_layout: None,
         }
     }
 }
 
+/// The memory layout of a uniform or storage block.
+///
+/// GLSL derives the offset of every member of a block from its layout, so
+/// the [`Writer`] only produces correct code for a block if the layout
+/// agrees with the offsets and strides in the module. See
+/// [`WriterFlags::CHECK_BLOCK_LAYOUTS`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
+pub enum BlockLayout {
+    /// Array strides, and the alignments of structs and matrix columns, are
+    /// rounded up to 16 bytes.
+    Std140,
+    /// Everything is aligned as in WGSL's `storage` address space. This
+    /// needs GLSL 4.30 or GLSL ES 3.10.
+    Std430,
+}
+
+impl fmt::Display for BlockLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match *self {
+            Self::Std140 => "std140",
+            Self::Std430 => "std430",
+        })
+    }
+}
+
 /// A subset of options meant to be changed per pipeline.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serialize", derive(serde::Serialize))]
@@ -309,6 +389,32 @@ pub struct ReflectionInfo {
     pub uniforms: crate::FastHashMap<Handle<crate::GlobalVariable>, String>,
     /// Mapping between names and attribute locations.
     pub varying: crate::FastHashMap<String, VaryingLocation>,
+    /// The uniforms a plain `uniform` push constant is made of, if any.
+    ///
+    /// This is empty if [`Options::push_constant_binding`] is set: the push
+    /// constant block is then listed in [`uniforms`](Self::uniforms).
+    pub push_constant_items: Vec<PushConstantItem>,
+    /// The layout each uniform, storage and push constant block was declared
+    /// with.
+    pub block_layouts: crate::FastHashMap<Handle<crate::GlobalVariable>, BlockLayout>,
+}
+
+/// A uniform holding part of the push constant data.
+///
+/// OpenGL sets the members of a plain `uniform` one scalar, vector or matrix
+/// at a time, so to emulate push constants, the user must copy each part of
+/// the push constant data to the uniform it belongs to.
+#[derive(Debug, Clone)]
+pub struct PushConstantItem {
+    /// The name of the uniform, for `glGetUniformLocation`.
+    ///
+    /// This is how the shader itself refers to it, like `pc.color` or
+    /// `pc.lights[1].position`.
+    pub access_path: String,
+    /// The type of the uniform, always a scalar, vector or matrix.
+    pub ty: Handle<crate::Type>,
+    /// The offset of the uniform's data within the push constant data.
+    pub offset: u32,
 }
 
 /// Mapping between a texture and its sampler, if it exists.
@@ -430,6 +536,26 @@ pub enum Error {
     /// A image was used with multiple samplers, which isn't supported.
     #[error("A image was used with multiple samplers")]
     ImageMultipleSamplers,
+    /// A depth comparison sample was requested that has no GLSL equivalent
+    /// with the selected [`WriterFlags`].
+    #[error("`{function}` isn't supported on `{sampler}` with the selected writer flags")]
+    UnsupportedShadowSample {
+        sampler: &'static str,
+        function: &'static str,
+        /// Whether [`WriterFlags::TEXTURE_SHADOW_LOD`] would make the sample
+        /// supported.
+        needs_shadow_lod: bool,
+    },
+    /// A block's [`BlockLayout`] places `member` at `glsl_offset` bytes into
+    /// the block, but the module places it at `offset`. This is only reported
+    /// with [`WriterFlags::CHECK_BLOCK_LAYOUTS`].
+    #[error("The {layout} layout places `{member}` at offset {glsl_offset}, but the module places it at offset {offset}")]
+    BlockLayoutMismatch {
+        layout: BlockLayout,
+        member: String,
+        offset: u32,
+        glsl_offset: u32,
+    },
     #[error("{0}")]
     Custom(String),
 }
@@ -469,6 +595,8 @@ pub struct Writer<'a, W> {
     names: crate::FastHashMap<NameKey, String>,
     /// A map with the names of global variables needed for reflections.
     reflection_names_globals: crate::FastHashMap<Handle<crate::GlobalVariable>, String>,
+    /// The layout each block was declared with, for reflection.
+    block_layouts: crate::FastHashMap<Handle<crate::GlobalVariable>, BlockLayout>,
     /// The selected entry point.
     entry_point: &'a crate::EntryPoint,
     /// The index of the selected entry point.
@@ -505,6 +633,12 @@ impl<'a, W: Write> Writer<'a, W> {
             log::error!("Version {}", options.version);
             return Err(Error::VersionNotSupported);
         }
+        if options.storage_block_layout == Some(BlockLayout::Std430)
+            && !options.version.supports_std430_layout()
+        {
+            log::error!("Version {} has no std430 layout", options.version);
+            return Err(Error::VersionNotSupported);
+        }
 
         // Try to find the entry point and corresponding index
         let ep_idx = module
@@ -539,6 +673,7 @@ impl<'a, W: Write> Writer<'a, W> {
             features: FeaturesManager::new(),
             names,
             reflection_names_globals: crate::FastHashMap::default(),
+            block_layouts: crate::FastHashMap::default(),
             entry_point: &module.entry_points[ep_idx],
             entry_point_idx: ep_idx as u16,
             multiview: pipeline_options.multiview,
@@ -606,29 +741,35 @@ impl<'a, W: Write> Writer<'a, W> {
             writeln!(self.out)?;
         }
 
-        // Enable early depth tests if needed
-        if let Some(depth_test) = self.entry_point.early_depth_test {
-            // If early depth test is supported for this version of GLSL
-            if self.options.version.supports_early_depth_test() {
-                writeln!(self.out, "layout(early_fragment_tests) in;")?;
-
-                if let Some(conservative) = depth_test.conservative {
-                    use crate::ConservativeDepth as Cd;
-
-                    let depth = match conservative {
-                        Cd::GreaterEqual => "greater",
-                        Cd::LessEqual => "less",
-                        Cd::Unchanged => "unchanged",
-                    };
-                    writeln!(self.out, "layout (depth_{depth}) out float gl_FragDepth;")?;
+        // Enable early depth tests, or describe how depth changes, if needed
+        let ep_info = self.info.get_entry_point(self.entry_point_idx as usize);
+        match self.entry_point.early_depth(ep_info) {
+            Some(back::EarlyDepth::Force) => {
+                // If early depth test is supported for this version of GLSL
+                if self.options.version.supports_early_depth_test() {
+                    writeln!(self.out, "layout(early_fragment_tests) in;")?;
+                    writeln!(self.out)?;
+                } else {
+                    log::warn!(
+                        "Early depth testing is not supported for this version of GLSL: {}",
+                        self.options.version
+                    );
                 }
+            }
+            Some(back::EarlyDepth::Conservative(conservative)) => {
+                use crate::ConservativeDepth as Cd;
+
+                // `early_fragment_tests` would make the driver ignore the
+                // depth the shader writes.
+                let depth = match conservative {
+                    Cd::GreaterEqual => "greater",
+                    Cd::LessEqual => "less",
+                    Cd::Unchanged => "unchanged",
+                };
+                writeln!(self.out, "layout (depth_{depth}) out float gl_FragDepth;")?;
                 writeln!(self.out)?;
-            } else {
-                log::warn!(
-                    "Early depth testing is not supported for this version of GLSL: {}",
-                    self.options.version
-                );
             }
+            None => {}
         }
 
         if self.entry_point.stage == ShaderStage::Vertex && self.options.version.is_webgl() {
@@ -733,8 +874,6 @@ impl<'a, W: Write> Writer<'a, W> {
             }
         }
 
-        let ep_info = self.info.get_entry_point(self.entry_point_idx as usize);
-
         // Write the globals
         //
         // Unless explicitly disabled with WriterFlags::INCLUDE_UNUSED_ITEMS,
@@ -773,7 +912,7 @@ impl<'a, W: Write> Writer<'a, W> {
                     }
 
                     // Gether the location if needed
-                    let layout_binding = if self.options.version.supports_explicit_locations() {
+                    let layout_binding = if self.explicit_bindings() {
                         let br = global.binding.as_ref().unwrap();
                         self.options.binding_map.get(br).cloned()
                     } else {
@@ -1060,44 +1199,64 @@ impl<'a, W: Write> Writer<'a, W> {
         handle: Handle<crate::GlobalVariable>,
         global: &crate::GlobalVariable,
     ) -> BackendResult {
-        if self.options.version.supports_explicit_locations() {
-            if let Some(ref br) = global.binding {
-                match self.options.binding_map.get(br) {
-                    Some(binding) => {
-                        let layout = match global.space {
-                            crate::AddressSpace::Storage { .. } => {
-                                if self.options.version.supports_std430_layout() {
-                                    "std430, "
-                                } else {
-                                    "std140, "
-                                }
-                            }
-                            crate::AddressSpace::Uniform => "std140, ",
-                            _ => "",
-                        };
-                        write!(self.out, "layout({layout}binding = {binding}) ")?
-                    }
-                    None => {
-                        log::debug!("unassigned binding for {:?}", global.name);
-                        if let crate::AddressSpace::Storage { .. } = global.space {
-                            if self.options.version.supports_std430_layout() {
-                                write!(self.out, "layout(std430) ")?
-                            }
-                        }
-                    }
-                }
+        // Storage buffers written as uniform blocks are `uniform`s from
+        // here on.
+        let space = match global.space {
+            crate::AddressSpace::Storage { access }
+                if self.storage_as_uniform(global.ty, access) =>
+            {
+                crate::AddressSpace::Uniform
+            }
+            space => space,
+        };
+        let push_constant_block = match space {
+            crate::AddressSpace::PushConstant => self.options.push_constant_binding,
+            _ => None,
+        };
+        let layout = match space {
+            crate::AddressSpace::Uniform => Some(BlockLayout::Std140),
+            crate::AddressSpace::PushConstant if push_constant_block.is_some() => {
+                Some(BlockLayout::Std140)
+            }
+            crate::AddressSpace::Storage { .. } => Some(self.storage_block_layout()),
+            _ => None,
+        };
+        let binding = if !self.explicit_bindings() {
+            None
+        } else if push_constant_block.is_some() {
+            push_constant_block
+        } else if let Some(ref br) = global.binding {
+            let binding = self.options.binding_map.get(br).cloned();
+            if binding.is_none() {
+                log::debug!("unassigned binding for {:?}", global.name);
+            }
+            binding
+        } else {
+            None
+        };
+
+        if let Some(layout) = layout {
+            self.check_block_layout(global, layout)?;
+            self.block_layouts.insert(handle, layout);
+        }
+        match (layout, binding) {
+            (Some(layout), Some(binding)) => {
+                write!(self.out, "layout({layout}, binding = {binding}) ")?
             }
+            (Some(layout), None) => write!(self.out, "layout({layout}) ")?,
+            (None, Some(binding)) => write!(self.out, "layout(binding = {binding}) ")?,
+            (None, None) => {}
         }
 
-        if let crate::AddressSpace::Storage { access } = global.space {
+        if let crate::AddressSpace::Storage { access } = space {
             self.write_storage_access(access)?;
         }
 
-        if let Some(storage_qualifier) = glsl_storage_qualifier(global.space) {
+        if let Some(storage_qualifier) = glsl_storage_qualifier(space) {
             write!(self.out, "{storage_qualifier} ")?;
         }
 
-        match global.space {
+        match space {
             crate::AddressSpace::Private => {
                 self.write_simple_global(handle, global)?;
             }
@@ -1105,7 +1264,11 @@ impl<'a, W: Write> Writer<'a, W> {
                 self.write_simple_global(handle, global)?;
             }
             crate::AddressSpace::PushConstant => {
-                self.write_simple_global(handle, global)?;
+                if push_constant_block.is_some() {
+                    self.write_interface_block(handle, global)?;
+                } else {
+                    self.write_simple_global(handle, global)?;
+                }
             }
             crate::AddressSpace::Uniform => {
                 self.write_interface_block(handle, global)?;
@@ -1227,7 +1390,14 @@ impl<'a, W: Write> Writer<'a, W> {
 
             let inner = expr_info.ty.inner_with(&self.module.types);
 
-            if let Expression::Math { fun, arg, arg1, .. } = *expr {
+            if let Expression::Math {
+                fun,
+                arg,
+                arg1,
+                arg2,
+                ..
+            } = *expr
+            {
                 match fun {
                     crate::MathFunction::Dot => {
                         // if the expression is a Dot product with integer arguments,
@@ -1247,6 +1417,13 @@ impl<'a, W: Write> Writer<'a, W> {
                             self.need_bake_expressions.insert(arg);
                         }
                     }
+                    // The offset is written twice, to clamp the count.
+                    crate::MathFunction::ExtractBits => {
+                        self.need_bake_expressions.insert(arg1.unwrap());
+                    }
+                    crate::MathFunction::InsertBits => {
+                        self.need_bake_expressions.insert(arg2.unwrap());
+                    }
                     _ => {}
                 }
             }
@@ -2226,6 +2403,16 @@ impl<'a, W: Write> Writer<'a, W> {
                 write!(self.out, "{level}")?;
                 self.write_image_store(ctx, image, coordinate, array_index, value)?
             }
+            Statement::ImageAtomic {
+                image,
+                coordinate,
+                array_index,
+                fun,
+                value,
+            } => {
+                write!(self.out, "{level}")?;
+                self.write_image_atomic(ctx, image, coordinate, array_index, fun, value)?
+            }
             // A `Call` is written `name(arguments)` where `arguments` is a comma separated expressions list
             Statement::Call {
                 function,
@@ -2356,12 +2543,18 @@ impl<'a, W: Write> Writer<'a, W> {
                     // decimal part even it's zero which is needed for a valid glsl float constant
                     crate::Literal::F64(value) => write!(self.out, "{:?}LF", value)?,
                     crate::Literal::F32(value) => write!(self.out, "{:?}", value)?,
+                    crate::Literal::F16(bits) => {
+                        write!(self.out, "{:?}hf", crate::proc::f16_to_f32(bits))?
+                    }
                     // Unsigned integers need a `u` at the end
                     //
                     // While `core` doesn't necessarily need it, it's allowed and since `es` needs it we
                     // always write it as the extra branch wouldn't have any benefit in readability
                     crate::Literal::U32(value) => write!(self.out, "{}u", value)?,
                     crate::Literal::I32(value) => write!(self.out, "{}", value)?,
+                    crate::Literal::U64(_) | crate::Literal::I64(_) => {
+                        return Err(Error::UnsupportedScalar(literal.scalar_kind(), 8));
+                    }
                     crate::Literal::Bool(value) => write!(self.out, "{}", value)?,
                 }
             }
@@ -2392,6 +2585,23 @@ impl<'a, W: Write> Writer<'a, W> {
                 }
                 write!(self.out, ")")?
             }
+            Expression::Repeat { ty, value } => {
+                self.write_type(ty)?;
+
+                if let TypeInner::Array { base, size, .. } = self.module.types[ty].inner {
+                    self.write_array_size(base, size)?;
+                }
+
+                write!(self.out, "(")?;
+                let components = proc::repeat_components(ty, value, &self.module.types);
+                for (index, component) in components.enumerate() {
+                    if index != 0 {
+                        write!(self.out, ", ")?;
+                    }
+                    write_expression(self, component)?;
+                }
+                write!(self.out, ")")?
+            }
             // `Splat` needs to actually write down a vector, it's not always inferred in GLSL.
             Expression::Splat { size: _, value } => {
                 let resolved = info(expr).inner_with(&self.module.types);
@@ -2400,6 +2610,63 @@ impl<'a, W: Write> Writer<'a, W> {
                 write_expression(self, value)?;
                 write!(self.out, ")")?
             }
+            // The rest are only ever override expressions.
+            Expression::Unary { op, expr } => {
+                let operator = match op {
+                    crate::UnaryOperator::Negate => "-",
+                    crate::UnaryOperator::LogicalNot => "!",
+                    crate::UnaryOperator::BitwiseNot => "~",
+                };
+                write!(self.out, "{operator}(")?;
+                write_expression(self, expr)?;
+                write!(self.out, ")")?
+            }
+            Expression::Binary { op, left, right } => {
+                write!(self.out, "(")?;
+                write_expression(self, left)?;
+                write!(self.out, " {} ", back::binary_operation_str(op))?;
+                write_expression(self, right)?;
+                write!(self.out, ")")?
+            }
+            Expression::As {
+                expr: value,
+                kind,
+                convert: Some(width),
+            } => {
+                let scalar = glsl_scalar(kind, width)?;
+                write!(self.out, "{}(", scalar.full)?;
+                write_expression(self, value)?;
+                write!(self.out, ")")?
+            }
+            Expression::Select {
+                condition,
+                accept,
+                reject,
+            } => {
+                write!(self.out, "(")?;
+                write_expression(self, condition)?;
+                write!(self.out, " ? ")?;
+                write_expression(self, accept)?;
+                write!(self.out, " : ")?;
+                write_expression(self, reject)?;
+                write!(self.out, ")")?
+            }
+            Expression::AccessIndex { base, index } => {
+                write_expression(self, base)?;
+                let base_ty = info(base);
+                match (base_ty.handle(), base_ty.inner_with(&self.module.types)) {
+                    (Some(ty), &TypeInner::Struct { .. }) => {
+                        let name = &self.names[&NameKey::StructMember(ty, index)];
+                        write!(self.out, ".{name}")?
+                    }
+                    _ => write!(self.out, "[{index}]")?,
+                }
+            }
+            Expression::As { convert: None, .. } => {
+                return Err(Error::Custom(
+                    "bitcast in an override expression".to_string(),
+                ));
+            }
             _ => unreachable!(),
         }
 
@@ -2427,6 +2694,7 @@ impl<'a, W: Write> Writer<'a, W> {
             | Expression::Constant(_)
             | Expression::ZeroValue(_)
             | Expression::Compose { .. }
+            | Expression::Repeat { .. }
             | Expression::Splat { .. } => {
                 self.write_possibly_const_expr(
                     expr,
@@ -2532,26 +2800,12 @@ impl<'a, W: Write> Writer<'a, W> {
                     _ => unreachable!(),
                 };
 
-                if dim == crate::ImageDimension::Cube
-                    && array_index.is_some()
-                    && depth_ref.is_some()
-                {
-                    match level {
-                        crate::SampleLevel::Zero
-                        | crate::SampleLevel::Exact(_)
-                        | crate::SampleLevel::Gradient { .. }
-                        | crate::SampleLevel::Bias(_) => {
-                            return Err(Error::Custom(String::from(
-                                "gsamplerCubeArrayShadow isn't supported in textureGrad, \
-                                 textureLod or texture with bias",
-                            )))
-                        }
-                        crate::SampleLevel::Auto => {}
-                    }
-                }
+                // Comparisons that GLSL can't express were already rejected by
+                // `collect_required_features`.
 
                 // textureLod on sampler2DArrayShadow and samplerCubeShadow does not exist in GLSL.
-                // To emulate this, we will have to use textureGrad with a constant gradient of 0.
+                // To emulate this, we will have to use textureGrad with a constant gradient of 0,
+                // which always samples the base level. See `WriterFlags::TEXTURE_SHADOW_LOD`.
                 let workaround_lod_array_shadow_as_grad = (array_index.is_some()
                     || dim == crate::ImageDimension::Cube)
                     && depth_ref.is_some()
@@ -2560,6 +2814,10 @@ impl<'a, W: Write> Writer<'a, W> {
                         .options
                         .writer_flags
                         .contains(WriterFlags::TEXTURE_SHADOW_LOD);
+                let grad_dim = match dim {
+                    crate::ImageDimension::Cube => 3,
+                    _ => 2,
+                };
 
                 //Write the function to be used depending on the sample level
                 let fun_name = match level {
@@ -2635,11 +2893,7 @@ impl<'a, W: Write> Writer<'a, W> {
                     // Zero needs level set to 0
                     crate::SampleLevel::Zero => {
                         if workaround_lod_array_shadow_as_grad {
-                            let vec_dim = match dim {
-                                crate::ImageDimension::Cube => 3,
-                                _ => 2,
-                            };
-                            write!(self.out, ", vec{vec_dim}(0.0), vec{vec_dim}(0.0)")?;
+                            write!(self.out, ", vec{grad_dim}(0.0), vec{grad_dim}(0.0)")?;
                         } else if gather.is_none() {
                             write!(self.out, ", 0.0")?;
                         }
@@ -2648,7 +2902,7 @@ impl<'a, W: Write> Writer<'a, W> {
                     crate::SampleLevel::Exact(expr) => {
                         if workaround_lod_array_shadow_as_grad {
                             log::warn!("Unable to `textureLod` a shadow array, ignoring the LOD");
-                            write!(self.out, ", vec2(0,0), vec2(0,0)")?;
+                            write!(self.out, ", vec{grad_dim}(0.0), vec{grad_dim}(0.0)")?;
                         } else {
                             write!(self.out, ", ")?;
                             self.write_expr(expr, ctx)?;
@@ -3171,28 +3425,32 @@ impl<'a, W: Write> Writer<'a, W> {
                     Mf::Determinant => "determinant",
                     // bits
                     Mf::CountTrailingZeros => {
+                        let bits = ctx
+                            .resolve_type(arg, &self.module.types)
+                            .scalar_width()
+                            .unwrap_or(32);
                         match *ctx.resolve_type(arg, &self.module.types) {
                             crate::TypeInner::Vector { size, kind, .. } => {
                                 let s = back::vector_size_str(size);
                                 if let crate::ScalarKind::Uint = kind {
                                     write!(self.out, "min(uvec{s}(findLSB(")?;
                                     self.write_expr(arg, ctx)?;
-                                    write!(self.out, ")), uvec{s}(32u))")?;
+                                    write!(self.out, ")), uvec{s}({bits}u))")?;
                                 } else {
                                     write!(self.out, "ivec{s}(min(uvec{s}(findLSB(")?;
                                     self.write_expr(arg, ctx)?;
-                                    write!(self.out, ")), uvec{s}(32u)))")?;
+                                    write!(self.out, ")), uvec{s}({bits}u)))")?;
                                 }
                             }
                             crate::TypeInner::Scalar { kind, .. } => {
                                 if let crate::ScalarKind::Uint = kind {
                                     write!(self.out, "min(uint(findLSB(")?;
                                     self.write_expr(arg, ctx)?;
-                                    write!(self.out, ")), 32u)")?;
+                                    write!(self.out, ")), {bits}u)")?;
                                 } else {
                                     write!(self.out, "int(min(uint(findLSB(")?;
                                     self.write_expr(arg, ctx)?;
-                                    write!(self.out, ")), 32u))")?;
+                                    write!(self.out, ")), {bits}u))")?;
                                 }
                             }
                             _ => unreachable!(),
@@ -3200,17 +3458,23 @@ impl<'a, W: Write> Writer<'a, W> {
                         return Ok(());
                     }
                     Mf::CountLeadingZeros => {
+                        // The index of the highest bit of the argument.
+                        let msb = ctx
+                            .resolve_type(arg, &self.module.types)
+                            .scalar_width()
+                            .unwrap_or(32)
+                            - 1;
                         if self.options.version.supports_integer_functions() {
                             match *ctx.resolve_type(arg, &self.module.types) {
                                 crate::TypeInner::Vector { size, kind, .. } => {
                                     let s = back::vector_size_str(size);
 
                                     if let crate::ScalarKind::Uint = kind {
-                                        write!(self.out, "uvec{s}(ivec{s}(31) - findMSB(")?;
+                                        write!(self.out, "uvec{s}(ivec{s}({msb}) - findMSB(")?;
                                         self.write_expr(arg, ctx)?;
                                         write!(self.out, "))")?;
                                     } else {
-                                        write!(self.out, "mix(ivec{s}(31) - findMSB(")?;
+                                        write!(self.out, "mix(ivec{s}({msb}) - findMSB(")?;
                                         self.write_expr(arg, ctx)?;
                                         write!(self.out, "), ivec{s}(0), lessThan(")?;
                                         self.write_expr(arg, ctx)?;
@@ -3219,11 +3483,11 @@ impl<'a, W: Write> Writer<'a, W> {
                                 }
                                 crate::TypeInner::Scalar { kind, .. } => {
                                     if let crate::ScalarKind::Uint = kind {
-                                        write!(self.out, "uint(31 - findMSB(")?;
+                                        write!(self.out, "uint({msb} - findMSB(")?;
                                     } else {
                                         write!(self.out, "(")?;
                                         self.write_expr(arg, ctx)?;
-                                        write!(self.out, " < 0 ? 0 : 31 - findMSB(")?;
+                                        write!(self.out, " < 0 ? 0 : {msb} - findMSB(")?;
                                     }
 
                                     self.write_expr(arg, ctx)?;
@@ -3238,12 +3502,15 @@ impl<'a, W: Write> Writer<'a, W> {
 
                                     if let crate::ScalarKind::Uint = kind {
                                         write!(self.out, "uvec{s}(")?;
-                                        write!(self.out, "vec{s}(31.0) - floor(log2(vec{s}(")?;
+                                        write!(self.out, "vec{s}({msb}.0) - floor(log2(vec{s}(")?;
                                         self.write_expr(arg, ctx)?;
                                         write!(self.out, ") + 0.5)))")?;
                                     } else {
                                         write!(self.out, "ivec{s}(")?;
-                                        write!(self.out, "mix(vec{s}(31.0) - floor(log2(vec{s}(")?;
+                                        write!(
+                                            self.out,
+                                            "mix(vec{s}({msb}.0) - floor(log2(vec{s}("
+                                        )?;
                                         self.write_expr(arg, ctx)?;
                                         write!(self.out, ") + 0.5)), ")?;
                                         write!(self.out, "vec{s}(0.0), lessThan(")?;
@@ -3253,14 +3520,14 @@ impl<'a, W: Write> Writer<'a, W> {
                                 }
                                 crate::TypeInner::Scalar { kind, .. } => {
                                     if let crate::ScalarKind::Uint = kind {
-                                        write!(self.out, "uint(31.0 - floor(log2(float(")?;
+                                        write!(self.out, "uint({msb}.0 - floor(log2(float(")?;
                                         self.write_expr(arg, ctx)?;
                                         write!(self.out, ") + 0.5)))")?;
                                     } else {
                                         write!(self.out, "(")?;
                                         self.write_expr(arg, ctx)?;
                                         write!(self.out, " < 0 ? 0 : int(")?;
-                                        write!(self.out, "31.0 - floor(log2(float(")?;
+                                        write!(self.out, "{msb}.0 - floor(log2(float(")?;
                                         self.write_expr(arg, ctx)?;
                                         write!(self.out, ") + 0.5))))")?;
                                     }
@@ -3347,33 +3614,31 @@ impl<'a, W: Write> Writer<'a, W> {
                     write!(self.out, ")")?
                 }
 
-                if let Some(arg) = arg1 {
-                    write!(self.out, ", ")?;
-                    if extract_bits {
-                        write!(self.out, "int(")?;
-                        self.write_expr(arg, ctx)?;
-                        write!(self.out, ")")?;
-                    } else {
-                        self.write_expr(arg, ctx)?;
-                    }
-                }
-                if let Some(arg) = arg2 {
-                    write!(self.out, ", ")?;
-                    if extract_bits || insert_bits {
-                        write!(self.out, "int(")?;
-                        self.write_expr(arg, ctx)?;
-                        write!(self.out, ")")?;
-                    } else {
-                        self.write_expr(arg, ctx)?;
-                    }
-                }
-                if let Some(arg) = arg3 {
-                    write!(self.out, ", ")?;
-                    if insert_bits {
-                        write!(self.out, "int(")?;
-                        self.write_expr(arg, ctx)?;
-                        write!(self.out, ")")?;
+                if extract_bits || insert_bits {
+                    // WGSL clamps the offset to the width of the value, and the
+                    // count to the bits above the offset, where GLSL leaves
+                    // out-of-range operands undefined.
+                    let (offset, count) = if extract_bits {
+                        (arg1.unwrap(), arg2.unwrap())
                     } else {
+                        write!(self.out, ", ")?;
+                        self.write_expr(arg1.unwrap(), ctx)?;
+                        (arg2.unwrap(), arg3.unwrap())
+                    };
+                    let bits = ctx
+                        .resolve_type(arg, &self.module.types)
+                        .scalar_width()
+                        .unwrap_or(32);
+                    write!(self.out, ", int(min(")?;
+                    self.write_expr(offset, ctx)?;
+                    write!(self.out, ", {bits}u)), int(min(")?;
+                    self.write_expr(count, ctx)?;
+                    write!(self.out, ", {bits}u - min(")?;
+                    self.write_expr(offset, ctx)?;
+                    write!(self.out, ", {bits}u))))")?;
+                } else {
+                    for arg in [arg1, arg2, arg3].into_iter().flatten() {
+                        write!(self.out, ", ")?;
                         self.write_expr(arg, ctx)?;
                     }
                 }
@@ -3476,6 +3741,47 @@ impl<'a, W: Write> Writer<'a, W> {
             }
             // not supported yet
             Expression::RayQueryGetIntersection { .. } => unreachable!(),
+            // OpenGL has no non-uniform qualifier.
+            Expression::NonUniform(value) => self.write_expr(value, ctx)?,
+            // Subgroup operations are function calls from `GL_KHR_shader_subgroup`
+            Expression::SubgroupOperation { op, argument } => {
+                use crate::{SubgroupOperation as So, SubgroupReduction as Sr};
+                let reduction_str = |reduction| match reduction {
+                    Sr::Add => "Add",
+                    Sr::Mul => "Mul",
+                    Sr::Min => "Min",
+                    Sr::Max => "Max",
+                    Sr::And => "And",
+                    Sr::Or => "Or",
+                    Sr::Xor => "Xor",
+                };
+                match op {
+                    So::Ballot => write!(self.out, "subgroupBallot(")?,
+                    So::All => write!(self.out, "subgroupAll(")?,
+                    So::Any => write!(self.out, "subgroupAny(")?,
+                    So::Reduce(reduction) => {
+                        write!(self.out, "subgroup{}(", reduction_str(reduction))?
+                    }
+                    So::InclusiveScan(reduction) => {
+                        write!(self.out, "subgroupInclusive{}(", reduction_str(reduction))?
+                    }
+                    So::ExclusiveScan(reduction) => {
+                        write!(self.out, "subgroupExclusive{}(", reduction_str(reduction))?
+                    }
+                    So::BroadcastFirst => write!(self.out, "subgroupBroadcastFirst(")?,
+                    So::Broadcast(_) => write!(self.out, "subgroupBroadcast(")?,
+                    So::Shuffle(_) => write!(self.out, "subgroupShuffle(")?,
+                    So::ShuffleDown(_) => write!(self.out, "subgroupShuffleDown(")?,
+                    So::ShuffleUp(_) => write!(self.out, "subgroupShuffleUp(")?,
+                    So::ShuffleXor(_) => write!(self.out, "subgroupShuffleXor(")?,
+                }
+                self.write_expr(argument, ctx)?;
+                if let Some(index) = op.index() {
+                    write!(self.out, ", ")?;
+                    self.write_expr(index, ctx)?;
+                }
+                write!(self.out, ")")?
+            }
         }
 
         Ok(())
@@ -3630,7 +3936,9 @@ impl<'a, W: Write> Writer<'a, W> {
         )?;
 
         // Separate the coordinate from the value to write and write the expression
-        // of the value to write.
+        // of the value to write. `imageStore` always takes a `gvec4`, which
+        // the image's format layout qualifier says how to pack, so the value
+        // needs no conversion.
         write!(self.out, ", ")?;
         self.write_expr(value, ctx)?;
         // End the call to `imageStore` and the statement.
@@ -3639,6 +3947,57 @@ impl<'a, W: Write> Writer<'a, W> {
         Ok(())
     }
 
+    /// Helper method to write the `ImageAtomic` statement
+    fn write_image_atomic(
+        &mut self,
+        ctx: &back::FunctionCtx,
+        image: Handle<crate::Expression>,
+        coordinate: Handle<crate::Expression>,
+        array_index: Option<Handle<crate::Expression>>,
+        fun: crate::AtomicFunction,
+        value: Handle<crate::Expression>,
+    ) -> Result<(), Error> {
+        use crate::ImageDimension as IDim;
+
+        // Like `imageStore`, the `imageAtomic` functions have no effect on
+        // invalid texels, so we don't need to generate bounds checks.
+
+        // This will only panic if the module is invalid
+        let dim = match *ctx.resolve_type(image, &self.module.types) {
+            TypeInner::Image { dim, .. } => dim,
+            _ => unreachable!(),
+        };
+
+        if let crate::AtomicFunction::Exchange { compare: Some(_) } = fun {
+            return Err(Error::Custom(
+                "image atomic CompareExchange is not implemented".to_string(),
+            ));
+        }
+
+        write!(self.out, "imageAtomic{}(", fun.to_glsl())?;
+        self.write_expr(image, ctx)?;
+        write!(self.out, ", ")?;
+
+        let tex_1d_hack = dim == IDim::D1 && self.options.version.is_es();
+        self.write_texture_coord(
+            ctx,
+            self.get_coordinate_vector_size(dim, array_index.is_some()),
+            coordinate,
+            array_index,
+            tex_1d_hack,
+        )?;
+
+        write!(self.out, ", ")?;
+        if let crate::AtomicFunction::Subtract = fun {
+            // we just wrote `imageAtomicAdd`, so negate the argument
+            write!(self.out, "-")?;
+        }
+        self.write_expr(value, ctx)?;
+        writeln!(self.out, ");")?;
+
+        Ok(())
+    }
+
     /// Helper method for writing an `ImageLoad` expression.
     #[allow(clippy::too_many_arguments)]
     fn write_image_load(
@@ -3725,23 +4084,28 @@ impl<'a, W: Write> Writer<'a, W> {
             // expressions emitted before or after it having more precedence
             write!(self.out, "(",)?;
 
+            // All of the comparisons are unsigned, so that negative values
+            // fail them too.
+
             // The lod check needs to precede the size check since we need
             // to use the lod to get the size of the image at that level.
             if let Some(level_expr) = level {
+                write!(self.out, "uint(")?;
                 self.write_expr(level_expr, ctx)?;
-                write!(self.out, " < textureQueryLevels(",)?;
+                write!(self.out, ") < uint(textureQueryLevels(",)?;
                 self.write_expr(image, ctx)?;
                 // Chain the next check
-                write!(self.out, ") && ")?;
+                write!(self.out, ")) && ")?;
             }
 
             // Check that the sample arguments doesn't exceed the number of samples
             if let Some(sample_expr) = sample {
+                write!(self.out, "uint(")?;
                 self.write_expr(sample_expr, ctx)?;
-                write!(self.out, " < textureSamples(",)?;
+                write!(self.out, ") < uint(textureSamples(",)?;
                 self.write_expr(image, ctx)?;
                 // Chain the next check
-                write!(self.out, ") && ")?;
+                write!(self.out, ")) && ")?;
             }
 
             // We now need to write the size checks for the coordinates and array index
@@ -3754,18 +4118,24 @@ impl<'a, W: Write> Writer<'a, W> {
             // return `true` if all the elements of the boolean vector are also `true`.
             //
             // So we'll end with one of the following forms
-            // - `coord < textureSize(image, lod)` for 1D images
-            // - `all(lessThan(coord, textureSize(image, lod)))` for normal images
-            // - `all(lessThan(ivec(coord, array_index), textureSize(image, lod)))`
+            // - `uint(coord) < uint(textureSize(image, lod))` for 1D images
+            // - `all(lessThan(uvec(coord), uvec(textureSize(image, lod))))` for normal images
+            // - `all(lessThan(uvec(ivec(coord, array_index)), uvec(textureSize(image, lod))))`
             //    for arrayed images
-            // - `all(lessThan(coord, textureSize(image)))` for multi sampled images
+            // - `all(lessThan(uvec(coord), uvec(textureSize(image))))` for multi sampled images
 
+            let uint_cast = match vector_size {
+                1 => "uint".to_string(),
+                size => format!("uvec{size}"),
+            };
             if vector_size != 1 {
                 write!(self.out, "all(lessThan(")?;
             }
 
             // Write the coordinate vector
+            write!(self.out, "{uint_cast}(")?;
             self.write_texture_coord(ctx, vector_size, coordinate, array_index, tex_1d_hack)?;
+            write!(self.out, ")")?;
 
             if vector_size != 1 {
                 // If we used the `lessThan` function we need to separate the
@@ -3778,7 +4148,11 @@ impl<'a, W: Write> Writer<'a, W> {
             }
 
             // Call `textureSize` to get our image size
-            write!(self.out, "textureSize(")?;
+            let size_fun = match class {
+                crate::ImageClass::Storage { .. } => "imageSize",
+                _ => "textureSize",
+            };
+            write!(self.out, "{uint_cast}({size_fun}(")?;
             self.write_expr(image, ctx)?;
             // `textureSize` uses the lod as a second argument for mipmapped images
             if let Some(level_expr) = level {
@@ -3786,8 +4160,8 @@ impl<'a, W: Write> Writer<'a, W> {
                 write!(self.out, ", ")?;
                 self.write_expr(level_expr, ctx)?;
             }
-            // Close the `textureSize` call
-            write!(self.out, ")")?;
+            // Close the `textureSize` call and the cast
+            write!(self.out, "))")?;
 
             if vector_size != 1 {
                 // Close the `all` and `lessThan` calls
@@ -3872,7 +4246,7 @@ impl<'a, W: Write> Writer<'a, W> {
                 self.write_expr(image, ctx)?;
                 // Close the `textureSamples` call, subtract 1 from it since the sample
                 // argument is zero based, and close the `clamp` call
-                writeln!(self.out, ") - 1)")?;
+                write!(self.out, ") - 1)")?;
             }
         } else if let Some(sample_or_level) = sample.or(level) {
             // If no bounds checking is need just add the sample or level argument
@@ -3890,10 +4264,11 @@ impl<'a, W: Write> Writer<'a, W> {
         if let proc::BoundsCheckPolicy::ReadZeroSkipWrite = policy {
             // Get the kind of the output value.
             let kind = match class {
-                // Only sampled images can reach here since storage images
-                // don't need bounds checks and depth images aren't implmented
                 crate::ImageClass::Sampled { kind, .. } => kind,
-                _ => unreachable!(),
+                // Storage images can reach here when targeting ES.
+                crate::ImageClass::Storage { format, .. } => format.into(),
+                // Depth images were rejected above.
+                crate::ImageClass::Depth { .. } => unreachable!(),
             };
 
             // End the first branch
@@ -4033,7 +4408,12 @@ impl<'a, W: Write> Writer<'a, W> {
     /// glsl allows adding both `readonly` and `writeonly` but this means that
     /// they can only be used to query information about the resource which isn't what
     /// we want here so when storage access is both `LOAD` and `STORE` add no modifiers
+    ///
+    /// [`COHERENT`](crate::StorageAccess::COHERENT) access adds `coherent`.
     fn write_storage_access(&mut self, storage_access: crate::StorageAccess) -> BackendResult {
+        if storage_access.contains(crate::StorageAccess::COHERENT) {
+            write!(self.out, "coherent ")?;
+        }
         if !storage_access.contains(crate::StorageAccess::STORE) {
             write!(self.out, "readonly ")?;
         }
@@ -4043,12 +4423,67 @@ impl<'a, W: Write> Writer<'a, W> {
         Ok(())
     }
 
+    /// Whether resources get `binding =` qualifiers.
+    fn explicit_bindings(&self) -> bool {
+        self.options.version.supports_explicit_locations()
+            && !self
+                .options
+                .writer_flags
+                .contains(WriterFlags::DESKTOP_COMPATIBILITY)
+    }
+
+    /// Whether a storage buffer of type `ty` with `access` is written as a
+    /// uniform block, per [`WriterFlags::DESKTOP_COMPATIBILITY`].
+    fn storage_as_uniform(&self, ty: Handle<crate::Type>, access: crate::StorageAccess) -> bool {
+        self.options
+            .writer_flags
+            .contains(WriterFlags::DESKTOP_COMPATIBILITY)
+            && !access.contains(crate::StorageAccess::STORE)
+            // Uniform blocks can't hold runtime-sized arrays.
+            && !self.module.types[ty]
+                .inner
+                .is_dynamically_sized(&self.module.types)
+            && layout::check(self.module, ty, BlockLayout::Std140, &mut String::new()).is_ok()
+    }
+
+    /// The layout storage blocks are declared with.
+    fn storage_block_layout(&self) -> BlockLayout {
+        match self.options.storage_block_layout {
+            Some(layout) => layout,
+            None if self.options.version.supports_std430_layout() => BlockLayout::Std430,
+            None => BlockLayout::Std140,
+        }
+    }
+
+    /// Check that `layout` places everything in `global` where the module
+    /// does, per [`WriterFlags::CHECK_BLOCK_LAYOUTS`].
+    fn check_block_layout(
+        &self,
+        global: &crate::GlobalVariable,
+        layout: BlockLayout,
+    ) -> BackendResult {
+        let mut path = global.name.clone().unwrap_or_default();
+        match layout::check(self.module, global.ty, layout, &mut path) {
+            Err(error)
+                if !self
+                    .options
+                    .writer_flags
+                    .contains(WriterFlags::CHECK_BLOCK_LAYOUTS) =>
+            {
+                log::warn!("{error}");
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
     /// Helper method used to produce the reflection info that's returned to the user
     fn collect_reflection_info(&mut self) -> Result<ReflectionInfo, Error> {
         use std::collections::hash_map::Entry;
         let info = self.info.get_entry_point(self.entry_point_idx as usize);
         let mut texture_mapping = crate::FastHashMap::default();
         let mut uniforms = crate::FastHashMap::default();
+        let mut push_constant_items = Vec::new();
 
         for sampling in info.sampling_set.iter() {
             let tex_name = self.reflection_names_globals[&sampling.image].clone();
@@ -4093,6 +4528,20 @@ impl<'a, W: Write> Writer<'a, W> {
                         let name = self.reflection_names_globals[&handle].clone();
                         uniforms.insert(handle, name);
                     }
+                    crate::AddressSpace::PushConstant => {
+                        let name = self.reflection_names_globals[&handle].clone();
+                        if self.options.push_constant_binding.is_some() {
+                            uniforms.insert(handle, name);
+                        } else {
+                            let mut access_path = name;
+                            self.collect_push_constant_items(
+                                var.ty,
+                                &mut access_path,
+                                0,
+                                &mut push_constant_items,
+                            );
+                        }
+                    }
                     _ => (),
                 },
             }
@@ -4102,8 +4551,59 @@ impl<'a, W: Write> Writer<'a, W> {
             texture_mapping,
             uniforms,
             varying: mem::take(&mut self.varying),
+            push_constant_items,
+            block_layouts: mem::take(&mut self.block_layouts),
         })
     }
+
+    /// Add the uniforms a push constant value of type `ty` at `offset` is
+    /// made of to `items`, extending `access_path` to name them.
+    fn collect_push_constant_items(
+        &self,
+        ty: Handle<crate::Type>,
+        access_path: &mut String,
+        offset: u32,
+        items: &mut Vec<PushConstantItem>,
+    ) {
+        let len = access_path.len();
+        match self.module.types[ty].inner {
+            TypeInner::Struct { ref members, .. } => {
+                for (index, member) in members.iter().enumerate() {
+                    let name = &self.names[&NameKey::StructMember(ty, index as u32)];
+                    access_path.push('.');
+                    access_path.push_str(name);
+                    self.collect_push_constant_items(
+                        member.ty,
+                        access_path,
+                        offset + member.offset,
+                        items,
+                    );
+                    access_path.truncate(len);
+                }
+            }
+            TypeInner::Array {
+                base,
+                size: crate::ArraySize::Constant(size),
+                stride,
+            } => {
+                for index in 0..size.get() {
+                    access_path.push_str(&format!("[{index}]"));
+                    self.collect_push_constant_items(
+                        base,
+                        access_path,
+                        offset + index * stride,
+                        items,
+                    );
+                    access_path.truncate(len);
+                }
+            }
+            _ => items.push(PushConstantItem {
+                access_path: access_path.clone(),
+                ty,
+                offset,
+            }),
+        }
+    }
 }
 
 /// Structure returned by [`glsl_scalar`]
@@ -4129,6 +4629,8 @@ const fn glsl_scalar(
     use crate::ScalarKind as Sk;
 
     Ok(match kind {
+        // 64-bit integers need extensions that we don't support.
+        Sk::Sint | Sk::Uint if width != 4 => return Err(Error::UnsupportedScalar(kind, width)),
         Sk::Sint => ScalarString {
             prefix: "i",
             full: "int",
@@ -4138,6 +4640,10 @@ const fn glsl_scalar(
             full: "uint",
         },
         Sk::Float => match width {
+            2 => ScalarString {
+                prefix: "f16",
+                full: "float16_t",
+            },
             4 => ScalarString {
                 prefix: "",
                 full: "float",
@@ -4201,6 +4707,9 @@ const fn glsl_built_in(
         Bi::WorkGroupId => "gl_WorkGroupID",
         Bi::WorkGroupSize => "gl_WorkGroupSize",
         Bi::NumWorkGroups => "gl_NumWorkGroups",
+        // subgroup
+        Bi::SubgroupSize => "gl_SubgroupSize",
+        Bi::SubgroupInvocationId => "gl_SubgroupInvocationID",
     }
 }
 
@@ -0,0 +1,313 @@
+use super::{Options, PipelineOptions, Version, WriterFlags};
+use crate::test_util::Fixture;
+
+fn pipeline_options(shader_stage: crate::ShaderStage) -> PipelineOptions {
+    PipelineOptions {
+        shader_stage,
+        entry_point: "main".to_string(),
+        multiview: None,
+    }
+}
+
+#[test]
+fn f16() {
+    let fixture = Fixture::wgsl_with_capabilities(
+        "
+        enable f16;
+
+        var<private> v: vec3<f16>;
+
+        @compute @workgroup_size(1)
+        fn main() {
+            let x = 1.5h;
+            v = vec3h(x) * f16(2.0);
+        }
+        ",
+        crate::valid::Capabilities::FLOAT16,
+    );
+    fixture
+        .to_glsl(
+            &Default::default(),
+            &pipeline_options(crate::ShaderStage::Compute),
+        )
+        .assert_contains("#extension GL_EXT_shader_explicit_arithmetic_types_float16 : require")
+        .assert_contains("f16vec3 v")
+        .assert_contains("1.5hf");
+}
+
+#[test]
+fn subgroup_operations() {
+    let fixture = Fixture::wgsl_with_capabilities(
+        "
+        enable subgroups;
+
+        @group(0) @binding(0) var<storage, read_write> out: array<u32, 2>;
+
+        @compute @workgroup_size(64)
+        fn main(
+            @builtin(local_invocation_index) index: u32,
+            @builtin(subgroup_size) size: u32,
+            @builtin(subgroup_invocation_id) lane: u32,
+        ) {
+            out[0] = subgroupInclusiveAdd(index) + subgroupBroadcastFirst(index);
+            out[1] = size + lane;
+        }
+        ",
+        crate::valid::Capabilities::SUBGROUP,
+    );
+    fixture
+        .to_glsl(
+            &Options {
+                version: Version::Desktop(430),
+                ..Default::default()
+            },
+            &pipeline_options(crate::ShaderStage::Compute),
+        )
+        .assert_contains("#extension GL_KHR_shader_subgroup_arithmetic : require")
+        .assert_contains("gl_SubgroupSize")
+        .assert_contains("gl_SubgroupInvocationID")
+        .assert_contains("subgroupInclusiveAdd(")
+        .assert_contains("subgroupBroadcastFirst(");
+}
+
+#[test]
+fn image_atomics() {
+    let fixture = Fixture::wgsl_with_capabilities(
+        "
+        @group(0) @binding(0) var image: texture_storage_2d<r32uint, atomic>;
+        @group(0) @binding(1) var layers: texture_storage_2d_array<r32sint, atomic>;
+
+        @compute @workgroup_size(1)
+        fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+            textureAtomicMax(image, id.xy, 1u);
+            textureAtomicSub(layers, id.xy, 2, 3);
+        }
+        ",
+        crate::valid::Capabilities::TEXTURE_ATOMIC,
+    );
+    fixture
+        .to_glsl(
+            &Options {
+                version: Version::new_gles(310),
+                ..Default::default()
+            },
+            &pipeline_options(crate::ShaderStage::Compute),
+        )
+        .assert_contains("#extension GL_OES_shader_image_atomic : require")
+        .assert_contains("imageAtomicMax(_group_0_binding_0_cs, ")
+        .assert_contains("imageAtomicAdd(_group_0_binding_1_cs, ");
+}
+
+#[test]
+fn push_constants() {
+    let fixture = Fixture::wgsl_with_capabilities(
+        "
+        struct Light {
+            position: vec3<f32>,
+            intensity: f32,
+        }
+
+        struct PushConstants {
+            color: vec4<f32>,
+            lights: array<Light, 2>,
+        }
+
+        var<push_constant> pc: PushConstants;
+
+        @fragment
+        fn main() -> @location(0) vec4<f32> {
+            return pc.color * pc.lights[1].intensity;
+        }
+        ",
+        crate::valid::Capabilities::PUSH_CONSTANT,
+    );
+    let write = |options: &Options| {
+        let mut text = String::new();
+        let reflection = super::Writer::new(
+            &mut text,
+            &fixture.module,
+            &fixture.info,
+            options,
+            &pipeline_options(crate::ShaderStage::Fragment),
+            crate::proc::BoundsCheckPolicies::default(),
+        )
+        .and_then(|mut writer| writer.write())
+        .unwrap();
+        (text, reflection)
+    };
+
+    let (text, reflection) = write(&Default::default());
+    assert!(text.contains("uniform PushConstants pc;"));
+    let items: Vec<_> = reflection
+        .push_constant_items
+        .iter()
+        .map(|item| (item.access_path.as_str(), item.offset))
+        .collect();
+    assert_eq!(
+        items,
+        [
+            ("pc.color", 0),
+            ("pc.lights[0].position", 16),
+            ("pc.lights[0].intensity", 28),
+            ("pc.lights[1].position", 32),
+            ("pc.lights[1].intensity", 44),
+        ]
+    );
+
+    let (text, reflection) = write(&Options {
+        push_constant_binding: Some(3),
+        ..Default::default()
+    });
+    assert!(text.contains("layout(std140, binding = 3) uniform PushConstants_block_"));
+    assert!(reflection.push_constant_items.is_empty());
+    assert_eq!(reflection.uniforms.len(), 1);
+}
+
+#[test]
+fn desktop_compatibility() {
+    let fixture = Fixture::wgsl(
+        "
+        struct Light { color: vec4<f32>, direction: vec3<f32> }
+        struct Lights { lights: array<Light, 4> }
+        @group(0) @binding(0) var<storage> lights: Lights;
+        @group(0) @binding(1) var<storage> weights: array<f32, 4>;
+        @group(0) @binding(2) var tex: texture_2d<f32>;
+        @group(0) @binding(3) var samp: sampler;
+
+        @fragment
+        fn main() -> @location(0) vec4<f32> {
+            return textureSample(tex, samp, vec2(0.5)) * lights.lights[1].color * weights[2];
+        }
+        ",
+    );
+    let binding_map = (0..4)
+        .map(|binding| (crate::ResourceBinding { group: 0, binding }, binding as u8))
+        .collect();
+    let pipeline_options = pipeline_options(crate::ShaderStage::Fragment);
+
+    fixture
+        .to_glsl(
+            &Options {
+                version: Version::Desktop(410),
+                binding_map: binding_map.clone(),
+                ..Default::default()
+            },
+            &pipeline_options,
+        )
+        .assert_contains("layout(binding = 2) uniform highp sampler2D")
+        .assert_contains("layout(std140, binding = 0) readonly buffer Lights_block_0Fragment");
+    fixture
+        .to_glsl(
+            &Options {
+                version: Version::Desktop(410),
+                writer_flags: WriterFlags::DESKTOP_COMPATIBILITY,
+                binding_map,
+                ..Default::default()
+            },
+            &pipeline_options,
+        )
+        .assert_not_contains("binding =")
+        .assert_contains("uniform Lights_block_0Fragment")
+        // `array<f32, 4>` has a stride of 4, which uniform blocks can't have.
+        .assert_contains("readonly buffer type_")
+        .assert_contains("uniform highp sampler2D");
+}
+
+#[test]
+fn repeated_array_elements() {
+    let fixture = Fixture::wgsl(
+        "
+        const ones = array<i32, 4>(1, 1, 1, 1);
+
+        @compute @workgroup_size(1)
+        fn main() {
+            var a = ones;
+        }
+        ",
+    );
+    fixture
+        .to_glsl(
+            &Options {
+                version: Version::Desktop(430),
+                ..Default::default()
+            },
+            &pipeline_options(crate::ShaderStage::Compute),
+        )
+        .assert_contains("int[4](1, 1, 1, 1)");
+}
+
+#[test]
+fn coherent_storage() {
+    let fixture = Fixture::wgsl(
+        "
+        @coherent @group(0) @binding(0) var<storage, read_write> data: array<u32>;
+        @group(0) @binding(1) var<storage, read_write> plain: array<u32>;
+        @coherent @group(0) @binding(2) var image: texture_storage_2d<r32uint, write>;
+        @group(0) @binding(3) var plain_image: texture_storage_2d<r32uint, write>;
+
+        @compute @workgroup_size(64)
+        fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+            data[id.x] = plain[id.x];
+            plain[id.x] = data[id.x + 1u];
+            textureStore(image, vec2<i32>(id.xy), vec4<u32>(data[id.x]));
+            textureStore(plain_image, vec2<i32>(id.xy), vec4<u32>(plain[id.x]));
+        }
+        ",
+    );
+    fixture
+        .to_glsl(
+            &Default::default(),
+            &pipeline_options(crate::ShaderStage::Compute),
+        )
+        .assert_contains("layout(std430) coherent buffer")
+        .assert_contains("layout(std430) buffer")
+        .assert_contains("layout(r32ui) coherent writeonly uniform")
+        .assert_contains("layout(r32ui) writeonly uniform");
+}
+
+#[test]
+fn image_load_read_zero_skip_write() {
+    use crate::proc::{BoundsCheckPolicies, BoundsCheckPolicy};
+
+    // Texel loads that need every kind of check: level of detail, array
+    // layer, sample index, and coordinates.
+    let fixture = Fixture::wgsl(
+        "
+        @group(0) @binding(0) var image_1d: texture_1d<f32>;
+        @group(0) @binding(1) var image_2d_array: texture_2d_array<f32>;
+        @group(0) @binding(2) var image_multisampled: texture_multisampled_2d<f32>;
+        @group(0) @binding(3) var image_3d: texture_3d<f32>;
+
+        @fragment
+        fn main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+            let coords = vec2<i32>(position.xy);
+            let level = i32(position.z);
+            return textureLoad(image_1d, coords.x, level)
+                + textureLoad(image_2d_array, coords, 1, level)
+                + textureLoad(image_multisampled, coords, 2)
+                + textureLoad(image_3d, vec3(coords, 0), level);
+        }
+        ",
+    );
+    let options = Options {
+        version: Version::Desktop(430),
+        ..Default::default()
+    };
+    let policies = BoundsCheckPolicies {
+        image_load: BoundsCheckPolicy::ReadZeroSkipWrite,
+        ..Default::default()
+    };
+    // Every comparison is unsigned, so negative values fail them.
+    fixture
+        .to_glsl_with_policies(
+            &options,
+            &pipeline_options(crate::ShaderStage::Fragment),
+            policies,
+        )
+        .assert_matches(
+            r"uint\(\w+\) < uint\(textureQueryLevels\(\w+\)\) && uint\(\S+\) < uint\(textureSize\(",
+        )
+        .assert_matches(r"all\(lessThan\(uvec3\(ivec3\(\w+, 1\)\), uvec3\(textureSize\(")
+        .assert_matches(r"uint\(2\) < uint\(textureSamples\(\w+\)\) && all\(lessThan\(uvec2\(")
+        .assert_not_contains(" < textureSize(");
+}
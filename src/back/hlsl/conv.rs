@@ -19,8 +19,16 @@ impl crate::ScalarKind {
     /// <https://docs.microsoft.com/en-us/windows/win32/direct3dhlsl/dx-graphics-hlsl-scalar>
     pub(super) const fn to_hlsl_str(self, width: crate::Bytes) -> Result<&'static str, Error> {
         match self {
-            Self::Sint => Ok("int"),
-            Self::Uint => Ok("uint"),
+            Self::Sint => match width {
+                4 => Ok("int"),
+                8 => Ok("int64_t"),
+                _ => Err(Error::UnsupportedScalar(self, width)),
+            },
+            Self::Uint => match width {
+                4 => Ok("uint"),
+                8 => Ok("uint64_t"),
+                _ => Err(Error::UnsupportedScalar(self, width)),
+            },
             Self::Float => match width {
                 2 => Ok("half"),
                 4 => Ok("float"),
@@ -30,6 +38,24 @@ impl crate::ScalarKind {
             Self::Bool => Ok("bool"),
         }
     }
+
+    /// Like [`to_hlsl_str`], but for writing types in `shader_model`.
+    ///
+    /// From shader model 6.2, DXC's `-enable-16bit-types` makes `half` a
+    /// true 16-bit float, which it spells `float16_t`. Before that, `half`
+    /// may be computed as a 32-bit float.
+    ///
+    /// [`to_hlsl_str`]: Self::to_hlsl_str
+    pub(super) fn to_hlsl_str_for(
+        self,
+        width: crate::Bytes,
+        shader_model: super::ShaderModel,
+    ) -> Result<&'static str, Error> {
+        match (self, width) {
+            (Self::Float, 2) if shader_model >= super::ShaderModel::V6_2 => Ok("float16_t"),
+            _ => self.to_hlsl_str(width),
+        }
+    }
 }
 
 impl crate::TypeInner {
@@ -140,6 +166,68 @@ impl crate::StorageFormat {
             | Self::Rgba32Sint => "int4",
         }
     }
+
+    /// Returns the swizzle that narrows a four-component texel value to the
+    /// element type given by [`to_hlsl_str`], or `""` if it has four components.
+    ///
+    /// [`to_hlsl_str`]: Self::to_hlsl_str
+    pub(super) const fn to_hlsl_store_swizzle(self) -> &'static str {
+        match self {
+            Self::R16Float
+            | Self::R8Unorm
+            | Self::R16Unorm
+            | Self::R8Snorm
+            | Self::R16Snorm
+            | Self::R8Uint
+            | Self::R16Uint
+            | Self::R8Sint
+            | Self::R16Sint => ".x",
+
+            Self::Rg16Float
+            | Self::Rg8Unorm
+            | Self::Rg16Unorm
+            | Self::Rg8Snorm
+            | Self::Rg16Snorm
+            | Self::Rg8Sint
+            | Self::Rg16Sint
+            | Self::Rg8Uint
+            | Self::Rg16Uint => ".xy",
+
+            Self::Rg11b10Float => ".xyz",
+
+            _ => "",
+        }
+    }
+
+    /// Returns the components that widen a texel loaded as the element type
+    /// given by [`to_hlsl_str`] to four components, as the IR expects, or
+    /// `""` if it has four already.
+    ///
+    /// Like WGSL, this fills in zero for missing colors and one for a missing
+    /// alpha, in the texel's own scalar type.
+    ///
+    /// [`to_hlsl_str`]: Self::to_hlsl_str
+    pub(super) const fn to_hlsl_load_padding(self) -> &'static str {
+        match self {
+            Self::R16Float | Self::R8Unorm | Self::R16Unorm | Self::R8Snorm | Self::R16Snorm => {
+                ", 0.0, 0.0, 1.0"
+            }
+            Self::R8Uint | Self::R16Uint => ", 0u, 0u, 1u",
+            Self::R8Sint | Self::R16Sint => ", 0, 0, 1",
+
+            Self::Rg16Float
+            | Self::Rg8Unorm
+            | Self::Rg16Unorm
+            | Self::Rg8Snorm
+            | Self::Rg16Snorm => ", 0.0, 1.0",
+            Self::Rg8Uint | Self::Rg16Uint => ", 0u, 1u",
+            Self::Rg8Sint | Self::Rg16Sint => ", 0, 1",
+
+            Self::Rg11b10Float => ", 1.0",
+
+            _ => "",
+        }
+    }
 }
 
 impl crate::BuiltIn {
@@ -151,6 +239,8 @@ impl crate::BuiltIn {
             Self::CullDistance => "SV_CullDistance",
             Self::InstanceIndex => "SV_InstanceID",
             Self::VertexIndex => "SV_VertexID",
+            // This needs shader model 6.1, which `Writer::write_semantic` checks.
+            Self::ViewIndex => "SV_ViewID",
             // fragment
             Self::FragDepth => "SV_Depth",
             Self::FrontFacing => "SV_IsFrontFace",
@@ -169,11 +259,28 @@ impl crate::BuiltIn {
             Self::BaseInstance | Self::BaseVertex | Self::WorkGroupSize => {
                 return Err(Error::Unimplemented(format!("builtin {self:?}")))
             }
-            Self::PointSize | Self::ViewIndex | Self::PointCoord => {
+            Self::PointSize | Self::PointCoord => {
                 return Err(Error::Custom(format!("Unsupported builtin {self:?}")))
             }
+            // Entry point arguments read these from intrinsics instead, but
+            // struct members have no way to.
+            Self::SubgroupSize | Self::SubgroupInvocationId => {
+                return Err(Error::Unimplemented(format!(
+                    "builtin {self:?} in a struct"
+                )))
+            }
         })
     }
+
+    /// Return the intrinsic that returns this built-in, for those HLSL has
+    /// no semantic for.
+    pub(super) const fn to_hlsl_intrinsic(self) -> Option<&'static str> {
+        match self {
+            Self::SubgroupSize => Some("WaveGetLaneCount()"),
+            Self::SubgroupInvocationId => Some("WaveGetLaneIndex()"),
+            _ => None,
+        }
+    }
 }
 
 impl crate::Interpolation {
@@ -0,0 +1,490 @@
+/*!
+Lowering modules for FXC.
+
+FXC, the compiler for shader models before 6, rejects some HLSL that DXC
+accepts. [`lower_for_fxc`] rewrites the IR constructs that lead to such
+HLSL, so that the [`Writer`] can produce code both compilers accept.
+
+[`Writer`]: super::Writer
+*/
+
+use crate::arena::{Arena, Handle, UniqueArena};
+use crate::proc::scalarize::{adjust_expression, retarget_function};
+use crate::proc::walk::remove_from_emits;
+use crate::{Expression, TypeInner};
+
+/// Rewrite the parts of `module` the [`Writer`] would turn into HLSL that
+/// FXC rejects, and return true if anything changed.
+///
+/// This moves arrays that functions build from constant parts out into
+/// module-scope [`Constant`]s. The [`Writer`] declares constants as
+/// `static const` globals, which FXC accepts, instead of building the
+/// arrays in the function body, which FXC may reject.
+///
+/// It also decomposes the matrices that functions build from column
+/// vectors, so that each column is built from its scalar components. With
+/// [`Options::fxc_compat`] set, the [`Writer`] then passes those scalars to
+/// the matrix constructor directly, since FXC rejects some matrix
+/// constructors that take vectors.
+///
+/// Set [`Options::fxc_compat`] when writing the lowered module, to have
+/// the [`Writer`] check that nothing FXC rejects is left. The module
+/// should be validated again first, to get a [`ModuleInfo`] that matches
+/// it.
+///
+/// # Panics
+///
+/// If `module` has not passed validation, this may panic.
+///
+/// [`Writer`]: super::Writer
+/// [`Constant`]: crate::Constant
+/// [`Options::fxc_compat`]: super::Options::fxc_compat
+/// [`ModuleInfo`]: crate::valid::ModuleInfo
+pub fn lower_for_fxc(module: &mut crate::Module) -> bool {
+    let crate::Module {
+        ref mut types,
+        ref mut constants,
+        ref mut const_expressions,
+        ref mut functions,
+        ref mut entry_points,
+        ..
+    } = *module;
+    let functions = functions.iter_mut().map(|(_, function)| function);
+    let entry_points = entry_points.iter_mut().map(|ep| &mut ep.function);
+
+    let mut changed = false;
+    for function in functions.chain(entry_points) {
+        changed |= hoist_constant_arrays(types, constants, const_expressions, function);
+        changed |= decompose_matrices(types, function);
+    }
+    changed
+}
+
+/// Return true if [`lower_for_fxc`] would leave `module` as it is.
+pub(super) fn is_lowered(module: &crate::Module) -> bool {
+    let functions = module.functions.iter().map(|(_, function)| function);
+    let entry_points = module.entry_points.iter().map(|ep| &ep.function);
+    functions.chain(entry_points).all(|function| {
+        !constant_arrays(&module.types, &module.constants, &function.expressions).contains(&true)
+            && function.expressions.iter().all(|(_, expression)| {
+                undecomposed_matrix(&module.types, &function.expressions, expression).is_none()
+            })
+    })
+}
+
+/// Move the arrays `function` builds from constant parts into
+/// `constants`, and return true if there were any.
+fn hoist_constant_arrays(
+    types: &UniqueArena<crate::Type>,
+    constants: &mut Arena<crate::Constant>,
+    const_expressions: &mut Arena<Expression>,
+    function: &mut crate::Function,
+) -> bool {
+    let hoisted = constant_arrays(types, constants, &function.expressions);
+    if !hoisted.contains(&true) {
+        return false;
+    }
+
+    let handles: Vec<_> = function
+        .expressions
+        .iter()
+        .map(|(handle, _)| handle)
+        .filter(|handle| hoisted[handle.index()])
+        .collect();
+    let mut copied = vec![None; function.expressions.len()];
+    for handle in handles {
+        let ty = match function.expressions[handle] {
+            Expression::Compose { ty, .. } => ty,
+            _ => unreachable!(),
+        };
+        let init = copy_expression(
+            &function.expressions,
+            handle,
+            const_expressions,
+            &mut copied,
+        );
+        let span = function.expressions.get_span(handle);
+        let constant = constants.append(
+            crate::Constant {
+                name: function.named_expressions.shift_remove(&handle),
+                r#override: crate::Override::None,
+                ty,
+                init,
+            },
+            span,
+        );
+        function.expressions[handle] = Expression::Constant(constant);
+    }
+    remove_from_emits(&mut function.body, &hoisted);
+    true
+}
+
+/// Rebuild the matrices `function` composes from column vectors so that
+/// each column is composed from its scalar components, and return true if
+/// there were any.
+///
+/// A column that isn't composed from scalars already is replaced by a
+/// vector composed of its own components, so the [`Writer`] can write the
+/// scalars out in place of the column.
+///
+/// [`Writer`]: super::Writer
+fn decompose_matrices(
+    types: &mut UniqueArena<crate::Type>,
+    function: &mut crate::Function,
+) -> bool {
+    let targets: crate::FastHashMap<_, _> = function
+        .expressions
+        .iter()
+        .filter_map(|(handle, expression)| {
+            undecomposed_matrix(types, &function.expressions, expression)
+                .map(|target| (handle, target))
+        })
+        .collect();
+    if targets.is_empty() {
+        return false;
+    }
+
+    // Rebuild the arena, placing each target's new columns just before the
+    // `Compose` that replaces it. `first[i]` is the first new expression
+    // produced for the old expression with index `i`, and `last[i]` is the
+    // one that takes its place.
+    let old = std::mem::take(&mut function.expressions);
+    let mut first = Vec::with_capacity(old.len());
+    let mut last: Vec<Handle<Expression>> = Vec::with_capacity(old.len());
+    for (handle, expression) in old.iter() {
+        let span = old.get_span(handle);
+        let mut expression = expression.clone();
+        adjust_expression(&mut expression, &mut |h| *h = last[h.index()]);
+
+        let start = function.expressions.len();
+        if let Some(&(rows, width)) = targets.get(&handle) {
+            let column_ty = types.insert(
+                crate::Type {
+                    name: None,
+                    inner: TypeInner::Vector {
+                        size: rows,
+                        kind: crate::ScalarKind::Float,
+                        width,
+                    },
+                },
+                crate::Span::UNDEFINED,
+            );
+            let (old_columns, columns) = match (&old[handle], &mut expression) {
+                (
+                    &Expression::Compose {
+                        components: ref old_columns,
+                        ..
+                    },
+                    &mut Expression::Compose {
+                        components: ref mut columns,
+                        ..
+                    },
+                ) => (old_columns, columns),
+                _ => unreachable!(),
+            };
+            for (column, &old_column) in columns.iter_mut().zip(old_columns) {
+                if is_decomposed(&old, old_column, rows) {
+                    continue;
+                }
+                let components = (0..rows as u32)
+                    .map(|index| {
+                        function.expressions.append(
+                            Expression::AccessIndex {
+                                base: *column,
+                                index,
+                            },
+                            span,
+                        )
+                    })
+                    .collect();
+                *column = function.expressions.append(
+                    Expression::Compose {
+                        ty: column_ty,
+                        components,
+                    },
+                    span,
+                );
+            }
+        }
+        let new = function.expressions.append(expression, span);
+        first.push(
+            function
+                .expressions
+                .range_from(start)
+                .first_and_last()
+                .unwrap()
+                .0,
+        );
+        last.push(new);
+    }
+
+    retarget_function(function, &first, &last);
+    true
+}
+
+/// If `expression` composes a matrix from columns that aren't all composed
+/// from their scalar components, return the matrix's row count and width.
+fn undecomposed_matrix(
+    types: &UniqueArena<crate::Type>,
+    expressions: &Arena<Expression>,
+    expression: &Expression,
+) -> Option<(crate::VectorSize, crate::Bytes)> {
+    let Expression::Compose { ty, ref components } = *expression else {
+        return None;
+    };
+    let TypeInner::Matrix { rows, width, .. } = types[ty].inner else {
+        return None;
+    };
+    let decomposed = components
+        .iter()
+        .all(|&column| is_decomposed(expressions, column, rows));
+    (!decomposed).then_some((rows, width))
+}
+
+/// Return true if the column `handle` is composed from its `rows` scalar
+/// components.
+///
+/// A vector composed from smaller vectors has fewer components than rows,
+/// so counting them is enough.
+fn is_decomposed(
+    expressions: &Arena<Expression>,
+    handle: Handle<Expression>,
+    rows: crate::VectorSize,
+) -> bool {
+    match expressions[handle] {
+        Expression::Compose { ref components, .. } => components.len() == rows as usize,
+        _ => false,
+    }
+}
+
+/// Return, for each of `expressions`, whether it builds an array from
+/// constant parts.
+fn constant_arrays(
+    types: &UniqueArena<crate::Type>,
+    constants: &Arena<crate::Constant>,
+    expressions: &Arena<Expression>,
+) -> Vec<bool> {
+    let mut constant = Vec::with_capacity(expressions.len());
+    let mut arrays = Vec::with_capacity(expressions.len());
+    for (_, expression) in expressions.iter() {
+        // Components always come before the expressions that use them.
+        let (is_constant, is_array) = match *expression {
+            Expression::Literal(_) | Expression::ZeroValue(_) => (true, false),
+            Expression::Constant(handle) => {
+                (constants[handle].r#override == crate::Override::None, false)
+            }
+            Expression::Compose { ty, ref components } => {
+                let is_constant = components
+                    .iter()
+                    .all(|component| constant[component.index()]);
+                let is_array = matches!(types[ty].inner, crate::TypeInner::Array { .. });
+                (is_constant, is_constant && is_array)
+            }
+            _ => (false, false),
+        };
+        constant.push(is_constant);
+        arrays.push(is_array);
+    }
+    arrays
+}
+
+/// Copy the constant expression `handle` from `expressions` into
+/// `const_expressions`, along with its components.
+///
+/// `copied` records the copies made so far, so each is made only once.
+fn copy_expression(
+    expressions: &Arena<Expression>,
+    handle: Handle<Expression>,
+    const_expressions: &mut Arena<Expression>,
+    copied: &mut Vec<Option<Handle<Expression>>>,
+) -> Handle<Expression> {
+    if let Some(copy) = copied[handle.index()] {
+        return copy;
+    }
+    let expression = match expressions[handle] {
+        Expression::Compose { ty, ref components } => Expression::Compose {
+            ty,
+            components: components
+                .iter()
+                .map(|&component| {
+                    copy_expression(expressions, component, const_expressions, copied)
+                })
+                .collect(),
+        },
+        ref other => other.clone(),
+    };
+    let copy = const_expressions.append(expression, expressions.get_span(handle));
+    copied[handle.index()] = Some(copy);
+    copy
+}
+
+#[cfg(all(test, feature = "wgsl-in"))]
+mod tests {
+    use crate::{Expression, Statement};
+
+    #[test]
+    fn hoist_constant_arrays() {
+        let mut module = crate::front::wgsl::parse_str(
+            "
+            @fragment
+            fn main(@location(0) index: i32) -> @location(0) vec4<f32> {
+                let weights = array(1.0, 2.0, 3.0);
+                let nested = array(array(1, 2), array(3, 4));
+                let dynamic = array(f32(index), 1.0);
+                return vec4(weights[index], f32(nested[index][1]), dynamic[index], 1.0);
+            }
+            ",
+        )
+        .unwrap();
+        let validate = |module: &crate::Module| {
+            crate::valid::Validator::new(
+                crate::valid::ValidationFlags::all(),
+                crate::valid::Capabilities::default(),
+            )
+            .validate(module)
+            .unwrap()
+        };
+        validate(&module);
+        let constants = module.constants.len();
+        assert!(!super::is_lowered(&module));
+
+        assert!(super::lower_for_fxc(&mut module));
+        validate(&module);
+        assert!(super::is_lowered(&module));
+        assert!(!super::lower_for_fxc(&mut module));
+
+        // `weights`, `nested` and its two inner arrays, but not `dynamic`.
+        assert_eq!(module.constants.len(), constants + 4);
+        let names: Vec<_> = module
+            .constants
+            .iter()
+            .filter_map(|(_, constant)| constant.name.as_deref())
+            .collect();
+        assert_eq!(names, ["weights", "nested"]);
+
+        let function = &module.entry_points[0].function;
+        assert!(function
+            .named_expressions
+            .values()
+            .all(|name| name == "dynamic"));
+        for (statement, _) in function.body.span_iter() {
+            if let Statement::Emit(ref range) = *statement {
+                for handle in range.clone() {
+                    assert!(!matches!(
+                        function.expressions[handle],
+                        Expression::Constant(_)
+                    ));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn decompose_matrices() {
+        let mut module = crate::front::wgsl::parse_str(
+            "
+            @fragment
+            fn main(@location(0) column: vec2<f32>, @location(1) x: f32) -> @location(0) vec4<f32> {
+                let a = mat2x2(column, vec2(x, 1.0));
+                let b = mat2x2(vec2(1.0, 2.0), vec2(3.0, x));
+                return vec4(a[0] + b[0], a[1] + b[1]);
+            }
+            ",
+        )
+        .unwrap();
+        let validate = |module: &crate::Module| {
+            crate::valid::Validator::new(
+                crate::valid::ValidationFlags::all(),
+                crate::valid::Capabilities::default(),
+            )
+            .validate(module)
+            .unwrap()
+        };
+        validate(&module);
+        assert!(!super::is_lowered(&module));
+
+        assert!(super::lower_for_fxc(&mut module));
+        validate(&module);
+        assert!(super::is_lowered(&module));
+        assert!(!super::lower_for_fxc(&mut module));
+
+        // Only `column` needed decomposing, into `column.x` and `column.y`.
+        let function = &module.entry_points[0].function;
+        let columns: Vec<_> = function
+            .expressions
+            .iter()
+            .filter_map(|(_, expression)| match *expression {
+                Expression::Compose { ty, ref components }
+                    if matches!(module.types[ty].inner, crate::TypeInner::Matrix { .. }) =>
+                {
+                    Some(components.clone())
+                }
+                _ => None,
+            })
+            .flatten()
+            .collect();
+        assert_eq!(columns.len(), 4);
+        let accesses = function
+            .expressions
+            .iter()
+            .filter(|&(_, expression)| matches!(*expression, Expression::AccessIndex { .. }))
+            .count();
+        // Two for `column`, and the four of the `return`.
+        assert_eq!(accesses, 6);
+        for column in columns {
+            assert!(matches!(
+                function.expressions[column],
+                Expression::Compose { ref components, .. } if components.len() == 2
+            ));
+        }
+    }
+
+    #[test]
+    fn writer_checks_lowering() {
+        use crate::back::hlsl;
+
+        let mut module = crate::front::wgsl::parse_str(
+            "
+            @fragment
+            fn main(@location(0) @interpolate(flat) index: i32) -> @location(0) vec4<f32> {
+                let weights = array(1.0, 2.0, 3.0);
+                return vec4(weights[index]);
+            }
+            ",
+        )
+        .unwrap();
+        let validate = |module: &crate::Module| {
+            crate::valid::Validator::new(
+                crate::valid::ValidationFlags::all(),
+                crate::valid::Capabilities::default(),
+            )
+            .validate(module)
+            .unwrap()
+        };
+        let write = |module: &crate::Module, options: &hlsl::Options| {
+            hlsl::Writer::new(&mut String::new(), options)
+                .write(module, &validate(module))
+                .map(|_| ())
+        };
+        let options = hlsl::Options {
+            fxc_compat: true,
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            write(&module, &options),
+            Err(hlsl::Error::FxcIncompatible(_))
+        ));
+        assert!(super::lower_for_fxc(&mut module));
+        assert!(write(&module, &options).is_ok());
+
+        let shader_model_6 = hlsl::Options {
+            shader_model: hlsl::ShaderModel::V6_0,
+            ..options
+        };
+        assert!(matches!(
+            write(&module, &shader_model_6),
+            Err(hlsl::Error::FxcIncompatible(_))
+        ));
+    }
+}
@@ -229,10 +229,7 @@ impl<'a, W: Write> super::Writer<'a, W> {
     /// <https://docs.microsoft.com/en-us/windows/win32/direct3dhlsl/dx-graphics-hlsl-to-getdimensions>
     pub(super) fn write_wrapped_image_query_function(
         &mut self,
-        module: &crate::Module,
         wiq: WrappedImageQuery,
-        expr_handle: Handle<crate::Expression>,
-        func_ctx: &FunctionCtx,
     ) -> BackendResult {
         use crate::{
             back::{COMPONENTS, INDENT},
@@ -243,26 +240,6 @@ impl<'a, W: Write> super::Writer<'a, W> {
         const RETURN_VARIABLE_NAME: &str = "ret";
         const MIP_LEVEL_PARAM: &str = "mip_level";
 
-        // Write function return type and name
-        let ret_ty = func_ctx.resolve_type(expr_handle, &module.types);
-        self.write_value_type(module, ret_ty)?;
-        write!(self.out, " ")?;
-        self.write_wrapped_image_query_function_name(wiq)?;
-
-        // Write function parameters
-        write!(self.out, "(")?;
-        // Texture always first parameter
-        self.write_image_type(wiq.dim, wiq.arrayed, wiq.class)?;
-        write!(self.out, " {ARGUMENT_VARIABLE_NAME}")?;
-        // Mipmap is a second parameter if exists
-        if let ImageQuery::SizeLevel = wiq.query {
-            write!(self.out, ", uint {MIP_LEVEL_PARAM}")?;
-        }
-        writeln!(self.out, ")")?;
-
-        // Write function body
-        writeln!(self.out, "{{")?;
-
         let array_coords = usize::from(wiq.arrayed);
         // extra parameter is the mip level count or the sample count
         let extra_coords = match wiq.class {
@@ -283,14 +260,43 @@ impl<'a, W: Write> super::Writer<'a, W> {
                 (ret, ret.len() + array_coords + extra_coords)
             }
             ImageQuery::NumLevels | ImageQuery::NumSamples | ImageQuery::NumLayers => {
-                if wiq.arrayed || wiq.dim == IDim::D3 {
-                    ("w", 4)
-                } else {
-                    ("z", 3)
-                }
+                let size_coords = match wiq.dim {
+                    IDim::D1 => 1,
+                    IDim::D2 | IDim::Cube => 2,
+                    IDim::D3 => 3,
+                };
+                let number_of_params = size_coords + array_coords + extra_coords;
+                // The layer count follows the size, and the level or sample
+                // count comes last.
+                let component = match wiq.query {
+                    ImageQuery::NumLayers => size_coords,
+                    _ => number_of_params - 1,
+                };
+                (&"xyzw"[component..component + 1], number_of_params)
             }
         };
 
+        // Write function return type and name
+        match ret_swizzle.len() {
+            1 => write!(self.out, "uint ")?,
+            size => write!(self.out, "uint{size} ")?,
+        }
+        self.write_wrapped_image_query_function_name(wiq)?;
+
+        // Write function parameters
+        write!(self.out, "(")?;
+        // Texture always first parameter
+        self.write_image_type(wiq.dim, wiq.arrayed, wiq.class)?;
+        write!(self.out, " {ARGUMENT_VARIABLE_NAME}")?;
+        // Mipmap is a second parameter if exists
+        if let ImageQuery::SizeLevel = wiq.query {
+            write!(self.out, ", uint {MIP_LEVEL_PARAM}")?;
+        }
+        writeln!(self.out, ")")?;
+
+        // Write function body
+        writeln!(self.out, "{{")?;
+
         // Write `GetDimensions` function.
         writeln!(self.out, "{INDENT}uint4 {RETURN_VARIABLE_NAME};")?;
         write!(self.out, "{INDENT}{ARGUMENT_VARIABLE_NAME}.GetDimensions(")?;
@@ -787,18 +793,14 @@ impl<'a, W: Write> super::Writer<'a, W> {
             match type_key {
                 &crate::PredeclaredType::ModfResult { size, width }
                 | &crate::PredeclaredType::FrexpResult { size, width } => {
+                    let float_name = crate::ScalarKind::Float
+                        .to_hlsl_str_for(width, self.options.shader_model)?;
                     let arg_type_name_owner;
                     let arg_type_name = if let Some(size) = size {
-                        arg_type_name_owner = format!(
-                            "{}{}",
-                            if width == 8 { "double" } else { "float" },
-                            size as u8
-                        );
+                        arg_type_name_owner = format!("{}{}", float_name, size as u8);
                         &arg_type_name_owner
-                    } else if width == 8 {
-                        "double"
                     } else {
-                        "float"
+                        float_name
                     };
 
                     let (defined_func_name, called_func_name, second_field_name, sign_multiplier) =
@@ -841,17 +843,25 @@ impl<'a, W: Write> super::Writer<'a, W> {
         expressions: &crate::Arena<crate::Expression>,
     ) -> BackendResult {
         for (handle, _) in expressions.iter() {
-            if let crate::Expression::Compose { ty, .. } = expressions[handle] {
-                match module.types[ty].inner {
-                    crate::TypeInner::Struct { .. } | crate::TypeInner::Array { .. } => {
-                        let constructor = WrappedConstructor { ty };
-                        if self.wrapped.constructors.insert(constructor) {
-                            self.write_wrapped_constructor_function(module, constructor)?;
-                        }
+            let ty = match expressions[handle] {
+                crate::Expression::Compose { ty, .. } => ty,
+                // Arrays of zeros are written as casts instead.
+                crate::Expression::Repeat { ty, value }
+                    if !crate::proc::is_zero_value(value, expressions) =>
+                {
+                    ty
+                }
+                _ => continue,
+            };
+            match module.types[ty].inner {
+                crate::TypeInner::Struct { .. } | crate::TypeInner::Array { .. } => {
+                    let constructor = WrappedConstructor { ty };
+                    if self.wrapped.constructors.insert(constructor) {
+                        self.write_wrapped_constructor_function(module, constructor)?;
                     }
-                    _ => {}
-                };
-            }
+                }
+                _ => {}
+            };
         }
         Ok(())
     }
@@ -906,7 +916,50 @@ impl<'a, W: Write> super::Writer<'a, W> {
                     };
 
                     if self.wrapped.image_queries.insert(wiq) {
-                        self.write_wrapped_image_query_function(module, wiq, handle, func_ctx)?;
+                        self.write_wrapped_image_query_function(wiq)?;
+                    }
+                }
+                // Loads checked under `ReadZeroSkipWrite` query the image's
+                // size, and its level, sample, and layer counts if they
+                // have those arguments.
+                crate::Expression::ImageLoad {
+                    image,
+                    array_index,
+                    sample,
+                    level,
+                    ..
+                } if self.options.bounds_check_policies.image_load
+                    == crate::proc::BoundsCheckPolicy::ReadZeroSkipWrite =>
+                {
+                    let (dim, arrayed, class) = match *func_ctx.resolve_type(image, &module.types) {
+                        crate::TypeInner::Image {
+                            dim,
+                            arrayed,
+                            class,
+                        } => (dim, arrayed, class),
+                        _ => unreachable!("we only load from images"),
+                    };
+                    let size = if level.is_some() {
+                        ImageQuery::SizeLevel
+                    } else {
+                        ImageQuery::Size
+                    };
+                    let queries = [
+                        level.map(|_| ImageQuery::NumLevels),
+                        sample.map(|_| ImageQuery::NumSamples),
+                        array_index.map(|_| ImageQuery::NumLayers),
+                        Some(size),
+                    ];
+                    for query in queries.into_iter().flatten() {
+                        let wiq = WrappedImageQuery {
+                            dim,
+                            arrayed,
+                            class,
+                            query,
+                        };
+                        if self.wrapped.image_queries.insert(wiq) {
+                            self.write_wrapped_image_query_function(wiq)?;
+                        }
                     }
                 }
                 // Write `WrappedConstructor` for structs that are loaded from `AddressSpace::Storage`
@@ -4,7 +4,69 @@ Backend for [HLSL][hlsl] (High-Level Shading Language).
 # Supported shader model versions:
 - 5.0
 - 5.1
-- 6.0
+- 6.0 through 6.7
+
+# Comparison sampling
+
+HLSL only has a subset of the comparison sampling forms expressible in the IR,
+and which ones are available depends on the shader model:
+
+| IR [`SampleLevel`]          | HLSL method             | Shader model |
+|-----------------------------|-------------------------|--------------|
+| `Auto`                      | `SampleCmp`             | all          |
+| `Zero`                      | `SampleCmpLevelZero`    | all          |
+| `Exact`, literal zero       | `SampleCmpLevelZero`    | all          |
+| `Exact`                     | `SampleCmpLevel`        | 6.7+         |
+| `Bias`, `Gradient`          | none                    |              |
+
+Gather operations with a depth reference always use `GatherCmp`. Forms that
+can't be expressed in the selected shader model produce
+[`Error::ShaderModelTooLow`] or [`Error::Unimplemented`] rather than HLSL that
+fails to compile.
+
+[`SampleLevel`]: crate::SampleLevel
+
+# Shader model 6 features
+
+Some IR features need a shader model 6 target:
+
+- Subgroup operations become wave intrinsics like `WaveActiveSum`, which
+  need shader model 6.0.
+
+- The [`ViewIndex`] built-in becomes `SV_ViewID`, which needs 6.1.
+
+- From shader model 6.2, 16-bit floats are written as `float16_t`, and
+  the output must be compiled with DXC's `-enable-16bit-types`. Earlier
+  shader models get `half`, which is only a minimum precision: the driver
+  may compute with 32 bits instead.
+
+[`ViewIndex`]: crate::BuiltIn::ViewIndex
+
+# FXC compatibility
+
+Shader models before 6 are compiled with FXC, which rejects some HLSL that
+DXC accepts, such as arrays of constants built inside functions. Running
+[`lower_for_fxc`] on the module first rewrites the constructs that would
+produce such code. Setting [`Options::fxc_compat`] has the [`Writer`] check
+that the module has been lowered and that the shader model is one FXC
+supports, returning [`Error::FxcIncompatible`] otherwise.
+
+# Entry point inputs and outputs
+
+By default, fragment shader inputs and vertex shader outputs are gathered into
+generated structs whose members come in ascending location order, followed by
+the built-ins. Direct3D matches a vertex shader's outputs with the next stage's
+inputs by their position in the signature, so sorting both sides the same way
+keeps them compatible even if the IR declares them in different orders. All
+other inputs and outputs are written as the IR declares them.
+
+Tools that post-process the generated entry points can set
+[`Options::entry_point_io`] to [`EntryPointIo::Direct`] to get the IR's
+signature everywhere instead: arguments become parameters, and struct results
+are returned as they are. They then have to keep the stages' interfaces in the
+same order themselves. Built-ins that HLSL has no semantic for, like
+`num_workgroups`, `base_vertex` and `base_instance`, are read from the special
+constants buffer either way.
 
 # Layout of values in `uniform` buffers
 
@@ -92,6 +154,28 @@ float3x2 GetMatmOnBaz(Baz obj) {
 We also emit an analogous `Set` function, as well as functions for
 accessing individual columns by dynamic index.
 
+## Declaring constant buffers
+
+By default, each `uniform` variable becomes a `cbuffer` block holding a
+single member of the variable's type:
+
+```ignore
+cbuffer globals : register(b0) { Globals globals; }
+```
+
+Setting [`Options::constant_buffer_style`] to
+[`ConstantBufferStyle::Template`] declares variables of struct type with
+shader model 5.1's `ConstantBuffer<T>` instead:
+
+```ignore
+ConstantBuffer<Globals> globals : register(b0);
+```
+
+Either way the struct starts at offset zero in the buffer and HLSL packs it
+the same, so the two styles can be used interchangeably with the same host
+data. Variables of other types are always declared as blocks, since
+`ConstantBuffer` only accepts structs.
+
 [hlsl]: https://docs.microsoft.com/en-us/windows/win32/direct3dhlsl/dx-graphics-hlsl
 [ilov]: https://gpuweb.github.io/gpuweb/wgsl/#internal-value-layout
 [16bb]: https://github.com/microsoft/DirectXShaderCompiler/wiki/Buffer-Packing#constant-buffer-packing
@@ -99,6 +183,7 @@ accessing individual columns by dynamic index.
 */
 
 mod conv;
+mod fxc;
 mod help;
 mod keywords;
 mod storage;
@@ -109,6 +194,8 @@ use thiserror::Error;
 
 use crate::{back, proc};
 
+pub use fxc::lower_for_fxc;
+
 #[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serialize", derive(serde::Serialize))]
 #[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
@@ -122,6 +209,37 @@ pub struct BindTarget {
 // Using `BTreeMap` instead of `HashMap` so that we can hash itself.
 pub type BindingMap = std::collections::BTreeMap<crate::ResourceBinding, BindTarget>;
 
+/// How the [`Writer`] declares entry point inputs and outputs.
+///
+/// See the [module-level documentation](self#entry-point-inputs-and-outputs).
+#[derive(Clone, Copy, Debug, Default, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
+pub enum EntryPointIo {
+    /// Gather fragment shader inputs and vertex shader outputs into structs
+    /// sorted by location, then built-in.
+    #[default]
+    Wrapped,
+    /// Write the entry point signatures as the IR declares them.
+    Direct,
+}
+
+/// How the [`Writer`] declares `uniform` buffers.
+///
+/// See the [module-level documentation](self#declaring-constant-buffers).
+#[derive(Clone, Copy, Debug, Default, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
+pub enum ConstantBufferStyle {
+    /// A `cbuffer` block holding the variable, which every shader model
+    /// supports.
+    #[default]
+    Block,
+    /// `ConstantBuffer<T>`, for variables of struct type. This needs shader
+    /// model 5.1.
+    Template,
+}
+
 /// A HLSL shader model version.
 #[allow(non_snake_case, non_camel_case_types)]
 #[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, PartialOrd)]
@@ -131,6 +249,13 @@ pub enum ShaderModel {
     V5_0,
     V5_1,
     V6_0,
+    V6_1,
+    V6_2,
+    V6_3,
+    V6_4,
+    V6_5,
+    V6_6,
+    V6_7,
 }
 
 impl ShaderModel {
@@ -139,6 +264,13 @@ impl ShaderModel {
             Self::V5_0 => "5_0",
             Self::V5_1 => "5_1",
             Self::V6_0 => "6_0",
+            Self::V6_1 => "6_1",
+            Self::V6_2 => "6_2",
+            Self::V6_3 => "6_3",
+            Self::V6_4 => "6_4",
+            Self::V6_5 => "6_5",
+            Self::V6_6 => "6_6",
+            Self::V6_7 => "6_7",
         }
     }
 }
@@ -192,7 +324,32 @@ pub struct Options {
     /// Bind target of the push constant buffer
     pub push_constants_target: Option<BindTarget>,
     /// Should workgroup variables be zero initialized (by polyfilling)?
+    ///
+    /// Turn this off for modules that have been through
+    /// [`zero_initialize_workgroup_memory`], which does the same in the IR.
+    ///
+    /// [`zero_initialize_workgroup_memory`]: crate::proc::zero_initialize_workgroup_memory
     pub zero_initialize_workgroup_memory: bool,
+    /// How to declare entry point inputs and outputs.
+    #[cfg_attr(feature = "deserialize", serde(default))]
+    pub entry_point_io: EntryPointIo,
+    /// How to declare `uniform` buffers.
+    #[cfg_attr(feature = "deserialize", serde(default))]
+    pub constant_buffer_style: ConstantBufferStyle,
+    /// Check that the output is meant for FXC, and that the module has
+    /// been through [`lower_for_fxc`]. This also has matrix constructors
+    /// take the scalars of the columns that pass decomposed.
+    #[cfg_attr(feature = "deserialize", serde(default))]
+    pub fxc_compat: bool,
+    /// How to handle out-of-range image loads.
+    ///
+    /// Only a [`ReadZeroSkipWrite`] policy for [`image_load`] adds checks.
+    /// Direct3D already discards out-of-range writes to storage textures.
+    ///
+    /// [`ReadZeroSkipWrite`]: crate::proc::BoundsCheckPolicy::ReadZeroSkipWrite
+    /// [`image_load`]: crate::proc::BoundsCheckPolicies::image_load
+    #[cfg_attr(feature = "deserialize", serde(default))]
+    pub bounds_check_policies: crate::proc::BoundsCheckPolicies,
 }
 
 impl Default for Options {
@@ -204,6 +361,10 @@ impl Default for Options {
             special_constants_binding: None,
             push_constants_target: None,
             zero_initialize_workgroup_memory: true,
+            entry_point_io: EntryPointIo::default(),
+            constant_buffer_style: ConstantBufferStyle::default(),
+            fxc_compat: false,
+            bounds_check_policies: crate::proc::BoundsCheckPolicies::default(),
         }
     }
 }
@@ -245,6 +406,16 @@ pub enum Error {
     UnsupportedScalar(crate::ScalarKind, crate::Bytes),
     #[error("{0}")]
     Unimplemented(String), // TODO: Error used only during development
+    #[error("{feature} requires shader model {required:?}, but {current:?} was requested")]
+    ShaderModelTooLow {
+        feature: &'static str,
+        required: ShaderModel,
+        current: ShaderModel,
+    },
+    #[error("`Options::fxc_compat` is set, but {0}")]
+    FxcIncompatible(&'static str),
+    #[error("Push constants are used, but `Options::push_constants_target` isn't set")]
+    MissingPushConstantsTarget,
     #[error("{0}")]
     Custom(String),
 }
@@ -299,4 +470,10 @@ pub struct Writer<'a, W> {
     /// [`AccessIndex`]: crate::Expression::AccessIndex
     temp_access_chain: Vec<storage::SubAccess>,
     need_bake_expressions: back::NeedBakeExpressions,
+    /// How the fragment entry point being written changes depth, if it
+    /// promises a conservative depth. This picks the semantic of its
+    /// [`FragDepth`] output.
+    ///
+    /// [`FragDepth`]: crate::BuiltIn::FragDepth
+    conservative_depth: Option<crate::ConservativeDepth>,
 }
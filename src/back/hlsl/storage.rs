@@ -17,7 +17,8 @@ To generate code for a [`Storage`] access:
   values.
 
 - Call [`Writer::write_storage_address`] to emit an HLSL expression
-  for a given slice of [`SubAccess`] values.
+  for a given slice of [`SubAccess`] values. This adds up the constant
+  offsets itself, so the expression has at most one constant term.
 
 Naga IR expressions can operate on composite values of any type, but
 [`ByteAddressBuffer`] and [`RWByteAddressBuffer`] have only a fixed
@@ -44,7 +45,7 @@ needed once HLSL for the access has been generated.
 [`Writer`]: super::Writer
 */
 
-use super::{super::FunctionCtx, BackendResult, Error};
+use super::{super::FunctionCtx, BackendResult, Error, ShaderModel};
 use crate::{
     proc::{Alignment, NameKey, TypeResolution},
     Handle,
@@ -100,26 +101,57 @@ impl<W: fmt::Write> super::Writer<'_, W> {
         chain: &[SubAccess],
         func_ctx: &FunctionCtx,
     ) -> BackendResult {
-        if chain.is_empty() {
-            write!(self.out, "0")?;
-        }
-        for (i, access) in chain.iter().enumerate() {
-            if i != 0 {
-                write!(self.out, "+")?;
-            }
+        // The constant offsets of members and elements are summed into a
+        // single term, written after the computed indices, so that deeply
+        // nested accesses don't turn into long sums of constants. This only
+        // changes how the address is written: the IR is left as it is, and
+        // the index terms are written as they come, without being combined
+        // or reordered.
+        let mut offset = 0;
+        let mut first = true;
+        for access in chain {
             match *access {
-                SubAccess::Offset(offset) => {
-                    write!(self.out, "{offset}")?;
-                }
+                SubAccess::Offset(member_offset) => offset += member_offset,
                 SubAccess::Index { value, stride } => {
+                    if !first {
+                        write!(self.out, "+")?;
+                    }
+                    first = false;
                     self.write_expr(module, value, func_ctx)?;
                     write!(self.out, "*{stride}")?;
                 }
             }
         }
+        if first {
+            write!(self.out, "{offset}")?;
+        } else if offset != 0 {
+            write!(self.out, "+{offset}")?;
+        }
         Ok(())
     }
 
+    /// Return the type argument of the templated `Load` and `Store` methods
+    /// for the 64-bit scalar or vector `inner`.
+    ///
+    /// The methods without a type argument only access 32-bit values, and
+    /// those with one need shader model 6.2.
+    fn templated_storage_type(&self, inner: &crate::TypeInner) -> Result<String, Error> {
+        if self.options.shader_model < ShaderModel::V6_2 {
+            return Err(Error::ShaderModelTooLow {
+                feature: "64-bit values in storage buffers",
+                required: ShaderModel::V6_2,
+                current: self.options.shader_model,
+            });
+        }
+        Ok(match *inner {
+            crate::TypeInner::Scalar { kind, width } => kind.to_hlsl_str(width)?.to_string(),
+            crate::TypeInner::Vector { size, kind, width } => {
+                format!("{}{}", kind.to_hlsl_str(width)?, size as u8)
+            }
+            _ => unreachable!(),
+        })
+    }
+
     fn write_storage_load_sequence<I: Iterator<Item = (TypeResolution, u32)>>(
         &mut self,
         module: &crate::Module,
@@ -157,6 +189,18 @@ impl<W: fmt::Write> super::Writer<'_, W> {
         func_ctx: &FunctionCtx,
     ) -> BackendResult {
         match *result_ty.inner_with(&module.types) {
+            ref inner @ (crate::TypeInner::Scalar { width: 8, .. }
+            | crate::TypeInner::Vector { width: 8, .. }) => {
+                // 64-bit values need the templated load, which also takes
+                // care of the type.
+                let ty = self.templated_storage_type(inner)?;
+                let chain = mem::take(&mut self.temp_access_chain);
+                let var_name = &self.names[&NameKey::GlobalVariable(var_handle)];
+                write!(self.out, "{var_name}.Load<{ty}>(")?;
+                self.write_storage_address(module, &chain, func_ctx)?;
+                write!(self.out, ")")?;
+                self.temp_access_chain = chain;
+            }
             crate::TypeInner::Scalar { kind, width: _ } => {
                 // working around the borrow checker in `self.write_expr`
                 let chain = mem::take(&mut self.temp_access_chain);
@@ -189,7 +233,7 @@ impl<W: fmt::Write> super::Writer<'_, W> {
                 write!(
                     self.out,
                     "{}{}x{}(",
-                    crate::ScalarKind::Float.to_hlsl_str(width)?,
+                    crate::ScalarKind::Float.to_hlsl_str_for(width, self.options.shader_model)?,
                     columns as u8,
                     rows as u8,
                 )?;
@@ -296,6 +340,18 @@ impl<W: fmt::Write> super::Writer<'_, W> {
             }
         };
         match *ty_resolution.inner_with(&module.types) {
+            ref inner @ (crate::TypeInner::Scalar { width: 8, .. }
+            | crate::TypeInner::Vector { width: 8, .. }) => {
+                let ty = self.templated_storage_type(inner)?;
+                let chain = mem::take(&mut self.temp_access_chain);
+                let var_name = &self.names[&NameKey::GlobalVariable(var_handle)];
+                write!(self.out, "{level}{var_name}.Store<{ty}>(")?;
+                self.write_storage_address(module, &chain, func_ctx)?;
+                write!(self.out, ", ")?;
+                self.write_store_value(module, &value, func_ctx)?;
+                writeln!(self.out, ");")?;
+                self.temp_access_chain = chain;
+            }
             crate::TypeInner::Scalar { .. } => {
                 // working around the borrow checker in `self.write_expr`
                 let chain = mem::take(&mut self.temp_access_chain);
@@ -330,7 +386,7 @@ impl<W: fmt::Write> super::Writer<'_, W> {
                     self.out,
                     "{}{}{}x{} {}{} = ",
                     level.next(),
-                    crate::ScalarKind::Float.to_hlsl_str(width)?,
+                    crate::ScalarKind::Float.to_hlsl_str_for(width, self.options.shader_model)?,
                     columns as u8,
                     rows as u8,
                     STORE_TEMP_NAME,
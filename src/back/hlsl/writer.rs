@@ -1,7 +1,10 @@
 use super::{
-    help::{WrappedArrayLength, WrappedConstructor, WrappedImageQuery, WrappedStructMatrixAccess},
+    help::{
+        ImageQuery, WrappedArrayLength, WrappedConstructor, WrappedImageQuery,
+        WrappedStructMatrixAccess,
+    },
     storage::StoreValue,
-    BackendResult, Error, Options,
+    BackendResult, Error, Options, ShaderModel,
 };
 use crate::{
     back,
@@ -20,6 +23,26 @@ const SPECIAL_OTHER: &str = "other";
 pub(crate) const MODF_FUNCTION: &str = "naga_modf";
 pub(crate) const FREXP_FUNCTION: &str = "naga_frexp";
 
+/// Return the intrinsic an entry point reads `arg` from, if it is a built-in
+/// HLSL has no semantic for.
+const fn argument_intrinsic(arg: &crate::FunctionArgument) -> Option<&'static str> {
+    match arg.binding {
+        Some(crate::Binding::BuiltIn(built_in)) => built_in.to_hlsl_intrinsic(),
+        _ => None,
+    }
+}
+
+/// Return the conservative depth `ep` promises, if HLSL needs to know it.
+fn conservative_depth(
+    ep: &crate::EntryPoint,
+    info: &valid::FunctionInfo,
+) -> Option<crate::ConservativeDepth> {
+    match ep.early_depth(info) {
+        Some(back::EarlyDepth::Conservative(conservative)) => Some(conservative),
+        _ => None,
+    }
+}
+
 struct EpStructMember {
     name: String,
     ty: Handle<crate::Type>,
@@ -87,6 +110,7 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
             wrapped: super::Wrapped::default(),
             temp_access_chain: Vec::new(),
             need_bake_expressions: Default::default(),
+            conservative_depth: None,
         }
     }
 
@@ -176,6 +200,16 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
                     self.need_bake_expressions.insert(expr);
                 }
             }
+
+            // Inclusive scans are written as exclusive scans combined with
+            // the argument.
+            if let Expression::SubgroupOperation {
+                op: crate::SubgroupOperation::InclusiveScan(_),
+                argument,
+            } = *expr
+            {
+                self.need_bake_expressions.insert(argument);
+            }
         }
     }
 
@@ -184,6 +218,19 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
         module: &Module,
         module_info: &valid::ModuleInfo,
     ) -> Result<super::ReflectionInfo, Error> {
+        if self.options.fxc_compat {
+            if self.options.shader_model >= ShaderModel::V6_0 {
+                return Err(Error::FxcIncompatible(
+                    "FXC only supports shader models before 6",
+                ));
+            }
+            if !super::fxc::is_lowered(module) {
+                return Err(Error::FxcIncompatible(
+                    "the module hasn't been through `lower_for_fxc`",
+                ));
+            }
+        }
+
         self.reset(module);
 
         // Write special constants, if needed
@@ -278,9 +325,11 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
         // Write all entry points wrapped structs
         for (index, ep) in module.entry_points.iter().enumerate() {
             let ep_name = self.names[&NameKey::EntryPoint(index as u16)].clone();
+            self.conservative_depth = conservative_depth(ep, module_info.get_entry_point(index));
             let ep_io = self.write_ep_interface(module, &ep.function, ep.stage, &ep_name)?;
             self.entry_point_io.push(ep_io);
         }
+        self.conservative_depth = None;
 
         // Write all regular functions
         for (handle, function) in module.functions.iter() {
@@ -368,8 +417,14 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
                 )?;
             }
 
+            if ep.early_depth(info) == Some(back::EarlyDepth::Force) {
+                writeln!(self.out, "[earlydepthstencil]")?;
+            }
+
             let name = self.names[&NameKey::EntryPoint(index as u16)].clone();
+            self.conservative_depth = conservative_depth(ep, info);
             self.write_function(module, &name, &ep.function, &ctx, info)?;
+            self.conservative_depth = None;
 
             if index < module.entry_points.len() - 1 {
                 writeln!(self.out)?;
@@ -418,7 +473,24 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
     ) -> BackendResult {
         match *binding {
             crate::Binding::BuiltIn(builtin) => {
-                let builtin_str = builtin.to_hlsl_str()?;
+                if builtin == crate::BuiltIn::ViewIndex
+                    && self.options.shader_model < ShaderModel::V6_1
+                {
+                    return Err(Error::ShaderModelTooLow {
+                        feature: "the view index",
+                        required: ShaderModel::V6_1,
+                        current: self.options.shader_model,
+                    });
+                }
+                let builtin_str = match (builtin, self.conservative_depth) {
+                    (crate::BuiltIn::FragDepth, Some(crate::ConservativeDepth::GreaterEqual)) => {
+                        "SV_DepthGreaterEqual"
+                    }
+                    (crate::BuiltIn::FragDepth, Some(crate::ConservativeDepth::LessEqual)) => {
+                        "SV_DepthLessEqual"
+                    }
+                    _ => builtin.to_hlsl_str()?,
+                };
                 write!(self.out, " : {builtin_str}")?;
             }
             crate::Binding::Location {
@@ -502,7 +574,11 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
         let struct_name = format!("{stage:?}Input_{entry_point_name}");
 
         let mut fake_members = Vec::new();
-        for arg in func.arguments.iter() {
+        for arg in func
+            .arguments
+            .iter()
+            .filter(|arg| argument_intrinsic(arg).is_none())
+        {
             match module.types[arg.ty].inner {
                 TypeInner::Struct { ref members, .. } => {
                     for member in members.iter() {
@@ -571,6 +647,10 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
     /// Writes special interface structures for an entry point. The special structures have
     /// all the fields flattened into them and sorted by binding. They are only needed for
     /// VS outputs and FS inputs, so that these interfaces match.
+    ///
+    /// With [`EntryPointIo::Direct`], no structures are written.
+    ///
+    /// [`EntryPointIo::Direct`]: super::EntryPointIo::Direct
     fn write_ep_interface(
         &mut self,
         module: &Module,
@@ -578,14 +658,30 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
         stage: ShaderStage,
         ep_name: &str,
     ) -> Result<EntryPointInterface, Error> {
+        if self.options.entry_point_io == super::EntryPointIo::Direct {
+            return Ok(EntryPointInterface {
+                input: None,
+                output: None,
+            });
+        }
         Ok(EntryPointInterface {
-            input: if !func.arguments.is_empty() && stage == ShaderStage::Fragment {
+            input: if func
+                .arguments
+                .iter()
+                .any(|arg| argument_intrinsic(arg).is_none())
+                && stage == ShaderStage::Fragment
+            {
                 Some(self.write_ep_input_struct(module, func, stage, ep_name)?)
             } else {
                 None
             },
+            // A struct's members have the same semantics wherever it is
+            // used, so a conservative depth needs a struct of its own.
             output: match func.result {
-                Some(ref fr) if fr.binding.is_none() && stage == ShaderStage::Vertex => {
+                Some(ref fr)
+                    if fr.binding.is_none()
+                        && (stage == ShaderStage::Vertex || self.conservative_depth.is_some()) =>
+                {
                     Some(self.write_ep_output_struct(module, fr, stage, ep_name)?)
                 }
                 _ => None,
@@ -606,6 +702,10 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
         };
         let mut fake_iter = ep_input.members.iter();
         for (arg_index, arg) in func.arguments.iter().enumerate() {
+            // `write_ep_intrinsic_arguments` takes care of these.
+            if argument_intrinsic(arg).is_some() {
+                continue;
+            }
             write!(self.out, "{}", back::INDENT)?;
             self.write_type(module, arg.ty)?;
             let arg_name = &self.names[&NameKey::EntryPointArgument(ep_index, arg_index as u32)];
@@ -637,6 +737,34 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
         Ok(())
     }
 
+    /// Write an entry point preface that initializes the arguments HLSL has
+    /// no semantics for from the intrinsics that return them.
+    fn write_ep_intrinsic_arguments(
+        &mut self,
+        module: &Module,
+        func: &crate::Function,
+        ep_index: u16,
+    ) -> BackendResult {
+        for (arg_index, arg) in func.arguments.iter().enumerate() {
+            let intrinsic = match argument_intrinsic(arg) {
+                Some(intrinsic) => intrinsic,
+                None => continue,
+            };
+            if self.options.shader_model < ShaderModel::V6_0 {
+                return Err(Error::ShaderModelTooLow {
+                    feature: "wave intrinsics",
+                    required: ShaderModel::V6_0,
+                    current: self.options.shader_model,
+                });
+            }
+            write!(self.out, "{}", back::INDENT)?;
+            self.write_type(module, arg.ty)?;
+            let arg_name = &self.names[&NameKey::EntryPointArgument(ep_index, arg_index as u32)];
+            writeln!(self.out, " {arg_name} = {intrinsic};")?;
+        }
+        Ok(())
+    }
+
     /// Helper method used to write global variables
     /// # Notes
     /// Always adds a newline
@@ -660,6 +788,18 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
             }
         }
 
+        // `ConstantBuffer<T>` only accepts structs.
+        let templated_cbuffer = global.space == crate::AddressSpace::Uniform
+            && self.options.constant_buffer_style == super::ConstantBufferStyle::Template
+            && matches!(*inner, TypeInner::Struct { .. });
+        if templated_cbuffer && self.options.shader_model < ShaderModel::V5_1 {
+            return Err(Error::ShaderModelTooLow {
+                feature: "`ConstantBuffer<T>` declarations",
+                required: ShaderModel::V5_1,
+                current: self.options.shader_model,
+            });
+        }
+
         // https://docs.microsoft.com/en-us/windows/win32/direct3dhlsl/dx-graphics-hlsl-variable-register
         let register_ty = match global.space {
             crate::AddressSpace::Function => unreachable!("Function address space"),
@@ -673,6 +813,12 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
                 self.write_type(module, global.ty)?;
                 ""
             }
+            crate::AddressSpace::Uniform if templated_cbuffer => {
+                write!(self.out, "ConstantBuffer<")?;
+                self.write_type(module, global.ty)?;
+                write!(self.out, ">")?;
+                "b"
+            }
             crate::AddressSpace::Uniform => {
                 // constant buffer declarations are expected to be inlined, e.g.
                 // `cbuffer foo: register(b0) { field1: type1; }`
@@ -685,6 +831,9 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
                 } else {
                     ("", "t")
                 };
+                if access.contains(crate::StorageAccess::COHERENT) {
+                    write!(self.out, "globallycoherent ")?;
+                }
                 write!(self.out, "{prefix}ByteAddressBuffer")?;
                 register
             }
@@ -698,9 +847,14 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
                     TypeInner::Sampler { .. } => "s",
                     // all storage textures are UAV, unconditionally
                     TypeInner::Image {
-                        class: crate::ImageClass::Storage { .. },
+                        class: crate::ImageClass::Storage { access, .. },
                         ..
-                    } => "u",
+                    } => {
+                        if access.contains(crate::StorageAccess::COHERENT) {
+                            write!(self.out, "globallycoherent ")?;
+                        }
+                        "u"
+                    }
                     _ => "t",
                 };
                 self.write_type(module, global.ty)?;
@@ -737,7 +891,7 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
                 .options
                 .push_constants_target
                 .as_ref()
-                .expect("No bind target was defined for the push constants block");
+                .ok_or(Error::MissingPushConstantsTarget)?;
             write!(self.out, ": register(b{}", target.register)?;
             if target.space != 0 {
                 write!(self.out, ", space{}", target.space)?;
@@ -778,7 +932,7 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
             }
         }
 
-        if global.space == crate::AddressSpace::Uniform {
+        if global.space == crate::AddressSpace::Uniform && !templated_cbuffer {
             write!(self.out, " {{ ")?;
 
             self.write_global_type(module, global.ty)?;
@@ -1025,13 +1179,17 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
     pub(super) fn write_value_type(&mut self, module: &Module, inner: &TypeInner) -> BackendResult {
         match *inner {
             TypeInner::Scalar { kind, width } | TypeInner::Atomic { kind, width } => {
-                write!(self.out, "{}", kind.to_hlsl_str(width)?)?;
+                write!(
+                    self.out,
+                    "{}",
+                    kind.to_hlsl_str_for(width, self.options.shader_model)?
+                )?;
             }
             TypeInner::Vector { size, kind, width } => {
                 write!(
                     self.out,
                     "{}{}",
-                    kind.to_hlsl_str(width)?,
+                    kind.to_hlsl_str_for(width, self.options.shader_model)?,
                     back::vector_size_str(size)
                 )?;
             }
@@ -1047,7 +1205,7 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
                 write!(
                     self.out,
                     "{}{}x{}",
-                    crate::ScalarKind::Float.to_hlsl_str(width)?,
+                    crate::ScalarKind::Float.to_hlsl_str_for(width, self.options.shader_model)?,
                     back::vector_size_str(columns),
                     back::vector_size_str(rows),
                 )?;
@@ -1166,10 +1324,16 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
                     write!(self.out, "{} {}", ep_input.ty_name, ep_input.arg_name,)?;
                 } else {
                     let stage = module.entry_points[ep_index as usize].stage;
+                    let mut first = true;
                     for (index, arg) in func.arguments.iter().enumerate() {
-                        if index != 0 {
+                        // The body reads these from intrinsics instead.
+                        if argument_intrinsic(arg).is_some() {
+                            continue;
+                        }
+                        if !first {
                             write!(self.out, ", ")?;
                         }
+                        first = false;
                         self.write_type(module, arg.ty)?;
 
                         let argument_name =
@@ -1186,7 +1350,7 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
                     }
 
                     if need_workgroup_variables_initialization {
-                        if !func.arguments.is_empty() {
+                        if !first {
                             write!(self.out, ", ")?;
                         }
                         write!(self.out, "uint3 __local_invocation_id : SV_GroupThreadID")?;
@@ -1218,13 +1382,51 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
         }
 
         if let back::FunctionType::EntryPoint(index) = func_ctx.ty {
+            self.write_ep_intrinsic_arguments(module, func, index)?;
             self.write_ep_arguments_initialization(module, func, index)?;
         }
 
-        // Write function local variables
-        for (handle, local) in func.local_variables.iter() {
+        // Write function local variables, except those scoped to a nested block
+        let nested_locals = func.body.nested_locals();
+        let locals: Vec<_> = func
+            .local_variables
+            .iter()
+            .map(|(handle, _)| handle)
+            .filter(|handle| !nested_locals.contains(handle))
+            .collect();
+        self.write_locals(module, &locals, func_ctx, back::Level(1), true)?;
+
+        // Write the function body (statement list)
+        for sta in func.body.iter() {
+            // The indentation should always be 1 when writing the function body
+            self.write_stmt(module, sta, func_ctx, back::Level(1))?;
+        }
+
+        writeln!(self.out, "}}")?;
+
+        self.named_expressions.clear();
+
+        Ok(())
+    }
+
+    /// Helper method used to write the declarations of `locals`
+    ///
+    /// # Notes
+    /// Ends in an empty line if `separate` is true and there are any locals
+    fn write_locals(
+        &mut self,
+        module: &Module,
+        locals: &[Handle<crate::LocalVariable>],
+        func_ctx: &back::FunctionCtx<'_>,
+        level: back::Level,
+        separate: bool,
+    ) -> BackendResult {
+        let func = func_ctx.function(module);
+        for &handle in locals {
+            let local = &func.local_variables[handle];
+
             // Write indentation (only for readability)
-            write!(self.out, "{}", back::INDENT)?;
+            write!(self.out, "{level}")?;
 
             // Write the local name
             // The leading space is important
@@ -1248,20 +1450,31 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
             writeln!(self.out, ";")?
         }
 
-        if !func.local_variables.is_empty() {
+        if separate && !locals.is_empty() {
             writeln!(self.out)?;
         }
 
-        // Write the function body (statement list)
-        for sta in func.body.iter() {
-            // The indentation should always be 1 when writing the function body
-            self.write_stmt(module, sta, func_ctx, back::Level(1))?;
-        }
-
-        writeln!(self.out, "}}")?;
-
-        self.named_expressions.clear();
+        Ok(())
+    }
 
+    /// Helper method used to write a block nested in a statement, declaring
+    /// the locals scoped to it where their scope starts
+    ///
+    /// # Notes
+    /// Locals in scope for the whole block come first, followed by an empty line
+    fn write_block(
+        &mut self,
+        module: &Module,
+        block: &crate::Block,
+        func_ctx: &back::FunctionCtx<'_>,
+        level: back::Level,
+    ) -> BackendResult {
+        for (run, (locals, statements)) in block.scoped_runs().into_iter().enumerate() {
+            self.write_locals(module, &locals, func_ctx, level, run == 0)?;
+            for sta in statements {
+                self.write_stmt(module, sta, func_ctx, level)?;
+            }
+        }
         Ok(())
     }
 
@@ -1348,10 +1561,8 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
             Statement::Block(ref block) => {
                 write!(self.out, "{level}")?;
                 writeln!(self.out, "{{")?;
-                for sta in block.iter() {
-                    // Increase the indentation to help with readability
-                    self.write_stmt(module, sta, func_ctx, level.next())?
-                }
+                // Increase the indentation to help with readability
+                self.write_block(module, block, func_ctx, level.next())?;
                 writeln!(self.out, "{level}}}")?
             }
             // TODO: copy-paste from glsl-out
@@ -1365,21 +1576,15 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
                 self.write_expr(module, condition, func_ctx)?;
                 writeln!(self.out, ") {{")?;
 
+                // Increase indentation to help with readability
                 let l2 = level.next();
-                for sta in accept {
-                    // Increase indentation to help with readability
-                    self.write_stmt(module, sta, func_ctx, l2)?;
-                }
+                self.write_block(module, accept, func_ctx, l2)?;
 
                 // If there are no statements in the reject block we skip writing it
                 // This is only for readability
                 if !reject.is_empty() {
                     writeln!(self.out, "{level}}} else {{")?;
-
-                    for sta in reject {
-                        // Increase indentation to help with readability
-                        self.write_stmt(module, sta, func_ctx, l2)?;
-                    }
+                    self.write_block(module, reject, func_ctx, l2)?;
                 }
 
                 writeln!(self.out, "{level}}}")?
@@ -1747,6 +1952,16 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
                 ref continuing,
                 break_if,
             } => {
+                // The continuing block comes before the body here, so locals
+                // scoped to either can only be declared outside the loop
+                let locals: Vec<_> = body
+                    .locals()
+                    .iter()
+                    .chain(continuing.locals())
+                    .map(|scoped| scoped.local)
+                    .collect();
+                self.write_locals(module, &locals, func_ctx, level, true)?;
+
                 let l2 = level.next();
                 if !continuing.is_empty() || break_if.is_some() {
                     let gate_name = self.namer.call("loop_init");
@@ -1804,8 +2019,61 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
 
                 write!(self.out, " = ")?;
                 self.write_expr(module, value, func_ctx)?;
+                // The value is always four components, but the texture's
+                // element type may have fewer; drop the rest explicitly
+                // rather than relying on implicit truncation.
+                if let TypeInner::Image {
+                    class: crate::ImageClass::Storage { format, .. },
+                    ..
+                } = *func_ctx.resolve_type(image, &module.types)
+                {
+                    write!(self.out, "{}", format.to_hlsl_store_swizzle())?;
+                }
                 writeln!(self.out, ";")?;
             }
+            Statement::ImageAtomic {
+                image,
+                coordinate,
+                array_index,
+                fun,
+                value,
+            } => {
+                write!(self.out, "{level}")?;
+                // `InterlockedExchange` always reports the original value,
+                // even though image atomics have no result.
+                let original = match fun {
+                    crate::AtomicFunction::Exchange { compare: None } => {
+                        let name = self.namer.call("original");
+                        let inner = func_ctx.resolve_type(value, &module.types);
+                        self.write_value_type(module, inner)?;
+                        write!(self.out, " {name}; ")?;
+                        Some(name)
+                    }
+                    _ => None,
+                };
+                write!(self.out, "Interlocked{}(", fun.to_hlsl_suffix())?;
+                self.write_expr(module, image, func_ctx)?;
+                write!(self.out, "[")?;
+                if let Some(index) = array_index {
+                    write!(self.out, "int3(")?;
+                    self.write_expr(module, coordinate, func_ctx)?;
+                    write!(self.out, ", ")?;
+                    self.write_expr(module, index, func_ctx)?;
+                    write!(self.out, ")")?;
+                } else {
+                    self.write_expr(module, coordinate, func_ctx)?;
+                }
+                write!(self.out, "], ")?;
+                if let crate::AtomicFunction::Subtract = fun {
+                    // we just wrote `InterlockedAdd`, so negate the argument
+                    write!(self.out, "-")?;
+                }
+                self.write_expr(module, value, func_ctx)?;
+                match original {
+                    Some(name) => writeln!(self.out, ", {name});")?,
+                    None => writeln!(self.out, ");")?,
+                }
+            }
             Statement::Call {
                 function,
                 ref arguments,
@@ -1858,6 +2126,21 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
 
                 let fun_str = fun.to_hlsl_suffix();
                 write!(self.out, " {res_name}; ")?;
+                // Byte address buffers have separate methods for 64-bit
+                // atomics.
+                let width_suffix = match *func_ctx.resolve_type(value, &module.types) {
+                    TypeInner::Scalar { width: 8, .. } => {
+                        if self.options.shader_model < ShaderModel::V6_6 {
+                            return Err(Error::ShaderModelTooLow {
+                                feature: "64-bit atomics",
+                                required: ShaderModel::V6_6,
+                                current: self.options.shader_model,
+                            });
+                        }
+                        "64"
+                    }
+                    _ => "",
+                };
                 match pointer_space {
                     crate::AddressSpace::WorkGroup => {
                         write!(self.out, "Interlocked{fun_str}(")?;
@@ -1870,7 +2153,7 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
                         // ownership of our reusable access chain buffer.
                         let chain = mem::take(&mut self.temp_access_chain);
                         let var_name = &self.names[&NameKey::GlobalVariable(var_handle)];
-                        write!(self.out, "{var_name}.Interlocked{fun_str}(")?;
+                        write!(self.out, "{var_name}.Interlocked{fun_str}{width_suffix}(")?;
                         self.write_storage_address(module, &chain, func_ctx)?;
                         self.temp_access_chain = chain;
                     }
@@ -1908,6 +2191,15 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
                 selector,
                 ref cases,
             } => {
+                // Case bodies may be written more than once, to emulate
+                // fallthrough, so declare the locals scoped to them first
+                let locals: Vec<_> = cases
+                    .iter()
+                    .flat_map(|case| case.body.locals())
+                    .map(|scoped| scoped.local)
+                    .collect();
+                self.write_locals(module, &locals, func_ctx, level, true)?;
+
                 // Start the switch
                 write!(self.out, "{level}")?;
                 write!(self.out, "switch(")?;
@@ -2040,8 +2332,13 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
                 // decimal part even it's zero
                 crate::Literal::F64(value) => write!(self.out, "{value:?}L")?,
                 crate::Literal::F32(value) => write!(self.out, "{value:?}")?,
+                crate::Literal::F16(bits) => {
+                    write!(self.out, "{:?}h", crate::proc::f16_to_f32(bits))?
+                }
                 crate::Literal::U32(value) => write!(self.out, "{}u", value)?,
                 crate::Literal::I32(value) => write!(self.out, "{}", value)?,
+                crate::Literal::U64(value) => write!(self.out, "{}uL", value)?,
+                crate::Literal::I64(value) => write!(self.out, "{}L", value)?,
                 crate::Literal::Bool(value) => write!(self.out, "{}", value)?,
             },
             Expression::Constant(handle) => {
@@ -2066,11 +2363,43 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
                     }
                 };
                 write!(self.out, "(")?;
-                for (index, component) in components.iter().enumerate() {
+                for (index, &component) in components.iter().enumerate() {
                     if index != 0 {
                         write!(self.out, ", ")?;
                     }
-                    write_expression(self, *component)?;
+                    match expressions[component] {
+                        // Pass the scalars of columns `lower_for_fxc` has
+                        // decomposed straight to the matrix constructor.
+                        Expression::Compose {
+                            components: ref scalars,
+                            ..
+                        } if self.options.fxc_compat
+                            && matches!(module.types[ty].inner, TypeInner::Matrix { .. }) =>
+                        {
+                            for (index, &scalar) in scalars.iter().enumerate() {
+                                if index != 0 {
+                                    write!(self.out, ", ")?;
+                                }
+                                write_expression(self, scalar)?;
+                            }
+                        }
+                        _ => write_expression(self, component)?,
+                    }
+                }
+                write!(self.out, ")")?;
+            }
+            Expression::Repeat { ty, value } if crate::proc::is_zero_value(value, expressions) => {
+                self.write_default_init(module, ty)?
+            }
+            Expression::Repeat { ty, value } => {
+                self.write_wrapped_constructor_function_name(module, WrappedConstructor { ty })?;
+                write!(self.out, "(")?;
+                let components = crate::proc::repeat_components(ty, value, &module.types);
+                for (index, component) in components.enumerate() {
+                    if index != 0 {
+                        write!(self.out, ", ")?;
+                    }
+                    write_expression(self, component)?;
                 }
                 write!(self.out, ")")?;
             }
@@ -2087,6 +2416,66 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
                 write_expression(self, value)?;
                 write!(self.out, ").{number_of_components}")?
             }
+            // The rest are only ever override expressions.
+            Expression::Unary { op, expr } => {
+                let op_str = match op {
+                    crate::UnaryOperator::Negate => "-",
+                    crate::UnaryOperator::LogicalNot => "!",
+                    crate::UnaryOperator::BitwiseNot => "~",
+                };
+                write!(self.out, "{op_str}(")?;
+                write_expression(self, expr)?;
+                write!(self.out, ")")?;
+            }
+            Expression::Binary { op, left, right } => {
+                write!(self.out, "(")?;
+                write_expression(self, left)?;
+                write!(self.out, " {} ", crate::back::binary_operation_str(op))?;
+                write_expression(self, right)?;
+                write!(self.out, ")")?;
+            }
+            Expression::As {
+                expr,
+                kind,
+                convert: Some(dst_width),
+            } => {
+                write!(
+                    self.out,
+                    "{}(",
+                    kind.to_hlsl_str_for(dst_width, self.options.shader_model)?,
+                )?;
+                write_expression(self, expr)?;
+                write!(self.out, ")")?;
+            }
+            Expression::Select {
+                condition,
+                accept,
+                reject,
+            } => {
+                write!(self.out, "(")?;
+                write_expression(self, condition)?;
+                write!(self.out, " ? ")?;
+                write_expression(self, accept)?;
+                write!(self.out, " : ")?;
+                write_expression(self, reject)?;
+                write!(self.out, ")")?
+            }
+            Expression::AccessIndex { base, index } => {
+                write_expression(self, base)?;
+                match crate::back::const_access_struct(module, expressions, base) {
+                    Some(ty) => write!(
+                        self.out,
+                        ".{}",
+                        &self.names[&NameKey::StructMember(ty, index)]
+                    )?,
+                    None => write!(self.out, "[{index}]")?,
+                }
+            }
+            Expression::As { convert: None, .. } => {
+                return Err(Error::Unimplemented(
+                    "bitcast in an override expression".to_string(),
+                ));
+            }
             _ => unreachable!(),
         }
 
@@ -2145,6 +2534,7 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
             | Expression::Constant(_)
             | Expression::ZeroValue(_)
             | Expression::Compose { .. }
+            | Expression::Repeat { .. }
             | Expression::Splat { .. } => {
                 self.write_possibly_const_expression(
                     module,
@@ -2376,6 +2766,10 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
                     Some(_) => "Cmp",
                     None => "",
                 };
+                let level = match (depth_ref, gather) {
+                    (Some(_), None) => self.comparison_sample_level(level, func_ctx)?,
+                    _ => level,
+                };
                 let level_str = match level {
                     Sl::Zero if gather.is_none() => "LevelZero",
                     Sl::Auto | Sl::Zero => "",
@@ -2462,6 +2856,39 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
                 sample,
                 level,
             } => {
+                // Check every argument rather than relying on Direct3D
+                // returning zero for out-of-range loads, so that the policy
+                // means the same thing in every backend.
+                let checked = self.options.bounds_check_policies.image_load
+                    == proc::BoundsCheckPolicy::ReadZeroSkipWrite;
+                if checked {
+                    write!(self.out, "(")?;
+                    self.write_image_load_checks(
+                        module,
+                        image,
+                        coordinate,
+                        array_index,
+                        sample,
+                        level,
+                        func_ctx,
+                    )?;
+                    write!(self.out, " ? ")?;
+                }
+
+                // Storage images with fewer than four channels load fewer
+                // than four components, so widen them to the IR's type.
+                let padding = match *func_ctx.resolve_type(image, &module.types) {
+                    TypeInner::Image {
+                        class: crate::ImageClass::Storage { format, .. },
+                        ..
+                    } => format.to_hlsl_load_padding(),
+                    _ => "",
+                };
+                if !padding.is_empty() {
+                    self.write_value_type(module, func_ctx.resolve_type(expr, &module.types))?;
+                    write!(self.out, "(")?;
+                }
+
                 // https://docs.microsoft.com/en-us/windows/win32/direct3dhlsl/dx-graphics-hlsl-to-load
                 self.write_expr(module, image, func_ctx)?;
                 write!(self.out, ".Load(")?;
@@ -2482,11 +2909,20 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
 
                 // close bracket for Load function
                 write!(self.out, ")")?;
+                if !padding.is_empty() {
+                    write!(self.out, "{padding})")?;
+                }
 
                 // return x component if return type is scalar
                 if let TypeInner::Scalar { .. } = *func_ctx.resolve_type(expr, &module.types) {
                     write!(self.out, ".x")?;
                 }
+
+                if checked {
+                    write!(self.out, " : (")?;
+                    self.write_value_type(module, func_ctx.resolve_type(expr, &module.types))?;
+                    write!(self.out, ")0)")?;
+                }
             }
             Expression::GlobalVariable(handle) => match module.global_variables[handle].space {
                 crate::AddressSpace::Storage { .. } => {}
@@ -2572,18 +3008,22 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
                                 write!(
                                     self.out,
                                     "{}{}(",
-                                    kind.to_hlsl_str(dst_width)?,
+                                    kind.to_hlsl_str_for(dst_width, self.options.shader_model)?,
                                     back::vector_size_str(size)
                                 )?;
                             }
                             TypeInner::Scalar { .. } => {
-                                write!(self.out, "{}(", kind.to_hlsl_str(dst_width)?,)?;
+                                write!(
+                                    self.out,
+                                    "{}(",
+                                    kind.to_hlsl_str_for(dst_width, self.options.shader_model)?,
+                                )?;
                             }
                             TypeInner::Matrix { columns, rows, .. } => {
                                 write!(
                                     self.out,
                                     "{}{}x{}(",
-                                    kind.to_hlsl_str(dst_width)?,
+                                    kind.to_hlsl_str_for(dst_width, self.options.shader_model)?,
                                     back::vector_size_str(columns),
                                     back::vector_size_str(rows)
                                 )?;
@@ -2633,6 +3073,14 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
                     CountLeadingZeros,
                 }
 
+                let (fun, arg1, arg2) = if fun == Mf::Clamp
+                    && back::clamps_to_unit_interval(module, func_ctx.expressions, arg1, arg2)
+                {
+                    (Mf::Saturate, None, None)
+                } else {
+                    (fun, arg1, arg2)
+                };
+
                 let fun = match fun {
                     // comparison
                     Mf::Abs => Function::Regular("abs"),
@@ -2742,19 +3190,42 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
                         // count: u32
                         // T is u32 or i32 or vecN<u32> or vecN<i32>
                         if let (Some(offset), Some(count)) = (arg1, arg2) {
-                            let scalar_width: u8 = 32;
+                            let scalar_width = func_ctx
+                                .resolve_type(arg, &module.types)
+                                .scalar_width()
+                                .unwrap_or(32);
+                            // The offset and count are clamped as WGSL requires,
+                            // so the shifts below stay in range.
                             // Works for signed and unsigned
                             // (count == 0 ? 0 : (e << (32 - count - offset)) >> (32 - count))
                             write!(self.out, "(")?;
-                            self.write_expr(module, count, func_ctx)?;
+                            self.write_clamped_bit_count(
+                                module,
+                                offset,
+                                count,
+                                scalar_width,
+                                func_ctx,
+                            )?;
                             write!(self.out, " == 0 ? 0 : (")?;
                             self.write_expr(module, arg, func_ctx)?;
                             write!(self.out, " << ({scalar_width} - ")?;
-                            self.write_expr(module, count, func_ctx)?;
+                            self.write_clamped_bit_count(
+                                module,
+                                offset,
+                                count,
+                                scalar_width,
+                                func_ctx,
+                            )?;
                             write!(self.out, " - ")?;
-                            self.write_expr(module, offset, func_ctx)?;
+                            self.write_clamped_bit_offset(module, offset, scalar_width, func_ctx)?;
                             write!(self.out, ")) >> ({scalar_width} - ")?;
-                            self.write_expr(module, count, func_ctx)?;
+                            self.write_clamped_bit_count(
+                                module,
+                                offset,
+                                count,
+                                scalar_width,
+                                func_ctx,
+                            )?;
                             write!(self.out, "))")?;
                         }
                     }
@@ -2766,12 +3237,22 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
                         // returns T
                         // T is i32, u32, vecN<i32>, or vecN<u32>
                         if let (Some(newbits), Some(offset), Some(count)) = (arg1, arg2, arg3) {
-                            let scalar_width: u8 = 32;
-                            let scalar_max: u32 = 0xFFFFFFFF;
+                            let scalar_width = func_ctx
+                                .resolve_type(arg, &module.types)
+                                .scalar_width()
+                                .unwrap_or(32);
+                            let scalar_max = u64::MAX >> (64 - scalar_width);
+                            let suffix = if scalar_width == 64 { "uL" } else { "u" };
                             // mask = ((0xFFFFFFFFu >> (32 - count)) << offset)
                             // (count == 0 ? e : ((e & ~mask) | ((newbits << offset) & mask)))
                             write!(self.out, "(")?;
-                            self.write_expr(module, count, func_ctx)?;
+                            self.write_clamped_bit_count(
+                                module,
+                                offset,
+                                count,
+                                scalar_width,
+                                func_ctx,
+                            )?;
                             write!(self.out, " == 0 ? ")?;
                             self.write_expr(module, arg, func_ctx)?;
                             write!(self.out, " : ")?;
@@ -2779,22 +3260,34 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
                             self.write_expr(module, arg, func_ctx)?;
                             write!(self.out, " & ~")?;
                             // mask
-                            write!(self.out, "(({scalar_max}u >> ({scalar_width}u - ")?;
-                            self.write_expr(module, count, func_ctx)?;
+                            write!(self.out, "(({scalar_max}{suffix} >> ({scalar_width}u - ")?;
+                            self.write_clamped_bit_count(
+                                module,
+                                offset,
+                                count,
+                                scalar_width,
+                                func_ctx,
+                            )?;
                             write!(self.out, ")) << ")?;
-                            self.write_expr(module, offset, func_ctx)?;
+                            self.write_clamped_bit_offset(module, offset, scalar_width, func_ctx)?;
                             write!(self.out, ")")?;
                             // end mask
                             write!(self.out, ") | ((")?;
                             self.write_expr(module, newbits, func_ctx)?;
                             write!(self.out, " << ")?;
-                            self.write_expr(module, offset, func_ctx)?;
+                            self.write_clamped_bit_offset(module, offset, scalar_width, func_ctx)?;
                             write!(self.out, ") & ")?;
                             // // mask
-                            write!(self.out, "(({scalar_max}u >> ({scalar_width}u - ")?;
-                            self.write_expr(module, count, func_ctx)?;
+                            write!(self.out, "(({scalar_max}{suffix} >> ({scalar_width}u - ")?;
+                            self.write_clamped_bit_count(
+                                module,
+                                offset,
+                                count,
+                                scalar_width,
+                                func_ctx,
+                            )?;
                             write!(self.out, ")) << ")?;
-                            self.write_expr(module, offset, func_ctx)?;
+                            self.write_clamped_bit_offset(module, offset, scalar_width, func_ctx)?;
                             write!(self.out, ")")?;
                             // // end mask
                             write!(self.out, "))")?;
@@ -2964,7 +3457,8 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
                     }
                     Function::CountTrailingZeros => {
                         match *func_ctx.resolve_type(arg, &module.types) {
-                            TypeInner::Vector { size, kind, .. } => {
+                            TypeInner::Vector { size, kind, width } => {
+                                let bits = u32::from(width) * 8;
                                 let s = match size {
                                     crate::VectorSize::Bi => ".xx",
                                     crate::VectorSize::Tri => ".xxx",
@@ -2972,22 +3466,23 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
                                 };
 
                                 if let ScalarKind::Uint = kind {
-                                    write!(self.out, "min((32u){s}, firstbitlow(")?;
+                                    write!(self.out, "min(({bits}u){s}, firstbitlow(")?;
                                     self.write_expr(module, arg, func_ctx)?;
                                     write!(self.out, "))")?;
                                 } else {
-                                    write!(self.out, "asint(min((32u){s}, firstbitlow(")?;
+                                    write!(self.out, "asint(min(({bits}u){s}, firstbitlow(")?;
                                     self.write_expr(module, arg, func_ctx)?;
                                     write!(self.out, ")))")?;
                                 }
                             }
-                            TypeInner::Scalar { kind, .. } => {
+                            TypeInner::Scalar { kind, width } => {
+                                let bits = u32::from(width) * 8;
                                 if let ScalarKind::Uint = kind {
-                                    write!(self.out, "min(32u, firstbitlow(")?;
+                                    write!(self.out, "min({bits}u, firstbitlow(")?;
                                     self.write_expr(module, arg, func_ctx)?;
                                     write!(self.out, "))")?;
                                 } else {
-                                    write!(self.out, "asint(min(32u, firstbitlow(")?;
+                                    write!(self.out, "asint(min({bits}u, firstbitlow(")?;
                                     self.write_expr(module, arg, func_ctx)?;
                                     write!(self.out, ")))")?;
                                 }
@@ -2999,7 +3494,8 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
                     }
                     Function::CountLeadingZeros => {
                         match *func_ctx.resolve_type(arg, &module.types) {
-                            TypeInner::Vector { size, kind, .. } => {
+                            TypeInner::Vector { size, kind, width } => {
+                                let bits = u32::from(width) * 8;
                                 let s = match size {
                                     crate::VectorSize::Bi => ".xx",
                                     crate::VectorSize::Tri => ".xxx",
@@ -3007,7 +3503,7 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
                                 };
 
                                 if let ScalarKind::Uint = kind {
-                                    write!(self.out, "((31u){s} - firstbithigh(")?;
+                                    write!(self.out, "(({}u){s} - firstbithigh(", bits - 1)?;
                                     self.write_expr(module, arg, func_ctx)?;
                                     write!(self.out, "))")?;
                                 } else {
@@ -3015,21 +3511,27 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
                                     self.write_expr(module, arg, func_ctx)?;
                                     write!(
                                         self.out,
-                                        " < (0){s} ? (0){s} : (31){s} - asint(firstbithigh("
+                                        " < (0){s} ? (0){s} : ({}){s} - asint(firstbithigh(",
+                                        bits - 1
                                     )?;
                                     self.write_expr(module, arg, func_ctx)?;
                                     write!(self.out, ")))")?;
                                 }
                             }
-                            TypeInner::Scalar { kind, .. } => {
+                            TypeInner::Scalar { kind, width } => {
+                                let bits = u32::from(width) * 8;
                                 if let ScalarKind::Uint = kind {
-                                    write!(self.out, "(31u - firstbithigh(")?;
+                                    write!(self.out, "({}u - firstbithigh(", bits - 1)?;
                                     self.write_expr(module, arg, func_ctx)?;
                                     write!(self.out, "))")?;
                                 } else {
                                     write!(self.out, "(")?;
                                     self.write_expr(module, arg, func_ctx)?;
-                                    write!(self.out, " < 0 ? 0 : 31 - asint(firstbithigh(")?;
+                                    write!(
+                                        self.out,
+                                        " < 0 ? 0 : {} - asint(firstbithigh(",
+                                        bits - 1
+                                    )?;
                                     self.write_expr(module, arg, func_ctx)?;
                                     write!(self.out, ")))")?;
                                 }
@@ -3146,6 +3648,82 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
                 self.write_expr(module, reject, func_ctx)?;
                 write!(self.out, ")")?
             }
+            // Binding array accesses wrap non-uniform indices in
+            // `NonUniformResourceIndex` themselves.
+            Expression::NonUniform(value) => self.write_expr(module, value, func_ctx)?,
+            Expression::SubgroupOperation { op, argument } => {
+                use crate::{SubgroupOperation as So, SubgroupReduction as Sr};
+                if self.options.shader_model < ShaderModel::V6_0 {
+                    return Err(Error::ShaderModelTooLow {
+                        feature: "wave intrinsics",
+                        required: ShaderModel::V6_0,
+                        current: self.options.shader_model,
+                    });
+                }
+                let reduction_str = |reduction| match reduction {
+                    Sr::Add => "Sum",
+                    Sr::Mul => "Product",
+                    Sr::Min => "Min",
+                    Sr::Max => "Max",
+                    Sr::And => "BitAnd",
+                    Sr::Or => "BitOr",
+                    Sr::Xor => "BitXor",
+                };
+                match op {
+                    So::Ballot | So::All | So::Any | So::BroadcastFirst => {
+                        let fun_str = match op {
+                            So::Ballot => "WaveActiveBallot",
+                            So::All => "WaveActiveAllTrue",
+                            So::Any => "WaveActiveAnyTrue",
+                            _ => "WaveReadLaneFirst",
+                        };
+                        write!(self.out, "{fun_str}(")?;
+                        self.write_expr(module, argument, func_ctx)?;
+                        write!(self.out, ")")?
+                    }
+                    So::Reduce(reduction) => {
+                        write!(self.out, "WaveActive{}(", reduction_str(reduction))?;
+                        self.write_expr(module, argument, func_ctx)?;
+                        write!(self.out, ")")?
+                    }
+                    So::InclusiveScan(reduction) | So::ExclusiveScan(reduction) => {
+                        // HLSL only has exclusive sums and products.
+                        let operator = match reduction {
+                            Sr::Add => "+",
+                            Sr::Mul => "*",
+                            _ => {
+                                return Err(Error::Unimplemented(format!(
+                                    "subgroup scan with {reduction:?}"
+                                )))
+                            }
+                        };
+                        write!(self.out, "WavePrefix{}(", reduction_str(reduction))?;
+                        self.write_expr(module, argument, func_ctx)?;
+                        write!(self.out, ")")?;
+                        if let So::InclusiveScan(_) = op {
+                            write!(self.out, " {operator} ")?;
+                            self.write_expr(module, argument, func_ctx)?;
+                        }
+                    }
+                    So::Broadcast(index)
+                    | So::Shuffle(index)
+                    | So::ShuffleDown(index)
+                    | So::ShuffleUp(index)
+                    | So::ShuffleXor(index) => {
+                        write!(self.out, "WaveReadLaneAt(")?;
+                        self.write_expr(module, argument, func_ctx)?;
+                        write!(self.out, ", ")?;
+                        match op {
+                            So::ShuffleDown(_) => write!(self.out, "WaveGetLaneIndex() + ")?,
+                            So::ShuffleUp(_) => write!(self.out, "WaveGetLaneIndex() - ")?,
+                            So::ShuffleXor(_) => write!(self.out, "WaveGetLaneIndex() ^ ")?,
+                            _ => {}
+                        }
+                        self.write_expr(module, index, func_ctx)?;
+                        write!(self.out, ")")?
+                    }
+                }
+            }
             // Not supported yet
             Expression::RayQueryGetIntersection { .. } => unreachable!(),
             // Nothing to do here, since call expression already cached
@@ -3161,6 +3739,119 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
         Ok(())
     }
 
+    /// Write the `offset` operand of `extractBits` or `insertBits`, clamped
+    /// to the `width` in bits of the value as WGSL requires.
+    fn write_clamped_bit_offset(
+        &mut self,
+        module: &Module,
+        offset: Handle<crate::Expression>,
+        width: u8,
+        func_ctx: &back::FunctionCtx<'_>,
+    ) -> BackendResult {
+        write!(self.out, "min(")?;
+        self.write_expr(module, offset, func_ctx)?;
+        write!(self.out, ", {width}u)")?;
+        Ok(())
+    }
+
+    /// Write the `count` operand of `extractBits` or `insertBits`, clamped
+    /// to the number of bits above the clamped `offset` as WGSL requires.
+    fn write_clamped_bit_count(
+        &mut self,
+        module: &Module,
+        offset: Handle<crate::Expression>,
+        count: Handle<crate::Expression>,
+        width: u8,
+        func_ctx: &back::FunctionCtx<'_>,
+    ) -> BackendResult {
+        write!(self.out, "min(")?;
+        self.write_expr(module, count, func_ctx)?;
+        write!(self.out, ", {width}u - ")?;
+        self.write_clamped_bit_offset(module, offset, width, func_ctx)?;
+        write!(self.out, ")")?;
+        Ok(())
+    }
+
+    /// Write the condition under which an [`ImageLoad`] is in bounds, for
+    /// the `ReadZeroSkipWrite` policy.
+    ///
+    /// The level of detail comes first, since the size depends on it. All of
+    /// the comparisons are unsigned, so that negative values fail them too.
+    ///
+    /// [`ImageLoad`]: crate::Expression::ImageLoad
+    #[allow(clippy::too_many_arguments)]
+    fn write_image_load_checks(
+        &mut self,
+        module: &Module,
+        image: Handle<crate::Expression>,
+        coordinate: Handle<crate::Expression>,
+        array_index: Option<Handle<crate::Expression>>,
+        sample: Option<Handle<crate::Expression>>,
+        level: Option<Handle<crate::Expression>>,
+        func_ctx: &back::FunctionCtx<'_>,
+    ) -> BackendResult {
+        let (dim, arrayed, class) = match *func_ctx.resolve_type(image, &module.types) {
+            TypeInner::Image {
+                dim,
+                arrayed,
+                class,
+            } => (dim, arrayed, class),
+            _ => unreachable!("we only load from images"),
+        };
+        let query = |query| WrappedImageQuery {
+            dim,
+            arrayed,
+            class,
+            query,
+        };
+
+        let counts = [
+            (level, ImageQuery::NumLevels),
+            (sample, ImageQuery::NumSamples),
+            (array_index, ImageQuery::NumLayers),
+        ];
+        for (value, count) in counts {
+            if let Some(value) = value {
+                write!(self.out, "uint(")?;
+                self.write_expr(module, value, func_ctx)?;
+                write!(self.out, ") < ")?;
+                self.write_wrapped_image_query_function_name(query(count))?;
+                write!(self.out, "(")?;
+                self.write_expr(module, image, func_ctx)?;
+                write!(self.out, ") && ")?;
+            }
+        }
+
+        let size = match dim {
+            crate::ImageDimension::D1 => 1,
+            crate::ImageDimension::D2 | crate::ImageDimension::Cube => 2,
+            crate::ImageDimension::D3 => 3,
+        };
+        if size == 1 {
+            write!(self.out, "uint(")?;
+        } else {
+            write!(self.out, "all(uint{size}(")?;
+        }
+        self.write_expr(module, coordinate, func_ctx)?;
+        write!(self.out, ") < ")?;
+        if let Some(level) = level {
+            self.write_wrapped_image_query_function_name(query(ImageQuery::SizeLevel))?;
+            write!(self.out, "(")?;
+            self.write_expr(module, image, func_ctx)?;
+            write!(self.out, ", ")?;
+            self.write_expr(module, level, func_ctx)?;
+        } else {
+            self.write_wrapped_image_query_function_name(query(ImageQuery::Size))?;
+            write!(self.out, "(")?;
+            self.write_expr(module, image, func_ctx)?;
+        }
+        write!(self.out, ")")?;
+        if size != 1 {
+            write!(self.out, ")")?;
+        }
+        Ok(())
+    }
+
     fn write_named_expr(
         &mut self,
         module: &Module,
@@ -3221,6 +3912,46 @@ impl<'a, W: fmt::Write> super::Writer<'a, W> {
         }
         Ok(())
     }
+
+    /// Choose the level of detail to use for a non-gather comparison sample.
+    ///
+    /// `SampleCmp` and `SampleCmpLevelZero` are available in every shader
+    /// model, but `SampleCmpLevel` requires SM 6.7, and there is no
+    /// comparison form taking a bias or gradients at all. An `Exact` level
+    /// that is a literal zero is rewritten to `Zero`, so that it can be
+    /// expressed everywhere.
+    fn comparison_sample_level(
+        &self,
+        level: crate::SampleLevel,
+        func_ctx: &back::FunctionCtx<'_>,
+    ) -> Result<crate::SampleLevel, Error> {
+        use crate::SampleLevel as Sl;
+
+        match level {
+            Sl::Auto | Sl::Zero => Ok(level),
+            Sl::Exact(expr) => {
+                let is_zero = match func_ctx.expressions[expr] {
+                    crate::Expression::Literal(crate::Literal::F32(value)) => value == 0.0,
+                    crate::Expression::ZeroValue(_) => true,
+                    _ => false,
+                };
+                if is_zero {
+                    Ok(Sl::Zero)
+                } else if self.options.shader_model >= ShaderModel::V6_7 {
+                    Ok(level)
+                } else {
+                    Err(Error::ShaderModelTooLow {
+                        feature: "SampleCmpLevel",
+                        required: ShaderModel::V6_7,
+                        current: self.options.shader_model,
+                    })
+                }
+            }
+            Sl::Bias(_) | Sl::Gradient { .. } => Err(Error::Unimplemented(format!(
+                "depth comparison sampling with {level:?}"
+            ))),
+        }
+    }
 }
 
 pub(super) struct MatrixType {
@@ -3358,3 +4089,714 @@ fn get_inner_matrix_of_global_uniform(
     }
     None
 }
+
+#[test]
+fn test_comparison_sample_level() {
+    use crate::valid::{Capabilities, ValidationFlags};
+
+    // create a function that samples a depth texture at an explicit level
+    let mut module = crate::Module::default();
+    let span = crate::Span::default();
+    let f32_ty = module.types.insert(
+        crate::Type {
+            name: None,
+            inner: TypeInner::Scalar {
+                kind: ScalarKind::Float,
+                width: 4,
+            },
+        },
+        span,
+    );
+    let vec2_ty = module.types.insert(
+        crate::Type {
+            name: None,
+            inner: TypeInner::Vector {
+                size: crate::VectorSize::Bi,
+                kind: ScalarKind::Float,
+                width: 4,
+            },
+        },
+        span,
+    );
+    let image_ty = module.types.insert(
+        crate::Type {
+            name: None,
+            inner: TypeInner::Image {
+                dim: crate::ImageDimension::D2,
+                arrayed: false,
+                class: crate::ImageClass::Depth { multi: false },
+            },
+        },
+        span,
+    );
+    let sampler_ty = module.types.insert(
+        crate::Type {
+            name: None,
+            inner: TypeInner::Sampler { comparison: true },
+        },
+        span,
+    );
+    let mut add_global = |name: &str, ty, binding| {
+        module.global_variables.append(
+            crate::GlobalVariable {
+                name: Some(name.to_string()),
+                space: crate::AddressSpace::Handle,
+                binding: Some(crate::ResourceBinding { group: 0, binding }),
+                ty,
+                init: None,
+            },
+            span,
+        )
+    };
+    let image_var = add_global("t", image_ty, 0);
+    let sampler_var = add_global("s", sampler_ty, 1);
+
+    let mut fun = crate::Function {
+        result: Some(crate::FunctionResult {
+            ty: f32_ty,
+            binding: None,
+        }),
+        ..Default::default()
+    };
+    let image = fun
+        .expressions
+        .append(crate::Expression::GlobalVariable(image_var), span);
+    let sampler = fun
+        .expressions
+        .append(crate::Expression::GlobalVariable(sampler_var), span);
+    let coordinate = fun
+        .expressions
+        .append(crate::Expression::ZeroValue(vec2_ty), span);
+    let depth_ref = fun
+        .expressions
+        .append(crate::Expression::Literal(crate::Literal::F32(0.5)), span);
+    let lod = fun
+        .expressions
+        .append(crate::Expression::Literal(crate::Literal::F32(2.0)), span);
+    let start = fun.expressions.len();
+    let sample = fun.expressions.append(
+        crate::Expression::ImageSample {
+            image,
+            sampler,
+            gather: None,
+            coordinate,
+            array_index: None,
+            offset: None,
+            level: crate::SampleLevel::Exact(lod),
+            depth_ref: Some(depth_ref),
+        },
+        span,
+    );
+    fun.body.push(
+        crate::Statement::Emit(fun.expressions.range_from(start)),
+        span,
+    );
+    fun.body.push(
+        crate::Statement::Return {
+            value: Some(sample),
+        },
+        span,
+    );
+    let _ = module.functions.append(fun, span);
+
+    let info = valid::Validator::new(ValidationFlags::all(), Capabilities::default())
+        .validate(&module)
+        .unwrap();
+
+    // SM 5.1 can only sample at level zero
+    let options = Options {
+        shader_model: ShaderModel::V5_1,
+        ..Default::default()
+    };
+    let mut writer = super::Writer::new(String::new(), &options);
+    match writer.write(&module, &info) {
+        Err(Error::ShaderModelTooLow { required, .. }) => {
+            assert_eq!(required, ShaderModel::V6_7)
+        }
+        other => panic!("unexpected result: {:?}", other.map(|_| ())),
+    }
+
+    // SM 6.7 has `SampleCmpLevel`
+    let options = Options {
+        shader_model: ShaderModel::V6_7,
+        ..Default::default()
+    };
+    let mut output = String::new();
+    super::Writer::new(&mut output, &options)
+        .write(&module, &info)
+        .unwrap();
+    assert!(output.contains(".SampleCmpLevel("));
+}
+
+#[cfg(feature = "wgsl-in")]
+#[test]
+fn scoped_locals() {
+    use crate::valid::{Capabilities, ValidationFlags};
+
+    let module = crate::front::wgsl::parse_str(
+        "
+        fn f(x: u32) -> u32 {
+            if x > 1u {
+                var a = x;
+                a += 1u;
+                var b = a * 2u;
+                return b;
+            }
+            return x;
+        }
+
+        @compute @workgroup_size(1)
+        fn main() {
+            _ = f(2u);
+        }
+        ",
+    )
+    .unwrap();
+    let info = valid::Validator::new(ValidationFlags::all(), Capabilities::default())
+        .validate(&module)
+        .unwrap();
+    let mut output = String::new();
+    super::Writer::new(&mut output, &Options::default())
+        .write(&module, &info)
+        .unwrap();
+
+    // `a` is in scope for the whole `if`, so it comes first, but `b` is
+    // only declared once `a` has been used.
+    assert!(output.contains("        uint a = (uint)0;\n\n        a = x;\n"));
+    assert!(!output.contains("{\n    uint a"));
+    let declared = |needle| output.find(needle).unwrap();
+    assert!(declared("uint b = (uint)0;") > declared("uint _expr"));
+}
+
+#[cfg(feature = "wgsl-in")]
+#[test]
+fn narrow_storage_texels() {
+    use crate::valid::{Capabilities, ValidationFlags};
+
+    let module = crate::front::wgsl::parse_str(
+        "
+        @group(0) @binding(0) var uints: texture_storage_2d<rg16uint, read_write>;
+        @group(0) @binding(1) var sints: texture_storage_2d<r8sint, read_write>;
+
+        @compute @workgroup_size(1)
+        fn main() {
+            let u = textureLoad(uints, vec2(0));
+            textureStore(uints, vec2(1), u + 1u);
+            let i = textureLoad(sints, vec2(0));
+            textureStore(sints, vec2(1), i - 1);
+        }
+        ",
+    )
+    .unwrap();
+    let info = valid::Validator::new(ValidationFlags::all(), Capabilities::default())
+        .validate(&module)
+        .unwrap();
+    let mut output = String::new();
+    super::Writer::new(&mut output, &Options::default())
+        .write(&module, &info)
+        .unwrap();
+
+    assert!(output.contains("RWTexture2D<uint2> uints"));
+    assert!(output.contains("RWTexture2D<int> sints"));
+    // Loads are widened to four components, and stores narrowed to the
+    // texel's.
+    let line = |needle: &str| output.lines().find(|line| line.contains(needle)).unwrap();
+    assert!(line("uint4(uints.Load(").ends_with(", 0u, 1u);"));
+    assert!(line("int4(sints.Load(").ends_with(", 0, 0, 1);"));
+    assert!(line("    uints[").ends_with(".xy;"));
+    assert!(line("    sints[").ends_with(".x;"));
+}
+
+#[cfg(feature = "wgsl-in")]
+#[test]
+fn storage_store_swizzles() {
+    use crate::valid::{Capabilities, ValidationFlags};
+
+    let module = crate::front::wgsl::parse_str(
+        "
+        @group(0) @binding(0) var rgba: texture_storage_2d<rgba8unorm, write>;
+        @group(0) @binding(1) var red: texture_storage_2d<r16float, write>;
+        @group(0) @binding(2) var rgb: texture_storage_2d<rg11b10float, write>;
+        @group(0) @binding(3) var rg: texture_storage_2d<rg8snorm, write>;
+
+        @compute @workgroup_size(1)
+        fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+            let value = vec4(vec3<f32>(id), 1.0);
+            textureStore(rgba, vec2(0), value);
+            textureStore(red, vec2(0), value);
+            textureStore(rgb, vec2(0), value);
+            textureStore(rg, vec2(0), value);
+        }
+        ",
+    )
+    .unwrap();
+    let info = valid::Validator::new(ValidationFlags::all(), Capabilities::default())
+        .validate(&module)
+        .unwrap();
+    let mut output = String::new();
+    super::Writer::new(&mut output, &Options::default())
+        .write(&module, &info)
+        .unwrap();
+
+    // Each store keeps only the components the texture's element type has.
+    let line = |needle: &str| output.lines().find(|line| line.contains(needle)).unwrap();
+    assert!(line("    rgba[").ends_with(" = value;"));
+    assert!(line("    red[").ends_with(" = value.x;"));
+    assert!(line("    rgb[").ends_with(" = value.xyz;"));
+    assert!(line("    rg[").ends_with(" = value.xy;"));
+}
+
+#[cfg(feature = "wgsl-in")]
+#[test]
+fn early_depth_test() {
+    use crate::valid::{Capabilities, ValidationFlags};
+
+    let module = crate::front::wgsl::parse_str(
+        "
+        struct Output {
+            @builtin(frag_depth) depth: f32,
+            @location(0) color: vec4<f32>,
+        }
+
+        @fragment @early_depth_test(less_equal)
+        fn forced() -> @location(0) vec4<f32> {
+            return vec4(1.0);
+        }
+
+        @fragment @early_depth_test(greater_equal)
+        fn direct(@builtin(position) position: vec4<f32>) -> @builtin(frag_depth) f32 {
+            return position.z;
+        }
+
+        @fragment @early_depth_test(less_equal)
+        fn structured(@builtin(position) position: vec4<f32>) -> Output {
+            return Output(position.z, vec4(1.0));
+        }
+        ",
+    )
+    .unwrap();
+    let info = valid::Validator::new(ValidationFlags::all(), Capabilities::default())
+        .validate(&module)
+        .unwrap();
+    let mut output = String::new();
+    super::Writer::new(&mut output, &Options::default())
+        .write(&module, &info)
+        .unwrap();
+
+    assert_eq!(output.matches("[earlydepthstencil]").count(), 1);
+    assert!(output.contains("[earlydepthstencil]\nfloat4 forced("));
+    assert!(output.contains(": SV_DepthGreaterEqual"));
+    assert!(output.contains(": SV_DepthLessEqual"));
+    // The depth writers' shared struct keeps the plain semantic.
+    assert!(output.contains("float depth : SV_Depth;"));
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn f16() {
+    let fixture = crate::test_util::Fixture::wgsl_with_capabilities(
+        "
+        enable f16;
+
+        var<private> v: vec3<f16>;
+
+        @compute @workgroup_size(1)
+        fn main() {
+            let x = 1.5h;
+            v = vec3h(x) * f16(2.0);
+        }
+        ",
+        valid::Capabilities::FLOAT16,
+    );
+    fixture
+        .to_hlsl(&Default::default())
+        .assert_contains("static half3 v")
+        .assert_contains("1.5h");
+    fixture
+        .to_hlsl(&Options {
+            shader_model: ShaderModel::V6_2,
+            ..Default::default()
+        })
+        .assert_contains("static float16_t3 v")
+        .assert_contains("1.5h");
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn int64() {
+    let fixture = crate::test_util::Fixture::wgsl_with_capabilities(
+        "
+        var<private> v: vec2<i64>;
+        var<private> u: u64;
+
+        @compute @workgroup_size(1)
+        fn main() {
+            v = vec2(1li, v.y) * 3li;
+            u = 4lu + u64(v.x);
+        }
+        ",
+        valid::Capabilities::SHADER_INT64,
+    );
+    fixture
+        .to_hlsl(&Default::default())
+        .assert_contains("static int64_t2 v")
+        .assert_contains("static uint64_t u")
+        .assert_contains("4uL");
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn clamp_to_saturate() {
+    let fixture = crate::test_util::Fixture::wgsl(
+        "
+        const ONE = 1.0;
+
+        @group(0) @binding(0) var<storage, read_write> out: vec4<f32>;
+
+        @compute @workgroup_size(1)
+        fn main() {
+            let a = clamp(out.x, 0.0, 1.0);
+            let b = clamp(out.yz, vec2(0.0), vec2(ONE));
+            let c = clamp(out.w, 0.0, 2.0);
+            out = vec4(a, b, c);
+        }
+        ",
+    );
+
+    // Only the clamps to `[0, 1]` become `saturate`.
+    let output = fixture.to_hlsl(&Default::default());
+    assert_eq!(output.as_str().matches("saturate(").count(), 2);
+    assert_eq!(output.as_str().matches("clamp(").count(), 1);
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn subgroup_operations() {
+    let fixture = crate::test_util::Fixture::wgsl_with_capabilities(
+        "
+        enable subgroups;
+
+        @group(0) @binding(0) var<storage, read_write> out: array<u32, 4>;
+
+        @compute @workgroup_size(64)
+        fn main(
+            @builtin(local_invocation_index) index: u32,
+            @builtin(subgroup_size) size: u32,
+            @builtin(subgroup_invocation_id) lane: u32,
+        ) {
+            out[0] = subgroupBallot(index < 16u).x;
+            out[1] = subgroupXor(index) + subgroupExclusiveMul(index);
+            out[2] = subgroupShuffleUp(index, 1u);
+            out[3] = size + lane;
+        }
+        ",
+        valid::Capabilities::SUBGROUP,
+    );
+    fixture
+        .to_hlsl(&Options {
+            shader_model: ShaderModel::V6_0,
+            ..Default::default()
+        })
+        .assert_contains("uint size = WaveGetLaneCount();")
+        .assert_contains("uint lane = WaveGetLaneIndex();")
+        .assert_contains("WaveActiveBallot(")
+        .assert_contains("WaveActiveBitXor(")
+        .assert_contains("WavePrefixProduct(")
+        .assert_contains("WaveGetLaneIndex() - ");
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn entry_point_io() {
+    let fixture = crate::test_util::Fixture::wgsl(
+        "
+        struct VsOut {
+            @builtin(position) position: vec4<f32>,
+            @location(1) color: vec4<f32>,
+            @location(0) uv: vec2<f32>,
+        }
+
+        @vertex
+        fn vs(@location(0) pos: vec4<f32>) -> VsOut {
+            return VsOut(pos, vec4(1.0), vec2(0.0));
+        }
+
+        @fragment
+        fn fs(@location(0) tex_coord: vec2<f32>, @location(1) tint: vec4<f32>) -> @location(0) vec4<f32> {
+            return tint * tex_coord.x;
+        }
+        ",
+    );
+
+    fixture
+        .to_hlsl(&Default::default())
+        .assert_contains("struct VertexOutput_vs {")
+        .assert_contains("struct FragmentInput_fs {");
+    fixture
+        .to_hlsl(&Options {
+            entry_point_io: super::EntryPointIo::Direct,
+            ..Default::default()
+        })
+        .assert_not_contains("VertexOutput_vs")
+        .assert_not_contains("FragmentInput_fs")
+        .assert_contains("VsOut vs(float4 pos : LOC0)")
+        .assert_contains("float4 fs(float2 tex_coord : LOC0, float4 tint : LOC1) : SV_Target0");
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn constant_buffer_style() {
+    use super::ConstantBufferStyle;
+
+    let fixture = crate::test_util::Fixture::wgsl(
+        "
+        struct Light { color: vec3<f32>, intensity: f32 }
+        struct Scene { view: mat4x4<f32>, lights: array<Light, 2> }
+
+        @group(0) @binding(0) var<uniform> scene: Scene;
+        @group(0) @binding(1) var<uniform> tint: vec4<f32>;
+
+        @fragment
+        fn main() -> @location(0) vec4<f32> {
+            return vec4(scene.lights[1].color, 1.0) * scene.view[0] * tint;
+        }
+        ",
+    );
+
+    let block = fixture.to_hlsl(&Default::default());
+    block
+        .assert_contains("cbuffer scene : register(b0) { Scene scene; }")
+        .assert_contains("cbuffer tint : register(b1) { float4 tint; }");
+
+    // Only struct types are templated; the accesses are the same either way.
+    let template = fixture.to_hlsl(&Options {
+        constant_buffer_style: ConstantBufferStyle::Template,
+        ..Default::default()
+    });
+    template
+        .assert_contains("ConstantBuffer<Scene> scene : register(b0);")
+        .assert_contains("cbuffer tint : register(b1) { float4 tint; }")
+        .assert_not_contains("cbuffer scene");
+    let body = |output: &crate::test_util::Output| {
+        let text = output.as_str();
+        text[text.find("float4 main()").unwrap()..].to_string()
+    };
+    assert_eq!(body(&block), body(&template));
+
+    let error = super::Writer::new(
+        &mut String::new(),
+        &Options {
+            shader_model: ShaderModel::V5_0,
+            constant_buffer_style: ConstantBufferStyle::Template,
+            ..Default::default()
+        },
+    )
+    .write(&fixture.module, &fixture.info)
+    .unwrap_err();
+    assert!(matches!(error, Error::ShaderModelTooLow { .. }));
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn view_index() {
+    let fixture = crate::test_util::Fixture::wgsl_with_capabilities(
+        "
+        @fragment
+        fn main(@builtin(view_index) view: i32) -> @location(0) vec4<f32> {
+            return vec4(f32(view));
+        }
+        ",
+        valid::Capabilities::MULTIVIEW,
+    );
+
+    fixture
+        .to_hlsl(&Options {
+            shader_model: ShaderModel::V6_1,
+            ..Default::default()
+        })
+        .assert_contains(": SV_ViewID");
+    let error = super::Writer::new(&mut String::new(), &Default::default())
+        .write(&fixture.module, &fixture.info)
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        Error::ShaderModelTooLow {
+            required: ShaderModel::V6_1,
+            ..
+        }
+    ));
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn storage_offsets() {
+    let fixture = crate::test_util::Fixture::wgsl(
+        "
+        struct Inner { pad: vec3<f32>, values: array<vec2<f32>, 4> }
+        struct Middle { pad: f32, inner: Inner }
+        struct Outer { pad: vec4<f32>, middle: array<Middle, 2> }
+        @group(0) @binding(0) var<storage, read_write> data: Outer;
+
+        @compute @workgroup_size(1)
+        fn main(@builtin(local_invocation_index) index: u32) {
+            data.middle[1].inner.values[2].y = 1.0;
+            data.middle[index].inner.values[3].x = 2.0;
+        }
+        ",
+    );
+
+    // Constant offsets are summed, leaving one constant term per address:
+    // `middle` is at 16, each `Middle` takes 64 bytes, `inner` is at 16 in
+    // it, and `values` is at 16 in that, past the `vec3` rounded up to the
+    // array's alignment.
+    fixture
+        .to_hlsl(&Default::default())
+        .assert_contains("data.Store(132, asuint(1.0));")
+        .assert_contains("data.Store(index*64+72, asuint(2.0));");
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn repeated_array_elements() {
+    let fixture = crate::test_util::Fixture::wgsl(
+        "
+        const ones = array<i32, 4>(1, 1, 1, 1);
+        const zeros = array<f32, 4>(0.0, 0.0, 0.0, 0.0);
+
+        @compute @workgroup_size(1)
+        fn main() {
+            var a = ones;
+            var c = zeros;
+        }
+        ",
+    );
+    fixture
+        .to_hlsl(&Default::default())
+        .assert_contains("(1, 1, 1, 1)")
+        .assert_contains("zeros[4] = (float[4])0;");
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn non_uniform_binding_array_index() {
+    let fixture = crate::test_util::Fixture::wgsl_with_capabilities(
+        "
+        enable nonuniform_indexing;
+
+        @group(0) @binding(0) var textures: binding_array<texture_2d<f32>, 4>;
+        @group(0) @binding(1) var samp: sampler;
+        @group(0) @binding(2) var<uniform> which: u32;
+
+        @fragment
+        fn main(@location(0) uv: vec2<f32>) -> @location(0) vec4<f32> {
+            return textureSample(textures[nonUniform(which)], samp, uv);
+        }
+        ",
+        valid::Capabilities::SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING,
+    );
+    fixture
+        .to_hlsl(&Default::default())
+        .assert_contains("[NonUniformResourceIndex(");
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn coherent_storage() {
+    let fixture = crate::test_util::Fixture::wgsl(
+        "
+        @coherent @group(0) @binding(0) var<storage, read_write> data: array<u32>;
+        @group(0) @binding(1) var<storage, read_write> plain: array<u32>;
+        @coherent @group(0) @binding(2) var image: texture_storage_2d<r32uint, write>;
+        @group(0) @binding(3) var plain_image: texture_storage_2d<r32uint, write>;
+
+        @compute @workgroup_size(64)
+        fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+            data[id.x] = plain[id.x];
+            plain[id.x] = data[id.x + 1u];
+            textureStore(image, vec2<i32>(id.xy), vec4<u32>(data[id.x]));
+            textureStore(plain_image, vec2<i32>(id.xy), vec4<u32>(plain[id.x]));
+        }
+        ",
+    );
+    fixture
+        .to_hlsl(&Default::default())
+        .assert_contains("globallycoherent RWByteAddressBuffer data")
+        .assert_not_contains("globallycoherent RWByteAddressBuffer plain")
+        .assert_contains("globallycoherent RWTexture2D<uint> image")
+        .assert_not_contains("globallycoherent RWTexture2D<uint> plain_image");
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn image_load_read_zero_skip_write() {
+    use crate::proc::BoundsCheckPolicy;
+
+    // Texel loads that need every kind of check: level of detail, array
+    // layer, sample index, and coordinates.
+    let fixture = crate::test_util::Fixture::wgsl(
+        "
+        @group(0) @binding(0) var image_1d: texture_1d<f32>;
+        @group(0) @binding(1) var image_2d_array: texture_2d_array<f32>;
+        @group(0) @binding(2) var image_multisampled: texture_multisampled_2d<f32>;
+        @group(0) @binding(3) var image_3d: texture_3d<f32>;
+
+        @fragment
+        fn main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+            let coords = vec2<i32>(position.xy);
+            let level = i32(position.z);
+            return textureLoad(image_1d, coords.x, level)
+                + textureLoad(image_2d_array, coords, 1, level)
+                + textureLoad(image_multisampled, coords, 2)
+                + textureLoad(image_3d, vec3(coords, 0), level);
+        }
+        ",
+    );
+    let options = Options {
+        bounds_check_policies: proc::BoundsCheckPolicies {
+            image_load: BoundsCheckPolicy::ReadZeroSkipWrite,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    fixture
+        .to_hlsl(&options)
+        .assert_contains(
+            "(uint(level) < NagaNumLevels1D(image_1d) \
+             && uint(coords.x) < NagaMipDimensions1D(image_1d, level) ? ",
+        )
+        .assert_contains(
+            "(uint(level) < NagaNumLevels2DArray(image_2d_array) \
+             && uint(1) < NagaNumLayers2DArray(image_2d_array) \
+             && all(uint2(coords) < NagaMipDimensions2DArray(image_2d_array, level)) ? ",
+        )
+        .assert_contains(
+            "(uint(2) < NagaMSNumSamples2D(image_multisampled) \
+             && all(uint2(coords) < NagaMSDimensions2D(image_multisampled)) ? ",
+        )
+        .assert_contains("all(uint3(int3(coords, 0)) < NagaMipDimensions3D(image_3d, level)) ? ")
+        .assert_contains(" : (float4)0)")
+        // The layer count follows the size in `GetDimensions`.
+        .assert_contains("tex.GetDimensions(0, ret.x, ret.y, ret.z, ret.w);\n    return ret.z;");
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn image_atomics() {
+    let fixture = crate::test_util::Fixture::wgsl_with_capabilities(
+        "
+        @group(0) @binding(0) var image: texture_storage_2d<r32uint, atomic>;
+        @group(0) @binding(1) var layers: texture_storage_2d_array<r32sint, atomic>;
+
+        @compute @workgroup_size(1)
+        fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+            textureAtomicMax(image, id.xy, 1u);
+            textureAtomicSub(layers, id.xy, 2, 3);
+        }
+        ",
+        valid::Capabilities::TEXTURE_ATOMIC,
+    );
+    fixture
+        .to_hlsl(&Default::default())
+        .assert_contains("InterlockedMax(image[id.xy], 1u);")
+        .assert_contains("InterlockedAdd(layers[int3(id.xy, 2)], -3);");
+}
@@ -16,26 +16,18 @@ pub mod spv;
 #[cfg(feature = "wgsl-out")]
 pub mod wgsl;
 
-const COMPONENTS: &[char] = &['x', 'y', 'z', 'w'];
-const INDENT: &str = "    ";
-const BAKE_PREFIX: &str = "_e";
+mod pipeline;
+pub mod text;
 
-type NeedBakeExpressions = crate::FastHashSet<crate::Handle<crate::Expression>>;
+pub use pipeline::{PipelineError, PipelineOptions};
 
-#[derive(Clone, Copy)]
-struct Level(usize);
+#[allow(unused_imports)] // unused if no text backend is enabled
+use text::{Level, INDENT};
 
-impl Level {
-    const fn next(&self) -> Self {
-        Level(self.0 + 1)
-    }
-}
+const COMPONENTS: &[char] = &['x', 'y', 'z', 'w'];
+const BAKE_PREFIX: &str = "_e";
 
-impl std::fmt::Display for Level {
-    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
-        (0..self.0).try_for_each(|_| formatter.write_str(INDENT))
-    }
-}
+type NeedBakeExpressions = crate::FastHashSet<crate::Handle<crate::Expression>>;
 
 /// Whether we're generating an entry point or a regular function.
 ///
@@ -94,6 +86,14 @@ impl FunctionCtx<'_> {
         self.info[handle].ty.inner_with(types)
     }
 
+    /// Returns the function being written.
+    fn function<'m>(&self, module: &'m crate::Module) -> &'m crate::Function {
+        match self.ty {
+            FunctionType::Function(handle) => &module.functions[handle],
+            FunctionType::EntryPoint(idx) => &module.entry_points[idx as usize].function,
+        }
+    }
+
     /// Helper method that generates a [`NameKey`](crate::proc::NameKey) for a local in the current function
     const fn name_key(&self, local: crate::Handle<crate::LocalVariable>) -> crate::proc::NameKey {
         match self.ty {
@@ -168,8 +168,8 @@ impl crate::Expression {
             crate::Expression::Access { .. } | crate::Expression::AccessIndex { .. } => usize::MAX,
             // sampling may use the control flow, and image ops look better by themselves
             crate::Expression::ImageSample { .. } | crate::Expression::ImageLoad { .. } => 1,
-            // derivatives use the control flow
-            crate::Expression::Derivative { .. } => 1,
+            // derivatives and subgroup operations use the control flow
+            crate::Expression::Derivative { .. } | crate::Expression::SubgroupOperation { .. } => 1,
             // TODO: We need a better fix for named `Load` expressions
             // More info - https://github.com/gfx-rs/naga/pull/914
             // And https://github.com/gfx-rs/naga/issues/910
@@ -218,6 +218,110 @@ const fn vector_size_str(size: crate::VectorSize) -> &'static str {
     }
 }
 
+/// Return true if clamping a float to `min` and `max` is the same as
+/// [`Saturate`]: that is, if they are 0 and 1, or vectors of them.
+///
+/// HLSL and MSL have a `saturate` function that costs nothing on most
+/// hardware, and `clamp(x, 0.0, 1.0)` is how other languages spell it.
+/// WGSL leaves the result of clamping a NaN indeterminate, so either way is
+/// faithful to it; `saturate` and SPIR-V's `NClamp` both return 0.
+///
+/// [`Saturate`]: crate::MathFunction::Saturate
+fn clamps_to_unit_interval(
+    module: &crate::Module,
+    expressions: &crate::Arena<crate::Expression>,
+    min: Option<crate::Handle<crate::Expression>>,
+    max: Option<crate::Handle<crate::Expression>>,
+) -> bool {
+    let (Some(min), Some(max)) = (min, max) else {
+        return false;
+    };
+    float_constant(module, expressions, min) == Some(0.0)
+        && float_constant(module, expressions, max) == Some(1.0)
+}
+
+/// Return the value of `expr`, if it is a float constant, or a vector whose
+/// components are all the same float constant.
+fn float_constant(
+    module: &crate::Module,
+    expressions: &crate::Arena<crate::Expression>,
+    expr: crate::Handle<crate::Expression>,
+) -> Option<f64> {
+    match expressions[expr] {
+        crate::Expression::Literal(literal) => match literal {
+            crate::Literal::F64(value) => Some(value),
+            crate::Literal::F32(value) => Some(value.into()),
+            crate::Literal::F16(bits) => Some(crate::proc::f16_to_f32(bits).into()),
+            _ => None,
+        },
+        crate::Expression::ZeroValue(ty) => match module.types[ty].inner {
+            crate::TypeInner::Scalar {
+                kind: crate::ScalarKind::Float,
+                ..
+            }
+            | crate::TypeInner::Vector {
+                kind: crate::ScalarKind::Float,
+                ..
+            } => Some(0.0),
+            _ => None,
+        },
+        crate::Expression::Splat { value, .. } => float_constant(module, expressions, value),
+        crate::Expression::Compose { ref components, .. } => {
+            let (&first, rest) = components.split_first()?;
+            let value = float_constant(module, expressions, first)?;
+            rest.iter()
+                .all(|&component| float_constant(module, expressions, component) == Some(value))
+                .then_some(value)
+        }
+        crate::Expression::Constant(handle) => {
+            let constant = &module.constants[handle];
+            if constant.r#override != crate::Override::None {
+                return None;
+            }
+            float_constant(module, &module.const_expressions, constant.init)
+        }
+        _ => None,
+    }
+}
+
+/// Return the struct that `AccessIndex { base, .. }` picks a member of, if
+/// `base` is a struct in a constant expression.
+///
+/// Constant expressions have no type resolutions to consult, so this only
+/// follows the expressions that spell out their type, and accesses of those.
+/// Override expressions only pick members of constants, which do.
+fn const_access_struct(
+    module: &crate::Module,
+    expressions: &crate::Arena<crate::Expression>,
+    base: crate::Handle<crate::Expression>,
+) -> Option<crate::Handle<crate::Type>> {
+    fn ty_of(
+        module: &crate::Module,
+        expressions: &crate::Arena<crate::Expression>,
+        expr: crate::Handle<crate::Expression>,
+    ) -> Option<crate::Handle<crate::Type>> {
+        match expressions[expr] {
+            crate::Expression::Constant(handle) => Some(module.constants[handle].ty),
+            crate::Expression::ZeroValue(ty)
+            | crate::Expression::Compose { ty, .. }
+            | crate::Expression::Repeat { ty, .. } => Some(ty),
+            crate::Expression::AccessIndex { base, index } => {
+                match module.types[ty_of(module, expressions, base)?].inner {
+                    crate::TypeInner::Struct { ref members, .. } => {
+                        Some(members[index as usize].ty)
+                    }
+                    crate::TypeInner::Array { base, .. } => Some(base),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    let ty = ty_of(module, expressions, base)?;
+    matches!(module.types[ty].inner, crate::TypeInner::Struct { .. }).then_some(ty)
+}
+
 impl crate::TypeInner {
     const fn is_handle(&self) -> bool {
         match *self {
@@ -227,6 +331,38 @@ impl crate::TypeInner {
     }
 }
 
+/// How a backend should write a fragment entry point's [`EarlyDepthTest`].
+///
+/// [`EarlyDepthTest`]: crate::EarlyDepthTest
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum EarlyDepth {
+    /// Run the depth and stencil tests before the entry point.
+    Force,
+    /// The entry point writes depth, but only in this direction, so the
+    /// tests can still run early when their outcome is already known.
+    Conservative(crate::ConservativeDepth),
+}
+
+impl crate::EntryPoint {
+    /// Decide how to write this entry point's `early_depth_test`, given what
+    /// `info` says it does.
+    ///
+    /// A conservative depth only means something if the entry point writes
+    /// depth. If it doesn't, and doesn't discard either, forcing the tests
+    /// to run early can't change its results, so that is what this asks for
+    /// instead. Forcing them otherwise would drop its depth writes and apply
+    /// the tests to fragments it discards.
+    fn early_depth(&self, info: &crate::valid::FunctionInfo) -> Option<EarlyDepth> {
+        let test = self.early_depth_test?;
+        match test.conservative {
+            None => Some(EarlyDepth::Force),
+            Some(conservative) if info.writes_depth => Some(EarlyDepth::Conservative(conservative)),
+            Some(_) if info.allows_early_depth_test() => Some(EarlyDepth::Force),
+            Some(_) => None,
+        }
+    }
+}
+
 impl crate::Statement {
     /// Returns true if the statement directly terminates the current block.
     ///
@@ -240,6 +376,81 @@ impl crate::Statement {
             _ => false,
         }
     }
+
+    /// Returns the blocks directly nested in the statement.
+    fn child_blocks(&self) -> Vec<&crate::Block> {
+        match *self {
+            crate::Statement::Block(ref block) => vec![block],
+            crate::Statement::If {
+                ref accept,
+                ref reject,
+                ..
+            } => vec![accept, reject],
+            crate::Statement::Switch { ref cases, .. } => {
+                cases.iter().map(|case| &case.body).collect()
+            }
+            crate::Statement::Loop {
+                ref body,
+                ref continuing,
+                ..
+            } => vec![body, continuing],
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Statements of a block, with the locals whose scope starts at the first
+/// of them, as `Block::scoped_runs` returns them.
+type ScopedRun<'a> = (
+    Vec<crate::Handle<crate::LocalVariable>>,
+    &'a [crate::Statement],
+);
+
+impl crate::Block {
+    /// Returns the locals scoped to blocks nested somewhere inside this one,
+    /// not counting its own.
+    ///
+    /// Backends declare these where their scope begins instead of at the
+    /// top of the function. See [`Block::locals`](crate::Block::locals).
+    fn nested_locals(&self) -> crate::FastHashSet<crate::Handle<crate::LocalVariable>> {
+        let mut locals = crate::FastHashSet::default();
+        let mut blocks: Vec<_> = self
+            .iter()
+            .flat_map(crate::Statement::child_blocks)
+            .collect();
+        while let Some(block) = blocks.pop() {
+            locals.extend(block.locals().iter().map(|scoped| scoped.local));
+            blocks.extend(block.iter().flat_map(crate::Statement::child_blocks));
+        }
+        locals
+    }
+
+    /// Split this block where the scopes of its locals start.
+    ///
+    /// Returns each run of statements, in order, with the locals whose
+    /// scope starts at its first statement, for backends to declare just
+    /// before it. The first run's locals are in scope for the whole block.
+    fn scoped_runs(&self) -> Vec<ScopedRun<'_>> {
+        let start_of = |scoped: &crate::ScopedLocal| scoped.start.min(self.len());
+        let mut starts: Vec<_> = self.locals().iter().map(start_of).collect();
+        starts.push(0);
+        starts.sort_unstable();
+        starts.dedup();
+        starts
+            .iter()
+            .enumerate()
+            .map(|(run, &start)| {
+                let end = starts.get(run + 1).copied().unwrap_or(self.len());
+                let locals = self
+                    .locals()
+                    .iter()
+                    .filter(|scoped| start_of(scoped) == start)
+                    .map(|scoped| scoped.local)
+                    .collect();
+                (locals, &self[start..end])
+            })
+            .collect()
+    }
 }
 
 bitflags::bitflags! {
@@ -23,6 +23,12 @@ pretend that MSL doesn't have all the restrictions it has.
 For the result type, if it's a structure, we re-compose it with a temporary value
 holding the result.
 
+Unlike the HLSL backend, this one has no option to write the IR's signature
+directly, since Metal doesn't allow both forms: vertex attributes need a
+`[[stage_in]]` struct, built-in inputs like `[[position]]` and
+`[[vertex_id]]` must be separate arguments, and several outputs must be
+returned as a struct.
+
 [msl]: https://developer.apple.com/metal/Metal-Shading-Language-Specification.pdf
 */
 
@@ -33,6 +39,9 @@ mod keywords;
 pub mod sampler;
 mod writer;
 
+/// The words the MSL backend never uses as names, renaming whatever the
+/// module calls them.
+pub use keywords::RESERVED;
 pub use writer::Writer;
 
 pub type Slot = u8;
@@ -51,6 +60,7 @@ pub enum BindSamplerTarget {
 #[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
 #[cfg_attr(any(feature = "serialize", feature = "deserialize"), serde(default))]
 pub struct BindTarget {
+    /// The buffer slot, for buffers and acceleration structures.
     pub buffer: Option<Slot>,
     pub texture: Option<Slot>,
     pub sampler: Option<BindSamplerTarget>,
@@ -141,8 +151,10 @@ pub enum Error {
     UnsupportedArrayOf(String),
     #[error("array of type '{0:?}' is not supported")]
     UnsupportedArrayOfType(Handle<crate::Type>),
-    #[error("ray tracing is not supported prior to MSL 2.3")]
+    #[error("ray tracing is not supported prior to MSL 2.4")]
     UnsupportedRayTracing,
+    #[error("member {1} of type {0:?} can't be stored as a packed matrix")]
+    UnsupportedPackedMatrix(Handle<crate::Type>, u32),
 }
 
 #[derive(Clone, Debug, PartialEq, thiserror::Error)]
@@ -203,7 +215,20 @@ pub struct Options {
     #[cfg_attr(feature = "deserialize", serde(default))]
     pub bounds_check_policies: index::BoundsCheckPolicies,
     /// Should workgroup variables be zero initialized (by polyfilling)?
+    ///
+    /// Turn this off for modules that have been through
+    /// [`zero_initialize_workgroup_memory`], which does the same in the IR.
+    ///
+    /// [`zero_initialize_workgroup_memory`]: crate::proc::zero_initialize_workgroup_memory
     pub zero_initialize_workgroup_memory: bool,
+    /// Struct members, as (struct type, member index) pairs, whose `mat3x3<f32>`
+    /// should be stored as three tightly packed `packed_float3` columns.
+    ///
+    /// This shrinks the matrix from 48 to 36 bytes, and moves the members that
+    /// follow it, so the host must lay such structs out as described by
+    /// [`TranslationInfo::host_struct_layouts`] instead of by the module.
+    #[cfg_attr(feature = "deserialize", serde(default))]
+    pub packed_matrices: Vec<(Handle<crate::Type>, u32)>,
 }
 
 impl Default for Options {
@@ -216,6 +241,7 @@ impl Default for Options {
             fake_missing_bindings: true,
             bounds_check_policies: index::BoundsCheckPolicies::default(),
             zero_initialize_workgroup_memory: true,
+            packed_matrices: Vec::new(),
         }
     }
 }
@@ -265,6 +291,12 @@ impl Options {
                     crate::BuiltIn::PrimitiveIndex if self.lang_version < (2, 2) => {
                         return Err(Error::UnsupportedAttribute("primitive_id".to_string()));
                     }
+                    // Like the SIMD-group functions.
+                    crate::BuiltIn::SubgroupSize | crate::BuiltIn::SubgroupInvocationId
+                        if self.lang_version < (2, 1) =>
+                    {
+                        return Err(Error::UnsupportedAttribute("simdgroup".to_string()));
+                    }
                     _ => {}
                 }
 
@@ -408,6 +440,23 @@ impl ResolvedBinding {
         }
     }
 
+    /// Returns the slot `try_fmt` would write for this binding, if any.
+    const fn as_slot(&self) -> Option<(SlotKind, Slot)> {
+        match *self {
+            Self::Resource(BindTarget {
+                buffer: Some(id), ..
+            }) => Some((SlotKind::Buffer, id)),
+            Self::Resource(BindTarget {
+                texture: Some(id), ..
+            }) => Some((SlotKind::Texture, id)),
+            Self::Resource(BindTarget {
+                sampler: Some(BindSamplerTarget::Resource(id)),
+                ..
+            }) => Some((SlotKind::Sampler, id)),
+            _ => None,
+        }
+    }
+
     fn try_fmt<W: Write>(&self, out: &mut W) -> Result<(), Error> {
         write!(out, " [[")?;
         match *self {
@@ -437,6 +486,9 @@ impl ResolvedBinding {
                     Bi::WorkGroupId => "threadgroup_position_in_grid",
                     Bi::WorkGroupSize => "dispatch_threads_per_threadgroup",
                     Bi::NumWorkGroups => "threadgroups_per_grid",
+                    // subgroup
+                    Bi::SubgroupSize => "threads_per_simdgroup",
+                    Bi::SubgroupInvocationId => "thread_index_in_simdgroup",
                     Bi::CullDistance | Bi::ViewIndex => {
                         return Err(Error::UnsupportedBuiltIn(built_in))
                     }
@@ -513,14 +565,87 @@ impl ResolvedInterpolation {
     }
 }
 
+/// The argument table a [`Slot`] indexes into.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SlotKind {
+    /// A `[[buffer(n)]]` slot.
+    Buffer,
+    /// A `[[texture(n)]]` slot.
+    Texture,
+    /// A `[[sampler(n)]]` slot.
+    Sampler,
+}
+
+impl SlotKind {
+    /// The name of the attribute, as written in MSL.
+    pub const fn to_msl_name(self) -> &'static str {
+        match self {
+            Self::Buffer => "buffer",
+            Self::Texture => "texture",
+            Self::Sampler => "sampler",
+        }
+    }
+}
+
+/// What the host is expected to bind to an [`EntryPointSlot`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SlotSource {
+    /// The global variable with this resource binding.
+    Resource(crate::ResourceBinding),
+    /// The push constants, see [`EntryPointResources::push_constant_buffer`].
+    PushConstants,
+    /// The sizes of runtime-sized arrays, see [`EntryPointResources::sizes_buffer`].
+    SizesBuffer,
+}
+
+/// A slot that a generated entry point expects to be bound.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct EntryPointSlot {
+    pub kind: SlotKind,
+    /// The first slot used.
+    pub index: Slot,
+    /// The number of consecutive slots used, starting at `index`.
+    ///
+    /// This is only greater than one for binding arrays.
+    pub count: u32,
+    pub source: SlotSource,
+    /// The name of the entry point argument bound to this slot.
+    pub name: String,
+}
+
 /// Information about a translated module that is required
 /// for the use of the result.
 pub struct TranslationInfo {
     /// Mapping of the entry point names. Each item in the array
     /// corresponds to an entry point index.
     ///
+    /// All entry points are written to the same source, so these names are
+    /// unique across it. They may differ from the names in the module, if
+    /// those are Metal keywords or already taken.
+    ///
     ///Note: Some entry points may fail translation because of missing bindings.
     pub entry_point_names: Vec<Result<String, EntryPointError>>,
+    /// The slots each entry point expects to be bound. Each item in the array
+    /// corresponds to an entry point index, and is empty for entry points that
+    /// failed translation.
+    ///
+    /// Inline samplers and bindings faked by [`Options::fake_missing_bindings`]
+    /// don't occupy a slot, and are not listed.
+    pub entry_point_slots: Vec<Vec<EntryPointSlot>>,
+    /// The layouts of the structs that have members listed in
+    /// [`Options::packed_matrices`], which differ from the module's.
+    pub host_struct_layouts: Vec<HostStructLayout>,
+}
+
+/// The layout of a struct whose members' offsets were changed by packing
+/// its matrices, as the host must see it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HostStructLayout {
+    pub ty: Handle<crate::Type>,
+    /// The offset of each member, in bytes.
+    pub member_offsets: Vec<u32>,
+    /// The size of the struct, in bytes.
+    pub span: u32,
 }
 
 pub fn write_string(
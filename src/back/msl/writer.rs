@@ -46,32 +46,44 @@ pub(crate) const FREXP_FUNCTION: &str = "naga_frexp";
 fn put_numeric_type(
     out: &mut impl Write,
     kind: crate::ScalarKind,
+    width: crate::Bytes,
     sizes: &[crate::VectorSize],
 ) -> Result<(), FmtError> {
-    match (kind, sizes) {
-        (kind, &[]) => {
-            write!(out, "{}", kind.to_msl_name())
+    let name = scalar_name(kind, width);
+    match *sizes {
+        [] => {
+            write!(out, "{name}")
         }
-        (kind, &[rows]) => {
+        [rows] => {
             write!(
                 out,
                 "{}::{}{}",
                 NAMESPACE,
-                kind.to_msl_name(),
+                name,
                 back::vector_size_str(rows)
             )
         }
-        (kind, &[rows, columns]) => {
+        [rows, columns] => {
             write!(
                 out,
                 "{}::{}{}x{}",
                 NAMESPACE,
-                kind.to_msl_name(),
+                name,
                 back::vector_size_str(columns),
                 back::vector_size_str(rows)
             )
         }
-        (_, _) => Ok(()), // not meaningful
+        _ => Ok(()), // not meaningful
+    }
+}
+
+/// The MSL name of the scalar type with the given `kind` and `width`.
+const fn scalar_name(kind: crate::ScalarKind, width: crate::Bytes) -> &'static str {
+    match (kind, width) {
+        (crate::ScalarKind::Float, 2) => "half",
+        (crate::ScalarKind::Sint, 8) => "long",
+        (crate::ScalarKind::Uint, 8) => "ulong",
+        _ => kind.to_msl_name(),
     }
 }
 
@@ -96,14 +108,18 @@ impl<'a> Display for TypeContext<'a> {
         }
 
         match ty.inner {
-            crate::TypeInner::Scalar { kind, .. } => put_numeric_type(out, kind, &[]),
-            crate::TypeInner::Atomic { kind, .. } => {
-                write!(out, "{}::atomic_{}", NAMESPACE, kind.to_msl_name())
+            crate::TypeInner::Scalar { kind, width } => put_numeric_type(out, kind, width, &[]),
+            crate::TypeInner::Atomic { kind, width } => {
+                write!(out, "{}::atomic_{}", NAMESPACE, scalar_name(kind, width))
             }
-            crate::TypeInner::Vector { size, kind, .. } => put_numeric_type(out, kind, &[size]),
-            crate::TypeInner::Matrix { columns, rows, .. } => {
-                put_numeric_type(out, crate::ScalarKind::Float, &[rows, columns])
+            crate::TypeInner::Vector { size, kind, width } => {
+                put_numeric_type(out, kind, width, &[size])
             }
+            crate::TypeInner::Matrix {
+                columns,
+                rows,
+                width,
+            } => put_numeric_type(out, crate::ScalarKind::Float, width, &[rows, columns]),
             crate::TypeInner::Pointer { base, space } => {
                 let sub = Self {
                     handle: base,
@@ -119,7 +135,7 @@ impl<'a> Display for TypeContext<'a> {
             crate::TypeInner::ValuePointer {
                 size,
                 kind,
-                width: _,
+                width,
                 space,
             } => {
                 match space.to_msl_name() {
@@ -127,8 +143,8 @@ impl<'a> Display for TypeContext<'a> {
                     None => return Ok(()),
                 };
                 match size {
-                    Some(rows) => put_numeric_type(out, kind, &[rows])?,
-                    None => put_numeric_type(out, kind, &[])?,
+                    Some(rows) => put_numeric_type(out, kind, width, &[rows])?,
+                    None => put_numeric_type(out, kind, width, &[])?,
                 };
 
                 write!(out, "&")
@@ -176,6 +192,7 @@ impl<'a> Display for TypeContext<'a> {
                         let access = if self
                             .access
                             .contains(crate::StorageAccess::LOAD | crate::StorageAccess::STORE)
+                            || self.access.contains(crate::StorageAccess::ATOMIC)
                         {
                             "read_write"
                         } else if self.access.contains(crate::StorageAccess::STORE) {
@@ -317,6 +334,13 @@ pub struct Writer<W> {
     /// Set of (struct type, struct field index) denoting which fields require
     /// padding inserted **before** them (i.e. between fields at index - 1 and index)
     struct_member_pads: FastHashSet<(Handle<crate::Type>, u32)>,
+    /// Set of (struct type, struct field index) denoting which `mat3x3<f32>`
+    /// fields are stored as arrays of three `packed_float3` columns.
+    ///
+    /// See [`Options::packed_matrices`].
+    packed_matrices: FastHashSet<(Handle<crate::Type>, u32)>,
+    /// The layouts of the structs whose matrices we packed, for `TranslationInfo`.
+    host_struct_layouts: Vec<super::HostStructLayout>,
 }
 
 impl crate::ScalarKind {
@@ -590,6 +614,8 @@ impl<W: Write> Writer<W> {
             #[cfg(test)]
             put_block_stack_pointers: Default::default(),
             struct_member_pads: FastHashSet::default(),
+            packed_matrices: FastHashSet::default(),
+            host_struct_layouts: Vec::new(),
         }
     }
 
@@ -722,10 +748,10 @@ impl<W: Write> Writer<W> {
         // coordinates in IR are int, but Metal expects uint
         match *context.resolve_type(expr) {
             crate::TypeInner::Scalar { .. } => {
-                put_numeric_type(&mut self.out, crate::ScalarKind::Uint, &[])?
+                put_numeric_type(&mut self.out, crate::ScalarKind::Uint, 4, &[])?
             }
             crate::TypeInner::Vector { size, .. } => {
-                put_numeric_type(&mut self.out, crate::ScalarKind::Uint, &[size])?
+                put_numeric_type(&mut self.out, crate::ScalarKind::Uint, 4, &[size])?
             }
             _ => return Err(Error::Validation),
         };
@@ -875,7 +901,7 @@ impl<W: Write> Writer<W> {
 
         // Check sample index, if present.
         if let Some(sample) = address.sample {
-            write!(self.out, "uint(")?;
+            write!(self.out, "{conjunction}uint(")?;
             self.put_expression(sample, context, true)?;
             write!(self.out, ") < ")?;
             self.put_expression(image, context, true)?;
@@ -988,6 +1014,9 @@ impl<W: Write> Writer<W> {
         value: Handle<crate::Expression>,
         context: &StatementContext,
     ) -> BackendResult {
+        // Metal's `write` always takes four components of the texture's
+        // channel type, and packs them into the pixel format itself, so the
+        // value needs no conversion.
         match context.expression.policies.image_store {
             proc::BoundsCheckPolicy::Restrict => {
                 // We don't have a restricted level value, because we don't
@@ -1017,6 +1046,90 @@ impl<W: Write> Writer<W> {
         Ok(())
     }
 
+    fn put_image_atomic(
+        &mut self,
+        level: back::Level,
+        image: Handle<crate::Expression>,
+        address: &TexelAddress,
+        fun: crate::AtomicFunction,
+        value: Handle<crate::Expression>,
+        context: &StatementContext,
+    ) -> BackendResult {
+        let method = match fun {
+            crate::AtomicFunction::Add => "fetch_add",
+            crate::AtomicFunction::Subtract => "fetch_sub",
+            crate::AtomicFunction::And => "fetch_and",
+            crate::AtomicFunction::InclusiveOr => "fetch_or",
+            crate::AtomicFunction::ExclusiveOr => "fetch_xor",
+            crate::AtomicFunction::Min => "fetch_min",
+            crate::AtomicFunction::Max => "fetch_max",
+            crate::AtomicFunction::Exchange { compare: None } => "exchange",
+            crate::AtomicFunction::Exchange { .. } => {
+                return Err(Error::FeatureNotImplemented(
+                    "image atomic CompareExchange".to_string(),
+                ));
+            }
+        };
+        if context.expression.lang_version < (3, 1) {
+            return Err(Error::UnsupportedFunction(format!("atomic_{method}")));
+        }
+
+        match context.expression.policies.image_store {
+            proc::BoundsCheckPolicy::Restrict => {
+                write!(self.out, "{level}")?;
+                self.put_expression(image, &context.expression, false)?;
+                write!(self.out, ".atomic_{method}(")?;
+                self.put_restricted_texel_address(image, address, &context.expression)?;
+                write!(self.out, ", ")?;
+                self.put_expression(value, &context.expression, true)?;
+                writeln!(self.out, ");")?;
+            }
+            proc::BoundsCheckPolicy::ReadZeroSkipWrite => {
+                write!(self.out, "{level}if (")?;
+                self.put_image_access_bounds_check(image, address, &context.expression)?;
+                writeln!(self.out, ") {{")?;
+                self.put_unchecked_image_atomic(
+                    level.next(),
+                    image,
+                    address,
+                    method,
+                    value,
+                    context,
+                )?;
+                writeln!(self.out, "{level}}}")?;
+            }
+            proc::BoundsCheckPolicy::Unchecked => {
+                self.put_unchecked_image_atomic(level, image, address, method, value, context)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn put_unchecked_image_atomic(
+        &mut self,
+        level: back::Level,
+        image: Handle<crate::Expression>,
+        address: &TexelAddress,
+        method: &str,
+        value: Handle<crate::Expression>,
+        context: &StatementContext,
+    ) -> BackendResult {
+        write!(self.out, "{level}")?;
+        self.put_expression(image, &context.expression, false)?;
+        write!(self.out, ".atomic_{method}(")?;
+        self.put_cast_to_uint_scalar_or_vector(address.coordinate, &context.expression)?;
+        if let Some(expr) = address.array_index {
+            write!(self.out, ", ")?;
+            self.put_expression(expr, &context.expression, true)?;
+        }
+        write!(self.out, ", ")?;
+        self.put_expression(value, &context.expression, true)?;
+        writeln!(self.out, ");")?;
+
+        Ok(())
+    }
+
     fn put_unchecked_image_store(
         &mut self,
         level: back::Level,
@@ -1259,12 +1372,24 @@ impl<W: Write> Writer<W> {
                         write!(self.out, "{value}{suffix}")?;
                     }
                 }
+                crate::Literal::F16(bits) => {
+                    // Validation rules out infinities and NaNs.
+                    let value = crate::proc::f16_to_f32(bits);
+                    let suffix = if value.fract() == 0.0 { ".0" } else { "" };
+                    write!(self.out, "{value}{suffix}h")?;
+                }
                 crate::Literal::U32(value) => {
                     write!(self.out, "{value}u")?;
                 }
                 crate::Literal::I32(value) => {
                     write!(self.out, "{value}")?;
                 }
+                crate::Literal::U64(value) => {
+                    write!(self.out, "{value}uL")?;
+                }
+                crate::Literal::I64(value) => {
+                    write!(self.out, "{value}L")?;
+                }
                 crate::Literal::Bool(value) => {
                     write!(self.out, "{value}")?;
                 }
@@ -1318,23 +1443,128 @@ impl<W: Write> Writer<W> {
                             if self.struct_member_pads.contains(&(ty, index as u32)) {
                                 write!(self.out, "{{}}, ")?;
                             }
-                            put_expression(self, ctx, component)?;
+                            // packed matrix columns are initialized one by one
+                            if self.packed_matrices.contains(&(ty, index as u32)) {
+                                write!(self.out, "{{")?;
+                                for column in 0..3 {
+                                    if column != 0 {
+                                        write!(self.out, ", ")?;
+                                    }
+                                    write!(self.out, "(")?;
+                                    put_expression(self, ctx, component)?;
+                                    write!(self.out, ")[{column}]")?;
+                                }
+                                write!(self.out, "}}")?;
+                            } else {
+                                put_expression(self, ctx, component)?;
+                            }
                         }
                         write!(self.out, "}}")?;
                     }
                     _ => return Err(Error::UnsupportedCompose(ty)),
                 }
             }
+            crate::Expression::Repeat { ty, value } => {
+                let ty_name = TypeContext {
+                    handle: ty,
+                    gctx: module.to_ctx(),
+                    names: &self.names,
+                    access: crate::StorageAccess::empty(),
+                    binding: None,
+                    first_time: false,
+                };
+                write!(self.out, "{ty_name} {{")?;
+                // An empty initializer list zeroes the whole array.
+                if !crate::proc::is_zero_value(value, expressions) {
+                    let components = crate::proc::repeat_components(ty, value, &module.types);
+                    for (index, component) in components.enumerate() {
+                        if index != 0 {
+                            write!(self.out, ", ")?;
+                        }
+                        put_expression(self, ctx, component)?;
+                    }
+                }
+                write!(self.out, "}}")?;
+            }
             crate::Expression::Splat { size, value } => {
-                let scalar_kind = match *get_expr_ty(ctx, value).inner_with(&module.types) {
-                    crate::TypeInner::Scalar { kind, .. } => kind,
+                let (scalar_kind, width) = match *get_expr_ty(ctx, value).inner_with(&module.types)
+                {
+                    crate::TypeInner::Scalar { kind, width } => (kind, width),
                     _ => return Err(Error::Validation),
                 };
-                put_numeric_type(&mut self.out, scalar_kind, &[size])?;
+                put_numeric_type(&mut self.out, scalar_kind, width, &[size])?;
                 write!(self.out, "(")?;
                 put_expression(self, ctx, value)?;
                 write!(self.out, ")")?;
             }
+            // The rest are only ever override expressions.
+            crate::Expression::Unary { op, expr } => {
+                let op_str = match op {
+                    crate::UnaryOperator::Negate => "-",
+                    crate::UnaryOperator::LogicalNot => "!",
+                    crate::UnaryOperator::BitwiseNot => "~",
+                };
+                write!(self.out, "{op_str}(")?;
+                put_expression(self, ctx, expr)?;
+                write!(self.out, ")")?;
+            }
+            crate::Expression::Binary { op, left, right } => {
+                write!(self.out, "(")?;
+                put_expression(self, ctx, left)?;
+                write!(self.out, " {} ", crate::back::binary_operation_str(op))?;
+                put_expression(self, ctx, right)?;
+                write!(self.out, ")")?;
+            }
+            crate::Expression::As {
+                expr,
+                kind,
+                convert,
+            } => {
+                let (src_kind, src_width) = match *get_expr_ty(ctx, expr).inner_with(&module.types)
+                {
+                    crate::TypeInner::Scalar { kind, width } => (kind, width),
+                    _ => return Err(Error::Validation),
+                };
+                let is_bool_cast =
+                    kind == crate::ScalarKind::Bool || src_kind == crate::ScalarKind::Bool;
+                let op = match convert {
+                    Some(w) if w == src_width || is_bool_cast => "static_cast",
+                    Some(_) => return Err(Error::Validation),
+                    None => "as_type",
+                };
+                write!(self.out, "{op}<")?;
+                put_numeric_type(&mut self.out, kind, convert.unwrap_or(src_width), &[])?;
+                write!(self.out, ">(")?;
+                put_expression(self, ctx, expr)?;
+                write!(self.out, ")")?;
+            }
+            crate::Expression::Select {
+                condition,
+                accept,
+                reject,
+            } => {
+                write!(self.out, "(")?;
+                put_expression(self, ctx, condition)?;
+                write!(self.out, " ? ")?;
+                put_expression(self, ctx, accept)?;
+                write!(self.out, " : ")?;
+                put_expression(self, ctx, reject)?;
+                write!(self.out, ")")?;
+            }
+            crate::Expression::AccessIndex { base, index } => {
+                put_expression(self, ctx, base)?;
+                let base_ty = get_expr_ty(ctx, base);
+                match (base_ty.handle(), base_ty.inner_with(&module.types)) {
+                    (Some(ty), &crate::TypeInner::Struct { .. }) => {
+                        let name = &self.names[&NameKey::StructMember(ty, index)];
+                        write!(self.out, ".{name}")?;
+                    }
+                    (_, &crate::TypeInner::Array { .. }) => {
+                        write!(self.out, ".{WRAPPED_ARRAY_FIELD}[{index}]")?;
+                    }
+                    _ => write!(self.out, "[{index}]")?,
+                }
+            }
             _ => unreachable!(),
         }
 
@@ -1376,6 +1606,7 @@ impl<W: Write> Writer<W> {
             | crate::Expression::Constant(_)
             | crate::Expression::ZeroValue(_)
             | crate::Expression::Compose { .. }
+            | crate::Expression::Repeat { .. }
             | crate::Expression::Splat { .. } => {
                 self.put_possibly_const_expression(
                     expr_handle,
@@ -1404,14 +1635,14 @@ impl<W: Write> Writer<W> {
                     )?
                 {
                     write!(self.out, " ? ")?;
-                    self.put_access_chain(expr_handle, policy, context)?;
+                    self.put_value_access_chain(expr_handle, policy, context)?;
                     write!(self.out, " : DefaultConstructible()")?;
 
                     if !is_scoped {
                         write!(self.out, ")")?;
                     }
                 } else {
-                    self.put_access_chain(expr_handle, policy, context)?;
+                    self.put_value_access_chain(expr_handle, policy, context)?;
                 }
             }
             crate::Expression::Swizzle {
@@ -1691,6 +1922,18 @@ impl<W: Write> Writer<W> {
                     _ => false,
                 };
 
+                let (fun, arg1, arg2) = if fun == Mf::Clamp
+                    && back::clamps_to_unit_interval(
+                        context.module,
+                        &context.function.expressions,
+                        arg1,
+                        arg2,
+                    ) {
+                    (Mf::Saturate, None, None)
+                } else {
+                    (fun, arg1, arg2)
+                };
+
                 let fun_name = match fun {
                     // comparison
                     Mf::Abs => "abs",
@@ -1819,8 +2062,13 @@ impl<W: Write> Writer<W> {
                     write!(self.out, ") + 1) % 33) - 1)")?;
                 } else if fun == Mf::FindMsb {
                     let inner = context.resolve_type(arg);
+                    let bits = inner.scalar_width().unwrap_or(32);
 
-                    write!(self.out, "{NAMESPACE}::select(31 - {NAMESPACE}::clz(")?;
+                    write!(
+                        self.out,
+                        "{NAMESPACE}::select({} - {NAMESPACE}::clz(",
+                        bits - 1
+                    )?;
 
                     if let Some(crate::ScalarKind::Sint) = inner.scalar_kind() {
                         write!(self.out, "{NAMESPACE}::select(")?;
@@ -1858,9 +2106,15 @@ impl<W: Write> Writer<W> {
 
                     write!(self.out, "(-1), ")?;
                     self.put_expression(arg, context, true)?;
-                    write!(self.out, " == 0 || ")?;
-                    self.put_expression(arg, context, true)?;
-                    write!(self.out, " == -1)")?;
+                    write!(self.out, " == 0")?;
+                    // Only a signed -1 has no bit that differs from its sign;
+                    // an unsigned 0xFFFFFFFF has its highest bit set.
+                    if let Some(crate::ScalarKind::Sint) = inner.scalar_kind() {
+                        write!(self.out, " || ")?;
+                        self.put_expression(arg, context, true)?;
+                        write!(self.out, " == -1")?;
+                    }
+                    write!(self.out, ")")?;
                 } else if fun == Mf::Unpack2x16float {
                     write!(self.out, "float2(as_type<half2>(")?;
                     self.put_expression(arg, context, false)?;
@@ -1877,6 +2131,29 @@ impl<W: Write> Writer<W> {
                     write!(self.out, "((")?;
                     self.put_expression(arg, context, false)?;
                     write!(self.out, ") * 57.295779513082322865)")?;
+                } else if fun == Mf::ExtractBits || fun == Mf::InsertBits {
+                    // WGSL clamps the offset to the width of the value, and the
+                    // count to the bits above the offset, where Metal leaves
+                    // out-of-range operands undefined.
+                    let (offset, count) = if fun == Mf::ExtractBits {
+                        (arg1.unwrap(), arg2.unwrap())
+                    } else {
+                        (arg2.unwrap(), arg3.unwrap())
+                    };
+                    let bits = context.resolve_type(arg).scalar_width().unwrap_or(32);
+                    write!(self.out, "{NAMESPACE}::{fun_name}(")?;
+                    self.put_expression(arg, context, true)?;
+                    if fun == Mf::InsertBits {
+                        write!(self.out, ", ")?;
+                        self.put_expression(arg1.unwrap(), context, true)?;
+                    }
+                    write!(self.out, ", {NAMESPACE}::min(")?;
+                    self.put_expression(offset, context, true)?;
+                    write!(self.out, ", {bits}u), {NAMESPACE}::min(")?;
+                    self.put_expression(count, context, true)?;
+                    write!(self.out, ", {bits}u - {NAMESPACE}::min(")?;
+                    self.put_expression(offset, context, true)?;
+                    write!(self.out, ", {bits}u)))")?;
                 } else if fun == Mf::Modf || fun == Mf::Frexp {
                     write!(self.out, "{fun_name}")?;
                     self.put_call_parameters(iter::once(arg), context)?;
@@ -1912,19 +2189,25 @@ impl<W: Write> Writer<W> {
                         Some(_) => return Err(Error::Validation),
                         None => "as_type",
                     };
+                    let width = convert.unwrap_or(src_width);
                     write!(self.out, "{op}<")?;
                     match *context.resolve_type(expr) {
                         crate::TypeInner::Vector { size, .. } => {
-                            put_numeric_type(&mut self.out, kind, &[size])?
+                            put_numeric_type(&mut self.out, kind, width, &[size])?
                         }
-                        _ => put_numeric_type(&mut self.out, kind, &[])?,
+                        _ => put_numeric_type(&mut self.out, kind, width, &[])?,
                     };
                     write!(self.out, ">(")?;
                     self.put_expression(expr, context, true)?;
                     write!(self.out, ")")?;
                 }
-                crate::TypeInner::Matrix { columns, rows, .. } => {
-                    put_numeric_type(&mut self.out, kind, &[rows, columns])?;
+                crate::TypeInner::Matrix {
+                    columns,
+                    rows,
+                    width,
+                } => {
+                    let width = convert.unwrap_or(width);
+                    put_numeric_type(&mut self.out, kind, width, &[rows, columns])?;
                     write!(self.out, "(")?;
                     self.put_expression(expr, context, true)?;
                     write!(self.out, ")")?;
@@ -1966,7 +2249,11 @@ impl<W: Write> Writer<W> {
                 }
 
                 if !committed {
-                    unimplemented!()
+                    // The `intersector` finds the closest hit in one go, and
+                    // never has a candidate to report.
+                    return Err(Error::FeatureNotImplemented(
+                        "candidate ray intersections".to_string(),
+                    ));
                 }
                 let ty = context.module.special_types.ray_intersection.unwrap();
                 let type_name = &self.names[&NameKey::Type(ty)];
@@ -1997,6 +2284,84 @@ impl<W: Write> Writer<W> {
                 }
                 write!(self.out, "}}")?;
             }
+            // Metal doesn't distinguish non-uniform indices.
+            crate::Expression::NonUniform(value) => {
+                self.put_expression(value, context, is_scoped)?;
+            }
+            crate::Expression::SubgroupOperation { op, argument } => {
+                use crate::{SubgroupOperation as So, SubgroupReduction as Sr};
+                // SIMD-group functions arrived in MSL 2.1 on macOS.
+                if context.lang_version < (2, 1) {
+                    return Err(Error::UnsupportedFunction("simd_*".to_string()));
+                }
+                let reduction_str = |reduction| match reduction {
+                    Sr::Add => "sum",
+                    Sr::Mul => "product",
+                    Sr::Min => "min",
+                    Sr::Max => "max",
+                    Sr::And => "and",
+                    Sr::Or => "or",
+                    Sr::Xor => "xor",
+                };
+                match op {
+                    So::Ballot => {
+                        // Bits 64 and up are always clear: SIMD-groups have at
+                        // most 64 threads.
+                        write!(
+                            self.out,
+                            "{NAMESPACE}::uint4(as_type<{NAMESPACE}::uint2>(static_cast<ulong>({NAMESPACE}::simd_ballot("
+                        )?;
+                        self.put_expression(argument, context, true)?;
+                        write!(self.out, "))), 0u, 0u)")?;
+                    }
+                    So::All | So::Any | So::BroadcastFirst => {
+                        let fun_str = match op {
+                            So::All => "simd_all",
+                            So::Any => "simd_any",
+                            _ => "simd_broadcast_first",
+                        };
+                        write!(self.out, "{NAMESPACE}::{fun_str}")?;
+                        self.put_call_parameters(iter::once(argument), context)?;
+                    }
+                    So::Reduce(reduction) => {
+                        write!(self.out, "{NAMESPACE}::simd_{}", reduction_str(reduction))?;
+                        self.put_call_parameters(iter::once(argument), context)?;
+                    }
+                    So::InclusiveScan(reduction) | So::ExclusiveScan(reduction) => {
+                        // Metal only has prefix sums and products.
+                        if !matches!(reduction, Sr::Add | Sr::Mul) {
+                            return Err(Error::FeatureNotImplemented(format!(
+                                "subgroup scan with {reduction:?}"
+                            )));
+                        }
+                        let kind = match op {
+                            So::InclusiveScan(_) => "inclusive",
+                            _ => "exclusive",
+                        };
+                        write!(
+                            self.out,
+                            "{NAMESPACE}::simd_prefix_{kind}_{}",
+                            reduction_str(reduction)
+                        )?;
+                        self.put_call_parameters(iter::once(argument), context)?;
+                    }
+                    So::Broadcast(index)
+                    | So::Shuffle(index)
+                    | So::ShuffleDown(index)
+                    | So::ShuffleUp(index)
+                    | So::ShuffleXor(index) => {
+                        let fun_str = match op {
+                            So::Broadcast(_) => "simd_broadcast",
+                            So::Shuffle(_) => "simd_shuffle",
+                            So::ShuffleDown(_) => "simd_shuffle_down",
+                            So::ShuffleUp(_) => "simd_shuffle_up",
+                            _ => "simd_shuffle_xor",
+                        };
+                        write!(self.out, "{NAMESPACE}::{fun_str}")?;
+                        self.put_call_parameters([argument, index].into_iter(), context)?;
+                    }
+                }
+            }
         }
         Ok(())
     }
@@ -2221,6 +2586,29 @@ impl<W: Write> Writer<W> {
         Ok(())
     }
 
+    /// Write the access chain `chain`, as an expression in its own right.
+    ///
+    /// This is [`put_access_chain`], except that packed matrices that aren't
+    /// behind a pointer are converted to `float3x3` values.
+    ///
+    /// [`put_access_chain`]: Writer::put_access_chain
+    fn put_value_access_chain(
+        &mut self,
+        chain: Handle<crate::Expression>,
+        policy: index::BoundsCheckPolicy,
+        context: &ExpressionContext,
+    ) -> BackendResult {
+        let is_pointer = matches!(
+            *context.resolve_type(chain),
+            crate::TypeInner::Pointer { .. } | crate::TypeInner::ValuePointer { .. }
+        );
+        if !is_pointer && self.is_packed_matrix(chain, context) {
+            self.put_unpacked_matrix(chain, policy, context)
+        } else {
+            self.put_access_chain(chain, policy, context)
+        }
+    }
+
     /// Write a `[]`-style access of `base` by `index`.
     ///
     /// If `policy` is [`Restrict`], then generate code as needed to force all index
@@ -2291,6 +2679,52 @@ impl<W: Write> Writer<W> {
         Ok(())
     }
 
+    /// Return true if `chain` is a struct member stored as packed matrix columns.
+    ///
+    /// See [`Options::packed_matrices`].
+    fn is_packed_matrix(
+        &self,
+        chain: Handle<crate::Expression>,
+        context: &ExpressionContext,
+    ) -> bool {
+        if let crate::Expression::AccessIndex { base, index } = context.function.expressions[chain]
+        {
+            let base_ty = match context.info[base].ty {
+                TypeResolution::Handle(handle) => match context.module.types[handle].inner {
+                    crate::TypeInner::Pointer { base, .. } => base,
+                    _ => handle,
+                },
+                TypeResolution::Value(crate::TypeInner::Pointer { base, .. }) => base,
+                TypeResolution::Value(_) => return false,
+            };
+            self.packed_matrices.contains(&(base_ty, index))
+        } else {
+            false
+        }
+    }
+
+    /// Write the access chain `chain`, which refers to a packed matrix, as a
+    /// `float3x3` value.
+    ///
+    /// See [`Options::packed_matrices`].
+    fn put_unpacked_matrix(
+        &mut self,
+        chain: Handle<crate::Expression>,
+        policy: index::BoundsCheckPolicy,
+        context: &ExpressionContext,
+    ) -> BackendResult {
+        write!(self.out, "{NAMESPACE}::float3x3(")?;
+        for column in 0..3 {
+            if column != 0 {
+                write!(self.out, ", ")?;
+            }
+            self.put_access_chain(chain, policy, context)?;
+            write!(self.out, "[{column}]")?;
+        }
+        write!(self.out, ")")?;
+        Ok(())
+    }
+
     fn put_load(
         &mut self,
         pointer: Handle<crate::Expression>,
@@ -2343,7 +2777,11 @@ impl<W: Write> Writer<W> {
             // We don't do any dereferencing with `*` here as pointer arguments to functions
             // are done by `&` references and not `*` pointers. These do not need to be
             // dereferenced.
-            self.put_access_chain(pointer, policy, context)?;
+            if self.is_packed_matrix(pointer, context) {
+                self.put_unpacked_matrix(pointer, policy, context)?;
+            } else {
+                self.put_access_chain(pointer, policy, context)?;
+            }
         }
 
         Ok(())
@@ -2463,7 +2901,14 @@ impl<W: Write> Writer<W> {
                 }
             }
 
-            if let Expression::Math { fun, arg, arg1, .. } = *expr {
+            if let Expression::Math {
+                fun,
+                arg,
+                arg1,
+                arg2,
+                ..
+            } = *expr
+            {
                 match fun {
                     crate::MathFunction::Dot => {
                         // WGSL's `dot` function works on any `vecN` type, but Metal's only
@@ -2488,6 +2933,13 @@ impl<W: Write> Writer<W> {
                     crate::MathFunction::FindMsb => {
                         self.need_bake_expressions.insert(arg);
                     }
+                    // The offset is written twice, to clamp the count.
+                    crate::MathFunction::ExtractBits => {
+                        self.need_bake_expressions.insert(arg1.unwrap());
+                    }
+                    crate::MathFunction::InsertBits => {
+                        self.need_bake_expressions.insert(arg2.unwrap());
+                    }
                     crate::MathFunction::Sign => {
                         // WGSL's `sign` function works also on signed ints, but Metal's only
                         // works on floating points, so we emit inline code for integer `sign`
@@ -2522,14 +2974,23 @@ impl<W: Write> Writer<W> {
                 };
                 write!(self.out, "{ty_name}")?;
             }
-            TypeResolution::Value(crate::TypeInner::Scalar { kind, .. }) => {
-                put_numeric_type(&mut self.out, kind, &[])?;
+            TypeResolution::Value(crate::TypeInner::Scalar { kind, width }) => {
+                put_numeric_type(&mut self.out, kind, width, &[])?;
             }
-            TypeResolution::Value(crate::TypeInner::Vector { size, kind, .. }) => {
-                put_numeric_type(&mut self.out, kind, &[size])?;
+            TypeResolution::Value(crate::TypeInner::Vector { size, kind, width }) => {
+                put_numeric_type(&mut self.out, kind, width, &[size])?;
             }
-            TypeResolution::Value(crate::TypeInner::Matrix { columns, rows, .. }) => {
-                put_numeric_type(&mut self.out, crate::ScalarKind::Float, &[rows, columns])?;
+            TypeResolution::Value(crate::TypeInner::Matrix {
+                columns,
+                rows,
+                width,
+            }) => {
+                put_numeric_type(
+                    &mut self.out,
+                    crate::ScalarKind::Float,
+                    width,
+                    &[rows, columns],
+                )?;
             }
             TypeResolution::Value(ref other) => {
                 log::warn!("Type {:?} isn't a known local", other); //TEMP!
@@ -2594,6 +3055,57 @@ impl<W: Write> Writer<W> {
         Ok(())
     }
 
+    /// Declare `locals`, zero-initializing those without an initializer.
+    fn put_locals(
+        &mut self,
+        level: back::Level,
+        locals: &[Handle<crate::LocalVariable>],
+        context: &ExpressionContext,
+    ) -> BackendResult {
+        for &handle in locals {
+            let local = &context.function.local_variables[handle];
+            let name_key = match context.origin {
+                FunctionOrigin::Handle(fun_handle) => NameKey::FunctionLocal(fun_handle, handle),
+                FunctionOrigin::EntryPoint(ep_index) => NameKey::EntryPointLocal(ep_index, handle),
+            };
+            let ty_name = TypeContext {
+                handle: local.ty,
+                gctx: context.module.to_ctx(),
+                names: &self.names,
+                access: crate::StorageAccess::empty(),
+                binding: None,
+                first_time: false,
+            };
+            write!(self.out, "{level}{} {}", ty_name, self.names[&name_key])?;
+            match local.init {
+                Some(value) => {
+                    write!(self.out, " = ")?;
+                    self.put_expression(value, context, true)?;
+                }
+                None => {
+                    write!(self.out, " = {{}}")?;
+                }
+            };
+            writeln!(self.out, ";")?;
+        }
+        Ok(())
+    }
+
+    /// Write `block`, declaring the locals scoped to it where their scope
+    /// starts.
+    fn put_scoped_block(
+        &mut self,
+        level: back::Level,
+        block: &crate::Block,
+        context: &StatementContext,
+    ) -> BackendResult {
+        for (locals, statements) in block.scoped_runs() {
+            self.put_locals(level, &locals, &context.expression)?;
+            self.put_block(level, statements, context)?;
+        }
+        Ok(())
+    }
+
     fn put_block(
         &mut self,
         level: back::Level,
@@ -2670,7 +3182,7 @@ impl<W: Write> Writer<W> {
                 crate::Statement::Block(ref block) => {
                     if !block.is_empty() {
                         writeln!(self.out, "{level}{{")?;
-                        self.put_block(level.next(), block, context)?;
+                        self.put_scoped_block(level.next(), block, context)?;
                         writeln!(self.out, "{level}}}")?;
                     }
                 }
@@ -2682,10 +3194,10 @@ impl<W: Write> Writer<W> {
                     write!(self.out, "{level}if (")?;
                     self.put_expression(condition, &context.expression, true)?;
                     writeln!(self.out, ") {{")?;
-                    self.put_block(level.next(), accept, context)?;
+                    self.put_scoped_block(level.next(), accept, context)?;
                     if !reject.is_empty() {
                         writeln!(self.out, "{level}}} else {{")?;
-                        self.put_block(level.next(), reject, context)?;
+                        self.put_scoped_block(level.next(), reject, context)?;
                     }
                     writeln!(self.out, "{level}}}")?;
                 }
@@ -2717,7 +3229,7 @@ impl<W: Write> Writer<W> {
                             writeln!(self.out)?;
                         }
 
-                        self.put_block(lcase.next(), &case.body, context)?;
+                        self.put_scoped_block(lcase.next(), &case.body, context)?;
                         if !case.fall_through
                             && case.body.last().map_or(true, |s| !s.is_terminator())
                         {
@@ -2735,6 +3247,15 @@ impl<W: Write> Writer<W> {
                     ref continuing,
                     break_if,
                 } => {
+                    // The continuing block comes before the body here, so
+                    // locals scoped to either are declared outside the loop.
+                    let locals: Vec<_> = body
+                        .locals()
+                        .iter()
+                        .chain(continuing.locals())
+                        .map(|scoped| scoped.local)
+                        .collect();
+                    self.put_locals(level, &locals, &context.expression)?;
                     if !continuing.is_empty() || break_if.is_some() {
                         let gate_name = self.namer.call("loop_init");
                         writeln!(self.out, "{level}bool {gate_name} = true;")?;
@@ -2800,6 +3321,21 @@ impl<W: Write> Writer<W> {
                     };
                     self.put_image_store(level, image, &address, value, context)?
                 }
+                crate::Statement::ImageAtomic {
+                    image,
+                    coordinate,
+                    array_index,
+                    fun,
+                    value,
+                } => {
+                    let address = TexelAddress {
+                        coordinate,
+                        array_index,
+                        sample: None,
+                        level: None,
+                    };
+                    self.put_image_atomic(level, image, &address, fun, value, context)?
+                }
                 crate::Statement::Call {
                     function,
                     ref arguments,
@@ -2857,6 +3393,32 @@ impl<W: Write> Writer<W> {
                     result,
                 } => {
                     write!(self.out, "{level}")?;
+                    // Metal only has `min` and `max` for 64-bit atomics, and
+                    // they don't return the original value.
+                    if let crate::TypeInner::Scalar { width: 8, .. } =
+                        *context.expression.resolve_type(value)
+                    {
+                        let key = match *fun {
+                            crate::AtomicFunction::Min => "min",
+                            crate::AtomicFunction::Max => "max",
+                            _ => {
+                                return Err(Error::UnsupportedFunction(format!(
+                                    "64-bit atomic {fun:?}"
+                                )))
+                            }
+                        };
+                        if context.expression.lang_version < (2, 4) {
+                            return Err(Error::UnsupportedFunction(format!("64-bit atomic {key}")));
+                        }
+                        if context.expression.info[result].ref_count != 0 {
+                            return Err(Error::FeatureNotImplemented(
+                                "result of 64-bit atomic min or max".to_string(),
+                            ));
+                        }
+                        self.put_atomic_operation(pointer, key, "", value, &context.expression)?;
+                        writeln!(self.out, ";")?;
+                        continue;
+                    }
                     let res_name = format!("{}{}", back::BAKE_PREFIX, result.index());
                     self.start_baking_expression(result, &context.expression, &res_name)?;
                     self.named_expressions.insert(result, res_name);
@@ -2984,7 +3546,7 @@ impl<W: Write> Writer<W> {
                             self.put_expression(acceleration_structure, &context.expression, true)?;
                             write!(self.out, ", ")?;
                             self.put_expression(descriptor, &context.expression, true)?;
-                            write!(self.out, ".cull_mask);")?;
+                            writeln!(self.out, ".cull_mask);")?;
 
                             write!(self.out, "{level}")?;
                             self.put_expression(query, &context.expression, true)?;
@@ -3068,6 +3630,16 @@ impl<W: Write> Writer<W> {
             write!(self.out, ", ")?;
             self.put_expression(value, &context.expression, true)?;
             writeln!(self.out, ", {NAMESPACE}::memory_order_relaxed);")?;
+        } else if self.is_packed_matrix(pointer, &context.expression) {
+            // Packed columns can't be assigned a `float3x3`, so store the
+            // columns one by one.
+            for column in 0..3 {
+                write!(self.out, "{level}")?;
+                self.put_access_chain(pointer, policy, &context.expression)?;
+                write!(self.out, "[{column}] = (")?;
+                self.put_expression(value, &context.expression, true)?;
+                writeln!(self.out, ")[{column}];")?;
+            }
         } else {
             write!(self.out, "{level}")?;
             self.put_access_chain(pointer, policy, &context.expression)?;
@@ -3096,6 +3668,45 @@ impl<W: Write> Writer<W> {
             &mut self.names,
         );
         self.struct_member_pads.clear();
+        self.packed_matrices.clear();
+        self.host_struct_layouts.clear();
+        for &(ty, index) in options.packed_matrices.iter() {
+            let is_mat3x3 = match module.types.get_handle(ty).map(|ty| &ty.inner) {
+                Ok(&crate::TypeInner::Struct { ref members, .. }) => {
+                    members.get(index as usize).map_or(false, |member| {
+                        module.types[member.ty].inner
+                            == crate::TypeInner::Matrix {
+                                columns: crate::VectorSize::Tri,
+                                rows: crate::VectorSize::Tri,
+                                width: 4,
+                            }
+                    })
+                }
+                _ => false,
+            };
+            if !is_mat3x3 {
+                return Err(Error::UnsupportedPackedMatrix(ty, index));
+            }
+            self.packed_matrices.insert((ty, index));
+        }
+        // The module's layout of any type containing such a struct would be
+        // wrong, so don't allow them.
+        for (_, ty) in module.types.iter() {
+            let contained = match ty.inner {
+                crate::TypeInner::Array { base, .. } => vec![base],
+                crate::TypeInner::Struct { ref members, .. } => {
+                    members.iter().map(|member| member.ty).collect()
+                }
+                _ => continue,
+            };
+            if let Some(&(ty, index)) = self
+                .packed_matrices
+                .iter()
+                .find(|&&(ty, _)| contained.contains(&ty))
+            {
+                return Err(Error::UnsupportedPackedMatrix(ty, index));
+            }
+        }
 
         writeln!(
             self.out,
@@ -3222,6 +3833,12 @@ impl<W: Write> Writer<W> {
     }
 
     fn write_type_defs(&mut self, module: &crate::Module) -> BackendResult {
+        let mut layouter = proc::Layouter::default();
+        if !self.packed_matrices.is_empty() {
+            layouter
+                .update(module.to_ctx())
+                .map_err(|_| Error::Validation)?;
+        }
         for (handle, ty) in module.types.iter() {
             if !ty.needs_alias() {
                 continue;
@@ -3273,6 +3890,12 @@ impl<W: Write> Writer<W> {
                         }
                     }
                 }
+                crate::TypeInner::Struct { ref members, .. }
+                    if (0..members.len() as u32)
+                        .any(|index| self.packed_matrices.contains(&(handle, index))) =>
+                {
+                    self.write_packed_struct(module, &layouter, handle, members)?;
+                }
                 crate::TypeInner::Struct {
                     ref members, span, ..
                 } => {
@@ -3352,18 +3975,16 @@ impl<W: Write> Writer<W> {
             match type_key {
                 &crate::PredeclaredType::ModfResult { size, width }
                 | &crate::PredeclaredType::FrexpResult { size, width } => {
+                    let float_name = match width {
+                        8 => "double",
+                        _ => scalar_name(crate::ScalarKind::Float, width),
+                    };
                     let arg_type_name_owner;
                     let arg_type_name = if let Some(size) = size {
-                        arg_type_name_owner = format!(
-                            "{NAMESPACE}::{}{}",
-                            if width == 8 { "double" } else { "float" },
-                            size as u8
-                        );
+                        arg_type_name_owner = format!("{NAMESPACE}::{}{}", float_name, size as u8);
                         &arg_type_name_owner
-                    } else if width == 8 {
-                        "double"
                     } else {
-                        "float"
+                        float_name
                     };
 
                     let other_type_name_owner;
@@ -3400,6 +4021,72 @@ impl<W: Write> Writer<W> {
         Ok(())
     }
 
+    /// Write the definition of the struct `handle`, some of whose members are
+    /// matrices stored as packed columns, and record its layout.
+    ///
+    /// Packing moves the members that follow a matrix, so the module's offsets
+    /// no longer apply. Instead, we let Metal lay the struct out without any
+    /// explicit padding, and compute the same layout for the host.
+    fn write_packed_struct(
+        &mut self,
+        module: &crate::Module,
+        layouter: &proc::Layouter,
+        handle: Handle<crate::Type>,
+        members: &[crate::StructMember],
+    ) -> BackendResult {
+        let name = &self.names[&NameKey::Type(handle)];
+        writeln!(self.out, "struct {name} {{")?;
+
+        let mut offset = 0;
+        let mut alignment = proc::Alignment::ONE;
+        let mut member_offsets = Vec::with_capacity(members.len());
+        for (index, member) in members.iter().enumerate() {
+            let member_name = &self.names[&NameKey::StructMember(handle, index as u32)];
+            let (size, member_alignment) = if self.packed_matrices.contains(&(handle, index as u32))
+            {
+                writeln!(
+                    self.out,
+                    "{}{NAMESPACE}::packed_float3 {member_name}[3];",
+                    back::INDENT
+                )?;
+                (36, proc::Alignment::FOUR)
+            } else {
+                let ty_name = TypeContext {
+                    handle: member.ty,
+                    gctx: module.to_ctx(),
+                    names: &self.names,
+                    access: crate::StorageAccess::empty(),
+                    binding: None,
+                    first_time: false,
+                };
+                writeln!(self.out, "{}{ty_name} {member_name};", back::INDENT)?;
+                let layout = layouter[member.ty];
+                let size = match module.types[member.ty].inner {
+                    // Metal's 3-component vectors are as large as 4-component ones.
+                    crate::TypeInner::Vector {
+                        size: crate::VectorSize::Tri,
+                        width,
+                        ..
+                    } => 4 * width as u32,
+                    _ => layout.size,
+                };
+                (size, layout.alignment)
+            };
+            offset = member_alignment.round_up(offset);
+            member_offsets.push(offset);
+            offset += size;
+            alignment = alignment.max(member_alignment);
+        }
+        writeln!(self.out, "}};")?;
+
+        self.host_struct_layouts.push(super::HostStructLayout {
+            ty: handle,
+            member_offsets,
+            span: alignment.round_up(offset),
+        });
+        Ok(())
+    }
+
     /// Writes all named constants
     fn write_global_constants(
         &mut self,
@@ -3620,28 +4307,14 @@ impl<W: Write> Writer<W> {
                 result_struct: None,
             };
 
-            for (local_handle, local) in fun.local_variables.iter() {
-                let ty_name = TypeContext {
-                    handle: local.ty,
-                    gctx: module.to_ctx(),
-                    names: &self.names,
-                    access: crate::StorageAccess::empty(),
-                    binding: None,
-                    first_time: false,
-                };
-                let local_name = &self.names[&NameKey::FunctionLocal(fun_handle, local_handle)];
-                write!(self.out, "{}{} {}", back::INDENT, ty_name, local_name)?;
-                match local.init {
-                    Some(value) => {
-                        write!(self.out, " = ")?;
-                        self.put_expression(value, &context.expression, true)?;
-                    }
-                    None => {
-                        write!(self.out, " = {{}}")?;
-                    }
-                };
-                writeln!(self.out, ";")?;
-            }
+            let nested_locals = fun.body.nested_locals();
+            let locals: Vec<_> = fun
+                .local_variables
+                .iter()
+                .map(|(handle, _)| handle)
+                .filter(|handle| !nested_locals.contains(handle))
+                .collect();
+            self.put_locals(back::Level(1), &locals, &context.expression)?;
 
             self.named_expressions.clear();
             self.update_expressions_to_bake(fun, fun_info, &context.expression);
@@ -3651,10 +4324,13 @@ impl<W: Write> Writer<W> {
 
         let mut info = TranslationInfo {
             entry_point_names: Vec::with_capacity(module.entry_points.len()),
+            entry_point_slots: Vec::with_capacity(module.entry_points.len()),
+            host_struct_layouts: std::mem::take(&mut self.host_struct_layouts),
         };
         for (ep_index, ep) in module.entry_points.iter().enumerate() {
             let fun = &ep.function;
             let fun_info = mod_info.get_entry_point(ep_index);
+            let early_depth = ep.early_depth(fun_info);
             let mut ep_error = None;
 
             log::trace!(
@@ -3735,6 +4411,7 @@ impl<W: Write> Writer<W> {
 
             if let Some(err) = ep_error {
                 info.entry_point_names.push(Err(err));
+                info.entry_point_slots.push(Vec::new());
                 continue;
             }
             let fun_name = &self.names[&NameKey::EntryPoint(ep_index as _)];
@@ -3801,8 +4478,9 @@ impl<W: Write> Writer<W> {
             }
 
             // Identify the varyings among the argument values, and emit a
-            // struct type named `<fun>Input` to hold them.
-            let stage_in_name = format!("{fun_name}Input");
+            // struct type named `<fun>Input` to hold them. Other entry points
+            // and the module's own types may already be using that name.
+            let stage_in_name = self.namer.call(&format!("{fun_name}Input"));
             let varyings_member_name = self.namer.call("varyings");
             let mut has_varyings = false;
             if !flattened_arguments.is_empty() {
@@ -3835,7 +4513,7 @@ impl<W: Write> Writer<W> {
 
             // Define a struct type named for the return value, if any, named
             // `<fun>Output`.
-            let stage_out_name = format!("{fun_name}Output");
+            let stage_out_name = self.namer.call(&format!("{fun_name}Output"));
             let result_member_name = self.namer.call("member");
             let result_type_name = match fun.result {
                 Some(ref result) => {
@@ -3890,7 +4568,22 @@ impl<W: Write> Writer<W> {
                         if let Some(array_len) = array_len {
                             write!(self.out, " [{array_len}]")?;
                         }
-                        resolved.try_fmt(&mut self.out)?;
+                        match (binding, early_depth) {
+                            (
+                                &crate::Binding::BuiltIn(crate::BuiltIn::FragDepth),
+                                Some(back::EarlyDepth::Conservative(conservative)),
+                            ) => {
+                                // Metal has no way to promise the depth is
+                                // unchanged.
+                                let depth = match conservative {
+                                    crate::ConservativeDepth::GreaterEqual => "greater",
+                                    crate::ConservativeDepth::LessEqual => "less",
+                                    crate::ConservativeDepth::Unchanged => "any",
+                                };
+                                write!(self.out, " [[depth({depth})]]")?;
+                            }
+                            _ => resolved.try_fmt(&mut self.out)?,
+                        }
                         writeln!(self.out, ";")?;
                     }
 
@@ -3912,6 +4605,9 @@ impl<W: Write> Writer<W> {
             };
 
             // Write the entry point function's name, and begin its argument list.
+            if early_depth == Some(back::EarlyDepth::Force) {
+                write!(self.out, "[[early_fragment_tests]] ")?;
+            }
             writeln!(self.out, "{em_str} {result_type_name} {fun_name}(")?;
             let mut is_first_argument = true;
 
@@ -3979,6 +4675,9 @@ impl<W: Write> Writer<W> {
                 )?;
             }
 
+            // The slots of the arguments below, for `TranslationInfo`.
+            let mut slots = Vec::new();
+
             // Those global variables used by this entry point and its callees
             // get passed as arguments. `Private` globals are an exception, they
             // don't outlive this invocation, so we declare them below as locals
@@ -4117,6 +4816,36 @@ impl<W: Write> Writer<W> {
                     }
                 }
 
+                if let Some((kind, index)) = resolved.as_ref().and_then(|r| r.as_slot()) {
+                    let count = match module.types[var.ty].inner {
+                        crate::TypeInner::BindingArray { size, .. } => {
+                            match (resolved.as_ref().and_then(|r| r.as_bind_target()), size) {
+                                (
+                                    Some(&super::BindTarget {
+                                        binding_array_size: Some(override_size),
+                                        ..
+                                    }),
+                                    _,
+                                ) => override_size,
+                                (_, crate::ArraySize::Constant(size)) => size.get(),
+                                (_, crate::ArraySize::Dynamic) => 1,
+                            }
+                        }
+                        _ => 1,
+                    };
+                    let source = match var.space {
+                        crate::AddressSpace::PushConstant => super::SlotSource::PushConstants,
+                        _ => super::SlotSource::Resource(var.binding.clone().unwrap()),
+                    };
+                    slots.push(super::EntryPointSlot {
+                        kind,
+                        index,
+                        count,
+                        source,
+                        name: self.names[&NameKey::GlobalVariable(handle)].clone(),
+                    });
+                }
+
                 let tyvar = TypedGlobalVariable {
                     module,
                     names: &self.names,
@@ -4159,7 +4888,17 @@ impl<W: Write> Writer<W> {
                 )?;
                 resolved.try_fmt(&mut self.out)?;
                 writeln!(self.out)?;
+                if let Some((kind, index)) = resolved.as_slot() {
+                    slots.push(super::EntryPointSlot {
+                        kind,
+                        index,
+                        count: 1,
+                        source: super::SlotSource::SizesBuffer,
+                        name: "_buffer_sizes".to_string(),
+                    });
+                }
             }
+            info.entry_point_slots.push(slots);
 
             // end of the entry point argument list
             writeln!(self.out, ") {{")?;
@@ -4288,30 +5027,16 @@ impl<W: Write> Writer<W> {
                 result_struct: Some(&stage_out_name),
             };
 
-            // Finally, declare all the local variables that we need
+            // Finally, declare the local variables not scoped to a nested block
             //TODO: we can postpone this till the relevant expressions are emitted
-            for (local_handle, local) in fun.local_variables.iter() {
-                let name = &self.names[&NameKey::EntryPointLocal(ep_index as _, local_handle)];
-                let ty_name = TypeContext {
-                    handle: local.ty,
-                    gctx: module.to_ctx(),
-                    names: &self.names,
-                    access: crate::StorageAccess::empty(),
-                    binding: None,
-                    first_time: false,
-                };
-                write!(self.out, "{}{} {}", back::INDENT, ty_name, name)?;
-                match local.init {
-                    Some(value) => {
-                        write!(self.out, " = ")?;
-                        self.put_expression(value, &context.expression, true)?;
-                    }
-                    None => {
-                        write!(self.out, " = {{}}")?;
-                    }
-                };
-                writeln!(self.out, ";")?;
-            }
+            let nested_locals = fun.body.nested_locals();
+            let locals: Vec<_> = fun
+                .local_variables
+                .iter()
+                .map(|(handle, _)| handle)
+                .filter(|handle| !nested_locals.contains(handle))
+                .collect();
+            self.put_locals(back::Level(1), &locals, &context.expression)?;
 
             self.named_expressions.clear();
             self.update_expressions_to_bake(fun, fun_info, &context.expression);
@@ -4622,3 +5347,470 @@ fn test_stack_size() {
         }
     }
 }
+
+#[cfg(feature = "wgsl-in")]
+#[test]
+fn doubles_unsupported() {
+    let module = crate::front::wgsl::parse_str(
+        "
+        @fragment
+        fn main() -> @location(0) vec4<f32> {
+            let half = f64(0.5);
+            return vec4(f32(half * f64(2.0)));
+        }
+        ",
+    )
+    .unwrap();
+    let info = crate::valid::Validator::new(
+        crate::valid::ValidationFlags::all(),
+        crate::valid::Capabilities::FLOAT64,
+    )
+    .validate(&module)
+    .unwrap();
+    assert!(matches!(
+        super::write_string(&module, &info, &Default::default(), &Default::default()),
+        Err(Error::CapabilityNotSupported(
+            crate::valid::Capabilities::FLOAT64
+        ))
+    ));
+}
+
+#[cfg(feature = "wgsl-in")]
+#[test]
+fn scoped_locals() {
+    let module = crate::front::wgsl::parse_str(
+        "
+        fn f(x: u32) -> u32 {
+            if x > 1u {
+                var a = x;
+                a += 1u;
+                var b = a * 2u;
+                return b;
+            }
+            return x;
+        }
+
+        @compute @workgroup_size(1)
+        fn main() {
+            _ = f(2u);
+        }
+        ",
+    )
+    .unwrap();
+    let info = crate::valid::Validator::new(
+        crate::valid::ValidationFlags::all(),
+        crate::valid::Capabilities::default(),
+    )
+    .validate(&module)
+    .unwrap();
+    let (output, _) =
+        super::write_string(&module, &info, &Default::default(), &Default::default()).unwrap();
+
+    // `a` is in scope for the whole `if`, so it comes first, but `b` is
+    // only declared once `a` has been used.
+    assert!(output.contains("        uint a = {};\n        a = x;\n"));
+    assert!(!output.contains("{\n    uint a"));
+    let declared = |needle| output.find(needle).unwrap();
+    assert!(declared("uint b = {};") > declared("uint _e"));
+}
+
+#[cfg(feature = "wgsl-in")]
+#[test]
+fn early_depth_test() {
+    let module = crate::front::wgsl::parse_str(
+        "
+        struct Output {
+            @builtin(frag_depth) depth: f32,
+            @location(0) color: vec4<f32>,
+        }
+
+        @fragment @early_depth_test(less_equal)
+        fn forced() -> @location(0) vec4<f32> {
+            return vec4(1.0);
+        }
+
+        @fragment @early_depth_test(greater_equal)
+        fn conservative(@builtin(position) position: vec4<f32>) -> Output {
+            return Output(position.z, vec4(1.0));
+        }
+        ",
+    )
+    .unwrap();
+    let info = crate::valid::Validator::new(
+        crate::valid::ValidationFlags::all(),
+        crate::valid::Capabilities::default(),
+    )
+    .validate(&module)
+    .unwrap();
+    let (output, _) =
+        super::write_string(&module, &info, &Default::default(), &Default::default()).unwrap();
+
+    assert_eq!(
+        output.matches("[[early_fragment_tests]] fragment").count(),
+        1
+    );
+    assert!(output.contains("[[early_fragment_tests]] fragment forcedOutput forced("));
+    assert!(output.contains("[[depth(greater)]]"));
+}
+
+#[cfg(feature = "wgsl-in")]
+#[test]
+fn storage_stores() {
+    let module = crate::front::wgsl::parse_str(
+        "
+        @group(0) @binding(0) var rgba: texture_storage_2d<rgba8unorm, write>;
+        @group(0) @binding(1) var red: texture_storage_2d<r16float, write>;
+        @group(0) @binding(2) var rgb: texture_storage_2d<rg11b10float, write>;
+        @group(0) @binding(3) var rg: texture_storage_2d<rg8snorm, write>;
+
+        @compute @workgroup_size(1)
+        fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+            let value = vec4(vec3<f32>(id), 1.0);
+            textureStore(rgba, vec2(0), value);
+            textureStore(red, vec2(0), value);
+            textureStore(rgb, vec2(0), value);
+            textureStore(rg, vec2(0), value);
+        }
+        ",
+    )
+    .unwrap();
+    let info = crate::valid::Validator::new(
+        crate::valid::ValidationFlags::all(),
+        crate::valid::Capabilities::default(),
+    )
+    .validate(&module)
+    .unwrap();
+    let (output, _) =
+        super::write_string(&module, &info, &Default::default(), &Default::default()).unwrap();
+
+    // Metal packs the four components into the pixel format itself.
+    for name in ["rgba", "red", "rgb", "rg"] {
+        assert!(output.contains(&format!("{name}.write(value, ")), "{name}");
+    }
+    assert_eq!(
+        output
+            .matches("metal::texture2d<float, metal::access::write>")
+            .count(),
+        4
+    );
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn packed_matrices() {
+    let fixture = crate::test_util::Fixture::wgsl(
+        "
+        struct Transform {
+            scale: f32,
+            m: mat3x3<f32>,
+            offset: vec3<f32>,
+        }
+        @group(0) @binding(0) var<storage, read_write> t: Transform;
+
+        @compute @workgroup_size(1)
+        fn main() {
+            let m = t.m;
+            t.m = m * 2.0;
+            t.m[1] = t.offset;
+            t.m[2].x = t.scale;
+        }
+        ",
+    );
+    let (ty, _) = fixture
+        .module
+        .types
+        .iter()
+        .find(|(_, ty)| ty.name.as_deref() == Some("Transform"))
+        .unwrap();
+
+    let options = Options {
+        packed_matrices: vec![(ty, 1)],
+        ..Default::default()
+    };
+    fixture
+        .to_msl(&options, &Default::default())
+        .assert_contains("metal::packed_float3 m[3];")
+        .assert_contains("metal::float3x3(t.m[0], t.m[1], t.m[2])")
+        .assert_contains("t.m[0] = (");
+    let (_, translation) = super::write_string(
+        &fixture.module,
+        &fixture.info,
+        &options,
+        &Default::default(),
+    )
+    .unwrap();
+    assert_eq!(
+        translation.host_struct_layouts,
+        [super::HostStructLayout {
+            ty,
+            member_offsets: vec![0, 4, 48],
+            span: 64,
+        }]
+    );
+
+    // Only `mat3x3<f32>` members can be packed.
+    let options = Options {
+        packed_matrices: vec![(ty, 2)],
+        ..Default::default()
+    };
+    assert!(matches!(
+        super::write_string(
+            &fixture.module,
+            &fixture.info,
+            &options,
+            &Default::default()
+        ),
+        Err(Error::UnsupportedPackedMatrix(_, 2))
+    ));
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn entry_point_names() {
+    // `vertex` and `kernel` are Metal keywords, and `drawInput` is the name
+    // the writer would give `draw`'s stage input struct.
+    let fixture = crate::test_util::Fixture::wgsl(
+        "
+        struct drawInput { x: f32 }
+        var<private> p: drawInput;
+
+        @vertex
+        fn vertex(@location(0) v: vec4<f32>) -> @builtin(position) vec4<f32> {
+            return v;
+        }
+
+        @fragment
+        fn draw(@location(0) c: vec4<f32>) -> @location(0) vec4<f32> {
+            return c * p.x;
+        }
+
+        @compute @workgroup_size(1)
+        fn kernel() {}
+
+        @compute @workgroup_size(1)
+        fn other() {}
+        ",
+    );
+    let (source, translation) = super::write_string(
+        &fixture.module,
+        &fixture.info,
+        &Default::default(),
+        &Default::default(),
+    )
+    .unwrap();
+    let names: Vec<_> = translation
+        .entry_point_names
+        .into_iter()
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(names, ["vertex_", "draw", "kernel_", "other"]);
+    for (name, stage) in names.iter().zip(["vertex", "fragment", "kernel", "kernel"]) {
+        let signature = format!(" {name}(");
+        assert!(
+            source
+                .lines()
+                .any(|line| line.starts_with(stage) && line.ends_with(&signature)),
+            "no `{name}` entry point:\n{source}"
+        );
+    }
+    assert_eq!(source.matches("struct drawInput ").count(), 1, "{source}");
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn f16() {
+    let fixture = crate::test_util::Fixture::wgsl_with_capabilities(
+        "
+        enable f16;
+
+        var<private> v: vec3<f16>;
+
+        @compute @workgroup_size(1)
+        fn main() {
+            let x = 1.5h;
+            v = vec3h(x) * f16(2.0);
+        }
+        ",
+        crate::valid::Capabilities::FLOAT16,
+    );
+    fixture
+        .to_msl(&Default::default(), &Default::default())
+        .assert_contains("metal::half3")
+        .assert_contains("1.5h");
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn int64() {
+    let fixture = crate::test_util::Fixture::wgsl_with_capabilities(
+        "
+        var<private> v: vec2<i64>;
+        var<private> u: u64;
+
+        @compute @workgroup_size(1)
+        fn main() {
+            v = vec2(1li, v.y) * 3li;
+            u = 4lu + u64(v.x);
+        }
+        ",
+        crate::valid::Capabilities::SHADER_INT64,
+    );
+    fixture
+        .to_msl(&Default::default(), &Default::default())
+        .assert_contains("metal::long2")
+        .assert_contains("ulong u")
+        .assert_contains("4uL");
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn clamp_to_saturate() {
+    let fixture = crate::test_util::Fixture::wgsl(
+        "
+        const ONE = 1.0;
+
+        @group(0) @binding(0) var<storage, read_write> out: vec4<f32>;
+
+        @compute @workgroup_size(1)
+        fn main() {
+            let a = clamp(out.x, 0.0, 1.0);
+            let b = clamp(out.yz, vec2(0.0), vec2(ONE));
+            let c = clamp(out.w, 0.0, 2.0);
+            out = vec4(a, b, c);
+        }
+        ",
+    );
+
+    // Only the clamps to `[0, 1]` become `saturate`.
+    let output = fixture.to_msl(&Default::default(), &Default::default());
+    assert_eq!(output.as_str().matches("metal::saturate(").count(), 2);
+    assert_eq!(output.as_str().matches("metal::clamp(").count(), 1);
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn subgroup_operations() {
+    let fixture = crate::test_util::Fixture::wgsl_with_capabilities(
+        "
+        enable subgroups;
+
+        @group(0) @binding(0) var<storage, read_write> out: array<u32, 3>;
+
+        @compute @workgroup_size(64)
+        fn main(
+            @builtin(local_invocation_index) index: u32,
+            @builtin(subgroup_size) size: u32,
+            @builtin(subgroup_invocation_id) lane: u32,
+        ) {
+            out[0] = subgroupBallot(index < 16u).x;
+            out[1] = subgroupInclusiveAdd(index) + subgroupShuffleDown(index, 1u);
+            out[2] = size + lane;
+        }
+        ",
+        crate::valid::Capabilities::SUBGROUP,
+    );
+    fixture
+        .to_msl(
+            &Options {
+                lang_version: (2, 1),
+                ..Default::default()
+            },
+            &Default::default(),
+        )
+        .assert_contains("[[threads_per_simdgroup]]")
+        .assert_contains("[[thread_index_in_simdgroup]]")
+        .assert_contains("metal::simd_ballot(")
+        .assert_contains("metal::simd_prefix_inclusive_sum(")
+        .assert_contains("metal::simd_shuffle_down(");
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn repeated_array_elements() {
+    let fixture = crate::test_util::Fixture::wgsl(
+        "
+        const ones = array<i32, 4>(1, 1, 1, 1);
+        const zeros = array<f32, 4>(0.0, 0.0, 0.0, 0.0);
+
+        @compute @workgroup_size(1)
+        fn main() {
+            var a = ones;
+            var c = zeros;
+        }
+        ",
+    );
+    fixture
+        .to_msl(&Default::default(), &Default::default())
+        .assert_contains(" {1, 1, 1, 1}")
+        .assert_matches(r"zeros = type_\d+ \{\};")
+        .assert_not_contains("0.0, 0.0");
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn image_load_read_zero_skip_write() {
+    use crate::proc::BoundsCheckPolicy;
+
+    // Texel loads that need every kind of check: level of detail, array
+    // layer, sample index, and coordinates.
+    let fixture = crate::test_util::Fixture::wgsl(
+        "
+        @group(0) @binding(0) var image_1d: texture_1d<f32>;
+        @group(0) @binding(1) var image_2d_array: texture_2d_array<f32>;
+        @group(0) @binding(2) var image_multisampled: texture_multisampled_2d<f32>;
+        @group(0) @binding(3) var image_3d: texture_3d<f32>;
+
+        @fragment
+        fn main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+            let coords = vec2<i32>(position.xy);
+            let level = i32(position.z);
+            return textureLoad(image_1d, coords.x, level)
+                + textureLoad(image_2d_array, coords, 1, level)
+                + textureLoad(image_multisampled, coords, 2)
+                + textureLoad(image_3d, vec3(coords, 0), level);
+        }
+        ",
+    );
+    let options = Options {
+        bounds_check_policies: crate::proc::BoundsCheckPolicies {
+            image_load: BoundsCheckPolicy::ReadZeroSkipWrite,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    fixture
+        .to_msl(&options, &Default::default())
+        .assert_contains("< image_1d.get_width()")
+        .assert_contains(".get_num_mip_levels() && uint(1) < image_2d_array.get_array_size() && ")
+        .assert_contains("uint(2) < image_multisampled.get_num_samples() && ")
+        .assert_contains("< image_3d.get_num_mip_levels() && metal::all(");
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn image_atomics() {
+    let fixture = crate::test_util::Fixture::wgsl_with_capabilities(
+        "
+        @group(0) @binding(0) var image: texture_storage_2d<r32uint, atomic>;
+        @group(0) @binding(1) var layers: texture_storage_2d_array<r32sint, atomic>;
+
+        @compute @workgroup_size(1)
+        fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+            textureAtomicMax(image, id.xy, 1u);
+            textureAtomicSub(layers, id.xy, 2, 3);
+        }
+        ",
+        crate::valid::Capabilities::TEXTURE_ATOMIC,
+    );
+    fixture
+        .to_msl(
+            &Options {
+                lang_version: (3, 1),
+                ..Default::default()
+            },
+            &Default::default(),
+        )
+        .assert_contains("metal::texture2d<uint, metal::access::read_write>")
+        .assert_contains("image.atomic_fetch_max(")
+        .assert_contains("layers.atomic_fetch_sub(");
+}
@@ -0,0 +1,298 @@
+/*!
+Choosing and naming the entry points a backend writes.
+
+Backends write every entry point in the module they're given, under the
+name the module gives it, except that the GLSL and SPIR-V backends can be
+told to write just one. [`PipelineOptions::apply`] does the same for all
+of them, by changing the module before it is written.
+
+No backend applies these options itself: callers apply them to the module,
+validate it again, and then pass it to whichever backends they like.
+*/
+
+use crate::ShaderStage;
+use std::collections::BTreeMap;
+
+/// Which of a module's entry points to write, and what to call them.
+///
+/// Unlike the `PipelineOptions` of the GLSL and SPIR-V backends, these don't
+/// go to a backend: see [`PipelineOptions::apply`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
+pub struct PipelineOptions {
+    /// The stage and name of the only entry point to keep, if any.
+    #[cfg_attr(feature = "deserialize", serde(default))]
+    pub entry_point: Option<(ShaderStage, String)>,
+    /// New names for entry points, keyed by their names in the module.
+    ///
+    /// This applies to entry points of every stage with the given name.
+    /// Backends still adjust the new names to suit their language, as
+    /// they do with the originals.
+    #[cfg_attr(feature = "deserialize", serde(default))]
+    pub entry_point_names: BTreeMap<String, String>,
+}
+
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum PipelineError {
+    #[error("The module has no {0:?} entry point named {1:?}")]
+    MissingEntryPoint(ShaderStage, String),
+    #[error("The module has no entry point named {0:?} to rename")]
+    MissingRenamedEntryPoint(String),
+    #[error("More than one {0:?} entry point would be named {1:?}")]
+    DuplicateName(ShaderStage, String),
+}
+
+impl PipelineOptions {
+    /// Keep only the selected entry point of `module`, if there is one,
+    /// and rename entry points as requested.
+    ///
+    /// Selecting an entry point removes the others, together with the
+    /// functions only they use and the code that can never run, as
+    /// [`eliminate_dead_code`] does. With the `compact` feature, this also
+    /// removes the global variables, types, constants and expressions
+    /// nothing uses any more.
+    ///
+    /// If anything requested doesn't match the module, this returns an
+    /// error before changing it. The module should be validated again
+    /// afterwards, to get a [`ModuleInfo`] that matches it. The stage-
+    /// specific `PipelineOptions` of the GLSL and SPIR-V backends then take
+    /// the entry point's new name.
+    ///
+    /// # Panics
+    ///
+    /// If `module` has not passed validation, this may panic.
+    ///
+    /// [`eliminate_dead_code`]: crate::proc::eliminate_dead_code
+    /// [`ModuleInfo`]: crate::valid::ModuleInfo
+    pub fn apply(&self, module: &mut crate::Module) -> Result<(), PipelineError> {
+        let selected = match self.entry_point {
+            Some((stage, ref name)) => Some(
+                module
+                    .entry_points
+                    .iter()
+                    .position(|ep| ep.stage == stage && ep.name == *name)
+                    .ok_or_else(|| PipelineError::MissingEntryPoint(stage, name.clone()))?,
+            ),
+            None => None,
+        };
+        if let Some(name) = self
+            .entry_point_names
+            .keys()
+            .find(|&name| module.entry_points.iter().all(|ep| ep.name != *name))
+        {
+            return Err(PipelineError::MissingRenamedEntryPoint(name.clone()));
+        }
+
+        let new_name = |name: &String| self.entry_point_names.get(name).unwrap_or(name);
+        let mut names = Vec::new();
+        for (index, ep) in module.entry_points.iter().enumerate() {
+            if selected.map_or(true, |selected| selected == index) {
+                let name = (ep.stage, new_name(&ep.name));
+                if names.contains(&name) {
+                    return Err(PipelineError::DuplicateName(ep.stage, name.1.clone()));
+                }
+                names.push(name);
+            }
+        }
+
+        for ep in module.entry_points.iter_mut() {
+            ep.name = new_name(&ep.name).clone();
+        }
+        if let Some(selected) = selected {
+            let ep = module.entry_points.swap_remove(selected);
+            module.entry_points = vec![ep];
+            crate::proc::eliminate_dead_code(module);
+            #[cfg(feature = "compact")]
+            crate::compact::compact_with(module, crate::compact::KeepUnused::No);
+        }
+        Ok(())
+    }
+
+    /// Also rename the entry points of `module` whose names, after the
+    /// renames already requested, are in `reserved`, by appending `_`.
+    ///
+    /// Backends rename entry points whose names their language reserves,
+    /// like `main` in MSL, as they write them, so that the entry points
+    /// a pipeline must ask for differ from the module's. Calling this with
+    /// those words, like `back::msl::RESERVED`, before [`apply`] settles the
+    /// names in the module instead.
+    ///
+    /// [`apply`]: PipelineOptions::apply
+    pub fn rename_reserved(&mut self, module: &crate::Module, reserved: &[&str]) {
+        for ep in module.entry_points.iter() {
+            let name = self.entry_point_names.get(&ep.name).unwrap_or(&ep.name);
+            if reserved.contains(&name.as_str()) {
+                let new_name = format!("{name}_");
+                self.entry_point_names.insert(ep.name.clone(), new_name);
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod tests {
+    use super::{PipelineError, PipelineOptions};
+    use crate::test_util::Fixture;
+    use crate::ShaderStage;
+
+    const SOURCE: &str = "
+        @group(0) @binding(0) var<uniform> tint: vec4<f32>;
+        @group(0) @binding(1) var<uniform> offset: vec4<f32>;
+
+        fn shade() -> vec4<f32> {
+            return tint;
+        }
+
+        fn place() -> vec4<f32> {
+            return offset;
+        }
+
+        @vertex
+        fn vs_main() -> @builtin(position) vec4<f32> {
+            return place();
+        }
+
+        @fragment
+        fn fs_main() -> @location(0) vec4<f32> {
+            return shade();
+        }
+
+        @fragment
+        fn fs_white() -> @location(0) vec4<f32> {
+            return vec4(1.0);
+        }
+    ";
+
+    #[test]
+    fn rename() {
+        let mut fixture = Fixture::wgsl(SOURCE);
+        let options = PipelineOptions {
+            entry_point_names: [("vs_main", "main"), ("fs_main", "main")]
+                .into_iter()
+                .map(|(old, new)| (old.to_string(), new.to_string()))
+                .collect(),
+            ..Default::default()
+        };
+        options.apply(&mut fixture.module).unwrap();
+        fixture.revalidate();
+        let module = &fixture.module;
+        let names: Vec<_> = module.entry_points.iter().map(|ep| &ep.name).collect();
+        assert_eq!(names, ["main", "main", "fs_white"]);
+        assert_eq!(module.functions.len(), 2);
+        assert_eq!(module.global_variables.len(), 2);
+    }
+
+    #[test]
+    fn select() {
+        let mut fixture = Fixture::wgsl(SOURCE);
+        let options = PipelineOptions {
+            entry_point: Some((ShaderStage::Fragment, "fs_main".to_string())),
+            entry_point_names: [("fs_main".to_string(), "main".to_string())]
+                .into_iter()
+                .collect(),
+        };
+        options.apply(&mut fixture.module).unwrap();
+        fixture.revalidate();
+        let module = &fixture.module;
+        assert_eq!(module.entry_points.len(), 1);
+        assert_eq!(module.entry_points[0].name, "main");
+        assert_eq!(module.entry_points[0].stage, ShaderStage::Fragment);
+        let functions: Vec<_> = module
+            .functions
+            .iter()
+            .map(|(_, function)| function.name.as_deref())
+            .collect();
+        assert_eq!(functions, [Some("shade")]);
+        #[cfg(feature = "compact")]
+        {
+            let globals: Vec<_> = module
+                .global_variables
+                .iter()
+                .map(|(_, global)| global.name.as_deref())
+                .collect();
+            assert_eq!(globals, [Some("tint")]);
+        }
+    }
+
+    #[test]
+    fn rename_reserved() {
+        let mut fixture = Fixture::wgsl(SOURCE);
+        let mut options = PipelineOptions {
+            entry_point_names: [("vs_main".to_string(), "main".to_string())]
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        };
+        options.rename_reserved(&fixture.module, &["main", "fs_main"]);
+        options.apply(&mut fixture.module).unwrap();
+        fixture.revalidate();
+        let module = &fixture.module;
+        let names: Vec<_> = module.entry_points.iter().map(|ep| &ep.name).collect();
+        assert_eq!(names, ["main_", "fs_main_", "fs_white"]);
+    }
+
+    #[test]
+    fn errors() {
+        let mut module = Fixture::wgsl(SOURCE).module;
+        let missing = PipelineOptions {
+            entry_point: Some((ShaderStage::Vertex, "fs_main".to_string())),
+            ..Default::default()
+        };
+        assert_eq!(
+            missing.apply(&mut module),
+            Err(PipelineError::MissingEntryPoint(
+                ShaderStage::Vertex,
+                "fs_main".to_string()
+            ))
+        );
+        let unknown = PipelineOptions {
+            entry_point_names: [("cs_main".to_string(), "main".to_string())]
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        };
+        assert_eq!(
+            unknown.apply(&mut module),
+            Err(PipelineError::MissingRenamedEntryPoint(
+                "cs_main".to_string()
+            ))
+        );
+        let duplicate = PipelineOptions {
+            entry_point_names: [("fs_white".to_string(), "fs_main".to_string())]
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        };
+        assert_eq!(
+            duplicate.apply(&mut module),
+            Err(PipelineError::DuplicateName(
+                ShaderStage::Fragment,
+                "fs_main".to_string()
+            ))
+        );
+        let names: Vec<_> = module.entry_points.iter().map(|ep| &ep.name).collect();
+        assert_eq!(names, ["vs_main", "fs_main", "fs_white"]);
+
+        // Entry points of different stages may share a name, and the
+        // duplicate doesn't matter if only one of them is kept.
+        let shared = PipelineOptions {
+            entry_point_names: [("vs_main".to_string(), "fs_main".to_string())]
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        };
+        assert!(shared.apply(&mut module).is_ok());
+        let selected = PipelineOptions {
+            entry_point: Some((ShaderStage::Fragment, "fs_white".to_string())),
+            entry_point_names: [("fs_white".to_string(), "fs_main".to_string())]
+                .into_iter()
+                .collect(),
+        };
+        assert!(selected.apply(&mut module).is_ok());
+        assert_eq!(module.entry_points.len(), 1);
+        assert_eq!(module.entry_points[0].name, "fs_main");
+        #[cfg(feature = "compact")]
+        assert!(module.global_variables.is_empty());
+    }
+}
@@ -4,8 +4,8 @@ Implementations for `BlockContext` methods.
 
 use super::{
     helpers, index::BoundsCheckResult, make_local, selection::Selection, Block, BlockContext,
-    Dimension, Error, Instruction, LocalType, LookupType, LoopContext, ResultMember, Writer,
-    WriterFlags,
+    Dimension, Error, Instruction, LocalType, LookupType, LoopContext, ResultMember,
+    TargetEnvironment, Writer, WriterFlags,
 };
 use crate::{arena::Handle, proc::TypeResolution, Statement};
 use spirv::Word;
@@ -38,6 +38,17 @@ enum ExpressionPointer {
     },
 }
 
+/// A [`Select`] between pointers that loads and stores must lower, as
+/// returned by `find_lowered_pointer_select`.
+///
+/// [`Select`]: crate::Expression::Select
+struct LoweredPointerSelect {
+    select: Handle<crate::Expression>,
+    condition: Handle<crate::Expression>,
+    accept: Handle<crate::Expression>,
+    reject: Handle<crate::Expression>,
+}
+
 /// The termination statement to be added to the end of the block
 pub enum BlockExit {
     /// Generates an OpReturn (void return)
@@ -66,6 +77,29 @@ pub(crate) struct DebugInfoInner<'a> {
     pub source_file_id: Word,
 }
 
+impl DebugInfoInner<'_> {
+    /// Return an `OpLine` for the start of `span`, if it is a span of the
+    /// source code `self` describes.
+    ///
+    /// Spans that passes made up, or that refer to other source files, get
+    /// no `OpLine`, rather than a misleading one.
+    fn line(&self, span: crate::Span) -> Option<Instruction> {
+        let range = span.to_range()?;
+        if span.source() != crate::SourceId::PRIMARY
+            || range.end > self.source_code.len()
+            || !self.source_code.is_char_boundary(range.start)
+        {
+            return None;
+        }
+        let loc = span.location(self.source_code);
+        Some(Instruction::line(
+            self.source_file_id,
+            loc.line_number,
+            loc.line_position,
+        ))
+    }
+}
+
 impl Writer {
     // Flip Y coordinate to adjust for coordinate space difference
     // between SPIR-V and our IR.
@@ -113,6 +147,65 @@ impl Writer {
     }
 
     // Clamp fragment depth between 0 and 1.
+    // Map the depth of the position from our IR's `0..1` to OpenGL's
+    // `-1..1`, by replacing `z` with `z * 2 - w`. Like
+    // `write_epilogue_position_y_flip`, `position_id` is a pointer to
+    // a `vec4<f32>`.
+    fn write_epilogue_position_depth_remap(
+        &mut self,
+        position_id: Word,
+        body: &mut Vec<Instruction>,
+    ) -> Result<(), Error> {
+        let float_ptr_type_id = self.get_type_id(LookupType::Local(LocalType::Value {
+            vector_size: None,
+            kind: crate::ScalarKind::Float,
+            width: 4,
+            pointer_space: Some(spirv::StorageClass::Output),
+        }));
+        let float_type_id = self.get_type_id(LookupType::Local(LocalType::Value {
+            vector_size: None,
+            kind: crate::ScalarKind::Float,
+            width: 4,
+            pointer_space: None,
+        }));
+        let two_id = self.get_constant_scalar(crate::Literal::F32(2.0));
+
+        let mut load_component = |writer: &mut Self, index| {
+            let access_id = writer.id_gen.next();
+            let index_id = writer.get_index_constant(index);
+            body.push(Instruction::access_chain(
+                float_ptr_type_id,
+                access_id,
+                position_id,
+                &[index_id],
+            ));
+            let load_id = writer.id_gen.next();
+            body.push(Instruction::load(float_type_id, load_id, access_id, None));
+            (access_id, load_id)
+        };
+        let (z_access_id, z_id) = load_component(self, 2);
+        let (_, w_id) = load_component(self, 3);
+
+        let scaled_id = self.id_gen.next();
+        body.push(Instruction::binary(
+            spirv::Op::FMul,
+            float_type_id,
+            scaled_id,
+            z_id,
+            two_id,
+        ));
+        let remapped_id = self.id_gen.next();
+        body.push(Instruction::binary(
+            spirv::Op::FSub,
+            float_type_id,
+            remapped_id,
+            scaled_id,
+            w_id,
+        ));
+        body.push(Instruction::store(z_access_id, remapped_id, None));
+        Ok(())
+    }
+
     fn write_epilogue_frag_depth_clamp(
         &mut self,
         frag_depth_id: Word,
@@ -177,6 +270,9 @@ impl Writer {
                     if self.flags.contains(WriterFlags::ADJUST_COORDINATE_SPACE) =>
                 {
                     self.write_epilogue_position_y_flip(res_member.id, body)?;
+                    if self.target_environment == TargetEnvironment::OpenGl {
+                        self.write_epilogue_position_depth_remap(res_member.id, body)?;
+                    }
                 }
                 Some(crate::BuiltIn::FragDepth)
                     if self.flags.contains(WriterFlags::CLAMP_FRAG_DEPTH) =>
@@ -270,6 +366,30 @@ impl<'w> BlockContext<'w> {
                     id
                 }
             }
+            crate::Expression::Repeat { ty, value } => {
+                if self.expression_constness.is_const(expr_handle) {
+                    let is_zero = crate::proc::is_zero_value(value, &self.ir_function.expressions);
+                    self.writer.get_constant_repeat(
+                        ty,
+                        self.cached[value],
+                        is_zero,
+                        &self.ir_module.types,
+                    )
+                } else {
+                    self.temp_list.clear();
+                    self.temp_list.extend(
+                        crate::proc::repeat_components(ty, value, &self.ir_module.types)
+                            .map(|component| self.cached[component]),
+                    );
+                    let id = self.gen_id();
+                    block.body.push(Instruction::composite_construct(
+                        result_type_id,
+                        id,
+                        &self.temp_list,
+                    ));
+                    id
+                }
+            }
             crate::Expression::Splat { size, value } => {
                 let value_id = self.cached[value];
                 let components = &[value_id; 4][..size as usize];
@@ -735,6 +855,16 @@ impl<'w> BlockContext<'w> {
                         other => unimplemented!("Unexpected max({:?})", other),
                     }),
                     Mf::Clamp => MathOp::Ext(match arg_scalar_kind {
+                        Some(crate::ScalarKind::Float)
+                            if crate::back::clamps_to_unit_interval(
+                                self.ir_module,
+                                &self.ir_function.expressions,
+                                arg1,
+                                arg2,
+                            ) =>
+                        {
+                            spirv::GLOp::NClamp
+                        }
                         Some(crate::ScalarKind::Float) => spirv::GLOp::FClamp,
                         Some(crate::ScalarKind::Sint) => spirv::GLOp::SClamp,
                         Some(crate::ScalarKind::Uint) => spirv::GLOp::UClamp,
@@ -769,9 +899,11 @@ impl<'w> BlockContext<'w> {
                             arg2_id = self.writer.get_constant_composite(ty, &self.temp_list);
                         }
 
+                        // Unlike `FClamp`, `NClamp` returns 0 for NaN, as
+                        // HLSL's `saturate` does.
                         MathOp::Custom(Instruction::ext_inst(
                             self.writer.gl450_ext_inst_id,
-                            spirv::GLOp::FClamp,
+                            spirv::GLOp::NClamp,
                             result_type_id,
                             id,
                             &[arg0_id, arg1_id, arg2_id],
@@ -1023,24 +1155,30 @@ impl<'w> BlockContext<'w> {
                             Some(crate::ScalarKind::Sint) => spirv::Op::BitFieldSExtract,
                             other => unimplemented!("Unexpected sign({:?})", other),
                         };
+                        let (offset_id, count_id) =
+                            self.write_clamped_bit_field(block, arg_ty, arg1_id, arg2_id)?;
                         MathOp::Custom(Instruction::ternary(
                             op,
                             result_type_id,
                             id,
                             arg0_id,
+                            offset_id,
+                            count_id,
+                        ))
+                    }
+                    Mf::InsertBits => {
+                        let (offset_id, count_id) =
+                            self.write_clamped_bit_field(block, arg_ty, arg2_id, arg3_id)?;
+                        MathOp::Custom(Instruction::quaternary(
+                            spirv::Op::BitFieldInsert,
+                            result_type_id,
+                            id,
+                            arg0_id,
                             arg1_id,
-                            arg2_id,
+                            offset_id,
+                            count_id,
                         ))
                     }
-                    Mf::InsertBits => MathOp::Custom(Instruction::quaternary(
-                        spirv::Op::BitFieldInsert,
-                        result_type_id,
-                        id,
-                        arg0_id,
-                        arg1_id,
-                        arg2_id,
-                        arg3_id,
-                    )),
                     Mf::FindLsb => MathOp::Ext(spirv::GLOp::FindILsb),
                     Mf::FindMsb => MathOp::Ext(match arg_scalar_kind {
                         Some(crate::ScalarKind::Uint) => spirv::GLOp::FindUMsb,
@@ -1073,58 +1211,7 @@ impl<'w> BlockContext<'w> {
             }
             crate::Expression::LocalVariable(variable) => self.function.variables[&variable].id,
             crate::Expression::Load { pointer } => {
-                match self.write_expression_pointer(pointer, block, None)? {
-                    ExpressionPointer::Ready { pointer_id } => {
-                        let id = self.gen_id();
-                        let atomic_space =
-                            match *self.fun_info[pointer].ty.inner_with(&self.ir_module.types) {
-                                crate::TypeInner::Pointer { base, space } => {
-                                    match self.ir_module.types[base].inner {
-                                        crate::TypeInner::Atomic { .. } => Some(space),
-                                        _ => None,
-                                    }
-                                }
-                                _ => None,
-                            };
-                        let instruction = if let Some(space) = atomic_space {
-                            let (semantics, scope) = space.to_spirv_semantics_and_scope();
-                            let scope_constant_id = self.get_scope_constant(scope as u32);
-                            let semantics_id = self.get_index_constant(semantics.bits());
-                            Instruction::atomic_load(
-                                result_type_id,
-                                id,
-                                pointer_id,
-                                scope_constant_id,
-                                semantics_id,
-                            )
-                        } else {
-                            Instruction::load(result_type_id, id, pointer_id, None)
-                        };
-                        block.body.push(instruction);
-                        id
-                    }
-                    ExpressionPointer::Conditional { condition, access } => {
-                        //TODO: support atomics?
-                        self.write_conditional_indexed_load(
-                            result_type_id,
-                            condition,
-                            block,
-                            move |id_gen, block| {
-                                // The in-bounds path. Perform the access and the load.
-                                let pointer_id = access.result_id.unwrap();
-                                let value_id = id_gen.next();
-                                block.body.push(access);
-                                block.body.push(Instruction::load(
-                                    result_type_id,
-                                    value_id,
-                                    pointer_id,
-                                    None,
-                                ));
-                                value_id
-                            },
-                        )
-                    }
-                }
+                self.write_load(pointer, result_type_id, block)?
             }
             crate::Expression::FunctionArgument(index) => self.function.parameter_id(index),
             crate::Expression::CallResult(_)
@@ -1318,6 +1405,18 @@ impl<'w> BlockContext<'w> {
                 depth_ref,
                 block,
             )?,
+            crate::Expression::Select { .. }
+                if self.fun_info[expr_handle]
+                    .ty
+                    .inner_with(&self.ir_module.types)
+                    .pointer_space()
+                    .is_some() =>
+            {
+                // Like `Access`, this is handled later, in
+                // `write_expression_pointer`, or in `write_load` and
+                // `write_store` if variable pointers aren't available.
+                0
+            }
             crate::Expression::Select {
                 condition,
                 accept,
@@ -1415,10 +1514,22 @@ impl<'w> BlockContext<'w> {
             }
             crate::Expression::ArrayLength(expr) => self.write_runtime_array_length(expr, block)?,
             crate::Expression::RayQueryGetIntersection { query, committed } => {
-                if !committed {
-                    return Err(Error::FeatureNotImplemented("candidate intersection"));
-                }
-                self.write_ray_query_get_intersection(query, block)
+                self.write_ray_query_get_intersection(query, committed, block)
+            }
+            crate::Expression::SubgroupOperation { op, argument } => {
+                self.write_subgroup_operation(result_type_id, op, argument, block)?
+            }
+            // Decorate a copy, so that other uses of the value aren't affected.
+            crate::Expression::NonUniform(value) => {
+                let id = self.gen_id();
+                block.body.push(Instruction::unary(
+                    spirv::Op::CopyObject,
+                    result_type_id,
+                    id,
+                    self.cached[value],
+                ));
+                self.writer.decorate_non_uniform_binding_array_access(id)?;
+                id
             }
         };
 
@@ -1426,6 +1537,590 @@ impl<'w> BlockContext<'w> {
         Ok(())
     }
 
+    /// Write code to load the value `pointer` points to.
+    ///
+    /// If `pointer` is based on a [`Select`] between pointers that `OpSelect`
+    /// can't produce, because variable pointers aren't available, load
+    /// through each operand in turn and merge the results with `OpPhi`.
+    ///
+    /// [`Select`]: crate::Expression::Select
+    fn write_load(
+        &mut self,
+        pointer: Handle<crate::Expression>,
+        result_type_id: Word,
+        block: &mut Block,
+    ) -> Result<Word, Error> {
+        if let Some(LoweredPointerSelect {
+            select,
+            condition,
+            accept,
+            reject,
+        }) = self.find_lowered_pointer_select(pointer)
+        {
+            // Load through `reject` unconditionally, and through `accept`
+            // only if `condition` holds.
+            let condition_id = self.cached[condition];
+            self.pointer_select_choices.insert(select, reject);
+            let reject_id = self.write_load(pointer, result_type_id, block)?;
+
+            let mut selection = Selection::start(block, result_type_id);
+            selection.if_true(self, condition_id, reject_id);
+            self.pointer_select_choices.insert(select, accept);
+            let accept_id = self.write_load(pointer, result_type_id, selection.block())?;
+            self.pointer_select_choices.remove(&select);
+            return Ok(selection.finish(self, accept_id));
+        }
+
+        let id = match self.write_expression_pointer(pointer, block, None)? {
+            ExpressionPointer::Ready { pointer_id } => {
+                let id = self.gen_id();
+                let atomic_space =
+                    match *self.fun_info[pointer].ty.inner_with(&self.ir_module.types) {
+                        crate::TypeInner::Pointer { base, space } => {
+                            match self.ir_module.types[base].inner {
+                                crate::TypeInner::Atomic { .. } => Some(space),
+                                _ => None,
+                            }
+                        }
+                        _ => None,
+                    };
+                let instruction = if let Some(space) = atomic_space {
+                    let (semantics, scope) = space.to_spirv_semantics_and_scope();
+                    let scope_constant_id = self.get_scope_constant(scope as u32);
+                    let semantics_id = self.get_index_constant(semantics.bits());
+                    Instruction::atomic_load(
+                        result_type_id,
+                        id,
+                        pointer_id,
+                        scope_constant_id,
+                        semantics_id,
+                    )
+                } else {
+                    Instruction::load(result_type_id, id, pointer_id, None)
+                };
+                block.body.push(instruction);
+                id
+            }
+            ExpressionPointer::Conditional { condition, access } => {
+                //TODO: support atomics?
+                self.write_conditional_indexed_load(
+                    result_type_id,
+                    condition,
+                    block,
+                    move |id_gen, block| {
+                        // The in-bounds path. Perform the access and the load.
+                        let pointer_id = access.result_id.unwrap();
+                        let value_id = id_gen.next();
+                        block.body.push(access);
+                        block.body.push(Instruction::load(
+                            result_type_id,
+                            value_id,
+                            pointer_id,
+                            None,
+                        ));
+                        value_id
+                    },
+                )
+            }
+        };
+        Ok(id)
+    }
+
+    /// Write code to store `value_id` to where `pointer` points.
+    ///
+    /// Like [`write_load`], this lowers a [`Select`] between pointers that
+    /// `OpSelect` can't produce, by storing through each operand under its
+    /// half of the condition.
+    ///
+    /// [`write_load`]: BlockContext::write_load
+    /// [`Select`]: crate::Expression::Select
+    fn write_store(
+        &mut self,
+        pointer: Handle<crate::Expression>,
+        value_id: Word,
+        block: &mut Block,
+    ) -> Result<(), Error> {
+        if let Some(lowered) = self.find_lowered_pointer_select(pointer) {
+            self.write_pointer_select_cases(lowered, None, block, |ctx, block| {
+                ctx.write_store(pointer, value_id, block)?;
+                Ok(0)
+            })?;
+            return Ok(());
+        }
+
+        match self.write_expression_pointer(pointer, block, None)? {
+            ExpressionPointer::Ready { pointer_id } => {
+                let atomic_space =
+                    match *self.fun_info[pointer].ty.inner_with(&self.ir_module.types) {
+                        crate::TypeInner::Pointer { base, space } => {
+                            match self.ir_module.types[base].inner {
+                                crate::TypeInner::Atomic { .. } => Some(space),
+                                _ => None,
+                            }
+                        }
+                        _ => None,
+                    };
+                let instruction = if let Some(space) = atomic_space {
+                    let (semantics, scope) = space.to_spirv_semantics_and_scope();
+                    let scope_constant_id = self.get_scope_constant(scope as u32);
+                    let semantics_id = self.get_index_constant(semantics.bits());
+                    Instruction::atomic_store(pointer_id, scope_constant_id, semantics_id, value_id)
+                } else {
+                    Instruction::store(pointer_id, value_id, None)
+                };
+                block.body.push(instruction);
+            }
+            ExpressionPointer::Conditional { condition, access } => {
+                let mut selection = Selection::start(block, ());
+                selection.if_true(self, condition, ());
+
+                // The in-bounds path. Perform the access and the store.
+                let pointer_id = access.result_id.unwrap();
+                selection.block().body.push(access);
+                selection
+                    .block()
+                    .body
+                    .push(Instruction::store(pointer_id, value_id, None));
+
+                // Finish the in-bounds block and start the merge block. This
+                // is the block we'll leave current on return.
+                selection.finish(self, ());
+            }
+        };
+        Ok(())
+    }
+
+    /// Write an atomic operation on `pointer`, and return its result's id.
+    ///
+    /// Like [`write_store`], this lowers a [`Select`] between pointers that
+    /// `OpSelect` can't produce, by performing the operation through each
+    /// operand under its half of the condition.
+    ///
+    /// [`write_store`]: BlockContext::write_store
+    /// [`Select`]: crate::Expression::Select
+    fn write_atomic(
+        &mut self,
+        pointer: Handle<crate::Expression>,
+        fun: &crate::AtomicFunction,
+        value: Handle<crate::Expression>,
+        result_type_id: Word,
+        block: &mut Block,
+    ) -> Result<Word, Error> {
+        if let Some(lowered) = self.find_lowered_pointer_select(pointer) {
+            return self.write_pointer_select_cases(
+                lowered,
+                Some(result_type_id),
+                block,
+                |ctx, block| ctx.write_atomic(pointer, fun, value, result_type_id, block),
+            );
+        }
+
+        let id = self.gen_id();
+
+        let pointer_id = match self.write_expression_pointer(pointer, block, None)? {
+            ExpressionPointer::Ready { pointer_id } => pointer_id,
+            ExpressionPointer::Conditional { .. } => {
+                return Err(Error::FeatureNotImplemented(
+                    "Atomics out-of-bounds handling",
+                ));
+            }
+        };
+
+        let space = self.fun_info[pointer]
+            .ty
+            .inner_with(&self.ir_module.types)
+            .pointer_space()
+            .unwrap();
+        let (semantics, scope) = space.to_spirv_semantics_and_scope();
+        let scope_constant_id = self.get_scope_constant(scope as u32);
+        let semantics_id = self.get_index_constant(semantics.bits());
+        let value_id = self.cached[value];
+        let value_inner = self.fun_info[value].ty.inner_with(&self.ir_module.types);
+
+        let instruction = match *fun {
+            crate::AtomicFunction::Add => Instruction::atomic_binary(
+                spirv::Op::AtomicIAdd,
+                result_type_id,
+                id,
+                pointer_id,
+                scope_constant_id,
+                semantics_id,
+                value_id,
+            ),
+            crate::AtomicFunction::Subtract => Instruction::atomic_binary(
+                spirv::Op::AtomicISub,
+                result_type_id,
+                id,
+                pointer_id,
+                scope_constant_id,
+                semantics_id,
+                value_id,
+            ),
+            crate::AtomicFunction::And => Instruction::atomic_binary(
+                spirv::Op::AtomicAnd,
+                result_type_id,
+                id,
+                pointer_id,
+                scope_constant_id,
+                semantics_id,
+                value_id,
+            ),
+            crate::AtomicFunction::InclusiveOr => Instruction::atomic_binary(
+                spirv::Op::AtomicOr,
+                result_type_id,
+                id,
+                pointer_id,
+                scope_constant_id,
+                semantics_id,
+                value_id,
+            ),
+            crate::AtomicFunction::ExclusiveOr => Instruction::atomic_binary(
+                spirv::Op::AtomicXor,
+                result_type_id,
+                id,
+                pointer_id,
+                scope_constant_id,
+                semantics_id,
+                value_id,
+            ),
+            crate::AtomicFunction::Min => {
+                let spirv_op = match *value_inner {
+                    crate::TypeInner::Scalar {
+                        kind: crate::ScalarKind::Sint,
+                        width: _,
+                    } => spirv::Op::AtomicSMin,
+                    crate::TypeInner::Scalar {
+                        kind: crate::ScalarKind::Uint,
+                        width: _,
+                    } => spirv::Op::AtomicUMin,
+                    _ => unimplemented!(),
+                };
+                Instruction::atomic_binary(
+                    spirv_op,
+                    result_type_id,
+                    id,
+                    pointer_id,
+                    scope_constant_id,
+                    semantics_id,
+                    value_id,
+                )
+            }
+            crate::AtomicFunction::Max => {
+                let spirv_op = match *value_inner {
+                    crate::TypeInner::Scalar {
+                        kind: crate::ScalarKind::Sint,
+                        width: _,
+                    } => spirv::Op::AtomicSMax,
+                    crate::TypeInner::Scalar {
+                        kind: crate::ScalarKind::Uint,
+                        width: _,
+                    } => spirv::Op::AtomicUMax,
+                    _ => unimplemented!(),
+                };
+                Instruction::atomic_binary(
+                    spirv_op,
+                    result_type_id,
+                    id,
+                    pointer_id,
+                    scope_constant_id,
+                    semantics_id,
+                    value_id,
+                )
+            }
+            crate::AtomicFunction::Exchange { compare: None } => Instruction::atomic_binary(
+                spirv::Op::AtomicExchange,
+                result_type_id,
+                id,
+                pointer_id,
+                scope_constant_id,
+                semantics_id,
+                value_id,
+            ),
+            crate::AtomicFunction::Exchange { compare: Some(cmp) } => {
+                let scalar_type_id = match *value_inner {
+                    crate::TypeInner::Scalar { kind, width } => {
+                        self.get_type_id(LookupType::Local(LocalType::Value {
+                            vector_size: None,
+                            kind,
+                            width,
+                            pointer_space: None,
+                        }))
+                    }
+                    _ => unimplemented!(),
+                };
+                let bool_type_id = self.get_type_id(LookupType::Local(LocalType::Value {
+                    vector_size: None,
+                    kind: crate::ScalarKind::Bool,
+                    width: crate::BOOL_WIDTH,
+                    pointer_space: None,
+                }));
+
+                let cas_result_id = self.gen_id();
+                let equality_result_id = self.gen_id();
+                let mut cas_instr = Instruction::new(spirv::Op::AtomicCompareExchange);
+                cas_instr.set_type(scalar_type_id);
+                cas_instr.set_result(cas_result_id);
+                cas_instr.add_operand(pointer_id);
+                cas_instr.add_operand(scope_constant_id);
+                cas_instr.add_operand(semantics_id); // semantics if equal
+                cas_instr.add_operand(semantics_id); // semantics if not equal
+                cas_instr.add_operand(value_id);
+                cas_instr.add_operand(self.cached[cmp]);
+                block.body.push(cas_instr);
+                block.body.push(Instruction::binary(
+                    spirv::Op::IEqual,
+                    bool_type_id,
+                    equality_result_id,
+                    cas_result_id,
+                    self.cached[cmp],
+                ));
+                Instruction::composite_construct(
+                    result_type_id,
+                    id,
+                    &[cas_result_id, equality_result_id],
+                )
+            }
+        };
+
+        block.body.push(instruction);
+        Ok(id)
+    }
+
+    /// Write a call to `local_function`, and return its result's id.
+    ///
+    /// A pointer [`Select`] passed as an argument is written with
+    /// `OpSelect` if variable pointers are available. Otherwise, like
+    /// [`write_store`], the call is written once for each operand, under its
+    /// half of the condition.
+    ///
+    /// [`write_store`]: BlockContext::write_store
+    /// [`Select`]: crate::Expression::Select
+    fn write_function_call(
+        &mut self,
+        local_function: Handle<crate::Function>,
+        arguments: &[Handle<crate::Expression>],
+        result_type_id: Option<Word>,
+        block: &mut Block,
+    ) -> Result<Word, Error> {
+        if let Some(lowered) = arguments
+            .iter()
+            .find_map(|&argument| self.find_lowered_pointer_select(argument))
+        {
+            return self.write_pointer_select_cases(
+                lowered,
+                result_type_id,
+                block,
+                |ctx, block| {
+                    ctx.write_function_call(local_function, arguments, result_type_id, block)
+                },
+            );
+        }
+
+        // Pointer selects have no cached id, since they are only written
+        // where they are used. `write_expression_pointer` uses `temp_list`,
+        // so gather the argument ids separately.
+        let mut argument_ids = Vec::with_capacity(arguments.len());
+        for &argument in arguments {
+            let id = match self.ir_function.expressions[argument] {
+                crate::Expression::Select { .. }
+                    if self.fun_info[argument]
+                        .ty
+                        .inner_with(&self.ir_module.types)
+                        .pointer_space()
+                        .is_some() =>
+                {
+                    match self.write_expression_pointer(argument, block, None)? {
+                        ExpressionPointer::Ready { pointer_id } => pointer_id,
+                        ExpressionPointer::Conditional { .. } => {
+                            return Err(Error::FeatureNotImplemented(
+                                "passing bounds-checked pointers to functions",
+                            ))
+                        }
+                    }
+                }
+                _ => self.cached[argument],
+            };
+            argument_ids.push(id);
+        }
+
+        let id = self.gen_id();
+        block.body.push(Instruction::function_call(
+            result_type_id.unwrap_or(self.writer.void_type),
+            id,
+            self.writer.lookup_function[&local_function],
+            &argument_ids,
+        ));
+        Ok(id)
+    }
+
+    /// Write an operation that uses the pointer select `lowered`, once for
+    /// each of its operands, under that operand's half of the condition.
+    ///
+    /// Unlike a load, the operation may have side effects, so only one copy
+    /// may run. `write` writes a copy to the block it is given, with
+    /// [`pointer_select_choices`] set to the operand it should use.
+    ///
+    /// If `result_type_id` is given, return the id of the result produced by
+    /// whichever copy ran, merging the copies' results with `OpPhi`.
+    /// Otherwise, `write`'s results are ignored, and this returns zero.
+    ///
+    /// [`pointer_select_choices`]: BlockContext::pointer_select_choices
+    fn write_pointer_select_cases(
+        &mut self,
+        lowered: LoweredPointerSelect,
+        result_type_id: Option<Word>,
+        block: &mut Block,
+        mut write: impl FnMut(&mut Self, &mut Block) -> Result<Word, Error>,
+    ) -> Result<Word, Error> {
+        let LoweredPointerSelect {
+            select,
+            condition,
+            accept,
+            reject,
+        } = lowered;
+        let condition_id = self.cached[condition];
+        let not_condition_id = self.gen_id();
+        block.body.push(Instruction::unary(
+            spirv::Op::LogicalNot,
+            self.writer.get_bool_type_id(),
+            not_condition_id,
+            condition_id,
+        ));
+
+        // Each selection's merged result is the previous one's if its copy
+        // doesn't run. The first copy's stand-in is never used, since the
+        // second copy runs exactly when the first doesn't.
+        let mut merged_id = match result_type_id {
+            Some(type_id) => self.writer.get_constant_null(type_id),
+            None => 0,
+        };
+        for (operand, operand_condition_id) in [(accept, condition_id), (reject, not_condition_id)]
+        {
+            self.pointer_select_choices.insert(select, operand);
+            merged_id = match result_type_id {
+                Some(type_id) => {
+                    let mut selection = Selection::start(block, type_id);
+                    selection.if_true(self, operand_condition_id, merged_id);
+                    let id = write(self, selection.block())?;
+                    selection.finish(self, id)
+                }
+                None => {
+                    let mut selection = Selection::start(block, ());
+                    selection.if_true(self, operand_condition_id, ());
+                    write(self, selection.block())?;
+                    selection.finish(self, ());
+                    0
+                }
+            };
+        }
+        self.pointer_select_choices.remove(&select);
+        Ok(merged_id)
+    }
+
+    /// Find a [`Select`] between pointers in `pointer`'s access chain that
+    /// must be lowered, because `OpSelect` can't choose between pointers in
+    /// its address space with the available capabilities.
+    ///
+    /// Selects that [`pointer_select_choices`] has already decided are
+    /// followed through their chosen operand.
+    ///
+    /// On success, return the select, and its `condition`, `accept` and
+    /// `reject` operands.
+    ///
+    /// [`Select`]: crate::Expression::Select
+    /// [`pointer_select_choices`]: BlockContext::pointer_select_choices
+    fn find_lowered_pointer_select(
+        &self,
+        mut pointer: Handle<crate::Expression>,
+    ) -> Option<LoweredPointerSelect> {
+        loop {
+            pointer = match self.ir_function.expressions[pointer] {
+                crate::Expression::Access { base, .. }
+                | crate::Expression::AccessIndex { base, .. } => base,
+                crate::Expression::Select {
+                    condition,
+                    accept,
+                    reject,
+                } => match self.pointer_select_choices.get(&pointer) {
+                    Some(&chosen) => chosen,
+                    None => {
+                        let space = self.fun_info[pointer]
+                            .ty
+                            .inner_with(&self.ir_module.types)
+                            .pointer_space()?;
+                        let capabilities = helpers::variable_pointers_capabilities(space);
+                        if self.writer.any_available(capabilities) {
+                            return None;
+                        }
+                        return Some(LoweredPointerSelect {
+                            select: pointer,
+                            condition,
+                            accept,
+                            reject,
+                        });
+                    }
+                },
+                _ => return None,
+            };
+        }
+    }
+
+    /// Choose between two pointers with `OpSelect`, using variable pointers.
+    ///
+    /// Loads, stores, atomics and calls lower pointer selects themselves
+    /// when variable pointers aren't available, so the capability is only
+    /// missing here if the pointer is used some other way.
+    fn write_pointer_select(
+        &mut self,
+        select: Handle<crate::Expression>,
+        condition: Handle<crate::Expression>,
+        accept: Handle<crate::Expression>,
+        reject: Handle<crate::Expression>,
+        block: &mut Block,
+    ) -> Result<Word, Error> {
+        let space = self.fun_info[select]
+            .ty
+            .inner_with(&self.ir_module.types)
+            .pointer_space()
+            .ok_or(Error::Validation("pointer select of non-pointers"))?;
+        let capabilities = helpers::variable_pointers_capabilities(space);
+        if capabilities.is_empty() {
+            return Err(Error::FeatureNotImplemented(
+                "selecting between pointers in this address space, other than to load or store",
+            ));
+        }
+        self.writer
+            .require_any("selecting between pointers", capabilities)?;
+        self.writer.use_extension("SPV_KHR_variable_pointers");
+
+        // Our caller is partway through building an access chain in
+        // `temp_list`, so build the operands' chains in a fresh one.
+        let saved_temp_list = std::mem::take(&mut self.temp_list);
+        let mut operand_ids = [0; 2];
+        for (operand_id, operand) in operand_ids.iter_mut().zip([accept, reject]) {
+            *operand_id = match self.write_expression_pointer(operand, block, None)? {
+                ExpressionPointer::Ready { pointer_id } => pointer_id,
+                ExpressionPointer::Conditional { .. } => {
+                    return Err(Error::FeatureNotImplemented(
+                        "selecting between bounds-checked pointers",
+                    ))
+                }
+            };
+        }
+        self.temp_list = saved_temp_list;
+
+        let result_type_id = self.get_expression_type_id(&self.fun_info[select].ty);
+        let id = self.gen_id();
+        block.body.push(Instruction::select(
+            result_type_id,
+            id,
+            self.cached[condition],
+            operand_ids[0],
+            operand_ids[1],
+        ));
+        Ok(id)
+    }
+
     /// Build an `OpAccessChain` instruction.
     ///
     /// Emit any needed bounds-checking expressions to `block`.
@@ -1539,6 +2234,22 @@ impl<'w> BlockContext<'w> {
                 crate::Expression::FunctionArgument(index) => {
                     break self.function.parameter_id(index);
                 }
+                crate::Expression::Select {
+                    condition,
+                    accept,
+                    reject,
+                } => match self.pointer_select_choices.get(&expr_handle) {
+                    Some(&chosen) => chosen,
+                    None => {
+                        break self.write_pointer_select(
+                            expr_handle,
+                            condition,
+                            accept,
+                            reject,
+                            block,
+                        )?
+                    }
+                },
                 ref other => unimplemented!("Unexpected pointer expression {:?}", other),
             }
         };
@@ -1638,6 +2349,55 @@ impl<'w> BlockContext<'w> {
         ));
     }
 
+    /// Clamp the `offset` and `count` operands of `extractBits` or
+    /// `insertBits` on a value of type `value_ty`, as WGSL requires: the
+    /// offset to the width of the value, and the count to the bits above the
+    /// clamped offset. SPIR-V leaves out-of-range operands undefined.
+    ///
+    /// Return the ids of the clamped offset and count.
+    fn write_clamped_bit_field(
+        &mut self,
+        block: &mut Block,
+        value_ty: &crate::TypeInner,
+        offset_id: Word,
+        count_id: Word,
+    ) -> Result<(Word, Word), Error> {
+        let bits = value_ty.scalar_width().unwrap_or(32);
+        let uint_type_id = self.writer.get_uint_type_id();
+        let bits_id = self
+            .writer
+            .get_constant_scalar(crate::Literal::U32(bits as u32));
+
+        let clamped_offset_id = self.gen_id();
+        block.body.push(Instruction::ext_inst(
+            self.writer.gl450_ext_inst_id,
+            spirv::GLOp::UMin,
+            uint_type_id,
+            clamped_offset_id,
+            &[offset_id, bits_id],
+        ));
+
+        let remaining_id = self.gen_id();
+        block.body.push(Instruction::binary(
+            spirv::Op::ISub,
+            uint_type_id,
+            remaining_id,
+            bits_id,
+            clamped_offset_id,
+        ));
+
+        let clamped_count_id = self.gen_id();
+        block.body.push(Instruction::ext_inst(
+            self.writer.gl450_ext_inst_id,
+            spirv::GLOp::UMin,
+            uint_type_id,
+            clamped_count_id,
+            &[count_id, remaining_id],
+        ));
+
+        Ok((clamped_offset_id, clamped_count_id))
+    }
+
     /// Build the instructions for vector - scalar multiplication
     fn write_vector_scalar_mult(
         &mut self,
@@ -1756,12 +2516,7 @@ impl<'w> BlockContext<'w> {
                         | Statement::Loop { .. })
                 ),
             ) {
-                let loc: crate::SourceLocation = span.location(debug_info.source_code);
-                block.body.push(Instruction::line(
-                    debug_info.source_file_id,
-                    loc.line_number,
-                    loc.line_position,
-                ));
+                block.body.extend(debug_info.line(*span));
             };
             match *statement {
                 crate::Statement::Emit(ref range) => {
@@ -1940,12 +2695,7 @@ impl<'w> BlockContext<'w> {
                     // HACK the loop statement is begin with branch instruction,
                     // so we need to put `OpLine` debug info before merge instruction
                     if let Some(debug_info) = debug_info {
-                        let loc: crate::SourceLocation = span.location(debug_info.source_code);
-                        block.body.push(Instruction::line(
-                            debug_info.source_file_id,
-                            loc.line_number,
-                            loc.line_position,
-                        ))
+                        block.body.extend(debug_info.line(*span));
                     }
                     block.body.push(Instruction::loop_merge(
                         merge_id,
@@ -2034,52 +2784,7 @@ impl<'w> BlockContext<'w> {
                 }
                 crate::Statement::Store { pointer, value } => {
                     let value_id = self.cached[value];
-                    match self.write_expression_pointer(pointer, &mut block, None)? {
-                        ExpressionPointer::Ready { pointer_id } => {
-                            let atomic_space = match *self.fun_info[pointer]
-                                .ty
-                                .inner_with(&self.ir_module.types)
-                            {
-                                crate::TypeInner::Pointer { base, space } => {
-                                    match self.ir_module.types[base].inner {
-                                        crate::TypeInner::Atomic { .. } => Some(space),
-                                        _ => None,
-                                    }
-                                }
-                                _ => None,
-                            };
-                            let instruction = if let Some(space) = atomic_space {
-                                let (semantics, scope) = space.to_spirv_semantics_and_scope();
-                                let scope_constant_id = self.get_scope_constant(scope as u32);
-                                let semantics_id = self.get_index_constant(semantics.bits());
-                                Instruction::atomic_store(
-                                    pointer_id,
-                                    scope_constant_id,
-                                    semantics_id,
-                                    value_id,
-                                )
-                            } else {
-                                Instruction::store(pointer_id, value_id, None)
-                            };
-                            block.body.push(instruction);
-                        }
-                        ExpressionPointer::Conditional { condition, access } => {
-                            let mut selection = Selection::start(&mut block, ());
-                            selection.if_true(self, condition, ());
-
-                            // The in-bounds path. Perform the access and the store.
-                            let pointer_id = access.result_id.unwrap();
-                            selection.block().body.push(access);
-                            selection
-                                .block()
-                                .body
-                                .push(Instruction::store(pointer_id, value_id, None));
-
-                            // Finish the in-bounds block and start the merge block. This
-                            // is the block we'll leave current on return.
-                            selection.finish(self, ());
-                        }
-                    };
+                    self.write_store(pointer, value_id, &mut block)?;
                 }
                 crate::Statement::ImageStore {
                     image,
@@ -2087,31 +2792,31 @@ impl<'w> BlockContext<'w> {
                     array_index,
                     value,
                 } => self.write_image_store(image, coordinate, array_index, value, &mut block)?,
+                crate::Statement::ImageAtomic {
+                    image,
+                    coordinate,
+                    array_index,
+                    fun,
+                    value,
+                } => {
+                    self.write_image_atomic(image, coordinate, array_index, fun, value, &mut block)?
+                }
                 crate::Statement::Call {
                     function: local_function,
                     ref arguments,
                     result,
                 } => {
-                    let id = self.gen_id();
-                    self.temp_list.clear();
-                    for &argument in arguments {
-                        self.temp_list.push(self.cached[argument]);
+                    let result_type_id =
+                        result.map(|expr| self.get_expression_type_id(&self.fun_info[expr].ty));
+                    let id = self.write_function_call(
+                        local_function,
+                        arguments,
+                        result_type_id,
+                        &mut block,
+                    )?;
+                    if let Some(expr) = result {
+                        self.cached[expr] = id;
                     }
-
-                    let type_id = match result {
-                        Some(expr) => {
-                            self.cached[expr] = id;
-                            self.get_expression_type_id(&self.fun_info[expr].ty)
-                        }
-                        None => self.writer.void_type,
-                    };
-
-                    block.body.push(Instruction::function_call(
-                        type_id,
-                        id,
-                        self.writer.lookup_function[&local_function],
-                        &self.temp_list,
-                    ));
                 }
                 crate::Statement::Atomic {
                     pointer,
@@ -2119,219 +2824,15 @@ impl<'w> BlockContext<'w> {
                     value,
                     result,
                 } => {
-                    let id = self.gen_id();
                     let result_type_id = self.get_expression_type_id(&self.fun_info[result].ty);
-
-                    self.cached[result] = id;
-
-                    let pointer_id =
-                        match self.write_expression_pointer(pointer, &mut block, None)? {
-                            ExpressionPointer::Ready { pointer_id } => pointer_id,
-                            ExpressionPointer::Conditional { .. } => {
-                                return Err(Error::FeatureNotImplemented(
-                                    "Atomics out-of-bounds handling",
-                                ));
-                            }
-                        };
-
-                    let space = self.fun_info[pointer]
-                        .ty
-                        .inner_with(&self.ir_module.types)
-                        .pointer_space()
-                        .unwrap();
-                    let (semantics, scope) = space.to_spirv_semantics_and_scope();
-                    let scope_constant_id = self.get_scope_constant(scope as u32);
-                    let semantics_id = self.get_index_constant(semantics.bits());
-                    let value_id = self.cached[value];
-                    let value_inner = self.fun_info[value].ty.inner_with(&self.ir_module.types);
-
-                    let instruction = match *fun {
-                        crate::AtomicFunction::Add => Instruction::atomic_binary(
-                            spirv::Op::AtomicIAdd,
-                            result_type_id,
-                            id,
-                            pointer_id,
-                            scope_constant_id,
-                            semantics_id,
-                            value_id,
-                        ),
-                        crate::AtomicFunction::Subtract => Instruction::atomic_binary(
-                            spirv::Op::AtomicISub,
-                            result_type_id,
-                            id,
-                            pointer_id,
-                            scope_constant_id,
-                            semantics_id,
-                            value_id,
-                        ),
-                        crate::AtomicFunction::And => Instruction::atomic_binary(
-                            spirv::Op::AtomicAnd,
-                            result_type_id,
-                            id,
-                            pointer_id,
-                            scope_constant_id,
-                            semantics_id,
-                            value_id,
-                        ),
-                        crate::AtomicFunction::InclusiveOr => Instruction::atomic_binary(
-                            spirv::Op::AtomicOr,
-                            result_type_id,
-                            id,
-                            pointer_id,
-                            scope_constant_id,
-                            semantics_id,
-                            value_id,
-                        ),
-                        crate::AtomicFunction::ExclusiveOr => Instruction::atomic_binary(
-                            spirv::Op::AtomicXor,
-                            result_type_id,
-                            id,
-                            pointer_id,
-                            scope_constant_id,
-                            semantics_id,
-                            value_id,
-                        ),
-                        crate::AtomicFunction::Min => {
-                            let spirv_op = match *value_inner {
-                                crate::TypeInner::Scalar {
-                                    kind: crate::ScalarKind::Sint,
-                                    width: _,
-                                } => spirv::Op::AtomicSMin,
-                                crate::TypeInner::Scalar {
-                                    kind: crate::ScalarKind::Uint,
-                                    width: _,
-                                } => spirv::Op::AtomicUMin,
-                                _ => unimplemented!(),
-                            };
-                            Instruction::atomic_binary(
-                                spirv_op,
-                                result_type_id,
-                                id,
-                                pointer_id,
-                                scope_constant_id,
-                                semantics_id,
-                                value_id,
-                            )
-                        }
-                        crate::AtomicFunction::Max => {
-                            let spirv_op = match *value_inner {
-                                crate::TypeInner::Scalar {
-                                    kind: crate::ScalarKind::Sint,
-                                    width: _,
-                                } => spirv::Op::AtomicSMax,
-                                crate::TypeInner::Scalar {
-                                    kind: crate::ScalarKind::Uint,
-                                    width: _,
-                                } => spirv::Op::AtomicUMax,
-                                _ => unimplemented!(),
-                            };
-                            Instruction::atomic_binary(
-                                spirv_op,
-                                result_type_id,
-                                id,
-                                pointer_id,
-                                scope_constant_id,
-                                semantics_id,
-                                value_id,
-                            )
-                        }
-                        crate::AtomicFunction::Exchange { compare: None } => {
-                            Instruction::atomic_binary(
-                                spirv::Op::AtomicExchange,
-                                result_type_id,
-                                id,
-                                pointer_id,
-                                scope_constant_id,
-                                semantics_id,
-                                value_id,
-                            )
-                        }
-                        crate::AtomicFunction::Exchange { compare: Some(cmp) } => {
-                            let scalar_type_id = match *value_inner {
-                                crate::TypeInner::Scalar { kind, width } => {
-                                    self.get_type_id(LookupType::Local(LocalType::Value {
-                                        vector_size: None,
-                                        kind,
-                                        width,
-                                        pointer_space: None,
-                                    }))
-                                }
-                                _ => unimplemented!(),
-                            };
-                            let bool_type_id =
-                                self.get_type_id(LookupType::Local(LocalType::Value {
-                                    vector_size: None,
-                                    kind: crate::ScalarKind::Bool,
-                                    width: crate::BOOL_WIDTH,
-                                    pointer_space: None,
-                                }));
-
-                            let cas_result_id = self.gen_id();
-                            let equality_result_id = self.gen_id();
-                            let mut cas_instr = Instruction::new(spirv::Op::AtomicCompareExchange);
-                            cas_instr.set_type(scalar_type_id);
-                            cas_instr.set_result(cas_result_id);
-                            cas_instr.add_operand(pointer_id);
-                            cas_instr.add_operand(scope_constant_id);
-                            cas_instr.add_operand(semantics_id); // semantics if equal
-                            cas_instr.add_operand(semantics_id); // semantics if not equal
-                            cas_instr.add_operand(value_id);
-                            cas_instr.add_operand(self.cached[cmp]);
-                            block.body.push(cas_instr);
-                            block.body.push(Instruction::binary(
-                                spirv::Op::IEqual,
-                                bool_type_id,
-                                equality_result_id,
-                                cas_result_id,
-                                self.cached[cmp],
-                            ));
-                            Instruction::composite_construct(
-                                result_type_id,
-                                id,
-                                &[cas_result_id, equality_result_id],
-                            )
-                        }
-                    };
-
-                    block.body.push(instruction);
+                    self.cached[result] =
+                        self.write_atomic(pointer, fun, value, result_type_id, &mut block)?;
                 }
                 crate::Statement::WorkGroupUniformLoad { pointer, result } => {
                     self.writer
                         .write_barrier(crate::Barrier::WORK_GROUP, &mut block);
                     let result_type_id = self.get_expression_type_id(&self.fun_info[result].ty);
-                    // Embed the body of
-                    match self.write_expression_pointer(pointer, &mut block, None)? {
-                        ExpressionPointer::Ready { pointer_id } => {
-                            let id = self.gen_id();
-                            block.body.push(Instruction::load(
-                                result_type_id,
-                                id,
-                                pointer_id,
-                                None,
-                            ));
-                            self.cached[result] = id;
-                        }
-                        ExpressionPointer::Conditional { condition, access } => {
-                            self.cached[result] = self.write_conditional_indexed_load(
-                                result_type_id,
-                                condition,
-                                &mut block,
-                                move |id_gen, block| {
-                                    // The in-bounds path. Perform the access and the load.
-                                    let pointer_id = access.result_id.unwrap();
-                                    let value_id = id_gen.next();
-                                    block.body.push(access);
-                                    block.body.push(Instruction::load(
-                                        result_type_id,
-                                        value_id,
-                                        pointer_id,
-                                        None,
-                                    ));
-                                    value_id
-                                },
-                            )
-                        }
-                    }
+                    self.cached[result] = self.write_load(pointer, result_type_id, &mut block)?;
                     self.writer
                         .write_barrier(crate::Barrier::WORK_GROUP, &mut block);
                 }
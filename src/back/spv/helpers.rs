@@ -20,6 +20,31 @@ pub(super) fn string_to_words(input: &str) -> Vec<Word> {
     words
 }
 
+/// The most bytes of source code to put in one `OpSource` or
+/// `OpSourceContinued`.
+///
+/// An instruction can't be longer than `u16::MAX` words, and `OpSource`
+/// needs four of them for its opcode and other operands, and one more byte
+/// for the literal's nul terminator.
+const MAX_SOURCE_BYTES: usize = (u16::MAX as usize - 4) * 4 - 1;
+
+/// Split `source` into pieces short enough for an `OpSource` followed by
+/// `OpSourceContinued` instructions, without splitting any characters.
+pub(super) fn split_source(mut source: &str) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    while source.len() > MAX_SOURCE_BYTES {
+        let mut end = MAX_SOURCE_BYTES;
+        while !source.is_char_boundary(end) {
+            end -= 1;
+        }
+        let (piece, rest) = source.split_at(end);
+        pieces.push(piece);
+        source = rest;
+    }
+    pieces.push(source);
+    pieces
+}
+
 pub(super) const fn map_storage_class(space: crate::AddressSpace) -> spirv::StorageClass {
     match space {
         crate::AddressSpace::Handle => spirv::StorageClass::UniformConstant,
@@ -32,20 +57,37 @@ pub(super) const fn map_storage_class(space: crate::AddressSpace) -> spirv::Stor
     }
 }
 
-pub(super) fn contains_builtin(
-    binding: Option<&crate::Binding>,
-    ty: Handle<crate::Type>,
-    arena: &UniqueArena<crate::Type>,
-    built_in: crate::BuiltIn,
-) -> bool {
-    if let Some(&crate::Binding::BuiltIn(bi)) = binding {
-        bi == built_in
-    } else if let crate::TypeInner::Struct { ref members, .. } = arena[ty].inner {
-        members
-            .iter()
-            .any(|member| contains_builtin(member.binding.as_ref(), member.ty, arena, built_in))
-    } else {
-        false // unreachable
+/// Return the capabilities that let `OpSelect` choose between pointers in
+/// `space`, most specific first.
+///
+/// SPIR-V only allows variable pointers into storage buffers and workgroup
+/// memory, so for other address spaces this is empty.
+pub(super) const fn variable_pointers_capabilities(
+    space: crate::AddressSpace,
+) -> &'static [spirv::Capability] {
+    match space {
+        crate::AddressSpace::Storage { .. } => &[
+            spirv::Capability::VariablePointersStorageBuffer,
+            spirv::Capability::VariablePointers,
+        ],
+        crate::AddressSpace::WorkGroup => &[spirv::Capability::VariablePointers],
+        _ => &[],
+    }
+}
+
+/// Return true if values of type `ty` contain `f16` values.
+pub(super) fn contains_f16(ty: Handle<crate::Type>, arena: &UniqueArena<crate::Type>) -> bool {
+    match arena[ty].inner {
+        crate::TypeInner::Scalar { width, .. }
+        | crate::TypeInner::Vector { width, .. }
+        | crate::TypeInner::Matrix { width, .. } => width == 2,
+        crate::TypeInner::Array { base, .. } | crate::TypeInner::BindingArray { base, .. } => {
+            contains_f16(base, arena)
+        }
+        crate::TypeInner::Struct { ref members, .. } => {
+            members.iter().any(|member| contains_f16(member.ty, arena))
+        }
+        _ => false,
     }
 }
 
@@ -230,6 +230,74 @@ impl Access for Store {
     fn out_of_bounds_value(&self, _ctx: &mut BlockContext<'_>) {}
 }
 
+/// Texel access information for an [`ImageAtomic`] statement.
+///
+/// [`ImageAtomic`]: crate::Statement::ImageAtomic
+struct Atomic {
+    /// The atomic instruction to apply to the texel.
+    op: spirv::Op,
+
+    /// The id of the `OpVariable` holding the image.
+    ///
+    /// `OpImageTexelPointer` wants a pointer to the image, not the image
+    /// itself.
+    image_pointer_id: Word,
+
+    /// The id of a pointer to the texel, in the `Image` storage class.
+    texel_pointer_type_id: Word,
+
+    /// The id of the texel's scalar type.
+    scalar_type_id: Word,
+
+    /// The zero sample index.
+    sample_id: Word,
+
+    /// The scope and memory semantics of the atomic.
+    scope_id: Word,
+    semantics_id: Word,
+
+    /// The value to apply to the texel.
+    value_id: Word,
+}
+
+impl Access for Atomic {
+    /// Naga's image atomics don't produce a value.
+    type Output = ();
+
+    fn generate(
+        &self,
+        id_gen: &mut IdGenerator,
+        coordinates_id: Word,
+        _level_id: Option<Word>,
+        _sample_id: Option<Word>,
+        block: &mut Block,
+    ) {
+        let pointer_id = id_gen.next();
+        block.body.push(Instruction::image_texel_pointer(
+            self.texel_pointer_type_id,
+            pointer_id,
+            self.image_pointer_id,
+            coordinates_id,
+            self.sample_id,
+        ));
+        block.body.push(Instruction::atomic_binary(
+            self.op,
+            self.scalar_type_id,
+            id_gen.next(),
+            pointer_id,
+            self.scope_id,
+            self.semantics_id,
+            self.value_id,
+        ));
+    }
+
+    /// Image atomics don't generate any value, so this just returns `()`.
+    fn result_type(&self) {}
+
+    /// Image atomics don't generate any value, so this just returns `()`.
+    fn out_of_bounds_value(&self, _ctx: &mut BlockContext<'_>) {}
+}
+
 impl<'w> BlockContext<'w> {
     /// Extend image coordinates with an array index, if necessary.
     ///
@@ -1166,6 +1234,8 @@ impl<'w> BlockContext<'w> {
     ) -> Result<(), Error> {
         let image_id = self.get_handle_id(image);
         let coordinates = self.write_image_coordinates(coordinate, array_index, block)?;
+        // `OpImageWrite` converts the texel to the image's format itself,
+        // and ignores the components the format doesn't have.
         let value_id = self.cached[value];
 
         let write = Store { image_id, value_id };
@@ -1214,4 +1284,106 @@ impl<'w> BlockContext<'w> {
 
         Ok(())
     }
+
+    pub(super) fn write_image_atomic(
+        &mut self,
+        image: Handle<crate::Expression>,
+        coordinate: Handle<crate::Expression>,
+        array_index: Option<Handle<crate::Expression>>,
+        fun: crate::AtomicFunction,
+        value: Handle<crate::Expression>,
+        block: &mut Block,
+    ) -> Result<(), Error> {
+        let image_handle_id = self.get_handle_id(image);
+        let image_pointer_id = match self.ir_function.expressions[image] {
+            crate::Expression::GlobalVariable(handle) => {
+                self.writer.global_variables[handle.index()].var_id
+            }
+            _ => {
+                return Err(Error::FeatureNotImplemented(
+                    "image atomics on function arguments",
+                ))
+            }
+        };
+        let coordinates = self.write_image_coordinates(coordinate, array_index, block)?;
+
+        let (kind, width) = match *self.fun_info[value].ty.inner_with(&self.ir_module.types) {
+            crate::TypeInner::Scalar { kind, width } => (kind, width),
+            _ => return Err(Error::Validation("image atomic value")),
+        };
+        let op = match (fun, kind) {
+            (crate::AtomicFunction::Add, _) => spirv::Op::AtomicIAdd,
+            (crate::AtomicFunction::Subtract, _) => spirv::Op::AtomicISub,
+            (crate::AtomicFunction::And, _) => spirv::Op::AtomicAnd,
+            (crate::AtomicFunction::InclusiveOr, _) => spirv::Op::AtomicOr,
+            (crate::AtomicFunction::ExclusiveOr, _) => spirv::Op::AtomicXor,
+            (crate::AtomicFunction::Min, crate::ScalarKind::Sint) => spirv::Op::AtomicSMin,
+            (crate::AtomicFunction::Min, _) => spirv::Op::AtomicUMin,
+            (crate::AtomicFunction::Max, crate::ScalarKind::Sint) => spirv::Op::AtomicSMax,
+            (crate::AtomicFunction::Max, _) => spirv::Op::AtomicUMax,
+            (crate::AtomicFunction::Exchange { compare: None }, _) => spirv::Op::AtomicExchange,
+            (crate::AtomicFunction::Exchange { compare: Some(_) }, _) => {
+                return Err(Error::Validation("image atomic compare exchange"))
+            }
+        };
+
+        let scalar_type_id = self.get_type_id(LookupType::Local(LocalType::Value {
+            vector_size: None,
+            kind,
+            width,
+            pointer_space: None,
+        }));
+        let texel_pointer_type_id = self.get_type_id(LookupType::Local(LocalType::Value {
+            vector_size: None,
+            kind,
+            width,
+            pointer_space: Some(spirv::StorageClass::Image),
+        }));
+        let atomic = Atomic {
+            op,
+            image_pointer_id,
+            texel_pointer_type_id,
+            scalar_type_id,
+            sample_id: self.get_index_constant(0),
+            scope_id: self.get_scope_constant(spirv::Scope::Device as u32),
+            semantics_id: self.get_index_constant(spirv::MemorySemantics::IMAGE_MEMORY.bits()),
+            value_id: self.cached[value],
+        };
+
+        // The bounds checks query the image's size, so they need the image
+        // itself, while the atomic needs the pointer to it.
+        match self.writer.bounds_check_policies.image_store {
+            crate::proc::BoundsCheckPolicy::Restrict => {
+                let (coords, _, _) = self.write_restricted_coordinates(
+                    image_handle_id,
+                    coordinates,
+                    None,
+                    None,
+                    block,
+                )?;
+                atomic.generate(&mut self.writer.id_gen, coords, None, None, block);
+            }
+            crate::proc::BoundsCheckPolicy::ReadZeroSkipWrite => {
+                self.write_conditional_image_access(
+                    image_handle_id,
+                    coordinates,
+                    None,
+                    None,
+                    block,
+                    &atomic,
+                )?;
+            }
+            crate::proc::BoundsCheckPolicy::Unchecked => {
+                atomic.generate(
+                    &mut self.writer.id_gen,
+                    coordinates.value_id,
+                    None,
+                    None,
+                    block,
+                );
+            }
+        }
+
+        Ok(())
+    }
 }
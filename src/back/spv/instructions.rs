@@ -28,19 +28,30 @@ impl super::Instruction {
         instruction
     }
 
+    /// Return an `OpSource`, and the `OpSourceContinued` instructions
+    /// needed to hold the rest of the source code, if it is long.
     pub(super) fn source(
         source_language: spirv::SourceLanguage,
         version: u32,
         source: &Option<DebugInfoInner>,
-    ) -> Self {
+    ) -> Vec<Self> {
         let mut instruction = Self::new(Op::Source);
         instruction.add_operand(source_language as u32);
         instruction.add_operands(helpers::bytes_to_words(&version.to_le_bytes()));
-        if let Some(source) = source.as_ref() {
-            instruction.add_operand(source.source_file_id);
-            instruction.add_operands(helpers::string_to_words(source.source_code));
+        let Some(source) = source.as_ref() else {
+            return vec![instruction];
+        };
+        instruction.add_operand(source.source_file_id);
+        let mut pieces = helpers::split_source(source.source_code).into_iter();
+        instruction.add_operands(helpers::string_to_words(pieces.next().unwrap()));
+
+        let mut instructions = vec![instruction];
+        for piece in pieces {
+            let mut continued = Self::new(Op::SourceContinued);
+            continued.add_operands(helpers::string_to_words(piece));
+            instructions.push(continued);
         }
-        instruction
+        instructions
     }
 
     pub(super) fn name(target_id: Word, name: &str) -> Self {
@@ -406,6 +417,24 @@ impl super::Instruction {
         instruction
     }
 
+    pub(super) fn spec_constant_op(
+        result_type_id: Word,
+        id: Word,
+        op: Op,
+        operand_ids: &[Word],
+    ) -> Self {
+        let mut instruction = Self::new(Op::SpecConstantOp);
+        instruction.set_type(result_type_id);
+        instruction.set_result(id);
+        instruction.add_operand(op as u32);
+
+        for operand_id in operand_ids {
+            instruction.add_operand(*operand_id);
+        }
+
+        instruction
+    }
+
     //
     //  Memory Instructions
     //
@@ -666,6 +695,22 @@ impl super::Instruction {
         instruction
     }
 
+    pub(super) fn image_texel_pointer(
+        result_type_id: Word,
+        id: Word,
+        image: Word,
+        coordinates: Word,
+        sample: Word,
+    ) -> Self {
+        let mut instruction = Self::new(Op::ImageTexelPointer);
+        instruction.set_type(result_type_id);
+        instruction.set_result(id);
+        instruction.add_operand(image);
+        instruction.add_operand(coordinates);
+        instruction.add_operand(sample);
+        instruction
+    }
+
     pub(super) fn image_query(op: Op, result_type_id: Word, id: Word, image: Word) -> Self {
         let mut instruction = Self::new(op);
         instruction.set_type(result_type_id);
@@ -708,6 +753,12 @@ impl super::Instruction {
         instruction
     }
 
+    pub(super) fn ray_query_terminate(query: Word) -> Self {
+        let mut instruction = Self::new(Op::RayQueryTerminateKHR);
+        instruction.add_operand(query);
+        instruction
+    }
+
     pub(super) fn ray_query_get_intersection(
         op: Op,
         result_type_id: Word,
@@ -1037,6 +1088,36 @@ impl super::Instruction {
         instruction.add_operand(semantics_id);
         instruction
     }
+
+    //
+    //  Non-Uniform Instructions
+    //
+
+    /// Build an `OpGroupNonUniform*` instruction on the subgroup at
+    /// `exec_scope_id`.
+    ///
+    /// `group_operation` is for the arithmetic instructions, which can
+    /// reduce or scan; `operands` are the rest.
+    pub(super) fn group_non_uniform(
+        op: Op,
+        result_type_id: Word,
+        id: Word,
+        exec_scope_id: Word,
+        group_operation: Option<spirv::GroupOperation>,
+        operands: &[Word],
+    ) -> Self {
+        let mut instruction = Self::new(op);
+        instruction.set_type(result_type_id);
+        instruction.set_result(id);
+        instruction.add_operand(exec_scope_id);
+        if let Some(group_operation) = group_operation {
+            instruction.add_operand(group_operation as u32);
+        }
+        for &operand in operands {
+            instruction.add_operand(operand);
+        }
+        instruction
+    }
 }
 
 impl From<crate::StorageFormat> for spirv::ImageFormat {
@@ -13,6 +13,7 @@ mod layout;
 mod ray;
 mod recyclable;
 mod selection;
+mod subgroup;
 mod writer;
 
 pub use spirv::Capability;
@@ -66,10 +67,16 @@ pub enum Error {
     UnsupportedVersion(u8, u8),
     #[error("using {0} requires at least one of the capabilities {1:?}, but none are available")]
     MissingCapabilities(&'static str, Vec<Capability>),
+    #[error("using {0} requires SPIR-V {1}.{2} or later")]
+    VersionTooLow(&'static str, u8, u8),
     #[error("unimplemented {0}")]
     FeatureNotImplemented(&'static str),
     #[error("module is not validated properly: {0}")]
     Validation(&'static str),
+    #[error("{0} can't be used with the {1:?} target environment")]
+    UnsupportedByTarget(&'static str, TargetEnvironment),
+    #[error("{0:?} needs a GL binding, since the module uses more than one group")]
+    MissingGlBinding(crate::ResourceBinding),
 }
 
 #[derive(Default)]
@@ -82,9 +89,14 @@ impl IdGenerator {
     }
 }
 
+/// The source code to include in the output, for debuggers.
+///
+/// See [`Options::debug_info`].
 #[derive(Debug, Clone)]
 pub struct DebugInfo<'a> {
+    /// The text of the module's primary source file.
     pub source_code: &'a str,
+    /// The name to give the source file in the output's `OpString`.
     pub file_name: &'a std::path::Path,
 }
 
@@ -558,6 +570,17 @@ struct BlockContext<'w> {
     /// The `Writer`'s temporary vector, for convenience.
     temp_list: Vec<Word>,
 
+    /// The operand to follow for each pointer [`Select`] being lowered.
+    ///
+    /// When `OpSelect` can't choose between two pointers, a load or store
+    /// through the select is written once for each operand, and this table
+    /// tells [`write_expression_pointer`] which operand the current copy uses.
+    ///
+    /// [`Select`]: crate::Expression::Select
+    /// [`write_expression_pointer`]: BlockContext::write_expression_pointer
+    pointer_select_choices:
+        crate::FastHashMap<Handle<crate::Expression>, Handle<crate::Expression>>,
+
     /// Tracks the constness of `Expression`s residing in `self.ir_function.expressions`
     expression_constness: crate::proc::ExpressionConstnessTracker,
 }
@@ -591,6 +614,23 @@ struct LoopContext {
     break_id: Option<Word>,
 }
 
+/// A SPIR-V writer, which can translate any number of modules.
+///
+/// Translating many modules with the same `Writer` is cheaper than creating a
+/// `Writer` for each: [`Writer::write`] clears the tables left over from the
+/// last module, but keeps their allocations, so a warmed-up `Writer` rarely
+/// needs to allocate. All modules are translated with the [`Options`] the
+/// `Writer` was created with.
+///
+/// ```ignore
+/// let mut writer = Writer::new(&options)?;
+/// let mut words = Vec::new();
+/// for (module, info) in modules {
+///     words.clear();
+///     writer.write(module, info, None, &None, &mut words)?;
+///     consume(&words);
+/// }
+/// ```
 pub struct Writer {
     physical_layout: PhysicalLayout,
     logical_layout: LogicalLayout,
@@ -615,6 +655,7 @@ pub struct Writer {
     flags: WriterFlags,
     bounds_check_policies: BoundsCheckPolicies,
     zero_initialize_workgroup_memory: ZeroInitializeWorkgroupMemoryMode,
+    target_environment: TargetEnvironment,
     void_type: Word,
     //TODO: convert most of these into vectors, addressable by handle indices
     lookup_type: crate::FastHashMap<LookupType, Word>,
@@ -642,6 +683,8 @@ bitflags::bitflags! {
         /// Include debug labels for everything.
         const DEBUG = 0x1;
         /// Flip Y coordinate of `BuiltIn::Position` output.
+        ///
+        /// For [`TargetEnvironment::OpenGl`], also map its depth to `-1..1`.
         const ADJUST_COORDINATE_SPACE = 0x2;
         /// Emit `OpName` for input/output locations.
         /// Contrary to spec, some drivers treat it as semantic, not allowing
@@ -652,15 +695,25 @@ bitflags::bitflags! {
         const FORCE_POINT_SIZE = 0x8;
         /// Clamp `BuiltIn::FragDepth` output between 0 and 1.
         const CLAMP_FRAG_DEPTH = 0x10;
+        /// Decorate all storage buffers with `Restrict`.
+        ///
+        /// Only set this if the caller guarantees that no two storage buffer
+        /// bindings used by a shader alias the same memory.
+        const RESTRICT_STORAGE_BUFFERS = 0x20;
     }
 }
 
 #[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serialize", derive(serde::Serialize))]
 #[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
+#[cfg_attr(feature = "deserialize", serde(default))]
 pub struct BindingInfo {
     /// If the binding is an unsized binding array, this overrides the size.
     pub binding_array_size: Option<u32>,
+    /// The binding point to use for [`TargetEnvironment::OpenGl`], which has
+    /// no descriptor sets. If this is `None`, the resource's own binding
+    /// number is used, as long as all resources are in the same group.
+    pub gl_binding: Option<u32>,
 }
 
 // Using `BTreeMap` instead of `HashMap` so that we can hash itself.
@@ -675,6 +728,28 @@ pub enum ZeroInitializeWorkgroupMemoryMode {
     None,
 }
 
+/// The API the SPIR-V is written for.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TargetEnvironment {
+    /// Vulkan, or anything else taking the same flavor of SPIR-V.
+    #[default]
+    Vulkan,
+    /// OpenGL, through `ARB_gl_spirv` or OpenGL 4.6.
+    ///
+    /// The output must be SPIR-V 1.0. It leaves out `DescriptorSet`
+    /// decorations, and binds each resource at its
+    /// [`BindingInfo::gl_binding`], which is required if the module uses more
+    /// than one group. The vertex stage's `position`, `point_size` and
+    /// clip and cull distance outputs are members of a `gl_PerVertex` block,
+    /// as OpenGL's interface matching expects. `vertex_index` and
+    /// `instance_index` use OpenGL's `VertexId` and `InstanceId` built-ins;
+    /// like the GLSL backend's `gl_InstanceID`, the latter doesn't include
+    /// the base instance. With [`WriterFlags::ADJUST_COORDINATE_SPACE`], the
+    /// depth of the position output is also mapped from `0..1` to `-1..1`,
+    /// as the GLSL backend does. Push constants aren't supported.
+    OpenGl,
+}
+
 #[derive(Debug, Clone)]
 pub struct Options<'a> {
     /// (Major, Minor) target version of the SPIR-V.
@@ -697,9 +772,24 @@ pub struct Options<'a> {
     pub bounds_check_policies: BoundsCheckPolicies,
 
     /// Dictates the way workgroup variables should be zero initialized
+    ///
+    /// Use [`ZeroInitializeWorkgroupMemoryMode::None`] for modules that have
+    /// been through [`zero_initialize_workgroup_memory`], which does the same
+    /// in the IR.
+    ///
+    /// [`zero_initialize_workgroup_memory`]: crate::proc::zero_initialize_workgroup_memory
     pub zero_initialize_workgroup_memory: ZeroInitializeWorkgroupMemoryMode,
 
+    /// The source code the module was parsed from, for debuggers.
+    ///
+    /// If this is given and [`WriterFlags::DEBUG`] is set, the output holds
+    /// the source code in `OpSource`, and an `OpLine` before each statement
+    /// with a span in it, so that tools like RenderDoc can step through the
+    /// original source. Spans that refer to other source files are left out.
     pub debug_info: Option<DebugInfo<'a>>,
+
+    /// The API the output is for.
+    pub target_environment: TargetEnvironment,
 }
 
 impl<'a> Default for Options<'a> {
@@ -718,6 +808,7 @@ impl<'a> Default for Options<'a> {
             bounds_check_policies: crate::proc::BoundsCheckPolicies::default(),
             zero_initialize_workgroup_memory: ZeroInitializeWorkgroupMemoryMode::Polyfill,
             debug_info: None,
+            target_environment: TargetEnvironment::Vulkan,
         }
     }
 }
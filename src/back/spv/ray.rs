@@ -106,20 +106,28 @@ impl<'w> BlockContext<'w> {
                     .body
                     .push(Instruction::ray_query_proceed(result_type_id, id, query_id));
             }
-            crate::RayQueryFunction::Terminate => {}
+            crate::RayQueryFunction::Terminate => {
+                block.body.push(Instruction::ray_query_terminate(query_id));
+            }
         }
     }
 
     pub(super) fn write_ray_query_get_intersection(
         &mut self,
         query: Handle<crate::Expression>,
+        committed: bool,
         block: &mut Block,
     ) -> spirv::Word {
         let width = 4;
         let query_id = self.cached[query];
-        let intersection_id = self.writer.get_constant_scalar(crate::Literal::U32(
-            spirv::RayQueryIntersection::RayQueryCommittedIntersectionKHR as _,
-        ));
+        let intersection = if committed {
+            spirv::RayQueryIntersection::RayQueryCommittedIntersectionKHR
+        } else {
+            spirv::RayQueryIntersection::RayQueryCandidateIntersectionKHR
+        };
+        let intersection_id = self
+            .writer
+            .get_constant_scalar(crate::Literal::U32(intersection as _));
 
         let flag_type_id = self.get_type_id(LookupType::Local(LocalType::Value {
             vector_size: None,
@@ -127,14 +135,47 @@ impl<'w> BlockContext<'w> {
             width,
             pointer_space: None,
         }));
-        let kind_id = self.gen_id();
+        let raw_kind_id = self.gen_id();
         block.body.push(Instruction::ray_query_get_intersection(
             spirv::Op::RayQueryGetIntersectionTypeKHR,
             flag_type_id,
-            kind_id,
+            raw_kind_id,
             query_id,
             intersection_id,
         ));
+        let kind_id = if committed {
+            // Committed intersection types match `RAY_QUERY_INTERSECTION_*`.
+            raw_kind_id
+        } else {
+            // Candidates are numbered differently: 0 for a triangle, and 1
+            // for an AABB. Translate them to `RAY_QUERY_INTERSECTION_*`.
+            let bool_type_id = self.get_type_id(LookupType::Local(LocalType::Value {
+                vector_size: None,
+                kind: crate::ScalarKind::Bool,
+                width: crate::BOOL_WIDTH,
+                pointer_space: None,
+            }));
+            let candidate_triangle_id = self.writer.get_constant_scalar(crate::Literal::U32(0));
+            let is_triangle_id = self.gen_id();
+            block.body.push(Instruction::binary(
+                spirv::Op::IEqual,
+                bool_type_id,
+                is_triangle_id,
+                raw_kind_id,
+                candidate_triangle_id,
+            ));
+            let triangle_id = self.writer.get_constant_scalar(crate::Literal::U32(1));
+            let aabb_id = self.writer.get_constant_scalar(crate::Literal::U32(4));
+            let kind_id = self.gen_id();
+            block.body.push(Instruction::select(
+                flag_type_id,
+                kind_id,
+                is_triangle_id,
+                triangle_id,
+                aabb_id,
+            ));
+            kind_id
+        };
         let instance_custom_index_id = self.gen_id();
         block.body.push(Instruction::ray_query_get_intersection(
             spirv::Op::RayQueryGetIntersectionInstanceCustomIndexKHR,
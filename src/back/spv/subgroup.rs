@@ -0,0 +1,106 @@
+/*!
+Generating SPIR-V for subgroup operations.
+*/
+
+use super::{Block, BlockContext, Error, Instruction};
+use crate::arena::Handle;
+use spirv::Word;
+
+impl<'w> BlockContext<'w> {
+    pub(super) fn write_subgroup_operation(
+        &mut self,
+        result_type_id: Word,
+        op: crate::SubgroupOperation,
+        argument: Handle<crate::Expression>,
+        block: &mut Block,
+    ) -> Result<Word, Error> {
+        use crate::{ScalarKind as Sk, SubgroupOperation as So, SubgroupReduction as Sr};
+        use spirv::{Capability as Cap, GroupOperation as Go, Op};
+
+        // `OpGroupNonUniform*` instructions arrived in SPIR-V 1.3.
+        if self.writer.physical_layout.version < 0x10300 {
+            return Err(Error::VersionTooLow("subgroup operations", 1, 3));
+        }
+
+        let mut operands = vec![self.cached[argument]];
+        operands.extend(op.index().map(|index| self.cached[index]));
+
+        let kind = self.fun_info[argument]
+            .ty
+            .inner_with(&self.ir_module.types)
+            .scalar_kind();
+        let (spirv_op, capability, group_operation) = match op {
+            So::Ballot => (Op::GroupNonUniformBallot, Cap::GroupNonUniformBallot, None),
+            So::All => (Op::GroupNonUniformAll, Cap::GroupNonUniformVote, None),
+            So::Any => (Op::GroupNonUniformAny, Cap::GroupNonUniformVote, None),
+            So::Reduce(reduction) | So::InclusiveScan(reduction) | So::ExclusiveScan(reduction) => {
+                let group_operation = match op {
+                    So::Reduce(_) => Go::Reduce,
+                    So::InclusiveScan(_) => Go::InclusiveScan,
+                    _ => Go::ExclusiveScan,
+                };
+                let op = match (reduction, kind) {
+                    (Sr::Add, Some(Sk::Float)) => Op::GroupNonUniformFAdd,
+                    (Sr::Add, _) => Op::GroupNonUniformIAdd,
+                    (Sr::Mul, Some(Sk::Float)) => Op::GroupNonUniformFMul,
+                    (Sr::Mul, _) => Op::GroupNonUniformIMul,
+                    (Sr::Min, Some(Sk::Float)) => Op::GroupNonUniformFMin,
+                    (Sr::Min, Some(Sk::Sint)) => Op::GroupNonUniformSMin,
+                    (Sr::Min, _) => Op::GroupNonUniformUMin,
+                    (Sr::Max, Some(Sk::Float)) => Op::GroupNonUniformFMax,
+                    (Sr::Max, Some(Sk::Sint)) => Op::GroupNonUniformSMax,
+                    (Sr::Max, _) => Op::GroupNonUniformUMax,
+                    (Sr::And, _) => Op::GroupNonUniformBitwiseAnd,
+                    (Sr::Or, _) => Op::GroupNonUniformBitwiseOr,
+                    (Sr::Xor, _) => Op::GroupNonUniformBitwiseXor,
+                };
+                (op, Cap::GroupNonUniformArithmetic, Some(group_operation))
+            }
+            So::BroadcastFirst => (
+                Op::GroupNonUniformBroadcastFirst,
+                Cap::GroupNonUniformBallot,
+                None,
+            ),
+            So::Broadcast(_) => (
+                Op::GroupNonUniformBroadcast,
+                Cap::GroupNonUniformBallot,
+                None,
+            ),
+            So::Shuffle(_) => (
+                Op::GroupNonUniformShuffle,
+                Cap::GroupNonUniformShuffle,
+                None,
+            ),
+            So::ShuffleXor(_) => (
+                Op::GroupNonUniformShuffleXor,
+                Cap::GroupNonUniformShuffle,
+                None,
+            ),
+            So::ShuffleDown(_) => (
+                Op::GroupNonUniformShuffleDown,
+                Cap::GroupNonUniformShuffleRelative,
+                None,
+            ),
+            So::ShuffleUp(_) => (
+                Op::GroupNonUniformShuffleUp,
+                Cap::GroupNonUniformShuffleRelative,
+                None,
+            ),
+        };
+        // Each of these implicitly declares `GroupNonUniform`.
+        self.writer
+            .require_any("subgroup operations", &[capability])?;
+
+        let exec_scope_id = self.get_index_constant(spirv::Scope::Subgroup as u32);
+        let id = self.gen_id();
+        block.body.push(Instruction::group_non_uniform(
+            spirv_op,
+            result_type_id,
+            id,
+            exec_scope_id,
+            group_operation,
+            &operands,
+        ));
+        Ok(id)
+    }
+}
@@ -1,14 +1,15 @@
 use super::{
     block::DebugInfoInner,
-    helpers::{contains_builtin, global_needs_wrapper, map_storage_class},
+    helpers::{contains_f16, global_needs_wrapper, map_storage_class},
     make_local, Block, BlockContext, CachedConstant, CachedExpressions, DebugInfo,
     EntryPointContext, Error, Function, FunctionArgument, GlobalVariable, IdGenerator, Instruction,
     LocalType, LocalVariable, LogicalLayout, LookupFunctionType, LookupType, LoopContext, Options,
-    PhysicalLayout, PipelineOptions, ResultMember, Writer, WriterFlags, BITS_PER_BYTE,
+    PhysicalLayout, PipelineOptions, ResultMember, TargetEnvironment, Writer, WriterFlags,
+    BITS_PER_BYTE,
 };
 use crate::{
     arena::{Handle, UniqueArena},
-    back::spv::BindingInfo,
+    back::{self, spv::BindingInfo},
     proc::{Alignment, TypeResolution},
     valid::{FunctionInfo, ModuleInfo},
 };
@@ -40,12 +41,33 @@ impl Function {
     }
 }
 
+/// Return true if `binding` is one of the outputs in OpenGL's
+/// `gl_PerVertex` block.
+const fn is_per_vertex(binding: &crate::Binding) -> bool {
+    matches!(
+        *binding,
+        crate::Binding::BuiltIn(
+            crate::BuiltIn::Position { .. }
+                | crate::BuiltIn::PointSize
+                | crate::BuiltIn::ClipDistance
+                | crate::BuiltIn::CullDistance
+        )
+    )
+}
+
 impl Writer {
     pub fn new(options: &Options) -> Result<Self, Error> {
         let (major, minor) = options.lang_version;
         if major != 1 {
             return Err(Error::UnsupportedVersion(major, minor));
         }
+        // `ARB_gl_spirv` and OpenGL 4.6 only take SPIR-V 1.0.
+        if options.target_environment == TargetEnvironment::OpenGl && minor != 0 {
+            return Err(Error::UnsupportedByTarget(
+                "SPIR-V versions after 1.0",
+                options.target_environment,
+            ));
+        }
         let raw_version = ((major as u32) << 16) | ((minor as u32) << 8);
 
         let mut capabilities_used = crate::FastIndexSet::default();
@@ -67,6 +89,7 @@ impl Writer {
             flags: options.flags,
             bounds_check_policies: options.bounds_check_policies,
             zero_initialize_workgroup_memory: options.zero_initialize_workgroup_memory,
+            target_environment: options.target_environment,
             void_type,
             lookup_type: crate::FastHashMap::default(),
             lookup_function: crate::FastHashMap::default(),
@@ -83,6 +106,10 @@ impl Writer {
 
     /// Reset `Writer` to its initial state, retaining any allocations.
     ///
+    /// [`write`] calls this before translating a module, so there is no need to
+    /// call it between modules. It is useful to forget the last module, and
+    /// the results [`get_capabilities_used`] reports for it, early.
+    ///
     /// Why not just implement `Recyclable` for `Writer`? By design,
     /// `Recyclable::recycle` requires ownership of the value, not just
     /// `&mut`; see the trait documentation. But we need to use this method
@@ -90,7 +117,10 @@ impl Writer {
     /// Workarounds include unsafe code (`std::ptr::read`, then `write`, ugh)
     /// or something like a `Default` impl that returns an oddly-initialized
     /// `Writer`, which is worse.
-    fn reset(&mut self) {
+    ///
+    /// [`write`]: Writer::write
+    /// [`get_capabilities_used`]: Writer::get_capabilities_used
+    pub fn reset(&mut self) {
         use super::recyclable::Recyclable;
         use std::mem::take;
 
@@ -105,6 +135,7 @@ impl Writer {
             flags: self.flags,
             bounds_check_policies: self.bounds_check_policies,
             zero_initialize_workgroup_memory: self.zero_initialize_workgroup_memory,
+            target_environment: self.target_environment,
             capabilities_available: take(&mut self.capabilities_available),
             binding_map: take(&mut self.binding_map),
 
@@ -176,6 +207,19 @@ impl Writer {
         }
     }
 
+    /// Return true if any of `capabilities` is available.
+    ///
+    /// Unlike [`require_any`], this doesn't record a requirement, so it can be
+    /// used to decide whether to generate code that avoids them.
+    ///
+    /// [`require_any`]: Writer::require_any
+    pub(super) fn any_available(&self, capabilities: &[spirv::Capability]) -> bool {
+        match self.capabilities_available {
+            None => !capabilities.is_empty(),
+            Some(ref available) => capabilities.iter().any(|cap| available.contains(cap)),
+        }
+    }
+
     /// Indicate that the code uses the given extension.
     pub(super) fn use_extension(&mut self, extension: &'static str) {
         self.extensions_used.insert(extension);
@@ -453,19 +497,32 @@ impl Writer {
                 if let Some(ref mut iface) = interface {
                     let mut has_point_size = false;
                     let class = spirv::StorageClass::Output;
+                    // OpenGL expects the vertex stage's built-in outputs
+                    // in a `gl_PerVertex` block. Its members' pointers are
+                    // filled in once the block is written.
+                    let uses_per_vertex_block = self.target_environment
+                        == TargetEnvironment::OpenGl
+                        && iface.stage == crate::ShaderStage::Vertex;
+                    let mut per_vertex_members = Vec::new();
                     if let Some(ref binding) = result.binding {
                         has_point_size |=
                             *binding == crate::Binding::BuiltIn(crate::BuiltIn::PointSize);
                         let type_id = self.get_type_id(LookupType::Handle(result.ty));
-                        let varying_id = self.write_varying(
-                            ir_module,
-                            iface.stage,
-                            class,
-                            None,
-                            result.ty,
-                            binding,
-                        )?;
-                        iface.varying_ids.push(varying_id);
+                        let varying_id = if uses_per_vertex_block && is_per_vertex(binding) {
+                            per_vertex_members.push((result.ty, binding, ep_context.results.len()));
+                            0
+                        } else {
+                            let varying_id = self.write_varying(
+                                ir_module,
+                                iface.stage,
+                                class,
+                                None,
+                                result.ty,
+                                binding,
+                            )?;
+                            iface.varying_ids.push(varying_id);
+                            varying_id
+                        };
                         ep_context.results.push(ResultMember {
                             id: varying_id,
                             type_id,
@@ -480,15 +537,25 @@ impl Writer {
                             let binding = member.binding.as_ref().unwrap();
                             has_point_size |=
                                 *binding == crate::Binding::BuiltIn(crate::BuiltIn::PointSize);
-                            let varying_id = self.write_varying(
-                                ir_module,
-                                iface.stage,
-                                class,
-                                name,
-                                member.ty,
-                                binding,
-                            )?;
-                            iface.varying_ids.push(varying_id);
+                            let varying_id = if uses_per_vertex_block && is_per_vertex(binding) {
+                                per_vertex_members.push((
+                                    member.ty,
+                                    binding,
+                                    ep_context.results.len(),
+                                ));
+                                0
+                            } else {
+                                let varying_id = self.write_varying(
+                                    ir_module,
+                                    iface.stage,
+                                    class,
+                                    name,
+                                    member.ty,
+                                    binding,
+                                )?;
+                                iface.varying_ids.push(varying_id);
+                                varying_id
+                            };
                             ep_context.results.push(ResultMember {
                                 id: varying_id,
                                 type_id,
@@ -499,10 +566,23 @@ impl Writer {
                         unreachable!("Missing result binding on an entry point");
                     }
 
-                    if self.flags.contains(WriterFlags::FORCE_POINT_SIZE)
+                    let force_point_size = self.flags.contains(WriterFlags::FORCE_POINT_SIZE)
                         && iface.stage == crate::ShaderStage::Vertex
-                        && !has_point_size
+                        && !has_point_size;
+                    if uses_per_vertex_block && (force_point_size || !per_vertex_members.is_empty())
                     {
+                        let member_ids = self.write_per_vertex_block(
+                            ir_module,
+                            &per_vertex_members,
+                            force_point_size,
+                            iface,
+                            &mut prelude,
+                        )?;
+                        for (&(_, _, result_index), id) in per_vertex_members.iter().zip(member_ids)
+                        {
+                            ep_context.results[result_index].id = id;
+                        }
+                    } else if force_point_size {
                         // add point size artificially
                         let varying_id = self.id_gen.next();
                         let pointer_type_id = self.get_float_pointer_type_id(class);
@@ -619,6 +699,7 @@ impl Writer {
 
             // Steal the Writer's temp list for a bit.
             temp_list: std::mem::take(&mut self.temp_list),
+            pointer_select_choices: crate::FastHashMap::default(),
             writer: self,
             expression_constness: crate::proc::ExpressionConstnessTracker::from_arena(
                 &ir_function.expressions,
@@ -765,18 +846,27 @@ impl Writer {
             crate::ShaderStage::Vertex => spirv::ExecutionModel::Vertex,
             crate::ShaderStage::Fragment => {
                 self.write_execution_mode(function_id, spirv::ExecutionMode::OriginUpperLeft)?;
-                if let Some(ref result) = entry_point.function.result {
-                    if contains_builtin(
-                        result.binding.as_ref(),
-                        result.ty,
-                        &ir_module.types,
-                        crate::BuiltIn::FragDepth,
-                    ) {
+                if info.writes_depth {
+                    self.write_execution_mode(function_id, spirv::ExecutionMode::DepthReplacing)?;
+                }
+                match entry_point.early_depth(info) {
+                    Some(back::EarlyDepth::Force) => {
                         self.write_execution_mode(
                             function_id,
-                            spirv::ExecutionMode::DepthReplacing,
+                            spirv::ExecutionMode::EarlyFragmentTests,
                         )?;
                     }
+                    Some(back::EarlyDepth::Conservative(conservative)) => {
+                        use crate::ConservativeDepth as Cd;
+
+                        let mode = match conservative {
+                            Cd::GreaterEqual => spirv::ExecutionMode::DepthGreater,
+                            Cd::LessEqual => spirv::ExecutionMode::DepthLess,
+                            Cd::Unchanged => spirv::ExecutionMode::DepthUnchanged,
+                        };
+                        self.write_execution_mode(function_id, mode)?;
+                    }
+                    None => {}
                 }
                 spirv::ExecutionModel::Fragment
             }
@@ -830,8 +920,14 @@ impl Writer {
                 Instruction::type_int(id, bits, signedness)
             }
             Sk::Float => {
-                if bits == 64 {
-                    self.capabilities_used.insert(spirv::Capability::Float64);
+                match bits {
+                    16 => {
+                        self.capabilities_used.insert(spirv::Capability::Float16);
+                    }
+                    64 => {
+                        self.capabilities_used.insert(spirv::Capability::Float64);
+                    }
+                    _ => {}
                 }
                 Instruction::type_float(id, bits)
             }
@@ -885,6 +981,9 @@ impl Writer {
             crate::TypeInner::RayQuery => {
                 self.require_any("Ray Query", &[spirv::Capability::RayQueryKHR])?;
             }
+            crate::TypeInner::Atomic { width: 8, .. } => {
+                self.require_any("64-bit integer atomics", &[spirv::Capability::Int64Atomics])?;
+            }
             _ => {}
         }
         Ok(())
@@ -1195,8 +1294,17 @@ impl Writer {
                 Instruction::constant_64bit(type_id, id, bits as u32, (bits >> 32) as u32)
             }
             crate::Literal::F32(value) => Instruction::constant_32bit(type_id, id, value.to_bits()),
+            // Constants narrower than 32 bits take a whole word, zero-extended.
+            crate::Literal::F16(bits) => Instruction::constant_32bit(type_id, id, bits as u32),
             crate::Literal::U32(value) => Instruction::constant_32bit(type_id, id, value),
             crate::Literal::I32(value) => Instruction::constant_32bit(type_id, id, value as u32),
+            crate::Literal::U64(value) => {
+                Instruction::constant_64bit(type_id, id, value as u32, (value >> 32) as u32)
+            }
+            crate::Literal::I64(value) => {
+                let bits = value as u64;
+                Instruction::constant_64bit(type_id, id, bits as u32, (bits >> 32) as u32)
+            }
             crate::Literal::Bool(true) => Instruction::constant_true(type_id, id),
             crate::Literal::Bool(false) => Instruction::constant_false(type_id, id),
         };
@@ -1256,6 +1364,25 @@ impl Writer {
         null_id
     }
 
+    /// Return the id of a constant array of type `ty` whose elements are all
+    /// `value_id`.
+    ///
+    /// If the elements are zero, the whole array is an `OpConstantNull`.
+    pub(super) fn get_constant_repeat(
+        &mut self,
+        ty: Handle<crate::Type>,
+        value_id: Word,
+        is_zero: bool,
+        types: &crate::UniqueArena<crate::Type>,
+    ) -> Word {
+        if is_zero {
+            let type_id = self.get_type_id(LookupType::Handle(ty));
+            return self.get_constant_null(type_id);
+        }
+        let count = types[ty].inner.components().unwrap_or(0) as usize;
+        self.get_constant_composite(LookupType::Handle(ty), &vec![value_id; count])
+    }
+
     fn write_constant_expr(
         &mut self,
         handle: Handle<crate::Expression>,
@@ -1283,6 +1410,15 @@ impl Writer {
                 .collect();
                 self.get_constant_composite(LookupType::Handle(ty), component_ids.as_slice())
             }
+            crate::Expression::Repeat { ty, value } => {
+                let is_zero = crate::proc::is_zero_value(value, &ir_module.const_expressions);
+                self.get_constant_repeat(
+                    ty,
+                    self.constant_ids[value.index()],
+                    is_zero,
+                    &ir_module.types,
+                )
+            }
             crate::Expression::Splat { size, value } => {
                 let value_id = self.constant_ids[value.index()];
                 let component_ids = &[value_id; 4][..size as usize];
@@ -1291,7 +1427,9 @@ impl Writer {
 
                 self.get_constant_composite(ty, component_ids)
             }
-            _ => unreachable!(),
+            ref expression => {
+                self.write_spec_constant_op(expression, handle, ir_module, mod_info)?
+            }
         };
 
         self.constant_ids[handle.index()] = id;
@@ -1299,6 +1437,143 @@ impl Writer {
         Ok(id)
     }
 
+    /// Write an [override expression] as an `OpSpecConstantOp`.
+    ///
+    /// Only the operations that the `Shader` capability allows are
+    /// supported, so floating-point operations are rejected.
+    ///
+    /// [override expression]: crate#constant-expressions
+    fn write_spec_constant_op(
+        &mut self,
+        expression: &crate::Expression,
+        handle: Handle<crate::Expression>,
+        ir_module: &crate::Module,
+        mod_info: &ModuleInfo,
+    ) -> Result<Word, Error> {
+        use crate::{BinaryOperator as Bo, ScalarKind as Sk, UnaryOperator as Uo};
+        use spirv::Op;
+
+        let kind_of = |expr: Handle<crate::Expression>| {
+            mod_info[expr]
+                .inner_with(&ir_module.types)
+                .scalar_kind()
+                .ok_or(Error::Validation("override expression operand"))
+        };
+        let not_implemented = Error::FeatureNotImplemented("floating-point override expression");
+
+        let (op, operands) = match *expression {
+            crate::Expression::Unary { op, expr } => {
+                let op = match (op, kind_of(expr)?) {
+                    (Uo::Negate, Sk::Sint) => Op::SNegate,
+                    (Uo::LogicalNot, Sk::Bool) => Op::LogicalNot,
+                    (Uo::BitwiseNot, Sk::Sint | Sk::Uint) => Op::Not,
+                    _ => return Err(not_implemented),
+                };
+                (op, vec![self.constant_ids[expr.index()]])
+            }
+            crate::Expression::Binary { op, left, right } => {
+                let op = match (op, kind_of(left)?) {
+                    (Bo::Add, Sk::Sint | Sk::Uint) => Op::IAdd,
+                    (Bo::Subtract, Sk::Sint | Sk::Uint) => Op::ISub,
+                    (Bo::Multiply, Sk::Sint | Sk::Uint) => Op::IMul,
+                    (Bo::Divide, Sk::Sint) => Op::SDiv,
+                    (Bo::Divide, Sk::Uint) => Op::UDiv,
+                    (Bo::Modulo, Sk::Sint) => Op::SRem,
+                    (Bo::Modulo, Sk::Uint) => Op::UMod,
+                    (Bo::Equal, Sk::Sint | Sk::Uint) => Op::IEqual,
+                    (Bo::Equal, Sk::Bool) => Op::LogicalEqual,
+                    (Bo::NotEqual, Sk::Sint | Sk::Uint) => Op::INotEqual,
+                    (Bo::NotEqual, Sk::Bool) => Op::LogicalNotEqual,
+                    (Bo::Less, Sk::Sint) => Op::SLessThan,
+                    (Bo::Less, Sk::Uint) => Op::ULessThan,
+                    (Bo::LessEqual, Sk::Sint) => Op::SLessThanEqual,
+                    (Bo::LessEqual, Sk::Uint) => Op::ULessThanEqual,
+                    (Bo::Greater, Sk::Sint) => Op::SGreaterThan,
+                    (Bo::Greater, Sk::Uint) => Op::UGreaterThan,
+                    (Bo::GreaterEqual, Sk::Sint) => Op::SGreaterThanEqual,
+                    (Bo::GreaterEqual, Sk::Uint) => Op::UGreaterThanEqual,
+                    (Bo::And, Sk::Sint | Sk::Uint) => Op::BitwiseAnd,
+                    (Bo::And | Bo::LogicalAnd, Sk::Bool) => Op::LogicalAnd,
+                    (Bo::InclusiveOr, Sk::Sint | Sk::Uint) => Op::BitwiseOr,
+                    (Bo::InclusiveOr | Bo::LogicalOr, Sk::Bool) => Op::LogicalOr,
+                    (Bo::ExclusiveOr, Sk::Sint | Sk::Uint) => Op::BitwiseXor,
+                    (Bo::ShiftLeft, Sk::Sint | Sk::Uint) => Op::ShiftLeftLogical,
+                    (Bo::ShiftRight, Sk::Sint) => Op::ShiftRightArithmetic,
+                    (Bo::ShiftRight, Sk::Uint) => Op::ShiftRightLogical,
+                    _ => return Err(not_implemented),
+                };
+                let operands = vec![
+                    self.constant_ids[left.index()],
+                    self.constant_ids[right.index()],
+                ];
+                (op, operands)
+            }
+            crate::Expression::Select {
+                condition,
+                accept,
+                reject,
+            } => {
+                let operands = vec![
+                    self.constant_ids[condition.index()],
+                    self.constant_ids[accept.index()],
+                    self.constant_ids[reject.index()],
+                ];
+                (Op::Select, operands)
+            }
+            crate::Expression::AccessIndex { base, index } => (
+                Op::CompositeExtract,
+                vec![self.constant_ids[base.index()], index],
+            ),
+            crate::Expression::As {
+                expr,
+                kind,
+                convert,
+            } => {
+                let (from, width) = match *mod_info[expr].inner_with(&ir_module.types) {
+                    crate::TypeInner::Scalar { kind, width } => (kind, width),
+                    _ => return Err(Error::FeatureNotImplemented("vector override cast")),
+                };
+                let value_id = self.constant_ids[expr.index()];
+                match (from, kind) {
+                    // Integers of the same width only differ in their type,
+                    // so adding zero reinterprets them.
+                    (Sk::Sint | Sk::Uint, Sk::Sint | Sk::Uint)
+                        if convert.map_or(true, |convert| convert == width) =>
+                    {
+                        let zero = crate::Literal::zero(from, width).unwrap();
+                        (Op::IAdd, vec![value_id, self.get_constant_scalar(zero)])
+                    }
+                    (Sk::Bool, Sk::Sint | Sk::Uint) => {
+                        let width = convert.unwrap_or(4);
+                        let one = crate::Literal::one(kind, width).unwrap();
+                        let zero = crate::Literal::zero(kind, width).unwrap();
+                        let operands = vec![
+                            value_id,
+                            self.get_constant_scalar(one),
+                            self.get_constant_scalar(zero),
+                        ];
+                        (Op::Select, operands)
+                    }
+                    (Sk::Sint | Sk::Uint, Sk::Bool) => {
+                        let zero = crate::Literal::zero(from, width).unwrap();
+                        (
+                            Op::INotEqual,
+                            vec![value_id, self.get_constant_scalar(zero)],
+                        )
+                    }
+                    _ => return Err(not_implemented),
+                }
+            }
+            _ => unreachable!(),
+        };
+
+        let type_id = self.get_expression_type_id(&mod_info[handle]);
+        let id = self.id_gen.next();
+        Instruction::spec_constant_op(type_id, id, op, &operands)
+            .to_words(&mut self.logical_layout.declarations);
+        Ok(id)
+    }
+
     pub(super) fn write_barrier(&mut self, flags: crate::Barrier, block: &mut Block) {
         let memory_scope = if flags.contains(crate::Barrier::STORAGE) {
             spirv::Scope::Device
@@ -1431,6 +1706,119 @@ impl Writer {
         Some(next_id)
     }
 
+    /// Write the `gl_PerVertex` block of an OpenGL vertex shader.
+    ///
+    /// `members` are the entry point's outputs that belong in the block,
+    /// each with its type and binding, and `force_point_size` adds a point
+    /// size of 1. The members' pointers are accessed in `prelude` and
+    /// returned in the order of `members`.
+    fn write_per_vertex_block(
+        &mut self,
+        ir_module: &crate::Module,
+        members: &[(Handle<crate::Type>, &crate::Binding, usize)],
+        force_point_size: bool,
+        iface: &mut FunctionInterface,
+        prelude: &mut Block,
+    ) -> Result<Vec<Word>, Error> {
+        use spirv::{BuiltIn, Decoration};
+
+        let class = spirv::StorageClass::Output;
+        let mut member_type_ids = Vec::with_capacity(members.len() + 1);
+        let mut pointer_type_ids = Vec::with_capacity(members.len() + 1);
+        let struct_id = self.id_gen.next();
+        for (index, &(ty, binding, _)) in members.iter().enumerate() {
+            let index = index as Word;
+            let built_in = match *binding {
+                crate::Binding::BuiltIn(crate::BuiltIn::Position { invariant }) => {
+                    if invariant {
+                        self.annotations.push(Instruction::member_decorate(
+                            struct_id,
+                            index,
+                            Decoration::Invariant,
+                            &[],
+                        ));
+                    }
+                    BuiltIn::Position
+                }
+                crate::Binding::BuiltIn(crate::BuiltIn::PointSize) => BuiltIn::PointSize,
+                crate::Binding::BuiltIn(crate::BuiltIn::ClipDistance) => {
+                    self.require_any(
+                        "`clip_distance` built-in",
+                        &[spirv::Capability::ClipDistance],
+                    )?;
+                    BuiltIn::ClipDistance
+                }
+                crate::Binding::BuiltIn(crate::BuiltIn::CullDistance) => {
+                    self.require_any(
+                        "`cull_distance` built-in",
+                        &[spirv::Capability::CullDistance],
+                    )?;
+                    BuiltIn::CullDistance
+                }
+                _ => unreachable!(),
+            };
+            self.annotations.push(Instruction::member_decorate(
+                struct_id,
+                index,
+                Decoration::BuiltIn,
+                &[built_in as u32],
+            ));
+            member_type_ids.push(self.get_type_id(LookupType::Handle(ty)));
+            pointer_type_ids.push(self.get_pointer_id(&ir_module.types, ty, class)?);
+        }
+        if force_point_size {
+            self.annotations.push(Instruction::member_decorate(
+                struct_id,
+                members.len() as Word,
+                Decoration::BuiltIn,
+                &[BuiltIn::PointSize as u32],
+            ));
+            member_type_ids.push(self.get_type_id(LookupType::Local(LocalType::Value {
+                vector_size: None,
+                kind: crate::ScalarKind::Float,
+                width: 4,
+                pointer_space: None,
+            })));
+            pointer_type_ids.push(self.get_float_pointer_type_id(class));
+        }
+
+        Instruction::type_struct(struct_id, &member_type_ids)
+            .to_words(&mut self.logical_layout.declarations);
+        self.decorate(struct_id, Decoration::Block, &[]);
+        if self.flags.contains(WriterFlags::DEBUG) {
+            self.debugs
+                .push(Instruction::name(struct_id, "gl_PerVertex"));
+        }
+        let struct_pointer_id = self.id_gen.next();
+        Instruction::type_pointer(struct_pointer_id, class, struct_id)
+            .to_words(&mut self.logical_layout.declarations);
+        let varying_id = self.id_gen.next();
+        Instruction::variable(struct_pointer_id, varying_id, class, None)
+            .to_words(&mut self.logical_layout.declarations);
+        iface.varying_ids.push(varying_id);
+
+        let mut member_ids = Vec::with_capacity(pointer_type_ids.len());
+        for (index, pointer_type_id) in pointer_type_ids.into_iter().enumerate() {
+            let index_id = self.get_index_constant(index as Word);
+            let id = self.id_gen.next();
+            prelude.body.push(Instruction::access_chain(
+                pointer_type_id,
+                id,
+                varying_id,
+                &[index_id],
+            ));
+            member_ids.push(id);
+        }
+        if force_point_size {
+            let point_size_id = member_ids.pop().unwrap();
+            let default_value_id = self.get_constant_scalar(crate::Literal::F32(1.0));
+            prelude
+                .body
+                .push(Instruction::store(point_size_id, default_value_id, None));
+        }
+        Ok(member_ids)
+    }
+
     /// Generate an `OpVariable` for one value in an [`EntryPoint`]'s IO interface.
     ///
     /// The [`Binding`]s of the arguments and result of an [`EntryPoint`]'s
@@ -1559,9 +1947,15 @@ impl Writer {
                         )?;
                         BuiltIn::CullDistance
                     }
-                    Bi::InstanceIndex => BuiltIn::InstanceIndex,
+                    Bi::InstanceIndex => match self.target_environment {
+                        TargetEnvironment::Vulkan => BuiltIn::InstanceIndex,
+                        TargetEnvironment::OpenGl => BuiltIn::InstanceId,
+                    },
                     Bi::PointSize => BuiltIn::PointSize,
-                    Bi::VertexIndex => BuiltIn::VertexIndex,
+                    Bi::VertexIndex => match self.target_environment {
+                        TargetEnvironment::Vulkan => BuiltIn::VertexIndex,
+                        TargetEnvironment::OpenGl => BuiltIn::VertexId,
+                    },
                     // fragment
                     Bi::FragDepth => BuiltIn::FragDepth,
                     Bi::PointCoord => BuiltIn::PointCoord,
@@ -1589,6 +1983,21 @@ impl Writer {
                     Bi::WorkGroupId => BuiltIn::WorkgroupId,
                     Bi::WorkGroupSize => BuiltIn::WorkgroupSize,
                     Bi::NumWorkGroups => BuiltIn::NumWorkgroups,
+                    // subgroup
+                    Bi::SubgroupSize | Bi::SubgroupInvocationId => {
+                        // Like `OpGroupNonUniform*`, these arrived in SPIR-V 1.3.
+                        if self.physical_layout.version < 0x10300 {
+                            return Err(Error::VersionTooLow("subgroup built-ins", 1, 3));
+                        }
+                        self.require_any(
+                            "subgroup built-ins",
+                            &[spirv::Capability::GroupNonUniform],
+                        )?;
+                        match built_in {
+                            Bi::SubgroupSize => BuiltIn::SubgroupSize,
+                            _ => BuiltIn::SubgroupLocalInvocationId,
+                        }
+                    }
                 };
 
                 self.decorate(id, Decoration::BuiltIn, &[built_in as u32]);
@@ -1627,6 +2036,15 @@ impl Writer {
     ) -> Result<Word, Error> {
         use spirv::Decoration;
 
+        if self.target_environment == TargetEnvironment::OpenGl
+            && global_variable.space == crate::AddressSpace::PushConstant
+        {
+            return Err(Error::UnsupportedByTarget(
+                "push constants",
+                self.target_environment,
+            ));
+        }
+
         let id = self.id_gen.next();
         let class = map_storage_class(global_variable.space);
 
@@ -1638,9 +2056,15 @@ impl Writer {
             }
         }
 
+        // Storage images may be wrapped in a binding array, in which case the
+        // access decorations apply to every element.
+        let image_ty = match ir_module.types[global_variable.ty].inner {
+            crate::TypeInner::BindingArray { base, .. } => base,
+            _ => global_variable.ty,
+        };
         let storage_access = match global_variable.space {
             crate::AddressSpace::Storage { access } => Some(access),
-            _ => match ir_module.types[global_variable.ty].inner {
+            _ => match ir_module.types[image_ty].inner {
                 crate::TypeInner::Image {
                     class: crate::ImageClass::Storage { access, .. },
                     ..
@@ -1655,6 +2079,32 @@ impl Writer {
             if !storage_access.contains(crate::StorageAccess::STORE) {
                 self.decorate(id, Decoration::NonWritable, &[]);
             }
+            if storage_access.contains(crate::StorageAccess::COHERENT) {
+                self.decorate(id, Decoration::Coherent, &[]);
+            }
+        }
+        if let crate::AddressSpace::Storage { .. } = global_variable.space {
+            if self.flags.contains(WriterFlags::RESTRICT_STORAGE_BUFFERS) {
+                self.decorate(id, Decoration::Restrict, &[]);
+            }
+        }
+
+        // Buffers holding `f16` values need 16-bit storage access.
+        let storage_capability = match global_variable.space {
+            crate::AddressSpace::Storage { .. } => {
+                Some(spirv::Capability::StorageBuffer16BitAccess)
+            }
+            crate::AddressSpace::Uniform => {
+                Some(spirv::Capability::UniformAndStorageBuffer16BitAccess)
+            }
+            crate::AddressSpace::PushConstant => Some(spirv::Capability::StoragePushConstant16),
+            _ => None,
+        };
+        if let Some(capability) = storage_capability {
+            if contains_f16(global_variable.ty, &ir_module.types) {
+                self.capabilities_used.insert(capability);
+                self.use_extension("SPV_KHR_16bit_storage");
+            }
         }
 
         // Note: we should be able to substitute `binding_array<Foo, 0>`,
@@ -1662,12 +2112,35 @@ impl Writer {
         // and it is failing on 0.
         let mut substitute_inner_type_lookup = None;
         if let Some(ref res_binding) = global_variable.binding {
-            self.decorate(id, Decoration::DescriptorSet, &[res_binding.group]);
-            self.decorate(id, Decoration::Binding, &[res_binding.binding]);
+            let info = self.binding_map.get(res_binding).cloned();
+            match self.target_environment {
+                TargetEnvironment::Vulkan => {
+                    self.decorate(id, Decoration::DescriptorSet, &[res_binding.group]);
+                    self.decorate(id, Decoration::Binding, &[res_binding.binding]);
+                }
+                TargetEnvironment::OpenGl => {
+                    let binding = match info.as_ref().and_then(|info| info.gl_binding) {
+                        Some(binding) => binding,
+                        // Without descriptor sets, the same binding number
+                        // in two groups would be the same binding point.
+                        None if ir_module
+                            .global_variables
+                            .iter()
+                            .filter_map(|(_, global)| global.binding.as_ref())
+                            .any(|other| other.group != res_binding.group) =>
+                        {
+                            return Err(Error::MissingGlBinding(res_binding.clone()));
+                        }
+                        None => res_binding.binding,
+                    };
+                    self.decorate(id, Decoration::Binding, &[binding]);
+                }
+            }
 
-            if let Some(&BindingInfo {
+            if let Some(BindingInfo {
                 binding_array_size: Some(remapped_binding_array_size),
-            }) = self.binding_map.get(res_binding)
+                ..
+            }) = info
             {
                 if let crate::TypeInner::BindingArray { base, .. } =
                     ir_module.types[global_variable.ty].inner
@@ -1892,7 +2365,7 @@ impl Writer {
                     source_code: debug_info.source_code,
                     source_file_id,
                 });
-                self.debugs.push(Instruction::source(
+                self.debugs.extend(Instruction::source(
                     spirv::SourceLanguage::Unknown,
                     0,
                     &debug_info_inner,
@@ -2058,6 +2531,25 @@ impl Writer {
     }
 }
 
+/// Split a module's words into instructions, skipping the header.
+#[cfg(all(test, feature = "wgsl-in"))]
+fn instructions(words: &[Word]) -> impl Iterator<Item = &[Word]> {
+    let mut rest = &words[5..];
+    std::iter::from_fn(move || {
+        let (inst, tail) = rest.split_at((*rest.first()? >> 16) as usize);
+        rest = tail;
+        Some(inst)
+    })
+}
+
+/// How many instructions in `words` have the opcode `op`.
+#[cfg(all(test, feature = "test-util"))]
+fn count_instructions(words: &[Word], op: spirv::Op) -> usize {
+    instructions(words)
+        .filter(|inst| inst[0] & 0xffff == op as u32)
+        .count()
+}
+
 #[test]
 fn test_write_physical_layout() {
     let mut writer = Writer::new(&Options::default()).unwrap();
@@ -2065,3 +2557,381 @@ fn test_write_physical_layout() {
     writer.write_physical_layout();
     assert_eq!(writer.physical_layout.bound, 3);
 }
+
+#[cfg(feature = "wgsl-in")]
+#[test]
+fn storage_stores() {
+    let module = crate::front::wgsl::parse_str(
+        "
+        @group(0) @binding(0) var rgba: texture_storage_2d<rgba8unorm, write>;
+        @group(0) @binding(1) var red: texture_storage_2d<r16float, write>;
+        @group(0) @binding(2) var rgb: texture_storage_2d<rg11b10float, write>;
+        @group(0) @binding(3) var rg: texture_storage_2d<rg8snorm, write>;
+
+        @compute @workgroup_size(1)
+        fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+            let value = vec4(vec3<f32>(id), 1.0);
+            textureStore(rgba, vec2(0), value);
+            textureStore(red, vec2(0), value);
+            textureStore(rgb, vec2(0), value);
+            textureStore(rg, vec2(0), value);
+        }
+        ",
+    )
+    .unwrap();
+    let info = crate::valid::Validator::new(
+        crate::valid::ValidationFlags::all(),
+        crate::valid::Capabilities::default(),
+    )
+    .validate(&module)
+    .unwrap();
+    let words = super::write_vec(&module, &info, &Options::default(), None).unwrap();
+
+    // Collect the formats of every `OpTypeImage`, and the texels of every
+    // `OpImageWrite`.
+    let (mut formats, mut texels) = (Vec::new(), Vec::new());
+    for inst in instructions(&words) {
+        if inst[0] & 0xffff == spirv::Op::TypeImage as u32 {
+            formats.push(inst[8]);
+        } else if inst[0] & 0xffff == spirv::Op::ImageWrite as u32 {
+            texels.push(inst[3]);
+        }
+    }
+    formats.sort_unstable();
+    let mut expected = [
+        spirv::ImageFormat::Rgba8,
+        spirv::ImageFormat::R16f,
+        spirv::ImageFormat::R11fG11fB10f,
+        spirv::ImageFormat::Rg8Snorm,
+    ]
+    .map(|format| format as u32);
+    expected.sort_unstable();
+    assert_eq!(formats, expected);
+    // The image's format does the packing, so every store writes the same
+    // four-component value.
+    assert_eq!(texels.len(), 4);
+    assert!(texels.iter().all(|&texel| texel == texels[0]));
+}
+
+#[cfg(feature = "wgsl-in")]
+#[test]
+fn early_depth_test() {
+    let module = crate::front::wgsl::parse_str(
+        "
+        @fragment @early_depth_test(less_equal)
+        fn forced() -> @location(0) vec4<f32> {
+            return vec4(1.0);
+        }
+
+        @fragment @early_depth_test(greater_equal)
+        fn conservative(@builtin(position) position: vec4<f32>) -> @builtin(frag_depth) f32 {
+            return position.z;
+        }
+
+        @fragment
+        fn replacing(@builtin(position) position: vec4<f32>) -> @builtin(frag_depth) f32 {
+            return position.z;
+        }
+        ",
+    )
+    .unwrap();
+    let info = crate::valid::Validator::new(
+        crate::valid::ValidationFlags::all(),
+        crate::valid::Capabilities::default(),
+    )
+    .validate(&module)
+    .unwrap();
+    let words = super::write_vec(&module, &info, &Options::default(), None).unwrap();
+
+    // Collect the modes of every `OpExecutionMode`.
+    let modes: Vec<_> = instructions(&words)
+        .filter(|inst| inst[0] & 0xffff == spirv::Op::ExecutionMode as u32)
+        .map(|inst| inst[2])
+        .collect();
+    let count = |mode: spirv::ExecutionMode| modes.iter().filter(|&&m| m == mode as u32).count();
+    assert_eq!(count(spirv::ExecutionMode::EarlyFragmentTests), 1);
+    assert_eq!(count(spirv::ExecutionMode::DepthGreater), 1);
+    assert_eq!(count(spirv::ExecutionMode::DepthReplacing), 2);
+    assert_eq!(count(spirv::ExecutionMode::DepthLess), 0);
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn ray_query_candidates() {
+    let fixture = crate::test_util::Fixture::wgsl_with_capabilities(
+        "
+        @group(0) @binding(0) var acc_struct: acceleration_structure;
+        @group(0) @binding(1) var<storage, read_write> out: u32;
+
+        @compute @workgroup_size(1)
+        fn main() {
+            var rq: ray_query;
+            rayQueryInitialize(&rq, acc_struct, RayDesc(RAY_FLAG_NONE, 0xFFu, 0.1, 100.0, vec3(0.0), vec3(0.0, 1.0, 0.0)));
+            while (rayQueryProceed(&rq)) {
+                let candidate = rayQueryGetCandidateIntersection(&rq);
+                if candidate.kind == RAY_QUERY_INTERSECTION_TRIANGLE && candidate.t > 10.0 {
+                    rayQueryTerminate(&rq);
+                }
+            }
+            out = rayQueryGetCommittedIntersection(&rq).kind;
+        }
+        ",
+        crate::valid::Capabilities::RAY_QUERY,
+    );
+
+    let words = fixture.to_spv(&Options {
+        lang_version: (1, 4),
+        ..Default::default()
+    });
+    assert_eq!(
+        count_instructions(&words, spirv::Op::RayQueryTerminateKHR),
+        1
+    );
+    // Once for the candidate, and once for the committed intersection.
+    assert_eq!(
+        count_instructions(&words, spirv::Op::RayQueryGetIntersectionTypeKHR),
+        2
+    );
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn opengl_target() {
+    use rspirv::binary::Disassemble;
+
+    let fixture = crate::test_util::Fixture::wgsl(
+        "
+        @group(1) @binding(0) var<uniform> offset: vec4<f32>;
+        @group(1) @binding(1) var<uniform> scale: f32;
+
+        @vertex
+        fn main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+            return offset * scale + f32(index);
+        }
+        ",
+    );
+    let mut binding_map = super::BindingMap::default();
+    binding_map.insert(
+        crate::ResourceBinding {
+            group: 1,
+            binding: 0,
+        },
+        BindingInfo {
+            gl_binding: Some(4),
+            ..Default::default()
+        },
+    );
+    let options = |target_environment| Options {
+        flags: WriterFlags::ADJUST_COORDINATE_SPACE,
+        binding_map: binding_map.clone(),
+        target_environment,
+        ..Default::default()
+    };
+    // Disassemble the output, with the ids left out so that the lines don't
+    // depend on the order things are written in.
+    let disassemble = |target_environment| -> Vec<String> {
+        let words = fixture.to_spv(&options(target_environment));
+        let dis = rspirv::dr::load_words(words)
+            .expect("Produced invalid SPIR-V")
+            .disassemble();
+        dis.lines()
+            .map(|line| {
+                line.split_whitespace()
+                    .map(|token| if token.starts_with('%') { "%" } else { token })
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect()
+    };
+    let count = |lines: &[String], line: &str| lines.iter().filter(|l| *l == line).count();
+
+    let vulkan = disassemble(TargetEnvironment::Vulkan);
+    assert_eq!(count(&vulkan, "OpDecorate % DescriptorSet 1"), 2);
+    assert_eq!(count(&vulkan, "OpDecorate % Binding 0"), 1);
+    assert_eq!(count(&vulkan, "OpDecorate % BuiltIn VertexIndex"), 1);
+    assert_eq!(count(&vulkan, "OpDecorate % BuiltIn Position"), 1);
+
+    let gl = disassemble(TargetEnvironment::OpenGl);
+    assert!(!gl.iter().any(|line| line.contains("DescriptorSet")));
+    assert_eq!(count(&gl, "OpDecorate % Binding 4"), 1);
+    // Bindings that aren't in the map keep their number.
+    assert_eq!(count(&gl, "OpDecorate % Binding 1"), 1);
+    assert_eq!(count(&gl, "OpDecorate % BuiltIn VertexId"), 1);
+    // The position is a member of a `gl_PerVertex` block.
+    assert_eq!(count(&gl, "OpDecorate % BuiltIn Position"), 0);
+    assert_eq!(count(&gl, "OpMemberDecorate % 0 BuiltIn Position"), 1);
+    let blocks = "OpDecorate % Block";
+    assert_eq!(count(&gl, blocks), count(&vulkan, blocks) + 1);
+    // The depth is remapped with a `z * 2 - w`.
+    let subtractions = |lines: &[String]| {
+        lines
+            .iter()
+            .filter(|line| line.starts_with("% = OpFSub"))
+            .count()
+    };
+    assert_eq!(subtractions(&gl), subtractions(&vulkan) + 1);
+
+    // OpenGL only takes SPIR-V 1.0.
+    let error = super::write_vec(
+        &fixture.module,
+        &fixture.info,
+        &Options {
+            lang_version: (1, 3),
+            ..options(TargetEnvironment::OpenGl)
+        },
+        None,
+    )
+    .unwrap_err();
+    assert!(matches!(error, Error::UnsupportedByTarget(..)));
+
+    // Without descriptor sets, resources in different groups need a GL
+    // binding each.
+    let fixture = crate::test_util::Fixture::wgsl(
+        "
+        @group(0) @binding(0) var<uniform> offset: vec4<f32>;
+        @group(1) @binding(0) var<uniform> scale: f32;
+
+        @vertex
+        fn main() -> @builtin(position) vec4<f32> {
+            return offset * scale;
+        }
+        ",
+    );
+    let error = super::write_vec(
+        &fixture.module,
+        &fixture.info,
+        &options(TargetEnvironment::OpenGl),
+        None,
+    )
+    .unwrap_err();
+    assert!(matches!(
+        error,
+        Error::MissingGlBinding(crate::ResourceBinding {
+            group: 0,
+            binding: 0
+        })
+    ));
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn debug_info() {
+    use spirv::Op;
+
+    // Pad the source past what one `OpSource` can hold.
+    let mut source = "// padding\n".repeat(30_000);
+    source.push_str(
+        "
+        @fragment
+        fn main() -> @location(0) vec4<f32> {
+            var color = vec4(1.0);
+            color.x = 0.5;
+            return color;
+        }
+        ",
+    );
+    let fixture = crate::test_util::Fixture::wgsl(&source);
+    let words = fixture.to_spv(&Options {
+        flags: WriterFlags::DEBUG,
+        debug_info: Some(DebugInfo {
+            source_code: &source,
+            file_name: "padded.wgsl".as_ref(),
+        }),
+        ..Default::default()
+    });
+
+    let string = |words: &[u32]| {
+        let bytes: Vec<u8> = words.iter().flat_map(|word| word.to_le_bytes()).collect();
+        let end = bytes.iter().position(|&byte| byte == 0).unwrap();
+        String::from_utf8(bytes[..end].to_vec()).unwrap()
+    };
+    let mut text = String::new();
+    let mut continued = 0;
+    let mut lines = Vec::new();
+    for inst in instructions(&words) {
+        match inst[0] & 0xffff {
+            op if op == Op::Source as u32 => text.push_str(&string(&inst[4..])),
+            op if op == Op::SourceContinued as u32 => {
+                continued += 1;
+                text.push_str(&string(&inst[1..]));
+            }
+            op if op == Op::Line as u32 => lines.push(inst[2]),
+            _ => {}
+        }
+    }
+    assert_eq!(continued, 1);
+    assert_eq!(text, source);
+    // The store to `color.x`, and nothing in the padding.
+    assert!(lines.contains(&30_005));
+    assert!(lines.iter().all(|&line| line > 30_000));
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn image_load_read_zero_skip_write() {
+    use crate::proc::BoundsCheckPolicy;
+
+    // Texel loads that need every kind of check: level of detail, array
+    // layer, sample index, and coordinates.
+    let fixture = crate::test_util::Fixture::wgsl(
+        "
+        @group(0) @binding(0) var image_1d: texture_1d<f32>;
+        @group(0) @binding(1) var image_2d_array: texture_2d_array<f32>;
+        @group(0) @binding(2) var image_multisampled: texture_multisampled_2d<f32>;
+        @group(0) @binding(3) var image_3d: texture_3d<f32>;
+
+        @fragment
+        fn main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+            let coords = vec2<i32>(position.xy);
+            let level = i32(position.z);
+            return textureLoad(image_1d, coords.x, level)
+                + textureLoad(image_2d_array, coords, 1, level)
+                + textureLoad(image_multisampled, coords, 2)
+                + textureLoad(image_3d, vec3(coords, 0), level);
+        }
+        ",
+    );
+    let words = fixture.to_spv(&Options {
+        bounds_check_policies: crate::proc::BoundsCheckPolicies {
+            image_load: BoundsCheckPolicy::ReadZeroSkipWrite,
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+
+    let count = |op| count_instructions(&words, op);
+    // Three loads have a level of detail, and one has a sample index.
+    assert_eq!(count(spirv::Op::ImageQueryLevels), 3);
+    assert_eq!(count(spirv::Op::ImageQuerySamples), 1);
+    // The coordinates, with the layer, are checked against the size of the
+    // level, or of the whole image if it has no levels.
+    assert_eq!(count(spirv::Op::ImageQuerySizeLod), 3);
+    assert_eq!(count(spirv::Op::ImageQuerySize), 1);
+    // Each check of a level, sample, or coordinates is unsigned.
+    assert_eq!(count(spirv::Op::ULessThan), 8);
+    assert_eq!(count(spirv::Op::SLessThan), 0);
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn clamps_to_unit_interval() {
+    let fixture = crate::test_util::Fixture::wgsl(
+        "
+        @fragment
+        fn main(@location(0) x: vec4<f32>) -> @location(0) vec4<f32> {
+            return saturate(x) + clamp(x, vec4(0.0), vec4(1.0)) + clamp(x, vec4(0.0), vec4(2.0));
+        }
+        ",
+    );
+    let words = fixture.to_spv(&Options::default());
+
+    // The GLSL.std.450 instruction each `OpExtInst` uses.
+    let ext_insts = instructions(&words)
+        .filter(|inst| inst[0] & 0xffff == spirv::Op::ExtInst as u32)
+        .map(|inst| inst[4])
+        .collect::<Vec<_>>();
+    let count = |op: spirv::GLOp| ext_insts.iter().filter(|&&inst| inst == op as u32).count();
+    // `saturate`, and the clamp to [0, 1], return 0 for NaN.
+    assert_eq!(count(spirv::GLOp::NClamp), 2);
+    assert_eq!(count(spirv::GLOp::FClamp), 1);
+}
@@ -0,0 +1,84 @@
+/*!
+Helpers shared by the text backends.
+
+The text backends write into any [`fmt::Write`] sink, so they can stream
+their output instead of building it up in a [`String`]. To write to an
+[`io::Write`] sink, such as a file, wrap it in an [`IoWriter`].
+*/
+
+use std::{fmt, io};
+
+/// The text a [`Level`] of indentation consists of.
+pub(super) const INDENT: &str = "    ";
+
+/// A level of indentation, which displays as that many [`INDENT`]s.
+#[derive(Clone, Copy)]
+pub(super) struct Level(pub(super) usize);
+
+impl Level {
+    pub(super) const fn next(&self) -> Self {
+        Level(self.0 + 1)
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        (0..self.0).try_for_each(|_| formatter.write_str(INDENT))
+    }
+}
+
+/// An adapter that lets the text backends write into an [`io::Write`] sink.
+///
+/// [`fmt::Write`] can't report why writing failed, so a backend writing
+/// into an `IoWriter` reports a failed write as a formatting error. The
+/// [`io::Error`] behind it is kept, and returned by [`into_inner`].
+///
+/// Writes go straight to the sink, so wrap it in an [`io::BufWriter`] if
+/// it is slow to write to.
+///
+/// [`into_inner`]: IoWriter::into_inner
+#[derive(Debug)]
+pub struct IoWriter<W> {
+    inner: W,
+    error: Option<io::Error>,
+}
+
+impl<W: io::Write> IoWriter<W> {
+    pub const fn new(inner: W) -> Self {
+        IoWriter { inner, error: None }
+    }
+
+    /// Return the sink, or the first error writing to it failed with.
+    pub fn into_inner(self) -> io::Result<W> {
+        match self.error {
+            Some(error) => Err(error),
+            None => Ok(self.inner),
+        }
+    }
+}
+
+impl<W: io::Write> fmt::Write for IoWriter<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if self.error.is_some() {
+            return Err(fmt::Error);
+        }
+        self.inner.write_all(s.as_bytes()).map_err(|error| {
+            self.error = Some(error);
+            fmt::Error
+        })
+    }
+}
+
+#[test]
+fn io_writer_keeps_error() {
+    use fmt::Write as _;
+
+    let mut buffer = [0; 4];
+    let mut writer = IoWriter::new(&mut buffer[..]);
+    write!(writer, "fn").unwrap();
+    assert!(write!(writer, " main").is_err());
+    assert_eq!(
+        writer.into_inner().unwrap_err().kind(),
+        io::ErrorKind::WriteZero
+    );
+}
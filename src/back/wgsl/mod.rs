@@ -1,9 +1,22 @@
 /*!
 Backend for [WGSL][wgsl] (WebGPU Shading Language).
 
+# Struct layouts
+
+WGSL places each struct member at the first offset after the previous one
+that suits the member's alignment. Where the IR leaves a larger gap, as
+front ends for other languages may, the member before the gap gets a
+`@size` attribute to cover it, and likewise the last member if the
+struct's span has room for more. Layouts that can't be written this way,
+like a first member at a nonzero offset or a member at an offset that
+doesn't suit its alignment, produce [`Error::UnrepresentableStructLayout`],
+naming the member.
+
 [wgsl]: https://gpuweb.github.io/gpuweb/wgsl.html
 */
 
+#[cfg(all(test, feature = "test-util"))]
+mod tests;
 mod writer;
 
 use thiserror::Error;
@@ -22,6 +35,12 @@ pub enum Error {
     UnsupportedMathFunction(crate::MathFunction),
     #[error("Unsupported relational function: {0:?}")]
     UnsupportedRelationalFunction(crate::RelationalFunction),
+    #[error("Member `{member}` of struct `{ty}` {problem}, which WGSL can't express")]
+    UnrepresentableStructLayout {
+        ty: String,
+        member: String,
+        problem: String,
+    },
 }
 
 pub fn write_string(
@@ -0,0 +1,352 @@
+use super::{write_string, Error, WriterFlags};
+use crate::test_util::Fixture;
+
+#[test]
+fn f16() {
+    let fixture = Fixture::wgsl_with_capabilities(
+        "
+        enable f16;
+
+        var<private> v: vec3<f16>;
+
+        @compute @workgroup_size(1)
+        fn main() {
+            let x = 1.5h;
+            v = vec3h(x) * f16(2.0);
+        }
+        ",
+        crate::valid::Capabilities::FLOAT16,
+    );
+    fixture
+        .to_wgsl()
+        .assert_contains("enable f16;")
+        .assert_contains("var<private> v: vec3<f16>;")
+        .assert_contains("1.5h");
+}
+
+#[test]
+fn int64() {
+    let fixture = Fixture::wgsl_with_capabilities(
+        "
+        var<private> v: vec2<i64>;
+        var<private> u: u64;
+
+        @compute @workgroup_size(1)
+        fn main() {
+            v = vec2(1li, v.y) * 3li;
+            u = 4lu + u64(v.x);
+        }
+        ",
+        crate::valid::Capabilities::SHADER_INT64,
+    );
+    fixture
+        .to_wgsl()
+        .assert_contains("var<private> v: vec2<i64>;")
+        .assert_contains("3li")
+        .assert_contains("4lu");
+}
+
+#[test]
+fn subgroup_operations() {
+    let fixture = Fixture::wgsl_with_capabilities(
+        "
+        enable subgroups;
+
+        @group(0) @binding(0) var<storage, read_write> out: array<u32, 2>;
+
+        @compute @workgroup_size(64)
+        fn main(
+            @builtin(local_invocation_index) index: u32,
+            @builtin(subgroup_size) size: u32,
+            @builtin(subgroup_invocation_id) lane: u32,
+        ) {
+            out[0] = subgroupBallot(index < 16u).x + subgroupExclusiveMul(index);
+            out[1] = subgroupShuffleUp(index, 1u) + size + lane;
+        }
+        ",
+        crate::valid::Capabilities::SUBGROUP,
+    );
+    fixture
+        .to_wgsl()
+        .assert_contains("enable subgroups;")
+        .assert_contains("@builtin(subgroup_size) size: u32")
+        .assert_contains("@builtin(subgroup_invocation_id) lane: u32")
+        .assert_contains("subgroupBallot(")
+        .assert_contains("subgroupExclusiveMul(")
+        .assert_contains("subgroupShuffleUp(");
+}
+
+#[test]
+fn repeated_array_elements() {
+    let fixture = Fixture::wgsl(
+        "
+        const ones = array<i32, 4>(1, 1, 1, 1);
+        const zeros = array<f32, 4>(0.0, 0.0, 0.0, 0.0);
+
+        @compute @workgroup_size(1)
+        fn main() {
+            var a = ones;
+            var b = array<f32, 3>(0.5, 0.5, 0.5);
+            var c = zeros;
+        }
+        ",
+    );
+    fixture
+        .to_wgsl()
+        .assert_contains("array<i32, 4>(1, 1, 1, 1)")
+        .assert_contains("array<f32, 3>(0.5, 0.5, 0.5)")
+        .assert_contains("zeros: array<f32, 4> = array<f32, 4>();");
+}
+
+#[test]
+fn minify() {
+    let fixture = Fixture::wgsl(
+        "
+        struct Light {
+            color: vec4<f32>,
+            intensity: f32,
+        }
+        @group(0) @binding(0) var<uniform> light: Light;
+
+        fn shade(normal: vec3<f32>) -> f32 {
+            let facing = dot(normal, vec3(0.0, 1.0, 0.0));
+            return max(facing, 0.0) * light.intensity;
+        }
+
+        @fragment
+        fn fs_main(@location(0) normal: vec3<f32>) -> @location(0) vec4<f32> {
+            let brightness = shade(normalize(normal));
+            return light.color * -brightness;
+        }
+        ",
+    );
+    let output = fixture.to_wgsl_with_flags(WriterFlags::MINIFY);
+    output
+        .assert_not_contains("\n")
+        .assert_not_contains("  ")
+        .assert_not_contains("Light")
+        .assert_not_contains("shade")
+        .assert_not_contains("brightness")
+        .assert_not_contains("facing")
+        .assert_contains("fn fs_main(")
+        .assert_matches(r"return max\(dot\(\w+,");
+
+    let module = Fixture::wgsl(output.as_str()).module;
+    assert_eq!(module.entry_points[0].name, "fs_main");
+}
+
+#[test]
+fn non_uniform_binding_array_index() {
+    let fixture = Fixture::wgsl_with_capabilities(
+        "
+        enable nonuniform_indexing;
+
+        @group(0) @binding(0) var textures: binding_array<texture_2d<f32>, 4>;
+        @group(0) @binding(1) var samp: sampler;
+        @group(0) @binding(2) var<uniform> which: u32;
+
+        @fragment
+        fn main(@location(0) uv: vec2<f32>) -> @location(0) vec4<f32> {
+            return textureSample(textures[nonUniform(which)], samp, uv);
+        }
+        ",
+        crate::valid::Capabilities::SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING,
+    );
+    fixture
+        .to_wgsl()
+        .assert_contains("enable nonuniform_indexing;")
+        .assert_matches(r"textures\[nonUniform\(\w+\)\]");
+}
+
+#[test]
+fn coherent_storage() {
+    let fixture = Fixture::wgsl(
+        "
+        @coherent @group(0) @binding(0) var<storage, read_write> data: array<u32>;
+        @group(0) @binding(1) var<storage, read_write> plain: array<u32>;
+        @coherent @group(0) @binding(2) var image: texture_storage_2d<r32uint, write>;
+        @group(0) @binding(3) var plain_image: texture_storage_2d<r32uint, write>;
+
+        @compute @workgroup_size(64)
+        fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+            data[id.x] = plain[id.x];
+            plain[id.x] = data[id.x + 1u];
+            textureStore(image, vec2<i32>(id.xy), vec4<u32>(data[id.x]));
+            textureStore(plain_image, vec2<i32>(id.xy), vec4<u32>(plain[id.x]));
+        }
+        ",
+    );
+    fixture
+        .to_wgsl()
+        .assert_contains("@group(0) @binding(0) @coherent \nvar<storage, read_write> data")
+        .assert_contains("@group(0) @binding(1) \nvar<storage, read_write> plain")
+        .assert_contains("@group(0) @binding(2) @coherent \nvar image")
+        .assert_contains("@group(0) @binding(3) \nvar plain_image");
+}
+
+#[test]
+fn image_atomics() {
+    let fixture = Fixture::wgsl_with_capabilities(
+        "
+        @group(0) @binding(0) var image: texture_storage_2d<r32uint, atomic>;
+        @group(0) @binding(1) var layers: texture_storage_2d_array<r32sint, atomic>;
+
+        @compute @workgroup_size(1)
+        fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+            textureAtomicMax(image, id.xy, 1u);
+            textureAtomicSub(layers, id.xy, 2, 3);
+        }
+        ",
+        crate::valid::Capabilities::TEXTURE_ATOMIC,
+    );
+    fixture
+        .to_wgsl()
+        .assert_contains("texture_storage_2d<r32uint,atomic>")
+        .assert_contains("textureAtomicMax(image, id.xy, 1u);")
+        .assert_contains("textureAtomicSub(layers, id.xy, 2, 3);");
+}
+
+#[test]
+fn reserved_names() {
+    use crate::keywords::wgsl::RESERVED;
+
+    // Give globals every reserved word as a name, as other front ends may.
+    let source: String = (0..RESERVED.len())
+        .map(|index| format!("var<private> v{index}: f32;\n"))
+        .chain(["@compute @workgroup_size(1) fn main() {".to_string()])
+        .chain((0..RESERVED.len()).map(|index| format!("v{index} = 1.0;\n")))
+        .chain(["}".to_string()])
+        .collect();
+    let mut fixture = Fixture::wgsl(&source);
+    for ((_, var), &word) in fixture.module.global_variables.iter_mut().zip(RESERVED) {
+        var.name = Some(word.to_string());
+    }
+
+    let output = fixture.to_wgsl();
+    output
+        .assert_contains("var<private> noncoherent_: f32;")
+        .assert_contains("var<private> diagnostic_: f32;");
+    if let Err(error) = crate::front::wgsl::parse_str(output.as_str()) {
+        panic!("{}", error.emit_to_string(output.as_str()));
+    }
+}
+
+#[cfg(feature = "glsl-in")]
+#[test]
+fn struct_layouts() {
+    let validate = |module: &crate::Module| {
+        crate::valid::Validator::new(
+            crate::valid::ValidationFlags::all(),
+            crate::valid::Capabilities::default(),
+        )
+        .validate(module)
+        .unwrap()
+    };
+
+    // std140 gives arrays and the block more room than WGSL would.
+    let module = crate::front::glsl::Frontend::default()
+        .parse(
+            &crate::front::glsl::Options::from(crate::ShaderStage::Fragment),
+            "
+            #version 450
+            layout(std140, binding = 0) uniform Params { float a; float b[2]; vec2 c; };
+            layout(location = 0) out vec4 o_color;
+
+            void main() {
+                o_color = vec4(a, b[1], c);
+            }
+            ",
+        )
+        .unwrap();
+    let info = validate(&module);
+    let output = write_string(&module, &info, WriterFlags::empty()).unwrap();
+    for member in [
+        "@size(16) a: f32,",
+        "    b: array<f32, 2>,",
+        "@size(16) c: vec2<f32>,",
+    ] {
+        assert!(
+            output.contains(member),
+            "{member:?} missing from:\n{output}"
+        );
+    }
+
+    let mut module = crate::Module::default();
+    let float = module.types.insert(
+        crate::Type {
+            name: None,
+            inner: crate::TypeInner::Scalar {
+                kind: crate::ScalarKind::Float,
+                width: 4,
+            },
+        },
+        crate::Span::UNDEFINED,
+    );
+    module.types.insert(
+        crate::Type {
+            name: Some("Shifted".to_string()),
+            inner: crate::TypeInner::Struct {
+                members: vec![crate::StructMember {
+                    name: Some("a".to_string()),
+                    ty: float,
+                    binding: None,
+                    offset: 4,
+                }],
+                span: 8,
+            },
+        },
+        crate::Span::UNDEFINED,
+    );
+    let info = validate(&module);
+    let error = write_string(&module, &info, WriterFlags::empty()).unwrap_err();
+    assert!(
+        matches!(
+            error,
+            Error::UnrepresentableStructLayout { ref ty, ref member, .. }
+                if ty == "Shifted" && member == "a"
+        ),
+        "{error}"
+    );
+}
+
+#[test]
+fn comparison_sample_level() {
+    let with_level = |depth_ref: &str| {
+        let mut fixture = Fixture::wgsl(&format!(
+            "
+            @group(0) @binding(0) var t: texture_depth_2d;
+            @group(0) @binding(1) var s: sampler_comparison;
+
+            @fragment
+            fn main() -> @location(0) vec4<f32> {{
+                return vec4(textureSampleCompare(t, s, vec2(0.5), {depth_ref}));
+            }}
+            "
+        ));
+        // Sample at the level the depth reference gives.
+        let expressions = &mut fixture.module.entry_points[0].function.expressions;
+        let (handle, _) = expressions
+            .iter()
+            .find(|&(_, expression)| matches!(*expression, crate::Expression::ImageSample { .. }))
+            .unwrap();
+        if let crate::Expression::ImageSample {
+            ref mut level,
+            depth_ref: Some(depth_ref),
+            ..
+        } = expressions[handle]
+        {
+            *level = crate::SampleLevel::Exact(depth_ref);
+        }
+        fixture.revalidate();
+        fixture
+    };
+
+    with_level("0.0")
+        .to_wgsl()
+        .assert_contains("textureSampleCompareLevel(t, s, ")
+        .assert_not_contains("textureSampleCompare(");
+
+    let fixture = with_level("0.5");
+    let error = write_string(&fixture.module, &fixture.info, WriterFlags::empty()).unwrap_err();
+    assert!(matches!(error, Error::Unimplemented(_)), "{error}");
+}
@@ -13,11 +13,13 @@ type BackendResult = Result<(), Error>;
 enum Attribute {
     Binding(u32),
     BuiltIn(crate::BuiltIn),
+    Coherent,
     Group(u32),
     Invariant,
     Interpolate(Option<crate::Interpolation>, Option<crate::Sampling>),
     Location(u32),
     SecondBlendSource,
+    Size(u32),
     Stage(ShaderStage),
     WorkGroupSize([u32; 3]),
 }
@@ -58,6 +60,13 @@ bitflags::bitflags! {
     pub struct WriterFlags: u32 {
         /// Always annotate the type information instead of inferring.
         const EXPLICIT_TYPES = 0x1;
+        /// Make the output as small as possible.
+        ///
+        /// All names except those of entry points are replaced with short
+        /// generated ones, whitespace is dropped wherever it doesn't separate
+        /// tokens, and named expressions that are used at most once and read
+        /// no memory are written in place rather than bound with `let`.
+        const MINIFY = 0x2;
     }
 }
 
@@ -84,6 +93,8 @@ impl<W: Write> Writer<W> {
 
     fn reset(&mut self, module: &Module) {
         self.names.clear();
+        self.namer
+            .set_short_names(self.flags.contains(WriterFlags::MINIFY));
         self.namer.reset(
             module,
             crate::keywords::wgsl::RESERVED,
@@ -106,8 +117,51 @@ impl<W: Write> Writer<W> {
     }
 
     pub fn write(&mut self, module: &Module, info: &valid::ModuleInfo) -> BackendResult {
+        if self.flags.contains(WriterFlags::MINIFY) {
+            let mut inner = Writer::new(String::new(), self.flags);
+            inner.write_module(module, info)?;
+            self.out.write_str(&minify_whitespace(&inner.out))?;
+            return Ok(());
+        }
+        self.write_module(module, info)
+    }
+
+    fn write_module(&mut self, module: &Module, info: &valid::ModuleInfo) -> BackendResult {
         self.reset(module);
 
+        let enable_f16 = uses_f16(module);
+        let enable_subgroups = uses_subgroups(module);
+        let enable_nonuniform_indexing = uses_non_uniform(module);
+        if enable_f16 {
+            writeln!(self.out, "enable f16;")?;
+        }
+        if enable_subgroups {
+            writeln!(self.out, "enable subgroups;")?;
+        }
+        if enable_nonuniform_indexing {
+            writeln!(self.out, "enable nonuniform_indexing;")?;
+        }
+        let filters = &module.diagnostic_filters;
+        for (index, filter) in filters.iter().enumerate() {
+            // Only the last filter for each rule applies.
+            let rule = filter.triggering_rule;
+            if filters[index + 1..]
+                .iter()
+                .any(|later| later.triggering_rule == rule)
+            {
+                continue;
+            }
+            writeln!(
+                self.out,
+                "diagnostic({}, {});",
+                filter.new_severity.to_wgsl_ident(),
+                rule.to_wgsl_ident()
+            )?;
+        }
+        if enable_f16 || enable_subgroups || enable_nonuniform_indexing || !filters.is_empty() {
+            writeln!(self.out)?;
+        }
+
         // Save all ep result types
         for (_, ep) in module.entry_points.iter().enumerate() {
             if let Some(ref result) = ep.function.result {
@@ -116,11 +170,16 @@ impl<W: Write> Writer<W> {
         }
 
         // Write all structs
+        let mut layouter = proc::Layouter::default();
+        layouter
+            .update(module.to_ctx())
+            .map_err(|err| Error::Custom(err.to_string()))?;
         for (handle, ty) in module.types.iter() {
-            if let TypeInner::Struct { ref members, .. } = ty.inner {
+            if let TypeInner::Struct { ref members, span } = ty.inner {
                 {
                     if !self.is_builtin_wgsl_struct(module, handle) {
-                        self.write_struct(module, handle, members)?;
+                        let sizes = self.member_sizes(module, &layouter, handle, members, span)?;
+                        self.write_struct(module, handle, members, &sizes)?;
                         writeln!(self.out)?;
                     }
                 }
@@ -272,14 +331,50 @@ impl<W: Write> Writer<W> {
         write!(self.out, " {{")?;
         writeln!(self.out)?;
 
-        // Write function local variables
-        for (handle, local) in func.local_variables.iter() {
-            // Write indentation (only for readability)
-            write!(self.out, "{}", back::INDENT)?;
+        // Write function local variables, except those scoped to a nested block
+        let nested_locals = func.body.nested_locals();
+        let locals: Vec<_> = func
+            .local_variables
+            .iter()
+            .map(|(handle, _)| handle)
+            .filter(|handle| !nested_locals.contains(handle))
+            .collect();
+        self.write_locals(module, &locals, func_ctx, back::Level(1), true)?;
+
+        // Write the function body (statement list)
+        for sta in func.body.iter() {
+            // The indentation should always be 1 when writing the function body
+            self.write_stmt(module, sta, func_ctx, back::Level(1))?;
+        }
+
+        writeln!(self.out, "}}")?;
+
+        self.named_expressions.clear();
+
+        Ok(())
+    }
+
+    /// Helper method to write the declarations of `locals`, followed by an
+    /// empty line if `separate` is true and there are any
+    fn write_locals(
+        &mut self,
+        module: &Module,
+        locals: &[Handle<crate::LocalVariable>],
+        func_ctx: &back::FunctionCtx<'_>,
+        level: back::Level,
+        separate: bool,
+    ) -> BackendResult {
+        let func = func_ctx.function(module);
+        for &handle in locals {
+            let local = &func.local_variables[handle];
 
             // Write the local name
             // The leading space is important
-            write!(self.out, "var {}: ", self.names[&func_ctx.name_key(handle)])?;
+            write!(
+                self.out,
+                "{level}var {}: ",
+                self.names[&func_ctx.name_key(handle)]
+            )?;
 
             // Write the local type
             self.write_type(module, local.ty)?;
@@ -299,20 +394,31 @@ impl<W: Write> Writer<W> {
             writeln!(self.out, ";")?
         }
 
-        if !func.local_variables.is_empty() {
+        if separate && !locals.is_empty() {
             writeln!(self.out)?;
         }
 
-        // Write the function body (statement list)
-        for sta in func.body.iter() {
-            // The indentation should always be 1 when writing the function body
-            self.write_stmt(module, sta, func_ctx, back::Level(1))?;
-        }
-
-        writeln!(self.out, "}}")?;
-
-        self.named_expressions.clear();
+        Ok(())
+    }
 
+    /// Helper method to write a block nested in a statement, declaring the
+    /// locals scoped to it where their scope starts
+    ///
+    /// # Notes
+    /// Locals in scope for the whole block come first, followed by an empty line
+    fn write_block(
+        &mut self,
+        module: &Module,
+        block: &crate::Block,
+        func_ctx: &back::FunctionCtx<'_>,
+        level: back::Level,
+    ) -> BackendResult {
+        for (run, (locals, statements)) in block.scoped_runs().into_iter().enumerate() {
+            self.write_locals(module, &locals, func_ctx, level, run == 0)?;
+            for sta in statements {
+                self.write_stmt(module, sta, func_ctx, level)?;
+            }
+        }
         Ok(())
     }
 
@@ -343,7 +449,9 @@ impl<W: Write> Writer<W> {
                 }
                 Attribute::Binding(id) => write!(self.out, "@binding({id}) ")?,
                 Attribute::Group(id) => write!(self.out, "@group({id}) ")?,
+                Attribute::Coherent => write!(self.out, "@coherent ")?,
                 Attribute::Invariant => write!(self.out, "@invariant ")?,
+                Attribute::Size(size) => write!(self.out, "@size({size}) ")?,
                 Attribute::Interpolate(interpolation, sampling) => {
                     if sampling.is_some() && sampling != Some(crate::Sampling::Center) {
                         write!(
@@ -371,6 +479,94 @@ impl<W: Write> Writer<W> {
         Ok(())
     }
 
+    /// Return the `@size` attribute each member of the struct `handle`
+    /// needs, for WGSL to lay it out as the IR does.
+    ///
+    /// WGSL gives arrays the stride of their elements, so a member that is
+    /// an array with any other stride is an error.
+    fn member_sizes(
+        &self,
+        module: &Module,
+        layouter: &proc::Layouter,
+        handle: Handle<crate::Type>,
+        members: &[crate::StructMember],
+        span: u32,
+    ) -> Result<Vec<Option<u32>>, Error> {
+        let error = |index: usize, problem: String| Error::UnrepresentableStructLayout {
+            ty: self.names[&NameKey::Type(handle)].clone(),
+            member: self.names[&NameKey::StructMember(handle, index as u32)].clone(),
+            problem,
+        };
+
+        let mut sizes = vec![None; members.len()];
+        let struct_alignment = layouter[handle].alignment;
+        for (index, member) in members.iter().enumerate() {
+            let layout = layouter[member.ty];
+            if index == 0 && member.offset != 0 {
+                return Err(error(
+                    index,
+                    format!("is at offset {}, not at the start", member.offset),
+                ));
+            }
+            if !layout.alignment.is_aligned(member.offset) {
+                return Err(error(
+                    index,
+                    format!(
+                        "is at offset {}, which isn't a multiple of its alignment {}",
+                        member.offset, layout.alignment
+                    ),
+                ));
+            }
+
+            let mut ty = member.ty;
+            while let TypeInner::Array { base, stride, .. } = module.types[ty].inner {
+                let natural = layouter[base].to_stride();
+                if stride != natural {
+                    return Err(error(
+                        index,
+                        format!("has an array stride of {stride} rather than {natural}"),
+                    ));
+                }
+                ty = base;
+            }
+
+            // A runtime-sized array has no size to adjust.
+            if let TypeInner::Array {
+                size: crate::ArraySize::Dynamic,
+                ..
+            } = module.types[member.ty].inner
+            {
+                continue;
+            }
+            let end = member.offset + layout.size;
+            let (next, natural) = match members.get(index + 1) {
+                Some(next) => (next.offset, layouter[next.ty].alignment.round_up(end)),
+                None => {
+                    if !struct_alignment.is_aligned(span) {
+                        return Err(error(
+                            index,
+                            format!(
+                                "is the last in a struct of span {span}, which isn't a \
+                                multiple of the struct's alignment {struct_alignment}"
+                            ),
+                        ));
+                    }
+                    (span, struct_alignment.round_up(end))
+                }
+            };
+            if next < end {
+                return Err(error(
+                    index,
+                    format!("ends at offset {end}, overlapping what follows at {next}"),
+                ));
+            }
+            if next != natural {
+                sizes[index] = Some(next - member.offset);
+            }
+        }
+        Ok(sizes)
+    }
+
     /// Helper method used to write structs
     ///
     /// # Notes
@@ -380,6 +576,7 @@ impl<W: Write> Writer<W> {
         module: &Module,
         handle: Handle<crate::Type>,
         members: &[crate::StructMember],
+        sizes: &[Option<u32>],
     ) -> BackendResult {
         write!(self.out, "struct ")?;
         self.write_struct_name(module, handle)?;
@@ -391,6 +588,9 @@ impl<W: Write> Writer<W> {
             if let Some(ref binding) = member.binding {
                 self.write_attributes(&map_binding_to_attribute(binding))?;
             }
+            if let Some(size) = sizes[index] {
+                self.write_attributes(&[Attribute::Size(size)])?;
+            }
             // Write struct member name and type
             let member_name = &self.names[&NameKey::StructMember(handle, index as u32)];
             write!(self.out, "{member_name}: ")?;
@@ -462,7 +662,10 @@ impl<W: Write> Writer<W> {
                         "storage_",
                         "",
                         storage_format_str(format),
-                        if access.contains(crate::StorageAccess::LOAD | crate::StorageAccess::STORE)
+                        if access.contains(crate::StorageAccess::ATOMIC) {
+                            ",atomic"
+                        } else if access
+                            .contains(crate::StorageAccess::LOAD | crate::StorageAccess::STORE)
                         {
                             ",read_write"
                         } else if access.contains(crate::StorageAccess::LOAD) {
@@ -524,14 +727,14 @@ impl<W: Write> Writer<W> {
             TypeInner::Matrix {
                 columns,
                 rows,
-                width: _,
+                width,
             } => {
                 write!(
                     self.out,
-                    //TODO: Can matrix be other than f32?
-                    "mat{}x{}<f32>",
+                    "mat{}x{}<{}>",
                     back::vector_size_str(columns),
                     back::vector_size_str(rows),
+                    scalar_kind_str(crate::ScalarKind::Float, width),
                 )?;
             }
             TypeInner::Pointer { base, space } => {
@@ -620,11 +823,19 @@ impl<W: Write> Writer<W> {
                 for handle in range.clone() {
                     let info = &func_ctx.info[handle];
                     let expr_name = if let Some(name) = func_ctx.named_expressions.get(&handle) {
-                        // Front end provides names for all variables at the start of writing.
-                        // But we write them to step by step. We need to recache them
-                        // Otherwise, we could accidentally write variable name instead of full expression.
-                        // Also, we use sanitized names! It defense backend from generating variable with name from reserved keywords.
-                        Some(self.namer.call(name))
+                        if self.flags.contains(WriterFlags::MINIFY)
+                            && info.ref_count < func_ctx.expressions[handle].bake_ref_count()
+                            && self.is_pure(handle, func_ctx)
+                        {
+                            // Writing it out in place is shorter than binding it.
+                            None
+                        } else {
+                            // Front end provides names for all variables at the start of writing.
+                            // But we write them to step by step. We need to recache them
+                            // Otherwise, we could accidentally write variable name instead of full expression.
+                            // Also, we use sanitized names! It defense backend from generating variable with name from reserved keywords.
+                            Some(self.namer.call(name))
+                        }
                     } else {
                         let expr = &func_ctx.expressions[handle];
                         let min_ref_count = expr.bake_ref_count();
@@ -636,7 +847,7 @@ impl<W: Write> Writer<W> {
                             _ => false,
                         };
                         if min_ref_count <= info.ref_count || required_baking_expr {
-                            Some(format!("{}{}", back::BAKE_PREFIX, handle.index()))
+                            Some(self.bake_name(handle))
                         } else {
                             None
                         }
@@ -662,21 +873,15 @@ impl<W: Write> Writer<W> {
                 self.write_expr(module, condition, func_ctx)?;
                 writeln!(self.out, " {{")?;
 
+                // Increase indentation to help with readability
                 let l2 = level.next();
-                for sta in accept {
-                    // Increase indentation to help with readability
-                    self.write_stmt(module, sta, func_ctx, l2)?;
-                }
+                self.write_block(module, accept, func_ctx, l2)?;
 
                 // If there are no statements in the reject block we skip writing it
                 // This is only for readability
                 if !reject.is_empty() {
                     writeln!(self.out, "{level}}} else {{")?;
-
-                    for sta in reject {
-                        // Increase indentation to help with readability
-                        self.write_stmt(module, sta, func_ctx, l2)?;
-                    }
+                    self.write_block(module, reject, func_ctx, l2)?;
                 }
 
                 writeln!(self.out, "{level}}}")?
@@ -728,7 +933,7 @@ impl<W: Write> Writer<W> {
             } => {
                 write!(self.out, "{level}")?;
                 if let Some(expr) = result {
-                    let name = format!("{}{}", back::BAKE_PREFIX, expr.index());
+                    let name = self.bake_name(expr);
                     self.start_named_expr(module, expr, func_ctx, &name)?;
                     self.named_expressions.insert(expr, name);
                 }
@@ -749,7 +954,7 @@ impl<W: Write> Writer<W> {
                 result,
             } => {
                 write!(self.out, "{level}")?;
-                let res_name = format!("{}{}", back::BAKE_PREFIX, result.index());
+                let res_name = self.bake_name(result);
                 self.start_named_expr(module, result, func_ctx, &res_name)?;
                 self.named_expressions.insert(result, res_name);
 
@@ -767,7 +972,7 @@ impl<W: Write> Writer<W> {
             Statement::WorkGroupUniformLoad { pointer, result } => {
                 write!(self.out, "{level}")?;
                 // TODO: Obey named expressions here.
-                let res_name = format!("{}{}", back::BAKE_PREFIX, result.index());
+                let res_name = self.bake_name(result);
                 self.start_named_expr(module, result, func_ctx, &res_name)?;
                 self.named_expressions.insert(result, res_name);
                 write!(self.out, "workgroupUniformLoad(")?;
@@ -793,14 +998,33 @@ impl<W: Write> Writer<W> {
                 self.write_expr(module, value, func_ctx)?;
                 writeln!(self.out, ");")?;
             }
+            Statement::ImageAtomic {
+                image,
+                coordinate,
+                array_index,
+                ref fun,
+                value,
+            } => {
+                write!(self.out, "{level}")?;
+                let fun_str = fun.to_wgsl();
+                write!(self.out, "textureAtomic{fun_str}(")?;
+                self.write_expr(module, image, func_ctx)?;
+                write!(self.out, ", ")?;
+                self.write_expr(module, coordinate, func_ctx)?;
+                if let Some(array_index_expr) = array_index {
+                    write!(self.out, ", ")?;
+                    self.write_expr(module, array_index_expr, func_ctx)?;
+                }
+                write!(self.out, ", ")?;
+                self.write_expr(module, value, func_ctx)?;
+                writeln!(self.out, ");")?;
+            }
             // TODO: copy-paste from glsl-out
             Statement::Block(ref block) => {
                 write!(self.out, "{level}")?;
                 writeln!(self.out, "{{")?;
-                for sta in block.iter() {
-                    // Increase the indentation to help with readability
-                    self.write_stmt(module, sta, func_ctx, level.next())?
-                }
+                // Increase the indentation to help with readability
+                self.write_block(module, block, func_ctx, level.next())?;
                 writeln!(self.out, "{level}}}")?
             }
             Statement::Switch {
@@ -856,9 +1080,7 @@ impl<W: Write> Writer<W> {
                         writeln!(self.out, ": {{")?;
                     }
 
-                    for sta in case.body.iter() {
-                        self.write_stmt(module, sta, func_ctx, l2.next())?;
-                    }
+                    self.write_block(module, &case.body, func_ctx, l2.next())?;
 
                     if !case.fall_through {
                         writeln!(self.out, "{l2}}}")?;
@@ -876,9 +1098,7 @@ impl<W: Write> Writer<W> {
                 writeln!(self.out, "loop {{")?;
 
                 let l2 = level.next();
-                for sta in body.iter() {
-                    self.write_stmt(module, sta, func_ctx, l2)?;
-                }
+                self.write_block(module, body, func_ctx, l2)?;
 
                 // The continuing is optional so we don't need to write it if
                 // it is empty, but the `break if` counts as a continuing statement
@@ -886,9 +1106,7 @@ impl<W: Write> Writer<W> {
                 // `break if` exists
                 if !continuing.is_empty() || break_if.is_some() {
                     writeln!(self.out, "{l2}continuing {{")?;
-                    for sta in continuing.iter() {
-                        self.write_stmt(module, sta, func_ctx, l2.next())?;
-                    }
+                    self.write_block(module, continuing, func_ctx, l2.next())?;
 
                     // The `break if` is always the last
                     // statement of the `continuing` block
@@ -926,6 +1144,59 @@ impl<W: Write> Writer<W> {
         Ok(())
     }
 
+    /// Return the name to bind the baked expression `handle` to.
+    fn bake_name(&mut self, handle: Handle<crate::Expression>) -> String {
+        if self.flags.contains(WriterFlags::MINIFY) {
+            self.namer.call("")
+        } else {
+            format!("{}{}", back::BAKE_PREFIX, handle.index())
+        }
+    }
+
+    /// Return true if `handle` can be written out wherever it is used,
+    /// instead of being bound with `let`.
+    ///
+    /// That is the case if it reads no memory, has no side effects, and
+    /// doesn't depend on the control flow, and the same goes for any of its
+    /// operands that haven't been bound already.
+    fn is_pure(&self, handle: Handle<crate::Expression>, func_ctx: &back::FunctionCtx) -> bool {
+        use crate::Expression as E;
+
+        if self.named_expressions.contains_key(&handle) {
+            return true;
+        }
+        let pure = |handle| self.is_pure(handle, func_ctx);
+        match func_ctx.expressions[handle] {
+            E::Literal(_)
+            | E::Constant(_)
+            | E::ZeroValue(_)
+            | E::FunctionArgument(_)
+            | E::GlobalVariable(_)
+            | E::LocalVariable(_) => true,
+            E::Compose { ref components, .. } => components.iter().all(|&c| pure(c)),
+            E::Repeat { value, .. } | E::Splat { value, .. } => pure(value),
+            E::Access { base, index } => pure(base) && pure(index),
+            E::AccessIndex { base, .. } => pure(base),
+            E::Swizzle { vector, .. } => pure(vector),
+            E::Unary { expr, .. } | E::As { expr, .. } => pure(expr),
+            E::Binary { left, right, .. } => pure(left) && pure(right),
+            E::Select {
+                condition,
+                accept,
+                reject,
+            } => pure(condition) && pure(accept) && pure(reject),
+            E::Relational { argument, .. } => pure(argument),
+            E::Math {
+                arg,
+                arg1,
+                arg2,
+                arg3,
+                ..
+            } => pure(arg) && [arg1, arg2, arg3].into_iter().flatten().all(pure),
+            _ => false,
+        }
+    }
+
     /// Return the sort of indirection that `expr`'s plain form evaluates to.
     ///
     /// An expression's 'plain form' is the most general rendition of that
@@ -1093,12 +1364,17 @@ impl<W: Write> Writer<W> {
                 match literal {
                     // Floats are written using `Debug` instead of `Display` because it always appends the
                     // decimal part even it's zero
-                    crate::Literal::F64(_) => {
-                        return Err(Error::Custom("unsupported f64 literal".to_string()));
-                    }
                     crate::Literal::F32(value) => write!(self.out, "{:?}", value)?,
+                    // WGSL has no `f64` literals, so convert the same
+                    // digits an `f32` would be written with.
+                    crate::Literal::F64(value) => write!(self.out, "f64({:?})", value)?,
+                    crate::Literal::F16(value) => {
+                        write!(self.out, "{:?}h", proc::f16_to_f32(value))?
+                    }
                     crate::Literal::U32(value) => write!(self.out, "{}u", value)?,
                     crate::Literal::I32(value) => write!(self.out, "{}", value)?,
+                    crate::Literal::U64(value) => write!(self.out, "{}lu", value)?,
+                    crate::Literal::I64(value) => write!(self.out, "{}li", value)?,
                     crate::Literal::Bool(value) => write!(self.out, "{}", value)?,
                 }
             }
@@ -1125,12 +1401,86 @@ impl<W: Write> Writer<W> {
                 }
                 write!(self.out, ")")?
             }
+            // An array of zeros is the array's zero value.
+            Expression::Repeat { ty, value } if crate::proc::is_zero_value(value, expressions) => {
+                self.write_type(module, ty)?;
+                write!(self.out, "()")?;
+            }
+            // Otherwise WGSL has no way to repeat an element, so write it out.
+            Expression::Repeat { ty, value } => {
+                self.write_type(module, ty)?;
+                write!(self.out, "(")?;
+                let components = crate::proc::repeat_components(ty, value, &module.types);
+                for (index, component) in components.enumerate() {
+                    if index != 0 {
+                        write!(self.out, ", ")?;
+                    }
+                    write_expression(self, component)?;
+                }
+                write!(self.out, ")")?
+            }
             Expression::Splat { size, value } => {
                 let size = back::vector_size_str(size);
                 write!(self.out, "vec{size}(")?;
                 write_expression(self, value)?;
                 write!(self.out, ")")?;
             }
+            // The rest are only ever override expressions.
+            Expression::Unary { op, expr } => {
+                let unary = match op {
+                    crate::UnaryOperator::Negate => "-",
+                    crate::UnaryOperator::LogicalNot => "!",
+                    crate::UnaryOperator::BitwiseNot => "~",
+                };
+                write!(self.out, "{unary}(")?;
+                write_expression(self, expr)?;
+                write!(self.out, ")")?
+            }
+            Expression::Binary { op, left, right } => {
+                write!(self.out, "(")?;
+                write_expression(self, left)?;
+                write!(self.out, " {} ", back::binary_operation_str(op))?;
+                write_expression(self, right)?;
+                write!(self.out, ")")?
+            }
+            Expression::As {
+                expr,
+                kind,
+                convert: Some(width),
+            } => {
+                write!(self.out, "{}(", scalar_kind_str(kind, width))?;
+                write_expression(self, expr)?;
+                write!(self.out, ")")?
+            }
+            Expression::Select {
+                condition,
+                accept,
+                reject,
+            } => {
+                write!(self.out, "select(")?;
+                write_expression(self, reject)?;
+                write!(self.out, ", ")?;
+                write_expression(self, accept)?;
+                write!(self.out, ", ")?;
+                write_expression(self, condition)?;
+                write!(self.out, ")")?
+            }
+            Expression::AccessIndex { base, index } => {
+                write_expression(self, base)?;
+                match back::const_access_struct(module, expressions, base) {
+                    Some(ty) => write!(
+                        self.out,
+                        ".{}",
+                        &self.names[&NameKey::StructMember(ty, index)]
+                    )?,
+                    None => write!(self.out, "[{index}]")?,
+                }
+            }
+            Expression::As { convert: None, .. } => {
+                return Err(Error::Unimplemented(
+                    "bitcast in an override expression".to_string(),
+                ));
+            }
             _ => unreachable!(),
         }
 
@@ -1173,6 +1523,7 @@ impl<W: Write> Writer<W> {
             | Expression::Constant(_)
             | Expression::ZeroValue(_)
             | Expression::Compose { .. }
+            | Expression::Repeat { .. }
             | Expression::Splat { .. } => {
                 self.write_possibly_const_expression(
                     module,
@@ -1248,6 +1599,25 @@ impl<W: Write> Writer<W> {
             } => {
                 use crate::SampleLevel as Sl;
 
+                // `textureSampleCompareLevel` can only sample level zero, so
+                // an explicit level must be a literal zero.
+                let level = match level {
+                    Sl::Exact(expr) if depth_ref.is_some() => {
+                        let is_zero = match func_ctx.expressions[expr] {
+                            Expression::Literal(crate::Literal::F32(value)) => value == 0.0,
+                            Expression::ZeroValue(_) => true,
+                            _ => false,
+                        };
+                        if !is_zero {
+                            return Err(Error::Unimplemented(
+                                "depth comparison sampling with an explicit level".to_string(),
+                            ));
+                        }
+                        Sl::Zero
+                    }
+                    _ => level,
+                };
+
                 let suffix_cmp = match depth_ref {
                     Some(_) => "Compare",
                     None => "",
@@ -1653,6 +2023,49 @@ impl<W: Write> Writer<W> {
 
                 write!(self.out, ")")?
             }
+            Expression::NonUniform(value) => {
+                write!(self.out, "nonUniform(")?;
+                self.write_expr(module, value, func_ctx)?;
+                write!(self.out, ")")?
+            }
+            Expression::SubgroupOperation { op, argument } => {
+                use crate::{SubgroupOperation as So, SubgroupReduction as Sr};
+                let reduction_str = |reduction| match reduction {
+                    Sr::Add => "Add",
+                    Sr::Mul => "Mul",
+                    Sr::Min => "Min",
+                    Sr::Max => "Max",
+                    Sr::And => "And",
+                    Sr::Or => "Or",
+                    Sr::Xor => "Xor",
+                };
+                match op {
+                    So::Ballot => write!(self.out, "subgroupBallot(")?,
+                    So::All => write!(self.out, "subgroupAll(")?,
+                    So::Any => write!(self.out, "subgroupAny(")?,
+                    So::Reduce(reduction) => {
+                        write!(self.out, "subgroup{}(", reduction_str(reduction))?
+                    }
+                    So::InclusiveScan(reduction) => {
+                        write!(self.out, "subgroupInclusive{}(", reduction_str(reduction))?
+                    }
+                    So::ExclusiveScan(reduction) => {
+                        write!(self.out, "subgroupExclusive{}(", reduction_str(reduction))?
+                    }
+                    So::BroadcastFirst => write!(self.out, "subgroupBroadcastFirst(")?,
+                    So::Broadcast(_) => write!(self.out, "subgroupBroadcast(")?,
+                    So::Shuffle(_) => write!(self.out, "subgroupShuffle(")?,
+                    So::ShuffleDown(_) => write!(self.out, "subgroupShuffleDown(")?,
+                    So::ShuffleUp(_) => write!(self.out, "subgroupShuffleUp(")?,
+                    So::ShuffleXor(_) => write!(self.out, "subgroupShuffleXor(")?,
+                }
+                self.write_expr(module, argument, func_ctx)?;
+                if let Some(index) = op.index() {
+                    write!(self.out, ", ")?;
+                    self.write_expr(module, index, func_ctx)?;
+                }
+                write!(self.out, ")")?
+            }
             // Not supported yet
             Expression::RayQueryGetIntersection { .. } => unreachable!(),
             // Nothing to do here, since call expression already cached
@@ -1674,12 +2087,30 @@ impl<W: Write> Writer<W> {
         global: &crate::GlobalVariable,
         handle: Handle<crate::GlobalVariable>,
     ) -> BackendResult {
-        // Write group and binding attributes if present
+        // Write group and binding attributes if present, and mark coherent
+        // storage
+        let mut attributes = Vec::new();
         if let Some(ref binding) = global.binding {
-            self.write_attributes(&[
-                Attribute::Group(binding.group),
-                Attribute::Binding(binding.binding),
-            ])?;
+            attributes.push(Attribute::Group(binding.group));
+            attributes.push(Attribute::Binding(binding.binding));
+        }
+        let storage_access = match global.space {
+            crate::AddressSpace::Storage { access } => Some(access),
+            _ => match module.types[global.ty].inner {
+                crate::TypeInner::Image {
+                    class: crate::ImageClass::Storage { access, .. },
+                    ..
+                } => Some(access),
+                _ => None,
+            },
+        };
+        if let Some(access) = storage_access {
+            if access.contains(crate::StorageAccess::COHERENT) {
+                attributes.push(Attribute::Coherent);
+            }
+        }
+        if !attributes.is_empty() {
+            self.write_attributes(&attributes)?;
             writeln!(self.out)?;
         }
 
@@ -1742,6 +2173,105 @@ impl<W: Write> Writer<W> {
     }
 }
 
+/// Drop the whitespace in `source` that isn't needed to separate tokens.
+///
+/// A space is kept only between two identifier characters, and between two
+/// operator characters, so that `a - -b` doesn't turn into `a--b`.
+fn minify_whitespace(source: &str) -> String {
+    let is_word = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let is_operator = |c: char| "+-*/%&|^<>=!~".contains(c);
+
+    let mut minified = String::with_capacity(source.len());
+    let mut space = false;
+    for c in source.chars() {
+        if c.is_whitespace() {
+            space = true;
+            continue;
+        }
+        if space {
+            if let Some(last) = minified.chars().next_back() {
+                if (is_word(last) && is_word(c)) || (is_operator(last) && is_operator(c)) {
+                    minified.push(' ');
+                }
+            }
+            space = false;
+        }
+        minified.push(c);
+    }
+    minified
+}
+
+/// Return true if `module` uses `f16` values, and so needs an `enable f16;`
+/// directive.
+fn uses_f16(module: &Module) -> bool {
+    let is_f16 = |inner: &TypeInner| match *inner {
+        TypeInner::Scalar { kind, width }
+        | TypeInner::Vector { kind, width, .. }
+        | TypeInner::Atomic { kind, width }
+        | TypeInner::ValuePointer { kind, width, .. } => {
+            (kind, width) == (crate::ScalarKind::Float, 2)
+        }
+        TypeInner::Matrix { width, .. } => width == 2,
+        _ => false,
+    };
+    let has_literal = |expressions: &crate::Arena<crate::Expression>| {
+        expressions
+            .iter()
+            .any(|(_, expr)| matches!(*expr, crate::Expression::Literal(crate::Literal::F16(_))))
+    };
+
+    module.types.iter().any(|(_, ty)| is_f16(&ty.inner))
+        || has_literal(&module.const_expressions)
+        || module
+            .functions
+            .iter()
+            .any(|(_, function)| has_literal(&function.expressions))
+        || module
+            .entry_points
+            .iter()
+            .any(|ep| has_literal(&ep.function.expressions))
+}
+
+/// Return true if `module` uses subgroup operations, and so needs an
+/// `enable subgroups;` directive.
+fn uses_subgroups(module: &Module) -> bool {
+    let has_subgroup_operation = |function: &crate::Function| {
+        function
+            .expressions
+            .iter()
+            .any(|(_, expr)| matches!(*expr, crate::Expression::SubgroupOperation { .. }))
+    };
+
+    module
+        .functions
+        .iter()
+        .any(|(_, function)| has_subgroup_operation(function))
+        || module
+            .entry_points
+            .iter()
+            .any(|ep| has_subgroup_operation(&ep.function))
+}
+
+/// Return true if `module` uses `NonUniform` expressions, and so needs an
+/// `enable nonuniform_indexing;` directive.
+fn uses_non_uniform(module: &Module) -> bool {
+    let has_non_uniform = |function: &crate::Function| {
+        function
+            .expressions
+            .iter()
+            .any(|(_, expr)| matches!(*expr, crate::Expression::NonUniform(_)))
+    };
+
+    module
+        .functions
+        .iter()
+        .any(|(_, function)| has_non_uniform(function))
+        || module
+            .entry_points
+            .iter()
+            .any(|ep| has_non_uniform(&ep.function))
+}
+
 fn builtin_str(built_in: crate::BuiltIn) -> Result<&'static str, Error> {
     use crate::BuiltIn as Bi;
 
@@ -1760,6 +2290,8 @@ fn builtin_str(built_in: crate::BuiltIn) -> Result<&'static str, Error> {
         Bi::SampleMask => "sample_mask",
         Bi::PrimitiveIndex => "primitive_index",
         Bi::ViewIndex => "view_index",
+        Bi::SubgroupSize => "subgroup_size",
+        Bi::SubgroupInvocationId => "subgroup_invocation_id",
         Bi::BaseInstance
         | Bi::BaseVertex
         | Bi::ClipDistance
@@ -1789,8 +2321,11 @@ const fn scalar_kind_str(kind: crate::ScalarKind, width: u8) -> &'static str {
     match (kind, width) {
         (Sk::Float, 8) => "f64",
         (Sk::Float, 4) => "f32",
+        (Sk::Float, 2) => "f16",
         (Sk::Sint, 4) => "i32",
         (Sk::Uint, 4) => "u32",
+        (Sk::Sint, 8) => "i64",
+        (Sk::Uint, 8) => "u64",
         (Sk::Bool, 1) => "bool",
         _ => unreachable!(),
     }
@@ -1,5 +1,27 @@
-use crate::{Span, Statement};
-use std::ops::{Deref, DerefMut, RangeBounds};
+use crate::{arena::Handle, LocalVariable, Span, Statement};
+use std::ops::{Bound, Deref, DerefMut, RangeBounds, RangeFrom};
+
+/// A local variable scoped to the statements of a [`Block`] from its
+/// declaration on.
+///
+/// See [`Block::locals`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct ScopedLocal {
+    pub local: Handle<LocalVariable>,
+    /// The index of the first statement the local is in scope for.
+    pub start: usize,
+}
+
+impl ScopedLocal {
+    /// The indices of the statements the local is in scope for: all of
+    /// them from [`start`](Self::start) to the end of its block.
+    pub const fn statements(&self) -> RangeFrom<usize> {
+        self.start..
+    }
+}
 
 /// A code block is a vector of statements, with maybe a vector of spans.
 #[derive(Debug, Clone, Default)]
@@ -11,6 +33,8 @@ pub struct Block {
     #[cfg(feature = "span")]
     #[cfg_attr(feature = "serialize", serde(skip))]
     span_info: Vec<Span>,
+    #[cfg_attr(feature = "serialize", serde(skip))]
+    locals: Vec<ScopedLocal>,
 }
 
 impl Block {
@@ -19,6 +43,7 @@ impl Block {
             body: Vec::new(),
             #[cfg(feature = "span")]
             span_info: Vec::new(),
+            locals: Vec::new(),
         }
     }
 
@@ -31,6 +56,7 @@ impl Block {
             body,
             #[cfg(feature = "span")]
             span_info,
+            locals: Vec::new(),
         }
     }
 
@@ -39,6 +65,7 @@ impl Block {
             body: Vec::with_capacity(capacity),
             #[cfg(feature = "span")]
             span_info: Vec::with_capacity(capacity),
+            locals: Vec::new(),
         }
     }
 
@@ -56,28 +83,68 @@ impl Block {
     }
 
     pub fn extend_block(&mut self, other: Self) {
+        self.adopt_locals(other.locals.iter().copied(), self.body.len());
         #[cfg(feature = "span")]
         self.span_info.extend(other.span_info);
         self.body.extend(other.body);
     }
 
     pub fn append(&mut self, other: &mut Self) {
+        self.adopt_locals(other.locals.drain(..), self.body.len());
         #[cfg(feature = "span")]
         self.span_info.append(&mut other.span_info);
         self.body.append(&mut other.body);
     }
 
     pub fn cull<R: RangeBounds<usize> + Clone>(&mut self, range: R) {
+        self.splice_locals(&range, 0);
         #[cfg(feature = "span")]
         self.span_info.drain(range.clone());
         self.body.drain(range);
     }
 
     pub fn splice<R: RangeBounds<usize> + Clone>(&mut self, range: R, other: Self) {
+        let start = self.splice_locals(&range, other.body.len());
+        self.adopt_locals(other.locals.iter().copied(), start);
         #[cfg(feature = "span")]
         self.span_info.splice(range.clone(), other.span_info);
         self.body.splice(range, other.body);
     }
+
+    /// Add `locals`, from a block whose statements are about to be inserted
+    /// at `offset`.
+    fn adopt_locals(&mut self, locals: impl Iterator<Item = ScopedLocal>, offset: usize) {
+        self.locals.extend(locals.map(|scoped| ScopedLocal {
+            local: scoped.local,
+            start: scoped.start + offset,
+        }));
+    }
+
+    /// Adjust the scopes of `self.locals` for the statements in `range`
+    /// being replaced by `count` others, and return the start of `range`.
+    ///
+    /// A local declared in the replaced statements is in scope from the
+    /// first of their replacements.
+    fn splice_locals(&mut self, range: &impl RangeBounds<usize>, count: usize) -> usize {
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&end) => end + 1,
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => self.body.len(),
+        };
+        for scoped in self.locals.iter_mut() {
+            if scoped.start >= end {
+                scoped.start = scoped.start - (end - start) + count;
+            } else if scoped.start > start {
+                scoped.start = start;
+            }
+        }
+        start
+    }
     pub fn span_iter(&self) -> impl Iterator<Item = (&Statement, &Span)> {
         #[cfg(feature = "span")]
         let span_iter = self.span_info.iter();
@@ -96,6 +163,43 @@ impl Block {
         self.body.iter_mut().zip(span_iter)
     }
 
+    /// Record that `local` is scoped to this block, from the next
+    /// statement pushed onto it to the end.
+    ///
+    /// See [`locals`](Self::locals).
+    pub fn declare_local(&mut self, local: Handle<LocalVariable>) {
+        self.declare_local_at(local, self.body.len());
+    }
+
+    /// Record that `local` is scoped to this block, from the statement at
+    /// index `start` to the end.
+    ///
+    /// See [`locals`](Self::locals).
+    pub fn declare_local_at(&mut self, local: Handle<LocalVariable>, start: usize) {
+        self.locals.push(ScopedLocal { local, start });
+    }
+
+    /// The local variables scoped to this block, with the statements they
+    /// are in scope for.
+    ///
+    /// Front ends may record here the locals their source declares inside
+    /// this block, so that writers can declare them where their source
+    /// did, instead of at the top of the function, and so that debug
+    /// information can say where each one is visible. A local shadowing
+    /// another of the same name is only in scope after its declaration.
+    ///
+    /// A scoped local must only be used by the statements it is in scope
+    /// for, and may not keep its value between entries to the block.
+    /// Locals not scoped to any block belong to the whole function.
+    ///
+    /// The methods that edit a block keep these scopes in step, but passes
+    /// that build a new block in place of an old one may drop them, leaving
+    /// the locals to the whole function. The serde implementations skip
+    /// them, but [`serialize`](crate::serialize) keeps them.
+    pub fn locals(&self) -> &[ScopedLocal] {
+        &self.locals
+    }
+
     pub fn is_empty(&self) -> bool {
         self.body.is_empty()
     }
@@ -142,3 +246,48 @@ impl From<Vec<Statement>> for Block {
         Self::from_vec(body)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Block;
+    use crate::{Arena, LocalVariable, Span, Statement};
+
+    #[test]
+    fn edits_keep_scopes() {
+        let mut locals = Arena::new();
+        let mut local = || {
+            let local = LocalVariable {
+                name: None,
+                ty: crate::Handle::DUMMY,
+                init: None,
+            };
+            locals.append(local, Span::UNDEFINED)
+        };
+        let starts = |block: &Block| -> Vec<usize> {
+            block.locals().iter().map(|scoped| scoped.start).collect()
+        };
+
+        let mut block = Block::new();
+        block.push(Statement::Kill, Span::UNDEFINED);
+        block.declare_local(local());
+        block.push(Statement::Break, Span::UNDEFINED);
+        block.push(Statement::Continue, Span::UNDEFINED);
+        block.declare_local(local());
+        block.push(Statement::Kill, Span::UNDEFINED);
+        assert_eq!(starts(&block), [1, 3]);
+
+        // Removing statements before a scope moves it up, and removing the
+        // statement it starts at has it start at the next one.
+        block.cull(0..2);
+        assert_eq!(starts(&block), [0, 1]);
+
+        let mut other = Block::from_vec(vec![Statement::Break, Statement::Break]);
+        other.declare_local_at(local(), 1);
+        block.splice(0..1, other);
+        assert_eq!(starts(&block), [0, 2, 1]);
+
+        let mut front = Block::from_vec(vec![Statement::Kill]);
+        front.append(&mut block);
+        assert_eq!(starts(&front), [1, 3, 2]);
+    }
+}
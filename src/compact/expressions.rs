@@ -68,6 +68,10 @@ impl<'tracer> ExpressionTracer<'tracer> {
                     self.trace_type(ty);
                     work_list.extend(components);
                 }
+                Ex::Repeat { ty, value } => {
+                    self.trace_type(ty);
+                    work_list.push(value);
+                }
                 Ex::Access { base, index } => work_list.extend([base, index]),
                 Ex::AccessIndex { base, index: _ } => work_list.push(base),
                 Ex::Splat { size: _, value } => work_list.push(value),
@@ -160,6 +164,11 @@ impl<'tracer> ExpressionTracer<'tracer> {
                     query,
                     committed: _,
                 } => work_list.push(query),
+                Ex::SubgroupOperation { op, argument } => {
+                    work_list.push(argument);
+                    work_list.extend(op.index());
+                }
+                Ex::NonUniform(value) => work_list.push(value),
             }
         }
     }
@@ -219,12 +228,12 @@ impl ModuleMap {
             // Expressions that do not contain handles that need to be adjusted.
             Ex::Literal(_)
             | Ex::FunctionArgument(_)
-            | Ex::GlobalVariable(_)
             | Ex::LocalVariable(_)
             | Ex::CallResult(_)
             | Ex::RayQueryProceedResult => {}
 
             // Expressions that contain handles that need to be adjusted.
+            Ex::GlobalVariable(ref mut global) => self.global_variables.adjust(global),
             Ex::Constant(ref mut constant) => self.constants.adjust(constant),
             Ex::ZeroValue(ref mut ty) => self.types.adjust(ty),
             Ex::Compose {
@@ -236,6 +245,13 @@ impl ModuleMap {
                     adjust(component);
                 }
             }
+            Ex::Repeat {
+                ref mut ty,
+                ref mut value,
+            } => {
+                self.types.adjust(ty);
+                adjust(value);
+            }
             Ex::Access {
                 ref mut base,
                 ref mut index,
@@ -354,6 +370,16 @@ impl ModuleMap {
                 ref mut query,
                 committed: _,
             } => adjust(query),
+            Ex::SubgroupOperation {
+                ref mut op,
+                ref mut argument,
+            } => {
+                adjust(argument);
+                if let Some(index) = op.index_mut() {
+                    adjust(index);
+                }
+            }
+            Ex::NonUniform(ref mut value) => adjust(value),
         }
     }
 
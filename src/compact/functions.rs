@@ -9,6 +9,7 @@ pub struct FunctionTracer<'a> {
     pub types_used: &'a mut HandleSet<crate::Type>,
     pub constants_used: &'a mut HandleSet<crate::Constant>,
     pub const_expressions_used: &'a mut HandleSet<crate::Expression>,
+    pub global_variables_used: &'a mut HandleSet<crate::GlobalVariable>,
 
     /// Function-local expressions used.
     pub expressions_used: HandleSet<crate::Expression>,
@@ -38,6 +39,15 @@ impl<'a> FunctionTracer<'a> {
         }
 
         self.trace_block(&self.function.body);
+
+        // Note the globals the used expressions refer to.
+        for (handle, expr) in self.function.expressions.iter() {
+            if let crate::Expression::GlobalVariable(global) = *expr {
+                if self.expressions_used.contains(handle) {
+                    self.global_variables_used.insert(global);
+                }
+            }
+        }
     }
 
     pub fn trace_type(&mut self, ty: Handle<crate::Type>) {
@@ -7,14 +7,25 @@ mod types;
 use crate::{arena, compact::functions::FunctionTracer};
 use handle_set_map::{HandleMap, HandleSet};
 
+/// Whether [`compact_with`] should keep global variables that nothing uses.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum KeepUnused {
+    /// Treat every global variable as used, since the pipeline may refer
+    /// to it whether or not the module's code does.
+    Yes,
+    /// Remove the global variables that no function or entry point
+    /// refers to.
+    No,
+}
+
 /// Remove unused types, expressions, and constants from `module`.
 ///
-/// Assuming that all globals, named constants, special types,
-/// functions and entry points in `module` are used, determine which
-/// types, constants, and expressions (both function-local and global
-/// constant expressions) are actually used, and remove the rest,
-/// adjusting all handles as necessary. The result should be a module
-/// functionally identical to the original.
+/// Assuming that all named constants, special types, functions, entry
+/// points and globals in `module` are used, determine which types,
+/// constants, and expressions (both function-local and global constant
+/// expressions) are actually used, and remove the rest, adjusting all
+/// handles as necessary. The result should be a module functionally
+/// identical to the original.
 ///
 /// This may be useful to apply to modules generated in the snapshot
 /// tests. Our backends often generate temporary names based on handle
@@ -29,19 +40,17 @@ use handle_set_map::{HandleMap, HandleSet};
 ///
 /// If `module` has not passed validation, this may panic.
 pub fn compact(module: &mut crate::Module) {
-    let mut module_tracer = ModuleTracer::new(module);
+    compact_with(module, KeepUnused::Yes)
+}
 
-    // We treat all globals as used by definition.
-    log::trace!("tracing global variables");
-    {
-        for (_, global) in module.global_variables.iter() {
-            log::trace!("tracing global {:?}", global.name);
-            module_tracer.as_type().trace_type(global.ty);
-            if let Some(init) = global.init {
-                module_tracer.as_const_expression().trace_expression(init);
-            }
-        }
-    }
+/// Like [`compact`], but also remove the global variables that nothing
+/// uses if `keep_unused` is [`KeepUnused::No`].
+///
+/// # Panics
+///
+/// If `module` has not passed validation, this may panic.
+pub fn compact_with(module: &mut crate::Module, keep_unused: KeepUnused) {
+    let mut module_tracer = ModuleTracer::new(module);
 
     // We treat all special types as used by definition.
     module_tracer.trace_special_types(&module.special_types);
@@ -87,6 +96,22 @@ pub fn compact(module: &mut crate::Module) {
         })
         .collect();
 
+    // Unless asked not to, we treat all globals as used by definition.
+    // Otherwise, the functions have found the ones they use.
+    log::trace!("tracing global variables");
+    for (handle, global) in module.global_variables.iter() {
+        if keep_unused == KeepUnused::Yes {
+            module_tracer.global_variables_used.insert(handle);
+        } else if !module_tracer.global_variables_used.contains(handle) {
+            continue;
+        }
+        log::trace!("tracing global {:?}", global.name);
+        module_tracer.as_type().trace_type(global.ty);
+        if let Some(init) = global.init {
+            module_tracer.as_const_expression().trace_expression(init);
+        }
+    }
+
     // Now that we know what is used and what is never touched,
     // produce maps from the `Handle`s that appear in `module` now to
     // the corresponding `Handle`s that will refer to the same items
@@ -134,15 +159,20 @@ pub fn compact(module: &mut crate::Module) {
         }
     });
 
-    // Adjust global variables' types and initializers.
+    // Drop unused global variables in place, and adjust the types and
+    // initializers of the rest.
     log::trace!("adjusting global variables");
-    for (_, global) in module.global_variables.iter_mut() {
+    module.global_variables.retain_mut(|handle, global| {
+        if !module_map.global_variables.used(handle) {
+            return false;
+        }
         log::trace!("adjusting global {:?}", global.name);
         module_map.types.adjust(&mut global.ty);
         if let Some(ref mut init) = global.init {
             module_map.const_expressions.adjust(init);
         }
-    }
+        true
+    });
 
     // Temporary storage to help us reuse allocations of existing
     // named expression tables.
@@ -163,6 +193,9 @@ pub fn compact(module: &mut crate::Module) {
             &mut reused_named_expressions,
         );
     }
+
+    // Give back the room the removed items took up.
+    module.shrink_to_fit();
 }
 
 struct ModuleTracer<'module> {
@@ -170,6 +203,7 @@ struct ModuleTracer<'module> {
     types_used: HandleSet<crate::Type>,
     constants_used: HandleSet<crate::Constant>,
     const_expressions_used: HandleSet<crate::Expression>,
+    global_variables_used: HandleSet<crate::GlobalVariable>,
 }
 
 impl<'module> ModuleTracer<'module> {
@@ -179,6 +213,7 @@ impl<'module> ModuleTracer<'module> {
             types_used: HandleSet::for_arena(&module.types),
             constants_used: HandleSet::for_arena(&module.constants),
             const_expressions_used: HandleSet::for_arena(&module.const_expressions),
+            global_variables_used: HandleSet::for_arena(&module.global_variables),
         }
     }
 
@@ -231,6 +266,7 @@ impl<'module> ModuleTracer<'module> {
             types_used: &mut self.types_used,
             constants_used: &mut self.constants_used,
             const_expressions_used: &mut self.const_expressions_used,
+            global_variables_used: &mut self.global_variables_used,
             expressions_used: HandleSet::for_arena(&function.expressions),
         }
     }
@@ -240,6 +276,7 @@ struct ModuleMap {
     types: HandleMap<crate::Type>,
     constants: HandleMap<crate::Constant>,
     const_expressions: HandleMap<crate::Expression>,
+    global_variables: HandleMap<crate::GlobalVariable>,
 }
 
 impl From<ModuleTracer<'_>> for ModuleMap {
@@ -248,6 +285,7 @@ impl From<ModuleTracer<'_>> for ModuleMap {
             types: HandleMap::from_set(used.types_used),
             constants: HandleMap::from_set(used.constants_used),
             const_expressions: HandleMap::from_set(used.const_expressions_used),
+            global_variables: HandleMap::from_set(used.global_variables_used),
         }
     }
 }
@@ -284,3 +322,158 @@ impl From<FunctionTracer<'_>> for FunctionMap {
         }
     }
 }
+
+#[test]
+fn drops_unused_entries() {
+    use crate::{Expression, Literal, Span, Type, TypeInner};
+
+    let mut module = crate::Module::default();
+    let scalar = |kind| Type {
+        name: None,
+        inner: TypeInner::Scalar { kind, width: 4 },
+    };
+    let unused_ty = module
+        .types
+        .insert(scalar(crate::ScalarKind::Uint), Span::default());
+    let float_ty = module
+        .types
+        .insert(scalar(crate::ScalarKind::Float), Span::default());
+    let unused_init = module
+        .const_expressions
+        .append(Expression::Literal(Literal::U32(1)), Span::default());
+    module.constants.append(
+        crate::Constant {
+            name: None,
+            r#override: crate::Override::None,
+            ty: unused_ty,
+            init: unused_init,
+        },
+        Span::default(),
+    );
+    let init = module
+        .const_expressions
+        .append(Expression::Literal(Literal::F32(1.0)), Span::default());
+    module.global_variables.append(
+        crate::GlobalVariable {
+            name: Some("g".to_string()),
+            space: crate::AddressSpace::Private,
+            binding: None,
+            ty: float_ty,
+            init: Some(init),
+        },
+        Span::default(),
+    );
+
+    let mut function = crate::Function::default();
+    function
+        .expressions
+        .append(Expression::Literal(Literal::F32(2.0)), Span::default());
+    let value = function
+        .expressions
+        .append(Expression::Literal(Literal::F32(3.0)), Span::default());
+    function.body.push(
+        crate::Statement::Return { value: Some(value) },
+        Span::default(),
+    );
+    function.result = Some(crate::FunctionResult {
+        ty: float_ty,
+        binding: None,
+    });
+    module.functions.append(function, Span::default());
+
+    compact(&mut module);
+
+    assert_eq!(module.types.len(), 1);
+    assert!(module.constants.is_empty());
+    assert_eq!(module.const_expressions.len(), 1);
+    let (_, global) = module.global_variables.iter().next().unwrap();
+    assert_eq!(
+        module.types[global.ty].inner,
+        scalar(crate::ScalarKind::Float).inner
+    );
+    assert!(matches!(
+        module.const_expressions[global.init.unwrap()],
+        Expression::Literal(Literal::F32(value)) if value == 1.0
+    ));
+    let (_, function) = module.functions.iter().next().unwrap();
+    assert_eq!(function.expressions.len(), 1);
+    assert_eq!(
+        function.result.as_ref().unwrap().ty,
+        global.ty,
+        "handles should be remapped"
+    );
+}
+
+#[test]
+fn drops_unused_globals() {
+    use crate::{Expression, Span, Type, TypeInner};
+
+    let build = || {
+        let mut module = crate::Module::default();
+        let scalar = |kind| Type {
+            name: None,
+            inner: TypeInner::Scalar { kind, width: 4 },
+        };
+        let uint_ty = module
+            .types
+            .insert(scalar(crate::ScalarKind::Uint), Span::default());
+        let float_ty = module
+            .types
+            .insert(scalar(crate::ScalarKind::Float), Span::default());
+        let global = |name: &str, ty| crate::GlobalVariable {
+            name: Some(name.to_string()),
+            space: crate::AddressSpace::Private,
+            binding: None,
+            ty,
+            init: None,
+        };
+        module
+            .global_variables
+            .append(global("unused", uint_ty), Span::default());
+        let used = module
+            .global_variables
+            .append(global("used", float_ty), Span::default());
+
+        let mut function = crate::Function::default();
+        let pointer = function
+            .expressions
+            .append(Expression::GlobalVariable(used), Span::default());
+        let value = function
+            .expressions
+            .append(Expression::Load { pointer }, Span::default());
+        function.body.push(
+            crate::Statement::Emit(function.expressions.range_from(1)),
+            Span::default(),
+        );
+        function.body.push(
+            crate::Statement::Return { value: Some(value) },
+            Span::default(),
+        );
+        function.result = Some(crate::FunctionResult {
+            ty: float_ty,
+            binding: None,
+        });
+        module.functions.append(function, Span::default());
+        module
+    };
+
+    let mut kept = build();
+    compact(&mut kept);
+    assert_eq!(kept.global_variables.len(), 2);
+    assert_eq!(kept.types.len(), 2);
+
+    let mut module = build();
+    compact_with(&mut module, KeepUnused::No);
+    assert_eq!(module.global_variables.len(), 1);
+    let (handle, global) = module.global_variables.iter().next().unwrap();
+    assert_eq!(global.name.as_deref(), Some("used"));
+    assert_eq!(module.types.len(), 1);
+    let (_, function) = module.functions.iter().next().unwrap();
+    assert!(
+        matches!(
+            *function.expressions.iter().next().unwrap().1,
+            Expression::GlobalVariable(global) if global == handle
+        ),
+        "handles should be remapped"
+    );
+}
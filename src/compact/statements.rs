@@ -64,6 +64,21 @@ impl FunctionTracer<'_> {
                         }
                         self.trace_expression(value);
                     }
+                    St::ImageAtomic {
+                        image,
+                        coordinate,
+                        array_index,
+                        ref fun,
+                        value,
+                    } => {
+                        self.trace_expression(image);
+                        self.trace_expression(coordinate);
+                        if let Some(array_index) = array_index {
+                            self.trace_expression(array_index);
+                        }
+                        self.trace_atomic_function(fun);
+                        self.trace_expression(value);
+                    }
                     St::Atomic {
                         pointer,
                         ref fun,
@@ -207,6 +222,21 @@ impl FunctionMap {
                         }
                         adjust(value);
                     }
+                    St::ImageAtomic {
+                        ref mut image,
+                        ref mut coordinate,
+                        ref mut array_index,
+                        ref mut fun,
+                        ref mut value,
+                    } => {
+                        adjust(image);
+                        adjust(coordinate);
+                        if let Some(ref mut array_index) = *array_index {
+                            adjust(array_index);
+                        }
+                        self.adjust_atomic_function(fun);
+                        adjust(value);
+                    }
                     St::Atomic {
                         ref mut pointer,
                         ref mut fun,
@@ -0,0 +1,112 @@
+/*!
+Choosing how seriously to take the diagnostics a module can trigger.
+
+WGSL lets a shader change the severity of some diagnostics with a global
+`diagnostic` directive, like `diagnostic(off, derivative_uniformity);`.
+Front ends record these as [`DiagnosticFilter`]s in
+[`Module::diagnostic_filters`], and the validator consults them.
+
+[`Module::diagnostic_filters`]: crate::Module::diagnostic_filters
+*/
+
+/// How a diagnostic is reported, when a [`DiagnosticFilter`] changes it.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum Severity {
+    /// The diagnostic isn't reported.
+    Off,
+    /// The diagnostic is reported as a warning.
+    ///
+    /// Naga has no lower level than warnings, so this is the same as
+    /// [`Warning`](Self::Warning).
+    Info,
+    /// The diagnostic is reported as a warning, which doesn't make
+    /// validation fail.
+    ///
+    /// This is what Naga does for every rule a module doesn't filter.
+    #[default]
+    Warning,
+    /// The diagnostic is reported as an error, which makes validation fail.
+    Error,
+}
+
+impl Severity {
+    /// Parse the name WGSL uses for this severity.
+    pub fn from_wgsl_ident(ident: &str) -> Option<Self> {
+        Some(match ident {
+            "off" => Self::Off,
+            "info" => Self::Info,
+            "warning" => Self::Warning,
+            "error" => Self::Error,
+            _ => return None,
+        })
+    }
+
+    /// The name WGSL uses for this severity.
+    pub const fn to_wgsl_ident(self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::Info => "info",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        }
+    }
+}
+
+/// A diagnostic that a [`DiagnosticFilter`] can apply to.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum FilterableTriggeringRule {
+    /// Sampling with an implicit level of detail in control flow that may
+    /// not be uniform.
+    ///
+    /// See [`ValidationWarning::ImplicitLevelInNonUniformControlFlow`].
+    ///
+    /// [`ValidationWarning::ImplicitLevelInNonUniformControlFlow`]: crate::valid::ValidationWarning::ImplicitLevelInNonUniformControlFlow
+    DerivativeUniformity,
+}
+
+impl FilterableTriggeringRule {
+    /// Parse the name WGSL uses for this rule.
+    pub fn from_wgsl_ident(ident: &str) -> Option<Self> {
+        match ident {
+            "derivative_uniformity" => Some(Self::DerivativeUniformity),
+            _ => None,
+        }
+    }
+
+    /// The name WGSL uses for this rule.
+    pub const fn to_wgsl_ident(self) -> &'static str {
+        match self {
+            Self::DerivativeUniformity => "derivative_uniformity",
+        }
+    }
+}
+
+/// A request to report a diagnostic with a different [`Severity`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct DiagnosticFilter {
+    pub new_severity: Severity,
+    pub triggering_rule: FilterableTriggeringRule,
+}
+
+/// The severity that `filters` give `rule`, if any of them apply to it.
+///
+/// If several do, the last one wins.
+pub fn severity_of(
+    filters: &[DiagnosticFilter],
+    rule: FilterableTriggeringRule,
+) -> Option<Severity> {
+    filters
+        .iter()
+        .rev()
+        .find(|filter| filter.triggering_rule == rule)
+        .map(|filter| filter.new_severity)
+}
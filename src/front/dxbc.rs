@@ -0,0 +1,721 @@
+/*!
+Reading binding reflection from compiled Direct3D shaders.
+
+This doesn't translate DXBC instructions into Naga IR. It reads the `RDEF`
+chunk that `fxc` writes into a DXBC container, which lists the resources the
+shader binds, and describes them with the types of the [`reflect`] module, so
+that asset pipelines holding both Naga modules and legacy compiled shaders can
+handle them the same way.
+
+DXIL containers only work here if they have an `RDEF` chunk. `dxc` usually
+keeps DXIL reflection in LLVM bitcode instead, which this doesn't read.
+
+[`reflect`]: crate::reflect
+*/
+
+use crate::arena::Handle;
+use crate::reflect::{EntryPointReflection, Reflection, Resource, ResourceUse};
+use crate::valid::{BindingType, GlobalUse};
+use crate::{
+    AddressSpace, ArraySize, GlobalVariable, ImageClass, ImageDimension, Module, ResourceBinding,
+    ScalarKind, ShaderStage, Span, StorageAccess, StorageFormat, Type, TypeInner,
+};
+
+#[derive(Clone, Debug, thiserror::Error, PartialEq)]
+pub enum Error {
+    #[error("not a DXBC container")]
+    InvalidHeader,
+    #[error("unexpected end of data at offset {0}")]
+    UnexpectedEnd(usize),
+    #[error("the container has no RDEF chunk")]
+    MissingReflection,
+    #[error("string at offset {0} is not valid UTF-8")]
+    InvalidString(usize),
+    #[error("unsupported shader program type {0:#x}")]
+    UnsupportedStage(u16),
+    #[error("resource `{name}` has unsupported input type {input_type} and dimension {dimension}")]
+    UnsupportedResource {
+        name: String,
+        input_type: u32,
+        dimension: u32,
+    },
+    #[error("invalid instruction length at offset {0}")]
+    InvalidInstruction(usize),
+}
+
+/// Read the binding reflection of the DXBC container in `data`.
+///
+/// This returns a module holding only a global variable for each resource,
+/// which the [`Resource::global`] handles refer to, along with the
+/// reflection itself. The reflection has a single entry point, whose name is
+/// empty, since containers don't record it. It doesn't describe vertex
+/// inputs or push constants.
+///
+/// Each resource's [`ResourceBinding`] has its register space as the group
+/// and its register number as the binding. Direct3D numbers each register
+/// class separately, so a texture and a sampler may both have binding 0.
+///
+/// Since `RDEF` doesn't record the format of storage images, the format is
+/// a guess from the return type and component count, such as
+/// [`Rgba32Float`] for a `RWTexture2D<float4>`.
+///
+/// [`Rgba32Float`]: StorageFormat::Rgba32Float
+pub fn parse_reflection(data: &[u8]) -> Result<(Module, Reflection), Error> {
+    let container = Reader { data, base: 0 };
+    if data.get(..4) != Some(b"DXBC") {
+        return Err(Error::InvalidHeader);
+    }
+    let chunk_count = container.u32(28)?;
+    let mut rdef = None;
+    let mut shader = None;
+    for index in 0..chunk_count as usize {
+        let offset = container.u32(32 + index * 4)? as usize;
+        let tag = container.bytes(offset, 4)?;
+        let size = container.u32(offset + 4)? as usize;
+        let chunk = Reader {
+            data: container.bytes(offset + 8, size)?,
+            base: offset + 8,
+        };
+        match tag {
+            b"RDEF" => rdef = Some(chunk),
+            b"SHDR" | b"SHEX" => shader = Some(chunk),
+            _ => {}
+        }
+    }
+    let rdef = rdef.ok_or(Error::MissingReflection)?;
+
+    let program_type = rdef.u16(18)?;
+    let stage = match program_type {
+        0xFFFF => ShaderStage::Fragment,
+        0xFFFE => ShaderStage::Vertex,
+        0x4353 => ShaderStage::Compute,
+        other => return Err(Error::UnsupportedStage(other)),
+    };
+    // Shader model 5.1 added register spaces to resource bindings.
+    let (minor, major) = (rdef.u8(16)?, rdef.u8(17)?);
+    let binding_size = if (major, minor) >= (5, 1) { 40 } else { 32 };
+
+    let mut module = Module::default();
+    let mut resources = Vec::new();
+    let mut uses = Vec::new();
+    let binding_count = rdef.u32(8)? as usize;
+    let binding_offset = rdef.u32(12)? as usize;
+    for index in 0..binding_count {
+        let offset = binding_offset + index * binding_size;
+        let desc = BindingDesc {
+            name: rdef.str(rdef.u32(offset)? as usize)?,
+            input_type: rdef.u32(offset + 4)?,
+            return_type: rdef.u32(offset + 8)?,
+            dimension: rdef.u32(offset + 12)?,
+            samples_or_stride: rdef.u32(offset + 16)?,
+            register: rdef.u32(offset + 20)?,
+            count: rdef.u32(offset + 24)?,
+            flags: rdef.u32(offset + 28)?,
+            space: match binding_size {
+                40 => rdef.u32(offset + 32)?,
+                _ => 0,
+            },
+        };
+        let (resource, usage) = desc.to_resource(&rdef, &mut module)?;
+        uses.push(ResourceUse {
+            resource: resources.len(),
+            usage,
+        });
+        resources.push(resource);
+    }
+
+    let workgroup_size = match (stage, shader) {
+        (ShaderStage::Compute, Some(shader)) => thread_group_size(&shader)?,
+        _ => [0; 3],
+    };
+    let entry_point = EntryPointReflection {
+        name: String::new(),
+        stage,
+        workgroup_size,
+        resources: uses,
+        vertex_inputs: Vec::new(),
+        push_constants: None,
+    };
+
+    let reflection = Reflection {
+        resources,
+        entry_points: vec![entry_point],
+    };
+    Ok((module, reflection))
+}
+
+/// A view of part of the container, with offsets relative to its start.
+#[derive(Clone, Copy)]
+struct Reader<'a> {
+    data: &'a [u8],
+    /// The offset of `data` in the container, for error messages.
+    base: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&self, offset: usize, len: usize) -> Result<&'a [u8], Error> {
+        offset
+            .checked_add(len)
+            .and_then(|end| self.data.get(offset..end))
+            .ok_or(Error::UnexpectedEnd(self.base + offset))
+    }
+
+    fn u8(&self, offset: usize) -> Result<u8, Error> {
+        Ok(self.bytes(offset, 1)?[0])
+    }
+
+    fn u16(&self, offset: usize) -> Result<u16, Error> {
+        let bytes = self.bytes(offset, 2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&self, offset: usize) -> Result<u32, Error> {
+        let bytes = self.bytes(offset, 4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Read the nul-terminated string at `offset`.
+    fn str(&self, offset: usize) -> Result<&'a str, Error> {
+        let rest = self
+            .data
+            .get(offset..)
+            .ok_or(Error::UnexpectedEnd(self.base + offset))?;
+        let len = rest
+            .iter()
+            .position(|&byte| byte == 0)
+            .ok_or(Error::UnexpectedEnd(self.base + self.data.len()))?;
+        std::str::from_utf8(&rest[..len]).map_err(|_| Error::InvalidString(self.base + offset))
+    }
+}
+
+// `D3D_SHADER_INPUT_TYPE` values.
+const SIT_CBUFFER: u32 = 0;
+const SIT_TBUFFER: u32 = 1;
+const SIT_TEXTURE: u32 = 2;
+const SIT_SAMPLER: u32 = 3;
+const SIT_UAV_RWTYPED: u32 = 4;
+const SIT_STRUCTURED: u32 = 5;
+const SIT_UAV_RWSTRUCTURED: u32 = 6;
+const SIT_BYTEADDRESS: u32 = 7;
+const SIT_UAV_RWBYTEADDRESS: u32 = 8;
+const SIT_UAV_APPEND_STRUCTURED: u32 = 9;
+const SIT_UAV_CONSUME_STRUCTURED: u32 = 10;
+const SIT_UAV_RWSTRUCTURED_WITH_COUNTER: u32 = 11;
+const SIT_RTACCELERATIONSTRUCTURE: u32 = 12;
+
+/// `D3D_SIF_COMPARISON_SAMPLER`
+const SIF_COMPARISON_SAMPLER: u32 = 0x2;
+
+/// One `D3D11_SHADER_INPUT_BIND_DESC`.
+struct BindingDesc<'a> {
+    name: &'a str,
+    input_type: u32,
+    return_type: u32,
+    dimension: u32,
+    samples_or_stride: u32,
+    register: u32,
+    /// The number of registers, or zero for an unbounded array.
+    count: u32,
+    flags: u32,
+    space: u32,
+}
+
+impl BindingDesc<'_> {
+    fn to_resource(
+        &self,
+        rdef: &Reader,
+        module: &mut Module,
+    ) -> Result<(Resource, GlobalUse), Error> {
+        let read_write = StorageAccess::LOAD | StorageAccess::STORE;
+        let (inner, space, usage) = match self.input_type {
+            SIT_CBUFFER | SIT_TBUFFER => {
+                let size = constant_buffer_size(rdef, self.name)?;
+                let element = vec4_type(module);
+                let inner = TypeInner::Array {
+                    base: element,
+                    size: ArraySize::Constant(
+                        std::num::NonZeroU32::new((size / 16).max(1)).unwrap(),
+                    ),
+                    stride: 16,
+                };
+                let space = match self.input_type {
+                    SIT_CBUFFER => AddressSpace::Uniform,
+                    _ => AddressSpace::Storage {
+                        access: StorageAccess::LOAD,
+                    },
+                };
+                (inner, space, GlobalUse::READ)
+            }
+            SIT_TEXTURE => {
+                let (dim, arrayed, multi) = self.image_dimension()?;
+                let inner = TypeInner::Image {
+                    dim,
+                    arrayed,
+                    class: ImageClass::Sampled {
+                        kind: self.scalar_kind(),
+                        multi,
+                    },
+                };
+                (
+                    inner,
+                    AddressSpace::Handle,
+                    GlobalUse::READ | GlobalUse::QUERY,
+                )
+            }
+            SIT_SAMPLER => {
+                let comparison = self.flags & SIF_COMPARISON_SAMPLER != 0;
+                (
+                    TypeInner::Sampler { comparison },
+                    AddressSpace::Handle,
+                    GlobalUse::READ,
+                )
+            }
+            SIT_UAV_RWTYPED => {
+                let (dim, arrayed, _) = self.image_dimension()?;
+                let inner = TypeInner::Image {
+                    dim,
+                    arrayed,
+                    class: ImageClass::Storage {
+                        format: self.storage_format(),
+                        access: read_write,
+                    },
+                };
+                (
+                    inner,
+                    AddressSpace::Handle,
+                    GlobalUse::READ | GlobalUse::WRITE | GlobalUse::QUERY,
+                )
+            }
+            SIT_STRUCTURED
+            | SIT_UAV_RWSTRUCTURED
+            | SIT_BYTEADDRESS
+            | SIT_UAV_RWBYTEADDRESS
+            | SIT_UAV_APPEND_STRUCTURED
+            | SIT_UAV_CONSUME_STRUCTURED
+            | SIT_UAV_RWSTRUCTURED_WITH_COUNTER => {
+                let word = scalar_type(module, ScalarKind::Uint);
+                let (base, stride) = match self.input_type {
+                    SIT_BYTEADDRESS | SIT_UAV_RWBYTEADDRESS => (word, 4),
+                    // Structured buffers record their element size. Keep
+                    // it with an array of words.
+                    _ => {
+                        let stride = self.samples_or_stride.max(4);
+                        let element = insert_type(
+                            module,
+                            TypeInner::Array {
+                                base: word,
+                                size: ArraySize::Constant(
+                                    std::num::NonZeroU32::new(stride / 4).unwrap(),
+                                ),
+                                stride: 4,
+                            },
+                        );
+                        (element, stride)
+                    }
+                };
+                let inner = TypeInner::Array {
+                    base,
+                    size: ArraySize::Dynamic,
+                    stride,
+                };
+                let (access, usage) = match self.input_type {
+                    SIT_STRUCTURED | SIT_BYTEADDRESS => (StorageAccess::LOAD, GlobalUse::READ),
+                    _ => (read_write, GlobalUse::READ | GlobalUse::WRITE),
+                };
+                (inner, AddressSpace::Storage { access }, usage)
+            }
+            SIT_RTACCELERATIONSTRUCTURE => (
+                TypeInner::AccelerationStructure,
+                AddressSpace::Handle,
+                GlobalUse::READ,
+            ),
+            _ => return Err(self.unsupported()),
+        };
+
+        let base = insert_type(module, inner);
+        let ty_binding = binding_type(module, space, base);
+        let count = match self.count {
+            0 => Some(ArraySize::Dynamic),
+            1 => None,
+            n => Some(ArraySize::Constant(std::num::NonZeroU32::new(n).unwrap())),
+        };
+        let ty = match count {
+            Some(size) => insert_type(module, TypeInner::BindingArray { base, size }),
+            None => base,
+        };
+        let binding = ResourceBinding {
+            group: self.space,
+            binding: self.register,
+        };
+        let global = module.global_variables.append(
+            GlobalVariable {
+                name: Some(self.name.to_string()),
+                space,
+                binding: Some(binding.clone()),
+                ty,
+                init: None,
+            },
+            Span::UNDEFINED,
+        );
+        let resource = Resource {
+            global,
+            name: Some(self.name.to_string()),
+            binding,
+            ty: ty_binding,
+            count,
+        };
+        Ok((resource, usage))
+    }
+
+    fn unsupported(&self) -> Error {
+        Error::UnsupportedResource {
+            name: self.name.to_string(),
+            input_type: self.input_type,
+            dimension: self.dimension,
+        }
+    }
+
+    /// Decode a `D3D_SRV_DIMENSION` into a dimension, whether the image is
+    /// arrayed, and whether it is multisampled.
+    fn image_dimension(&self) -> Result<(ImageDimension, bool, bool), Error> {
+        Ok(match self.dimension {
+            2 => (ImageDimension::D1, false, false),
+            3 => (ImageDimension::D1, true, false),
+            4 => (ImageDimension::D2, false, false),
+            5 => (ImageDimension::D2, true, false),
+            6 => (ImageDimension::D2, false, true),
+            7 => (ImageDimension::D2, true, true),
+            8 => (ImageDimension::D3, false, false),
+            9 => (ImageDimension::Cube, false, false),
+            10 => (ImageDimension::Cube, true, false),
+            // Typed buffers have no Naga equivalent.
+            _ => return Err(self.unsupported()),
+        })
+    }
+
+    /// Decode a `D3D_RESOURCE_RETURN_TYPE` into the kind of value the
+    /// resource produces.
+    fn scalar_kind(&self) -> ScalarKind {
+        match self.return_type {
+            3 => ScalarKind::Sint,
+            4 => ScalarKind::Uint,
+            _ => ScalarKind::Float,
+        }
+    }
+
+    /// Guess a storage format from the return type and the component count
+    /// in the flags.
+    fn storage_format(&self) -> StorageFormat {
+        let components = ((self.flags >> 2) & 0x3) + 1;
+        match (self.return_type, components) {
+            (1, 1) => StorageFormat::R8Unorm,
+            (1, 2) => StorageFormat::Rg8Unorm,
+            (1, _) => StorageFormat::Rgba8Unorm,
+            (2, 1) => StorageFormat::R8Snorm,
+            (2, 2) => StorageFormat::Rg8Snorm,
+            (2, _) => StorageFormat::Rgba8Snorm,
+            (3, 1) => StorageFormat::R32Sint,
+            (3, 2) => StorageFormat::Rg32Sint,
+            (3, _) => StorageFormat::Rgba32Sint,
+            (4, 1) => StorageFormat::R32Uint,
+            (4, 2) => StorageFormat::Rg32Uint,
+            (4, _) => StorageFormat::Rgba32Uint,
+            (_, 1) => StorageFormat::R32Float,
+            (_, 2) => StorageFormat::Rg32Float,
+            (_, _) => StorageFormat::Rgba32Float,
+        }
+    }
+}
+
+/// Return the size of the constant buffer called `name`.
+fn constant_buffer_size(rdef: &Reader, name: &str) -> Result<u32, Error> {
+    let count = rdef.u32(0)? as usize;
+    let offset = rdef.u32(4)? as usize;
+    for index in 0..count {
+        // Each `D3D11_SHADER_BUFFER_DESC` is six words.
+        let desc = offset + index * 24;
+        if rdef.str(rdef.u32(desc)? as usize)? == name {
+            return rdef.u32(desc + 12);
+        }
+    }
+    Ok(0)
+}
+
+/// Find the `dcl_thread_group` declaration in a `SHDR` or `SHEX` chunk.
+fn thread_group_size(shader: &Reader) -> Result<[u32; 3], Error> {
+    const OPCODE_CUSTOMDATA: u32 = 35;
+    const OPCODE_DCL_THREAD_GROUP: u32 = 155;
+
+    // Skip the version and length tokens.
+    let mut offset = 8;
+    while offset < shader.data.len() {
+        let token = shader.u32(offset)?;
+        let opcode = token & 0x7ff;
+        let len = match opcode {
+            OPCODE_CUSTOMDATA => shader.u32(offset + 4)?,
+            _ => (token >> 24) & 0x7f,
+        } as usize;
+        if len == 0 {
+            return Err(Error::InvalidInstruction(shader.base + offset));
+        }
+        if opcode == OPCODE_DCL_THREAD_GROUP {
+            return Ok([
+                shader.u32(offset + 4)?,
+                shader.u32(offset + 8)?,
+                shader.u32(offset + 12)?,
+            ]);
+        }
+        offset += len * 4;
+    }
+    Ok([0; 3])
+}
+
+fn insert_type(module: &mut Module, inner: TypeInner) -> Handle<Type> {
+    module
+        .types
+        .insert(Type { name: None, inner }, Span::UNDEFINED)
+}
+
+fn scalar_type(module: &mut Module, kind: ScalarKind) -> Handle<Type> {
+    insert_type(module, TypeInner::Scalar { kind, width: 4 })
+}
+
+fn vec4_type(module: &mut Module) -> Handle<Type> {
+    insert_type(
+        module,
+        TypeInner::Vector {
+            size: crate::VectorSize::Quad,
+            kind: ScalarKind::Float,
+            width: 4,
+        },
+    )
+}
+
+fn binding_type(module: &Module, space: AddressSpace, base: Handle<Type>) -> BindingType {
+    let mut layouter = crate::proc::Layouter::default();
+    // Every type above has a layout.
+    layouter.update(module.to_ctx()).unwrap();
+    crate::valid::declared_binding_type(module, &layouter, space, base)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Error;
+    use crate::valid::{BindingType, GlobalUse};
+
+    /// A binding for [`container`]: name, input type, return type,
+    /// dimension, register, count and flags.
+    type Binding = (&'static str, u32, u32, u32, u32, u32, u32);
+
+    /// Build a shader model 5.0 container with the given bindings and
+    /// constant buffer sizes, and optionally a compute shader chunk that
+    /// declares `thread_group`.
+    fn container(
+        program_type: u16,
+        bindings: &[Binding],
+        cbuffers: &[(&str, u32)],
+        thread_group: Option<[u32; 3]>,
+    ) -> Vec<u8> {
+        let mut rdef = Vec::new();
+        let push = |bytes: &mut Vec<u8>, word: u32| bytes.extend_from_slice(&word.to_le_bytes());
+
+        let header = 28;
+        let cbuffer_offset = header;
+        let binding_offset = cbuffer_offset + cbuffers.len() * 24;
+        let mut strings = binding_offset + bindings.len() * 32;
+        let mut names = Vec::new();
+        let mut name_offset = |name: &str, names: &mut Vec<u8>| {
+            let offset = strings;
+            names.extend_from_slice(name.as_bytes());
+            names.push(0);
+            strings += name.len() + 1;
+            offset as u32
+        };
+
+        push(&mut rdef, cbuffers.len() as u32);
+        push(&mut rdef, cbuffer_offset as u32);
+        push(&mut rdef, bindings.len() as u32);
+        push(&mut rdef, binding_offset as u32);
+        rdef.extend_from_slice(&[0, 5]);
+        rdef.extend_from_slice(&program_type.to_le_bytes());
+        push(&mut rdef, 0);
+        push(&mut rdef, 0);
+        for &(name, size) in cbuffers {
+            let offset = name_offset(name, &mut names);
+            for word in [offset, 0, 0, size, 0, 0] {
+                push(&mut rdef, word);
+            }
+        }
+        for &(name, input_type, return_type, dimension, register, count, flags) in bindings {
+            let offset = name_offset(name, &mut names);
+            for word in [
+                offset,
+                input_type,
+                return_type,
+                dimension,
+                0,
+                register,
+                count,
+                flags,
+            ] {
+                push(&mut rdef, word);
+            }
+        }
+        rdef.extend(names);
+
+        let mut chunks = vec![(*b"RDEF", rdef)];
+        if let Some([x, y, z]) = thread_group {
+            let mut shex = Vec::new();
+            let dcl_temps = 104 | (2 << 24);
+            let dcl_thread_group = 155 | (4 << 24);
+            let ret = 62 | (1 << 24);
+            for word in [0x50050, 9, dcl_temps, 1, dcl_thread_group, x, y, z, ret] {
+                push(&mut shex, word);
+            }
+            chunks.push((*b"SHEX", shex));
+        }
+
+        let mut data = b"DXBC".to_vec();
+        data.extend_from_slice(&[0; 16]);
+        push(&mut data, 1);
+        push(&mut data, 0);
+        push(&mut data, chunks.len() as u32);
+        let mut offset = data.len() + chunks.len() * 4;
+        for (_, chunk) in chunks.iter() {
+            push(&mut data, offset as u32);
+            offset += 8 + chunk.len();
+        }
+        for (tag, chunk) in chunks {
+            data.extend_from_slice(&tag);
+            push(&mut data, chunk.len() as u32);
+            data.extend(chunk);
+        }
+        let len = data.len() as u32;
+        data[24..28].copy_from_slice(&len.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn resources() {
+        let data = container(
+            0x4353,
+            &[
+                ("Globals", 0, 0, 0, 0, 1, 0),
+                ("tex", 2, 5, 4, 1, 1, 0xc),
+                ("shadow", 3, 0, 0, 2, 1, 0x2),
+                ("output", 4, 5, 4, 0, 1, 0xc),
+                ("data", 8, 6, 1, 1, 4, 0),
+            ],
+            &[("Globals", 48)],
+            Some([8, 8, 1]),
+        );
+        let (module, reflection) = super::parse_reflection(&data).unwrap();
+
+        let bindings: Vec<_> = reflection
+            .resources
+            .iter()
+            .map(|r| (r.name.as_deref(), r.binding.binding, r.ty, r.count))
+            .collect();
+        assert_eq!(
+            bindings,
+            [
+                (
+                    Some("Globals"),
+                    0,
+                    BindingType::UniformBuffer { min_size: Some(48) },
+                    None
+                ),
+                (
+                    Some("tex"),
+                    1,
+                    BindingType::Image {
+                        dim: crate::ImageDimension::D2,
+                        arrayed: false,
+                        class: crate::ImageClass::Sampled {
+                            kind: crate::ScalarKind::Float,
+                            multi: false
+                        }
+                    },
+                    None
+                ),
+                (
+                    Some("shadow"),
+                    2,
+                    BindingType::Sampler { comparison: true },
+                    None
+                ),
+                (
+                    Some("output"),
+                    0,
+                    BindingType::Image {
+                        dim: crate::ImageDimension::D2,
+                        arrayed: false,
+                        class: crate::ImageClass::Storage {
+                            format: crate::StorageFormat::Rgba32Float,
+                            access: crate::StorageAccess::LOAD | crate::StorageAccess::STORE,
+                        }
+                    },
+                    None
+                ),
+                (
+                    Some("data"),
+                    1,
+                    BindingType::StorageBuffer {
+                        access: crate::StorageAccess::LOAD | crate::StorageAccess::STORE,
+                        min_size: Some(4)
+                    },
+                    Some(crate::ArraySize::Constant(
+                        std::num::NonZeroU32::new(4).unwrap()
+                    ))
+                ),
+            ]
+        );
+        assert_eq!(module.global_variables.len(), 5);
+        let data_var = &module.global_variables[reflection.resources[4].global];
+        assert_eq!(data_var.name.as_deref(), Some("data"));
+
+        let ep = &reflection.entry_points[0];
+        assert_eq!(ep.stage, crate::ShaderStage::Compute);
+        assert_eq!(ep.workgroup_size, [8, 8, 1]);
+        assert_eq!(ep.resources.len(), 5);
+        assert_eq!(ep.resources[0].usage, GlobalUse::READ);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            super::parse_reflection(b"DXIL").unwrap_err(),
+            Error::InvalidHeader
+        );
+
+        let mut data = container(0xFFFF, &[], &[], None);
+        data[36..40].copy_from_slice(b"STAT");
+        assert_eq!(
+            super::parse_reflection(&data).unwrap_err(),
+            Error::MissingReflection
+        );
+
+        let data = container(0x4753, &[], &[], None);
+        assert_eq!(
+            super::parse_reflection(&data).unwrap_err(),
+            Error::UnsupportedStage(0x4753)
+        );
+
+        // A typed `Buffer<float4>`.
+        let data = container(0xFFFF, &[("texels", 2, 5, 1, 0, 1, 0xc)], &[], None);
+        assert_eq!(
+            super::parse_reflection(&data).unwrap_err(),
+            Error::UnsupportedResource {
+                name: "texels".to_string(),
+                input_type: 2,
+                dimension: 1,
+            }
+        );
+
+        let data = container(0xFFFF, &[], &[], None);
+        assert!(matches!(
+            super::parse_reflection(&data[..40]).unwrap_err(),
+            Error::UnexpectedEnd(_)
+        ));
+    }
+}
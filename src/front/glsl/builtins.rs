@@ -1039,9 +1039,24 @@ fn inject_standard_builtins(
                     .push(module.add_builtin(args, MacroCall::Clamp(size)))
             }
         }
-        "barrier" => declaration
-            .overloads
-            .push(module.add_builtin(Vec::new(), MacroCall::Barrier)),
+        "barrier"
+        | "memoryBarrier"
+        | "groupMemoryBarrier"
+        | "memoryBarrierShared"
+        | "memoryBarrierBuffer"
+        | "memoryBarrierImage" => {
+            // Naga's barriers are always control barriers as well, so the
+            // memory barriers are lowered to a barrier for the memory they
+            // order, which is stronger than needed.
+            let barrier = match name {
+                "memoryBarrierShared" => crate::Barrier::WORK_GROUP,
+                "memoryBarrierBuffer" | "memoryBarrierImage" => crate::Barrier::STORAGE,
+                _ => crate::Barrier::all(),
+            };
+            let mut overload = module.add_builtin(Vec::new(), MacroCall::Barrier(barrier));
+            overload.void = true;
+            declaration.overloads.push(overload)
+        }
         // Add common builtins with floats
         _ => inject_common_builtin(declaration, module, name, 4),
     }
@@ -1614,7 +1629,7 @@ pub enum MacroCall {
     Clamp(Option<VectorSize>),
     BitCast(Sk),
     Derivate(Axis, Ctrl),
-    Barrier,
+    Barrier(crate::Barrier),
     /// SmoothStep needs a separate variant because it might need it's inputs
     /// to be splatted depending on the overload
     SmoothStep {
@@ -2063,10 +2078,15 @@ impl MacroCall {
                 },
                 Span::default(),
             )?,
-            MacroCall::Barrier => {
+            MacroCall::Barrier(barrier) => {
                 ctx.emit_restart();
-                ctx.body
-                    .push(crate::Statement::Barrier(crate::Barrier::all()), meta);
+                // A memory barrier is usually followed by `barrier()`, so
+                // adjacent barriers become one, rather than synchronizing
+                // the invocations twice.
+                match ctx.body.last_mut() {
+                    Some(&mut crate::Statement::Barrier(ref mut previous)) => *previous |= barrier,
+                    _ => ctx.body.push(crate::Statement::Barrier(barrier), meta),
+                }
                 return Ok(None);
             }
             MacroCall::SmoothStep { splatted } => {
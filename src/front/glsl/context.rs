@@ -275,11 +275,14 @@ impl<'a> Context<'a> {
                     if needs_pre_emit {
                         self.body.extend(self.emitter.finish(&self.expressions));
                     }
-                    let h = self.expressions.append(expr, meta);
+                    let h = self.expressions.try_append(expr, meta).map_err(|e| Error {
+                        kind: ErrorKind::ArenaFull(e),
+                        meta,
+                    });
                     if needs_pre_emit {
                         self.emitter.start(&self.expressions);
                     }
-                    Ok(h)
+                    h
                 }
             }
         }
@@ -106,9 +106,18 @@ pub enum ErrorKind {
     /// A semantic error was detected in the shader.
     #[error("{0}")]
     SemanticError(Cow<'static, str>),
+    /// An `#include` directive couldn't be expanded.
+    ///
+    /// `reason` is the error the [`IncludeResolver`](super::IncludeResolver)
+    /// returned, or says that the file includes itself.
+    #[error("Couldn't include {path:?}: {reason}")]
+    IncludeFailed { path: String, reason: String },
     /// An error was returned by the preprocessor.
     #[error("{0:?}")]
     PreprocessorError(PreprocessorError),
+    /// The shader needs more items of some kind than an arena can hold.
+    #[error("{0}")]
+    ArenaFull(crate::ArenaFull),
     /// The parser entered an illegal state and exited
     ///
     /// This obviously is a bug and as such should be reported in the github issue tracker
@@ -0,0 +1,172 @@
+/*!
+Expanding `#include` directives.
+
+The preprocessor doesn't know about includes, so the frontend replaces each
+`#include "path"` or `#include <path>` line with the text its
+[`IncludeResolver`] returns before preprocessing starts. The result is one
+string, so [`Expanded`] keeps track of which file each part of it came from,
+to turn spans in the expanded text back into spans in the right file.
+*/
+
+use super::{
+    error::{Error, ErrorKind},
+    Include, IncludeResolver,
+};
+use crate::{SourceId, Span};
+use std::ops::Range;
+
+/// A shader with its includes expanded.
+pub struct Expanded {
+    pub text: String,
+    /// The parts of `text`, in order, each copied from one file.
+    segments: Vec<Segment>,
+}
+
+#[derive(Clone, Copy)]
+struct Segment {
+    /// Where the segment starts in the expanded text.
+    start: usize,
+    /// The file the segment was copied from: 0 for the shader itself, or
+    /// one more than the file's index in the include list.
+    file: u32,
+    /// Where the segment starts in that file.
+    offset: usize,
+}
+
+impl Expanded {
+    /// Turn `span`, a span in the expanded text, into a span in the file it
+    /// came from.
+    ///
+    /// Spans that reach past the end of their segment are cut short there.
+    pub fn map(&self, span: Span) -> Span {
+        let Some(range) = span.to_range() else {
+            return span;
+        };
+        let index = self
+            .segments
+            .partition_point(|segment| segment.start <= range.start)
+            .saturating_sub(1);
+        let Some(&segment) = self.segments.get(index) else {
+            return span;
+        };
+        let end = match self.segments.get(index + 1) {
+            Some(next) => range.end.min(next.start),
+            None => range.end,
+        };
+        let start = range.start - segment.start + segment.offset;
+        let end = end - segment.start + segment.offset;
+        Span::new(start as u32, end as u32).with_source(SourceId::new(segment.file))
+    }
+}
+
+/// Expand the `#include` directives in `source`, recording each included
+/// file in `includes`.
+pub fn expand(
+    source: &str,
+    resolver: &dyn IncludeResolver,
+    includes: &mut Vec<Include>,
+) -> Result<Expanded, Error> {
+    let mut expander = Expander {
+        resolver,
+        includes,
+        text: String::with_capacity(source.len()),
+        segments: Vec::new(),
+        stack: Vec::new(),
+    };
+    expander.file(source, 0)?;
+    Ok(Expanded {
+        text: expander.text,
+        segments: expander.segments,
+    })
+}
+
+struct Expander<'a> {
+    resolver: &'a dyn IncludeResolver,
+    includes: &'a mut Vec<Include>,
+    text: String,
+    segments: Vec<Segment>,
+    /// The included files being expanded, innermost last.
+    stack: Vec<u32>,
+}
+
+impl Expander<'_> {
+    fn file(&mut self, source: &str, file: u32) -> Result<(), Error> {
+        let mut copied = 0;
+        let mut line_start = 0;
+        for line in source.split_inclusive('\n') {
+            if let Some((path, directive)) = parse_include(line) {
+                let directive = line_start + directive.start..line_start + directive.end;
+                self.copy(source, file, copied..directive.start);
+                let meta = Span::new(directive.start as u32, directive.end as u32)
+                    .with_source(SourceId::new(file));
+                self.include(path, meta, file)?;
+                copied = directive.end;
+            }
+            line_start += line.len();
+        }
+        self.copy(source, file, copied..source.len());
+        Ok(())
+    }
+
+    fn copy(&mut self, source: &str, file: u32, range: Range<usize>) {
+        if range.is_empty() {
+            return;
+        }
+        self.segments.push(Segment {
+            start: self.text.len(),
+            file,
+            offset: range.start,
+        });
+        self.text.push_str(&source[range]);
+    }
+
+    fn include(&mut self, path: &str, meta: Span, includer: u32) -> Result<(), Error> {
+        let error = |reason: String| Error {
+            kind: ErrorKind::IncludeFailed {
+                path: path.to_string(),
+                reason,
+            },
+            meta,
+        };
+
+        let included = |file: u32| &self.includes[file as usize - 1].path;
+        if self.stack.iter().any(|&file| included(file) == path) {
+            return Err(error("it includes itself".to_string()));
+        }
+        let includer_path = match includer {
+            0 => None,
+            file => Some(included(file).as_str()),
+        };
+        let text = self.resolver.resolve(path, includer_path).map_err(error)?;
+
+        let file = self.includes.len() as u32 + 1;
+        self.includes.push(Include {
+            path: path.to_string(),
+            text: text.clone(),
+            source: SourceId::new(file),
+            meta,
+        });
+        self.stack.push(file);
+        let result = self.file(&text, file);
+        self.stack.pop();
+        result
+    }
+}
+
+/// If `line` is an `#include` directive, return the path it names and the
+/// range of the directive in `line`.
+fn parse_include(line: &str) -> Option<(&str, Range<usize>)> {
+    let trimmed = line.trim_start();
+    let start = line.len() - trimmed.len();
+    let rest = trimmed.strip_prefix('#')?.trim_start();
+    let rest = rest.strip_prefix("include")?.trim_start();
+    let close = match rest.chars().next()? {
+        '"' => '"',
+        '<' => '>',
+        _ => return None,
+    };
+    let body = &rest[1..];
+    let len = body.find(close)?;
+    let end = line.len() - body.len() + len + 1;
+    Some((&body[..len], start..end))
+}
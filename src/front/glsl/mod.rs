@@ -25,6 +25,7 @@ mod builtins;
 mod context;
 mod error;
 mod functions;
+mod include;
 mod lex;
 mod offset;
 mod parser;
@@ -46,7 +47,6 @@ type Result<T> = std::result::Result<T, Error>;
 /// # use naga::front::glsl::Options;
 /// Options::from(ShaderStage::Vertex);
 /// ```
-#[derive(Debug)]
 pub struct Options {
     /// The shader stage in the pipeline.
     pub stage: ShaderStage,
@@ -56,6 +56,24 @@ pub struct Options {
     /// ```
     /// for each key value pair in the map.
     pub defines: FastHashMap<String, String>,
+    /// Where to find the files `#include` directives name.
+    ///
+    /// If this is `None`, `#include` directives are left to the
+    /// preprocessor, which doesn't support them.
+    ///
+    /// The resolver must be `Send` and `Sync`, so that `Options` can be
+    /// shared between threads.
+    pub include_resolver: Option<Box<dyn IncludeResolver + Send + Sync>>,
+}
+
+impl std::fmt::Debug for Options {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Options")
+            .field("stage", &self.stage)
+            .field("defines", &self.defines)
+            .field("include_resolver", &self.include_resolver.is_some())
+            .finish()
+    }
 }
 
 impl From<ShaderStage> for Options {
@@ -63,10 +81,39 @@ impl From<ShaderStage> for Options {
         Options {
             stage,
             defines: FastHashMap::default(),
+            include_resolver: None,
         }
     }
 }
 
+/// Supplies the text of the files a shader includes.
+///
+/// Each `#include "path"` or `#include <path>` line is replaced with the
+/// text [`resolve`](Self::resolve) returns for it, before the rest of the
+/// preprocessing is done. This means that includes inside `#if` blocks that
+/// end up disabled are still resolved, and that a file including itself, even
+/// behind an include guard, is an error.
+///
+/// Closures taking the same arguments as `resolve` implement this trait.
+pub trait IncludeResolver {
+    /// Return the text of the file `path` refers to, or an error message
+    /// saying why it can't be read.
+    ///
+    /// `includer` is the path of the included file holding the directive,
+    /// for resolving relative paths, or `None` if the directive is in the
+    /// shader passed to [`parse`](Frontend::parse).
+    fn resolve(&self, path: &str, includer: Option<&str>) -> std::result::Result<String, String>;
+}
+
+impl<F> IncludeResolver for F
+where
+    F: Fn(&str, Option<&str>) -> std::result::Result<String, String>,
+{
+    fn resolve(&self, path: &str, includer: Option<&str>) -> std::result::Result<String, String> {
+        self(path, includer)
+    }
+}
+
 /// Additional information about the GLSL shader.
 ///
 /// Stores additional information about the GLSL shader which might not be
@@ -86,6 +133,15 @@ pub struct ShaderMetadata {
     /// The workgroup size for compute shaders, defaults to `[1; 3]` for
     /// compute shaders and `[0; 3]` for non compute shaders.
     pub workgroup_size: [u32; 3],
+    /// The specialization constant ids given to each workgroup size dimension
+    /// with the `local_size_{x,y,z}_id` layout qualifiers, if any.
+    ///
+    /// Naga's IR can't yet express workgroup sizes that depend on overrides,
+    /// so the entry point always uses [`workgroup_size`], which holds the
+    /// default value of each dimension just like it does in Vulkan.
+    ///
+    /// [`workgroup_size`]: Self::workgroup_size
+    pub workgroup_size_ids: [Option<u32>; 3],
     /// Whether or not early fragment tests where requested by the shader.
     /// Defaults to `false`.
     pub early_fragment_tests: bool,
@@ -98,6 +154,29 @@ pub struct ShaderMetadata {
     /// This field only stores extensions which were required or requested to
     /// be enabled if possible and they are supported.
     pub extensions: FastHashSet<String>,
+
+    /// Whether the shader asked to be optimized, with
+    /// `#pragma optimize(on)` or `#pragma optimize(off)`. Defaults to `true`.
+    pub optimize: bool,
+    /// Whether the shader asked to be compiled with debug information, with
+    /// `#pragma debug(on)` or `#pragma debug(off)`. Defaults to `false`.
+    pub debug: bool,
+    /// The `#pragma` and `#extension` directives that the frontend doesn't
+    /// act on itself, in the order they appear in the shader.
+    ///
+    /// This includes every `#extension` directive, since the frontend doesn't
+    /// check for extension support, and every `#pragma` other than the
+    /// `optimize` and `debug` ones above.
+    pub unhandled_directives: Vec<UnhandledDirective>,
+    /// The files the shader included, in the order their `#include`
+    /// directives were expanded.
+    ///
+    /// Spans in the resulting module and errors refer to these files by
+    /// their [`source`](Include::source) ids, while spans in the shader
+    /// itself keep [`SourceId::PRIMARY`](crate::SourceId::PRIMARY). Adding
+    /// the shader and then each of these files, in order, to a
+    /// [`SourceMap`](crate::SourceMap) gives them matching ids.
+    pub includes: Vec<Include>,
 }
 
 impl ShaderMetadata {
@@ -106,8 +185,13 @@ impl ShaderMetadata {
         self.profile = Profile::Core;
         self.stage = stage;
         self.workgroup_size = [u32::from(stage == ShaderStage::Compute); 3];
+        self.workgroup_size_ids = [None; 3];
         self.early_fragment_tests = false;
         self.extensions.clear();
+        self.optimize = true;
+        self.debug = false;
+        self.unhandled_directives.clear();
+        self.includes.clear();
     }
 }
 
@@ -118,12 +202,53 @@ impl Default for ShaderMetadata {
             profile: Profile::Core,
             stage: ShaderStage::Vertex,
             workgroup_size: [0; 3],
+            workgroup_size_ids: [None; 3],
             early_fragment_tests: false,
             extensions: FastHashSet::default(),
+            optimize: true,
+            debug: false,
+            unhandled_directives: Vec::new(),
+            includes: Vec::new(),
         }
     }
 }
 
+/// Which kind of directive an [`UnhandledDirective`] is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnhandledDirectiveKind {
+    Pragma,
+    Extension,
+}
+
+/// A preprocessor directive that the frontend passes on to the caller.
+///
+/// See [`ShaderMetadata::unhandled_directives`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnhandledDirective {
+    pub kind: UnhandledDirectiveKind,
+    /// The source text of the directive after its name, like `optimize(off)`
+    /// for `#pragma optimize(off)`, or `GL_EXT_foo : enable` for
+    /// `#extension GL_EXT_foo : enable`.
+    pub text: String,
+    /// The location of [`text`](Self::text) in the source.
+    pub meta: Span,
+}
+
+/// A file pulled into the shader by an `#include` directive.
+///
+/// See [`ShaderMetadata::includes`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Include {
+    /// The path named by the directive.
+    pub path: String,
+    /// The text the [`IncludeResolver`] returned for [`path`](Self::path).
+    pub text: String,
+    /// The id that spans in this file carry.
+    pub source: crate::SourceId,
+    /// The location of the directive, in the file that included this one.
+    pub meta: Span,
+}
+
 /// The `Frontend` is the central structure of the GLSL frontend.
 ///
 /// To instantiate a new `Frontend` the [`Default`] trait is used, so a
@@ -199,8 +324,41 @@ impl Frontend {
     ) -> std::result::Result<Module, Vec<Error>> {
         self.reset(options.stage);
 
+        let Some(ref resolver) = options.include_resolver else {
+            return self.parse_expanded(options, source);
+        };
+        let expanded =
+            include::expand(source, &**resolver, &mut self.meta.includes).map_err(|e| vec![e])?;
+        let mut result = self.parse_expanded(options, &expanded.text);
+        if !self.meta.includes.is_empty() {
+            match result {
+                #[cfg(feature = "span")]
+                Ok(ref mut module) => crate::span::for_each_module_span(module, &mut |span| {
+                    *span = expanded.map(*span)
+                }),
+                #[cfg(not(feature = "span"))]
+                Ok(_) => {}
+                Err(ref mut errors) => {
+                    for error in errors.iter_mut() {
+                        error.meta = expanded.map(error.meta);
+                    }
+                }
+            }
+            for directive in self.meta.unhandled_directives.iter_mut() {
+                directive.meta = expanded.map(directive.meta);
+            }
+        }
+        result
+    }
+
+    /// Parse `source`, whose includes have already been expanded.
+    fn parse_expanded(
+        &mut self,
+        options: &Options,
+        source: &str,
+    ) -> std::result::Result<Module, Vec<Error>> {
         let lexer = lex::Lexer::new(source, &options.defines);
-        let mut ctx = ParsingContext::new(lexer);
+        let mut ctx = ParsingContext::new(lexer, source);
 
         match ctx.parse(self) {
             Ok(module) => {
@@ -217,6 +375,45 @@ impl Frontend {
         }
     }
 
+    /// Like [`parse`](Self::parse), but make the spans of the resulting
+    /// module, or of the errors, refer to the file `id`.
+    ///
+    /// This is for modules whose errors are reported with a
+    /// [`SourceMap`](crate::SourceMap) holding several files. Files the
+    /// shader includes get the ids following `id`, in the order of
+    /// [`ShaderMetadata::includes`].
+    pub fn parse_with_source_id(
+        &mut self,
+        options: &Options,
+        source: &str,
+        id: crate::SourceId,
+    ) -> std::result::Result<Module, Vec<Error>> {
+        let relabel =
+            |span: Span| span.with_source(crate::SourceId::new(id.index() + span.source().index()));
+        let mut result = self.parse(options, source);
+        match result {
+            #[cfg(feature = "span")]
+            Ok(ref mut module) => {
+                crate::span::for_each_module_span(module, &mut |span| *span = relabel(*span))
+            }
+            #[cfg(not(feature = "span"))]
+            Ok(_) => {}
+            Err(ref mut errors) => {
+                for error in errors.iter_mut() {
+                    error.meta = relabel(error.meta);
+                }
+            }
+        }
+        for include in self.meta.includes.iter_mut() {
+            include.source = crate::SourceId::new(id.index() + include.source.index());
+            include.meta = relabel(include.meta);
+        }
+        for directive in self.meta.unhandled_directives.iter_mut() {
+            directive.meta = relabel(directive.meta);
+        }
+        result
+    }
+
     /// Returns additional information about the parsed shader which might not
     /// be stored in the [`Module`], see the documentation for
     /// [`ShaderMetadata`] for more information about the returned data.
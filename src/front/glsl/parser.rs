@@ -7,7 +7,7 @@ use super::{
     token::{Directive, DirectiveKind},
     token::{Token, TokenValue},
     variables::{GlobalOrConstant, VarDeclaration},
-    Frontend, Result,
+    Frontend, Result, UnhandledDirective, UnhandledDirectiveKind,
 };
 use crate::{arena::Handle, proc::U32EvalError, Expression, Module, Span, Type};
 use pp_rs::token::{PreprocessorError, Token as PPToken, TokenValue as PPTokenValue};
@@ -20,15 +20,18 @@ mod types;
 
 pub struct ParsingContext<'source> {
     lexer: Peekable<Lexer<'source>>,
+    /// The text `lexer` is reading, for directives passed on to the caller
+    source: &'source str,
     /// Used to store tokens already consumed by the parser but that need to be backtracked
     backtracked_token: Option<Token>,
     last_meta: Span,
 }
 
 impl<'source> ParsingContext<'source> {
-    pub fn new(lexer: Lexer<'source>) -> Self {
+    pub fn new(lexer: Lexer<'source>, source: &'source str) -> Self {
         ParsingContext {
             lexer: lexer.peekable(),
+            source,
             backtracked_token: None,
             last_meta: Span::default(),
         }
@@ -95,7 +98,7 @@ impl<'source> ParsingContext<'source> {
                     break Some(token);
                 }
                 LexerResultKind::Directive(directive) => {
-                    frontend.handle_directive(directive, res.meta)
+                    frontend.handle_directive(directive, res.meta, self.source)
                 }
                 LexerResultKind::Error(error) => frontend.errors.push(Error {
                     kind: ErrorKind::PreprocessorError(error),
@@ -141,7 +144,7 @@ impl<'source> ParsingContext<'source> {
 
                     match res.kind {
                         LexerResultKind::Directive(directive) => {
-                            frontend.handle_directive(directive, res.meta)
+                            frontend.handle_directive(directive, res.meta, self.source)
                         }
                         LexerResultKind::Error(error) => frontend.errors.push(Error {
                             kind: ErrorKind::PreprocessorError(error),
@@ -231,7 +234,36 @@ impl<'source> ParsingContext<'source> {
 }
 
 impl Frontend {
-    fn handle_directive(&mut self, directive: Directive, meta: Span) {
+    fn handle_directive(&mut self, directive: Directive, meta: Span, source: &str) {
+        if matches!(directive.kind, DirectiveKind::Pragma)
+            && self.handle_known_pragma(&directive.tokens)
+        {
+            return;
+        }
+        if matches!(
+            directive.kind,
+            DirectiveKind::Pragma | DirectiveKind::Extension
+        ) {
+            let (text, meta) = match (directive.tokens.first(), directive.tokens.last()) {
+                (Some(first), Some(last)) => {
+                    let range = first.location.start as usize..last.location.end as usize;
+                    (
+                        source.get(range).unwrap_or_default().to_string(),
+                        Span::new(first.location.start, last.location.end),
+                    )
+                }
+                _ => (String::new(), meta),
+            };
+            self.meta.unhandled_directives.push(UnhandledDirective {
+                kind: match directive.kind {
+                    DirectiveKind::Pragma => UnhandledDirectiveKind::Pragma,
+                    _ => UnhandledDirectiveKind::Extension,
+                },
+                text,
+                meta,
+            });
+        }
+
         let mut tokens = directive.tokens.into_iter();
 
         match directive.kind {
@@ -384,10 +416,43 @@ impl Frontend {
                     })
                 }
             }
-            DirectiveKind::Pragma => {
-                // TODO: handle some common pragmas?
-            }
+            // Passed on to the caller above.
+            DirectiveKind::Pragma => {}
+        }
+    }
+
+    /// Record the standard `optimize` and `debug` pragmas in the shader's
+    /// metadata, returning `false` for any other pragma.
+    fn handle_known_pragma(&mut self, tokens: &[PPToken]) -> bool {
+        use pp_rs::token::Punct;
+
+        let (name, value) = match *tokens {
+            [PPToken {
+                value: PPTokenValue::Ident(ref name),
+                ..
+            }, PPToken {
+                value: PPTokenValue::Punct(Punct::LeftParen),
+                ..
+            }, PPToken {
+                value: PPTokenValue::Ident(ref value),
+                ..
+            }, PPToken {
+                value: PPTokenValue::Punct(Punct::RightParen),
+                ..
+            }] => (name.as_str(), value.as_str()),
+            _ => return false,
+        };
+        let value = match value {
+            "on" => true,
+            "off" => false,
+            _ => return false,
+        };
+        match name {
+            "optimize" => self.meta.optimize = value,
+            "debug" => self.meta.debug = value,
+            _ => return false,
         }
+        true
     }
 }
 
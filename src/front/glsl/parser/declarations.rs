@@ -450,20 +450,26 @@ impl<'source> ParsingContext<'source> {
                         }
                     }
                     TokenValue::Semicolon => {
-                        if let Some(value) =
-                            qualifiers.uint_layout_qualifier("local_size_x", &mut frontend.errors)
+                        for (i, (size, id)) in [
+                            ("local_size_x", "local_size_x_id"),
+                            ("local_size_y", "local_size_y_id"),
+                            ("local_size_z", "local_size_z_id"),
+                        ]
+                        .into_iter()
+                        .enumerate()
                         {
-                            frontend.meta.workgroup_size[0] = value;
-                        }
-                        if let Some(value) =
-                            qualifiers.uint_layout_qualifier("local_size_y", &mut frontend.errors)
-                        {
-                            frontend.meta.workgroup_size[1] = value;
-                        }
-                        if let Some(value) =
-                            qualifiers.uint_layout_qualifier("local_size_z", &mut frontend.errors)
-                        {
-                            frontend.meta.workgroup_size[2] = value;
+                            if let Some(value) =
+                                qualifiers.uint_layout_qualifier(size, &mut frontend.errors)
+                            {
+                                frontend.meta.workgroup_size[i] = value;
+                            }
+                            // The size itself, if given, is the default value
+                            // used when the constant isn't specialized.
+                            if let Some(value) =
+                                qualifiers.uint_layout_qualifier(id, &mut frontend.errors)
+                            {
+                                frontend.meta.workgroup_size_ids[i] = Some(value);
+                            }
                         }
 
                         frontend.meta.early_fragment_tests |= qualifiers
@@ -35,15 +35,30 @@ impl<'source> ParsingContext<'source> {
                     Literal::U32(int.value as u32)
                 }
             }
-            TokenValue::FloatConstant(float) => {
-                if float.width != 32 {
+            TokenValue::FloatConstant(float) => match float.width {
+                32 => Literal::F32(float.value),
+                // The preprocessor only keeps single precision, even for
+                // literals with the `lf` suffix, so read the digits again.
+                64 => {
+                    let value = token
+                        .meta
+                        .to_range()
+                        .and_then(|range| self.source.get(range))
+                        .map(|text| text.trim_end_matches(|c| matches!(c, 'l' | 'L' | 'f' | 'F')))
+                        .and_then(|digits| digits.parse::<f64>().ok())
+                        // Tokens from macros may not be where their text is.
+                        .filter(|&value| value as f32 == float.value)
+                        .unwrap_or(float.value.into());
+                    Literal::F64(value)
+                }
+                _ => {
                     frontend.errors.push(Error {
-                        kind: ErrorKind::SemanticError("Unsupported floating-point value (expected single-precision floating-point number)".into()),
+                        kind: ErrorKind::SemanticError("Unsupported floating-point value (expected single- or double-precision floating-point number)".into()),
                         meta: token.meta,
                     });
+                    Literal::F32(float.value)
                 }
-                Literal::F32(float.value)
-            }
+            },
             TokenValue::BoolConstant(value) => Literal::Bool(value),
             TokenValue::LeftParen => {
                 let expr = self.parse_expression(frontend, ctx, stmt)?;
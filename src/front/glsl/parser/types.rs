@@ -53,17 +53,24 @@ impl<'source> ParsingContext<'source> {
 
             frontend.layouter.update(ctx.module.to_ctx()).unwrap();
             let stride = frontend.layouter[*ty].to_stride();
-            *ty = ctx.module.types.insert(
-                Type {
-                    name: None,
-                    inner: TypeInner::Array {
-                        base: *ty,
-                        size,
-                        stride,
+            *ty = ctx
+                .module
+                .types
+                .try_insert(
+                    Type {
+                        name: None,
+                        inner: TypeInner::Array {
+                            base: *ty,
+                            size,
+                            stride,
+                        },
                     },
-                },
-                *span,
-            );
+                    *span,
+                )
+                .map_err(|e| Error {
+                    kind: ErrorKind::ArenaFull(e),
+                    meta: *span,
+                })?;
 
             Ok(true)
         } else {
@@ -79,7 +86,15 @@ impl<'source> ParsingContext<'source> {
         let token = self.bump(frontend)?;
         let mut handle = match token.value {
             TokenValue::Void => return Ok((None, token.meta)),
-            TokenValue::TypeName(ty) => ctx.module.types.insert(ty, token.meta),
+            TokenValue::TypeName(ty) => {
+                ctx.module
+                    .types
+                    .try_insert(ty, token.meta)
+                    .map_err(|e| Error {
+                        kind: ErrorKind::ArenaFull(e),
+                        meta: token.meta,
+                    })?
+            }
             TokenValue::Struct => {
                 let mut meta = token.meta;
                 let ty_name = self.expect_ident(frontend)?.0;
@@ -93,13 +108,20 @@ impl<'source> ParsingContext<'source> {
                 )?;
                 let end_meta = self.expect(frontend, TokenValue::RightBrace)?.meta;
                 meta.subsume(end_meta);
-                let ty = ctx.module.types.insert(
-                    Type {
-                        name: Some(ty_name.clone()),
-                        inner: TypeInner::Struct { members, span },
-                    },
-                    meta,
-                );
+                let ty = ctx
+                    .module
+                    .types
+                    .try_insert(
+                        Type {
+                            name: Some(ty_name.clone()),
+                            inner: TypeInner::Struct { members, span },
+                        },
+                        meta,
+                    )
+                    .map_err(|e| Error {
+                        kind: ErrorKind::ArenaFull(e),
+                        meta,
+                    })?;
                 frontend.lookup_type.insert(ty_name, ty);
                 ty
             }
@@ -228,7 +250,7 @@ impl<'source> ParsingContext<'source> {
                         }
                         TokenValue::Buffer => {
                             StorageQualifier::AddressSpace(AddressSpace::Storage {
-                                access: crate::StorageAccess::all(),
+                                access: crate::StorageAccess::LOAD | crate::StorageAccess::STORE,
                             })
                         }
                         _ => unreachable!(),
@@ -274,10 +296,11 @@ impl<'source> ParsingContext<'source> {
                     qualifiers.precision = Some((p, token.meta));
                 }
                 TokenValue::MemoryQualifier(access) => {
+                    let all = crate::StorageAccess::LOAD | crate::StorageAccess::STORE;
                     let storage_access = qualifiers
                         .storage_access
-                        .get_or_insert((crate::StorageAccess::all(), Span::default()));
-                    if !storage_access.0.contains(!access) {
+                        .get_or_insert((all, Span::default()));
+                    if !storage_access.0.contains(all & !access) {
                         frontend.errors.push(Error {
                             kind: ErrorKind::SemanticError(
                                 "The same memory qualifier can only be used once".into(),
@@ -3,9 +3,9 @@ use super::{
     error::ExpectedToken,
     error::{Error, ErrorKind},
     token::TokenValue,
-    Frontend, Options, Span,
+    Frontend, Options, Span, UnhandledDirective, UnhandledDirectiveKind,
 };
-use crate::ShaderStage;
+use crate::{ShaderStage, SourceId};
 use pp_rs::token::PreprocessorError;
 
 #[test]
@@ -113,6 +113,52 @@ fn version() {
     );
 }
 
+#[test]
+fn directives() {
+    let mut frontend = Frontend::default();
+
+    frontend
+        .parse(
+            &Options::from(ShaderStage::Vertex),
+            "#version 450\n\
+             #pragma optimize(off)\n\
+             #pragma vendor_hint(fast, 2)\n\
+             #extension GL_EXT_foo : enable\n\
+             void main() {}",
+        )
+        .unwrap();
+    let metadata = frontend.metadata();
+    assert!(!metadata.optimize);
+    assert!(!metadata.debug);
+    assert_eq!(
+        metadata.unhandled_directives,
+        vec![
+            UnhandledDirective {
+                kind: UnhandledDirectiveKind::Pragma,
+                text: "vendor_hint(fast, 2)".into(),
+                meta: Span::new(43, 63),
+            },
+            UnhandledDirective {
+                kind: UnhandledDirectiveKind::Extension,
+                text: "GL_EXT_foo : enable".into(),
+                meta: Span::new(75, 94),
+            },
+        ]
+    );
+
+    // Reusing the frontend starts over.
+    frontend
+        .parse(
+            &Options::from(ShaderStage::Vertex),
+            "#version 450\n#pragma debug(on)\nvoid main() {}",
+        )
+        .unwrap();
+    let metadata = frontend.metadata();
+    assert!(metadata.optimize);
+    assert!(metadata.debug);
+    assert!(metadata.unhandled_directives.is_empty());
+}
+
 #[test]
 fn control_flow() {
     let mut frontend = Frontend::default();
@@ -322,6 +368,127 @@ fn declarations() {
         .unwrap();
 }
 
+#[test]
+fn workgroup_size_ids() {
+    let mut frontend = Frontend::default();
+
+    let module = frontend
+        .parse(
+            &Options::from(ShaderStage::Compute),
+            r#"
+        #version 450
+        layout(local_size_x = 64, local_size_y_id = 1, local_size_z = 2, local_size_z_id = 2) in;
+
+        void main() {}
+        "#,
+        )
+        .unwrap();
+
+    assert_eq!(frontend.metadata().workgroup_size, [64, 1, 2]);
+    assert_eq!(
+        frontend.metadata().workgroup_size_ids,
+        [None, Some(1), Some(2)]
+    );
+    assert_eq!(module.entry_points[0].workgroup_size, [64, 1, 2]);
+}
+
+#[test]
+fn compute() {
+    let mut frontend = Frontend::default();
+
+    let module = frontend
+        .parse(
+            &Options::from(ShaderStage::Compute),
+            r#"
+        #version 450
+        layout(local_size_x = 16, local_size_y = 16) in;
+
+        layout(rgba8, binding = 0) readonly uniform image2D src;
+        layout(rgba8, binding = 1) writeonly uniform image2D dst;
+
+        shared vec4 tile[256];
+
+        void main() {
+            ivec2 pos = ivec2(gl_GlobalInvocationID.xy);
+            tile[gl_LocalInvocationIndex] = imageLoad(src, pos);
+            memoryBarrierShared();
+            barrier();
+            imageStore(dst, pos, tile[255u - gl_LocalInvocationIndex]);
+        }
+        "#,
+        )
+        .unwrap();
+
+    let (_, tile) = module
+        .global_variables
+        .iter()
+        .find(|&(_, var)| var.name.as_deref() == Some("tile"))
+        .unwrap();
+    assert_eq!(tile.space, crate::AddressSpace::WorkGroup);
+
+    let entry_point = &module.entry_points[0];
+    assert_eq!(entry_point.workgroup_size, [16, 16, 1]);
+
+    // `main` itself is a function, which the entry point calls.
+    let (_, main) = module.functions.iter().next().unwrap();
+    let barriers: Vec<_> = main
+        .body
+        .iter()
+        .filter_map(|statement| match *statement {
+            crate::Statement::Barrier(barrier) => Some(barrier),
+            _ => None,
+        })
+        .collect();
+    // `memoryBarrierShared()` and `barrier()` become a single barrier.
+    assert_eq!(barriers, [crate::Barrier::all()]);
+
+    let module = frontend
+        .parse(
+            &Options::from(ShaderStage::Compute),
+            r#"
+            #version 450
+            layout(local_size_x = 1) in;
+            shared uint value;
+            void main() {
+                memoryBarrierShared();
+                value = 1u;
+                memoryBarrierBuffer();
+            }
+            "#,
+        )
+        .unwrap();
+    let (_, main) = module.functions.iter().next().unwrap();
+    let barriers: Vec<_> = main
+        .body
+        .iter()
+        .filter_map(|statement| match *statement {
+            crate::Statement::Barrier(barrier) => Some(barrier),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        barriers,
+        [crate::Barrier::WORK_GROUP, crate::Barrier::STORAGE]
+    );
+
+    let errors = frontend
+        .parse(
+            &Options::from(ShaderStage::Fragment),
+            r#"
+            #version 450
+            shared float value;
+            void main() {}
+            "#,
+        )
+        .err()
+        .unwrap();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].kind,
+        ErrorKind::SemanticError("shared variables are only allowed in compute shaders".into())
+    );
+}
+
 #[test]
 fn textures() {
     let mut frontend = Frontend::default();
@@ -845,3 +1012,152 @@ fn expressions() {
         )
         .unwrap();
 }
+
+#[test]
+fn doubles() {
+    use crate::valid::{Capabilities, ValidationFlags, Validator};
+    use crate::{Expression, Literal, ScalarKind, TypeInner, VectorSize};
+
+    let module = Frontend::default()
+        .parse(
+            &Options::from(ShaderStage::Fragment),
+            r#"
+        #  version 450
+        layout(location = 0) out vec4 o_color;
+
+        dvec4 scale(dvec4 v, double s) {
+            dmat4 m = dmat4(s);
+            return m * v * 0.1lf;
+        }
+
+        void main() {
+            o_color = vec4(scale(dvec4(1.0lf), 2.0lf));
+        }
+        "#,
+        )
+        .unwrap();
+
+    for expected in [
+        TypeInner::Scalar {
+            kind: ScalarKind::Float,
+            width: 8,
+        },
+        TypeInner::Vector {
+            size: VectorSize::Quad,
+            kind: ScalarKind::Float,
+            width: 8,
+        },
+        TypeInner::Matrix {
+            columns: VectorSize::Quad,
+            rows: VectorSize::Quad,
+            width: 8,
+        },
+    ] {
+        assert!(
+            module.types.iter().any(|(_, ty)| ty.inner == expected),
+            "missing {expected:?}"
+        );
+    }
+
+    // The literal keeps all of its digits, not just those of an `f32`.
+    let (_, scale) = module.functions.iter().next().unwrap();
+    assert!(scale
+        .expressions
+        .iter()
+        .chain(module.const_expressions.iter())
+        .any(|(_, expr)| *expr == Expression::Literal(Literal::F64(0.1))));
+
+    assert!(
+        Validator::new(ValidationFlags::all(), Capabilities::empty())
+            .validate(&module)
+            .is_err(),
+        "doubles should require `Capabilities::FLOAT64`"
+    );
+    Validator::new(ValidationFlags::all(), Capabilities::FLOAT64)
+        .validate(&module)
+        .unwrap();
+}
+
+#[test]
+fn includes() {
+    // Options holding a resolver must still be shareable between threads.
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Options>();
+
+    fn options() -> Options {
+        let resolver = |path: &str, _: Option<&str>| match path {
+            "common.glsl" => Ok("#include <constants.glsl>\n\
+                                 float scale(float v) { return v * SCALE; }\n"
+                .to_string()),
+            "constants.glsl" => Ok("#define SCALE 2.0\n".to_string()),
+            "broken.glsl" => Ok("void broken() { undeclared; }\n".to_string()),
+            "self.glsl" => Ok("#include \"self.glsl\"\n".to_string()),
+            _ => Err("not found".to_string()),
+        };
+        Options {
+            include_resolver: Some(Box::new(resolver)),
+            ..Options::from(ShaderStage::Vertex)
+        }
+    }
+    let mut frontend = Frontend::default();
+
+    frontend
+        .parse(
+            &options(),
+            "#version 450\n\
+             #include \"common.glsl\"\n\
+             void main() { float x = scale(1.0); }",
+        )
+        .unwrap();
+    let includes = &frontend.metadata().includes;
+    assert_eq!(includes.len(), 2);
+    assert_eq!(includes[0].path, "common.glsl");
+    assert_eq!(includes[0].source, SourceId::new(1));
+    assert_eq!(includes[0].meta, Span::new(13, 35));
+    assert_eq!(includes[1].path, "constants.glsl");
+    assert_eq!(includes[1].source, SourceId::new(2));
+    assert_eq!(
+        includes[1].meta,
+        Span::new(0, 25).with_source(SourceId::new(1))
+    );
+
+    // Errors in included files point into them.
+    let errors = frontend
+        .parse(
+            &options(),
+            "#version 450\n#include \"broken.glsl\"\nvoid main() {}",
+        )
+        .unwrap_err();
+    assert!(matches!(errors[0].kind, ErrorKind::UnknownVariable(ref name) if name == "undeclared"));
+    assert_eq!(errors[0].meta.source(), SourceId::new(1));
+    let text = &frontend.metadata().includes[0].text;
+    assert!(errors[0].meta.to_range().unwrap().end <= text.len());
+
+    assert_eq!(
+        frontend
+            .parse(
+                &options(),
+                "#version 450\n#include \"missing.glsl\"\nvoid main() {}",
+            )
+            .unwrap_err(),
+        vec![Error {
+            kind: ErrorKind::IncludeFailed {
+                path: "missing.glsl".into(),
+                reason: "not found".into(),
+            },
+            meta: Span::new(13, 36),
+        }],
+    );
+    assert_eq!(
+        frontend
+            .parse(&options(), "#version 450\n#include \"self.glsl\"\n")
+            .unwrap_err(),
+        vec![Error {
+            kind: ErrorKind::IncludeFailed {
+                path: "self.glsl".into(),
+                reason: "it includes itself".into(),
+            },
+            meta: Span::new(0, 20).with_source(SourceId::new(1)),
+        }],
+    );
+}
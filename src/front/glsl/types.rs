@@ -169,7 +169,7 @@ pub fn parse_type(type_name: &str) -> Option<Type> {
 
                 let class = ImageClass::Storage {
                     format: crate::StorageFormat::R8Uint,
-                    access: crate::StorageAccess::all(),
+                    access: crate::StorageAccess::LOAD | crate::StorageAccess::STORE,
                 };
 
                 // TODO: glsl support multisampled storage images, naga doesn't
@@ -358,6 +358,12 @@ impl Context<'_> {
                     .const_expressions
                     .append(Expression::Compose { ty, components }, meta)
             }
+            Expression::Repeat { ty, value } => {
+                let value = self.lift_up_const_expression(value)?;
+                self.module
+                    .const_expressions
+                    .append(Expression::Repeat { ty, value }, meta)
+            }
             Expression::Splat { size, value } => {
                 let value = self.lift_up_const_expression(value)?;
                 self.module
@@ -561,6 +561,24 @@ impl Frontend {
                         }
                     },
                     AddressSpace::Function => space = AddressSpace::Private,
+                    AddressSpace::WorkGroup => {
+                        if self.meta.stage != ShaderStage::Compute {
+                            self.errors.push(Error {
+                                kind: ErrorKind::SemanticError(
+                                    "shared variables are only allowed in compute shaders".into(),
+                                ),
+                                meta,
+                            });
+                        }
+                        if init.is_some() {
+                            self.errors.push(Error {
+                                kind: ErrorKind::SemanticError(
+                                    "shared variables can't have initializers".into(),
+                                ),
+                                meta,
+                            });
+                        }
+                    }
                     _ => {}
                 };
 
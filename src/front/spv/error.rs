@@ -33,6 +33,8 @@ pub enum Error {
     UnsupportedStorageClass(spirv::Word),
     #[error("unsupported image dimension %{0}")]
     UnsupportedImageDim(spirv::Word),
+    #[error("unsupported input attachment type %{0}")]
+    UnsupportedInputAttachment(spirv::Word),
     #[error("unsupported image format %{0}")]
     UnsupportedImageFormat(spirv::Word),
     #[error("unsupported builtin %{0}")]
@@ -114,10 +116,18 @@ pub enum Error {
     ControlFlowGraphCycle(crate::front::spv::BlockId),
     #[error("recursive function call %{0}")]
     FunctionCallCycle(spirv::Word),
+    #[error("unsupported OpSpecConstantOp operation {0:?}")]
+    UnsupportedSpecConstantOp(spirv::Op),
+    #[error("can't evaluate OpSpecConstantOp %{0}: {1}")]
+    InvalidSpecConstantOp(spirv::Word, crate::proc::ConstantEvaluatorError),
     #[error("invalid array size {0:?}")]
     InvalidArraySize(Handle<crate::Constant>),
     #[error("invalid barrier scope %{0}")]
     InvalidBarrierScope(spirv::Word),
+    #[error("invalid subgroup operation scope %{0}, only the subgroup scope is supported")]
+    InvalidSubgroupScope(spirv::Word),
+    #[error("unsupported group operation {0}")]
+    UnsupportedGroupOperation(spirv::Word),
     #[error("invalid barrier memory semantics %{0}")]
     InvalidBarrierMemorySemantics(spirv::Word),
     #[error(
@@ -126,4 +136,6 @@ pub enum Error {
          come from a binding)"
     )]
     NonBindingArrayOfImageOrSamplers,
+    #[error(transparent)]
+    ArenaFull(#[from] crate::ArenaFull),
 }
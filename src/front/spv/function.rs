@@ -35,6 +35,7 @@ impl<I: Iterator<Item = u32>> super::Frontend<I> {
         self.lookup_expression.clear();
         self.lookup_load_override.clear();
         self.lookup_sampled_image.clear();
+        self.lookup_image_texel_pointer.clear();
 
         let result_type_id = self.next()?;
         let fun_id = self.next()?;
@@ -349,6 +350,45 @@ impl<I: Iterator<Item = u32>> super::Frontend<I> {
                     function.arguments.push(arg);
                 }
             }
+            // Provide the position that reads from rewritten input
+            // attachments are relative to, reusing the shader's own
+            // `FragCoord` input if it has one.
+            if let (crate::ShaderStage::Fragment, Some(frag_coord)) =
+                (ep.stage, self.subpass_frag_coord)
+            {
+                let index = function
+                    .arguments
+                    .iter()
+                    .position(|arg| {
+                        matches!(
+                            arg.binding,
+                            Some(crate::Binding::BuiltIn(crate::BuiltIn::Position { .. }))
+                        )
+                    })
+                    .unwrap_or_else(|| {
+                        function.arguments.push(crate::FunctionArgument {
+                            name: None,
+                            ty: module.global_variables[frag_coord].ty,
+                            binding: Some(crate::Binding::BuiltIn(crate::BuiltIn::Position {
+                                invariant: false,
+                            })),
+                        });
+                        function.arguments.len() - 1
+                    });
+                let span = crate::Span::default();
+                let value = function
+                    .expressions
+                    .append(crate::Expression::FunctionArgument(index as u32), span);
+                function.body.push(
+                    crate::Statement::Store {
+                        pointer: function
+                            .expressions
+                            .append(crate::Expression::GlobalVariable(frag_coord), span),
+                        value,
+                    },
+                    span,
+                );
+            }
             // 2. call the wrapped function
             let fake_id = !(module.entry_points.len() as u32); // doesn't matter, as long as it's not a collision
             let dummy_handle = self.add_call(fake_id, fun_id);
@@ -531,7 +571,7 @@ impl<I: Iterator<Item = u32>> super::Frontend<I> {
                     let span = crate::Span::total_span(
                         components.iter().map(|h| function.expressions.get_span(*h)),
                     );
-                    let ty = module.types.insert(
+                    let ty = module.types.try_insert(
                         crate::Type {
                             name: None,
                             inner: crate::TypeInner::Struct {
@@ -540,7 +580,7 @@ impl<I: Iterator<Item = u32>> super::Frontend<I> {
                             },
                         },
                         span,
-                    );
+                    )?;
                     let result_expr = function
                         .expressions
                         .append(crate::Expression::Compose { ty, components }, span);
@@ -8,6 +8,30 @@ pub(super) struct LookupSampledImage {
     sampler: Handle<crate::Expression>,
 }
 
+/// The texel an `OpImageTexelPointer` points to.
+#[derive(Clone, Debug)]
+pub(super) struct LookupImageTexelPointer {
+    image: Handle<crate::Expression>,
+    coordinate: Handle<crate::Expression>,
+    array_index: Option<Handle<crate::Expression>>,
+}
+
+impl LookupImageTexelPointer {
+    pub(super) const fn into_statement(
+        self,
+        fun: crate::AtomicFunction,
+        value: Handle<crate::Expression>,
+    ) -> crate::Statement {
+        crate::Statement::ImageAtomic {
+            image: self.image,
+            coordinate: self.coordinate,
+            array_index: self.array_index,
+            fun,
+            value,
+        }
+    }
+}
+
 bitflags::bitflags! {
     /// Flags describing sampling method.
     #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -219,6 +243,42 @@ pub(super) fn patch_comparison_type(
     true
 }
 
+/// Add [`StorageAccess::ATOMIC`] to the access of the storage image `var`.
+///
+/// [`StorageAccess::ATOMIC`]: crate::StorageAccess::ATOMIC
+pub(super) fn patch_atomic_access(
+    var: &mut crate::GlobalVariable,
+    arena: &mut UniqueArena<crate::Type>,
+) {
+    let original_ty = &arena[var.ty];
+    let original_ty_span = arena.get_span(var.ty);
+    let ty_inner = match original_ty.inner {
+        crate::TypeInner::Image {
+            class: crate::ImageClass::Storage { format, access },
+            dim,
+            arrayed,
+        } => crate::TypeInner::Image {
+            class: crate::ImageClass::Storage {
+                format,
+                access: access | crate::StorageAccess::ATOMIC,
+            },
+            dim,
+            arrayed,
+        },
+        // Let the validator report atomics on other kinds of images.
+        _ => return,
+    };
+
+    let name = original_ty.name.clone();
+    var.ty = arena.insert(
+        crate::Type {
+            name,
+            inner: ty_inner,
+        },
+        original_ty_span,
+    );
+}
+
 impl<I: Iterator<Item = u32>> super::Frontend<I> {
     pub(super) fn parse_image_couple(&mut self) -> Result<(), Error> {
         let _result_type_id = self.next()?;
@@ -311,6 +371,61 @@ impl<I: Iterator<Item = u32>> super::Frontend<I> {
         })
     }
 
+    pub(super) fn parse_image_texel_pointer(
+        &mut self,
+        ctx: &mut super::BlockContext,
+        emitter: &mut crate::proc::Emitter,
+        block: &mut crate::Block,
+        body_idx: usize,
+    ) -> Result<(), Error> {
+        let _result_type_id = self.next()?;
+        let result_id = self.next()?;
+        let image_id = self.next()?;
+        let coordinate_id = self.next()?;
+        let _sample_id = self.next()?;
+
+        // The image operand is a pointer to the image variable, which is the
+        // same expression as the image itself.
+        let image_lexp = self.lookup_expression.lookup(image_id)?;
+        let image_ty = ctx.get_image_expr_ty(image_lexp.handle)?;
+        if let crate::Expression::GlobalVariable(handle) = ctx.expressions[image_lexp.handle] {
+            self.atomic_images.insert(handle);
+        }
+
+        let coord_lexp = self.lookup_expression.lookup(coordinate_id)?;
+        let coord_handle =
+            self.get_expr_handle(coordinate_id, coord_lexp, ctx, emitter, block, body_idx);
+        let coord_type_handle = self.lookup_type.lookup(coord_lexp.type_id)?.handle;
+        let (coordinate, array_index) = match ctx.type_arena[image_ty].inner {
+            crate::TypeInner::Image {
+                dim,
+                arrayed,
+                class: _,
+            } => extract_image_coordinates(
+                dim,
+                if arrayed {
+                    ExtraCoordinate::ArrayLayer
+                } else {
+                    ExtraCoordinate::Garbage
+                },
+                coord_handle,
+                coord_type_handle,
+                ctx,
+            ),
+            _ => return Err(Error::InvalidImage(image_ty)),
+        };
+
+        self.lookup_image_texel_pointer.insert(
+            result_id,
+            LookupImageTexelPointer {
+                image: image_lexp.handle,
+                coordinate,
+                array_index,
+            },
+        );
+        Ok(())
+    }
+
     pub(super) fn parse_image_load(
         &mut self,
         mut words_left: u16,
@@ -321,7 +436,7 @@ impl<I: Iterator<Item = u32>> super::Frontend<I> {
         body_idx: usize,
     ) -> Result<(), Error> {
         let start = self.data_offset;
-        let result_type_id = self.next()?;
+        let _result_type_id = self.next()?;
         let result_id = self.next()?;
         let image_id = self.next()?;
         let coordinate_id = self.next()?;
@@ -369,9 +484,67 @@ impl<I: Iterator<Item = u32>> super::Frontend<I> {
         let image_ty = ctx.get_image_expr_ty(image_lexp.handle)?;
 
         let coord_lexp = self.lookup_expression.lookup(coordinate_id)?;
-        let coord_handle =
+        let mut coord_handle =
             self.get_expr_handle(coordinate_id, coord_lexp, ctx, emitter, block, body_idx);
         let coord_type_handle = self.lookup_type.lookup(coord_lexp.type_id)?.handle;
+
+        // Input attachments are read at an offset from the fragment's
+        // position, and have no mipmaps.
+        if let Some(frag_coord) = self
+            .subpass_frag_coord
+            .filter(|_| self.subpass_image_types.contains(&image_lexp.type_id))
+        {
+            let kind = ctx.type_arena[coord_type_handle]
+                .inner
+                .scalar_kind()
+                .ok_or(Error::InvalidImage(image_ty))?;
+            let span = self.span_from_with_op(start);
+
+            block.extend(emitter.finish(ctx.expressions));
+            let pointer = ctx
+                .expressions
+                .append(crate::Expression::GlobalVariable(frag_coord), span);
+            if sample.is_none() {
+                level = Some(
+                    ctx.expressions
+                        .append(crate::Expression::Literal(crate::Literal::I32(0)), span),
+                );
+            }
+            emitter.start(ctx.expressions);
+
+            let position = ctx
+                .expressions
+                .append(crate::Expression::Load { pointer }, span);
+            let position = ctx.expressions.append(
+                crate::Expression::Swizzle {
+                    size: crate::VectorSize::Bi,
+                    vector: position,
+                    pattern: [
+                        crate::SwizzleComponent::X,
+                        crate::SwizzleComponent::Y,
+                        crate::SwizzleComponent::X,
+                        crate::SwizzleComponent::X,
+                    ],
+                },
+                span,
+            );
+            let position = ctx.expressions.append(
+                crate::Expression::As {
+                    expr: position,
+                    kind,
+                    convert: Some(4),
+                },
+                span,
+            );
+            coord_handle = ctx.expressions.append(
+                crate::Expression::Binary {
+                    op: crate::BinaryOperator::Add,
+                    left: position,
+                    right: coord_handle,
+                },
+                span,
+            );
+        }
         let (coordinate, array_index) = match ctx.type_arena[image_ty].inner {
             crate::TypeInner::Image {
                 dim,
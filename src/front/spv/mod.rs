@@ -64,10 +64,20 @@ pub const SUPPORTED_CAPABILITIES: &[spirv::Capability] = &[
     spirv::Capability::Int8,
     spirv::Capability::Int16,
     spirv::Capability::Int64,
+    spirv::Capability::Int64Atomics,
     spirv::Capability::Float16,
     spirv::Capability::Float64,
+    spirv::Capability::StorageBuffer16BitAccess,
+    spirv::Capability::UniformAndStorageBuffer16BitAccess,
+    spirv::Capability::StoragePushConstant16,
     spirv::Capability::Geometry,
     spirv::Capability::MultiView,
+    spirv::Capability::GroupNonUniform,
+    spirv::Capability::GroupNonUniformVote,
+    spirv::Capability::GroupNonUniformBallot,
+    spirv::Capability::GroupNonUniformArithmetic,
+    spirv::Capability::GroupNonUniformShuffle,
+    spirv::Capability::GroupNonUniformShuffleRelative,
     // tricky ones
     spirv::Capability::UniformBufferArrayDynamicIndexing,
     spirv::Capability::StorageBufferArrayDynamicIndexing,
@@ -76,6 +86,7 @@ pub const SUPPORTED_EXTENSIONS: &[&str] = &[
     "SPV_KHR_storage_buffer_storage_class",
     "SPV_KHR_vulkan_memory_model",
     "SPV_KHR_multiview",
+    "SPV_KHR_16bit_storage",
 ];
 pub const SUPPORTED_EXT_SETS: &[&str] = &["GLSL.std.450"];
 
@@ -168,12 +179,14 @@ bitflags::bitflags! {
     struct DecorationFlags: u32 {
         const NON_READABLE = 0x1;
         const NON_WRITABLE = 0x2;
+        const NON_UNIFORM = 0x4;
+        const COHERENT = 0x8;
     }
 }
 
 impl DecorationFlags {
     fn to_storage_access(self) -> crate::StorageAccess {
-        let mut access = crate::StorageAccess::all();
+        let mut access = crate::StorageAccess::LOAD | crate::StorageAccess::STORE;
         if self.contains(DecorationFlags::NON_READABLE) {
             access &= !crate::StorageAccess::LOAD;
         }
@@ -356,6 +369,21 @@ pub struct Options {
     /// Only allow shaders with the known set of capabilities.
     pub strict_capabilities: bool,
     pub block_ctx_dump_prefix: Option<PathBuf>,
+    /// Accept input attachments (`SubpassData` images), which have no IR
+    /// equivalent, by rewriting them into sampled 2D textures.
+    ///
+    /// Reads from them become [`ImageLoad`]s at the fragment's position plus
+    /// the given offset, so the texture bound in their place has to hold
+    /// the attachment's contents at the same resolution.
+    ///
+    /// [`ImageLoad`]: crate::Expression::ImageLoad
+    pub input_attachments_as_textures: bool,
+    /// Accept `NonSemantic.*` extended instruction sets, like
+    /// `NonSemantic.Shader.DebugInfo.100`, and skip their instructions.
+    ///
+    /// These carry debug info and other annotations that don't affect what
+    /// the shader does, so dropping them is always safe.
+    pub ignore_non_semantic_instructions: bool,
 }
 
 impl Default for Options {
@@ -364,6 +392,8 @@ impl Default for Options {
             adjust_coordinate_space: true,
             strict_capabilities: false,
             block_ctx_dump_prefix: None,
+            input_attachments_as_textures: false,
+            ignore_non_semantic_instructions: false,
         }
     }
 }
@@ -570,11 +600,17 @@ pub struct Frontend<I> {
     lookup_storage_buffer_types: FastHashMap<Handle<crate::Type>, crate::StorageAccess>,
     // Lookup for samplers and sampled images, storing flags on how they are used.
     lookup_constant: FastHashMap<spirv::Word, LookupConstant>,
+    /// The values of the constants made by `OpSpecConstantOp` when no
+    /// specialization constants are overridden, for those whose values are
+    /// override expressions.
+    spec_constant_defaults: FastHashMap<Handle<crate::Constant>, Handle<crate::Expression>>,
     lookup_variable: FastHashMap<spirv::Word, LookupVariable>,
     lookup_expression: FastHashMap<spirv::Word, LookupExpression>,
     // Load overrides are used to work around row-major matrices
     lookup_load_override: FastHashMap<spirv::Word, LookupLoadOverride>,
     lookup_sampled_image: FastHashMap<spirv::Word, image::LookupSampledImage>,
+    // Texel pointers from `OpImageTexelPointer`, for atomics on images.
+    lookup_image_texel_pointer: FastHashMap<spirv::Word, image::LookupImageTexelPointer>,
     lookup_function_type: FastHashMap<spirv::Word, LookupFunctionType>,
     lookup_function: FastHashMap<spirv::Word, LookupFunction>,
     lookup_entry_point: FastHashMap<spirv::Word, EntryPoint>,
@@ -603,6 +639,38 @@ pub struct Frontend<I> {
     /// glslang declares those by default even though they are never written to
     /// (see <https://github.com/KhronosGroup/glslang/issues/1868>)
     gl_per_vertex_builtin_access: FastHashSet<crate::BuiltIn>,
+
+    /// The ids of `SubpassData` image types rewritten into sampled textures.
+    ///
+    /// See [`Options::input_attachments_as_textures`].
+    subpass_image_types: FastHashSet<spirv::Word>,
+
+    /// The ids of imported `NonSemantic.*` instruction sets, whose
+    /// instructions we skip.
+    ///
+    /// See [`Options::ignore_non_semantic_instructions`].
+    non_semantic_sets: FastHashSet<spirv::Word>,
+
+    /// A private global holding the fragment's position, which reads from
+    /// rewritten input attachments are relative to.
+    ///
+    /// Entry point wrappers store the `Position` built-in into it.
+    subpass_frag_coord: Option<Handle<crate::GlobalVariable>>,
+
+    /// The result types of the `ModfStruct` and `FrexpStruct` instructions
+    /// we've seen, which the module must predeclare.
+    ///
+    /// Types can't be added while parsing function bodies, so we generate
+    /// these once parsing is done.
+    predeclared_types: crate::FastIndexSet<crate::PredeclaredType>,
+
+    /// The image globals that atomic operations are applied to.
+    ///
+    /// SPIR-V has no access qualifier for this, so these get
+    /// [`StorageAccess::ATOMIC`] added to their types once parsing is done.
+    ///
+    /// [`StorageAccess::ATOMIC`]: crate::StorageAccess::ATOMIC
+    atomic_images: FastHashSet<Handle<crate::GlobalVariable>>,
 }
 
 impl<I: Iterator<Item = u32>> Frontend<I> {
@@ -622,10 +690,12 @@ impl<I: Iterator<Item = u32>> Frontend<I> {
             lookup_void_type: None,
             lookup_storage_buffer_types: FastHashMap::default(),
             lookup_constant: FastHashMap::default(),
+            spec_constant_defaults: FastHashMap::default(),
             lookup_variable: FastHashMap::default(),
             lookup_expression: FastHashMap::default(),
             lookup_load_override: FastHashMap::default(),
             lookup_sampled_image: FastHashMap::default(),
+            lookup_image_texel_pointer: FastHashMap::default(),
             lookup_function_type: FastHashMap::default(),
             lookup_function: FastHashMap::default(),
             lookup_entry_point: FastHashMap::default(),
@@ -635,6 +705,11 @@ impl<I: Iterator<Item = u32>> Frontend<I> {
             options: options.clone(),
             switch_cases: FastIndexMap::default(),
             gl_per_vertex_builtin_access: FastHashSet::default(),
+            subpass_image_types: FastHashSet::default(),
+            non_semantic_sets: FastHashSet::default(),
+            subpass_frag_coord: None,
+            predeclared_types: crate::FastIndexSet::default(),
+            atomic_images: FastHashSet::default(),
         }
     }
 
@@ -747,6 +822,12 @@ impl<I: Iterator<Item = u32>> Frontend<I> {
             spirv::Decoration::NonWritable => {
                 dec.flags |= DecorationFlags::NON_WRITABLE;
             }
+            spirv::Decoration::NonUniform => {
+                dec.flags |= DecorationFlags::NON_UNIFORM;
+            }
+            spirv::Decoration::Coherent => {
+                dec.flags |= DecorationFlags::COHERENT;
+            }
             spirv::Decoration::ColMajor => {
                 dec.matrix_major = Some(Majority::Column);
             }
@@ -1643,6 +1724,24 @@ impl<I: Iterator<Item = u32>> Frontend<I> {
                             }
                             // This must be a vector or an array.
                             _ => {
+                                // Indexing a binding array with a `NonUniform`
+                                // index is non-uniform indexing.
+                                let non_uniform =
+                                    matches!(ty.inner, crate::TypeInner::BindingArray { .. })
+                                        && self.future_decor.get(&access_id).map_or(
+                                            false,
+                                            |decor| {
+                                                decor.flags.contains(DecorationFlags::NON_UNIFORM)
+                                            },
+                                        );
+                                let index_expr_handle = if non_uniform {
+                                    ctx.expressions.append(
+                                        crate::Expression::NonUniform(index_expr_handle),
+                                        span,
+                                    )
+                                } else {
+                                    index_expr_handle
+                                };
                                 let base_handle = ctx.expressions.append(
                                     crate::Expression::Access {
                                         base: acex.base_handle,
@@ -2589,6 +2688,53 @@ impl<I: Iterator<Item = u32>> Frontend<I> {
                     block.push(stmt, span);
                     emitter.start(ctx.expressions);
                 }
+                Op::ImageTexelPointer => {
+                    inst.expect(6)?;
+                    self.parse_image_texel_pointer(ctx, &mut emitter, &mut block, body_idx)?;
+                }
+                Op::AtomicIAdd
+                | Op::AtomicISub
+                | Op::AtomicAnd
+                | Op::AtomicOr
+                | Op::AtomicXor
+                | Op::AtomicSMin
+                | Op::AtomicUMin
+                | Op::AtomicSMax
+                | Op::AtomicUMax
+                | Op::AtomicExchange => {
+                    inst.expect(7)?;
+                    let _result_type_id = self.next()?;
+                    let _result_id = self.next()?;
+                    let pointer_id = self.next()?;
+                    let _scope_id = self.next()?;
+                    let _semantics_id = self.next()?;
+                    let value_id = self.next()?;
+
+                    // Only atomics on images are supported. Naga's image
+                    // atomics have no result, so the result id is left
+                    // undefined, and any use of it is an error.
+                    let texel = self
+                        .lookup_image_texel_pointer
+                        .get(&pointer_id)
+                        .cloned()
+                        .ok_or(Error::UnsupportedInstruction(self.state, inst.op))?;
+                    let fun = match inst.op {
+                        Op::AtomicIAdd => crate::AtomicFunction::Add,
+                        Op::AtomicISub => crate::AtomicFunction::Subtract,
+                        Op::AtomicAnd => crate::AtomicFunction::And,
+                        Op::AtomicOr => crate::AtomicFunction::InclusiveOr,
+                        Op::AtomicXor => crate::AtomicFunction::ExclusiveOr,
+                        Op::AtomicSMin | Op::AtomicUMin => crate::AtomicFunction::Min,
+                        Op::AtomicSMax | Op::AtomicUMax => crate::AtomicFunction::Max,
+                        _ => crate::AtomicFunction::Exchange { compare: None },
+                    };
+                    let value_lexp = self.lookup_expression.lookup(value_id)?;
+                    let value = get_expr_handle!(value_id, value_lexp);
+
+                    block.extend(emitter.finish(ctx.expressions));
+                    block.push(texel.into_statement(fun, value), span);
+                    emitter.start(ctx.expressions);
+                }
                 Op::ImageFetch | Op::ImageRead => {
                     let extra = inst.expect_at_least(5)?;
                     self.parse_image_load(
@@ -2910,6 +3056,12 @@ impl<I: Iterator<Item = u32>> Frontend<I> {
                     let result_type_id = self.next()?;
                     let result_id = self.next()?;
                     let set_id = self.next()?;
+                    if self.non_semantic_sets.contains(&set_id) {
+                        for _ in base_wc - 1..inst.wc {
+                            let _ = self.next()?;
+                        }
+                        continue;
+                    }
                     if Some(set_id) != self.ext_glsl_id {
                         return Err(Error::UnsupportedExtInstSet(set_id));
                     }
@@ -2990,6 +3142,28 @@ impl<I: Iterator<Item = u32>> Frontend<I> {
                         }
                     };
 
+                    if fun == Mf::Modf || fun == Mf::Frexp {
+                        // The result is a struct whose first member has the
+                        // argument's type.
+                        let result_ty = self.lookup_type.lookup(result_type_id)?.handle;
+                        let arg_ty = match ctx.type_arena[result_ty].inner {
+                            crate::TypeInner::Struct { ref members, .. } if !members.is_empty() => {
+                                members[0].ty
+                            }
+                            _ => return Err(Error::InvalidInnerType(result_type_id)),
+                        };
+                        let (size, width) = match ctx.type_arena[arg_ty].inner {
+                            crate::TypeInner::Scalar { width, .. } => (None, width),
+                            crate::TypeInner::Vector { size, width, .. } => (Some(size), width),
+                            _ => return Err(Error::InvalidInnerType(result_type_id)),
+                        };
+                        self.predeclared_types.insert(if fun == Mf::Modf {
+                            crate::PredeclaredType::ModfResult { size, width }
+                        } else {
+                            crate::PredeclaredType::FrexpResult { size, width }
+                        });
+                    }
+
                     let arg_count = fun.argument_count();
                     inst.expect(base_wc + arg_count as u16)?;
                     let arg = {
@@ -3673,6 +3847,118 @@ impl<I: Iterator<Item = u32>> Frontend<I> {
                         log::warn!("Unsupported barrier execution scope: {}", exec_scope);
                     }
                 }
+                Op::GroupNonUniformBallot
+                | Op::GroupNonUniformAll
+                | Op::GroupNonUniformAny
+                | Op::GroupNonUniformBroadcastFirst
+                | Op::GroupNonUniformBroadcast
+                | Op::GroupNonUniformShuffle
+                | Op::GroupNonUniformShuffleXor
+                | Op::GroupNonUniformShuffleUp
+                | Op::GroupNonUniformShuffleDown
+                | Op::GroupNonUniformIAdd
+                | Op::GroupNonUniformFAdd
+                | Op::GroupNonUniformIMul
+                | Op::GroupNonUniformFMul
+                | Op::GroupNonUniformSMin
+                | Op::GroupNonUniformUMin
+                | Op::GroupNonUniformFMin
+                | Op::GroupNonUniformSMax
+                | Op::GroupNonUniformUMax
+                | Op::GroupNonUniformFMax
+                | Op::GroupNonUniformBitwiseAnd
+                | Op::GroupNonUniformBitwiseOr
+                | Op::GroupNonUniformBitwiseXor => {
+                    use crate::{SubgroupOperation as So, SubgroupReduction as Sr};
+                    let reduction = match inst.op {
+                        Op::GroupNonUniformIAdd | Op::GroupNonUniformFAdd => Some(Sr::Add),
+                        Op::GroupNonUniformIMul | Op::GroupNonUniformFMul => Some(Sr::Mul),
+                        Op::GroupNonUniformSMin
+                        | Op::GroupNonUniformUMin
+                        | Op::GroupNonUniformFMin => Some(Sr::Min),
+                        Op::GroupNonUniformSMax
+                        | Op::GroupNonUniformUMax
+                        | Op::GroupNonUniformFMax => Some(Sr::Max),
+                        Op::GroupNonUniformBitwiseAnd => Some(Sr::And),
+                        Op::GroupNonUniformBitwiseOr => Some(Sr::Or),
+                        Op::GroupNonUniformBitwiseXor => Some(Sr::Xor),
+                        _ => None,
+                    };
+                    let takes_index = matches!(
+                        inst.op,
+                        Op::GroupNonUniformBroadcast
+                            | Op::GroupNonUniformShuffle
+                            | Op::GroupNonUniformShuffleXor
+                            | Op::GroupNonUniformShuffleUp
+                            | Op::GroupNonUniformShuffleDown
+                    );
+                    // Clustered reductions aren't supported.
+                    inst.expect(if reduction.is_some() || takes_index {
+                        6
+                    } else {
+                        5
+                    })?;
+                    let result_type_id = self.next()?;
+                    let result_id = self.next()?;
+                    let exec_scope_id = self.next()?;
+                    let exec_scope_const = self.lookup_constant.lookup(exec_scope_id)?;
+                    if resolve_constant(ctx.gctx(), exec_scope_const.handle)
+                        != Some(spirv::Scope::Subgroup as u32)
+                    {
+                        return Err(Error::InvalidSubgroupScope(exec_scope_id));
+                    }
+
+                    let group_operation = match reduction {
+                        Some(_) => Some(self.next()?),
+                        None => None,
+                    };
+                    let argument_id = self.next()?;
+                    let argument_lexp = self.lookup_expression.lookup(argument_id)?;
+                    let argument = get_expr_handle!(argument_id, argument_lexp);
+                    let index = if takes_index {
+                        let index_id = self.next()?;
+                        let index_lexp = self.lookup_expression.lookup(index_id)?;
+                        Some(get_expr_handle!(index_id, index_lexp))
+                    } else {
+                        None
+                    };
+
+                    let op = match (reduction, group_operation, index) {
+                        (Some(reduction), Some(group_operation), _) => match group_operation {
+                            x if x == spirv::GroupOperation::Reduce as u32 => So::Reduce(reduction),
+                            x if x == spirv::GroupOperation::InclusiveScan as u32 => {
+                                So::InclusiveScan(reduction)
+                            }
+                            x if x == spirv::GroupOperation::ExclusiveScan as u32 => {
+                                So::ExclusiveScan(reduction)
+                            }
+                            other => return Err(Error::UnsupportedGroupOperation(other)),
+                        },
+                        (_, _, Some(index)) => match inst.op {
+                            Op::GroupNonUniformBroadcast => So::Broadcast(index),
+                            Op::GroupNonUniformShuffle => So::Shuffle(index),
+                            Op::GroupNonUniformShuffleXor => So::ShuffleXor(index),
+                            Op::GroupNonUniformShuffleUp => So::ShuffleUp(index),
+                            _ => So::ShuffleDown(index),
+                        },
+                        _ => match inst.op {
+                            Op::GroupNonUniformBallot => So::Ballot,
+                            Op::GroupNonUniformAll => So::All,
+                            Op::GroupNonUniformAny => So::Any,
+                            _ => So::BroadcastFirst,
+                        },
+                    };
+
+                    let expr = crate::Expression::SubgroupOperation { op, argument };
+                    self.lookup_expression.insert(
+                        result_id,
+                        LookupExpression {
+                            handle: ctx.expressions.append(expr, span),
+                            type_id: result_type_id,
+                            block_id,
+                        },
+                    );
+                }
                 Op::CopyObject => {
                     inst.expect(4)?;
                     let result_type_id = self.next()?;
@@ -3810,6 +4096,7 @@ impl<I: Iterator<Item = u32>> Frontend<I> {
                 | S::Barrier(_)
                 | S::Store { .. }
                 | S::ImageStore { .. }
+                | S::ImageAtomic { .. }
                 | S::Atomic { .. }
                 | S::RayQuery { .. } => {}
                 S::Call {
@@ -3917,6 +4204,7 @@ impl<I: Iterator<Item = u32>> Frontend<I> {
                 Op::Capability => self.parse_capability(inst),
                 Op::Extension => self.parse_extension(inst),
                 Op::ExtInstImport => self.parse_ext_inst_import(inst),
+                Op::ExtInst => self.parse_non_semantic_ext_inst(inst),
                 Op::MemoryModel => self.parse_memory_model(inst),
                 Op::EntryPoint => self.parse_entry_point(inst),
                 Op::ExecutionMode => self.parse_execution_mode(inst),
@@ -3943,10 +4231,17 @@ impl<I: Iterator<Item = u32>> Frontend<I> {
                 Op::TypeSampledImage => self.parse_type_sampled_image(inst),
                 Op::TypeSampler => self.parse_type_sampler(inst, &mut module),
                 Op::Constant | Op::SpecConstant => self.parse_constant(inst, &mut module),
-                Op::ConstantComposite => self.parse_composite_constant(inst, &mut module),
+                Op::ConstantComposite | Op::SpecConstantComposite => {
+                    self.parse_composite_constant(inst, &mut module)
+                }
                 Op::ConstantNull | Op::Undef => self.parse_null_constant(inst, &mut module),
-                Op::ConstantTrue => self.parse_bool_constant(inst, true, &mut module),
-                Op::ConstantFalse => self.parse_bool_constant(inst, false, &mut module),
+                Op::ConstantTrue | Op::SpecConstantTrue => {
+                    self.parse_bool_constant(inst, true, &mut module)
+                }
+                Op::ConstantFalse | Op::SpecConstantFalse => {
+                    self.parse_bool_constant(inst, false, &mut module)
+                }
+                Op::SpecConstantOp => self.parse_spec_constant_op(inst, &mut module),
                 Op::Variable => self.parse_global_variable(inst, &mut module),
                 Op::Function => {
                     self.switch(ModuleState::Function, inst.op)?;
@@ -3996,6 +4291,14 @@ impl<I: Iterator<Item = u32>> Frontend<I> {
             }
         }
 
+        for handle in self.atomic_images.drain() {
+            image::patch_atomic_access(module.global_variables.get_mut(handle), &mut module.types);
+        }
+
+        for predeclared in self.predeclared_types.drain(..) {
+            module.generate_predeclared_type(predeclared);
+        }
+
         if !self.future_decor.is_empty() {
             log::warn!("Unused item decorations: {:?}", self.future_decor);
             self.future_decor.clear();
@@ -4014,7 +4317,10 @@ impl<I: Iterator<Item = u32>> Frontend<I> {
         let capability = self.next()?;
         let cap =
             spirv::Capability::from_u32(capability).ok_or(Error::UnknownCapability(capability))?;
-        if !SUPPORTED_CAPABILITIES.contains(&cap) {
+        let supported = SUPPORTED_CAPABILITIES.contains(&cap)
+            || (cap == spirv::Capability::InputAttachment
+                && self.options.input_attachments_as_textures);
+        if !supported {
             if self.options.strict_capabilities {
                 return Err(Error::UnsupportedCapability(cap));
             } else {
@@ -4031,7 +4337,10 @@ impl<I: Iterator<Item = u32>> Frontend<I> {
         if left != 0 {
             return Err(Error::InvalidOperand);
         }
-        if !SUPPORTED_EXTENSIONS.contains(&name.as_str()) {
+        let supported = SUPPORTED_EXTENSIONS.contains(&name.as_str())
+            || (name == "SPV_KHR_non_semantic_info"
+                && self.options.ignore_non_semantic_instructions);
+        if !supported {
             return Err(Error::UnsupportedExtension(name));
         }
         Ok(())
@@ -4045,6 +4354,10 @@ impl<I: Iterator<Item = u32>> Frontend<I> {
         if left != 0 {
             return Err(Error::InvalidOperand);
         }
+        if name.starts_with("NonSemantic.") && self.options.ignore_non_semantic_instructions {
+            self.non_semantic_sets.insert(result_id);
+            return Ok(());
+        }
         if !SUPPORTED_EXT_SETS.contains(&name.as_str()) {
             return Err(Error::UnsupportedExtSet(name));
         }
@@ -4052,6 +4365,22 @@ impl<I: Iterator<Item = u32>> Frontend<I> {
         Ok(())
     }
 
+    /// Skip an `OpExtInst` among the global declarations, which only
+    /// non-semantic instruction sets may place there.
+    fn parse_non_semantic_ext_inst(&mut self, inst: Instruction) -> Result<(), Error> {
+        inst.expect_at_least(5)?;
+        let _result_type_id = self.next()?;
+        let _result_id = self.next()?;
+        let set_id = self.next()?;
+        if !self.non_semantic_sets.contains(&set_id) {
+            return Err(Error::UnsupportedExtInstSet(set_id));
+        }
+        for _ in 4..inst.wc {
+            let _ = self.next()?;
+        }
+        Ok(())
+    }
+
     fn parse_memory_model(&mut self, inst: Instruction) -> Result<(), Error> {
         self.switch(ModuleState::MemoryModel, inst.op)?;
         inst.expect(3)?;
@@ -4251,13 +4580,13 @@ impl<I: Iterator<Item = u32>> Frontend<I> {
         self.lookup_type.insert(
             id,
             LookupType {
-                handle: module.types.insert(
+                handle: module.types.try_insert(
                     crate::Type {
                         name: self.future_decor.remove(&id).and_then(|dec| dec.name),
                         inner,
                     },
                     self.span_from_with_op(start),
-                ),
+                )?,
                 base_id: None,
             },
         );
@@ -4286,13 +4615,13 @@ impl<I: Iterator<Item = u32>> Frontend<I> {
         self.lookup_type.insert(
             id,
             LookupType {
-                handle: module.types.insert(
+                handle: module.types.try_insert(
                     crate::Type {
                         name: self.future_decor.remove(&id).and_then(|dec| dec.name),
                         inner,
                     },
                     self.span_from_with_op(start),
-                ),
+                )?,
                 base_id: None,
             },
         );
@@ -4316,13 +4645,13 @@ impl<I: Iterator<Item = u32>> Frontend<I> {
         self.lookup_type.insert(
             id,
             LookupType {
-                handle: module.types.insert(
+                handle: module.types.try_insert(
                     crate::Type {
                         name: self.future_decor.remove(&id).and_then(|dec| dec.name),
                         inner,
                     },
                     self.span_from_with_op(start),
-                ),
+                )?,
                 base_id: None,
             },
         );
@@ -4353,13 +4682,13 @@ impl<I: Iterator<Item = u32>> Frontend<I> {
         self.lookup_type.insert(
             id,
             LookupType {
-                handle: module.types.insert(
+                handle: module.types.try_insert(
                     crate::Type {
                         name: self.future_decor.remove(&id).and_then(|dec| dec.name),
                         inner,
                     },
                     self.span_from_with_op(start),
-                ),
+                )?,
                 base_id: Some(type_id),
             },
         );
@@ -4392,13 +4721,13 @@ impl<I: Iterator<Item = u32>> Frontend<I> {
         self.lookup_type.insert(
             id,
             LookupType {
-                handle: module.types.insert(
+                handle: module.types.try_insert(
                     crate::Type {
                         name: decor.and_then(|dec| dec.name),
                         inner,
                     },
                     self.span_from_with_op(start),
-                ),
+                )?,
                 base_id: Some(vector_type_id),
             },
         );
@@ -4474,7 +4803,7 @@ impl<I: Iterator<Item = u32>> Frontend<I> {
             base_lookup_ty.clone()
         } else {
             LookupType {
-                handle: module.types.insert(
+                handle: module.types.try_insert(
                     crate::Type {
                         name: decor.and_then(|dec| dec.name),
                         inner: crate::TypeInner::Pointer {
@@ -4483,7 +4812,7 @@ impl<I: Iterator<Item = u32>> Frontend<I> {
                         },
                     },
                     self.span_from_with_op(start),
-                ),
+                )?,
                 base_id: Some(type_id),
             }
         };
@@ -4504,7 +4833,10 @@ impl<I: Iterator<Item = u32>> Frontend<I> {
         let length_id = self.next()?;
         let length_const = self.lookup_constant.lookup(length_id)?;
 
-        let size = resolve_constant(module.to_ctx(), length_const.handle)
+        // Arrays sized by specialization constant operations get the
+        // operation's default value.
+        let length = self.default_value(module, length_const.handle);
+        let size = resolve_const_expression(module.to_ctx(), length)
             .and_then(NonZeroU32::new)
             .ok_or(Error::InvalidArraySize(length_const.handle))?;
 
@@ -4565,13 +4897,13 @@ impl<I: Iterator<Item = u32>> Frontend<I> {
         self.lookup_type.insert(
             id,
             LookupType {
-                handle: module.types.insert(
+                handle: module.types.try_insert(
                     crate::Type {
                         name: decor.name,
                         inner,
                     },
                     self.span_from_with_op(start),
-                ),
+                )?,
                 base_id: Some(type_id),
             },
         );
@@ -4616,13 +4948,13 @@ impl<I: Iterator<Item = u32>> Frontend<I> {
         self.lookup_type.insert(
             id,
             LookupType {
-                handle: module.types.insert(
+                handle: module.types.try_insert(
                     crate::Type {
                         name: decor.name,
                         inner,
                     },
                     self.span_from_with_op(start),
-                ),
+                )?,
                 base_id: Some(type_id),
             },
         );
@@ -4648,6 +4980,7 @@ impl<I: Iterator<Item = u32>> Frontend<I> {
         let mut members = Vec::<crate::StructMember>::with_capacity(inst.wc as usize - 2);
         let mut member_lookups = Vec::with_capacity(members.capacity());
         let mut storage_access = crate::StorageAccess::empty();
+        let (mut any_member_coherent, mut all_members_coherent) = (false, true);
         let mut span = 0;
         let mut alignment = Alignment::ONE;
         for i in 0..u32::from(inst.wc) - 2 {
@@ -4659,6 +4992,9 @@ impl<I: Iterator<Item = u32>> Frontend<I> {
                 .unwrap_or_default();
 
             storage_access |= decor.flags.to_storage_access();
+            let coherent = decor.flags.contains(DecorationFlags::COHERENT);
+            any_member_coherent |= coherent;
+            all_members_coherent &= coherent;
 
             member_lookups.push(LookupMember {
                 type_id,
@@ -4705,17 +5041,29 @@ impl<I: Iterator<Item = u32>> Frontend<I> {
             });
         }
 
+        // Our IR only records coherence for whole buffers, so a block is
+        // coherent if all its members are, which is how glslang decorates
+        // a `coherent` block. There's nowhere to record coherence for only
+        // some members.
+        if any_member_coherent {
+            if all_members_coherent {
+                storage_access |= crate::StorageAccess::COHERENT;
+            } else {
+                log::warn!("Ignoring Coherent decorations on only some members of struct {id}");
+            }
+        }
+
         span = alignment.round_up(span);
 
         let inner = crate::TypeInner::Struct { span, members };
 
-        let ty_handle = module.types.insert(
+        let ty_handle = module.types.try_insert(
             crate::Type {
                 name: parent_decor.and_then(|dec| dec.name),
                 inner,
             },
             self.span_from_with_op(start),
-        );
+        )?;
 
         if is_storage_buffer {
             self.lookup_storage_buffer_types
@@ -4753,11 +5101,19 @@ impl<I: Iterator<Item = u32>> Frontend<I> {
         let _is_sampled = self.next()?;
         let format = self.next()?;
 
-        let dim = map_image_dim(dim)?;
+        let is_subpass_data = dim == spirv::Dim::DimSubpassData as u32;
+        let dim = if is_subpass_data {
+            if !self.options.input_attachments_as_textures || is_array {
+                return Err(Error::UnsupportedInputAttachment(id));
+            }
+            crate::ImageDimension::D2
+        } else {
+            map_image_dim(dim)?
+        };
         let decor = self.future_decor.remove(&id).unwrap_or_default();
 
         // ensure there is a type for texture coordinate without extra components
-        module.types.insert(
+        module.types.try_insert(
             crate::Type {
                 name: None,
                 inner: {
@@ -4770,7 +5126,7 @@ impl<I: Iterator<Item = u32>> Frontend<I> {
                 },
             },
             Default::default(),
-        );
+        )?;
 
         let base_handle = self.lookup_type.lookup(sample_type_id)?.handle;
         let kind = module.types[base_handle]
@@ -4796,13 +5152,40 @@ impl<I: Iterator<Item = u32>> Frontend<I> {
             arrayed: is_array,
         };
 
-        let handle = module.types.insert(
+        let handle = module.types.try_insert(
             crate::Type {
                 name: decor.name,
                 inner,
             },
             self.span_from_with_op(start),
-        );
+        )?;
+
+        if is_subpass_data {
+            self.subpass_image_types.insert(id);
+            if self.subpass_frag_coord.is_none() {
+                let ty = module.types.try_insert(
+                    crate::Type {
+                        name: None,
+                        inner: crate::TypeInner::Vector {
+                            size: crate::VectorSize::Quad,
+                            kind: crate::ScalarKind::Float,
+                            width: 4,
+                        },
+                    },
+                    Default::default(),
+                )?;
+                self.subpass_frag_coord = Some(module.global_variables.append(
+                    crate::GlobalVariable {
+                        name: Some("subpass_frag_coord".to_string()),
+                        space: crate::AddressSpace::Private,
+                        binding: None,
+                        ty,
+                        init: None,
+                    },
+                    Default::default(),
+                ));
+            }
+        }
 
         self.lookup_type.insert(
             id,
@@ -4839,13 +5222,13 @@ impl<I: Iterator<Item = u32>> Frontend<I> {
         inst.expect(2)?;
         let id = self.next()?;
         let decor = self.future_decor.remove(&id).unwrap_or_default();
-        let handle = module.types.insert(
+        let handle = module.types.try_insert(
             crate::Type {
                 name: decor.name,
                 inner: crate::TypeInner::Sampler { comparison: false },
             },
             self.span_from_with_op(start),
-        );
+        )?;
         self.lookup_type.insert(
             id,
             LookupType {
@@ -4877,6 +5260,11 @@ impl<I: Iterator<Item = u32>> Frontend<I> {
                 let low = self.next()?;
                 match width {
                     4 => crate::Literal::U32(low),
+                    8 => {
+                        inst.expect(5)?;
+                        let high = self.next()?;
+                        crate::Literal::U64((u64::from(high) << 32) | u64::from(low))
+                    }
                     _ => return Err(Error::InvalidTypeWidth(width as u32)),
                 }
             }
@@ -4887,6 +5275,11 @@ impl<I: Iterator<Item = u32>> Frontend<I> {
                 let low = self.next()?;
                 match width {
                     4 => crate::Literal::I32(low as i32),
+                    8 => {
+                        inst.expect(5)?;
+                        let high = self.next()?;
+                        crate::Literal::I64(((u64::from(high) << 32) | u64::from(low)) as i64)
+                    }
                     _ => return Err(Error::InvalidTypeWidth(width as u32)),
                 }
             }
@@ -4896,6 +5289,8 @@ impl<I: Iterator<Item = u32>> Frontend<I> {
             } => {
                 let low = self.next()?;
                 match width {
+                    // Narrow constants are zero-extended to a whole word.
+                    2 => crate::Literal::F16(low as u16),
                     4 => crate::Literal::F32(f32::from_bits(low)),
                     8 => {
                         inst.expect(5)?;
@@ -4949,11 +5344,25 @@ impl<I: Iterator<Item = u32>> Frontend<I> {
         let type_lookup = self.lookup_type.lookup(type_id)?;
         let ty = type_lookup.handle;
 
-        let mut components = Vec::with_capacity(inst.wc as usize - 3);
-        for _ in 0..components.capacity() {
+        let mut component_ids = Vec::with_capacity(inst.wc as usize - 3);
+        for _ in 0..component_ids.capacity() {
             let start = self.data_offset;
             let component_id = self.next()?;
-            let span = self.span_from_with_op(start);
+            component_ids.push((component_id, self.span_from_with_op(start)));
+        }
+        // Arrays of a single repeated constant, which is how large zeroed or
+        // filled arrays usually come, don't need a component each.
+        let repeated = matches!(module.types[ty].inner, crate::TypeInner::Array { .. })
+            && component_ids.len() > 1
+            && component_ids
+                .iter()
+                .all(|&(component_id, _)| component_id == component_ids[0].0);
+        if repeated {
+            component_ids.truncate(1);
+        }
+
+        let mut components = Vec::with_capacity(component_ids.len());
+        for (component_id, span) in component_ids {
             let constant = self.lookup_constant.lookup(component_id)?;
             let expr = module
                 .const_expressions
@@ -4965,9 +5374,15 @@ impl<I: Iterator<Item = u32>> Frontend<I> {
 
         let span = self.span_from_with_op(start);
 
-        let init = module
-            .const_expressions
-            .append(crate::Expression::Compose { ty, components }, span);
+        let expr = if repeated {
+            crate::Expression::Repeat {
+                ty,
+                value: components[0],
+            }
+        } else {
+            crate::Expression::Compose { ty, components }
+        };
+        let init = module.const_expressions.append(expr, span);
         self.lookup_constant.insert(
             id,
             LookupConstant {
@@ -5060,6 +5475,161 @@ impl<I: Iterator<Item = u32>> Frontend<I> {
         Ok(())
     }
 
+    /// Parse an `OpSpecConstantOp`.
+    ///
+    /// If any of the operation's operands are overridable, its value is an
+    /// [override expression] on them, which the constant is
+    /// [`Override::Derived`] from. Otherwise, it's folded to a plain
+    /// constant.
+    ///
+    /// Either way, the operation is also evaluated using the default values
+    /// of the specialization constants involved, and recorded in
+    /// [`spec_constant_defaults`], since Naga needs the lengths of arrays
+    /// sized by such operations.
+    ///
+    /// [override expression]: crate#override-expressions
+    /// [`Override::Derived`]: crate::Override::Derived
+    /// [`spec_constant_defaults`]: Frontend::spec_constant_defaults
+    fn parse_spec_constant_op(
+        &mut self,
+        inst: Instruction,
+        module: &mut crate::Module,
+    ) -> Result<(), Error> {
+        use spirv::Op;
+
+        let start = self.data_offset;
+        self.switch(ModuleState::Type, inst.op)?;
+        inst.expect_at_least(5)?;
+        let type_id = self.next()?;
+        let id = self.next()?;
+        let opcode = self.next()? as u16;
+        let op = Op::from_u16(opcode).ok_or(Error::UnknownInstruction(opcode))?;
+        let mut words = Vec::with_capacity(inst.wc as usize - 4);
+        for _ in 0..words.capacity() {
+            words.push(self.next()?);
+        }
+        let span = self.span_from_with_op(start);
+
+        let ty = self.lookup_type.lookup(type_id)?.handle;
+        let kind = module.types[ty].inner.scalar_kind();
+
+        // The operands of `OpCompositeExtract` after the first are literal
+        // indices, not constants.
+        let (constant_ids, indices) = match op {
+            Op::CompositeExtract => words.split_at(1),
+            _ => (&words[..], &[][..]),
+        };
+        let mut operands = Vec::with_capacity(constant_ids.len());
+        for &operand_id in constant_ids {
+            let lconst = self.lookup_constant.lookup(operand_id)?;
+            let operand_ty = self.lookup_type.lookup(lconst.type_id)?.handle;
+            let (kind, width) = match module.types[operand_ty].inner {
+                crate::TypeInner::Scalar { kind, width }
+                | crate::TypeInner::Vector { kind, width, .. } => (Some(kind), width),
+                _ => (None, 0),
+            };
+            operands.push(SpecConstantOperand {
+                handle: lconst.handle,
+                kind,
+                width,
+            });
+        }
+        let expected = match op {
+            Op::SNegate | Op::Not | Op::LogicalNot => 1,
+            Op::Select => 3,
+            Op::CompositeExtract => 1,
+            _ => 2,
+        };
+        if operands.len() != expected || (op == Op::CompositeExtract && indices.is_empty()) {
+            return Err(Error::InvalidOperandCount(op, inst.wc));
+        }
+        let op = SpecConstantOp::new(op, kind, operands[0].kind)
+            .ok_or(Error::UnsupportedSpecConstantOp(op))?;
+
+        let defaults: Vec<_> = operands
+            .iter()
+            .map(|operand| self.default_value(module, operand.handle))
+            .collect();
+
+        // Naga can't evaluate `Select`, so pick the default by hand.
+        let condition = match op {
+            SpecConstantOp::Select => match module.const_expressions[defaults[0]] {
+                crate::Expression::Literal(crate::Literal::Bool(condition)) => condition,
+                _ => return Err(Error::InvalidOperand),
+            },
+            _ => false,
+        };
+
+        let default = {
+            let mut evaluator = crate::proc::ConstantEvaluator::for_glsl_module(module);
+            op.build(
+                &operands,
+                &defaults,
+                indices,
+                kind,
+                &mut |expr| match expr {
+                    crate::Expression::Select { accept, reject, .. } => {
+                        Ok(if condition { accept } else { reject })
+                    }
+                    expr => evaluator.try_eval_and_append(&expr, span),
+                },
+            )
+            .map_err(|error| Error::InvalidSpecConstantOp(id, error))?
+        };
+
+        let is_override = operands
+            .iter()
+            .any(|operand| module.constants[operand.handle].r#override != crate::Override::None);
+        let (r#override, init) = if is_override {
+            let expressions = &mut module.const_expressions;
+            let values: Vec<_> = operands
+                .iter()
+                .map(|operand| {
+                    expressions.append(crate::Expression::Constant(operand.handle), span)
+                })
+                .collect();
+            let init = op.build(&operands, &values, indices, kind, &mut |expr| {
+                Ok::<_, std::convert::Infallible>(expressions.append(expr, span))
+            });
+            (
+                crate::Override::Derived,
+                init.unwrap_or_else(|never| match never {}),
+            )
+        } else {
+            (crate::Override::None, default)
+        };
+
+        let decor = self.future_decor.remove(&id).unwrap_or_default();
+        let handle = module.constants.append(
+            crate::Constant {
+                r#override,
+                name: decor.name,
+                ty,
+                init,
+            },
+            span,
+        );
+        if is_override {
+            self.spec_constant_defaults.insert(handle, default);
+        }
+        self.lookup_constant
+            .insert(id, LookupConstant { handle, type_id });
+        Ok(())
+    }
+
+    /// Return the value `constant` has when no specialization constants are
+    /// overridden.
+    fn default_value(
+        &self,
+        module: &crate::Module,
+        constant: Handle<crate::Constant>,
+    ) -> Handle<crate::Expression> {
+        match self.spec_constant_defaults.get(&constant) {
+            Some(&init) => init,
+            None => module.constants[constant].init,
+        }
+    }
+
     fn parse_global_variable(
         &mut self,
         inst: Instruction,
@@ -5111,9 +5681,15 @@ impl<I: Iterator<Item = u32>> Frontend<I> {
             // Storage image types in IR have to contain the access, but not in the SPIR-V.
             // The same image type in SPIR-V can be used (and has to be used) for multiple images.
             // So we copy the type out and apply the variable access decorations.
-            let access = dec.flags.to_storage_access();
+            let mut access = dec.flags.to_storage_access();
+            // Coherence only matters for images that can be written.
+            if dec.flags.contains(DecorationFlags::COHERENT)
+                && access.contains(crate::StorageAccess::STORE)
+            {
+                access |= crate::StorageAccess::COHERENT;
+            }
 
-            ty = module.types.insert(
+            ty = module.types.try_insert(
                 crate::Type {
                     name: None,
                     inner: crate::TypeInner::Image {
@@ -5123,7 +5699,7 @@ impl<I: Iterator<Item = u32>> Frontend<I> {
                     },
                 },
                 Default::default(),
-            );
+            )?;
         }
 
         let ext_class = match self.lookup_storage_buffer_types.get(&ty) {
@@ -5145,7 +5721,14 @@ impl<I: Iterator<Item = u32>> Frontend<I> {
         let (inner, var) = match ext_class {
             ExtendedClass::Global(mut space) => {
                 if let crate::AddressSpace::Storage { ref mut access } = space {
-                    *access &= dec.flags.to_storage_access();
+                    *access &= dec.flags.to_storage_access() | crate::StorageAccess::COHERENT;
+                    if dec.flags.contains(DecorationFlags::COHERENT) {
+                        *access |= crate::StorageAccess::COHERENT;
+                    }
+                    // Coherence only matters for buffers that can be written.
+                    if !access.contains(crate::StorageAccess::STORE) {
+                        access.remove(crate::StorageAccess::COHERENT);
+                    }
                 }
                 let var = crate::GlobalVariable {
                     binding: dec.resource_binding(),
@@ -5167,7 +5750,9 @@ impl<I: Iterator<Item = u32>> Frontend<I> {
                         | crate::BuiltIn::SampleIndex
                         | crate::BuiltIn::VertexIndex
                         | crate::BuiltIn::PrimitiveIndex
-                        | crate::BuiltIn::LocalInvocationIndex => Some(crate::TypeInner::Scalar {
+                        | crate::BuiltIn::LocalInvocationIndex
+                        | crate::BuiltIn::SubgroupSize
+                        | crate::BuiltIn::SubgroupInvocationId => Some(crate::TypeInner::Scalar {
                             kind: crate::ScalarKind::Uint,
                             width: 4,
                         }),
@@ -5307,11 +5892,173 @@ fn make_index_literal(
     Ok(expr)
 }
 
+/// An operand of an `OpSpecConstantOp`.
+struct SpecConstantOperand {
+    handle: Handle<crate::Constant>,
+    /// The scalar kind of the operand's type, if it has one.
+    kind: Option<crate::ScalarKind>,
+    width: crate::Bytes,
+}
+
+/// The operation an `OpSpecConstantOp` performs.
+#[derive(Clone, Copy)]
+enum SpecConstantOp {
+    Unary(crate::UnaryOperator),
+    /// A binary operation, and the signedness it treats integer operands as
+    /// having, if it cares.
+    Binary(crate::BinaryOperator, Option<crate::ScalarKind>),
+    Select,
+    CompositeExtract,
+}
+
+impl SpecConstantOp {
+    /// Return the operation `op` performs, producing a value of `kind` from
+    /// a first operand of `operand_kind`.
+    fn new(
+        op: spirv::Op,
+        kind: Option<crate::ScalarKind>,
+        operand_kind: Option<crate::ScalarKind>,
+    ) -> Option<Self> {
+        use crate::{BinaryOperator as Bo, ScalarKind as Sk, UnaryOperator as Uo};
+        use spirv::Op;
+
+        let int_kind = kind.filter(|&kind| kind == Sk::Sint || kind == Sk::Uint);
+        let binary = |op, kind| Some(Self::Binary(op, kind));
+        match op {
+            Op::SNegate => Some(Self::Unary(Uo::Negate)),
+            Op::Not => Some(Self::Unary(Uo::BitwiseNot)),
+            Op::LogicalNot => Some(Self::Unary(Uo::LogicalNot)),
+            Op::IAdd => binary(Bo::Add, int_kind),
+            Op::ISub => binary(Bo::Subtract, int_kind),
+            Op::IMul => binary(Bo::Multiply, int_kind),
+            Op::UDiv => binary(Bo::Divide, Some(Sk::Uint)),
+            Op::SDiv => binary(Bo::Divide, Some(Sk::Sint)),
+            Op::UMod => binary(Bo::Modulo, Some(Sk::Uint)),
+            Op::SRem => binary(Bo::Modulo, Some(Sk::Sint)),
+            Op::ShiftLeftLogical => binary(Bo::ShiftLeft, int_kind),
+            Op::ShiftRightLogical => binary(Bo::ShiftRight, Some(Sk::Uint)),
+            Op::ShiftRightArithmetic => binary(Bo::ShiftRight, Some(Sk::Sint)),
+            Op::BitwiseOr => binary(Bo::InclusiveOr, int_kind),
+            Op::BitwiseXor => binary(Bo::ExclusiveOr, int_kind),
+            Op::BitwiseAnd => binary(Bo::And, int_kind),
+            Op::LogicalOr => binary(Bo::LogicalOr, None),
+            Op::LogicalAnd => binary(Bo::LogicalAnd, None),
+            Op::LogicalEqual => binary(Bo::Equal, None),
+            Op::LogicalNotEqual => binary(Bo::NotEqual, None),
+            Op::IEqual => binary(Bo::Equal, operand_kind),
+            Op::INotEqual => binary(Bo::NotEqual, operand_kind),
+            Op::ULessThan => binary(Bo::Less, Some(Sk::Uint)),
+            Op::SLessThan => binary(Bo::Less, Some(Sk::Sint)),
+            Op::ULessThanEqual => binary(Bo::LessEqual, Some(Sk::Uint)),
+            Op::SLessThanEqual => binary(Bo::LessEqual, Some(Sk::Sint)),
+            Op::UGreaterThan => binary(Bo::Greater, Some(Sk::Uint)),
+            Op::SGreaterThan => binary(Bo::Greater, Some(Sk::Sint)),
+            Op::UGreaterThanEqual => binary(Bo::GreaterEqual, Some(Sk::Uint)),
+            Op::SGreaterThanEqual => binary(Bo::GreaterEqual, Some(Sk::Sint)),
+            Op::Select => Some(Self::Select),
+            Op::CompositeExtract => Some(Self::CompositeExtract),
+            _ => None,
+        }
+    }
+
+    /// Build the expression for this operation, producing a value of `kind`.
+    ///
+    /// The expressions in `values` hold the values of `operands`, and
+    /// `indices` are the literal indices of a `CompositeExtract`. This calls
+    /// `append` to add each new expression to its arena.
+    fn build<E>(
+        self,
+        operands: &[SpecConstantOperand],
+        values: &[Handle<crate::Expression>],
+        indices: &[u32],
+        kind: Option<crate::ScalarKind>,
+        append: &mut impl FnMut(crate::Expression) -> Result<Handle<crate::Expression>, E>,
+    ) -> Result<Handle<crate::Expression>, E> {
+        use crate::{BinaryOperator as Bo, ScalarKind as Sk};
+
+        let int_kind = kind.filter(|&kind| kind == Sk::Sint || kind == Sk::Uint);
+        let width = operands[0].width;
+        let operand = |append: &mut _, index: usize, to| {
+            let from = operands[index].kind;
+            cast_integer(append, values[index], from, to, width)
+        };
+        match self {
+            Self::Unary(op) => {
+                let expr = operand(append, 0, int_kind)?;
+                append(crate::Expression::Unary { op, expr })
+            }
+            Self::Binary(op, operand_kind) => {
+                let left = operand(append, 0, operand_kind)?;
+                // Shift amounts are always unsigned in Naga.
+                let right_kind = match op {
+                    Bo::ShiftLeft | Bo::ShiftRight => Some(Sk::Uint),
+                    _ => operand_kind,
+                };
+                let right = operand(append, 1, right_kind)?;
+                let result = append(crate::Expression::Binary { op, left, right })?;
+                match kind {
+                    Some(Sk::Bool) => Ok(result),
+                    _ => cast_integer(append, result, operand_kind, int_kind, width),
+                }
+            }
+            Self::Select => {
+                let accept = operand(append, 1, int_kind)?;
+                let reject = operand(append, 2, int_kind)?;
+                append(crate::Expression::Select {
+                    condition: values[0],
+                    accept,
+                    reject,
+                })
+            }
+            Self::CompositeExtract => {
+                let mut base = values[0];
+                for &index in indices {
+                    base = append(crate::Expression::AccessIndex { base, index })?;
+                }
+                Ok(base)
+            }
+        }
+    }
+}
+
+/// Reinterpret the integer `expr` of the signedness `from` as having the
+/// signedness `to`, if they differ.
+///
+/// Converting between integers of the same `width` keeps their bits.
+fn cast_integer<E>(
+    append: &mut impl FnMut(crate::Expression) -> Result<Handle<crate::Expression>, E>,
+    expr: Handle<crate::Expression>,
+    from: Option<crate::ScalarKind>,
+    to: Option<crate::ScalarKind>,
+    width: crate::Bytes,
+) -> Result<Handle<crate::Expression>, E> {
+    use crate::ScalarKind as Sk;
+    match (from, to) {
+        (Some(from @ (Sk::Sint | Sk::Uint)), Some(kind @ (Sk::Sint | Sk::Uint)))
+            if from != kind =>
+        {
+            append(crate::Expression::As {
+                expr,
+                kind,
+                convert: Some(width),
+            })
+        }
+        _ => Ok(expr),
+    }
+}
+
 fn resolve_constant(
     gctx: crate::proc::GlobalCtx,
     constant: Handle<crate::Constant>,
 ) -> Option<u32> {
-    match gctx.const_expressions[gctx.constants[constant].init] {
+    resolve_const_expression(gctx, gctx.constants[constant].init)
+}
+
+fn resolve_const_expression(
+    gctx: crate::proc::GlobalCtx,
+    expr: Handle<crate::Expression>,
+) -> Option<u32> {
+    match gctx.const_expressions[expr] {
         crate::Expression::Literal(crate::Literal::U32(id)) => Some(id),
         crate::Expression::Literal(crate::Literal::I32(id)) => Some(id as u32),
         _ => None,
@@ -5344,6 +6091,548 @@ mod test {
         ];
         let _ = super::parse_u8_slice(&bin, &Default::default()).unwrap();
     }
+
+    #[test]
+    fn spec_constant_op() {
+        use spirv::Op;
+
+        let mut words = vec![spirv::MAGIC_NUMBER, 0x0001_0000, 0, 12, 0];
+        let mut inst = |op: Op, operands: &[u32]| {
+            words.push(((operands.len() as u32 + 1) << 16) | op as u32);
+            words.extend_from_slice(operands);
+        };
+        inst(Op::Capability, &[spirv::Capability::Shader as u32]);
+        inst(Op::MemoryModel, &[0, 1]);
+        inst(Op::Decorate, &[3, spirv::Decoration::SpecId as u32, 0]);
+        inst(Op::TypeInt, &[1, 32, 0]);
+        inst(Op::TypeInt, &[2, 32, 1]);
+        // %3 = OpSpecConstant %int 4
+        inst(Op::SpecConstant, &[2, 3, 4]);
+        inst(Op::Constant, &[1, 4, 2]);
+        // %5 = OpSpecConstantOp %uint IAdd %3 %4
+        inst(Op::SpecConstantOp, &[1, 5, Op::IAdd as u32, 3, 4]);
+        inst(Op::TypeFloat, &[6, 32]);
+        inst(Op::TypeArray, &[7, 6, 5]);
+        inst(Op::TypeBool, &[8]);
+        inst(Op::SpecConstantFalse, &[8, 9]);
+        // %10 = OpSpecConstantOp %uint Select %9 %5 %4
+        inst(Op::SpecConstantOp, &[1, 10, Op::Select as u32, 9, 5, 4]);
+        // %11 = OpSpecConstantOp %int ShiftLeftLogical %3 %4
+        inst(
+            Op::SpecConstantOp,
+            &[2, 11, Op::ShiftLeftLogical as u32, 3, 4],
+        );
+        let bytes: Vec<u8> = words.iter().flat_map(|word| word.to_le_bytes()).collect();
+
+        let module = super::parse_u8_slice(&bytes, &Default::default()).unwrap();
+        crate::valid::Validator::new(
+            crate::valid::ValidationFlags::all(),
+            crate::valid::Capabilities::default(),
+        )
+        .validate(&module)
+        .unwrap();
+
+        let overrides: Vec<_> = module
+            .constants
+            .iter()
+            .map(|(_, constant)| &constant.r#override)
+            .collect();
+        assert_eq!(
+            overrides,
+            [
+                &crate::Override::ByNameOrId(0),
+                &crate::Override::None,
+                &crate::Override::Derived,
+                &crate::Override::None,
+                &crate::Override::Derived,
+                &crate::Override::Derived,
+            ]
+        );
+        let handles: Vec<_> = module.constants.iter().map(|(handle, _)| handle).collect();
+        let init = |index: usize| &module.const_expressions[module.constants[handles[index]].init];
+        let constant = |expr| match module.const_expressions[expr] {
+            crate::Expression::Constant(handle) => handles.iter().position(|&h| h == handle),
+            _ => None,
+        };
+        // `%5` converts `%3` to unsigned before adding `%4` to it.
+        match *init(2) {
+            crate::Expression::Binary {
+                op: crate::BinaryOperator::Add,
+                left,
+                right,
+            } => {
+                match module.const_expressions[left] {
+                    crate::Expression::As {
+                        expr,
+                        kind: crate::ScalarKind::Uint,
+                        convert: Some(4),
+                    } => assert_eq!(constant(expr), Some(0)),
+                    ref other => panic!("unexpected {other:?}"),
+                }
+                assert_eq!(constant(right), Some(1));
+            }
+            ref other => panic!("unexpected {other:?}"),
+        }
+        match *init(4) {
+            crate::Expression::Select {
+                condition,
+                accept,
+                reject,
+            } => {
+                assert_eq!(constant(condition), Some(3));
+                assert_eq!(constant(accept), Some(2));
+                assert_eq!(constant(reject), Some(1));
+            }
+            ref other => panic!("unexpected {other:?}"),
+        }
+        assert!(matches!(
+            *init(5),
+            crate::Expression::Binary {
+                op: crate::BinaryOperator::ShiftLeft,
+                ..
+            }
+        ));
+
+        // The array takes the default value of `%5`.
+        assert!(module.types.iter().any(|(_, ty)| matches!(
+            ty.inner,
+            crate::TypeInner::Array {
+                size: crate::ArraySize::Constant(size),
+                ..
+            } if size.get() == 6
+        )));
+    }
+
+    /// Write `fixture` as SPIR-V with `options`, and read it back.
+    #[cfg(all(feature = "test-util", feature = "spv-out"))]
+    fn round_trip(
+        fixture: &crate::test_util::Fixture,
+        options: &crate::back::spv::Options,
+    ) -> crate::Module {
+        let words = fixture.to_spv(options);
+        let bytes: Vec<u8> = words.iter().flat_map(|word| word.to_le_bytes()).collect();
+        super::parse_u8_slice(&bytes, &Default::default()).unwrap()
+    }
+
+    #[cfg(all(feature = "test-util", feature = "spv-out"))]
+    #[test]
+    fn non_semantic_instructions() {
+        use spirv::Op;
+
+        fn string_words(string: &str) -> Vec<u32> {
+            let mut bytes = string.as_bytes().to_vec();
+            bytes.resize((bytes.len() / 4 + 1) * 4, 0);
+            bytes
+                .chunks(4)
+                .map(|chunk| u32::from_le_bytes(chunk.try_into().unwrap()))
+                .collect()
+        }
+        fn instruction(op: Op, operands: &[u32]) -> Vec<u32> {
+            let mut words = vec![((operands.len() as u32 + 1) << 16) | op as u32];
+            words.extend_from_slice(operands);
+            words
+        }
+
+        let fixture = crate::test_util::Fixture::wgsl(
+            "
+            @fragment
+            fn main() -> @location(0) vec4<f32> {
+                return vec4(1.0);
+            }
+            ",
+        );
+        let words = fixture.to_spv(&Default::default());
+
+        // Split the module into instructions, and add debug info from
+        // `NonSemantic.Shader.DebugInfo.100` both among the global
+        // declarations and in the function body.
+        let (header, mut rest) = words.split_at(5);
+        let mut instructions = Vec::new();
+        while let Some(&first) = rest.first() {
+            let (inst, tail) = rest.split_at((first >> 16) as usize);
+            instructions.push(inst.to_vec());
+            rest = tail;
+        }
+        let opcode = |inst: &Vec<u32>| inst[0] & 0xffff;
+        let mut bound = header[3];
+        let mut next_id = || {
+            bound += 1;
+            bound - 1
+        };
+        let set_id = next_id();
+        let void_id = instructions
+            .iter()
+            .find(|inst| opcode(inst) == Op::TypeVoid as u32)
+            .unwrap()[1];
+        let extensions = instructions
+            .iter()
+            .position(|inst| opcode(inst) != Op::Capability as u32)
+            .unwrap();
+        let mut operands = vec![set_id];
+        operands.extend(string_words("NonSemantic.Shader.DebugInfo.100"));
+        instructions.insert(extensions, instruction(Op::ExtInstImport, &operands));
+        instructions.insert(
+            extensions,
+            instruction(Op::Extension, &string_words("SPV_KHR_non_semantic_info")),
+        );
+        let function = instructions
+            .iter()
+            .position(|inst| opcode(inst) == Op::Function as u32)
+            .unwrap();
+        // DebugInfoNone
+        let global_debug_info = instruction(Op::ExtInst, &[void_id, next_id(), set_id, 0]);
+        instructions.insert(function, global_debug_info);
+        let label = instructions
+            .iter()
+            .position(|inst| opcode(inst) == Op::Label as u32)
+            .unwrap();
+        let body = label
+            + 1
+            + instructions[label + 1..]
+                .iter()
+                .position(|inst| opcode(inst) != Op::Variable as u32)
+                .unwrap();
+        // DebugNoScope
+        let local_debug_info = instruction(Op::ExtInst, &[void_id, next_id(), set_id, 24]);
+        instructions.insert(body, local_debug_info);
+
+        let mut header = header.to_vec();
+        header[3] = bound;
+        let bytes: Vec<u8> = header
+            .into_iter()
+            .chain(instructions.into_iter().flatten())
+            .flat_map(|word| word.to_le_bytes())
+            .collect();
+
+        let mut options = super::Options::default();
+        assert!(matches!(
+            super::parse_u8_slice(&bytes, &options),
+            Err(super::Error::UnsupportedExtension(ref name))
+                if name == "SPV_KHR_non_semantic_info"
+        ));
+        options.ignore_non_semantic_instructions = true;
+        let module = super::parse_u8_slice(&bytes, &options).unwrap();
+        crate::valid::Validator::new(
+            crate::valid::ValidationFlags::all(),
+            crate::valid::Capabilities::default(),
+        )
+        .validate(&module)
+        .unwrap();
+    }
+
+    #[cfg(all(feature = "test-util", feature = "spv-out"))]
+    #[test]
+    fn f16_round_trip() {
+        let fixture = crate::test_util::Fixture::wgsl_with_capabilities(
+            "
+            enable f16;
+
+            var<private> v: vec3<f16>;
+
+            @compute @workgroup_size(1)
+            fn main() {
+                v = vec3h(1.5h) * f16(2.0);
+            }
+            ",
+            crate::valid::Capabilities::FLOAT16,
+        );
+        let module = round_trip(&fixture, &Default::default());
+        assert!(module.types.iter().any(|(_, ty)| matches!(
+            ty.inner,
+            crate::TypeInner::Vector {
+                kind: crate::ScalarKind::Float,
+                width: 2,
+                ..
+            }
+        )));
+    }
+
+    #[cfg(all(feature = "test-util", feature = "spv-out"))]
+    #[test]
+    fn int64_round_trip() {
+        let fixture = crate::test_util::Fixture::wgsl_with_capabilities(
+            "
+            var<private> v: vec2<i64>;
+
+            @compute @workgroup_size(1)
+            fn main() {
+                v = vec2(1li, v.y) * 3li;
+            }
+            ",
+            crate::valid::Capabilities::SHADER_INT64,
+        );
+        let module = round_trip(&fixture, &Default::default());
+        assert!(module.types.iter().any(|(_, ty)| matches!(
+            ty.inner,
+            crate::TypeInner::Vector {
+                kind: crate::ScalarKind::Sint,
+                width: 8,
+                ..
+            }
+        )));
+    }
+
+    // SPIR-V's `ModfStruct` and `FrexpStruct` come back as the same
+    // built-ins.
+    #[cfg(all(feature = "test-util", feature = "spv-out", feature = "wgsl-out"))]
+    #[test]
+    fn modf_frexp_round_trip() {
+        let fixture = crate::test_util::Fixture::wgsl(
+            "
+            @group(0) @binding(0) var<storage, read_write> out: vec4<f32>;
+
+            @compute @workgroup_size(1)
+            fn main() {
+                let m = modf(out.xy);
+                let f = frexp(out.z);
+                out = vec4(m.fract + m.whole, f.fract, f32(f.exp));
+            }
+            ",
+        );
+        let module = round_trip(&fixture, &Default::default());
+        let info = crate::valid::Validator::new(
+            crate::valid::ValidationFlags::all(),
+            crate::valid::Capabilities::default(),
+        )
+        .validate(&module)
+        .unwrap();
+        let wgsl = crate::back::wgsl::write_string(
+            &module,
+            &info,
+            crate::back::wgsl::WriterFlags::empty(),
+        )
+        .unwrap();
+        assert!(wgsl.contains("modf("), "{wgsl}");
+        assert!(wgsl.contains("frexp("), "{wgsl}");
+    }
+
+    #[cfg(all(feature = "test-util", feature = "spv-out"))]
+    #[test]
+    fn subgroup_round_trip() {
+        let fixture = crate::test_util::Fixture::wgsl_with_capabilities(
+            "
+            enable subgroups;
+
+            @group(0) @binding(0) var<storage, read_write> out: array<u32, 7>;
+
+            @compute @workgroup_size(64)
+            fn main(@builtin(local_invocation_index) index: u32) {
+                out[0] = subgroupBallot(index < 16u).x;
+                out[1] = u32(subgroupAll(index < 64u) && subgroupAny(index == 0u));
+                out[2] = subgroupAdd(index) + subgroupMax(index) + subgroupXor(index);
+                out[3] = subgroupInclusiveAdd(index) + subgroupExclusiveMul(index);
+                out[4] = subgroupBroadcastFirst(index) + subgroupBroadcast(index, 1u);
+                out[5] = subgroupShuffle(index, index ^ 1u) + subgroupShuffleXor(index, 1u);
+                out[6] = subgroupShuffleDown(index, 1u) + subgroupShuffleUp(index, 1u);
+            }
+            ",
+            crate::valid::Capabilities::SUBGROUP,
+        );
+        let module = round_trip(
+            &fixture,
+            &crate::back::spv::Options {
+                lang_version: (1, 3),
+                ..Default::default()
+            },
+        );
+        // The SPIR-V front end moves entry point bodies into functions.
+        let count = |module: &crate::Module| {
+            module
+                .functions
+                .iter()
+                .map(|(_, function)| function)
+                .chain(module.entry_points.iter().map(|ep| &ep.function))
+                .flat_map(|function| function.expressions.iter())
+                .filter(|&(_, expr)| matches!(*expr, crate::Expression::SubgroupOperation { .. }))
+                .count()
+        };
+        assert_eq!(count(&module), count(&fixture.module));
+        crate::valid::Validator::new(
+            crate::valid::ValidationFlags::all(),
+            crate::valid::Capabilities::SUBGROUP,
+        )
+        .validate(&module)
+        .unwrap();
+    }
+
+    #[cfg(all(feature = "test-util", feature = "spv-out"))]
+    #[test]
+    fn repeated_array_elements_round_trip() {
+        let fixture = crate::test_util::Fixture::wgsl(
+            "
+            const ones = array<i32, 4>(1, 1, 1, 1);
+
+            @compute @workgroup_size(1)
+            fn main() {
+                var a = ones;
+            }
+            ",
+        );
+        let module = round_trip(&fixture, &Default::default());
+        assert!(module
+            .const_expressions
+            .iter()
+            .any(|(_, expr)| matches!(*expr, crate::Expression::Repeat { .. })));
+    }
+
+    // The `NonUniform` decoration comes back as a `NonUniform` expression.
+    #[cfg(all(feature = "test-util", feature = "spv-out"))]
+    #[test]
+    fn non_uniform_round_trip() {
+        let fixture = crate::test_util::Fixture::wgsl_with_capabilities(
+            "
+            enable nonuniform_indexing;
+
+            @group(0) @binding(0) var textures: binding_array<texture_2d<f32>, 4>;
+            @group(0) @binding(1) var samp: sampler;
+            @group(0) @binding(2) var<uniform> which: u32;
+
+            @fragment
+            fn main(@location(0) uv: vec2<f32>) -> @location(0) vec4<f32> {
+                return textureSample(textures[nonUniform(which)], samp, uv);
+            }
+            ",
+            crate::valid::Capabilities::SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING,
+        );
+        let module = round_trip(&fixture, &Default::default());
+        let function = &module.entry_points[0].function;
+        assert!(function
+            .expressions
+            .iter()
+            .any(|(_, expr)| matches!(*expr, crate::Expression::NonUniform(_))));
+    }
+
+    #[cfg(all(feature = "test-util", feature = "spv-out"))]
+    #[test]
+    fn coherent_round_trip() {
+        let fixture = crate::test_util::Fixture::wgsl(
+            "
+            @coherent @group(0) @binding(0) var<storage, read_write> data: array<u32>;
+            @group(0) @binding(1) var<storage, read_write> plain: array<u32>;
+            @coherent @group(0) @binding(2) var image: texture_storage_2d<r32uint, write>;
+
+            @compute @workgroup_size(64)
+            fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+                data[id.x] = plain[id.x];
+                plain[id.x] = data[id.x + 1u];
+                textureStore(image, vec2<i32>(id.xy), vec4<u32>(data[id.x]));
+            }
+            ",
+        );
+        let module = round_trip(&fixture, &Default::default());
+        let coherent: Vec<_> = module
+            .global_variables
+            .iter()
+            .map(|(_, global)| match global.space {
+                crate::AddressSpace::Storage { access } => {
+                    access.contains(crate::StorageAccess::COHERENT)
+                }
+                _ => match module.types[global.ty].inner {
+                    crate::TypeInner::Image {
+                        class: crate::ImageClass::Storage { access, .. },
+                        ..
+                    } => access.contains(crate::StorageAccess::COHERENT),
+                    _ => false,
+                },
+            })
+            .collect();
+        assert_eq!(coherent, [true, false, true]);
+    }
+
+    #[test]
+    fn coherent_block_members() {
+        use spirv::{Decoration, Op};
+
+        // Parse a `BufferBlock` with two members, of which `coherent_members`
+        // are decorated `Coherent`, and return whether the buffer is coherent.
+        let coherent = |coherent_members: &[u32]| {
+            let mut words = vec![spirv::MAGIC_NUMBER, 0x0001_0000, 0, 5, 0];
+            let mut inst = |op: Op, operands: &[u32]| {
+                words.push(((operands.len() as u32 + 1) << 16) | op as u32);
+                words.extend_from_slice(operands);
+            };
+            inst(Op::Capability, &[spirv::Capability::Shader as u32]);
+            inst(Op::MemoryModel, &[0, 1]);
+            inst(Op::Decorate, &[2, Decoration::BufferBlock as u32]);
+            for member in 0..2 {
+                inst(
+                    Op::MemberDecorate,
+                    &[2, member, Decoration::Offset as u32, member * 4],
+                );
+            }
+            for &member in coherent_members {
+                inst(
+                    Op::MemberDecorate,
+                    &[2, member, Decoration::Coherent as u32],
+                );
+            }
+            inst(Op::Decorate, &[4, Decoration::DescriptorSet as u32, 0]);
+            inst(Op::Decorate, &[4, Decoration::Binding as u32, 0]);
+            inst(Op::TypeInt, &[1, 32, 0]);
+            inst(Op::TypeStruct, &[2, 1, 1]);
+            let uniform = spirv::StorageClass::Uniform as u32;
+            inst(Op::TypePointer, &[3, uniform, 2]);
+            inst(Op::Variable, &[3, 4, uniform]);
+            let bytes: Vec<u8> = words.iter().flat_map(|word| word.to_le_bytes()).collect();
+
+            let module = super::parse_u8_slice(&bytes, &Default::default()).unwrap();
+            match module.global_variables.iter().next().unwrap().1.space {
+                crate::AddressSpace::Storage { access } => {
+                    access.contains(crate::StorageAccess::COHERENT)
+                }
+                ref other => panic!("expected a storage buffer, found {other:?}"),
+            }
+        };
+
+        // glslang decorates every member of a `coherent` block.
+        assert!(coherent(&[0, 1]));
+        // Coherence of a single member can't be recorded, and mustn't spread
+        // to the rest of the buffer.
+        assert!(!coherent(&[0]));
+        assert!(!coherent(&[]));
+    }
+
+    #[cfg(all(feature = "test-util", feature = "spv-out"))]
+    #[test]
+    fn image_atomics_round_trip() {
+        let fixture = crate::test_util::Fixture::wgsl_with_capabilities(
+            "
+            @group(0) @binding(0) var image: texture_storage_2d<r32uint, atomic>;
+            @group(0) @binding(1) var layers: texture_storage_2d_array<r32sint, atomic>;
+
+            @compute @workgroup_size(1)
+            fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+                textureAtomicMax(image, id.xy, 1u);
+                textureAtomicSub(layers, id.xy, 2, 3);
+            }
+            ",
+            crate::valid::Capabilities::TEXTURE_ATOMIC,
+        );
+        let module = round_trip(&fixture, &Default::default());
+        // The entry point calls a function holding the original body.
+        let atomics: Vec<_> = module
+            .functions
+            .iter()
+            .flat_map(|(_, function)| function.body.iter())
+            .filter_map(|stmt| match *stmt {
+                crate::Statement::ImageAtomic { fun, .. } => Some(fun),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            atomics,
+            [crate::AtomicFunction::Max, crate::AtomicFunction::Subtract]
+        );
+        for (_, var) in module.global_variables.iter() {
+            assert!(matches!(
+                module.types[var.ty].inner,
+                crate::TypeInner::Image {
+                    class: crate::ImageClass::Storage { access, .. },
+                    ..
+                } if access.contains(crate::StorageAccess::ATOMIC)
+            ));
+        }
+    }
 }
 
 /// Helper function to check if `child` is in the scope of `parent`
@@ -1,6 +1,6 @@
 use crate::front::wgsl::parse::lexer::Token;
 use crate::proc::{Alignment, ConstantEvaluatorError, ResolveError};
-use crate::{SourceLocation, Span};
+use crate::{SourceLocation, Span, Suggestion};
 use codespan_reporting::diagnostic::{Diagnostic, Label};
 use codespan_reporting::files::SimpleFile;
 use codespan_reporting::term;
@@ -14,6 +14,15 @@ pub struct ParseError {
     message: String,
     labels: Vec<(Span, Cow<'static, str>)>,
     notes: Vec<String>,
+    code: &'static str,
+    suggestions: Vec<Suggestion>,
+}
+
+/// The parts of a [`ParseError`] that are shown to the user.
+struct Description {
+    message: String,
+    labels: Vec<(Span, Cow<'static, str>)>,
+    notes: Vec<String>,
 }
 
 impl ParseError {
@@ -27,6 +36,35 @@ impl ParseError {
         &self.message
     }
 
+    /// Returns `self`, but with its spans referring to the file `source`.
+    pub(crate) fn with_source(mut self, source: crate::SourceId) -> Self {
+        for label in self.labels.iter_mut() {
+            label.0 = label.0.with_source(source);
+        }
+        for suggestion in self.suggestions.iter_mut() {
+            suggestion.span = suggestion.span.with_source(source);
+        }
+        self
+    }
+
+    /// Describes the error as a [`Diagnostic`], for tools that present errors
+    /// themselves.
+    ///
+    /// [`Diagnostic`]: crate::Diagnostic
+    pub fn to_diagnostic(&self) -> crate::Diagnostic {
+        crate::Diagnostic {
+            severity: crate::Severity::Error,
+            code: self.code,
+            message: self.message.clone(),
+            labels: self
+                .labels()
+                .map(|(span, label)| (span, label.to_string()))
+                .collect(),
+            notes: self.notes.clone(),
+            suggestions: self.suggestions.clone(),
+        }
+    }
+
     fn diagnostic(&self) -> Diagnostic<()> {
         let diagnostic = Diagnostic::error()
             .with_message(self.message.to_string())
@@ -76,6 +114,20 @@ impl ParseError {
         String::from_utf8(writer.into_inner()).unwrap()
     }
 
+    /// Emits a summary of the error to a string, rendered as `options` say.
+    pub fn emit_to_string_with_options(
+        &self,
+        source: &str,
+        path: &str,
+        options: &crate::EmitOptions,
+    ) -> String {
+        let mut diagnostic = self.to_diagnostic();
+        for note in diagnostic.notes.iter_mut() {
+            *note = format!("note: {note}");
+        }
+        options.emit_to_string(&[diagnostic], source, path)
+    }
+
     /// Returns a [`SourceLocation`] for the first label in the error message.
     pub fn location(&self, source: &str) -> Option<SourceLocation> {
         self.labels.get(0).map(|label| label.0.location(source))
@@ -122,8 +174,6 @@ pub enum NumberError {
     Invalid,
     #[error("numeric literal not representable by target type")]
     NotRepresentable,
-    #[error("unimplemented f16 type")]
-    UnimplementedF16,
 }
 
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -157,6 +207,14 @@ pub enum Error<'a> {
     InvalidForInitializer(Span),
     /// A break if appeared outside of a continuing block
     InvalidBreakIf(Span),
+    /// A `continue` statement skips over a declaration that the loop's
+    /// `continuing` block refers to.
+    ContinueBypassesDeclaration {
+        continue_span: Span,
+        name: &'a str,
+        decl_span: Span,
+        usage_span: Span,
+    },
     InvalidGatherComponent(Span),
     InvalidConstructorComponentType(Span, i32),
     InvalidIdentifierUnderscore(Span),
@@ -194,6 +252,36 @@ pub enum Error<'a> {
         span: Span,
         ty: InvalidAssignmentType,
     },
+    /// An assignment or increment/decrement stores to memory whose address
+    /// space or access mode does not permit writing.
+    InvalidStoreAccess {
+        /// The left-hand side of the assignment.
+        span: Span,
+
+        /// The address space of the reference being stored through.
+        space: crate::AddressSpace,
+
+        /// The name and type of the global variable being assigned to, if
+        /// the reference is rooted in one.
+        global: Option<(String, String)>,
+    },
+    /// An access mode was given for an address space that doesn't take one.
+    AccessModeNotAllowed {
+        /// The access mode.
+        span: Span,
+
+        /// The address space it was given for.
+        space: crate::AddressSpace,
+    },
+    /// The `@coherent` attribute was given for a variable that is neither a
+    /// storage buffer nor a storage texture.
+    CoherentNotAllowed {
+        /// The attribute.
+        span: Span,
+
+        /// The address space of the variable.
+        space: crate::AddressSpace,
+    },
     ReservedKeyword(Span),
     /// Redefinition of an identifier (used for both module-scope and local redefinitions).
     Redefinition {
@@ -243,10 +331,160 @@ pub enum Error<'a> {
     ExpectedPositiveArrayLength(Span),
     MissingWorkgroupSize(Span),
     ConstantEvaluatorError(ConstantEvaluatorError, Span),
+    /// A function needs more expressions than a Naga arena can hold.
+    TooManyExpressions(Span),
+    /// A module needs more types than a Naga arena can hold.
+    TooManyTypes(Span),
+    UnknownEnableExtension(Span),
+    /// Something was used that needs an `enable` directive the source
+    /// doesn't have.
+    EnableExtensionNotEnabled {
+        span: Span,
+        /// The name of the extension.
+        kind: &'static str,
+    },
+    /// Something only Naga accepts was used under the WebGPU [`Profile`].
+    ///
+    /// [`Profile`]: crate::valid::Profile
+    NotInWebGpu {
+        span: Span,
+        /// The word that introduced it.
+        kind: &'static str,
+    },
+    /// A `diagnostic` directive names a severity that doesn't exist.
+    UnknownDiagnosticSeverity(Span),
+    /// Two `diagnostic` directives give the same rule different severities.
+    ConflictingDiagnosticFilters {
+        /// The name of the rule.
+        rule: &'static str,
+        previous: Span,
+        current: Span,
+    },
 }
 
 impl<'a> Error<'a> {
     pub(crate) fn as_parse_error(&self, source: &'a str) -> ParseError {
+        let Description {
+            message,
+            labels,
+            notes,
+        } = self.describe(source);
+        ParseError {
+            message,
+            labels,
+            notes,
+            code: self.code(),
+            suggestions: self.suggestions(),
+        }
+    }
+
+    /// A short identifier for this kind of error.
+    ///
+    /// See [`Diagnostic::code`](crate::Diagnostic::code).
+    const fn code(&self) -> &'static str {
+        match *self {
+            Error::Unexpected(..) => "wgsl::unexpected",
+            Error::UnexpectedComponents(..) => "wgsl::unexpected-components",
+            Error::UnexpectedOperationInConstContext(..) => {
+                "wgsl::unexpected-operation-in-const-context"
+            }
+            Error::BadNumber(..) => "wgsl::bad-number",
+            Error::BadMatrixScalarKind(..) => "wgsl::bad-matrix-scalar-kind",
+            Error::BadAccessor(..) => "wgsl::bad-accessor",
+            Error::BadTexture(..) => "wgsl::bad-texture",
+            Error::BadTypeCast { .. } => "wgsl::bad-type-cast",
+            Error::BadTextureSampleType { .. } => "wgsl::bad-texture-sample-type",
+            Error::BadIncrDecrReferenceType(..) => "wgsl::bad-incr-decr-reference-type",
+            Error::InvalidResolve(..) => "wgsl::invalid-resolve",
+            Error::InvalidForInitializer(..) => "wgsl::invalid-for-initializer",
+            Error::InvalidBreakIf(..) => "wgsl::invalid-break-if",
+            Error::ContinueBypassesDeclaration { .. } => "wgsl::continue-bypasses-declaration",
+            Error::InvalidGatherComponent(..) => "wgsl::invalid-gather-component",
+            Error::InvalidConstructorComponentType(..) => {
+                "wgsl::invalid-constructor-component-type"
+            }
+            Error::InvalidIdentifierUnderscore(..) => "wgsl::invalid-identifier-underscore",
+            Error::ReservedIdentifierPrefix(..) => "wgsl::reserved-identifier-prefix",
+            Error::UnknownAddressSpace(..) => "wgsl::unknown-address-space",
+            Error::RepeatedAttribute(..) => "wgsl::repeated-attribute",
+            Error::UnknownAttribute(..) => "wgsl::unknown-attribute",
+            Error::UnknownBuiltin(..) => "wgsl::unknown-builtin",
+            Error::UnknownAccess(..) => "wgsl::unknown-access",
+            Error::UnknownIdent(..) => "wgsl::unknown-ident",
+            Error::UnknownScalarType(..) => "wgsl::unknown-scalar-type",
+            Error::UnknownType(..) => "wgsl::unknown-type",
+            Error::UnknownStorageFormat(..) => "wgsl::unknown-storage-format",
+            Error::UnknownConservativeDepth(..) => "wgsl::unknown-conservative-depth",
+            Error::SizeAttributeTooLow(..) => "wgsl::size-attribute-too-low",
+            Error::AlignAttributeTooLow(..) => "wgsl::align-attribute-too-low",
+            Error::NonPowerOfTwoAlignAttribute(..) => "wgsl::non-power-of-two-align-attribute",
+            Error::InconsistentBinding(..) => "wgsl::inconsistent-binding",
+            Error::TypeNotConstructible(..) => "wgsl::type-not-constructible",
+            Error::TypeNotInferrable(..) => "wgsl::type-not-inferrable",
+            Error::InitializationTypeMismatch { .. } => "wgsl::initialization-type-mismatch",
+            Error::MissingType(..) => "wgsl::missing-type",
+            Error::MissingAttribute(..) => "wgsl::missing-attribute",
+            Error::InvalidAtomicPointer(..) => "wgsl::invalid-atomic-pointer",
+            Error::InvalidAtomicOperandType(..) => "wgsl::invalid-atomic-operand-type",
+            Error::InvalidRayQueryPointer(..) => "wgsl::invalid-ray-query-pointer",
+            Error::Pointer(..) => "wgsl::pointer",
+            Error::NotPointer(..) => "wgsl::not-pointer",
+            Error::NotReference(..) => "wgsl::not-reference",
+            Error::InvalidAssignment { .. } => "wgsl::invalid-assignment",
+            Error::InvalidStoreAccess { .. } => "wgsl::invalid-store-access",
+            Error::AccessModeNotAllowed { .. } => "wgsl::access-mode-not-allowed",
+            Error::CoherentNotAllowed { .. } => "wgsl::coherent-not-allowed",
+            Error::ReservedKeyword(..) => "wgsl::reserved-keyword",
+            Error::Redefinition { .. } => "wgsl::redefinition",
+            Error::RecursiveDeclaration { .. } => "wgsl::recursive-declaration",
+            Error::CyclicDeclaration { .. } => "wgsl::cyclic-declaration",
+            Error::InvalidSwitchValue { .. } => "wgsl::invalid-switch-value",
+            Error::CalledEntryPoint(..) => "wgsl::called-entry-point",
+            Error::WrongArgumentCount { .. } => "wgsl::wrong-argument-count",
+            Error::FunctionReturnsVoid(..) => "wgsl::function-returns-void",
+            Error::InvalidWorkGroupUniformLoad(..) => "wgsl::invalid-work-group-uniform-load",
+            Error::Internal(..) => "wgsl::internal",
+            Error::ExpectedConstExprConcreteIntegerScalar(..) => {
+                "wgsl::expected-const-expr-concrete-integer-scalar"
+            }
+            Error::ExpectedNonNegative(..) => "wgsl::expected-non-negative",
+            Error::ExpectedPositiveArrayLength(..) => "wgsl::expected-positive-array-length",
+            Error::MissingWorkgroupSize(..) => "wgsl::missing-workgroup-size",
+            Error::ConstantEvaluatorError(..) => "wgsl::constant-evaluator-error",
+            Error::TooManyExpressions(..) => "wgsl::too-many-expressions",
+            Error::TooManyTypes(..) => "wgsl::too-many-types",
+            Error::UnknownEnableExtension(..) => "wgsl::unknown-enable-extension",
+            Error::EnableExtensionNotEnabled { .. } => "wgsl::enable-extension-not-enabled",
+            Error::NotInWebGpu { .. } => "wgsl::not-in-webgpu",
+            Error::UnknownDiagnosticSeverity(..) => "wgsl::unknown-diagnostic-severity",
+            Error::ConflictingDiagnosticFilters { .. } => "wgsl::conflicting-diagnostic-filters",
+        }
+    }
+
+    /// Changes to the source that would fix this error, if we know of any.
+    fn suggestions(&self) -> Vec<Suggestion> {
+        match *self {
+            Error::Unexpected(
+                span,
+                ExpectedToken::Token(Token::Separator(c) | Token::Paren(c)),
+            ) => {
+                let start = span.to_range().map_or(0, |range| range.start as u32);
+                vec![Suggestion {
+                    message: format!("insert '{c}'"),
+                    span: Span::new(start, start),
+                    replacement: c.to_string(),
+                }]
+            }
+            Error::EnableExtensionNotEnabled { kind, .. } => vec![Suggestion {
+                message: format!("enable `{kind}`"),
+                span: Span::new(0, 0),
+                replacement: format!("enable {kind};\n"),
+            }],
+            _ => Vec::new(),
+        }
+    }
+
+    fn describe(&self, source: &'a str) -> Description {
         match *self {
             Error::Unexpected(unexpected_span, expected) => {
                 let expected_str = match expected {
@@ -280,7 +518,7 @@ impl<'a> Error<'a> {
                     ExpectedToken::Variable => "variable access".to_string(),
                     ExpectedToken::Function => "function name".to_string(),
                 };
-                ParseError {
+                Description {
                     message: format!(
                         "expected {}, found '{}'",
                         expected_str, &source[unexpected_span],
@@ -289,22 +527,22 @@ impl<'a> Error<'a> {
                     notes: vec![],
                 }
             }
-            Error::UnexpectedComponents(bad_span) => ParseError {
+            Error::UnexpectedComponents(bad_span) => Description {
                 message: "unexpected components".to_string(),
                 labels: vec![(bad_span, "unexpected components".into())],
                 notes: vec![],
             },
-            Error::UnexpectedOperationInConstContext(span) => ParseError {
+            Error::UnexpectedOperationInConstContext(span) => Description {
                 message: "this operation is not supported in a const context".to_string(),
                 labels: vec![(span, "operation not supported here".into())],
                 notes: vec![],
             },
-            Error::BadNumber(bad_span, ref err) => ParseError {
+            Error::BadNumber(bad_span, ref err) => Description {
                 message: format!("{}: `{}`", err, &source[bad_span],),
                 labels: vec![(bad_span, err.to_string().into())],
                 notes: vec![],
             },
-            Error::BadMatrixScalarKind(span, kind, width) => ParseError {
+            Error::BadMatrixScalarKind(span, kind, width) => Description {
                 message: format!(
                     "matrix scalar type must be floating-point, but found `{}`",
                     kind.to_wgsl(width)
@@ -312,22 +550,22 @@ impl<'a> Error<'a> {
                 labels: vec![(span, "must be floating-point (e.g. `f32`)".into())],
                 notes: vec![],
             },
-            Error::BadAccessor(accessor_span) => ParseError {
+            Error::BadAccessor(accessor_span) => Description {
                 message: format!("invalid field accessor `{}`", &source[accessor_span],),
                 labels: vec![(accessor_span, "invalid accessor".into())],
                 notes: vec![],
             },
-            Error::UnknownIdent(ident_span, ident) => ParseError {
+            Error::UnknownIdent(ident_span, ident) => Description {
                 message: format!("no definition in scope for identifier: '{ident}'"),
                 labels: vec![(ident_span, "unknown identifier".into())],
                 notes: vec![],
             },
-            Error::UnknownScalarType(bad_span) => ParseError {
+            Error::UnknownScalarType(bad_span) => Description {
                 message: format!("unknown scalar type: '{}'", &source[bad_span]),
                 labels: vec![(bad_span, "unknown scalar type".into())],
-                notes: vec!["Valid scalar types are f32, f64, i32, u32, bool".into()],
+                notes: vec!["Valid scalar types are f16, f32, f64, i32, u32, bool".into()],
             },
-            Error::BadTextureSampleType { span, kind, width } => ParseError {
+            Error::BadTextureSampleType { span, kind, width } => Description {
                 message: format!(
                     "texture sample type must be one of f32, i32 or u32, but found {}",
                     kind.to_wgsl(width)
@@ -335,14 +573,14 @@ impl<'a> Error<'a> {
                 labels: vec![(span, "must be one of f32, i32 or u32".into())],
                 notes: vec![],
             },
-            Error::BadIncrDecrReferenceType(span) => ParseError {
+            Error::BadIncrDecrReferenceType(span) => Description {
                 message:
                     "increment/decrement operation requires reference type to be one of i32 or u32"
                         .to_string(),
                 labels: vec![(span, "must be a reference type of i32 or u32".into())],
                 notes: vec![],
             },
-            Error::BadTexture(bad_span) => ParseError {
+            Error::BadTexture(bad_span) => Description {
                 message: format!(
                     "expected an image, but found '{}' which is not an image",
                     &source[bad_span]
@@ -356,18 +594,18 @@ impl<'a> Error<'a> {
                 ref to_type,
             } => {
                 let msg = format!("cannot cast a {from_type} to a {to_type}");
-                ParseError {
+                Description {
                     message: msg.clone(),
                     labels: vec![(span, msg.into())],
                     notes: vec![],
                 }
             }
-            Error::InvalidResolve(ref resolve_error) => ParseError {
+            Error::InvalidResolve(ref resolve_error) => Description {
                 message: resolve_error.to_string(),
                 labels: vec![],
                 notes: vec![],
             },
-            Error::InvalidForInitializer(bad_span) => ParseError {
+            Error::InvalidForInitializer(bad_span) => Description {
                 message: format!(
                     "for(;;) initializer is not an assignment or a function call: '{}'",
                     &source[bad_span]
@@ -375,12 +613,32 @@ impl<'a> Error<'a> {
                 labels: vec![(bad_span, "not an assignment or function call".into())],
                 notes: vec![],
             },
-            Error::InvalidBreakIf(bad_span) => ParseError {
+            Error::InvalidBreakIf(bad_span) => Description {
                 message: "A break if is only allowed in a continuing block".to_string(),
                 labels: vec![(bad_span, "not in a continuing block".into())],
                 notes: vec![],
             },
-            Error::InvalidGatherComponent(bad_span) => ParseError {
+            Error::ContinueBypassesDeclaration {
+                continue_span,
+                name,
+                decl_span,
+                usage_span,
+            } => Description {
+                message: format!(
+                    "`continue` bypasses the declaration of `{name}`, \
+                     which is used in the `continuing` block"
+                ),
+                labels: vec![
+                    (continue_span, "this `continue` skips the declaration".into()),
+                    (decl_span, format!("`{name}` is declared here").into()),
+                    (usage_span, "and used in the `continuing` block here".into()),
+                ],
+                notes: vec![format!(
+                    "move the declaration of `{name}` before the `continue`, \
+                     or out of the loop body"
+                )],
+            },
+            Error::InvalidGatherComponent(bad_span) => Description {
                 message: format!(
                     "textureGather component '{}' doesn't exist, must be 0, 1, 2, or 3",
                     &source[bad_span]
@@ -388,12 +646,12 @@ impl<'a> Error<'a> {
                 labels: vec![(bad_span, "invalid component".into())],
                 notes: vec![],
             },
-            Error::InvalidConstructorComponentType(bad_span, component) => ParseError {
+            Error::InvalidConstructorComponentType(bad_span, component) => Description {
                 message: format!("invalid type for constructor component at index [{component}]"),
                 labels: vec![(bad_span, "invalid component type".into())],
                 notes: vec![],
             },
-            Error::InvalidIdentifierUnderscore(bad_span) => ParseError {
+            Error::InvalidIdentifierUnderscore(bad_span) => Description {
                 message: "Identifier can't be '_'".to_string(),
                 labels: vec![(bad_span, "invalid identifier".into())],
                 notes: vec![
@@ -401,7 +659,7 @@ impl<'a> Error<'a> {
                         .to_string(),
                 ],
             },
-            Error::ReservedIdentifierPrefix(bad_span) => ParseError {
+            Error::ReservedIdentifierPrefix(bad_span) => Description {
                 message: format!(
                     "Identifier starts with a reserved prefix: '{}'",
                     &source[bad_span]
@@ -409,78 +667,78 @@ impl<'a> Error<'a> {
                 labels: vec![(bad_span, "invalid identifier".into())],
                 notes: vec![],
             },
-            Error::UnknownAddressSpace(bad_span) => ParseError {
+            Error::UnknownAddressSpace(bad_span) => Description {
                 message: format!("unknown address space: '{}'", &source[bad_span]),
                 labels: vec![(bad_span, "unknown address space".into())],
                 notes: vec![],
             },
-            Error::RepeatedAttribute(bad_span) => ParseError {
+            Error::RepeatedAttribute(bad_span) => Description {
                 message: format!("repeated attribute: '{}'", &source[bad_span]),
                 labels: vec![(bad_span, "repeated attribute".into())],
                 notes: vec![],
             },
-            Error::UnknownAttribute(bad_span) => ParseError {
+            Error::UnknownAttribute(bad_span) => Description {
                 message: format!("unknown attribute: '{}'", &source[bad_span]),
                 labels: vec![(bad_span, "unknown attribute".into())],
                 notes: vec![],
             },
-            Error::UnknownBuiltin(bad_span) => ParseError {
+            Error::UnknownBuiltin(bad_span) => Description {
                 message: format!("unknown builtin: '{}'", &source[bad_span]),
                 labels: vec![(bad_span, "unknown builtin".into())],
                 notes: vec![],
             },
-            Error::UnknownAccess(bad_span) => ParseError {
+            Error::UnknownAccess(bad_span) => Description {
                 message: format!("unknown access: '{}'", &source[bad_span]),
                 labels: vec![(bad_span, "unknown access".into())],
                 notes: vec![],
             },
-            Error::UnknownStorageFormat(bad_span) => ParseError {
+            Error::UnknownStorageFormat(bad_span) => Description {
                 message: format!("unknown storage format: '{}'", &source[bad_span]),
                 labels: vec![(bad_span, "unknown storage format".into())],
                 notes: vec![],
             },
-            Error::UnknownConservativeDepth(bad_span) => ParseError {
+            Error::UnknownConservativeDepth(bad_span) => Description {
                 message: format!("unknown conservative depth: '{}'", &source[bad_span]),
                 labels: vec![(bad_span, "unknown conservative depth".into())],
                 notes: vec![],
             },
-            Error::UnknownType(bad_span) => ParseError {
+            Error::UnknownType(bad_span) => Description {
                 message: format!("unknown type: '{}'", &source[bad_span]),
                 labels: vec![(bad_span, "unknown type".into())],
                 notes: vec![],
             },
-            Error::SizeAttributeTooLow(bad_span, min_size) => ParseError {
+            Error::SizeAttributeTooLow(bad_span, min_size) => Description {
                 message: format!("struct member size must be at least {min_size}"),
                 labels: vec![(bad_span, format!("must be at least {min_size}").into())],
                 notes: vec![],
             },
-            Error::AlignAttributeTooLow(bad_span, min_align) => ParseError {
+            Error::AlignAttributeTooLow(bad_span, min_align) => Description {
                 message: format!("struct member alignment must be at least {min_align}"),
                 labels: vec![(bad_span, format!("must be at least {min_align}").into())],
                 notes: vec![],
             },
-            Error::NonPowerOfTwoAlignAttribute(bad_span) => ParseError {
+            Error::NonPowerOfTwoAlignAttribute(bad_span) => Description {
                 message: "struct member alignment must be a power of 2".to_string(),
                 labels: vec![(bad_span, "must be a power of 2".into())],
                 notes: vec![],
             },
-            Error::InconsistentBinding(span) => ParseError {
+            Error::InconsistentBinding(span) => Description {
                 message: "input/output binding is not consistent".to_string(),
                 labels: vec![(span, "input/output binding is not consistent".into())],
                 notes: vec![],
             },
-            Error::TypeNotConstructible(span) => ParseError {
+            Error::TypeNotConstructible(span) => Description {
                 message: format!("type `{}` is not constructible", &source[span]),
                 labels: vec![(span, "type is not constructible".into())],
                 notes: vec![],
             },
-            Error::TypeNotInferrable(span) => ParseError {
+            Error::TypeNotInferrable(span) => Description {
                 message: "type can't be inferred".to_string(),
                 labels: vec![(span, "type can't be inferred".into())],
                 notes: vec![],
             },
             Error::InitializationTypeMismatch { name, ref expected, ref got } => {
-                ParseError {
+                Description {
                     message: format!(
                         "the type of `{}` is expected to be `{}`, but got `{}`",
                         &source[name], expected, got,
@@ -492,7 +750,7 @@ impl<'a> Error<'a> {
                     notes: vec![],
                 }
             }
-            Error::MissingType(name_span) => ParseError {
+            Error::MissingType(name_span) => Description {
                 message: format!("variable `{}` needs a type", &source[name_span]),
                 labels: vec![(
                     name_span,
@@ -500,7 +758,7 @@ impl<'a> Error<'a> {
                 )],
                 notes: vec![],
             },
-            Error::MissingAttribute(name, name_span) => ParseError {
+            Error::MissingAttribute(name, name_span) => Description {
                 message: format!(
                     "variable `{}` needs a '{}' attribute",
                     &source[name_span], name
@@ -511,27 +769,27 @@ impl<'a> Error<'a> {
                 )],
                 notes: vec![],
             },
-            Error::InvalidAtomicPointer(span) => ParseError {
+            Error::InvalidAtomicPointer(span) => Description {
                 message: "atomic operation is done on a pointer to a non-atomic".to_string(),
                 labels: vec![(span, "atomic pointer is invalid".into())],
                 notes: vec![],
             },
-            Error::InvalidAtomicOperandType(span) => ParseError {
+            Error::InvalidAtomicOperandType(span) => Description {
                 message: "atomic operand type is inconsistent with the operation".to_string(),
                 labels: vec![(span, "atomic operand type is invalid".into())],
                 notes: vec![],
             },
-            Error::InvalidRayQueryPointer(span) => ParseError {
+            Error::InvalidRayQueryPointer(span) => Description {
                 message: "ray query operation is done on a pointer to a non-ray-query".to_string(),
                 labels: vec![(span, "ray query pointer is invalid".into())],
                 notes: vec![],
             },
-            Error::NotPointer(span) => ParseError {
+            Error::NotPointer(span) => Description {
                 message: "the operand of the `*` operator must be a pointer".to_string(),
                 labels: vec![(span, "expression is not a pointer".into())],
                 notes: vec![],
             },
-            Error::NotReference(what, span) => ParseError {
+            Error::NotReference(what, span) => Description {
                 message: format!("{what} must be a reference"),
                 labels: vec![(span, "expression is not a reference".into())],
                 notes: vec![],
@@ -555,7 +813,7 @@ impl<'a> Error<'a> {
                     InvalidAssignmentType::Other => (None, vec![]),
                 };
 
-                ParseError {
+                Description {
                     message: "invalid left-hand side of assignment".into(),
                     labels: std::iter::once((span, "cannot assign to this expression".into()))
                         .chain(extra_label)
@@ -563,12 +821,68 @@ impl<'a> Error<'a> {
                     notes,
                 }
             }
-            Error::Pointer(what, span) => ParseError {
+            Error::InvalidStoreAccess {
+                span,
+                space,
+                ref global,
+            } => {
+                let label = match space {
+                    crate::AddressSpace::Storage { .. } => {
+                        "declared with `read` access, but assignment requires `read_write`".into()
+                    }
+                    _ => format!(
+                        "the `{}` address space is read-only",
+                        address_space_str(space)
+                    )
+                    .into(),
+                };
+                let notes = match *global {
+                    Some((ref name, ref ty)) => vec![
+                        format!(
+                            "`{name}` is declared as `{} {name}: {ty}`",
+                            var_decl_str(space)
+                        ),
+                        format!(
+                            "consider declaring it as `var<storage, read_write> {name}: {ty}`"
+                        ),
+                    ],
+                    None => vec![],
+                };
+                Description {
+                    message: format!(
+                        "cannot assign to read-only memory in the `{}` address space",
+                        address_space_str(space)
+                    ),
+                    labels: vec![(span, label)],
+                    notes,
+                }
+            }
+            Error::AccessModeNotAllowed { span, space } => Description {
+                message: format!(
+                    "access mode `{}` cannot be specified for the `{}` address space",
+                    &source[span],
+                    address_space_str(space)
+                ),
+                labels: vec![(span, "access mode not allowed here".into())],
+                notes: vec![
+                    "only variables in the `storage` address space take an access mode".into(),
+                    format!("consider declaring it as `{}`", var_decl_str(space)),
+                ],
+            },
+            Error::CoherentNotAllowed { span, space } => Description {
+                message: format!(
+                    "the `@coherent` attribute cannot be used in the `{}` address space",
+                    address_space_str(space)
+                ),
+                labels: vec![(span, "attribute not allowed here".into())],
+                notes: vec!["only storage buffers and storage textures can be coherent".into()],
+            },
+            Error::Pointer(what, span) => Description {
                 message: format!("{what} must not be a pointer"),
                 labels: vec![(span, "expression is a pointer".into())],
                 notes: vec![],
             },
-            Error::ReservedKeyword(name_span) => ParseError {
+            Error::ReservedKeyword(name_span) => Description {
                 message: format!("name `{}` is a reserved keyword", &source[name_span]),
                 labels: vec![(
                     name_span,
@@ -576,7 +890,7 @@ impl<'a> Error<'a> {
                 )],
                 notes: vec![],
             },
-            Error::Redefinition { previous, current } => ParseError {
+            Error::Redefinition { previous, current } => Description {
                 message: format!("redefinition of `{}`", &source[current]),
                 labels: vec![
                     (
@@ -590,12 +904,12 @@ impl<'a> Error<'a> {
                 ],
                 notes: vec![],
             },
-            Error::RecursiveDeclaration { ident, usage } => ParseError {
+            Error::RecursiveDeclaration { ident, usage } => Description {
                 message: format!("declaration of `{}` is recursive", &source[ident]),
                 labels: vec![(ident, "".into()), (usage, "uses itself here".into())],
                 notes: vec![],
             },
-            Error::CyclicDeclaration { ident, ref path } => ParseError {
+            Error::CyclicDeclaration { ident, ref path } => Description {
                 message: format!("declaration of `{}` is cyclic", &source[ident]),
                 labels: path
                     .iter()
@@ -616,7 +930,7 @@ impl<'a> Error<'a> {
                     .collect(),
                 notes: vec![],
             },
-            Error::InvalidSwitchValue { uint, span } => ParseError {
+            Error::InvalidSwitchValue { uint, span } => Description {
                 message: "invalid switch value".to_string(),
                 labels: vec![(
                     span,
@@ -637,7 +951,7 @@ impl<'a> Error<'a> {
                     )
                 }],
             },
-            Error::CalledEntryPoint(span) => ParseError {
+            Error::CalledEntryPoint(span) => Description {
                 message: "entry point cannot be called".to_string(),
                 labels: vec![(span, "entry point cannot be called".into())],
                 notes: vec![],
@@ -646,7 +960,7 @@ impl<'a> Error<'a> {
                 span,
                 ref expected,
                 found,
-            } => ParseError {
+            } => Description {
                 message: format!(
                     "wrong number of arguments: expected {}, found {}",
                     if expected.len() < 2 {
@@ -659,44 +973,95 @@ impl<'a> Error<'a> {
                 labels: vec![(span, "wrong number of arguments".into())],
                 notes: vec![],
             },
-            Error::FunctionReturnsVoid(span) => ParseError {
+            Error::FunctionReturnsVoid(span) => Description {
                 message: "function does not return any value".to_string(),
                 labels: vec![(span, "".into())],
                 notes: vec![
                     "perhaps you meant to call the function in a separate statement?".into(),
                 ],
             },
-            Error::InvalidWorkGroupUniformLoad(span) => ParseError {
+            Error::InvalidWorkGroupUniformLoad(span) => Description {
                 message: "incorrect type passed to workgroupUniformLoad".into(),
                 labels: vec![(span, "".into())],
                 notes: vec!["passed type must be a workgroup pointer".into()],
             },
-            Error::Internal(message) => ParseError {
+            Error::Internal(message) => Description {
                 message: "internal WGSL front end error".to_string(),
                 labels: vec![],
                 notes: vec![message.into()],
             },
-            Error::ExpectedConstExprConcreteIntegerScalar(span) => ParseError {
+            Error::ExpectedConstExprConcreteIntegerScalar(span) => Description {
                 message: "must be a const-expression that resolves to a concrete integer scalar (u32 or i32)".to_string(),
                 labels: vec![(span, "must resolve to u32 or i32".into())],
                 notes: vec![],
             },
-            Error::ExpectedNonNegative(span) => ParseError {
+            Error::ExpectedNonNegative(span) => Description {
                 message: "must be non-negative (>= 0)".to_string(),
                 labels: vec![(span, "must be non-negative".into())],
                 notes: vec![],
             },
-            Error::ExpectedPositiveArrayLength(span) => ParseError {
+            Error::ExpectedPositiveArrayLength(span) => Description {
                 message: "array element count must be positive (> 0)".to_string(),
                 labels: vec![(span, "must be positive".into())],
                 notes: vec![],
             },
-            Error::ConstantEvaluatorError(ref e, span) => ParseError {
+            Error::UnknownEnableExtension(span) => Description {
+                message: format!("unknown enable-extension `{}`", &source[span]),
+                labels: vec![(span, "unknown enable-extension".into())],
+                notes: vec![
+                    "Naga supports the `f16`, `subgroups` and `nonuniform_indexing` enable-extensions"
+                        .into(),
+                ],
+            },
+            Error::EnableExtensionNotEnabled { span, kind } => Description {
+                message: format!("the `{kind}` enable-extension is not enabled"),
+                labels: vec![(span, format!("this needs the `{kind}` enable-extension").into())],
+                notes: vec![format!("add `enable {kind};` at the start of the shader")],
+            },
+            Error::NotInWebGpu { span, kind } => Description {
+                message: format!("`{kind}` is not part of WebGPU"),
+                labels: vec![(span, "only native applications may use this".into())],
+                notes: vec!["this shader is being parsed under the WebGPU profile".into()],
+            },
+            Error::UnknownDiagnosticSeverity(span) => Description {
+                message: format!("unknown diagnostic severity `{}`", &source[span]),
+                labels: vec![(span, "unknown severity".into())],
+                notes: vec!["the severities are `error`, `warning`, `info` and `off`".into()],
+            },
+            Error::ConflictingDiagnosticFilters {
+                rule,
+                previous,
+                current,
+            } => Description {
+                message: format!("conflicting diagnostic filters for `{rule}`"),
+                labels: vec![
+                    (current, "but this gives it another".into()),
+                    (previous, format!("this gives `{rule}` one severity").into()),
+                ],
+                notes: vec![],
+            },
+            Error::TooManyExpressions(span) => Description {
+                message: "function is too large".to_string(),
+                labels: vec![(span, "no room for this expression".into())],
+                notes: vec![format!(
+                    "a function can contain at most {} expressions",
+                    u32::MAX
+                )],
+            },
+            Error::TooManyTypes(span) => Description {
+                message: "module has too many types".to_string(),
+                labels: vec![(span, "no room for this type".into())],
+                notes: vec![format!(
+                    "a module can contain at most {} types",
+                    u32::MAX
+                )],
+            },
+            Error::ConstantEvaluatorError(ref e, span) => Description {
                 message: e.to_string(),
                 labels: vec![(span, "see msg".into())],
                 notes: vec![],
             },
-            Error::MissingWorkgroupSize(span) => ParseError {
+            Error::MissingWorkgroupSize(span) => Description {
                 message: "workgroup size is missing on compute shader entry point".to_string(),
                 labels: vec![(
                     span,
@@ -707,3 +1072,35 @@ impl<'a> Error<'a> {
         }
     }
 }
+
+/// Returns the WGSL spelling of `space`, as it appears in `var<...>`.
+const fn address_space_str(space: crate::AddressSpace) -> &'static str {
+    match space {
+        crate::AddressSpace::Function => "function",
+        crate::AddressSpace::Private => "private",
+        crate::AddressSpace::WorkGroup => "workgroup",
+        crate::AddressSpace::Uniform => "uniform",
+        crate::AddressSpace::Storage { .. } => "storage",
+        crate::AddressSpace::Handle => "handle",
+        crate::AddressSpace::PushConstant => "push_constant",
+    }
+}
+
+/// Returns the `var<...>` prefix that declares a variable in `space`.
+fn var_decl_str(space: crate::AddressSpace) -> String {
+    match space {
+        crate::AddressSpace::Handle => "var".to_string(),
+        crate::AddressSpace::Storage { access } => {
+            let access =
+                if access.contains(crate::StorageAccess::LOAD | crate::StorageAccess::STORE) {
+                    "read_write"
+                } else if access.contains(crate::StorageAccess::STORE) {
+                    "write"
+                } else {
+                    "read"
+                };
+            format!("var<storage, {access}>")
+        }
+        _ => format!("var<{}>", address_space_str(space)),
+    }
+}
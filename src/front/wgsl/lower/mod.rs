@@ -357,9 +357,11 @@ impl<'source, 'temp, 'out> ExpressionContext<'source, 'temp, 'out> {
             // `expr` is not a constant expression. This is fine as
             // long as we're not building `Module::const_expressions`.
             Err(err) => match self.expr_type {
-                ExpressionContextType::Runtime(ref mut rctx) => {
-                    Ok(rctx.function.expressions.append(expr, span))
-                }
+                ExpressionContextType::Runtime(ref mut rctx) => rctx
+                    .function
+                    .expressions
+                    .try_append(expr, span)
+                    .map_err(|_| Error::TooManyExpressions(span)),
                 ExpressionContextType::Constant => Err(Error::ConstantEvaluatorError(err, span)),
             },
         }
@@ -633,6 +635,53 @@ impl<'source, 'temp, 'out> ExpressionContext<'source, 'temp, 'out> {
         }
     }
 
+    /// Check that `pointer`, the target of an assignment, permits stores.
+    ///
+    /// The validator rejects such stores too, but here we can still tell
+    /// which declaration is at fault and how to fix it.
+    fn check_store_access(
+        &mut self,
+        pointer: Handle<crate::Expression>,
+        span: Span,
+    ) -> Result<(), Error<'source>> {
+        let space = match *resolve_inner!(self, pointer) {
+            crate::TypeInner::Pointer { space, .. }
+            | crate::TypeInner::ValuePointer { space, .. } => space,
+            _ => return Ok(()),
+        };
+        if space.access().contains(crate::StorageAccess::STORE) {
+            return Ok(());
+        }
+
+        let mut global = None;
+        if let ExpressionContextType::Runtime(ref rctx) = self.expr_type {
+            let mut root = pointer;
+            loop {
+                match rctx.function.expressions[root] {
+                    crate::Expression::Access { base, .. }
+                    | crate::Expression::AccessIndex { base, .. } => root = base,
+                    crate::Expression::GlobalVariable(handle) => {
+                        global = Some(handle);
+                        break;
+                    }
+                    _ => break,
+                }
+            }
+        }
+        let global = global.and_then(|handle| {
+            let var = &self.module.global_variables[handle];
+            var.name
+                .clone()
+                .map(|name| (name, self.format_type(var.ty)))
+        });
+
+        Err(Error::InvalidStoreAccess {
+            span,
+            space,
+            global,
+        })
+    }
+
     fn format_typeinner(&self, inner: &crate::TypeInner) -> String {
         inner.to_wgsl(self.module.to_ctx())
     }
@@ -832,6 +881,10 @@ impl Texture {
 pub struct Lowerer<'source, 'temp> {
     index: &'temp Index<'source>,
     layouter: Layouter,
+    /// Whether the translation unit enables subgroup built-in functions.
+    enable_subgroups: bool,
+    /// Whether the translation unit enables the `nonUniform` built-in function.
+    enable_nonuniform_indexing: bool,
 }
 
 impl<'source, 'temp> Lowerer<'source, 'temp> {
@@ -839,6 +892,8 @@ impl<'source, 'temp> Lowerer<'source, 'temp> {
         Self {
             index,
             layouter: Layouter::default(),
+            enable_subgroups: false,
+            enable_nonuniform_indexing: false,
         }
     }
 
@@ -847,6 +902,11 @@ impl<'source, 'temp> Lowerer<'source, 'temp> {
         tu: &'temp ast::TranslationUnit<'source>,
     ) -> Result<crate::Module, Error<'source>> {
         let mut module = crate::Module::default();
+        self.enable_subgroups = tu.enable_subgroups;
+        self.enable_nonuniform_indexing = tu.enable_nonuniform_indexing;
+        module
+            .diagnostic_filters
+            .extend_from_slice(&tu.diagnostic_filters);
 
         let mut ctx = GlobalContext {
             ast_expressions: &tu.expressions,
@@ -866,7 +926,42 @@ impl<'source, 'temp> Lowerer<'source, 'temp> {
                     ctx.globals.insert(f.name.name, lowered_decl);
                 }
                 ast::GlobalDeclKind::Var(ref v) => {
-                    let ty = self.resolve_ast_type(v.ty, &mut ctx)?;
+                    let mut ty = self.resolve_ast_type(v.ty, &mut ctx)?;
+                    let mut space = v.space;
+                    if let Some(span) = v.coherent {
+                        // Storage textures record their access in their
+                        // type, so a coherent one needs a type of its own.
+                        match space {
+                            crate::AddressSpace::Storage { ref mut access } => {
+                                *access |= crate::StorageAccess::COHERENT;
+                            }
+                            crate::AddressSpace::Handle => match ctx.module.types[ty].inner {
+                                crate::TypeInner::Image {
+                                    dim,
+                                    arrayed,
+                                    class: crate::ImageClass::Storage { format, access },
+                                } => {
+                                    let ty_span = ctx.module.types.get_span(ty);
+                                    ty = ctx.module.types.insert(
+                                        crate::Type {
+                                            name: None,
+                                            inner: crate::TypeInner::Image {
+                                                dim,
+                                                arrayed,
+                                                class: crate::ImageClass::Storage {
+                                                    format,
+                                                    access: access | crate::StorageAccess::COHERENT,
+                                                },
+                                            },
+                                        },
+                                        ty_span,
+                                    );
+                                }
+                                _ => return Err(Error::CoherentNotAllowed { span, space }),
+                            },
+                            _ => return Err(Error::CoherentNotAllowed { span, space }),
+                        }
+                    }
 
                     let init = v
                         .init
@@ -885,7 +980,7 @@ impl<'source, 'temp> Lowerer<'source, 'temp> {
                     let handle = ctx.module.global_variables.append(
                         crate::GlobalVariable {
                             name: Some(v.name.name.to_string()),
-                            space: v.space,
+                            space,
                             binding,
                             ty,
                             init,
@@ -1194,6 +1289,7 @@ impl<'source, 'temp> Lowerer<'source, 'temp> {
                         },
                         stmt.span,
                     );
+                    block.declare_local(var);
 
                     let handle = ctx.as_expression(block, &mut emitter).interrupt_emitter(
                         crate::Expression::LocalVariable(var),
@@ -1294,7 +1390,9 @@ impl<'source, 'temp> Lowerer<'source, 'temp> {
                 let mut emitter = Emitter::default();
                 emitter.start(&ctx.function.expressions);
                 let break_if = break_if
-                    .map(|expr| self.expression(expr, &mut ctx.as_expression(block, &mut emitter)))
+                    .map(|expr| {
+                        self.expression(expr, &mut ctx.as_expression(&mut continuing, &mut emitter))
+                    })
                     .transpose()?;
                 continuing.extend(emitter.finish(&ctx.function.expressions));
 
@@ -1353,6 +1451,9 @@ impl<'source, 'temp> Lowerer<'source, 'temp> {
                         ty,
                     });
                 }
+                let target_span = ctx.ast_expressions.get_span(target);
+                ctx.as_expression(block, &mut emitter)
+                    .check_store_access(expr.handle, target_span)?;
 
                 let value = match op {
                     Some(op) => {
@@ -1391,6 +1492,7 @@ impl<'source, 'temp> Lowerer<'source, 'temp> {
                 let reference = self
                     .expression_for_reference(value, &mut ctx.as_expression(block, &mut emitter))?;
                 let mut ectx = ctx.as_expression(block, &mut emitter);
+                ectx.check_store_access(reference.handle, value_span)?;
 
                 let (kind, width) = match *resolve_inner!(ectx, reference.handle) {
                     crate::TypeInner::ValuePointer {
@@ -1437,8 +1539,15 @@ impl<'source, 'temp> Lowerer<'source, 'temp> {
                 let mut emitter = Emitter::default();
                 emitter.start(&ctx.function.expressions);
 
-                let _ = self.expression(expr, &mut ctx.as_expression(block, &mut emitter))?;
+                let value = self.expression(expr, &mut ctx.as_expression(block, &mut emitter))?;
                 block.extend(emitter.finish(&ctx.function.expressions));
+
+                // Naming the value makes the backends evaluate it, rather
+                // than dropping it as unused, and lets the analyzer count
+                // resources used only this way as used.
+                ctx.named_expressions
+                    .entry(value)
+                    .or_insert_with(|| ("phony".to_string(), stmt.span));
                 return Ok(());
             }
         };
@@ -1469,8 +1578,11 @@ impl<'source, 'temp> Lowerer<'source, 'temp> {
             ast::Expression::Literal(literal) => {
                 let literal = match literal {
                     ast::Literal::Number(Number::F32(f)) => crate::Literal::F32(f),
+                    ast::Literal::Number(Number::F16(f)) => crate::Literal::F16(f),
                     ast::Literal::Number(Number::I32(i)) => crate::Literal::I32(i),
                     ast::Literal::Number(Number::U32(u)) => crate::Literal::U32(u),
+                    ast::Literal::Number(Number::I64(i)) => crate::Literal::I64(i),
+                    ast::Literal::Number(Number::U64(u)) => crate::Literal::U64(u),
                     ast::Literal::Number(_) => {
                         unreachable!("got abstract numeric type when not expected");
                     }
@@ -1816,6 +1928,44 @@ impl<'source, 'temp> Lowerer<'source, 'temp> {
                     args.finish()?;
 
                     crate::Expression::Derivative { axis, ctrl, expr }
+                } else if let Some(fun) = conv::map_subgroup_function(function.name) {
+                    if !self.enable_subgroups {
+                        return Err(Error::EnableExtensionNotEnabled {
+                            span,
+                            kind: "subgroups",
+                        });
+                    }
+                    let expected = match fun {
+                        conv::SubgroupFunction::Unary(_) => 1,
+                        conv::SubgroupFunction::Indexed(_) => 2,
+                    };
+                    let mut args = ctx.prepare_args(arguments, expected, span);
+                    let argument = self.expression(args.next()?, ctx)?;
+                    let op = match fun {
+                        conv::SubgroupFunction::Unary(op) => op,
+                        conv::SubgroupFunction::Indexed(op) => {
+                            let index = self.expression(args.next()?, ctx)?;
+                            // WGSL accepts `i32` invocation IDs and offsets too.
+                            let index = match *resolve_inner!(ctx, index) {
+                                crate::TypeInner::Scalar {
+                                    kind: crate::ScalarKind::Sint,
+                                    width,
+                                } => ctx.append_expression(
+                                    crate::Expression::As {
+                                        expr: index,
+                                        kind: crate::ScalarKind::Uint,
+                                        convert: Some(width),
+                                    },
+                                    span,
+                                )?,
+                                _ => index,
+                            };
+                            op(index)
+                        }
+                    };
+                    args.finish()?;
+
+                    crate::Expression::SubgroupOperation { op, argument }
                 } else if let Some(fun) = conv::map_standard_fun(function.name) {
                     let expected = fun.argument_count() as _;
                     let mut args = ctx.prepare_args(arguments, expected, span);
@@ -1890,6 +2040,19 @@ impl<'source, 'temp> Lowerer<'source, 'temp> {
 
                             crate::Expression::ArrayLength(expr)
                         }
+                        "nonUniform" => {
+                            if !self.enable_nonuniform_indexing {
+                                return Err(Error::EnableExtensionNotEnabled {
+                                    span,
+                                    kind: "nonuniform_indexing",
+                                });
+                            }
+                            let mut args = ctx.prepare_args(arguments, 1, span);
+                            let value = self.expression(args.next()?, ctx)?;
+                            args.finish()?;
+
+                            crate::Expression::NonUniform(value)
+                        }
                         "atomicLoad" => {
                             let mut args = ctx.prepare_args(arguments, 1, span);
                             let pointer = self.atomic_pointer(args.next()?, ctx)?;
@@ -2097,6 +2260,78 @@ impl<'source, 'temp> Lowerer<'source, 'temp> {
                             rctx.block.push(stmt, span);
                             return Ok(None);
                         }
+                        "textureAtomicAdd" => {
+                            self.texture_atomic_helper(
+                                span,
+                                crate::AtomicFunction::Add,
+                                arguments,
+                                ctx,
+                            )?;
+                            return Ok(None);
+                        }
+                        "textureAtomicSub" => {
+                            self.texture_atomic_helper(
+                                span,
+                                crate::AtomicFunction::Subtract,
+                                arguments,
+                                ctx,
+                            )?;
+                            return Ok(None);
+                        }
+                        "textureAtomicAnd" => {
+                            self.texture_atomic_helper(
+                                span,
+                                crate::AtomicFunction::And,
+                                arguments,
+                                ctx,
+                            )?;
+                            return Ok(None);
+                        }
+                        "textureAtomicOr" => {
+                            self.texture_atomic_helper(
+                                span,
+                                crate::AtomicFunction::InclusiveOr,
+                                arguments,
+                                ctx,
+                            )?;
+                            return Ok(None);
+                        }
+                        "textureAtomicXor" => {
+                            self.texture_atomic_helper(
+                                span,
+                                crate::AtomicFunction::ExclusiveOr,
+                                arguments,
+                                ctx,
+                            )?;
+                            return Ok(None);
+                        }
+                        "textureAtomicMin" => {
+                            self.texture_atomic_helper(
+                                span,
+                                crate::AtomicFunction::Min,
+                                arguments,
+                                ctx,
+                            )?;
+                            return Ok(None);
+                        }
+                        "textureAtomicMax" => {
+                            self.texture_atomic_helper(
+                                span,
+                                crate::AtomicFunction::Max,
+                                arguments,
+                                ctx,
+                            )?;
+                            return Ok(None);
+                        }
+                        "textureAtomicExchange" => {
+                            self.texture_atomic_helper(
+                                span,
+                                crate::AtomicFunction::Exchange { compare: None },
+                                arguments,
+                                ctx,
+                            )?;
+                            return Ok(None);
+                        }
                         "textureLoad" => {
                             let mut args = ctx.prepare_args(arguments, 2, span);
 
@@ -2218,7 +2453,21 @@ impl<'source, 'temp> Lowerer<'source, 'temp> {
                                 .push(crate::Statement::RayQuery { query, fun }, span);
                             return Ok(Some(result));
                         }
-                        "rayQueryGetCommittedIntersection" => {
+                        "rayQueryTerminate" => {
+                            let mut args = ctx.prepare_args(arguments, 1, span);
+                            let query = self.ray_query_pointer(args.next()?, ctx)?;
+                            args.finish()?;
+
+                            let fun = crate::RayQueryFunction::Terminate;
+                            let rctx = ctx.runtime_expression_ctx(span)?;
+                            rctx.block
+                                .extend(rctx.emitter.finish(&rctx.function.expressions));
+                            rctx.emitter.start(&rctx.function.expressions);
+                            rctx.block
+                                .push(crate::Statement::RayQuery { query, fun }, span);
+                            return Ok(None);
+                        }
+                        "rayQueryGetCommittedIntersection" | "rayQueryGetCandidateIntersection" => {
                             let mut args = ctx.prepare_args(arguments, 1, span);
                             let query = self.ray_query_pointer(args.next()?, ctx)?;
                             args.finish()?;
@@ -2227,7 +2476,7 @@ impl<'source, 'temp> Lowerer<'source, 'temp> {
 
                             crate::Expression::RayQueryGetIntersection {
                                 query,
-                                committed: true,
+                                committed: function.name == "rayQueryGetCommittedIntersection",
                             }
                         }
                         "RayDesc" => {
@@ -2311,6 +2560,50 @@ impl<'source, 'temp> Lowerer<'source, 'temp> {
         Ok(result)
     }
 
+    fn texture_atomic_helper(
+        &mut self,
+        span: Span,
+        fun: crate::AtomicFunction,
+        args: &[Handle<ast::Expression<'source>>],
+        ctx: &mut ExpressionContext<'source, '_, '_>,
+    ) -> Result<(), Error<'source>> {
+        let mut args = ctx.prepare_args(args, 3, span);
+
+        let image = args.next()?;
+        let image_span = ctx.ast_expressions.get_span(image);
+        let image = self.expression(image, ctx)?;
+
+        let coordinate = self.expression(args.next()?, ctx)?;
+
+        let (_, arrayed) = ctx.image_data(image, image_span)?;
+        let array_index = arrayed
+            .then(|| {
+                args.min_args += 1;
+                self.expression(args.next()?, ctx)
+            })
+            .transpose()?;
+
+        let value = self.expression(args.next()?, ctx)?;
+
+        args.finish()?;
+
+        let rctx = ctx.runtime_expression_ctx(span)?;
+        rctx.block
+            .extend(rctx.emitter.finish(&rctx.function.expressions));
+        rctx.emitter.start(&rctx.function.expressions);
+        rctx.block.push(
+            crate::Statement::ImageAtomic {
+                image,
+                coordinate,
+                array_index,
+                fun,
+                value,
+            },
+            span,
+        );
+        Ok(())
+    }
+
     fn texture_sample_helper(
         &mut self,
         fun: Texture,
@@ -2497,14 +2790,16 @@ impl<'source, 'temp> Lowerer<'source, 'temp> {
             span: size,
         };
 
-        let handle = ctx.module.types.insert(
-            crate::Type {
-                name: Some(s.name.name.to_string()),
-                inner,
-            },
-            span,
-        );
-        Ok(handle)
+        ctx.module
+            .types
+            .try_insert(
+                crate::Type {
+                    name: Some(s.name.name.to_string()),
+                    inner,
+                },
+                span,
+            )
+            .map_err(|_| Error::TooManyTypes(span))
     }
 
     fn const_u32(
@@ -2621,7 +2916,11 @@ impl<'source, 'temp> Lowerer<'source, 'temp> {
             }
         };
 
-        Ok(ctx.ensure_type_exists(inner))
+        let span = ctx.types.get_span(handle);
+        ctx.module
+            .types
+            .try_insert(crate::Type { inner, name: None }, Span::UNDEFINED)
+            .map_err(|_| Error::TooManyTypes(span))
     }
 
     fn binding(
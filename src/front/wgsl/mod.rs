@@ -29,10 +29,46 @@ impl Frontend {
         }
     }
 
+    /// Construct a front end that parses sources under `profile`.
+    ///
+    /// Under [`Profile::WebGpu`], sources may only use WGSL as the WebGPU
+    /// specification defines it: the address spaces, types, attributes and
+    /// enable-extensions only Naga accepts are errors, but the names of
+    /// those types are free for declarations. [`new`] uses
+    /// [`Profile::Native`], which allows them and reserves their names.
+    ///
+    /// [`Profile::WebGpu`]: crate::valid::Profile::WebGpu
+    /// [`Profile::Native`]: crate::valid::Profile::Native
+    /// [`new`]: Frontend::new
+    pub const fn with_profile(profile: crate::valid::Profile) -> Self {
+        let mut parser = Parser::new();
+        parser.profile = profile;
+        Self { parser }
+    }
+
     pub fn parse(&mut self, source: &str) -> Result<crate::Module, ParseError> {
         self.inner(source).map_err(|x| x.as_parse_error(source))
     }
 
+    /// Parse `source`, making the spans of the resulting module, or of the
+    /// error, refer to the file `id`.
+    ///
+    /// This is for modules whose errors are reported with a
+    /// [`SourceMap`](crate::SourceMap) holding several files.
+    pub fn parse_with_source_id(
+        &mut self,
+        source: &str,
+        id: crate::SourceId,
+    ) -> Result<crate::Module, ParseError> {
+        #[cfg_attr(not(feature = "span"), allow(unused_mut))]
+        let mut module = self.parse(source).map_err(|error| error.with_source(id))?;
+        #[cfg(feature = "span")]
+        crate::span::set_module_source(&mut module, id);
+        #[cfg(not(feature = "span"))]
+        let _ = id;
+        Ok(module)
+    }
+
     fn inner<'a>(&mut self, source: &'a str) -> Result<crate::Module, Error<'a>> {
         let tu = self.parser.parse(source)?;
         let index = index::Index::generate(&tu)?;
@@ -46,6 +82,13 @@ pub fn parse_str(source: &str) -> Result<crate::Module, ParseError> {
     Frontend::new().parse(source)
 }
 
+impl crate::MathFunction {
+    /// Return the function WGSL calls `name`, like `inverseSqrt`, if any.
+    pub fn from_wgsl_ident(name: &str) -> Option<Self> {
+        parse::conv::map_standard_fun(name)
+    }
+}
+
 impl crate::StorageFormat {
     const fn to_wgsl(self) -> &'static str {
         use crate::StorageFormat as Sf;
@@ -174,7 +217,9 @@ impl crate::TypeInner {
                     }
                     crate::ImageClass::Depth { multi: _ } => String::new(),
                     crate::ImageClass::Storage { format, access } => {
-                        if access.contains(crate::StorageAccess::STORE) {
+                        if access.contains(crate::StorageAccess::ATOMIC) {
+                            format!("<{},atomic>", format.to_wgsl())
+                        } else if access.contains(crate::StorageAccess::STORE) {
                             format!("<{},write>", format.to_wgsl())
                         } else {
                             format!("<{}>", format.to_wgsl())
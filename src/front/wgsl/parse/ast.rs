@@ -23,6 +23,16 @@ pub struct TranslationUnit<'a> {
     /// These are referred to by `Handle<ast::Type<'a>>` values.
     /// User-defined types are referred to by name until lowering.
     pub types: Arena<Type<'a>>,
+
+    /// Whether the source has an `enable subgroups;` directive.
+    pub enable_subgroups: bool,
+
+    /// Whether the source has an `enable nonuniform_indexing;` directive.
+    pub enable_nonuniform_indexing: bool,
+
+    /// The filters the source's `diagnostic` directives give, one for each
+    /// rule.
+    pub diagnostic_filters: Vec<crate::diagnostic_filter::DiagnosticFilter>,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -169,6 +179,8 @@ pub struct GlobalVariable<'a> {
     pub binding: Option<ResourceBinding<'a>>,
     pub ty: Handle<Type<'a>>,
     pub init: Option<Handle<Expression<'a>>>,
+    /// The span of the `@coherent` attribute, if present.
+    pub coherent: Option<Span>,
 }
 
 #[derive(Debug)]
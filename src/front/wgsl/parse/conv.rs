@@ -34,6 +34,9 @@ pub fn map_built_in(word: &str, span: Span) -> Result<crate::BuiltIn, Error<'_>>
         "local_invocation_index" => crate::BuiltIn::LocalInvocationIndex,
         "workgroup_id" => crate::BuiltIn::WorkGroupId,
         "num_workgroups" => crate::BuiltIn::NumWorkGroups,
+        // subgroup
+        "subgroup_size" => crate::BuiltIn::SubgroupSize,
+        "subgroup_invocation_id" => crate::BuiltIn::SubgroupInvocationId,
         _ => return Err(Error::UnknownBuiltin(span)),
     })
 }
@@ -105,11 +108,13 @@ pub fn map_storage_format(word: &str, span: Span) -> Result<crate::StorageFormat
 
 pub fn get_scalar_type(word: &str) -> Option<(crate::ScalarKind, crate::Bytes)> {
     match word {
-        // "f16" => Some((crate::ScalarKind::Float, 2)),
+        "f16" => Some((crate::ScalarKind::Float, 2)),
         "f32" => Some((crate::ScalarKind::Float, 4)),
         "f64" => Some((crate::ScalarKind::Float, 8)),
         "i32" => Some((crate::ScalarKind::Sint, 4)),
         "u32" => Some((crate::ScalarKind::Uint, 4)),
+        "i64" => Some((crate::ScalarKind::Sint, 8)),
+        "u64" => Some((crate::ScalarKind::Uint, 8)),
         "bool" => Some((crate::ScalarKind::Bool, crate::BOOL_WIDTH)),
         _ => None,
     }
@@ -131,6 +136,50 @@ pub fn map_derivative(word: &str) -> Option<(crate::DerivativeAxis, crate::Deriv
     }
 }
 
+/// A subgroup built-in function.
+pub enum SubgroupFunction {
+    /// A function taking only the value to operate on.
+    Unary(crate::SubgroupOperation),
+    /// A function also taking an invocation ID or offset.
+    Indexed(fn(crate::Handle<crate::Expression>) -> crate::SubgroupOperation),
+}
+
+pub fn map_subgroup_function(word: &str) -> Option<SubgroupFunction> {
+    use crate::{SubgroupOperation as So, SubgroupReduction as Sr};
+    use SubgroupFunction::{Indexed, Unary};
+    let reduction = |name: &str| match name {
+        "Add" => Some(Sr::Add),
+        "Mul" => Some(Sr::Mul),
+        "Min" => Some(Sr::Min),
+        "Max" => Some(Sr::Max),
+        "And" => Some(Sr::And),
+        "Or" => Some(Sr::Or),
+        "Xor" => Some(Sr::Xor),
+        _ => None,
+    };
+    let name = word.strip_prefix("subgroup")?;
+    Some(match name {
+        "Ballot" => Unary(So::Ballot),
+        "All" => Unary(So::All),
+        "Any" => Unary(So::Any),
+        "BroadcastFirst" => Unary(So::BroadcastFirst),
+        "Broadcast" => Indexed(So::Broadcast),
+        "Shuffle" => Indexed(So::Shuffle),
+        "ShuffleDown" => Indexed(So::ShuffleDown),
+        "ShuffleUp" => Indexed(So::ShuffleUp),
+        "ShuffleXor" => Indexed(So::ShuffleXor),
+        _ => {
+            if let Some(name) = name.strip_prefix("Inclusive") {
+                Unary(So::InclusiveScan(reduction(name)?))
+            } else if let Some(name) = name.strip_prefix("Exclusive") {
+                Unary(So::ExclusiveScan(reduction(name)?))
+            } else {
+                Unary(So::Reduce(reduction(name)?))
+            }
+        }
+    })
+}
+
 pub fn map_relational_fun(word: &str) -> Option<crate::RelationalFunction> {
     match word {
         "any" => Some(crate::RelationalFunction::Any),
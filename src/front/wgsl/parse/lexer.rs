@@ -204,6 +204,14 @@ pub(in crate::front::wgsl) struct Lexer<'a> {
     pub(in crate::front::wgsl) source: &'a str,
     // The byte offset of the end of the last non-trivia token.
     last_end_offset: usize,
+    /// Whether the source has an `enable f16;` directive.
+    pub(in crate::front::wgsl) enable_f16: bool,
+    /// Whether the source has an `enable subgroups;` directive.
+    pub(in crate::front::wgsl) enable_subgroups: bool,
+    /// Whether the source has an `enable nonuniform_indexing;` directive.
+    pub(in crate::front::wgsl) enable_nonuniform_indexing: bool,
+    /// The rules the source is parsed under.
+    pub(in crate::front::wgsl) profile: crate::valid::Profile,
 }
 
 impl<'a> Lexer<'a> {
@@ -212,6 +220,10 @@ impl<'a> Lexer<'a> {
             input,
             source: input,
             last_end_offset: 0,
+            enable_f16: false,
+            enable_subgroups: false,
+            enable_nonuniform_indexing: false,
+            profile: crate::valid::Profile::Native,
         }
     }
 
@@ -366,7 +378,13 @@ impl<'a> Lexer<'a> {
             .next_ident_with_span()
             .map(|(name, span)| super::ast::Ident { name, span })?;
 
-        if crate::keywords::wgsl::RESERVED.contains(&ident.name) {
+        let native_reserved = match self.profile {
+            crate::valid::Profile::WebGpu => false,
+            crate::valid::Profile::Native => {
+                crate::keywords::wgsl::NATIVE_RESERVED.contains(&ident.name)
+            }
+        };
+        if native_reserved || crate::keywords::wgsl::RESERVED.contains(&ident.name) {
             return Err(Error::ReservedKeyword(ident.span));
         }
 
@@ -377,15 +395,8 @@ impl<'a> Lexer<'a> {
     pub(in crate::front::wgsl) fn next_scalar_generic(
         &mut self,
     ) -> Result<(crate::ScalarKind, crate::Bytes), Error<'a>> {
-        self.expect_generic_paren('<')?;
-        let pair = match self.next() {
-            (Token::Word(word), span) => {
-                conv::get_scalar_type(word).ok_or(Error::UnknownScalarType(span))
-            }
-            (_, span) => Err(Error::UnknownScalarType(span)),
-        }?;
-        self.expect_generic_paren('>')?;
-        Ok(pair)
+        let (kind, width, _) = self.next_scalar_generic_with_span()?;
+        Ok((kind, width))
     }
 
     /// Parses a generic scalar type, for example `<f32>`.
@@ -401,10 +412,49 @@ impl<'a> Lexer<'a> {
                 .ok_or(Error::UnknownScalarType(span)),
             (_, span) => Err(Error::UnknownScalarType(span)),
         }?;
+        self.require_scalar(pair.0, pair.1, pair.2)?;
         self.expect_generic_paren('>')?;
         Ok(pair)
     }
 
+    /// Check that the scalar type the source uses at `span` is enabled.
+    pub(in crate::front::wgsl) fn require_scalar(
+        &self,
+        kind: crate::ScalarKind,
+        width: crate::Bytes,
+        span: Span,
+    ) -> Result<(), Error<'a>> {
+        match (kind, width) {
+            (crate::ScalarKind::Float, 2) => self.require_f16(span),
+            (crate::ScalarKind::Float, 8) => self.require_native("f64", span),
+            (crate::ScalarKind::Sint, 8) => self.require_native("i64", span),
+            (crate::ScalarKind::Uint, 8) => self.require_native("u64", span),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check that the source may use `kind`, which only Naga accepts, at
+    /// `span`.
+    pub(in crate::front::wgsl) const fn require_native(
+        &self,
+        kind: &'static str,
+        span: Span,
+    ) -> Result<(), Error<'a>> {
+        match self.profile {
+            crate::valid::Profile::WebGpu => Err(Error::NotInWebGpu { span, kind }),
+            crate::valid::Profile::Native => Ok(()),
+        }
+    }
+
+    /// Check that the source enables `f16`, which it uses at `span`.
+    pub(in crate::front::wgsl) const fn require_f16(&self, span: Span) -> Result<(), Error<'a>> {
+        if self.enable_f16 {
+            Ok(())
+        } else {
+            Err(Error::EnableExtensionNotEnabled { span, kind: "f16" })
+        }
+    }
+
     pub(in crate::front::wgsl) fn next_storage_access(
         &mut self,
     ) -> Result<crate::StorageAccess, Error<'a>> {
@@ -424,7 +474,13 @@ impl<'a> Lexer<'a> {
         let (ident, ident_span) = self.next_ident_with_span()?;
         let format = conv::map_storage_format(ident, ident_span)?;
         self.expect(Token::Separator(','))?;
-        let access = self.next_storage_access()?;
+        // Atomic access only makes sense for textures, so it's not one of
+        // the modes `next_storage_access` accepts for buffers.
+        let access = if self.skip(Token::Word("atomic")) {
+            crate::StorageAccess::LOAD | crate::StorageAccess::STORE | crate::StorageAccess::ATOMIC
+        } else {
+            self.next_storage_access()?
+        };
         self.expect(Token::Paren('>'))?;
         Ok((format, access))
     }
@@ -483,14 +539,14 @@ fn test_numbers() {
             Token::Number(Ok(Number::F32(0.01))),
             Token::Number(Ok(Number::F32(12.34))),
             Token::Number(Ok(Number::F32(0.))),
-            Token::Number(Err(NumberError::UnimplementedF16)),
+            Token::Number(Ok(Number::F16(0x0000))),
             Token::Number(Ok(Number::F32(0.001))),
             Token::Number(Ok(Number::F32(43.75))),
             Token::Number(Ok(Number::F32(16.))),
             Token::Number(Ok(Number::F32(0.1875))),
-            Token::Number(Err(NumberError::UnimplementedF16)),
+            Token::Number(Ok(Number::F16(0x4a00))),
             Token::Number(Ok(Number::F32(0.12109375))),
-            Token::Number(Err(NumberError::UnimplementedF16)),
+            Token::Number(Ok(Number::F16(0x4a40))),
         ],
     );
 
@@ -538,6 +594,26 @@ fn test_numbers() {
         ],
     );
 
+    // min / max 64-bit integers
+    sub_test(
+        "-9223372036854775808li 9223372036854775807li 9223372036854775808li 0x7FFFFFFFFFFFFFFFli",
+        &[
+            Token::Number(Ok(Number::I64(i64::MIN))),
+            Token::Number(Ok(Number::I64(i64::MAX))),
+            Token::Number(Err(NumberError::NotRepresentable)),
+            Token::Number(Ok(Number::I64(i64::MAX))),
+        ],
+    );
+    sub_test(
+        "0lu 18446744073709551615lu -1lu 0xFFFFFFFFFFFFFFFFlu",
+        &[
+            Token::Number(Ok(Number::U64(u64::MIN))),
+            Token::Number(Ok(Number::U64(u64::MAX))),
+            Token::Number(Err(NumberError::NotRepresentable)),
+            Token::Number(Ok(Number::U64(u64::MAX))),
+        ],
+    );
+
     /// ≈ 2^-126 * 2^−23 (= 2^−149)
     const SMALLEST_POSITIVE_SUBNORMAL_F32: f32 = 1e-45;
     /// ≈ 2^-126 * (1 − 2^−23)
@@ -220,11 +220,16 @@ impl<'a> BindingParser<'a> {
 
 pub struct Parser {
     rules: Vec<(Rule, usize)>,
+    /// The rules sources are parsed under.
+    pub(in crate::front::wgsl) profile: crate::valid::Profile,
 }
 
 impl Parser {
     pub const fn new() -> Self {
-        Parser { rules: Vec::new() }
+        Parser {
+            rules: Vec::new(),
+            profile: crate::valid::Profile::Native,
+        }
     }
 
     fn reset(&mut self) {
@@ -278,6 +283,7 @@ impl Parser {
         ctx: &mut ExpressionContext<'a, '_, '_>,
     ) -> Result<Option<ast::ConstructorType<'a>>, Error<'a>> {
         if let Some((kind, width)) = conv::get_scalar_type(word) {
+            lexer.require_scalar(kind, width, span)?;
             return Ok(Some(ast::ConstructorType::Scalar { kind, width }));
         }
 
@@ -453,6 +459,102 @@ impl Parser {
                     width: 4,
                 }))
             }
+            "vec2h" => {
+                lexer.require_f16(span)?;
+                return Ok(Some(ast::ConstructorType::Vector {
+                    size: crate::VectorSize::Bi,
+                    kind: crate::ScalarKind::Float,
+                    width: 2,
+                }));
+            }
+            "vec3h" => {
+                lexer.require_f16(span)?;
+                return Ok(Some(ast::ConstructorType::Vector {
+                    size: crate::VectorSize::Tri,
+                    kind: crate::ScalarKind::Float,
+                    width: 2,
+                }));
+            }
+            "vec4h" => {
+                lexer.require_f16(span)?;
+                return Ok(Some(ast::ConstructorType::Vector {
+                    size: crate::VectorSize::Quad,
+                    kind: crate::ScalarKind::Float,
+                    width: 2,
+                }));
+            }
+            "mat2x2h" => {
+                lexer.require_f16(span)?;
+                return Ok(Some(ast::ConstructorType::Matrix {
+                    columns: crate::VectorSize::Bi,
+                    rows: crate::VectorSize::Bi,
+                    width: 2,
+                }));
+            }
+            "mat2x3h" => {
+                lexer.require_f16(span)?;
+                return Ok(Some(ast::ConstructorType::Matrix {
+                    columns: crate::VectorSize::Bi,
+                    rows: crate::VectorSize::Tri,
+                    width: 2,
+                }));
+            }
+            "mat2x4h" => {
+                lexer.require_f16(span)?;
+                return Ok(Some(ast::ConstructorType::Matrix {
+                    columns: crate::VectorSize::Bi,
+                    rows: crate::VectorSize::Quad,
+                    width: 2,
+                }));
+            }
+            "mat3x2h" => {
+                lexer.require_f16(span)?;
+                return Ok(Some(ast::ConstructorType::Matrix {
+                    columns: crate::VectorSize::Tri,
+                    rows: crate::VectorSize::Bi,
+                    width: 2,
+                }));
+            }
+            "mat3x3h" => {
+                lexer.require_f16(span)?;
+                return Ok(Some(ast::ConstructorType::Matrix {
+                    columns: crate::VectorSize::Tri,
+                    rows: crate::VectorSize::Tri,
+                    width: 2,
+                }));
+            }
+            "mat3x4h" => {
+                lexer.require_f16(span)?;
+                return Ok(Some(ast::ConstructorType::Matrix {
+                    columns: crate::VectorSize::Tri,
+                    rows: crate::VectorSize::Quad,
+                    width: 2,
+                }));
+            }
+            "mat4x2h" => {
+                lexer.require_f16(span)?;
+                return Ok(Some(ast::ConstructorType::Matrix {
+                    columns: crate::VectorSize::Quad,
+                    rows: crate::VectorSize::Bi,
+                    width: 2,
+                }));
+            }
+            "mat4x3h" => {
+                lexer.require_f16(span)?;
+                return Ok(Some(ast::ConstructorType::Matrix {
+                    columns: crate::VectorSize::Quad,
+                    rows: crate::VectorSize::Tri,
+                    width: 2,
+                }));
+            }
+            "mat4x4h" => {
+                lexer.require_f16(span)?;
+                return Ok(Some(ast::ConstructorType::Matrix {
+                    columns: crate::VectorSize::Quad,
+                    rows: crate::VectorSize::Quad,
+                    width: 2,
+                }));
+            }
             "array" => ast::ConstructorType::PartialArray,
             "atomic"
             | "binding_array"
@@ -633,6 +735,9 @@ impl Parser {
             (Token::Number(res), span) => {
                 let _ = lexer.next();
                 let num = res.map_err(|err| Error::BadNumber(span, err))?;
+                if let Number::F16(_) = num {
+                    lexer.require_f16(span)?;
+                }
                 ast::Expression::Literal(ast::Literal::Number(num))
             }
             (Token::Word("RAY_FLAG_NONE"), _) => {
@@ -647,6 +752,18 @@ impl Parser {
                 let _ = lexer.next();
                 ast::Expression::Literal(ast::Literal::Number(Number::U32(0)))
             }
+            (Token::Word("RAY_QUERY_INTERSECTION_TRIANGLE"), _) => {
+                let _ = lexer.next();
+                ast::Expression::Literal(ast::Literal::Number(Number::U32(1)))
+            }
+            (Token::Word("RAY_QUERY_INTERSECTION_GENERATED"), _) => {
+                let _ = lexer.next();
+                ast::Expression::Literal(ast::Literal::Number(Number::U32(2)))
+            }
+            (Token::Word("RAY_QUERY_INTERSECTION_AABB"), _) => {
+                let _ = lexer.next();
+                ast::Expression::Literal(ast::Literal::Number(Number::U32(4)))
+            }
             (Token::Word(word), span) => {
                 let start = lexer.start_byte_offset();
                 let _ = lexer.next();
@@ -956,7 +1073,20 @@ impl Parser {
                     };
                     crate::AddressSpace::Storage { access }
                 }
-                _ => conv::map_address_space(class_str, span)?,
+                _ => {
+                    let space = conv::map_address_space(class_str, span)?;
+                    if space == crate::AddressSpace::PushConstant {
+                        lexer.require_native("push_constant", span)?;
+                    }
+                    if lexer.skip(Token::Separator(',')) {
+                        let (_, access_span) = lexer.next_ident_with_span()?;
+                        return Err(Error::AccessModeNotAllowed {
+                            span: access_span,
+                            space,
+                        });
+                    }
+                    space
+                }
             };
             lexer.expect(Token::Paren('>'))?;
         }
@@ -979,6 +1109,7 @@ impl Parser {
             binding: None,
             ty,
             init,
+            coherent: None,
         })
     }
 
@@ -1060,9 +1191,11 @@ impl Parser {
         &mut self,
         lexer: &mut Lexer<'a>,
         word: &'a str,
+        span: Span,
         ctx: &mut ExpressionContext<'a, '_, '_>,
     ) -> Result<Option<ast::Type<'a>>, Error<'a>> {
         if let Some((kind, width)) = conv::get_scalar_type(word) {
+            lexer.require_scalar(kind, width, span)?;
             return Ok(Some(ast::Type::Scalar { kind, width }));
         }
 
@@ -1208,6 +1341,102 @@ impl Parser {
                 rows: crate::VectorSize::Quad,
                 width: 4,
             },
+            "vec2h" => {
+                lexer.require_f16(span)?;
+                ast::Type::Vector {
+                    size: crate::VectorSize::Bi,
+                    kind: crate::ScalarKind::Float,
+                    width: 2,
+                }
+            }
+            "vec3h" => {
+                lexer.require_f16(span)?;
+                ast::Type::Vector {
+                    size: crate::VectorSize::Tri,
+                    kind: crate::ScalarKind::Float,
+                    width: 2,
+                }
+            }
+            "vec4h" => {
+                lexer.require_f16(span)?;
+                ast::Type::Vector {
+                    size: crate::VectorSize::Quad,
+                    kind: crate::ScalarKind::Float,
+                    width: 2,
+                }
+            }
+            "mat2x2h" => {
+                lexer.require_f16(span)?;
+                ast::Type::Matrix {
+                    columns: crate::VectorSize::Bi,
+                    rows: crate::VectorSize::Bi,
+                    width: 2,
+                }
+            }
+            "mat2x3h" => {
+                lexer.require_f16(span)?;
+                ast::Type::Matrix {
+                    columns: crate::VectorSize::Bi,
+                    rows: crate::VectorSize::Tri,
+                    width: 2,
+                }
+            }
+            "mat2x4h" => {
+                lexer.require_f16(span)?;
+                ast::Type::Matrix {
+                    columns: crate::VectorSize::Bi,
+                    rows: crate::VectorSize::Quad,
+                    width: 2,
+                }
+            }
+            "mat3x2h" => {
+                lexer.require_f16(span)?;
+                ast::Type::Matrix {
+                    columns: crate::VectorSize::Tri,
+                    rows: crate::VectorSize::Bi,
+                    width: 2,
+                }
+            }
+            "mat3x3h" => {
+                lexer.require_f16(span)?;
+                ast::Type::Matrix {
+                    columns: crate::VectorSize::Tri,
+                    rows: crate::VectorSize::Tri,
+                    width: 2,
+                }
+            }
+            "mat3x4h" => {
+                lexer.require_f16(span)?;
+                ast::Type::Matrix {
+                    columns: crate::VectorSize::Tri,
+                    rows: crate::VectorSize::Quad,
+                    width: 2,
+                }
+            }
+            "mat4x2h" => {
+                lexer.require_f16(span)?;
+                ast::Type::Matrix {
+                    columns: crate::VectorSize::Quad,
+                    rows: crate::VectorSize::Bi,
+                    width: 2,
+                }
+            }
+            "mat4x3h" => {
+                lexer.require_f16(span)?;
+                ast::Type::Matrix {
+                    columns: crate::VectorSize::Quad,
+                    rows: crate::VectorSize::Tri,
+                    width: 2,
+                }
+            }
+            "mat4x4h" => {
+                lexer.require_f16(span)?;
+                ast::Type::Matrix {
+                    columns: crate::VectorSize::Quad,
+                    rows: crate::VectorSize::Quad,
+                    width: 2,
+                }
+            }
             "atomic" => {
                 let (kind, width) = lexer.next_scalar_generic()?;
                 ast::Type::Atomic { kind, width }
@@ -1216,6 +1445,9 @@ impl Parser {
                 lexer.expect_generic_paren('<')?;
                 let (ident, span) = lexer.next_ident_with_span()?;
                 let mut space = conv::map_address_space(ident, span)?;
+                if space == crate::AddressSpace::PushConstant {
+                    lexer.require_native("push_constant", span)?;
+                }
                 lexer.expect(Token::Separator(','))?;
                 let base = self.type_decl(lexer, ctx)?;
                 if let crate::AddressSpace::Storage { ref mut access } = space {
@@ -1242,6 +1474,7 @@ impl Parser {
                 ast::Type::Array { base, size }
             }
             "binding_array" => {
+                lexer.require_native("binding_array", span)?;
                 lexer.expect_generic_paren('<')?;
                 let base = self.type_decl(lexer, ctx)?;
                 let size = if lexer.skip(Token::Separator(',')) {
@@ -1402,10 +1635,22 @@ impl Parser {
                     class: crate::ImageClass::Storage { format, access },
                 }
             }
-            "acceleration_structure" => ast::Type::AccelerationStructure,
-            "ray_query" => ast::Type::RayQuery,
-            "RayDesc" => ast::Type::RayDesc,
-            "RayIntersection" => ast::Type::RayIntersection,
+            "acceleration_structure" => {
+                lexer.require_native("acceleration_structure", span)?;
+                ast::Type::AccelerationStructure
+            }
+            "ray_query" => {
+                lexer.require_native("ray_query", span)?;
+                ast::Type::RayQuery
+            }
+            "RayDesc" => {
+                lexer.require_native("RayDesc", span)?;
+                ast::Type::RayDesc
+            }
+            "RayIntersection" => {
+                lexer.require_native("RayIntersection", span)?;
+                ast::Type::RayIntersection
+            }
             _ => return Ok(None),
         }))
     }
@@ -1433,7 +1678,7 @@ impl Parser {
 
         let (name, span) = lexer.next_ident_with_span()?;
 
-        let ty = match self.type_decl_impl(lexer, name, ctx)? {
+        let ty = match self.type_decl_impl(lexer, name, span, ctx)? {
             Some(ty) => ty,
             None => {
                 ctx.unresolved.insert(ast::Dependency {
@@ -1566,6 +1811,16 @@ impl Parser {
     ) -> Result<(), Error<'a>> {
         let span_start = lexer.start_byte_offset();
         match lexer.peek() {
+            (Token::Word("_"), _) => {
+                let _ = lexer.next();
+                lexer.expect(Token::Operation('='))?;
+                let expr = self.general_expression(lexer, context)?;
+                block.stmts.push(ast::Statement {
+                    kind: ast::StatementKind::Ignore(expr),
+                    span: lexer.span_from(span_start),
+                });
+                Ok(())
+            }
             (Token::Word(name), span) => {
                 // A little hack for 2 token lookahead.
                 let cloned = lexer.clone();
@@ -1834,6 +2089,7 @@ impl Parser {
                                 match block.stmts.last().unwrap().kind {
                                     ast::StatementKind::Call { .. }
                                     | ast::StatementKind::Assign { .. }
+                                    | ast::StatementKind::Ignore(_)
                                     | ast::StatementKind::LocalDecl(_) => {}
                                     _ => return Err(Error::InvalidForInitializer(span)),
                                 }
@@ -1939,6 +2195,11 @@ impl Parser {
         let mut body = ast::Block::default();
         let mut continuing = ast::Block::default();
         let mut break_if = None;
+        // The first `continue` targeting this loop, and the declarations in
+        // the loop body that follow it. The `continuing` block must not use
+        // any of those, since they may not have been evaluated.
+        let mut first_continue = None;
+        let mut bypassed = Vec::new();
 
         lexer.expect(Token::Paren('{'))?;
 
@@ -1948,6 +2209,7 @@ impl Parser {
             if lexer.skip(Token::Word("continuing")) {
                 // Branch for the `continuing` block, this must be
                 // the last thing in the loop body
+                let first_continuing_expression = ctx.expressions.len();
 
                 // Expect a opening brace to start the continuing block
                 lexer.expect(Token::Paren('{'))?;
@@ -1982,6 +2244,22 @@ impl Parser {
                         self.statement(lexer, ctx, &mut continuing)?;
                     }
                 }
+                if let Some(continue_span) = first_continue {
+                    for (handle, expr) in ctx.expressions.iter().skip(first_continuing_expression) {
+                        let local = match *expr {
+                            ast::Expression::Ident(ast::IdentExpr::Local(local)) => local,
+                            _ => continue,
+                        };
+                        if let Some(&(name, _)) = bypassed.iter().find(|&&(_, l)| l == local) {
+                            return Err(Error::ContinueBypassesDeclaration {
+                                continue_span,
+                                name: name.name,
+                                decl_span: name.span,
+                                usage_span: ctx.expressions.get_span(handle),
+                            });
+                        }
+                    }
+                }
                 // Since the continuing block must be the last part of the loop body,
                 // we expect to see a closing brace to end the loop body
                 lexer.expect(Token::Paren('}'))?;
@@ -1993,7 +2271,24 @@ impl Parser {
                 break;
             }
             // Otherwise try to parse a statement
+            let num_statements = body.stmts.len();
             self.statement(lexer, ctx, &mut body)?;
+            for stmt in body.stmts[num_statements..].iter() {
+                match stmt.kind {
+                    ast::StatementKind::LocalDecl(ast::LocalDecl::Var(ast::LocalVariable {
+                        name,
+                        handle,
+                        ..
+                    }))
+                    | ast::StatementKind::LocalDecl(ast::LocalDecl::Let(ast::Let {
+                        name,
+                        handle,
+                        ..
+                    })) if first_continue.is_some() => bypassed.push((name, handle)),
+                    _ if first_continue.is_none() => first_continue = find_continue(stmt),
+                    _ => {}
+                }
+            }
         }
 
         ctx.local_table.pop_scope();
@@ -2138,6 +2433,7 @@ impl Parser {
         let mut compute_span = Span::new(0, 0);
         let mut workgroup_size = ParsedAttribute::default();
         let mut early_depth_test = ParsedAttribute::default();
+        let mut coherent = ParsedAttribute::default();
         let (mut bind_index, mut bind_group) =
             (ParsedAttribute::default(), ParsedAttribute::default());
 
@@ -2192,6 +2488,7 @@ impl Parser {
                     workgroup_size.set(new_workgroup_size, name_span)?;
                 }
                 ("early_depth_test", name_span) => {
+                    lexer.require_native("early_depth_test", name_span)?;
                     let conservative = if lexer.skip(Token::Paren('(')) {
                         let (ident, ident_span) = lexer.next_ident_with_span()?;
                         let value = conv::map_conservative_depth(ident, ident_span)?;
@@ -2202,6 +2499,10 @@ impl Parser {
                     };
                     early_depth_test.set(crate::EarlyDepthTest { conservative }, name_span)?;
                 }
+                ("coherent", name_span) => {
+                    lexer.require_native("coherent", name_span)?;
+                    coherent.set(name_span, name_span)?;
+                }
                 (_, word_span) => return Err(Error::UnknownAttribute(word_span)),
             }
         }
@@ -2256,6 +2557,7 @@ impl Parser {
             (Token::Word("var"), _) => {
                 let mut var = self.variable_decl(lexer, &mut ctx)?;
                 var.binding = binding.take();
+                var.coherent = coherent.value;
                 Some(ast::GlobalDeclKind::Var(var))
             }
             (Token::Word("fn"), _) => {
@@ -2299,11 +2601,125 @@ impl Parser {
         }
     }
 
+    /// Parse the directives at the start of the source. Record the
+    /// extensions `enable` directives enable in `lexer`, and the filters
+    /// `diagnostic` directives give in `tu`.
+    fn global_directives<'a>(
+        lexer: &mut Lexer<'a>,
+        tu: &mut ast::TranslationUnit<'a>,
+    ) -> Result<(), Error<'a>> {
+        // The span of the directive that gave each filter in `tu`.
+        let mut filter_spans = Vec::new();
+        loop {
+            if lexer.skip(Token::Word("enable")) {
+                Self::enable_extensions(lexer)?;
+            } else if lexer.skip(Token::Word("diagnostic")) {
+                let start = lexer.start_byte_offset();
+                let filter = Self::diagnostic_control(lexer)?;
+                let span = lexer.span_from(start);
+                lexer.expect(Token::Separator(';'))?;
+                let Some(filter) = filter else {
+                    continue;
+                };
+                let previous = tu
+                    .diagnostic_filters
+                    .iter()
+                    .position(|other| other.triggering_rule == filter.triggering_rule);
+                match previous {
+                    Some(index) if tu.diagnostic_filters[index] == filter => {}
+                    Some(index) => {
+                        return Err(Error::ConflictingDiagnosticFilters {
+                            rule: filter.triggering_rule.to_wgsl_ident(),
+                            previous: filter_spans[index],
+                            current: span,
+                        })
+                    }
+                    None => {
+                        tu.diagnostic_filters.push(filter);
+                        filter_spans.push(span);
+                    }
+                }
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Parse the list of extensions after `enable`, up to the `;`, and
+    /// record them in `lexer`.
+    fn enable_extensions<'a>(lexer: &mut Lexer<'a>) -> Result<(), Error<'a>> {
+        loop {
+            let (name, span) = lexer.next_ident_with_span()?;
+            match name {
+                "f16" => lexer.enable_f16 = true,
+                "subgroups" => lexer.enable_subgroups = true,
+                "nonuniform_indexing" => {
+                    lexer.require_native("nonuniform_indexing", span)?;
+                    lexer.enable_nonuniform_indexing = true;
+                }
+                _ => return Err(Error::UnknownEnableExtension(span)),
+            }
+            // The list may have a trailing comma.
+            if !lexer.skip(Token::Separator(',')) || lexer.peek().0 == Token::Separator(';') {
+                break;
+            }
+        }
+        lexer.expect(Token::Separator(';'))
+    }
+
+    /// Parse the `(severity, rule)` after `diagnostic`.
+    ///
+    /// WGSL only warns about rules it doesn't recognize, so for those this
+    /// logs a warning and returns `None`.
+    fn diagnostic_control<'a>(
+        lexer: &mut Lexer<'a>,
+    ) -> Result<Option<crate::diagnostic_filter::DiagnosticFilter>, Error<'a>> {
+        use crate::diagnostic_filter::{FilterableTriggeringRule, Severity};
+
+        lexer.open_arguments()?;
+        let (severity, severity_span) = lexer.next_ident_with_span()?;
+        let new_severity = Severity::from_wgsl_ident(severity)
+            .ok_or(Error::UnknownDiagnosticSeverity(severity_span))?;
+        lexer.expect(Token::Separator(','))?;
+        let start = lexer.start_byte_offset();
+        let (rule, _) = lexer.next_ident_with_span()?;
+        // Rules like `chromium.subgroup_matrix_uniformity` belong to other
+        // implementations.
+        let namespaced = lexer.skip(Token::Separator('.'));
+        if namespaced {
+            lexer.next_ident_with_span()?;
+        }
+        let rule_span = lexer.span_from(start);
+        lexer.close_arguments()?;
+
+        let triggering_rule = if namespaced {
+            None
+        } else {
+            FilterableTriggeringRule::from_wgsl_ident(rule)
+        };
+        let Some(triggering_rule) = triggering_rule else {
+            log::warn!(
+                "Unknown diagnostic rule `{}` at {:?}",
+                &lexer.source[rule_span],
+                rule_span
+            );
+            return Ok(None);
+        };
+        Ok(Some(crate::diagnostic_filter::DiagnosticFilter {
+            new_severity,
+            triggering_rule,
+        }))
+    }
+
     pub fn parse<'a>(&mut self, source: &'a str) -> Result<ast::TranslationUnit<'a>, Error<'a>> {
         self.reset();
 
         let mut lexer = Lexer::new(source);
+        lexer.profile = self.profile;
         let mut tu = ast::TranslationUnit::default();
+        Self::global_directives(&mut lexer, &mut tu)?;
+        tu.enable_subgroups = lexer.enable_subgroups;
+        tu.enable_nonuniform_indexing = lexer.enable_nonuniform_indexing;
         loop {
             match self.global_decl(&mut lexer, &mut tu) {
                 Err(error) => return Err(error),
@@ -2318,3 +2734,23 @@ impl Parser {
         Ok(tu)
     }
 }
+
+/// Return the span of the first `continue` in `stmt` that would continue the
+/// innermost loop enclosing `stmt`, if any.
+fn find_continue(stmt: &ast::Statement) -> Option<Span> {
+    let find_in_block = |block: &ast::Block| block.stmts.iter().find_map(find_continue);
+    match stmt.kind {
+        ast::StatementKind::Continue => Some(stmt.span),
+        ast::StatementKind::Block(ref block) => find_in_block(block),
+        ast::StatementKind::If {
+            ref accept,
+            ref reject,
+            ..
+        } => find_in_block(accept).or_else(|| find_in_block(reject)),
+        ast::StatementKind::Switch { ref cases, .. } => {
+            cases.iter().find_map(|case| find_in_block(&case.body))
+        }
+        // A `continue` in a nested loop continues that loop instead.
+        _ => None,
+    }
+}
@@ -14,8 +14,14 @@ pub enum Number {
     I32(i32),
     /// Concrete u32
     U32(u32),
+    /// Concrete i64
+    I64(i64),
+    /// Concrete u64
+    U64(u64),
     /// Concrete f32
     F32(f32),
+    /// Concrete f16, as the bits of an IEEE-754 binary16 value
+    F16(u16),
 }
 
 impl Number {
@@ -59,6 +65,8 @@ enum Kind {
 enum IntKind {
     I32,
     U32,
+    I64,
+    U64,
 }
 
 enum FloatKind {
@@ -69,9 +77,9 @@ enum FloatKind {
 // The following regexes (from the WGSL spec) will be matched:
 
 // int_literal:
-// | / 0                                                                [iu]?   /
-// | / [1-9][0-9]*                                                      [iu]?   /
-// | / 0[xX][0-9a-fA-F]+                                                [iu]?   /
+// | / 0                                                                (l?[iu])? /
+// | / [1-9][0-9]*                                                      (l?[iu])? /
+// | / 0[xX][0-9a-fA-F]+                                                (l?[iu])? /
 
 // decimal_float_literal:
 // | / 0                                                                [fh]    /
@@ -226,7 +234,14 @@ fn parse(input: &str) -> (Result<Number, NumberError>, &str) {
                     rest_to_str!(bytes),
                 )
             } else {
-                let kind = consume_map!(bytes, [b'i' => IntKind::I32, b'u' => IntKind::U32]);
+                let kind = if consume!(bytes, b'l') {
+                    match consume_map!(bytes, [b'i' => IntKind::I64, b'u' => IntKind::U64]) {
+                        Some(kind) => Some(kind),
+                        None => return (Err(NumberError::Invalid), rest_to_str!(bytes)),
+                    }
+                } else {
+                    consume_map!(bytes, [b'i' => IntKind::I32, b'u' => IntKind::U32])
+                };
 
                 (
                     parse_hex_int(is_negative, digits, kind),
@@ -286,12 +301,22 @@ fn parse(input: &str) -> (Result<Number, NumberError>, &str) {
 
                 let digits_with_sign = general_extract.end(bytes);
 
-                let kind = consume_map!(bytes, [
-                    b'i' => Kind::Int(IntKind::I32),
-                    b'u' => Kind::Int(IntKind::U32),
-                    b'f' => Kind::Float(FloatKind::F32),
-                    b'h' => Kind::Float(FloatKind::F16)
-                ]);
+                let kind = if consume!(bytes, b'l') {
+                    match consume_map!(bytes, [
+                        b'i' => Kind::Int(IntKind::I64),
+                        b'u' => Kind::Int(IntKind::U64)
+                    ]) {
+                        Some(kind) => Some(kind),
+                        None => return (Err(NumberError::Invalid), rest_to_str!(bytes)),
+                    }
+                } else {
+                    consume_map!(bytes, [
+                        b'i' => Kind::Int(IntKind::I32),
+                        b'u' => Kind::Int(IntKind::U32),
+                        b'f' => Kind::Float(FloatKind::F32),
+                        b'h' => Kind::Float(FloatKind::F16)
+                    ])
+                };
 
                 (
                     parse_dec(is_negative, digits_with_sign, kind),
@@ -387,7 +412,18 @@ fn parse_hex_float(input: &str, kind: Option<FloatKind>) -> Result<Number, Numbe
             // can only be ParseHexfErrorKind::Inexact but we can't check since it's private
             _ => Err(NumberError::NotRepresentable),
         },
-        Some(FloatKind::F16) => Err(NumberError::UnimplementedF16),
+        Some(FloatKind::F16) => match hexf_parse::parse_hexf32(input, false) {
+            // Hex literals must be exact.
+            Ok(num) => {
+                let bits = crate::proc::f32_to_f16(num);
+                if crate::proc::f16_to_f32(bits) == num {
+                    Ok(Number::F16(bits))
+                } else {
+                    Err(NumberError::NotRepresentable)
+                }
+            }
+            _ => Err(NumberError::NotRepresentable),
+        },
     }
 }
 
@@ -407,7 +443,14 @@ fn parse_dec_float(input: &str, kind: Option<FloatKind>) -> Result<Number, Numbe
                 .then_some(Number::F32(num))
                 .ok_or(NumberError::NotRepresentable)
         }
-        Some(FloatKind::F16) => Err(NumberError::UnimplementedF16),
+        Some(FloatKind::F16) => {
+            let num = input.parse::<f32>().unwrap(); // will never fail
+            let bits = crate::proc::f32_to_f16(num);
+            crate::proc::f16_to_f32(bits)
+                .is_finite()
+                .then_some(Number::F16(bits))
+                .ok_or(NumberError::NotRepresentable)
+        }
     }
 }
 
@@ -439,5 +482,14 @@ fn parse_int(
             Ok(num) => Ok(Number::U32(num)),
             Err(e) => Err(map_err(e)),
         },
+        Some(IntKind::I64) => match i64::from_str_radix(input, radix) {
+            Ok(num) => Ok(Number::I64(num)),
+            Err(e) => Err(map_err(e)),
+        },
+        Some(IntKind::U64) if is_negative => Err(NumberError::NotRepresentable),
+        Some(IntKind::U64) => match u64::from_str_radix(input, radix) {
+            Ok(num) => Ok(Number::U64(num)),
+            Err(e) => Err(map_err(e)),
+        },
     }
 }
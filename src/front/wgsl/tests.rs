@@ -635,3 +635,122 @@ fn parse_missing_workgroup_size() {
         Error::MissingWorkgroupSize(span) if span == Span::new(1, 8)
     ));
 }
+
+/// Build loops out of every combination of loop form, placement of a control
+/// flow statement within the body, and `continuing` contents, and check that
+/// each one lowers to a valid module.
+#[test]
+fn lower_loop_control_flow_combinations() {
+    use crate::valid::{Capabilities, ValidationFlags, Validator};
+
+    // Ways of wrapping a loop around `BODY`, with `CONTINUING` as the
+    // contents of the `continuing` block where the form has one.
+    let loops = [
+        "loop { if i >= n { break; } BODY continuing { CONTINUING } }",
+        "loop { if i >= n { break; } BODY continuing { CONTINUING break if stop(i); } }",
+        "loop { BODY continuing { CONTINUING break if i >= n; } }",
+        "while i < n { BODY }",
+        "for (var j = 0; j < n; j++) { BODY }",
+        "for (; stop(i) == false; i++) { BODY }",
+    ];
+    // Places to put `STMT` within a loop body.
+    let placements = [
+        "STMT",
+        "if i == 2 { STMT }",
+        "if i == 2 { acc += 1; } else { STMT }",
+        "switch i { case 1: { STMT } default: { acc += 2; } }",
+        "switch i { case 1, 2: { acc += 1; } default: { STMT } }",
+        "switch i { default: { STMT } }",
+        "if i > 1 { switch i { case 3: { if acc > 4 { STMT } } default: {} } }",
+        "switch i { case 0: { switch acc { case 1: { STMT } default: {} } } default: {} }",
+        "{ let k = i * 2; if k > acc { STMT } }",
+        "loop { if acc > 8 { break; } acc += 1; STMT }",
+        "for (var k = 0; k < 2; k++) { switch k { case 1: { STMT } default: {} } }",
+    ];
+    let statements = ["continue;", "break;", "return acc;", "acc += 3;"];
+    let continuings = [
+        "",
+        "i += 1;",
+        "let m = i + acc; acc = m;",
+        "i++; if acc > 100 { acc = 0; }",
+    ];
+
+    for lp in loops {
+        for placement in placements {
+            for stmt in statements {
+                let body = format!("acc += 1; i += 1; {}", placement.replace("STMT", stmt));
+                for continuing in continuings {
+                    if !lp.contains("CONTINUING") && !continuing.is_empty() {
+                        continue;
+                    }
+                    let source = format!(
+                        "
+                        fn stop(i: i32) -> bool {{
+                            return i > 10;
+                        }}
+
+                        fn f(n: i32) -> i32 {{
+                            var i = 0;
+                            var acc = 0;
+                            {}
+                            return acc;
+                        }}
+                        ",
+                        lp.replace("BODY", &body).replace("CONTINUING", continuing)
+                    );
+                    let module = parse_str(&source)
+                        .unwrap_or_else(|e| panic!("{}\n{source}", e.emit_to_string(&source)));
+                    Validator::new(ValidationFlags::all(), Capabilities::empty())
+                        .validate(&module)
+                        .unwrap_or_else(|e| panic!("{e:?}\n{source}"));
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn phony_assignment() {
+    use crate::valid::{Capabilities, ValidationFlags, Validator};
+
+    let module = parse_str(
+        "
+        @group(0) @binding(0) var tex: texture_2d<f32>;
+        @group(0) @binding(1) var samp: sampler;
+        @group(0) @binding(2) var<storage> data: array<u32>;
+        @group(0) @binding(3) var<uniform> scale: f32;
+
+        fn five() -> i32 { return 5; }
+
+        @compute @workgroup_size(1)
+        fn main() {
+            _ = tex;
+            _ = samp;
+            _ = &data;
+            let a = scale * 2.0;
+            _ = a;
+            for (_ = five(); false; _ = five()) {}
+        }
+        ",
+    )
+    .unwrap();
+    let info = Validator::new(ValidationFlags::all(), Capabilities::empty())
+        .validate(&module)
+        .unwrap();
+
+    // Every global is part of the entry point's interface.
+    let ep_info = info.get_entry_point(0);
+    for (handle, var) in module.global_variables.iter() {
+        assert!(!ep_info[handle].is_empty(), "{:?} is unused", var.name);
+    }
+
+    // Phony assignments of named values keep their names.
+    let function = &module.entry_points[0].function;
+    let names: Vec<_> = function
+        .named_expressions
+        .values()
+        .map(|name| name.as_str())
+        .collect();
+    assert!(names.contains(&"a"));
+    assert!(names.contains(&"phony"));
+}
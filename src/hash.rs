@@ -0,0 +1,499 @@
+/*!
+Stable fingerprints of modules, for caching.
+
+A cache of translated shaders needs a key that changes whenever the module
+does, and only then. [`module_fingerprint`] hashes everything that affects
+the meaning of a module, skipping its spans, so reformatting or moving
+code around in the source doesn't invalidate the cache:
+
+```
+# let module = naga::Module::default();
+let key = naga::hash::module_fingerprint(&module);
+println!("cache/{key}.spv");
+```
+
+The module is hashed through its `serialize` implementations, a field at a
+time, without building a serialized copy. Integers are hashed as
+little-endian bytes and floats by their bits, so the fingerprint is the same
+on every platform, and two literals only hash the same if they are
+bit-for-bit identical.
+
+# Stability
+
+A module's fingerprint stays the same in every patch release of the same
+minor version of Naga. It may change in any minor release, since the IR
+does, so caches should include Naga's version in their keys too, or be
+dropped when it is upgraded.
+*/
+
+use crate::Module;
+use serde::ser::{self, Serialize};
+use std::fmt;
+
+/// A 128-bit fingerprint of a module.
+///
+/// This displays as 32 lowercase hexadecimal digits, which are safe to use
+/// in file names.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Fingerprint(pub u128);
+
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:032x}", self.0)
+    }
+}
+
+/// What [`module_fingerprint_with_options`] leaves out of the fingerprint.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Options {
+    /// Leave out the names of types, struct members, constants, globals,
+    /// functions, arguments, local variables and named expressions.
+    ///
+    /// Entry point names are always hashed, since pipelines refer to entry
+    /// points by name. Renaming anything else only changes the names in the
+    /// generated code, which caches of compiled shaders may not care about.
+    pub ignore_names: bool,
+}
+
+/// Return the fingerprint of `module`, ignoring spans.
+pub fn module_fingerprint(module: &Module) -> Fingerprint {
+    module_fingerprint_with_options(module, &Options::default())
+}
+
+/// Return the fingerprint of `module`, ignoring spans, and whatever else
+/// `options` asks for.
+pub fn module_fingerprint_with_options(module: &Module, options: &Options) -> Fingerprint {
+    let mut hasher = Hasher {
+        state: FNV_OFFSET_BASIS,
+        ignore_names: options.ignore_names,
+    };
+    // Nothing in the IR fails to serialize, and the hasher itself never fails.
+    module.serialize(&mut hasher).unwrap();
+    Fingerprint(hasher.state)
+}
+
+const FNV_OFFSET_BASIS: u128 = 0x6c62272e07bb014262b821756295c58d;
+const FNV_PRIME: u128 = 0x0000000001000000000000000000013b;
+
+/// A serializer that feeds everything it is given to a 128-bit FNV-1a hash.
+///
+/// Every value is hashed in a way that can't be confused with its
+/// neighbours: sequences and strings hash their lengths, enums the index of
+/// their variant, and structs the names of their fields.
+struct Hasher {
+    state: u128,
+    ignore_names: bool,
+}
+
+impl Hasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.state ^= byte as u128;
+            self.state = self.state.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    fn write_len(&mut self, len: usize) {
+        self.write(&(len as u64).to_le_bytes());
+    }
+
+    fn compound(&mut self, len: Option<usize>) -> Compound<'_> {
+        if let Some(len) = len {
+            self.write_len(len);
+        }
+        Compound {
+            hasher: self,
+            unknown_len: len.is_none(),
+            entry_point: false,
+        }
+    }
+
+    fn fixed(&mut self) -> Compound<'_> {
+        Compound {
+            hasher: self,
+            unknown_len: false,
+            entry_point: false,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Error(String);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+/// The state of a sequence, map or struct being hashed.
+struct Compound<'a> {
+    hasher: &'a mut Hasher,
+    /// Whether the length wasn't known in advance, so each element must be
+    /// marked, and the end too.
+    unknown_len: bool,
+    /// Whether this is the struct of an entry point.
+    entry_point: bool,
+}
+
+impl Compound<'_> {
+    fn element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        if self.unknown_len {
+            self.hasher.write(&[1]);
+        }
+        value.serialize(&mut *self.hasher)
+    }
+
+    fn field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) -> Result<(), Error> {
+        self.hasher.write_len(key.len());
+        self.hasher.write(key.as_bytes());
+        let name = (key == "name" && !self.entry_point) || key == "named_expressions";
+        if name && self.hasher.ignore_names {
+            return Ok(());
+        }
+        value.serialize(&mut *self.hasher)
+    }
+
+    fn finish(self) -> Result<(), Error> {
+        if self.unknown_len {
+            self.hasher.write(&[0]);
+        }
+        Ok(())
+    }
+}
+
+macro_rules! write_le {
+    ($($method:ident: $ty:ty,)*) => {
+        $(
+            fn $method(self, v: $ty) -> Result<(), Error> {
+                self.write(&v.to_le_bytes());
+                Ok(())
+            }
+        )*
+    };
+}
+
+impl<'a> ser::Serializer for &'a mut Hasher {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Compound<'a>;
+    type SerializeTuple = Compound<'a>;
+    type SerializeTupleStruct = Compound<'a>;
+    type SerializeTupleVariant = Compound<'a>;
+    type SerializeMap = Compound<'a>;
+    type SerializeStruct = Compound<'a>;
+    type SerializeStructVariant = Compound<'a>;
+
+    write_le! {
+        serialize_i8: i8,
+        serialize_i16: i16,
+        serialize_i32: i32,
+        serialize_i64: i64,
+        serialize_i128: i128,
+        serialize_u8: u8,
+        serialize_u16: u16,
+        serialize_u32: u32,
+        serialize_u64: u64,
+        serialize_u128: u128,
+    }
+
+    fn serialize_bool(self, v: bool) -> Result<(), Error> {
+        self.write(&[v as u8]);
+        Ok(())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), Error> {
+        self.serialize_u32(v.to_bits())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), Error> {
+        self.serialize_u64(v.to_bits())
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), Error> {
+        self.serialize_u32(v as u32)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), Error> {
+        self.serialize_bytes(v.as_bytes())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+        self.write_len(v.len());
+        self.write(v);
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<(), Error> {
+        self.write(&[0]);
+        Ok(())
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<(), Error> {
+        self.write(&[1]);
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<(), Error> {
+        self.serialize_u32(variant_index)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.write(&variant_index.to_le_bytes());
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Compound<'a>, Error> {
+        Ok(self.compound(len))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Compound<'a>, Error> {
+        Ok(self.fixed())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a>, Error> {
+        Ok(self.fixed())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a>, Error> {
+        self.write(&variant_index.to_le_bytes());
+        Ok(self.fixed())
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Compound<'a>, Error> {
+        Ok(self.compound(len))
+    }
+
+    fn serialize_struct(self, name: &'static str, _len: usize) -> Result<Compound<'a>, Error> {
+        let mut compound = self.fixed();
+        compound.entry_point = name == "EntryPoint";
+        Ok(compound)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a>, Error> {
+        self.write(&variant_index.to_le_bytes());
+        Ok(self.fixed())
+    }
+
+    fn collect_str<T: ?Sized + fmt::Display>(self, value: &T) -> Result<(), Error> {
+        self.serialize_str(&value.to_string())
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+impl ser::SerializeSeq for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeMap for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), Error> {
+        self.element(key)
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        value.serialize(&mut *self.hasher)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+#[cfg(all(test, feature = "wgsl-in"))]
+mod tests {
+    use super::{module_fingerprint, module_fingerprint_with_options, Fingerprint, Options};
+
+    fn fingerprint(source: &str, ignore_names: bool) -> Fingerprint {
+        let module = crate::front::wgsl::parse_str(source).unwrap();
+        module_fingerprint_with_options(&module, &Options { ignore_names })
+    }
+
+    const SOURCE: &str = "
+        @group(0) @binding(0) var<storage, read_write> data: array<f32>;
+
+        @compute @workgroup_size(64)
+        fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+            let value = data[id.x];
+            data[id.x] = value * 2.0;
+        }
+    ";
+
+    #[test]
+    fn spans_are_ignored() {
+        let module = crate::front::wgsl::parse_str(SOURCE).unwrap();
+        assert_eq!(module_fingerprint(&module), fingerprint(SOURCE, false));
+
+        let reformatted = SOURCE.replace("        ", "").replace(" * ", "*");
+        assert_eq!(fingerprint(&reformatted, false), fingerprint(SOURCE, false));
+    }
+
+    #[test]
+    fn meaning_is_not_ignored() {
+        let doubled = fingerprint(SOURCE, true);
+        assert_ne!(fingerprint(&SOURCE.replace("2.0", "3.0"), true), doubled);
+        assert_ne!(fingerprint(&SOURCE.replace("64", "32"), true), doubled);
+        // Entry points are looked up by name, so their names always count.
+        assert_ne!(
+            fingerprint(&SOURCE.replace("fn main", "fn entry"), true),
+            doubled
+        );
+    }
+
+    #[test]
+    fn names_can_be_ignored() {
+        let renamed = SOURCE
+            .replace("value", "x")
+            .replace("data", "buffer")
+            .replace("id:", "gid:")
+            .replace("id.x", "gid.x");
+        assert_ne!(fingerprint(&renamed, false), fingerprint(SOURCE, false));
+        assert_eq!(fingerprint(&renamed, true), fingerprint(SOURCE, true));
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(
+            Fingerprint(0xabc).to_string(),
+            "00000000000000000000000000000abc"
+        );
+    }
+}
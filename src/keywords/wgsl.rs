@@ -5,7 +5,11 @@ Keywords for [WGSL][wgsl] (WebGPU Shading Language).
 */
 
 // https://gpuweb.github.io/gpuweb/wgsl/#keyword-summary
-// last sync: https://github.com/gpuweb/gpuweb/blob/39f2321f547c8f0b7f473cf1d47fba30b1691303/wgsl/index.bs
+// https://gpuweb.github.io/gpuweb/wgsl/#reserved-words
+//
+// This keeps words the specification has since dropped, like
+// `static_assert`, so that the WGSL backend never uses them as names for
+// compilers that still reserve them.
 pub const RESERVED: &[&str] = &[
     // Type-defining Keywords
     "array",
@@ -52,9 +56,11 @@ pub const RESERVED: &[&str] = &[
     "break",
     "case",
     "const",
+    "const_assert",
     "continue",
     "continuing",
     "default",
+    "diagnostic",
     "discard",
     "else",
     "enable",
@@ -161,6 +167,8 @@ pub const RESERVED: &[&str] = &[
     "noexcept",
     "noinline",
     "nointerpolation",
+    "non_coherent",
+    "noncoherent",
     "noperspective",
     "null",
     "nullptr",
@@ -184,6 +192,7 @@ pub const RESERVED: &[&str] = &[
     "regardless",
     "register",
     "reinterpret_cast",
+    "require",
     "requires",
     "resource",
     "restrict",
@@ -227,3 +236,21 @@ pub const RESERVED: &[&str] = &[
     "writeonly",
     "yield",
 ];
+
+/// Words that name types only Naga provides.
+///
+/// Under [`Profile::Native`] these are reserved like [`RESERVED`], so that
+/// sources can't declare names the types would clash with. WebGPU has no
+/// such types, so under [`Profile::WebGpu`] they are ordinary identifiers.
+///
+/// [`Profile::Native`]: crate::valid::Profile::Native
+/// [`Profile::WebGpu`]: crate::valid::Profile::WebGpu
+pub const NATIVE_RESERVED: &[&str] = &[
+    "f64",
+    "i64",
+    "u64",
+    "acceleration_structure",
+    "ray_query",
+    "RayDesc",
+    "RayIntersection",
+];
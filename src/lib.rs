@@ -178,6 +178,7 @@ variants, whose operands (if any) are also constant expressions:
 - [`Constant`], for [`Constant`s][const_type] whose [`override`] is [`None`]
 - [`ZeroValue`], for fixed-size types
 - [`Compose`]
+- [`Repeat`]
 - [`Access`]
 - [`AccessIndex`]
 - [`Splat`]
@@ -227,6 +228,7 @@ An override expression can be evaluated at pipeline creation time.
 [`Literal`]: Expression::Literal
 [`ZeroValue`]: Expression::ZeroValue
 [`Compose`]: Expression::Compose
+[`Repeat`]: Expression::Repeat
 [`Access`]: Expression::Access
 [`AccessIndex`]: Expression::AccessIndex
 [`Splat`]: Expression::Splat
@@ -281,15 +283,31 @@ pub mod back;
 mod block;
 #[cfg(feature = "compact")]
 pub mod compact;
+pub mod diagnostic_filter;
 pub mod front;
+#[cfg(feature = "hash")]
+pub mod hash;
 pub mod keywords;
 pub mod proc;
+pub mod reflect;
+#[cfg(feature = "binary")]
+pub mod serialize;
 mod span;
+#[cfg(feature = "test-util")]
+pub mod test_util;
 pub mod valid;
 
-pub use crate::arena::{Arena, Handle, Range, UniqueArena};
-
-pub use crate::span::{SourceLocation, Span, SpanContext, WithSpan};
+pub use crate::arena::{Arena, ArenaFull, ArenaUsage, Handle, Range, UniqueArena};
+pub use crate::proc::{link, LinkError};
+
+#[cfg(any(feature = "span", feature = "wgsl-in"))]
+pub use crate::span::EmitOptions;
+#[cfg(feature = "span")]
+pub use crate::span::SourceMap;
+pub use crate::span::{
+    Diagnose, Diagnostic, Severity, SourceId, SourceLocation, Span, SpanContext, Suggestion,
+    WithSpan,
+};
 #[cfg(feature = "arbitrary")]
 use arbitrary::Arbitrary;
 #[cfg(feature = "deserialize")]
@@ -435,6 +453,9 @@ pub enum BuiltIn {
     WorkGroupId,
     WorkGroupSize,
     NumWorkGroups,
+    // subgroup, which need `Capabilities::SUBGROUP`
+    SubgroupSize,
+    SubgroupInvocationId,
 }
 
 /// Number of bytes per scalar.
@@ -564,6 +585,16 @@ bitflags::bitflags! {
         const LOAD = 0x1;
         /// Storage can be used as a target for store ops.
         const STORE = 0x2;
+        /// Storage can be used as a target for atomic ops.
+        const ATOMIC = 0x4;
+        /// Writes to the storage are visible to invocations in other
+        /// workgroups, not just to those in the same one.
+        ///
+        /// This is only supported for storage buffers and storage textures
+        /// that can be written. Backends declare them `globallycoherent` in
+        /// HLSL, `coherent` in GLSL, and with the `Coherent` decoration in
+        /// SPIR-V.
+        const COHERENT = 0x8;
     }
 }
 
@@ -778,7 +809,9 @@ pub enum TypeInner {
     /// There must always be at least one member.
     ///
     /// A `Struct` type is [`DATA`], and the types of its members must be
-    /// `DATA` as well.
+    /// `DATA` as well. In particular, members can't be images, samplers,
+    /// acceleration structures, or binding arrays of them: resource handles
+    /// can only be global variables or function arguments.
     ///
     /// Member types must be [`SIZED`], except for the final member of a
     /// struct, which may be a dynamically sized [`Array`]. The
@@ -859,8 +892,15 @@ pub enum Literal {
     F64(f64),
     /// May not be NaN or infinity.
     F32(f32),
+    /// The bits of an IEEE 754 binary16 value.
+    ///
+    /// May not be NaN or infinity. See [`proc::f16_to_f32`] and
+    /// [`proc::f32_to_f16`] for conversions.
+    F16(u16),
     U32(u32),
     I32(i32),
+    U64(u64),
+    I64(i64),
     Bool(bool),
 }
 
@@ -873,6 +913,12 @@ pub enum Override {
     None,
     ByName,
     ByNameOrId(u32),
+    /// The constant can't be overridden itself, but its [`init`] is an
+    /// [override expression] on constants that can.
+    ///
+    /// [`init`]: Constant::init
+    /// [override expression]: index.html#override-expressions
+    Derived,
 }
 
 /// Constant value.
@@ -1107,6 +1153,68 @@ pub enum DerivativeAxis {
     Width,
 }
 
+/// How a subgroup operation combines values.
+#[derive(Clone, Copy, Debug, Hash, Eq, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serialize", derive(Serialize))]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
+pub enum SubgroupReduction {
+    Add,
+    Mul,
+    Min,
+    Max,
+    /// Bitwise and. Only applies to integers.
+    And,
+    /// Bitwise or. Only applies to integers.
+    Or,
+    /// Bitwise exclusive or. Only applies to integers.
+    Xor,
+}
+
+/// Operation across the active invocations of a subgroup.
+///
+/// Invocations that don't execute the operation, because of control flow,
+/// don't take part in it.
+#[derive(Clone, Copy, Debug, Hash, Eq, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serialize", derive(Serialize))]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(Arbitrary))]
+pub enum SubgroupOperation {
+    /// Return a `vec4<u32>` bitmask of the invocations whose `bool` argument
+    /// is true, with the invocation of ID `i` at bit `i % 32` of component
+    /// `i / 32`.
+    Ballot,
+    /// Return whether the `bool` argument is true in all invocations.
+    All,
+    /// Return whether the `bool` argument is true in any invocation.
+    Any,
+    /// Combine the argument across all invocations.
+    Reduce(SubgroupReduction),
+    /// Combine the argument across the invocations whose ID is at most this
+    /// invocation's.
+    InclusiveScan(SubgroupReduction),
+    /// Combine the argument across the invocations whose ID is less than
+    /// this invocation's. The invocation of lowest ID gets the reduction's
+    /// identity.
+    ExclusiveScan(SubgroupReduction),
+    /// Return the argument of the invocation of lowest ID.
+    BroadcastFirst,
+    /// Return the argument of the invocation whose ID is the given `u32`,
+    /// which must be a constant.
+    Broadcast(Handle<Expression>),
+    /// Return the argument of the invocation whose ID is the given `u32`.
+    Shuffle(Handle<Expression>),
+    /// Return the argument of the invocation whose ID is this invocation's
+    /// plus the given `u32`.
+    ShuffleDown(Handle<Expression>),
+    /// Return the argument of the invocation whose ID is this invocation's
+    /// minus the given `u32`.
+    ShuffleUp(Handle<Expression>),
+    /// Return the argument of the invocation whose ID is this invocation's
+    /// exclusive or the given `u32`.
+    ShuffleXor(Handle<Expression>),
+}
+
 /// Built-in shader function for testing relation between values.
 #[derive(Clone, Copy, Debug, Hash, Eq, Ord, PartialEq, PartialOrd)]
 #[cfg_attr(feature = "serialize", derive(Serialize))]
@@ -1291,6 +1399,19 @@ pub enum Expression {
         ty: Handle<Type>,
         components: Vec<Handle<Expression>>,
     },
+    /// An array whose elements are all `value`.
+    ///
+    /// This is equivalent to a [`Compose`] whose components are all `value`,
+    /// but it doesn't grow with the length of the array.
+    ///
+    /// `ty` must be an array type with a constant size, whose element type
+    /// is the type of `value`.
+    ///
+    /// [`Compose`]: Expression::Compose
+    Repeat {
+        ty: Handle<Type>,
+        value: Handle<Expression>,
+    },
 
     /// Array access with a computed index.
     ///
@@ -1501,6 +1622,11 @@ pub enum Expression {
     ///
     /// Note that, because expressions have no side effects, it is unobservable
     /// whether the non-selected branch is evaluated.
+    ///
+    /// With [`Capabilities::POINTER_SELECT`], `accept` and `reject` may also
+    /// be pointers of the same type, when `condition` is a single boolean.
+    ///
+    /// [`Capabilities::POINTER_SELECT`]: valid::Capabilities::POINTER_SELECT
     Select {
         /// Boolean expression
         condition: Handle<Expression>,
@@ -1527,6 +1653,33 @@ pub enum Expression {
         arg3: Option<Handle<Expression>>,
     },
     /// Cast a simple type to another kind.
+    ///
+    /// When `convert` is `Some`, each component is converted by value:
+    ///
+    /// - A [`Bool`] converts to one if it is `true`, and zero if it is `false`.
+    ///
+    /// - A number converts to [`Bool`] `true` if it is not zero. NaN converts
+    ///   to `true`.
+    ///
+    /// - A [`Float`] converts to [`Sint`] or [`Uint`] by rounding toward zero.
+    ///   If the result is out of range, the conversion saturates to the
+    ///   nearest value that is exactly representable in both types, as WGSL
+    ///   specifies; for `f32` to `i32`, that range is -2<sup>31</sup> to
+    ///   2<sup>31</sup> - 128. Not every backend's target language does this
+    ///   on its own, so backends only guarantee it after
+    ///   [`proc::saturate_float_to_int`] has run. Converting NaN is
+    ///   unspecified.
+    ///
+    /// - Integers convert to [`Float`] by rounding to nearest, and between
+    ///   [`Sint`] and [`Uint`] of the same width by reinterpreting their bits.
+    ///
+    /// The constant evaluator follows these rules.
+    ///
+    /// [`Bool`]: ScalarKind::Bool
+    /// [`Float`]: ScalarKind::Float
+    /// [`Sint`]: ScalarKind::Sint
+    /// [`Uint`]: ScalarKind::Uint
+    /// [`proc::saturate_float_to_int`]: proc::saturate_float_to_int
     As {
         /// Source expression, which can only be a scalar or a vector.
         expr: Handle<Expression>,
@@ -1567,9 +1720,37 @@ pub enum Expression {
         query: Handle<Expression>,
         committed: bool,
     },
+
+    /// Perform `op` on `argument` across the active invocations of the
+    /// subgroup.
+    ///
+    /// Shuffles and broadcasts take a scalar or vector of numbers, and so do
+    /// reductions and scans, except that the bitwise ones only take integers.
+    /// Operations whose result is undefined, like reading from an inactive
+    /// invocation, return an unspecified value.
+    ///
+    /// Requires [`Capabilities::SUBGROUP`].
+    ///
+    /// [`Capabilities::SUBGROUP`]: valid::Capabilities::SUBGROUP
+    SubgroupOperation {
+        op: SubgroupOperation,
+        argument: Handle<Expression>,
+    },
+
+    /// The value of an integer scalar, which may differ between the
+    /// invocations of a draw or dispatch.
+    ///
+    /// The uniformity analysis takes the result to be non-uniform, even if
+    /// the operand is uniform. Indexing a [`BindingArray`] with it is
+    /// non-uniform indexing: the validator requires the capability for that,
+    /// and backends mark the access as non-uniform, like SPIR-V's
+    /// `NonUniform` decoration or HLSL's `NonUniformResourceIndex`.
+    ///
+    /// [`BindingArray`]: TypeInner::BindingArray
+    NonUniform(Handle<Expression>),
 }
 
-pub use block::Block;
+pub use block::{Block, ScopedLocal};
 
 /// The value of the switch case.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
@@ -1782,6 +1963,12 @@ pub enum Statement {
     /// images with mipmaps is not supported, so there are no `level` or
     /// `sample` operands.
     ///
+    /// The `value` is always a four-component vector whose scalar kind is the
+    /// channel type of the image's [`StorageFormat`]: `Float` for float and
+    /// normalized formats, and `Sint` or `Uint` for integer formats. Converting
+    /// it to the texel's representation, including dropping components the
+    /// format doesn't have, is left to the backend.
+    ///
     /// This statement is a barrier for any operations on the corresponding
     /// [`Expression::GlobalVariable`] for this image.
     ///
@@ -1792,6 +1979,31 @@ pub enum Statement {
         array_index: Option<Handle<Expression>>,
         value: Handle<Expression>,
     },
+    /// Performs an atomic operation on a texel of an image.
+    ///
+    /// The `image`, `coordinate`, and `array_index` fields have the same
+    /// meanings as the corresponding operands of an [`ImageStore`] statement.
+    /// The image must be a storage image with [`StorageAccess::ATOMIC`]
+    /// access, and a single-channel 32-bit integer format: [`R32Uint`] or
+    /// [`R32Sint`].
+    ///
+    /// The `value` is a scalar of the format's channel type. There is no
+    /// result expression, so [`AtomicFunction::Exchange`] may not have a
+    /// `compare` operand.
+    ///
+    /// This statement is a barrier for any operations on the corresponding
+    /// [`Expression::GlobalVariable`] for this image.
+    ///
+    /// [`ImageStore`]: Statement::ImageStore
+    /// [`R32Uint`]: StorageFormat::R32Uint
+    /// [`R32Sint`]: StorageFormat::R32Sint
+    ImageAtomic {
+        image: Handle<Expression>,
+        coordinate: Handle<Expression>,
+        array_index: Option<Handle<Expression>>,
+        fun: AtomicFunction,
+        value: Handle<Expression>,
+    },
     /// Atomic function.
     Atomic {
         /// Pointer to an atomic value.
@@ -2046,4 +2258,10 @@ pub struct Module {
     pub functions: Arena<Function>,
     /// Entry points.
     pub entry_points: Vec<EntryPoint>,
+    /// Filters that change the severity of diagnostics anywhere in the
+    /// module, like WGSL's global `diagnostic` directives.
+    ///
+    /// Where several apply to the same rule, the last one wins.
+    #[cfg_attr(any(feature = "serialize", feature = "deserialize"), serde(default))]
+    pub diagnostic_filters: Vec<diagnostic_filter::DiagnosticFilter>,
 }
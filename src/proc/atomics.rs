@@ -0,0 +1,501 @@
+/*!
+Making the atomics of unshared variables plain.
+
+WGSL only allows atomics in the [`Storage`] and [`WorkGroup`] address
+spaces, where other invocations can see them. SPIR-V has no such rule, and
+compilers like DXC sometimes leave atomics on [`Private`] and [`Function`]
+variables. No other invocation can reach those, so an atomic operation on
+them is no different from a load, an ordinary operation and a store.
+[`lower_unshared_atomics`] rewrites them that way, so that the module
+validates.
+
+[`Storage`]: crate::AddressSpace::Storage
+[`WorkGroup`]: crate::AddressSpace::WorkGroup
+[`Private`]: crate::AddressSpace::Private
+[`Function`]: crate::AddressSpace::Function
+*/
+
+use super::scalarize::{adjust_expression, for_each_function, retarget_function, typify};
+use super::walk::{for_each_block_mut, for_each_statement};
+use crate::arena::{Arena, Handle, Range};
+use crate::{
+    AtomicFunction, BinaryOperator, Block, Expression, FastHashMap, FastHashSet, Function,
+    GlobalVariable, MathFunction, Statement, Type, TypeInner,
+};
+
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum AtomicLoweringError {
+    #[error(
+        "Function {caller:?} passes a pointer to atomics in a private or function \
+        variable to {callee:?}, which may not treat them as such once they are plain"
+    )]
+    PointerArgument {
+        caller: Option<String>,
+        callee: Handle<Function>,
+    },
+}
+
+/// Replace the atomics in `module`'s [`Private`] and [`Function`] variables
+/// with plain scalars, and each atomic operation on them with a load, the
+/// equivalent non-atomic operation and a store.
+///
+/// `atomicLoad` and `atomicStore` become plain loads and stores, and a
+/// compare-exchange becomes a select. Variables that hold atomics get new
+/// types with the atomics replaced by their scalar types.
+///
+/// A pointer to atomics in such a variable can't be passed to another
+/// function, since the callee's parameter type would no longer match. If
+/// the module does that, this returns an error and leaves `module`
+/// unchanged.
+///
+/// The module should be validated afterwards, to get a [`ModuleInfo`]
+/// that matches the new arenas.
+///
+/// # Panics
+///
+/// If `module` is malformed in other ways than where its atomics are, for
+/// example if its handles are out of range, this may panic.
+///
+/// [`Private`]: crate::AddressSpace::Private
+/// [`Function`]: crate::AddressSpace::Function
+/// [`ModuleInfo`]: crate::valid::ModuleInfo
+pub fn lower_unshared_atomics(module: &mut crate::Module) -> Result<(), AtomicLoweringError> {
+    let globals: FastHashSet<Handle<GlobalVariable>> = module
+        .global_variables
+        .iter()
+        .filter(|&(_, var)| {
+            var.space == crate::AddressSpace::Private && contains_atomic(&module.types, var.ty)
+        })
+        .map(|(handle, _)| handle)
+        .collect();
+
+    let functions = module.functions.iter().map(|(_, fun)| fun);
+    for fun in functions.chain(module.entry_points.iter().map(|ep| &ep.function)) {
+        check_calls(module, fun, &globals)?;
+    }
+
+    let handles: Vec<_> = module
+        .global_variables
+        .iter()
+        .map(|(handle, _)| handle)
+        .filter(|handle| globals.contains(handle))
+        .collect();
+    for handle in handles {
+        let ty = module.global_variables[handle].ty;
+        let plain = plain_type(&mut module.types, ty);
+        if let Some(init) = module.global_variables[handle].init {
+            plain_init(&mut module.types, &mut module.const_expressions, init);
+        }
+        module.global_variables.get_mut(handle).ty = plain;
+    }
+    for_each_function(module, |module, fun| lower_function(module, fun, &globals));
+    Ok(())
+}
+
+/// Return true if `ty` is or holds an atomic.
+fn contains_atomic(types: &crate::UniqueArena<Type>, ty: Handle<Type>) -> bool {
+    match types[ty].inner {
+        TypeInner::Atomic { .. } => true,
+        TypeInner::Array { base, .. } => contains_atomic(types, base),
+        TypeInner::Struct { ref members, .. } => members
+            .iter()
+            .any(|member| contains_atomic(types, member.ty)),
+        _ => false,
+    }
+}
+
+/// Return `ty` with each atomic in it replaced by its scalar type.
+fn plain_type(types: &mut crate::UniqueArena<Type>, ty: Handle<Type>) -> Handle<Type> {
+    if !contains_atomic(types, ty) {
+        return ty;
+    }
+    let mut inner = match types[ty].inner {
+        TypeInner::Atomic { kind, width } => TypeInner::Scalar { kind, width },
+        TypeInner::Array { base, size, stride } => TypeInner::Array { base, size, stride },
+        TypeInner::Struct { ref members, span } => TypeInner::Struct {
+            members: members.clone(),
+            span,
+        },
+        _ => unreachable!(),
+    };
+    match inner {
+        TypeInner::Array { ref mut base, .. } => *base = plain_type(types, *base),
+        TypeInner::Struct {
+            ref mut members, ..
+        } => {
+            for member in members.iter_mut() {
+                member.ty = plain_type(types, member.ty);
+            }
+        }
+        _ => {}
+    }
+    let name = types[ty].name.clone();
+    let span = types.get_span(ty);
+    types.insert(Type { name, inner }, span)
+}
+
+/// Give the initializer `init` of a variable whose atomics are being
+/// lowered the variable's new type.
+///
+/// Atomics can only be initialized with zeros, so the initializer is made
+/// of zero values and compositions of them.
+fn plain_init(
+    types: &mut crate::UniqueArena<Type>,
+    expressions: &mut Arena<Expression>,
+    init: Handle<Expression>,
+) {
+    match *expressions.get_mut(init) {
+        Expression::ZeroValue(ref mut ty) => *ty = plain_type(types, *ty),
+        Expression::Compose {
+            ref mut ty,
+            ref components,
+        } => {
+            *ty = plain_type(types, *ty);
+            for component in components.clone() {
+                plain_init(types, expressions, component);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Return, for each expression in `fun`, whether it is a pointer into one
+/// of the variables whose atomics are being lowered.
+fn lowered_pointers(
+    module: &crate::Module,
+    fun: &Function,
+    globals: &FastHashSet<Handle<GlobalVariable>>,
+) -> Vec<bool> {
+    let mut lowered = Vec::with_capacity(fun.expressions.len());
+    for (_, expr) in fun.expressions.iter() {
+        let is_lowered = match *expr {
+            Expression::GlobalVariable(handle) => globals.contains(&handle),
+            Expression::LocalVariable(handle) => {
+                contains_atomic(&module.types, fun.local_variables[handle].ty)
+            }
+            Expression::Access { base, .. } | Expression::AccessIndex { base, .. } => {
+                lowered[base.index()]
+            }
+            _ => false,
+        };
+        lowered.push(is_lowered);
+    }
+    lowered
+}
+
+/// Check that `fun` doesn't pass a pointer to atomics that are being
+/// lowered to another function.
+fn check_calls(
+    module: &crate::Module,
+    fun: &Function,
+    globals: &FastHashSet<Handle<GlobalVariable>>,
+) -> Result<(), AtomicLoweringError> {
+    let lowered = lowered_pointers(module, fun, globals);
+    if !lowered.contains(&true) {
+        return Ok(());
+    }
+    let typifier = typify(module, fun);
+    let mut calls = Vec::new();
+    for_each_statement(&fun.body, &mut |stmt| {
+        if let Statement::Call {
+            function,
+            ref arguments,
+            ..
+        } = *stmt
+        {
+            calls.push((function, arguments.clone()));
+        }
+    });
+    for (callee, arguments) in calls {
+        for argument in arguments {
+            if !lowered[argument.index()] {
+                continue;
+            }
+            if let TypeInner::Pointer { base, .. } = *typifier.get(argument, &module.types) {
+                if contains_atomic(&module.types, base) {
+                    return Err(AtomicLoweringError::PointerArgument {
+                        caller: fun.name.clone(),
+                        callee,
+                    });
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn lower_function(
+    module: &mut crate::Module,
+    fun: &mut Function,
+    globals: &FastHashSet<Handle<GlobalVariable>>,
+) -> bool {
+    let lowered = lowered_pointers(module, fun, globals);
+
+    // Find the atomic operations to lower, by their result expressions.
+    let mut targets = FastHashMap::default();
+    for_each_statement(&fun.body, &mut |stmt| {
+        if let Statement::Atomic {
+            pointer,
+            fun: op,
+            value,
+            result,
+        } = *stmt
+        {
+            if lowered[pointer.index()] {
+                targets.insert(result, (pointer, op, value));
+            }
+        }
+    });
+
+    let mut changed = false;
+    for (_, local) in fun.local_variables.iter_mut() {
+        let plain = plain_type(&mut module.types, local.ty);
+        if plain != local.ty {
+            if let Some(init) = local.init {
+                plain_init(&mut module.types, &mut fun.expressions, init);
+            }
+            changed = true;
+        }
+        local.ty = plain;
+    }
+    if targets.is_empty() {
+        return changed;
+    }
+
+    // Rebuild the arena, putting each operation's load and computation in
+    // place of its result, which front ends add after its operands. The
+    // result isn't covered by any `Emit`, so the new expressions aren't
+    // either, and get their own `Emit` below.
+    let old = std::mem::take(&mut fun.expressions);
+    let mut first = Vec::with_capacity(old.len());
+    let mut last: Vec<Handle<Expression>> = Vec::with_capacity(old.len());
+    let mut stores = FastHashMap::default();
+    for (handle, expr) in old.iter() {
+        let span = old.get_span(handle);
+        let Some(&(pointer, op, value)) = targets.get(&handle) else {
+            let mut expr = expr.clone();
+            adjust_expression(&mut expr, &mut |h| *h = last[h.index()]);
+            let new = fun.expressions.append(expr, span);
+            first.push(new);
+            last.push(new);
+            continue;
+        };
+
+        let (pointer, value) = (last[pointer.index()], last[value.index()]);
+        let start = fun.expressions.len();
+        let loaded = fun.expressions.append(Expression::Load { pointer }, span);
+        let binary = |op| Expression::Binary {
+            op,
+            left: loaded,
+            right: value,
+        };
+        let (stored, result) = match op {
+            AtomicFunction::Add => (binary(BinaryOperator::Add), loaded),
+            AtomicFunction::Subtract => (binary(BinaryOperator::Subtract), loaded),
+            AtomicFunction::And => (binary(BinaryOperator::And), loaded),
+            AtomicFunction::ExclusiveOr => (binary(BinaryOperator::ExclusiveOr), loaded),
+            AtomicFunction::InclusiveOr => (binary(BinaryOperator::InclusiveOr), loaded),
+            AtomicFunction::Min | AtomicFunction::Max => {
+                let math = match op {
+                    AtomicFunction::Min => MathFunction::Min,
+                    _ => MathFunction::Max,
+                };
+                let stored = Expression::Math {
+                    fun: math,
+                    arg: loaded,
+                    arg1: Some(value),
+                    arg2: None,
+                    arg3: None,
+                };
+                (stored, loaded)
+            }
+            AtomicFunction::Exchange { compare: None } => {
+                // Store the value as it is, without a new expression.
+                stores.insert(loaded, (fun.expressions.range_from(start), value));
+                first.push(loaded);
+                last.push(loaded);
+                continue;
+            }
+            AtomicFunction::Exchange {
+                compare: Some(compare),
+            } => {
+                let exchanged = fun.expressions.append(
+                    Expression::Binary {
+                        op: BinaryOperator::Equal,
+                        left: loaded,
+                        right: last[compare.index()],
+                    },
+                    span,
+                );
+                let stored = Expression::Select {
+                    condition: exchanged,
+                    accept: value,
+                    reject: loaded,
+                };
+                let ty = match *expr {
+                    Expression::AtomicResult { ty, .. } => ty,
+                    _ => unreachable!(),
+                };
+                let result = fun.expressions.append(
+                    Expression::Compose {
+                        ty,
+                        components: vec![loaded, exchanged],
+                    },
+                    span,
+                );
+                (stored, result)
+            }
+        };
+        let stored = fun.expressions.append(stored, span);
+        stores.insert(result, (fun.expressions.range_from(start), stored));
+        first.push(loaded);
+        last.push(result);
+    }
+
+    retarget_function(fun, &first, &last);
+    lower_block(&mut fun.body, &stores);
+    true
+}
+
+/// Replace each atomic operation in `block` whose result is in `stores`
+/// with an `Emit` of that range and a store of that value.
+fn lower_block(
+    block: &mut Block,
+    stores: &FastHashMap<Handle<Expression>, (Range<Expression>, Handle<Expression>)>,
+) {
+    for_each_block_mut(block, &mut |block| {
+        let mut index = 0;
+        while index < block.len() {
+            if let Statement::Atomic {
+                pointer, result, ..
+            } = block[index]
+            {
+                if let Some(&(ref range, value)) = stores.get(&result) {
+                    let span = block
+                        .span_iter()
+                        .nth(index)
+                        .map(|(_, &span)| span)
+                        .unwrap_or_default();
+                    let mut replacement = Block::with_capacity(2);
+                    replacement.push(Statement::Emit(range.clone()), span);
+                    replacement.push(Statement::Store { pointer, value }, span);
+                    block.splice(index..index + 1, replacement);
+                    index += 2;
+                    continue;
+                }
+            }
+            index += 1;
+        }
+    });
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod tests {
+    use super::AtomicLoweringError;
+    use crate::test_util::{self, Fixture};
+    use crate::valid::Capabilities;
+
+    #[test]
+    fn lower() {
+        // Atomics in these address spaces are what the validator rejects.
+        let mut module = test_util::parse(
+            "
+            struct Counters { hits: atomic<u32>, total: f32 }
+            var<private> counters: Counters;
+
+            @compute @workgroup_size(1)
+            fn main() {
+                var slot: atomic<i32>;
+                atomicStore(&slot, 3);
+                let old = atomicAdd(&counters.hits, 1u);
+                atomicMax(&slot, i32(old));
+                let swapped = atomicCompareExchangeWeak(&slot, 3, 7);
+                if swapped.exchanged {
+                    atomicExchange(&counters.hits, u32(atomicLoad(&slot)));
+                }
+                counters.total = f32(swapped.old_value);
+            }
+            ",
+        );
+        // Give `slot` an explicit zero initializer, as SPIR-V may.
+        let fun = &mut module.entry_points[0].function;
+        let (_, slot) = fun.local_variables.iter_mut().next().unwrap();
+        let zero = fun.expressions.append(
+            crate::Expression::ZeroValue(slot.ty),
+            crate::Span::UNDEFINED,
+        );
+        slot.init = Some(zero);
+        test_util::module_validation_error(&module, Capabilities::default());
+
+        super::lower_unshared_atomics(&mut module).unwrap();
+        let module = Fixture::module(module, Capabilities::default()).module;
+
+        let fun = &module.entry_points[0].function;
+        let (_, slot) = fun.local_variables.iter().next().unwrap();
+        assert_eq!(
+            fun.expressions[slot.init.unwrap()],
+            crate::Expression::ZeroValue(slot.ty)
+        );
+        let mut atomics = 0;
+        super::for_each_statement(&fun.body, &mut |stmt| {
+            if let crate::Statement::Atomic { .. } = *stmt {
+                atomics += 1;
+            }
+        });
+        assert_eq!(atomics, 0);
+        assert!(fun
+            .expressions
+            .iter()
+            .all(|(_, expr)| !matches!(*expr, crate::Expression::AtomicResult { .. })));
+    }
+
+    #[test]
+    fn shared_atomics_are_kept() {
+        let source = "
+            @group(0) @binding(0) var<storage, read_write> counter: atomic<u32>;
+            var<workgroup> local_counter: atomic<u32>;
+
+            @compute @workgroup_size(64)
+            fn main() {
+                atomicAdd(&counter, atomicAdd(&local_counter, 1u));
+            }
+        ";
+        let mut fixture = Fixture::wgsl(source);
+        super::lower_unshared_atomics(&mut fixture.module).unwrap();
+        fixture.revalidate();
+        let fun = &fixture.module.entry_points[0].function;
+        let mut atomics = 0;
+        super::for_each_statement(&fun.body, &mut |stmt| {
+            if let crate::Statement::Atomic { .. } = *stmt {
+                atomics += 1;
+            }
+        });
+        assert_eq!(atomics, 2);
+    }
+
+    #[test]
+    fn pointer_argument() {
+        let mut module = test_util::parse(
+            "
+            fn bump(p: ptr<private, atomic<u32>>) {
+                atomicAdd(p, 1u);
+            }
+
+            var<private> counter: atomic<u32>;
+
+            @compute @workgroup_size(1)
+            fn main() {
+                bump(&counter);
+            }
+            ",
+        );
+        let (bump, _) = module.functions.iter().next().unwrap();
+        assert_eq!(
+            super::lower_unshared_atomics(&mut module),
+            Err(AtomicLoweringError::PointerArgument {
+                caller: Some("main".to_string()),
+                callee: bump,
+            })
+        );
+    }
+}
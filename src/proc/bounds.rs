@@ -0,0 +1,578 @@
+/*!
+Applying bounds check policies in the IR.
+
+Each backend applies [`BoundsCheckPolicies`] itself as it generates code,
+in its own way. [`BoundsCheckLowering`] applies them to the module
+instead, as ordinary expressions and statements, so that every backend
+emits the same checks, and so that the checks can be tested on the IR.
+*/
+
+use super::index::{access_needs_check, GuardedIndex, IndexableLength};
+use super::scalarize::{adjust_expression, retarget_function};
+use super::walk::for_each_block_mut;
+use super::{BoundsCheckPolicies, BoundsCheckPolicy, TypeResolution};
+use crate::arena::{Arena, Handle, UniqueArena};
+use crate::valid::{FunctionInfo, ModuleInfo};
+use crate::{
+    ArraySize, BinaryOperator, Block, Expression, FastHashMap, Function, Literal, MathFunction,
+    ScalarKind, Span, Statement, Type, TypeInner,
+};
+
+/// Rewrites the indexing in a module according to bounds check policies,
+/// leaving backends to emit plain indexing.
+///
+/// [`lower`] rewrites every [`Access`] and [`AccessIndex`] that may be out
+/// of bounds according to the index policies in [`policies`]:
+///
+/// - Under [`Restrict`], the index is replaced with
+///   `min(u32(index), length - 1)`, where the length of a runtime-sized
+///   array comes from an [`ArrayLength`] expression.
+///
+/// - Under [`ReadZeroSkipWrite`], the index is restricted as well, and
+///   whether it was in bounds decides what the access does: loads through
+///   the pointer it produces [`Select`] zero instead, stores to it are
+///   skipped with an [`If`], and accesses to values select zero. Structs,
+///   arrays and matrices are selected one scalar or vector at a time, and
+///   composed again. Atomics, workgroup uniform loads and calls that take
+///   such a pointer only see the restricted index.
+///
+/// - Under [`Unchecked`], the access is left alone.
+///
+/// There is no zero resource to read from an out-of-bounds binding array,
+/// so accesses to those are restricted under both policies, and
+/// runtime-sized binding arrays are left unchecked. The image policies are
+/// still up to the backends.
+///
+/// Once a module has been through this, backends should be given
+/// [`Unchecked`] index policies, so that they emit plain indexing.
+///
+/// [`lower`]: BoundsCheckLowering::lower
+/// [`policies`]: BoundsCheckLowering::policies
+/// [`Access`]: Expression::Access
+/// [`AccessIndex`]: Expression::AccessIndex
+/// [`ArrayLength`]: Expression::ArrayLength
+/// [`Select`]: Expression::Select
+/// [`If`]: Statement::If
+/// [`Restrict`]: BoundsCheckPolicy::Restrict
+/// [`ReadZeroSkipWrite`]: BoundsCheckPolicy::ReadZeroSkipWrite
+/// [`Unchecked`]: BoundsCheckPolicy::Unchecked
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BoundsCheckLowering {
+    /// The policies to apply.
+    pub policies: BoundsCheckPolicies,
+}
+
+impl BoundsCheckLowering {
+    pub const fn new(policies: BoundsCheckPolicies) -> Self {
+        Self { policies }
+    }
+
+    /// Apply [`policies`] to the functions and entry points of `module`.
+    ///
+    /// `info` must be the result of validating `module`. The module should
+    /// be validated again afterwards, to get a [`ModuleInfo`] that matches
+    /// the new arenas.
+    ///
+    /// [`policies`]: BoundsCheckLowering::policies
+    pub fn lower(&self, module: &mut crate::Module, info: &ModuleInfo) {
+        let handles: Vec<_> = module.functions.iter().map(|(handle, _)| handle).collect();
+        for handle in handles {
+            let mut fun = std::mem::take(module.functions.get_mut(handle));
+            lower_function(module, &mut fun, &info[handle], self.policies);
+            *module.functions.get_mut(handle) = fun;
+        }
+        let mut entry_points = std::mem::take(&mut module.entry_points);
+        for (index, ep) in entry_points.iter_mut().enumerate() {
+            lower_function(
+                module,
+                &mut ep.function,
+                info.get_entry_point(index),
+                self.policies,
+            );
+        }
+        module.entry_points = entry_points;
+    }
+}
+
+/// How to check one access.
+#[derive(Clone, Copy)]
+struct Check {
+    /// The index of an `AccessIndex`, which becomes an `Access`.
+    constant_index: Option<u32>,
+    /// The number of elements, or `None` for a runtime-sized array.
+    length: Option<u32>,
+    read_zero: bool,
+}
+
+fn lower_function(
+    module: &mut crate::Module,
+    fun: &mut Function,
+    info: &FunctionInfo,
+    policies: BoundsCheckPolicies,
+) {
+    // Decide which accesses to check, and which pointers they make
+    // conditional under `ReadZeroSkipWrite`.
+    let mut checks = vec![None; fun.expressions.len()];
+    let mut guarded = vec![false; fun.expressions.len()];
+    let mut literals = Vec::new();
+    let mut zeros = Vec::new();
+    for (handle, expr) in fun.expressions.iter() {
+        let (base, index) = match *expr {
+            Expression::Access { base, index } => (base, GuardedIndex::Expression(index)),
+            Expression::AccessIndex { base, index } => {
+                // Only runtime-sized arrays can be indexed out of bounds by a
+                // constant; struct members never are.
+                let dynamic = matches!(
+                    *info[base].ty.inner_with(&module.types),
+                    TypeInner::Pointer { base: pointee, .. }
+                        if matches!(
+                            module.types[pointee].inner,
+                            TypeInner::Array { size: crate::ArraySize::Dynamic, .. }
+                        )
+                );
+                if !dynamic {
+                    guarded[handle.index()] = guarded[base.index()];
+                    continue;
+                }
+                (base, GuardedIndex::Known(index))
+            }
+            Expression::Load { pointer } => {
+                if guarded[pointer.index()] {
+                    zeros.push(handle);
+                }
+                continue;
+            }
+            _ => continue,
+        };
+        guarded[handle.index()] = guarded[base.index()];
+
+        let base_inner = info[base].ty.inner_with(&module.types);
+        let binding_array = match *base_inner {
+            TypeInner::BindingArray { .. } => true,
+            TypeInner::Pointer { base: pointee, .. } => {
+                matches!(module.types[pointee].inner, TypeInner::BindingArray { .. })
+            }
+            _ => false,
+        };
+        let read_zero = match policies.choose_policy(base, &module.types, info) {
+            BoundsCheckPolicy::Unchecked => continue,
+            BoundsCheckPolicy::Restrict => false,
+            BoundsCheckPolicy::ReadZeroSkipWrite => !binding_array,
+        };
+        let length = match access_needs_check(base, index, module, fun, info) {
+            None => continue,
+            Some(IndexableLength::Known(length)) => Some(length),
+            Some(IndexableLength::Dynamic) if binding_array => continue,
+            Some(IndexableLength::Dynamic) => None,
+        };
+        let constant_index = match index {
+            GuardedIndex::Known(index) => Some(index),
+            GuardedIndex::Expression(_) => None,
+        };
+
+        literals.extend(constant_index);
+        match length {
+            Some(length) => {
+                literals.push(length - 1);
+                if read_zero {
+                    literals.push(length);
+                }
+            }
+            None => literals.push(1),
+        }
+        if read_zero {
+            if base_inner.pointer_space().is_some() {
+                guarded[handle.index()] = true;
+            } else {
+                zeros.push(handle);
+            }
+        }
+        checks[handle.index()] = Some(Check {
+            constant_index,
+            length,
+            read_zero,
+        });
+    }
+    if checks.iter().all(Option::is_none) {
+        return;
+    }
+
+    // Rebuild the arena. The literals and zero values are never emitted,
+    // so they go ahead of everything else, where they can't end up inside
+    // an `Emit` range.
+    let old = std::mem::take(&mut fun.expressions);
+    literals.sort_unstable();
+    literals.dedup();
+    let literals: FastHashMap<u32, Handle<Expression>> = literals
+        .into_iter()
+        .map(|value| {
+            let literal = Expression::Literal(Literal::U32(value));
+            (value, fun.expressions.append(literal, Span::UNDEFINED))
+        })
+        .collect();
+    let mut zero_values = FastHashMap::default();
+    for &handle in zeros.iter() {
+        let ty = match info[handle].ty {
+            TypeResolution::Handle(ty) => ty,
+            TypeResolution::Value(ref inner) => module.types.insert(
+                Type {
+                    name: None,
+                    inner: inner.clone(),
+                },
+                Span::UNDEFINED,
+            ),
+        };
+        let zero = fun
+            .expressions
+            .append(Expression::ZeroValue(ty), Span::UNDEFINED);
+        zero_values.insert(handle, (zero, ty));
+    }
+
+    let mut first = Vec::with_capacity(old.len());
+    let mut last: Vec<Handle<Expression>> = Vec::with_capacity(old.len());
+    // The condition under which each pointer is in bounds, if it depends on
+    // a `ReadZeroSkipWrite` check.
+    let mut guards: Vec<Option<Handle<Expression>>> = Vec::with_capacity(old.len());
+    for (handle, original) in old.iter() {
+        let span = old.get_span(handle);
+        let mut expr = original.clone();
+        adjust_expression(&mut expr, &mut |h| *h = last[h.index()]);
+        let start = fun.expressions.len();
+        let mut append = |expr| fun.expressions.append(expr, span);
+
+        let mut guard = match *original {
+            Expression::Access { base, .. } | Expression::AccessIndex { base, .. } => {
+                guards[base.index()]
+            }
+            _ => None,
+        };
+        if let Some(check) = checks[handle.index()] {
+            let (base, index) = match expr {
+                Expression::Access { base, index } => (base, index),
+                Expression::AccessIndex { base, .. } => {
+                    (base, literals[&check.constant_index.unwrap()])
+                }
+                _ => unreachable!(),
+            };
+            let index = match original {
+                Expression::Access {
+                    index: old_index, ..
+                } if matches!(
+                    *info[*old_index].ty.inner_with(&module.types),
+                    TypeInner::Scalar {
+                        kind: ScalarKind::Sint,
+                        ..
+                    }
+                ) =>
+                {
+                    append(Expression::As {
+                        expr: index,
+                        kind: ScalarKind::Uint,
+                        convert: None,
+                    })
+                }
+                _ => index,
+            };
+            let (limit, length) = match check.length {
+                Some(length) => (literals[&(length - 1)], None),
+                None => {
+                    let length = append(Expression::ArrayLength(base));
+                    let limit = append(Expression::Binary {
+                        op: BinaryOperator::Subtract,
+                        left: length,
+                        right: literals[&1],
+                    });
+                    (limit, Some(length))
+                }
+            };
+            let restricted = append(Expression::Math {
+                fun: MathFunction::Min,
+                arg: index,
+                arg1: Some(limit),
+                arg2: None,
+                arg3: None,
+            });
+            expr = Expression::Access {
+                base,
+                index: restricted,
+            };
+            if check.read_zero {
+                let length = length.unwrap_or_else(|| literals[&check.length.unwrap()]);
+                let in_bounds = append(Expression::Binary {
+                    op: BinaryOperator::Less,
+                    left: index,
+                    right: length,
+                });
+                guard = Some(match guard {
+                    Some(outer) => append(Expression::Binary {
+                        op: BinaryOperator::LogicalAnd,
+                        left: outer,
+                        right: in_bounds,
+                    }),
+                    None => in_bounds,
+                });
+            }
+        }
+
+        // Values are selected as soon as they are accessed; pointers carry
+        // their guard along to the loads and stores that use them.
+        let new = match zero_values.get(&handle) {
+            Some(&(zero, ty)) => {
+                let condition = match *original {
+                    Expression::Load { pointer } => guards[pointer.index()],
+                    _ => guard.take(),
+                };
+                let accept = append(expr);
+                select(
+                    &mut fun.expressions,
+                    &module.types,
+                    ty,
+                    condition.unwrap(),
+                    accept,
+                    zero,
+                    span,
+                )
+            }
+            None => append(expr),
+        };
+        first.push(
+            fun.expressions
+                .range_from(start)
+                .first_and_last()
+                .unwrap()
+                .0,
+        );
+        last.push(new);
+        guards.push(guard);
+    }
+    retarget_function(fun, &first, &last);
+
+    let store_guards: FastHashMap<_, _> = guards
+        .iter()
+        .enumerate()
+        .filter_map(|(index, guard)| guard.map(|guard| (last[index], guard)))
+        .collect();
+    guard_stores(&mut fun.body, &store_guards);
+}
+
+/// Select `accept` if `condition` holds, and `reject` otherwise, where
+/// both have the type `ty`.
+///
+/// [`Select`] only chooses between scalars and vectors, so structs, arrays
+/// and matrices are taken apart, selected member by member, and composed
+/// again.
+///
+/// [`Select`]: Expression::Select
+fn select(
+    expressions: &mut Arena<Expression>,
+    types: &UniqueArena<Type>,
+    ty: Handle<Type>,
+    condition: Handle<Expression>,
+    accept: Handle<Expression>,
+    reject: Handle<Expression>,
+    span: Span,
+) -> Handle<Expression> {
+    let members: Vec<Option<Handle<Type>>> = match types[ty].inner {
+        TypeInner::Matrix { columns, .. } => vec![None; columns as usize],
+        TypeInner::Array {
+            base,
+            size: ArraySize::Constant(size),
+            ..
+        } => vec![Some(base); size.get() as usize],
+        TypeInner::Struct { ref members, .. } => {
+            members.iter().map(|member| Some(member.ty)).collect()
+        }
+        _ => {
+            return expressions.append(
+                Expression::Select {
+                    condition,
+                    accept,
+                    reject,
+                },
+                span,
+            )
+        }
+    };
+
+    let mut components = Vec::with_capacity(members.len());
+    for (index, member) in (0..).zip(members) {
+        let accept = expressions.append(
+            Expression::AccessIndex {
+                base: accept,
+                index,
+            },
+            span,
+        );
+        let reject = expressions.append(
+            Expression::AccessIndex {
+                base: reject,
+                index,
+            },
+            span,
+        );
+        components.push(match member {
+            Some(member) => select(expressions, types, member, condition, accept, reject, span),
+            // A matrix column is a vector.
+            None => expressions.append(
+                Expression::Select {
+                    condition,
+                    accept,
+                    reject,
+                },
+                span,
+            ),
+        });
+    }
+    expressions.append(Expression::Compose { ty, components }, span)
+}
+
+/// Wrap each store to a pointer in `guards` in an `if` on its guard.
+fn guard_stores(body: &mut Block, guards: &FastHashMap<Handle<Expression>, Handle<Expression>>) {
+    for_each_block_mut(body, &mut |block| {
+        for stmt in block.iter_mut() {
+            if let Statement::Store { pointer, .. } = *stmt {
+                if let Some(&condition) = guards.get(&pointer) {
+                    let store = std::mem::replace(stmt, Statement::Break);
+                    *stmt = Statement::If {
+                        condition,
+                        accept: Block::from_vec(vec![store]),
+                        reject: Block::new(),
+                    };
+                }
+            }
+        }
+    });
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod tests {
+    use crate::proc::{BoundsCheckPolicies, BoundsCheckPolicy};
+    use crate::test_util::Fixture;
+    use crate::{Expression, Literal, MathFunction, Statement};
+
+    fn lower(source: &str, policy: BoundsCheckPolicy) -> crate::Module {
+        let mut fixture = Fixture::wgsl(source);
+        let policies = BoundsCheckPolicies {
+            index: policy,
+            buffer: policy,
+            ..Default::default()
+        };
+        super::BoundsCheckLowering::new(policies).lower(&mut fixture.module, &fixture.info);
+        fixture.revalidate();
+        fixture.module
+    }
+
+    fn count(fun: &crate::Function, f: impl Fn(&Expression) -> bool) -> usize {
+        fun.expressions.iter().filter(|&(_, expr)| f(expr)).count()
+    }
+
+    const SOURCE: &str = "
+        @group(0) @binding(0) var<storage, read_write> data: array<f32>;
+
+        fn get(i: i32) -> f32 {
+            var local = array<f32, 4>(1.0, 2.0, 3.0, 4.0);
+            return local[i] + local[2];
+        }
+
+        @compute @workgroup_size(1)
+        fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+            data[id.x] = data[id.x + 1u] * get(i32(id.y));
+        }
+    ";
+
+    #[test]
+    fn restrict() {
+        let module = lower(SOURCE, BoundsCheckPolicy::Restrict);
+        let is_min = |expr: &Expression| {
+            matches!(
+                *expr,
+                Expression::Math {
+                    fun: MathFunction::Min,
+                    ..
+                }
+            )
+        };
+
+        // Only the dynamic index needs restricting; `local[2]` is known to
+        // be in bounds.
+        let (_, get) = module.functions.iter().next().unwrap();
+        assert_eq!(count(get, is_min), 1);
+        assert_eq!(
+            count(get, |expr| *expr == Expression::Literal(Literal::U32(3))),
+            1
+        );
+
+        let main = &module.entry_points[0].function;
+        assert_eq!(count(main, is_min), 2);
+        assert_eq!(
+            count(main, |expr| matches!(*expr, Expression::ArrayLength(_))),
+            2
+        );
+    }
+
+    #[test]
+    fn read_zero_skip_write() {
+        let module = lower(SOURCE, BoundsCheckPolicy::ReadZeroSkipWrite);
+
+        let (_, get) = module.functions.iter().next().unwrap();
+        assert_eq!(
+            count(get, |expr| matches!(*expr, Expression::Select { .. })),
+            1
+        );
+
+        let main = &module.entry_points[0].function;
+        assert_eq!(
+            count(main, |expr| matches!(*expr, Expression::Select { .. })),
+            1
+        );
+        assert!(main.body.iter().any(|stmt| matches!(
+            *stmt,
+            Statement::If { ref accept, .. } if matches!(accept[..], [Statement::Store { .. }])
+        )));
+    }
+
+    #[test]
+    fn read_zero_composites() {
+        // `Select` can't choose between structs, arrays or matrices, so
+        // `lower` validates the module only if these are taken apart.
+        let module = lower(
+            "
+            struct S {
+                m: mat2x2<f32>,
+                a: array<vec2<f32>, 3>,
+            }
+
+            @group(0) @binding(0) var<storage, read_write> data: array<S>;
+            @group(0) @binding(1) var<storage, read_write> out: S;
+
+            @compute @workgroup_size(1)
+            fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+                out = data[id.x];
+            }
+            ",
+            BoundsCheckPolicy::ReadZeroSkipWrite,
+        );
+
+        let main = &module.entry_points[0].function;
+        // Two columns and three elements.
+        assert_eq!(
+            count(main, |expr| matches!(*expr, Expression::Select { .. })),
+            5
+        );
+        // The matrix, the array and the struct.
+        assert_eq!(
+            count(main, |expr| matches!(*expr, Expression::Compose { .. })),
+            3
+        );
+    }
+
+    #[test]
+    fn unchecked() {
+        let module = Fixture::wgsl(SOURCE).module;
+        let lowered = lower(SOURCE, BoundsCheckPolicy::Unchecked);
+        assert_eq!(
+            lowered.entry_points[0].function.expressions.len(),
+            module.entry_points[0].function.expressions.len()
+        );
+    }
+}
@@ -16,14 +16,15 @@ enum Behavior {
 /// newly evaluated expressions: you pass [`try_eval_and_append`] whatever kind
 /// of Naga [`Expression`] you like, and if its value can be computed at compile
 /// time, `try_eval_and_append` appends an expression representing the computed
-/// value - a tree of [`Literal`], [`Compose`], [`ZeroValue`], and [`Swizzle`]
-/// expressions - to the arena. See the [`try_eval_and_append`] method for details.
+/// value - a tree of [`Literal`], [`Compose`], [`Repeat`], [`ZeroValue`], and
+/// [`Swizzle`] expressions - to the arena. See the [`try_eval_and_append`] method for details.
 ///
 /// A `ConstantEvaluator` also holds whatever information we need to carry out
 /// that evaluation: types, other constants, and so on.
 ///
 /// [`try_eval_and_append`]: ConstantEvaluator::try_eval_and_append
 /// [`Compose`]: Expression::Compose
+/// [`Repeat`]: Expression::Repeat
 /// [`ZeroValue`]: Expression::ZeroValue
 /// [`Literal`]: Expression::Literal
 /// [`Swizzle`]: Expression::Swizzle
@@ -43,6 +44,9 @@ pub struct ConstantEvaluator<'a> {
     /// The module's constant arena.
     constants: &'a Arena<Constant>,
 
+    /// The module's special types, for the results of built-ins like `modf`.
+    special_types: &'a crate::SpecialTypes,
+
     /// The arena to which we are contributing expressions.
     expressions: &'a mut Arena<Expression>,
 
@@ -96,7 +100,8 @@ impl ExpressionConstnessTracker {
                 crate::Expression::Compose { ref components, .. } => {
                     components.iter().all(|h| tracker.is_const(*h))
                 }
-                crate::Expression::Splat { value, .. } => tracker.is_const(value),
+                crate::Expression::Splat { value, .. }
+                | crate::Expression::Repeat { value, .. } => tracker.is_const(value),
                 _ => false,
             };
             if insert {
@@ -133,6 +138,10 @@ pub enum ConstantEvaluatorError {
     ImageExpression,
     #[error("Constants don't support ray query expressions")]
     RayQueryExpression,
+    #[error("Constants don't support subgroup operations")]
+    SubgroupOperation,
+    #[error("Constants can't be non-uniform")]
+    NonUniform,
     #[error("Cannot access the type")]
     InvalidAccessBase,
     #[error("Cannot access at the index")]
@@ -153,6 +162,8 @@ pub enum ConstantEvaluatorError {
     InvalidMathArgCount(crate::MathFunction, usize, usize),
     #[error("value of `low` is greater than `high` for clamp built-in function")]
     InvalidClamp,
+    #[error("The result type of the {0:?} built-in function has not been predeclared")]
+    MissingPredeclaredType(crate::MathFunction),
     #[error("Splat is defined only on scalar values")]
     SplatScalarOnly,
     #[error("Can only swizzle vector constants")]
@@ -173,8 +184,12 @@ pub enum ConstantEvaluatorError {
     RemainderByZero,
     #[error("RHS of shift operation is greater than or equal to 32")]
     ShiftedMoreThan32Bits,
+    #[error("RHS of shift operation is greater than or equal to 64")]
+    ShiftedMoreThan64Bits,
     #[error(transparent)]
     Literal(#[from] crate::valid::LiteralError),
+    #[error(transparent)]
+    ArenaFull(#[from] crate::ArenaFull),
 }
 
 impl<'a> ConstantEvaluator<'a> {
@@ -199,11 +214,35 @@ impl<'a> ConstantEvaluator<'a> {
             behavior,
             types: &mut module.types,
             constants: &module.constants,
+            special_types: &module.special_types,
             expressions: &mut module.const_expressions,
             function_local_data: None,
         }
     }
 
+    /// Return a [`ConstantEvaluator`] that will add expressions to
+    /// `expressions`, a scratch arena that belongs to no module.
+    ///
+    /// `types` and `constants` need not be the module's: this is for
+    /// evaluating expressions that refer to neither, like arithmetic on
+    /// scalar literals. Report errors according to WGSL's rules for
+    /// constant evaluation.
+    pub(crate) fn for_scratch(
+        types: &'a mut UniqueArena<Type>,
+        constants: &'a Arena<Constant>,
+        special_types: &'a crate::SpecialTypes,
+        expressions: &'a mut Arena<Expression>,
+    ) -> Self {
+        Self {
+            behavior: Behavior::Wgsl,
+            types,
+            constants,
+            special_types,
+            expressions,
+            function_local_data: None,
+        }
+    }
+
     /// Return a [`ConstantEvaluator`] that will add expressions to `function`'s
     /// expression arena.
     ///
@@ -258,6 +297,7 @@ impl<'a> ConstantEvaluator<'a> {
             behavior,
             types: &mut module.types,
             constants: &module.constants,
+            special_types: &module.special_types,
             expressions,
             function_local_data: Some(FunctionLocalData {
                 const_expressions: &module.const_expressions,
@@ -308,7 +348,7 @@ impl<'a> ConstantEvaluator<'a> {
     ///
     /// The `expr` argument can be any sort of Naga [`Expression`] you like. If
     /// we can determine its value at compile time, we append an expression
-    /// representing its value - a tree of [`Literal`], [`Compose`],
+    /// representing its value - a tree of [`Literal`], [`Compose`], [`Repeat`],
     /// [`ZeroValue`], and [`Swizzle`] expressions - to the expression arena
     /// `self` contributes to.
     ///
@@ -323,6 +363,7 @@ impl<'a> ConstantEvaluator<'a> {
     ///
     /// [`Literal`]: Expression::Literal
     /// [`Compose`]: Expression::Compose
+    /// [`Repeat`]: Expression::Repeat
     /// [`ZeroValue`]: Expression::ZeroValue
     /// [`Swizzle`]: Expression::Swizzle
     pub fn try_eval_and_append(
@@ -345,7 +386,15 @@ impl<'a> ConstantEvaluator<'a> {
                     .iter()
                     .map(|component| self.check_and_get(*component))
                     .collect::<Result<Vec<_>, _>>()?;
-                self.register_evaluated_expr(Expression::Compose { ty, components }, span)
+                let expr = match self.repeated_component(ty, &components) {
+                    Some(value) => Expression::Repeat { ty, value },
+                    None => Expression::Compose { ty, components },
+                };
+                self.register_evaluated_expr(expr, span)
+            }
+            Expression::Repeat { ty, value } => {
+                let value = self.check_and_get(value)?;
+                self.register_evaluated_expr(Expression::Repeat { ty, value }, span)
             }
             Expression::Splat { size, value } => {
                 let value = self.check_and_get(value)?;
@@ -439,6 +488,8 @@ impl<'a> ConstantEvaluator<'a> {
             Expression::RayQueryProceedResult | Expression::RayQueryGetIntersection { .. } => {
                 Err(ConstantEvaluatorError::RayQueryExpression)
             }
+            Expression::SubgroupOperation { .. } => Err(ConstantEvaluatorError::SubgroupOperation),
+            Expression::NonUniform(_) => Err(ConstantEvaluatorError::NonUniform),
         }
     }
 
@@ -452,13 +503,13 @@ impl<'a> ConstantEvaluator<'a> {
             Expression::Literal(literal) => {
                 let kind = literal.scalar_kind();
                 let width = literal.width();
-                let ty = self.types.insert(
+                let ty = self.types.try_insert(
                     Type {
                         name: None,
                         inner: TypeInner::Vector { size, kind, width },
                     },
                     span,
-                );
+                )?;
                 let expr = Expression::Compose {
                     ty,
                     components: vec![value; size as usize],
@@ -470,7 +521,7 @@ impl<'a> ConstantEvaluator<'a> {
                     TypeInner::Scalar { kind, width } => TypeInner::Vector { size, kind, width },
                     _ => return Err(ConstantEvaluatorError::SplatScalarOnly),
                 };
-                let res_ty = self.types.insert(Type { name: None, inner }, span);
+                let res_ty = self.types.try_insert(Type { name: None, inner }, span)?;
                 let expr = Expression::ZeroValue(res_ty);
                 self.register_evaluated_expr(expr, span)
             }
@@ -490,13 +541,13 @@ impl<'a> ConstantEvaluator<'a> {
                 size: _,
                 kind,
                 width,
-            } => Ok(self.types.insert(
+            } => Ok(self.types.try_insert(
                 Type {
                     name: None,
                     inner: crate::TypeInner::Vector { size, kind, width },
                 },
                 span,
-            )),
+            )?),
             _ => Err(ConstantEvaluatorError::SwizzleVectorOnly),
         };
 
@@ -551,26 +602,255 @@ impl<'a> ConstantEvaluator<'a> {
         fun: crate::MathFunction,
         span: Span,
     ) -> Result<Handle<Expression>, ConstantEvaluatorError> {
-        let expected = fun.argument_count();
-        let given = Some(arg)
+        use crate::MathFunction as Mf;
+        use Literal as Li;
+
+        let args: Vec<_> = Some(arg)
             .into_iter()
             .chain(arg1)
             .chain(arg2)
             .chain(arg3)
-            .count();
-        if expected != given {
+            .collect();
+        let expected = fun.argument_count();
+        if expected != args.len() {
             return Err(ConstantEvaluatorError::InvalidMathArgCount(
-                fun, expected, given,
+                fun,
+                expected,
+                args.len(),
             ));
         }
 
-        match fun {
-            crate::MathFunction::Pow => self.math_pow(arg, arg1.unwrap(), span),
-            crate::MathFunction::Clamp => self.math_clamp(arg, arg1.unwrap(), arg2.unwrap(), span),
-            fun => Err(ConstantEvaluatorError::NotImplemented(format!(
-                "{fun:?} built-in function"
-            ))),
+        let f: fn(&[Literal]) -> Result<Literal, ConstantEvaluatorError> = match fun {
+            Mf::Pow => return self.math_pow(arg, arg1.unwrap(), span),
+            Mf::Clamp => return self.math_clamp(arg, arg1.unwrap(), arg2.unwrap(), span),
+            Mf::Modf | Mf::Frexp => return self.math_modf_frexp(fun, arg, span),
+            Mf::Abs => |args| match *args {
+                [Li::I32(a)] => Ok(Li::I32(a.wrapping_abs())),
+                [Li::U32(a)] => Ok(Li::U32(a)),
+                _ => map_floats(args, |v| v[0].abs()),
+            },
+            Mf::Min => |args| match *args {
+                [Li::I32(a), Li::I32(b)] => Ok(Li::I32(a.min(b))),
+                [Li::U32(a), Li::U32(b)] => Ok(Li::U32(a.min(b))),
+                _ => map_floats(args, |v| v[0].min(v[1])),
+            },
+            Mf::Max => |args| match *args {
+                [Li::I32(a), Li::I32(b)] => Ok(Li::I32(a.max(b))),
+                [Li::U32(a), Li::U32(b)] => Ok(Li::U32(a.max(b))),
+                _ => map_floats(args, |v| v[0].max(v[1])),
+            },
+            Mf::Sign => |args| match *args {
+                [Li::I32(a)] => Ok(Li::I32(a.signum())),
+                // Keep the sign of zero.
+                _ => map_floats(args, |v| if v[0] == 0.0 { v[0] } else { v[0].signum() }),
+            },
+            Mf::Saturate => |args| map_floats(args, |v| v[0].clamp(0.0, 1.0)),
+            Mf::Floor => |args| map_floats(args, |v| v[0].floor()),
+            Mf::Ceil => |args| map_floats(args, |v| v[0].ceil()),
+            Mf::Round => |args| map_floats(args, |v| round_ties_even(v[0])),
+            Mf::Trunc => |args| map_floats(args, |v| v[0].trunc()),
+            Mf::Fract => |args| map_floats(args, |v| v[0] - v[0].floor()),
+            Mf::Sqrt => |args| map_floats(args, |v| v[0].sqrt()),
+            Mf::InverseSqrt => |args| map_floats(args, |v| v[0].sqrt().recip()),
+            Mf::Sin => |args| map_floats(args, |v| v[0].sin()),
+            Mf::Cos => |args| map_floats(args, |v| v[0].cos()),
+            Mf::Tan => |args| map_floats(args, |v| v[0].tan()),
+            Mf::Asin => |args| map_floats(args, |v| v[0].asin()),
+            Mf::Acos => |args| map_floats(args, |v| v[0].acos()),
+            Mf::Atan => |args| map_floats(args, |v| v[0].atan()),
+            Mf::Atan2 => |args| map_floats(args, |v| v[0].atan2(v[1])),
+            Mf::Sinh => |args| map_floats(args, |v| v[0].sinh()),
+            Mf::Cosh => |args| map_floats(args, |v| v[0].cosh()),
+            Mf::Tanh => |args| map_floats(args, |v| v[0].tanh()),
+            Mf::Asinh => |args| map_floats(args, |v| v[0].asinh()),
+            Mf::Acosh => |args| map_floats(args, |v| v[0].acosh()),
+            Mf::Atanh => |args| map_floats(args, |v| v[0].atanh()),
+            Mf::Radians => |args| map_floats(args, |v| v[0].to_radians()),
+            Mf::Degrees => |args| map_floats(args, |v| v[0].to_degrees()),
+            Mf::Exp => |args| map_floats(args, |v| v[0].exp()),
+            Mf::Exp2 => |args| map_floats(args, |v| v[0].exp2()),
+            Mf::Log => |args| map_floats(args, |v| v[0].ln()),
+            Mf::Log2 => |args| map_floats(args, |v| v[0].log2()),
+            Mf::Step => |args| map_floats(args, |v| if v[1] >= v[0] { 1.0 } else { 0.0 }),
+            Mf::Fma => |args| map_floats(args, |v| v[0].mul_add(v[1], v[2])),
+            Mf::Mix => |args| map_floats(args, |v| v[0] * (1.0 - v[2]) + v[1] * v[2]),
+            Mf::SmoothStep => |args| {
+                map_floats(args, |v| {
+                    let t = ((v[2] - v[0]) / (v[1] - v[0])).clamp(0.0, 1.0);
+                    t * t * (3.0 - 2.0 * t)
+                })
+            },
+            fun => {
+                return Err(ConstantEvaluatorError::NotImplemented(format!(
+                    "{fun:?} built-in function"
+                )))
+            }
+        };
+        if self.function_local_data.is_some() {
+            // Only fold these where a constant is required, like `const`
+            // declarations and array sizes. Back ends write runtime
+            // expressions as they were written, and may compute them
+            // differently than `f64` does.
+            return Err(ConstantEvaluatorError::NotImplemented(format!(
+                "{fun:?} built-in function in a function"
+            )));
+        }
+        // The arguments that may be scalars when the others are vectors:
+        // WGSL's `mix(a, b, t)`, and GLSL's `step(edge, x)` and
+        // `smoothstep(edge0, edge1, x)`.
+        let scalars: &[usize] = match fun {
+            Mf::Mix => &[2],
+            Mf::Step => &[0],
+            Mf::SmoothStep => &[0, 1],
+            _ => &[],
+        };
+        self.math_component_wise(&args, scalars, f, span)
+    }
+
+    /// Apply `f` to the literal values of `args`, or to corresponding
+    /// components of them, if any are vectors.
+    ///
+    /// If any argument is a vector, all must be vectors of the same type,
+    /// except those whose indices are in `scalars`: those may be scalars,
+    /// which are used for every component, as for `mix(a, b, t)` with a
+    /// scalar `t`.
+    fn math_component_wise(
+        &mut self,
+        args: &[Handle<Expression>],
+        scalars: &[usize],
+        f: fn(&[Literal]) -> Result<Literal, ConstantEvaluatorError>,
+        span: Span,
+    ) -> Result<Handle<Expression>, ConstantEvaluatorError> {
+        let args = args
+            .iter()
+            .map(|&arg| self.eval_zero_value_and_splat(arg, span))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut vector_ty = None;
+        for &arg in args.iter() {
+            match self.expressions[arg] {
+                Expression::Literal(_) => {}
+                Expression::Compose { ty, .. }
+                    if matches!(self.types[ty].inner, TypeInner::Vector { .. })
+                        && vector_ty.map_or(true, |vector_ty| vector_ty == ty) =>
+                {
+                    vector_ty = Some(ty);
+                }
+                _ => return Err(ConstantEvaluatorError::InvalidMathArg),
+            }
+        }
+        if vector_ty.is_some() {
+            let mixed = args.iter().enumerate().any(|(index, &arg)| {
+                matches!(self.expressions[arg], Expression::Literal(_)) && !scalars.contains(&index)
+            });
+            if mixed {
+                return Err(ConstantEvaluatorError::InvalidMathArg);
+            }
+        }
+
+        let ty = match vector_ty {
+            Some(ty) => ty,
+            None => {
+                let literals: Vec<_> = args
+                    .iter()
+                    .map(|&arg| match self.expressions[arg] {
+                        Expression::Literal(literal) => literal,
+                        _ => unreachable!(),
+                    })
+                    .collect();
+                return self.register_evaluated_expr(Expression::Literal(f(&literals)?), span);
+            }
+        };
+
+        let (vector_size, size) = match self.types[ty].inner {
+            TypeInner::Vector { size, .. } => (size, size as usize),
+            _ => unreachable!(),
+        };
+        let columns: Vec<Vec<_>> = args
+            .iter()
+            .map(|&arg| match self.expressions[arg] {
+                Expression::Compose { ty, ref components } => {
+                    crate::proc::flatten_compose(ty, components, self.expressions, self.types)
+                        .collect()
+                }
+                _ => vec![arg; size],
+            })
+            .collect();
+        let mut components = Vec::with_capacity(size);
+        for index in 0..size {
+            let component_args: Vec<_> = columns.iter().map(|column| column[index]).collect();
+            components.push(self.math_component_wise(&component_args, &[], f, span)?);
+        }
+
+        // The result's components may not have the arguments' type, as for
+        // `frexp`'s exponents.
+        let ty = match self.expressions[components[0]] {
+            Expression::Literal(literal) => self.types.try_insert(
+                Type {
+                    name: None,
+                    inner: TypeInner::Vector {
+                        size: vector_size,
+                        kind: literal.scalar_kind(),
+                        width: literal.width(),
+                    },
+                },
+                span,
+            )?,
+            _ => unreachable!(),
+        };
+        self.register_evaluated_expr(Expression::Compose { ty, components }, span)
+    }
+
+    /// Evaluate `modf(arg)` or `frexp(arg)`, whose results are the module's
+    /// predeclared structs.
+    fn math_modf_frexp(
+        &mut self,
+        fun: crate::MathFunction,
+        arg: Handle<Expression>,
+        span: Span,
+    ) -> Result<Handle<Expression>, ConstantEvaluatorError> {
+        if self.function_local_data.is_some() {
+            // See `math`.
+            return Err(ConstantEvaluatorError::NotImplemented(format!(
+                "{fun:?} built-in function in a function"
+            )));
         }
+
+        let arg = self.eval_zero_value_and_splat(arg, span)?;
+        let (size, width) = match self.expressions[arg] {
+            Expression::Literal(literal) => (None, literal.width()),
+            Expression::Compose { ty, .. } => match self.types[ty].inner {
+                TypeInner::Vector { size, width, .. } => (Some(size), width),
+                _ => return Err(ConstantEvaluatorError::InvalidMathArg),
+            },
+            _ => return Err(ConstantEvaluatorError::InvalidMathArg),
+        };
+
+        type Part = fn(&[Literal]) -> Result<Literal, ConstantEvaluatorError>;
+        let (predeclared, fract, other): (_, Part, Part) = if fun == crate::MathFunction::Modf {
+            (
+                crate::PredeclaredType::ModfResult { size, width },
+                |args| map_floats(args, |v| v[0] - v[0].trunc()),
+                |args| map_floats(args, |v| v[0].trunc()),
+            )
+        } else {
+            (
+                crate::PredeclaredType::FrexpResult { size, width },
+                |args| map_floats(args, |v| frexp(v[0]).0),
+                |args| Ok(Literal::I32(frexp(float_values(args)?[0]).1)),
+            )
+        };
+        let ty = *self
+            .special_types
+            .predeclared_types
+            .get(&predeclared)
+            .ok_or(ConstantEvaluatorError::MissingPredeclaredType(fun))?;
+
+        let components = vec![
+            self.math_component_wise(&[arg], &[], fract, span)?,
+            self.math_component_wise(&[arg], &[], other, span)?,
+        ];
+        self.register_evaluated_expr(Expression::Compose { ty, components }, span)
     }
 
     fn math_pow(
@@ -747,20 +1027,18 @@ impl<'a> ConstantEvaluator<'a> {
         span: Span,
     ) -> Result<Handle<Expression>, ConstantEvaluatorError> {
         match self.expressions[array] {
-            Expression::ZeroValue(ty) | Expression::Compose { ty, .. } => {
-                match self.types[ty].inner {
-                    TypeInner::Array { size, .. } => match size {
-                        crate::ArraySize::Constant(len) => {
-                            let expr = Expression::Literal(Literal::U32(len.get()));
-                            self.register_evaluated_expr(expr, span)
-                        }
-                        crate::ArraySize::Dynamic => {
-                            Err(ConstantEvaluatorError::ArrayLengthDynamic)
-                        }
-                    },
-                    _ => Err(ConstantEvaluatorError::InvalidArrayLengthArg),
-                }
-            }
+            Expression::ZeroValue(ty)
+            | Expression::Compose { ty, .. }
+            | Expression::Repeat { ty, .. } => match self.types[ty].inner {
+                TypeInner::Array { size, .. } => match size {
+                    crate::ArraySize::Constant(len) => {
+                        let expr = Expression::Literal(Literal::U32(len.get()));
+                        self.register_evaluated_expr(expr, span)
+                    }
+                    crate::ArraySize::Dynamic => Err(ConstantEvaluatorError::ArrayLengthDynamic),
+                },
+                _ => Err(ConstantEvaluatorError::InvalidArrayLengthArg),
+            },
             _ => Err(ConstantEvaluatorError::InvalidArrayLengthArg),
         }
     }
@@ -787,7 +1065,7 @@ impl<'a> ConstantEvaluator<'a> {
                     let ty = match ty_res {
                         crate::proc::TypeResolution::Handle(ty) => ty,
                         crate::proc::TypeResolution::Value(inner) => {
-                            self.types.insert(Type { name: None, inner }, span)
+                            self.types.try_insert(Type { name: None, inner }, span)?
                         }
                     };
                     self.register_evaluated_expr(Expression::ZeroValue(ty), span)
@@ -800,6 +1078,13 @@ impl<'a> ConstantEvaluator<'a> {
                     Ok(value)
                 }
             }
+            Expression::Repeat { ty, value } => match self.types[ty].inner {
+                TypeInner::Array {
+                    size: ArraySize::Constant(size),
+                    ..
+                } if index < size.get() as usize => Ok(value),
+                _ => Err(ConstantEvaluatorError::InvalidAccessIndex),
+            },
             Expression::Compose { ty, ref components } => {
                 let _ = self.types[ty]
                     .inner
@@ -832,7 +1117,28 @@ impl<'a> ConstantEvaluator<'a> {
         }
     }
 
-    /// Transforms `Expression::ZeroValue` and `Expression::Splat` into either `Expression::Literal` or `Expression::Compose`
+    /// If `components` compose an array of type `ty` whose elements are all
+    /// the same, return the element to use in an [`Expression::Repeat`].
+    fn repeated_component(
+        &self,
+        ty: Handle<Type>,
+        components: &[Handle<Expression>],
+    ) -> Option<Handle<Expression>> {
+        if !matches!(self.types[ty].inner, TypeInner::Array { .. }) {
+            return None;
+        }
+        let (&first, rest) = components.split_first()?;
+        if rest.is_empty() {
+            return None;
+        }
+        rest.iter()
+            .all(|&component| {
+                component == first || self.expressions[component] == self.expressions[first]
+            })
+            .then_some(first)
+    }
+
+    /// Transforms `Expression::ZeroValue` and `Expression::Splat` into either `Expression::Literal`, `Expression::Compose`, or `Expression::Repeat`
     fn eval_zero_value_and_splat(
         &mut self,
         expr: Handle<Expression>,
@@ -859,13 +1165,13 @@ impl<'a> ConstantEvaluator<'a> {
                 self.register_evaluated_expr(expr, span)
             }
             TypeInner::Vector { size, kind, width } => {
-                let scalar_ty = self.types.insert(
+                let scalar_ty = self.types.try_insert(
                     Type {
                         name: None,
                         inner: TypeInner::Scalar { kind, width },
                     },
                     span,
-                );
+                )?;
                 let el = self.eval_zero_value_impl(scalar_ty, span)?;
                 let expr = Expression::Compose {
                     ty,
@@ -878,7 +1184,7 @@ impl<'a> ConstantEvaluator<'a> {
                 rows,
                 width,
             } => {
-                let vec_ty = self.types.insert(
+                let vec_ty = self.types.try_insert(
                     Type {
                         name: None,
                         inner: TypeInner::Vector {
@@ -888,7 +1194,7 @@ impl<'a> ConstantEvaluator<'a> {
                         },
                     },
                     span,
-                );
+                )?;
                 let el = self.eval_zero_value_impl(vec_ty, span)?;
                 let expr = Expression::Compose {
                     ty,
@@ -898,15 +1204,11 @@ impl<'a> ConstantEvaluator<'a> {
             }
             TypeInner::Array {
                 base,
-                size: ArraySize::Constant(size),
+                size: ArraySize::Constant(_),
                 ..
             } => {
-                let el = self.eval_zero_value_impl(base, span)?;
-                let expr = Expression::Compose {
-                    ty,
-                    components: vec![el; size.get() as usize],
-                };
-                self.register_evaluated_expr(expr, span)
+                let value = self.eval_zero_value_impl(base, span)?;
+                self.register_evaluated_expr(Expression::Repeat { ty, value }, span)
             }
             TypeInner::Struct { ref members, .. } => {
                 let types: Vec<_> = members.iter().map(|m| m.ty).collect();
@@ -936,28 +1238,74 @@ impl<'a> ConstantEvaluator<'a> {
                     (ScalarKind::Sint, 4) => Literal::I32(match literal {
                         Literal::I32(v) => v,
                         Literal::U32(v) => v as i32,
-                        Literal::F32(v) => v as i32,
+                        Literal::I64(v) => v as i32,
+                        Literal::U64(v) => v as i32,
+                        // Saturate to the nearest value that is also an
+                        // `f32`, as documented on `Expression::As`.
+                        Literal::F32(v) => v.clamp(-2147483648.0, 2147483520.0) as i32,
+                        Literal::F16(v) => super::f16_to_f32(v) as i32,
                         Literal::Bool(v) => v as i32,
                         Literal::F64(_) => return Err(ConstantEvaluatorError::InvalidCastArg),
                     }),
                     (ScalarKind::Uint, 4) => Literal::U32(match literal {
                         Literal::I32(v) => v as u32,
                         Literal::U32(v) => v,
-                        Literal::F32(v) => v as u32,
+                        Literal::I64(v) => v as u32,
+                        Literal::U64(v) => v as u32,
+                        Literal::F32(v) => v.clamp(0.0, 4294967040.0) as u32,
+                        Literal::F16(v) => super::f16_to_f32(v) as u32,
                         Literal::Bool(v) => v as u32,
                         Literal::F64(_) => return Err(ConstantEvaluatorError::InvalidCastArg),
                     }),
+                    (ScalarKind::Sint, 8) => Literal::I64(match literal {
+                        Literal::I32(v) => v.into(),
+                        Literal::U32(v) => v.into(),
+                        Literal::I64(v) => v,
+                        Literal::U64(v) => v as i64,
+                        Literal::F32(v) => {
+                            v.clamp(-9223372036854775808.0, 9223371487098961920.0) as i64
+                        }
+                        Literal::F16(v) => super::f16_to_f32(v) as i64,
+                        Literal::Bool(v) => v.into(),
+                        Literal::F64(_) => return Err(ConstantEvaluatorError::InvalidCastArg),
+                    }),
+                    (ScalarKind::Uint, 8) => Literal::U64(match literal {
+                        Literal::I32(v) => v as u64,
+                        Literal::U32(v) => v.into(),
+                        Literal::I64(v) => v as u64,
+                        Literal::U64(v) => v,
+                        Literal::F32(v) => v.clamp(0.0, 18446742974197923840.0) as u64,
+                        Literal::F16(v) => super::f16_to_f32(v) as u64,
+                        Literal::Bool(v) => v.into(),
+                        Literal::F64(_) => return Err(ConstantEvaluatorError::InvalidCastArg),
+                    }),
                     (ScalarKind::Float, 4) => Literal::F32(match literal {
                         Literal::I32(v) => v as f32,
                         Literal::U32(v) => v as f32,
+                        Literal::I64(v) => v as f32,
+                        Literal::U64(v) => v as f32,
                         Literal::F32(v) => v,
+                        Literal::F16(v) => super::f16_to_f32(v),
                         Literal::Bool(v) => v as u32 as f32,
                         Literal::F64(_) => return Err(ConstantEvaluatorError::InvalidCastArg),
                     }),
+                    (ScalarKind::Float, 2) => Literal::F16(match literal {
+                        Literal::I32(v) => super::f32_to_f16(v as f32),
+                        Literal::U32(v) => super::f32_to_f16(v as f32),
+                        Literal::I64(v) => super::f32_to_f16(v as f32),
+                        Literal::U64(v) => super::f32_to_f16(v as f32),
+                        Literal::F32(v) => super::f32_to_f16(v),
+                        Literal::F16(v) => v,
+                        Literal::Bool(v) => super::f32_to_f16(v as u32 as f32),
+                        Literal::F64(_) => return Err(ConstantEvaluatorError::InvalidCastArg),
+                    }),
                     (ScalarKind::Bool, crate::BOOL_WIDTH) => Literal::Bool(match literal {
                         Literal::I32(v) => v != 0,
                         Literal::U32(v) => v != 0,
+                        Literal::I64(v) => v != 0,
+                        Literal::U64(v) => v != 0,
                         Literal::F32(v) => v != 0.0,
+                        Literal::F16(v) => super::f16_to_f32(v) != 0.0,
                         Literal::Bool(v) => v,
                         Literal::F64(_) => return Err(ConstantEvaluatorError::InvalidCastArg),
                     }),
@@ -988,13 +1336,13 @@ impl<'a> ConstantEvaluator<'a> {
                     *component = self.cast(*component, kind, target_width, span)?;
                 }
 
-                let ty = self.types.insert(
+                let ty = self.types.try_insert(
                     Type {
                         name: None,
                         inner: ty_inner,
                     },
                     span,
-                );
+                )?;
 
                 Expression::Compose { ty, components }
             }
@@ -1015,8 +1363,16 @@ impl<'a> ConstantEvaluator<'a> {
         let expr = match self.expressions[expr] {
             Expression::Literal(value) => Expression::Literal(match op {
                 UnaryOperator::Negate => match value {
-                    Literal::I32(v) => Literal::I32(-v),
+                    Literal::I32(v) => Literal::I32(
+                        v.checked_neg()
+                            .ok_or_else(|| ConstantEvaluatorError::Overflow("negation".into()))?,
+                    ),
+                    Literal::I64(v) => Literal::I64(
+                        v.checked_neg()
+                            .ok_or_else(|| ConstantEvaluatorError::Overflow("negation".into()))?,
+                    ),
                     Literal::F32(v) => Literal::F32(-v),
+                    Literal::F16(v) => Literal::F16(v ^ 0x8000),
                     _ => return Err(ConstantEvaluatorError::InvalidUnaryOpArg),
                 },
                 UnaryOperator::LogicalNot => match value {
@@ -1026,6 +1382,8 @@ impl<'a> ConstantEvaluator<'a> {
                 UnaryOperator::BitwiseNot => match value {
                     Literal::I32(v) => Literal::I32(!v),
                     Literal::U32(v) => Literal::U32(!v),
+                    Literal::I64(v) => Literal::I64(!v),
+                    Literal::U64(v) => Literal::U64(!v),
                     _ => return Err(ConstantEvaluatorError::InvalidUnaryOpArg),
                 },
             }),
@@ -1062,6 +1420,28 @@ impl<'a> ConstantEvaluator<'a> {
         let right = self.eval_zero_value_and_splat(right, span)?;
 
         let expr = match (&self.expressions[left], &self.expressions[right]) {
+            // Every `f16` is exactly representable as an `f32`, and `f32`
+            // has enough precision that rounding its results again to `f16`
+            // gives the correctly rounded `f16` result.
+            (&Expression::Literal(Literal::F16(a)), &Expression::Literal(Literal::F16(b))) => {
+                let a = super::f16_to_f32(a);
+                let b = super::f16_to_f32(b);
+                let literal = match op {
+                    BinaryOperator::Equal => Literal::Bool(a == b),
+                    BinaryOperator::NotEqual => Literal::Bool(a != b),
+                    BinaryOperator::Less => Literal::Bool(a < b),
+                    BinaryOperator::LessEqual => Literal::Bool(a <= b),
+                    BinaryOperator::Greater => Literal::Bool(a > b),
+                    BinaryOperator::GreaterEqual => Literal::Bool(a >= b),
+                    BinaryOperator::Add => Literal::F16(super::f32_to_f16(a + b)),
+                    BinaryOperator::Subtract => Literal::F16(super::f32_to_f16(a - b)),
+                    BinaryOperator::Multiply => Literal::F16(super::f32_to_f16(a * b)),
+                    BinaryOperator::Divide => Literal::F16(super::f32_to_f16(a / b)),
+                    BinaryOperator::Modulo => Literal::F16(super::f32_to_f16(a % b)),
+                    _ => return Err(ConstantEvaluatorError::InvalidBinaryOpArgs),
+                };
+                Expression::Literal(literal)
+            }
             (&Expression::Literal(left_value), &Expression::Literal(right_value)) => {
                 let literal = match op {
                     BinaryOperator::Equal => Literal::Bool(left_value == right_value),
@@ -1137,6 +1517,74 @@ impl<'a> ConstantEvaluator<'a> {
                                 .ok_or(ConstantEvaluatorError::ShiftedMoreThan32Bits)?,
                             _ => return Err(ConstantEvaluatorError::InvalidBinaryOpArgs),
                         }),
+                        (Literal::I64(a), Literal::I64(b)) => Literal::I64(match op {
+                            BinaryOperator::Add => a.checked_add(b).ok_or_else(|| {
+                                ConstantEvaluatorError::Overflow("addition".into())
+                            })?,
+                            BinaryOperator::Subtract => a.checked_sub(b).ok_or_else(|| {
+                                ConstantEvaluatorError::Overflow("subtraction".into())
+                            })?,
+                            BinaryOperator::Multiply => a.checked_mul(b).ok_or_else(|| {
+                                ConstantEvaluatorError::Overflow("multiplication".into())
+                            })?,
+                            BinaryOperator::Divide => a.checked_div(b).ok_or_else(|| {
+                                if b == 0 {
+                                    ConstantEvaluatorError::DivisionByZero
+                                } else {
+                                    ConstantEvaluatorError::Overflow("division".into())
+                                }
+                            })?,
+                            BinaryOperator::Modulo => a.checked_rem(b).ok_or_else(|| {
+                                if b == 0 {
+                                    ConstantEvaluatorError::RemainderByZero
+                                } else {
+                                    ConstantEvaluatorError::Overflow("remainder".into())
+                                }
+                            })?,
+                            BinaryOperator::And => a & b,
+                            BinaryOperator::ExclusiveOr => a ^ b,
+                            BinaryOperator::InclusiveOr => a | b,
+                            _ => return Err(ConstantEvaluatorError::InvalidBinaryOpArgs),
+                        }),
+                        (Literal::I64(a), Literal::U32(b)) => Literal::I64(match op {
+                            BinaryOperator::ShiftLeft => a
+                                .checked_shl(b)
+                                .ok_or(ConstantEvaluatorError::ShiftedMoreThan64Bits)?,
+                            BinaryOperator::ShiftRight => a
+                                .checked_shr(b)
+                                .ok_or(ConstantEvaluatorError::ShiftedMoreThan64Bits)?,
+                            _ => return Err(ConstantEvaluatorError::InvalidBinaryOpArgs),
+                        }),
+                        (Literal::U64(a), Literal::U64(b)) => Literal::U64(match op {
+                            BinaryOperator::Add => a.checked_add(b).ok_or_else(|| {
+                                ConstantEvaluatorError::Overflow("addition".into())
+                            })?,
+                            BinaryOperator::Subtract => a.checked_sub(b).ok_or_else(|| {
+                                ConstantEvaluatorError::Overflow("subtraction".into())
+                            })?,
+                            BinaryOperator::Multiply => a.checked_mul(b).ok_or_else(|| {
+                                ConstantEvaluatorError::Overflow("multiplication".into())
+                            })?,
+                            BinaryOperator::Divide => a
+                                .checked_div(b)
+                                .ok_or(ConstantEvaluatorError::DivisionByZero)?,
+                            BinaryOperator::Modulo => a
+                                .checked_rem(b)
+                                .ok_or(ConstantEvaluatorError::RemainderByZero)?,
+                            BinaryOperator::And => a & b,
+                            BinaryOperator::ExclusiveOr => a ^ b,
+                            BinaryOperator::InclusiveOr => a | b,
+                            _ => return Err(ConstantEvaluatorError::InvalidBinaryOpArgs),
+                        }),
+                        (Literal::U64(a), Literal::U32(b)) => Literal::U64(match op {
+                            BinaryOperator::ShiftLeft => a
+                                .checked_shl(b)
+                                .ok_or(ConstantEvaluatorError::ShiftedMoreThan64Bits)?,
+                            BinaryOperator::ShiftRight => a
+                                .checked_shr(b)
+                                .ok_or(ConstantEvaluatorError::ShiftedMoreThan64Bits)?,
+                            _ => return Err(ConstantEvaluatorError::InvalidBinaryOpArgs),
+                        }),
                         (Literal::F32(a), Literal::F32(b)) => Literal::F32(match op {
                             BinaryOperator::Add => a + b,
                             BinaryOperator::Subtract => a - b,
@@ -1211,6 +1659,10 @@ impl<'a> ConstantEvaluator<'a> {
                 }
                 self.register_evaluated_expr(Expression::Compose { ty, components }, span)
             }
+            Expression::Repeat { ty, value } => {
+                let value = self.copy_from(value, expressions)?;
+                self.register_evaluated_expr(Expression::Repeat { ty, value }, span)
+            }
             Expression::Splat { size, value } => {
                 let value = self.copy_from(value, expressions)?;
                 self.register_evaluated_expr(Expression::Splat { size, value }, span)
@@ -1245,21 +1697,84 @@ impl<'a> ConstantEvaluator<'a> {
             let needs_pre_emit = expr.needs_pre_emit();
             if is_running && needs_pre_emit {
                 block.extend(emitter.finish(self.expressions));
-                let h = self.expressions.append(expr, span);
+                let h = self.expressions.try_append(expr, span)?;
                 emitter.start(self.expressions);
                 expression_constness.insert(h);
                 Ok(h)
             } else {
-                let h = self.expressions.append(expr, span);
+                let h = self.expressions.try_append(expr, span)?;
                 expression_constness.insert(h);
                 Ok(h)
             }
         } else {
-            Ok(self.expressions.append(expr, span))
+            Ok(self.expressions.try_append(expr, span)?)
         }
     }
 }
 
+/// Apply `f` to the values of float literals `args`, which must all have
+/// the same type.
+///
+/// This does the arithmetic in `f64`, and rounds the result to the type of
+/// `args`.
+fn map_floats(
+    args: &[Literal],
+    f: impl Fn(&[f64]) -> f64,
+) -> Result<Literal, ConstantEvaluatorError> {
+    let values = float_values(args)?;
+    let result = f(&values[..args.len()]);
+    Ok(match args[0] {
+        Literal::F64(_) => Literal::F64(result),
+        Literal::F32(_) => Literal::F32(result as f32),
+        _ => Literal::F16(super::f32_to_f16(result as f32)),
+    })
+}
+
+/// Return the values of float literals `args`, which must all have the same
+/// type.
+fn float_values(args: &[Literal]) -> Result<[f64; 3], ConstantEvaluatorError> {
+    let mut values = [0.0; 3];
+    for (value, &arg) in values.iter_mut().zip(args) {
+        *value = match (arg, args[0]) {
+            (Literal::F64(v), Literal::F64(_)) => v,
+            (Literal::F32(v), Literal::F32(_)) => v.into(),
+            (Literal::F16(bits), Literal::F16(_)) => super::f16_to_f32(bits).into(),
+            _ => return Err(ConstantEvaluatorError::InvalidMathArg),
+        };
+    }
+    Ok(values)
+}
+
+/// Split `x` into a fraction with magnitude in `[0.5, 1)` and a power of two,
+/// as WGSL's `frexp` does.
+///
+/// Zero, infinities and NaN are returned unchanged, with an exponent of zero.
+fn frexp(x: f64) -> (f64, i32) {
+    if x == 0.0 || !x.is_finite() {
+        return (x, 0);
+    }
+    let bits = x.to_bits();
+    let exponent = ((bits >> 52) & 0x7ff) as i32;
+    if exponent == 0 {
+        // Subnormal: scale into the normal range first.
+        let (fract, exp) = frexp(x * 2f64.powi(54));
+        return (fract, exp - 54);
+    }
+    let fract = f64::from_bits((bits & !(0x7ff << 52)) | (1022 << 52));
+    (fract, exponent - 1022)
+}
+
+/// Round `x` to the nearest integer, and halfway cases to even, as WGSL's
+/// `round` does.
+fn round_ties_even(x: f64) -> f64 {
+    let rounded = x.round();
+    if (rounded - x).abs() == 0.5 {
+        2.0 * (x / 2.0).round()
+    } else {
+        rounded
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::vec;
@@ -1360,6 +1875,7 @@ mod tests {
             behavior: Behavior::Wgsl,
             types: &mut types,
             constants: &constants,
+            special_types: &Default::default(),
             expressions: &mut const_expressions,
             function_local_data: None,
         };
@@ -1447,6 +1963,7 @@ mod tests {
             behavior: Behavior::Wgsl,
             types: &mut types,
             constants: &constants,
+            special_types: &Default::default(),
             expressions: &mut const_expressions,
             function_local_data: None,
         };
@@ -1566,6 +2083,7 @@ mod tests {
             behavior: Behavior::Wgsl,
             types: &mut types,
             constants: &constants,
+            special_types: &Default::default(),
             expressions: &mut const_expressions,
             function_local_data: None,
         };
@@ -1661,6 +2179,7 @@ mod tests {
             behavior: Behavior::Wgsl,
             types: &mut types,
             constants: &constants,
+            special_types: &Default::default(),
             expressions: &mut const_expressions,
             function_local_data: None,
         };
@@ -1745,6 +2264,7 @@ mod tests {
             behavior: Behavior::Wgsl,
             types: &mut types,
             constants: &constants,
+            special_types: &Default::default(),
             expressions: &mut const_expressions,
             function_local_data: None,
         };
@@ -1782,4 +2302,183 @@ mod tests {
             panic!("unexpected evaluation result")
         }
     }
+
+    #[test]
+    fn math() {
+        use crate::MathFunction as Mf;
+
+        let mut types = UniqueArena::new();
+        let constants = Arena::new();
+        let mut const_expressions = Arena::new();
+
+        let vec2_f32_ty = types.insert(
+            Type {
+                name: None,
+                inner: TypeInner::Vector {
+                    size: VectorSize::Bi,
+                    kind: ScalarKind::Float,
+                    width: 4,
+                },
+            },
+            Default::default(),
+        );
+
+        let mut literal =
+            |literal| const_expressions.append(Expression::Literal(literal), Default::default());
+        let half = literal(Literal::F32(0.5));
+        let two = literal(Literal::F32(2.0));
+        let three = literal(Literal::F32(3.0));
+        let minus_four = literal(Literal::I32(-4));
+        let minus_one = literal(Literal::F32(-1.0));
+        let vector = const_expressions.append(
+            Expression::Compose {
+                ty: vec2_f32_ty,
+                components: vec![two, three],
+            },
+            Default::default(),
+        );
+        let reversed = const_expressions.append(
+            Expression::Compose {
+                ty: vec2_f32_ty,
+                components: vec![three, two],
+            },
+            Default::default(),
+        );
+
+        let mut solver = ConstantEvaluator {
+            behavior: Behavior::Wgsl,
+            types: &mut types,
+            constants: &constants,
+            special_types: &Default::default(),
+            expressions: &mut const_expressions,
+            function_local_data: None,
+        };
+        let mut eval = |fun, args: &[_]| {
+            solver.try_eval_and_append(
+                &Expression::Math {
+                    fun,
+                    arg: args[0],
+                    arg1: args.get(1).copied(),
+                    arg2: args.get(2).copied(),
+                    arg3: None,
+                },
+                Default::default(),
+            )
+        };
+
+        let round = eval(Mf::Round, &[half]).unwrap();
+        let abs = eval(Mf::Abs, &[minus_four]).unwrap();
+        let max = eval(Mf::Max, &[two, three]).unwrap();
+        // A scalar `t` mixes every component.
+        let mix = eval(Mf::Mix, &[vector, reversed, half]).unwrap();
+        // But there are no overloads like `max(vec2<f32>, f32)`.
+        assert!(matches!(
+            eval(Mf::Max, &[vector, three]),
+            Err(super::ConstantEvaluatorError::InvalidMathArg)
+        ));
+        assert!(matches!(
+            eval(Mf::Mix, &[vector, three, half]),
+            Err(super::ConstantEvaluatorError::InvalidMathArg)
+        ));
+        assert!(matches!(
+            eval(Mf::Sqrt, &[minus_one]),
+            Err(super::ConstantEvaluatorError::Literal(_))
+        ));
+        assert!(matches!(
+            eval(Mf::Max, &[two, minus_four]),
+            Err(super::ConstantEvaluatorError::InvalidMathArg)
+        ));
+
+        assert_eq!(
+            const_expressions[round],
+            Expression::Literal(Literal::F32(0.0))
+        );
+        assert_eq!(const_expressions[abs], Expression::Literal(Literal::I32(4)));
+        assert_eq!(
+            const_expressions[max],
+            Expression::Literal(Literal::F32(3.0))
+        );
+        match const_expressions[mix] {
+            Expression::Compose { ty, ref components } => {
+                assert_eq!(ty, vec2_f32_ty);
+                let components: Vec<_> = components
+                    .iter()
+                    .map(|&component| const_expressions[component].clone())
+                    .collect();
+                assert_eq!(
+                    components,
+                    [
+                        Expression::Literal(Literal::F32(2.5)),
+                        Expression::Literal(Literal::F32(2.5)),
+                    ]
+                );
+            }
+            ref other => panic!("expected a vector, got {other:?}"),
+        }
+    }
+
+    #[cfg(feature = "test-util")]
+    #[test]
+    fn modf_frexp() {
+        let module = crate::test_util::Fixture::wgsl(
+            "
+            const c_whole = modf(-2.5).whole;
+            const c_fract = modf(-2.5).fract;
+            const c_exp = frexp(vec2(1.5, 8.0)).exp;
+            ",
+        )
+        .module;
+
+        // The constants are evaluated, including through the result structs.
+        let init = |name: &str| {
+            let (_, constant) = module
+                .constants
+                .iter()
+                .find(|(_, c)| c.name.as_deref() == Some(name))
+                .unwrap();
+            &module.const_expressions[constant.init]
+        };
+        assert_eq!(*init("c_whole"), Expression::Literal(Literal::F32(-2.0)));
+        assert_eq!(*init("c_fract"), Expression::Literal(Literal::F32(-0.5)));
+        let Expression::Compose { ref components, .. } = *init("c_exp") else {
+            panic!("expected a vector, got {:?}", init("c_exp"));
+        };
+        let components: Vec<_> = components
+            .iter()
+            .map(|&c| module.const_expressions[c].clone())
+            .collect();
+        assert_eq!(
+            components,
+            [
+                Expression::Literal(Literal::I32(1)),
+                Expression::Literal(Literal::I32(4)),
+            ]
+        );
+    }
+
+    #[cfg(feature = "test-util")]
+    #[test]
+    fn repeated_array_elements() {
+        let module = crate::test_util::Fixture::wgsl(
+            "
+            const ones = array<i32, 4>(1, 1, 1, 1);
+            const zeros = array<f32, 4>(0.0, 0.0, 0.0, 0.0);
+
+            @compute @workgroup_size(1)
+            fn main() {
+                var a = ones;
+                var b = array<f32, 3>(0.5, 0.5, 0.5);
+                var c = zeros;
+                let x = ones[2];
+            }
+            ",
+        )
+        .module;
+        let repeats = module
+            .const_expressions
+            .iter()
+            .filter(|&(_, expr)| matches!(*expr, Expression::Repeat { .. }))
+            .count();
+        assert_eq!(repeats, 2);
+    }
 }
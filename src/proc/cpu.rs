@@ -0,0 +1,133 @@
+/*!
+Evaluating simple functions on the CPU, to check that passes keep their
+meaning.
+
+Several of the passes here rewrite straight-line arithmetic, which is easy
+to evaluate directly. [`call`] runs a function whose body is only `Emit`
+statements and a final `Return`, treating every value as a list of `f64`
+components. It panics on anything it doesn't understand. Tests build the
+modules to run with [`test_util`](crate::test_util).
+
+Converting an out-of-range float to an integer also panics, because that
+is what the languages our backends write leave undefined; a pass that
+claims to make such conversions well-defined can be checked by running
+its output here.
+*/
+
+use crate::{Expression, Function, Literal, MathFunction, ScalarKind};
+
+/// The function named `name` in `module`.
+pub fn function<'a>(module: &'a crate::Module, name: &str) -> &'a Function {
+    module
+        .functions
+        .iter()
+        .map(|(_, fun)| fun)
+        .find(|fun| fun.name.as_deref() == Some(name))
+        .unwrap_or_else(|| panic!("no function named `{name}`"))
+}
+
+/// The expression the function returns.
+pub fn returned(fun: &Function) -> crate::Handle<Expression> {
+    match fun.body.last() {
+        Some(&crate::Statement::Return { value: Some(value) }) => value,
+        other => panic!("expected a return, found {other:?}"),
+    }
+}
+
+/// Call the function named `name` in `module` with `args`, each given as
+/// its components, and return the components of its result.
+pub fn call(module: &crate::Module, name: &str, args: &[&[f64]]) -> Vec<f64> {
+    let fun = function(module, name);
+    eval(fun, args, returned(fun))
+}
+
+fn eval(fun: &Function, args: &[&[f64]], expr: crate::Handle<Expression>) -> Vec<f64> {
+    let eval = |expr| eval(fun, args, expr);
+    match fun.expressions[expr] {
+        Expression::FunctionArgument(index) => args[index as usize].to_vec(),
+        Expression::Literal(literal) => vec![match literal {
+            Literal::F64(value) => value,
+            Literal::F32(value) => value.into(),
+            Literal::F16(bits) => super::f16_to_f32(bits).into(),
+            Literal::U32(value) => value.into(),
+            Literal::I32(value) => value.into(),
+            Literal::U64(value) => value as f64,
+            Literal::I64(value) => value as f64,
+            Literal::Bool(value) => f64::from(u8::from(value)),
+        }],
+        Expression::Compose { ref components, .. } => components
+            .iter()
+            .flat_map(|&component| eval(component))
+            .collect(),
+        Expression::Splat { size, value } => eval(value).repeat(size as usize),
+        Expression::AccessIndex { base, index } => vec![eval(base)[index as usize]],
+        Expression::Binary { op, left, right } => {
+            let op: fn(f64, f64) -> f64 = match op {
+                crate::BinaryOperator::Add => |a, b| a + b,
+                crate::BinaryOperator::Subtract => |a, b| a - b,
+                crate::BinaryOperator::Multiply => |a, b| a * b,
+                other => panic!("can't evaluate {other:?}"),
+            };
+            zip(eval(left), eval(right), op)
+        }
+        Expression::Math {
+            fun: math,
+            arg,
+            arg1,
+            arg2,
+            ..
+        } => {
+            let arg = eval(arg);
+            let [arg1, arg2] = [arg1, arg2].map(|arg| arg.map(&eval));
+            match (math, arg1, arg2) {
+                (MathFunction::Abs, None, None) => arg.into_iter().map(f64::abs).collect(),
+                (MathFunction::Sin, None, None) => arg.into_iter().map(f64::sin).collect(),
+                (MathFunction::Cos, None, None) => arg.into_iter().map(f64::cos).collect(),
+                (MathFunction::Pow, Some(arg1), None) => zip(arg, arg1, f64::powf),
+                (MathFunction::Min, Some(arg1), None) => zip(arg, arg1, f64::min),
+                (MathFunction::Max, Some(arg1), None) => zip(arg, arg1, f64::max),
+                (MathFunction::Mix, Some(arg1), Some(t)) => zip(
+                    zip(arg, t.clone(), |a, t| a * (1.0 - t)),
+                    zip(arg1, t, |b, t| b * t),
+                    |a, b| a + b,
+                ),
+                (MathFunction::Clamp, Some(low), Some(high)) => {
+                    zip(zip(arg, low, f64::max), high, f64::min)
+                }
+                other => panic!("can't evaluate {other:?}"),
+            }
+        }
+        Expression::As {
+            expr,
+            kind,
+            convert: Some(width),
+        } => {
+            let bits = i32::from(width) * 8;
+            let range = match kind {
+                ScalarKind::Sint => -(2f64.powi(bits - 1))..2f64.powi(bits - 1),
+                ScalarKind::Uint => 0.0..2f64.powi(bits),
+                ScalarKind::Float => return eval(expr),
+                ScalarKind::Bool => panic!("can't evaluate conversions to bool"),
+            };
+            eval(expr)
+                .into_iter()
+                .map(|value| {
+                    let value = value.trunc();
+                    assert!(
+                        range.contains(&value),
+                        "{value} is out of range for a {width}-byte {kind:?}"
+                    );
+                    value
+                })
+                .collect()
+        }
+        ref other => panic!("can't evaluate {other:?}"),
+    }
+}
+
+fn zip(a: Vec<f64>, b: Vec<f64>, op: impl Fn(f64, f64) -> f64) -> Vec<f64> {
+    // A scalar operand applies to every component of a vector one.
+    let len = a.len().max(b.len());
+    let at = |values: &[f64], i: usize| values[if values.len() == 1 { 0 } else { i }];
+    (0..len).map(|i| op(at(&a, i), at(&b, i))).collect()
+}
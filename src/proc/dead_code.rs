@@ -0,0 +1,381 @@
+/*!
+Removing code that can never run.
+
+Front ends translate dead code faithfully, and so do the backends: a
+statement after a `return`, the branch of an `if` whose condition is a
+constant, or a helper function that no entry point calls all end up in
+the output. [`eliminate_dead_code`] removes them from the module first.
+*/
+
+use super::scalarize::for_each_function;
+use crate::arena::{Arena, Handle};
+use crate::{Block, Expression, Function, Literal, Statement};
+
+/// Remove code from `module` that can never run.
+///
+/// This removes:
+///
+/// - statements following a `return`, `kill`, `break` or `continue`, or
+///   an `if` both of whose branches end that way,
+///
+/// - the branch of an `if` that its condition rules out, when the
+///   condition is a `bool` literal or a constant that can't be
+///   overridden, and
+///
+/// - functions that no entry point calls, directly or indirectly.
+///
+/// Removing functions changes the handles of those that remain, and the
+/// removed code may leave unused expressions behind, which [`compact`]
+/// can clean up. The module should be validated again afterwards, to get
+/// a [`ModuleInfo`] that matches it.
+///
+/// # Panics
+///
+/// If `module` has not passed validation, this may panic.
+///
+/// [`compact`]: crate::compact::compact
+/// [`ModuleInfo`]: crate::valid::ModuleInfo
+pub fn eliminate_dead_code(module: &mut crate::Module) {
+    for_each_function(module, |module, fun| {
+        prune_block(module, &fun.expressions, &mut fun.body);
+        false
+    });
+    remove_uncalled_functions(module);
+}
+
+/// Remove unreachable statements from `block`, and return true if control
+/// never reaches its end.
+fn prune_block(module: &crate::Module, expressions: &Arena<Expression>, block: &mut Block) -> bool {
+    for index in 0..block.len() {
+        let diverges = match block[index] {
+            Statement::If {
+                condition,
+                ref mut accept,
+                ref mut reject,
+            } => match constant_bool(module, expressions, condition) {
+                Some(value) => {
+                    let taken = std::mem::take(if value { accept } else { reject });
+                    block[index] = Statement::Block(taken);
+                    match block[index] {
+                        Statement::Block(ref mut body) => prune_block(module, expressions, body),
+                        _ => unreachable!(),
+                    }
+                }
+                None => {
+                    let accept_diverges = prune_block(module, expressions, accept);
+                    let reject_diverges = prune_block(module, expressions, reject);
+                    accept_diverges && reject_diverges
+                }
+            },
+            Statement::Block(ref mut body) => prune_block(module, expressions, body),
+            Statement::Switch { ref mut cases, .. } => {
+                // A `break` in a case ends the switch, not the enclosing
+                // block, so a switch never counts as diverging.
+                for case in cases.iter_mut() {
+                    prune_block(module, expressions, &mut case.body);
+                }
+                false
+            }
+            Statement::Loop {
+                ref mut body,
+                ref mut continuing,
+                ..
+            } => {
+                prune_block(module, expressions, body);
+                prune_block(module, expressions, continuing);
+                false
+            }
+            Statement::Break | Statement::Continue | Statement::Return { .. } | Statement::Kill => {
+                true
+            }
+            _ => false,
+        };
+        if diverges {
+            block.cull(index + 1..);
+            return true;
+        }
+    }
+    false
+}
+
+/// Return the value of `condition`, if it is known before the shader runs.
+fn constant_bool(
+    module: &crate::Module,
+    expressions: &Arena<Expression>,
+    condition: Handle<Expression>,
+) -> Option<bool> {
+    let init = match expressions[condition] {
+        Expression::Literal(Literal::Bool(value)) => return Some(value),
+        Expression::Constant(handle) => {
+            let constant = &module.constants[handle];
+            if constant.r#override != crate::Override::None {
+                return None;
+            }
+            constant.init
+        }
+        _ => return None,
+    };
+    match module.const_expressions[init] {
+        Expression::Literal(Literal::Bool(value)) => Some(value),
+        _ => None,
+    }
+}
+
+/// Remove the functions no entry point calls, and fix up the calls to the
+/// rest.
+fn remove_uncalled_functions(module: &mut crate::Module) {
+    let mut called = vec![false; module.functions.len()];
+    let mut pending: Vec<Handle<Function>> = Vec::new();
+    for ep in module.entry_points.iter() {
+        for_each_callee(&ep.function, &mut |function| pending.push(function));
+    }
+    while let Some(function) = pending.pop() {
+        if !std::mem::replace(&mut called[function.index()], true) {
+            for_each_callee(&module.functions[function], &mut |callee| {
+                pending.push(callee)
+            });
+        }
+    }
+    if called.iter().all(|&called| called) {
+        return;
+    }
+
+    let old_functions = std::mem::take(&mut module.functions);
+    let spans: Vec<_> = old_functions
+        .iter()
+        .map(|(handle, _)| old_functions.get_span(handle))
+        .collect();
+    let mut new_handles = Vec::with_capacity(called.len());
+    for ((function, span), called) in old_functions
+        .into_inner()
+        .into_iter()
+        .zip(spans)
+        .zip(called)
+    {
+        new_handles.push(called.then(|| module.functions.append(function, span)));
+    }
+
+    // Every function still called from somewhere was kept.
+    let new_handle = |function: Handle<Function>| new_handles[function.index()].unwrap();
+    let retarget = |function: &mut Function| {
+        retarget_calls(&mut function.body, &new_handle);
+        for (_, expression) in function.expressions.iter_mut() {
+            if let Expression::CallResult(ref mut callee) = *expression {
+                *callee = new_handle(*callee);
+            }
+        }
+    };
+    for (_, function) in module.functions.iter_mut() {
+        retarget(function);
+    }
+    for ep in module.entry_points.iter_mut() {
+        retarget(&mut ep.function);
+    }
+}
+
+/// Call `f` on each function `function` calls.
+///
+/// This includes the functions named by [`CallResult`] expressions, even
+/// if pruning has removed the calls that produced them, so that the
+/// expressions still refer to functions in the module.
+///
+/// [`CallResult`]: Expression::CallResult
+fn for_each_callee(function: &Function, f: &mut impl FnMut(Handle<Function>)) {
+    for_each_call(&function.body, f);
+    for (_, expression) in function.expressions.iter() {
+        if let Expression::CallResult(callee) = *expression {
+            f(callee);
+        }
+    }
+}
+
+/// Call `f` on the function each [`Call`] statement in `block` calls.
+///
+/// [`Call`]: Statement::Call
+pub(super) fn for_each_call(block: &Block, f: &mut impl FnMut(Handle<Function>)) {
+    for statement in block.iter() {
+        match *statement {
+            Statement::Call { function, .. } => f(function),
+            Statement::Block(ref body) => for_each_call(body, f),
+            Statement::If {
+                ref accept,
+                ref reject,
+                ..
+            } => {
+                for_each_call(accept, f);
+                for_each_call(reject, f);
+            }
+            Statement::Switch { ref cases, .. } => {
+                for case in cases.iter() {
+                    for_each_call(&case.body, f);
+                }
+            }
+            Statement::Loop {
+                ref body,
+                ref continuing,
+                ..
+            } => {
+                for_each_call(body, f);
+                for_each_call(continuing, f);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Replace the function each [`Call`] statement in `block` calls with
+/// `new_handle` of it.
+///
+/// [`Call`]: Statement::Call
+pub(super) fn retarget_calls(
+    block: &mut Block,
+    new_handle: &impl Fn(Handle<Function>) -> Handle<Function>,
+) {
+    for statement in block.iter_mut() {
+        match *statement {
+            Statement::Call {
+                ref mut function, ..
+            } => *function = new_handle(*function),
+            Statement::Block(ref mut body) => retarget_calls(body, new_handle),
+            Statement::If {
+                ref mut accept,
+                ref mut reject,
+                ..
+            } => {
+                retarget_calls(accept, new_handle);
+                retarget_calls(reject, new_handle);
+            }
+            Statement::Switch { ref mut cases, .. } => {
+                for case in cases.iter_mut() {
+                    retarget_calls(&mut case.body, new_handle);
+                }
+            }
+            Statement::Loop {
+                ref mut body,
+                ref mut continuing,
+                ..
+            } => {
+                retarget_calls(body, new_handle);
+                retarget_calls(continuing, new_handle);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod tests {
+    fn module(source: &str) -> crate::Module {
+        let mut fixture = crate::test_util::Fixture::wgsl(source);
+        super::eliminate_dead_code(&mut fixture.module);
+        fixture.revalidate();
+        fixture.module
+    }
+
+    #[test]
+    fn statements() {
+        let module = module(
+            "
+            const DEBUG = false;
+            var<private> x: i32;
+
+            @compute @workgroup_size(1)
+            fn main() {
+                loop {
+                    if x > 3 {
+                        break;
+                    } else {
+                        continue;
+                    }
+                    x = 1;
+                }
+                if DEBUG {
+                    x = 2;
+                }
+                if true {
+                    x = 3;
+                    return;
+                }
+                x = 4;
+            }
+            ",
+        );
+        let body = &module.entry_points[0].function.body;
+        let crate::Statement::Loop {
+            body: ref loop_body,
+            ..
+        } = body[0]
+        else {
+            panic!("expected a loop, got {:?}", body[0]);
+        };
+        assert!(matches!(
+            loop_body.last(),
+            Some(&crate::Statement::If { .. })
+        ));
+
+        // `if DEBUG` became an empty block, and `if true` its accept
+        // block, after which nothing runs.
+        assert!(matches!(body[1], crate::Statement::Block(ref b) if b.is_empty()));
+        let crate::Statement::Block(ref taken) = body[2] else {
+            panic!("expected a block, got {:?}", body[2]);
+        };
+        assert!(matches!(
+            taken.last(),
+            Some(&crate::Statement::Return { .. })
+        ));
+        assert_eq!(body.len(), 3);
+    }
+
+    #[test]
+    fn functions() {
+        let module = module(
+            "
+            fn unused() -> i32 { return 1; }
+            fn leaf() -> i32 { return 2; }
+            fn helper() -> i32 { return leaf(); }
+            fn also_unused() -> i32 { return helper() + unused(); }
+
+            @compute @workgroup_size(1)
+            fn main() {
+                _ = helper();
+            }
+            ",
+        );
+        let names: Vec<_> = module
+            .functions
+            .iter()
+            .map(|(_, f)| f.name.as_deref().unwrap())
+            .collect();
+        assert_eq!(names, ["leaf", "helper"]);
+    }
+
+    #[test]
+    fn call_results() {
+        // Removing `unused` moves `called` to a new handle, which the call's
+        // result must follow.
+        let module = module(
+            "
+            fn unused() -> i32 { return 1; }
+            fn called() -> i32 { return 2; }
+
+            @compute @workgroup_size(1)
+            fn main() {
+                let x = called();
+                _ = x;
+            }
+            ",
+        );
+        assert_eq!(module.functions.len(), 1);
+        let function = &module.entry_points[0].function;
+        let callees: Vec<_> = function
+            .expressions
+            .iter()
+            .filter_map(|(_, expression)| match *expression {
+                crate::Expression::CallResult(callee) => Some(callee),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(callees.len(), 1);
+        assert_eq!(module.functions[callees[0]].name.as_deref(), Some("called"));
+    }
+}
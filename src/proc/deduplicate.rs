@@ -0,0 +1,247 @@
+/*!
+Merging types that differ only in name.
+
+Front ends often produce several types with the same structure: a SPIR-V
+module, for example, may declare the same struct more than once, under
+different names or none. [`deduplicate_types`] keeps one of each, so that
+backends write fewer declarations.
+*/
+
+use super::link::copy_type_inner;
+use crate::arena::Handle;
+use crate::{Expression, FastHashMap, Function, Type, TypeInner};
+
+/// Merge the types of `module` that have the same structure, and return
+/// true if any were merged.
+///
+/// Two types have the same structure if they are the same apart from their
+/// names, once the types they refer to have been merged. The merged type
+/// takes the name of the first of them that has one, and every use of any
+/// of them, in globals, constants, functions and expressions, refers to the
+/// merged type instead.
+///
+/// Types that [`special_types`] refers to are never merged with others,
+/// since front ends and backends recognize them by handle.
+///
+/// The module should be validated again afterwards, to get a
+/// [`ModuleInfo`] that matches it.
+///
+/// [`special_types`]: crate::Module::special_types
+/// [`ModuleInfo`]: crate::valid::ModuleInfo
+pub fn deduplicate_types(module: &mut crate::Module) -> bool {
+    let special_types = &module.special_types;
+    let special: Vec<Handle<Type>> = special_types
+        .ray_desc
+        .iter()
+        .chain(special_types.ray_intersection.iter())
+        .chain(special_types.predeclared_types.values())
+        .copied()
+        .collect();
+
+    // For each type, the first type with the same structure. Types only
+    // refer to types that come before them, so those have been merged
+    // already.
+    let mut representatives = Vec::with_capacity(module.types.len());
+    let mut structures: FastHashMap<TypeInner, Handle<Type>> = FastHashMap::default();
+    for (handle, ty) in module.types.iter() {
+        let inner = copy_type_inner(&ty.inner, &representatives);
+        let representative = if special.contains(&handle) {
+            handle
+        } else {
+            *structures.entry(inner).or_insert(handle)
+        };
+        representatives.push(representative);
+    }
+    if representatives
+        .iter()
+        .enumerate()
+        .all(|(index, representative)| representative.index() == index)
+    {
+        return false;
+    }
+
+    let mut names: Vec<Option<String>> = vec![None; representatives.len()];
+    for (handle, ty) in module.types.iter() {
+        let name = &mut names[representatives[handle.index()].index()];
+        if name.is_none() {
+            name.clone_from(&ty.name);
+        }
+    }
+
+    let old_types = std::mem::take(&mut module.types);
+    let mut new_handles = Vec::with_capacity(representatives.len());
+    for (handle, ty) in old_types.iter() {
+        let representative = representatives[handle.index()];
+        let new_handle = if representative == handle {
+            let ty = Type {
+                name: names[handle.index()].take(),
+                inner: copy_type_inner(&ty.inner, &new_handles),
+            };
+            module.types.insert(ty, old_types.get_span(handle))
+        } else {
+            new_handles[representative.index()]
+        };
+        new_handles.push(new_handle);
+    }
+
+    retarget_types(module, &new_handles);
+    true
+}
+
+/// Make every use of a type in `module` refer to `new_handles[old.index()]`
+/// instead of `old`, in the special types, constants, globals, functions
+/// and expressions.
+///
+/// This doesn't touch the types themselves, which the caller has already
+/// rebuilt.
+pub(super) fn retarget_types(module: &mut crate::Module, new_handles: &[Handle<Type>]) {
+    let adjust = |ty: &mut Handle<Type>| *ty = new_handles[ty.index()];
+    let special_types = &mut module.special_types;
+    if let Some(ref mut ty) = special_types.ray_desc {
+        adjust(ty);
+    }
+    if let Some(ref mut ty) = special_types.ray_intersection {
+        adjust(ty);
+    }
+    for ty in special_types.predeclared_types.values_mut() {
+        adjust(ty);
+    }
+    for (_, constant) in module.constants.iter_mut() {
+        adjust(&mut constant.ty);
+    }
+    for (_, global) in module.global_variables.iter_mut() {
+        adjust(&mut global.ty);
+    }
+    for (_, expression) in module.const_expressions.iter_mut() {
+        adjust_expression(expression, &adjust);
+    }
+    let functions = module.functions.iter_mut().map(|(_, function)| function);
+    let entry_points = module.entry_points.iter_mut().map(|ep| &mut ep.function);
+    for function in functions.chain(entry_points) {
+        adjust_function(function, &adjust);
+    }
+}
+
+fn adjust_function(function: &mut Function, adjust: &impl Fn(&mut Handle<Type>)) {
+    for argument in function.arguments.iter_mut() {
+        adjust(&mut argument.ty);
+    }
+    if let Some(ref mut result) = function.result {
+        adjust(&mut result.ty);
+    }
+    for (_, local) in function.local_variables.iter_mut() {
+        adjust(&mut local.ty);
+    }
+    for (_, expression) in function.expressions.iter_mut() {
+        adjust_expression(expression, adjust);
+    }
+}
+
+/// Adjust the type `expression` refers to, if any.
+fn adjust_expression(expression: &mut Expression, adjust: &impl Fn(&mut Handle<Type>)) {
+    match *expression {
+        Expression::ZeroValue(ref mut ty)
+        | Expression::Compose { ref mut ty, .. }
+        | Expression::Repeat { ref mut ty, .. }
+        | Expression::AtomicResult { ref mut ty, .. }
+        | Expression::WorkGroupUniformLoadResult { ref mut ty } => adjust(ty),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::deduplicate_types;
+    use crate::{Span, Type, TypeInner};
+
+    #[test]
+    fn prefer_named() {
+        let mut module = crate::Module::default();
+        let float = |name: Option<&str>| Type {
+            name: name.map(str::to_string),
+            inner: TypeInner::Scalar {
+                kind: crate::ScalarKind::Float,
+                width: 4,
+            },
+        };
+        let unnamed = module.types.insert(float(None), Span::UNDEFINED);
+        let named = module.types.insert(float(Some("float")), Span::UNDEFINED);
+        let global = |ty| crate::GlobalVariable {
+            name: None,
+            space: crate::AddressSpace::Private,
+            binding: None,
+            ty,
+            init: None,
+        };
+        module
+            .global_variables
+            .append(global(unnamed), Span::UNDEFINED);
+        module
+            .global_variables
+            .append(global(named), Span::UNDEFINED);
+
+        assert!(deduplicate_types(&mut module));
+        assert_eq!(module.types.len(), 1);
+        let (handle, ty) = module.types.iter().next().unwrap();
+        assert_eq!(ty.name.as_deref(), Some("float"));
+        assert!(module
+            .global_variables
+            .iter()
+            .all(|(_, global)| global.ty == handle));
+        assert!(!deduplicate_types(&mut module));
+    }
+
+    #[cfg(feature = "wgsl-in")]
+    #[test]
+    fn merge_structs() {
+        let mut module = crate::front::wgsl::parse_str(
+            "
+            struct A { x: f32, y: vec2<f32> }
+            struct B { x: f32, y: vec2<f32> }
+            struct C { x: f32, z: vec2<f32> }
+
+            @group(0) @binding(0) var<storage> a: array<A, 2>;
+            @group(0) @binding(1) var<storage> b: array<B, 2>;
+            @group(0) @binding(2) var<storage, read_write> c: C;
+
+            fn get(value: B) -> f32 {
+                return value.x;
+            }
+
+            @compute @workgroup_size(1)
+            fn main() {
+                c.x = get(B(a[0].x, b[1].y));
+            }
+            ",
+        )
+        .unwrap();
+        let validate = |module: &crate::Module| {
+            crate::valid::Validator::new(
+                crate::valid::ValidationFlags::all(),
+                crate::valid::Capabilities::default(),
+            )
+            .validate(module)
+            .unwrap()
+        };
+        validate(&module);
+        let types = module.types.len();
+
+        assert!(deduplicate_types(&mut module));
+        validate(&module);
+        // `B` and `array<B, 2>` are gone.
+        assert_eq!(module.types.len(), types - 2);
+        let names: Vec<_> = module
+            .types
+            .iter()
+            .filter_map(|(_, ty)| ty.name.as_deref())
+            .collect();
+        assert_eq!(names, ["A", "C"]);
+        let globals: Vec<_> = module
+            .global_variables
+            .iter()
+            .map(|(_, global)| global.ty)
+            .collect();
+        assert_eq!(globals[0], globals[1]);
+        assert_ne!(globals[0], globals[2]);
+    }
+}
@@ -0,0 +1,115 @@
+//! Conversions between `f32` and the bits of IEEE 754 binary16 values.
+//!
+//! Rust has no stable `f16` type, so [`Literal::F16`] holds the bits, and
+//! constant evaluation does its arithmetic in `f32`.
+//!
+//! [`Literal::F16`]: crate::Literal::F16
+
+/// Convert `value` to the nearest binary16 value, rounding ties to even.
+///
+/// Values too large for binary16 become infinities.
+pub fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+
+    if exponent == 0xff {
+        // Infinity, or NaN, which we keep quiet.
+        let nan = if mantissa != 0 { 0x200 } else { 0 };
+        return sign | 0x7c00 | nan;
+    }
+
+    // Rebias the exponent.
+    let exponent = exponent - 127 + 15;
+    if exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+
+    if exponent <= 0 {
+        // The result is subnormal, or rounds to zero.
+        if exponent < -10 {
+            return sign;
+        }
+        let mantissa = mantissa | 0x80_0000;
+        let shift = (14 - exponent) as u32;
+        let mut result = mantissa >> shift;
+        let rest = mantissa & ((1 << shift) - 1);
+        let halfway = 1 << (shift - 1);
+        if rest > halfway || (rest == halfway && result & 1 != 0) {
+            // This may carry into the exponent, giving the smallest normal
+            // value, which is right.
+            result += 1;
+        }
+        return sign | result as u16;
+    }
+
+    let mut result = ((exponent as u32) << 10) | (mantissa >> 13);
+    let rest = mantissa & 0x1fff;
+    if rest > 0x1000 || (rest == 0x1000 && result & 1 != 0) {
+        // This may carry into the exponent, up to infinity, which is right.
+        result += 1;
+    }
+    sign | result as u16
+}
+
+/// Convert the binary16 value with the given `bits` to `f32`, exactly.
+pub fn f16_to_f32(bits: u16) -> f32 {
+    let sign = ((bits & 0x8000) as u32) << 16;
+    let exponent = ((bits >> 10) & 0x1f) as u32;
+    let mantissa = (bits & 0x3ff) as u32;
+
+    let bits = match exponent {
+        0 if mantissa == 0 => sign,
+        0 => {
+            // Subnormal: every subnormal binary16 value is a normal `f32`.
+            let shift = mantissa.leading_zeros() - 21;
+            let mantissa = (mantissa << shift) & 0x3ff;
+            sign | ((113 - shift) << 23) | (mantissa << 13)
+        }
+        0x1f => sign | 0x7f80_0000 | (mantissa << 13),
+        _ => sign | ((exponent + 127 - 15) << 23) | (mantissa << 13),
+    };
+    f32::from_bits(bits)
+}
+
+/// Convert a small integer to binary16, for [`Literal::new`].
+///
+/// [`Literal::new`]: crate::Literal::new
+pub(super) const fn u8_to_f16(value: u8) -> u16 {
+    if value == 0 {
+        return 0;
+    }
+    // Every `u8` fits in the 11 significant bits of binary16.
+    let top = 7 - value.leading_zeros();
+    let mantissa = ((value as u32) << (10 - top)) & 0x3ff;
+    (((top + 15) << 10) | mantissa) as u16
+}
+
+#[test]
+fn conversions() {
+    for (value, bits) in [
+        (0.0, 0x0000),
+        (-0.0, 0x8000),
+        (1.0, 0x3c00),
+        (-2.0, 0xc000),
+        (0.5, 0x3800),
+        (65504.0, 0x7bff),
+        // The smallest normal and subnormal values.
+        (6.1035156e-5, 0x0400),
+        (5.9604645e-8, 0x0001),
+    ] {
+        assert_eq!(f32_to_f16(value), bits, "{value}");
+        assert_eq!(f16_to_f32(bits), value, "{bits:#x}");
+    }
+
+    // Too large, and halfway between two values.
+    assert_eq!(f32_to_f16(65520.0), 0x7c00);
+    assert_eq!(f32_to_f16(1.0 + 1.0 / 2048.0), 0x3c00);
+    assert_eq!(f32_to_f16(1.0 + 3.0 / 2048.0), 0x3c02);
+    assert!(f16_to_f32(f32_to_f16(f32::NAN)).is_nan());
+
+    for value in 0..=u8::MAX {
+        assert_eq!(u8_to_f16(value), f32_to_f16(value as f32), "{value}");
+    }
+}
@@ -112,6 +112,9 @@ pub struct BoundsCheckPolicies {
     /// This controls the behavior of [`ImageLoad`] expressions when a coordinate,
     /// texture array index, level of detail, or multisampled sample number is out of range.
     ///
+    /// Under `ReadZeroSkipWrite`, each backend checks all four, as unsigned
+    /// values, so negative values are out of range too.
+    ///
     /// [`ImageLoad`]: crate::Expression::ImageLoad
     #[cfg_attr(feature = "deserialize", serde(default))]
     pub image_load: BoundsCheckPolicy,
@@ -0,0 +1,694 @@
+/*!
+Inlining function calls.
+
+Some targets handle calls poorly: GLSL ES can't pass samplers or textures
+to functions in all the ways WGSL can, and some Metal versions have trouble
+with similar patterns. [`inline_functions`] replaces calls with the bodies
+of the functions they call, so the backends never see them.
+*/
+
+use super::scalarize::{adjust_expression, adjust_statement, for_each_function};
+use crate::arena::{Arena, Handle, Range};
+use crate::{Block, Expression, Function, LocalVariable, Span, Statement};
+
+/// Which calls [`inline_functions`] should inline.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InlinePolicy {
+    /// Inline every call.
+    Always,
+
+    /// Inline calls to functions with at most this many expressions.
+    SizeThreshold(usize),
+
+    /// Inline calls to functions that take images, samplers, binding arrays
+    /// or acceleration structures as arguments, which some targets can't
+    /// pass to functions.
+    RequiredOnly,
+}
+
+/// Replace the calls that `policy` selects with the bodies of the functions
+/// they call.
+///
+/// Inlined functions stay in the module, even if there are no calls to them
+/// left; use [`eliminate_dead_code`] to remove those. The module should be
+/// validated again afterwards, to get a [`ModuleInfo`] that matches it.
+///
+/// A function that returns from inside a loop or a `switch` can't be inlined,
+/// since nothing but a `return` could leave both at once, so calls to those
+/// are left alone.
+///
+/// # Panics
+///
+/// If `module` has not passed validation, this may panic.
+///
+/// [`eliminate_dead_code`]: super::eliminate_dead_code
+/// [`ModuleInfo`]: crate::valid::ModuleInfo
+pub fn inline_functions(module: &mut crate::Module, policy: InlinePolicy) {
+    let inline: Vec<bool> = module
+        .functions
+        .iter()
+        .map(|(_, fun)| selected(module, fun, policy) && can_inline(&fun.body, false))
+        .collect();
+    if !inline.contains(&true) {
+        return;
+    }
+
+    // Functions only call functions before them in the arena, and
+    // `for_each_function` visits them in order, so callees have had their
+    // own calls inlined by the time we inline them.
+    for_each_function(module, |module, fun| inline_calls(module, fun, &inline));
+}
+
+fn selected(module: &crate::Module, fun: &Function, policy: InlinePolicy) -> bool {
+    match policy {
+        InlinePolicy::Always => true,
+        InlinePolicy::SizeThreshold(max) => fun.expressions.len() <= max,
+        InlinePolicy::RequiredOnly => fun.arguments.iter().any(|arg| {
+            matches!(
+                module.types[arg.ty].inner,
+                crate::TypeInner::Image { .. }
+                    | crate::TypeInner::Sampler { .. }
+                    | crate::TypeInner::BindingArray { .. }
+                    | crate::TypeInner::AccelerationStructure
+            )
+        }),
+    }
+}
+
+/// Return true if no `Return` in `block` is inside a loop or a `switch`.
+fn can_inline(block: &Block, nested: bool) -> bool {
+    block.iter().all(|statement| match *statement {
+        Statement::Return { .. } => !nested,
+        Statement::Block(ref body) => can_inline(body, nested),
+        Statement::If {
+            ref accept,
+            ref reject,
+            ..
+        } => can_inline(accept, nested) && can_inline(reject, nested),
+        Statement::Switch { ref cases, .. } => {
+            cases.iter().all(|case| can_inline(&case.body, true))
+        }
+        Statement::Loop {
+            ref body,
+            ref continuing,
+            ..
+        } => can_inline(body, true) && can_inline(continuing, true),
+        _ => true,
+    })
+}
+
+/// Return true if `block` contains a `Return` other than its last statement.
+///
+/// This assumes [`can_inline`] is true of `block`.
+fn returns_early(block: &Block) -> bool {
+    fn contains_return(statement: &Statement) -> bool {
+        match *statement {
+            Statement::Return { .. } => true,
+            Statement::Block(ref body) => body.iter().any(contains_return),
+            Statement::If {
+                ref accept,
+                ref reject,
+                ..
+            } => accept.iter().chain(reject.iter()).any(contains_return),
+            _ => false,
+        }
+    }
+    let rest = match block.split_last() {
+        Some((&Statement::Return { .. }, rest)) => rest,
+        _ => block,
+    };
+    rest.iter().any(contains_return)
+}
+
+/// A call `inline_calls` is inlining.
+struct Call {
+    function: Handle<Function>,
+    arguments: Vec<Handle<Expression>>,
+    result: Option<Handle<Expression>>,
+
+    /// The caller's handles for the callee's expressions.
+    expressions: Vec<Handle<Expression>>,
+
+    /// The caller's handles for the callee's local variables.
+    locals: Vec<Handle<LocalVariable>>,
+
+    /// A pointer to the local holding the callee's return value, and the
+    /// `Load` of it that replaces the call's result.
+    result_pointer: Option<(Handle<Expression>, Handle<Expression>)>,
+}
+
+fn collect_calls(block: &Block, inline: &[bool], calls: &mut Vec<Call>) {
+    for statement in block.iter() {
+        match *statement {
+            Statement::Call {
+                function,
+                ref arguments,
+                result,
+            } if inline[function.index()] => calls.push(Call {
+                function,
+                arguments: arguments.clone(),
+                result,
+                expressions: Vec::new(),
+                locals: Vec::new(),
+                result_pointer: None,
+            }),
+            Statement::Block(ref body) => collect_calls(body, inline, calls),
+            Statement::If {
+                ref accept,
+                ref reject,
+                ..
+            } => {
+                collect_calls(accept, inline, calls);
+                collect_calls(reject, inline, calls);
+            }
+            Statement::Switch { ref cases, .. } => {
+                for case in cases.iter() {
+                    collect_calls(&case.body, inline, calls);
+                }
+            }
+            Statement::Loop {
+                ref body,
+                ref continuing,
+                ..
+            } => {
+                collect_calls(body, inline, calls);
+                collect_calls(continuing, inline, calls);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn inline_calls(module: &crate::Module, caller: &mut Function, inline: &[bool]) -> bool {
+    let mut calls = Vec::new();
+    collect_calls(&caller.body, inline, &mut calls);
+    if calls.is_empty() {
+        return false;
+    }
+
+    // Rebuild the caller's expression arena, putting each callee's
+    // expressions where its call's result was, so that they come after the
+    // arguments they use, and before the expressions that use the result.
+    let old_expressions = std::mem::take(&mut caller.expressions);
+    let mut map = Vec::with_capacity(old_expressions.len());
+    let by_result: crate::FastHashMap<_, _> = calls
+        .iter()
+        .enumerate()
+        .filter_map(|(index, call)| Some((call.result?, index)))
+        .collect();
+    for (handle, expr) in old_expressions.iter() {
+        let span = old_expressions.get_span(handle);
+        let new_handle = match by_result.get(&handle) {
+            Some(&index) => {
+                let call = &mut calls[index];
+                copy_callee(module, caller, call, &map, span);
+                call.result_pointer.unwrap().1
+            }
+            None => {
+                let mut expr = expr.clone();
+                adjust_expression(&mut expr, &mut |h| *h = map[h.index()]);
+                caller.expressions.append(expr, span)
+            }
+        };
+        map.push(new_handle);
+    }
+    for call in calls.iter_mut().filter(|call| call.result.is_none()) {
+        copy_callee(module, caller, call, &map, Span::UNDEFINED);
+    }
+
+    for (&handle, name) in std::mem::take(&mut caller.named_expressions).iter() {
+        caller
+            .named_expressions
+            .insert(map[handle.index()], name.clone());
+    }
+    for call in calls.iter() {
+        let callee = &module.functions[call.function];
+        for (&handle, name) in callee.named_expressions.iter() {
+            caller
+                .named_expressions
+                .entry(call.expressions[handle.index()])
+                .or_insert_with(|| name.clone());
+        }
+    }
+
+    let old_body = std::mem::take(&mut caller.body);
+    let mut inliner = Inliner {
+        module,
+        inline,
+        expressions: &mut caller.expressions,
+        calls,
+        next_call: 0,
+    };
+    let scope = Scope {
+        expressions: &map,
+        locals: None,
+        return_to: None,
+    };
+    caller.body = inliner.rebuild_block(&old_body, &scope, false);
+    true
+}
+
+/// Append `call`'s callee's expressions and local variables to `caller`.
+///
+/// `map` gives the caller's new handles for its old expressions, which must
+/// include `call`'s arguments.
+fn copy_callee(
+    module: &crate::Module,
+    caller: &mut Function,
+    call: &mut Call,
+    map: &[Handle<Expression>],
+    span: Span,
+) {
+    let callee = &module.functions[call.function];
+
+    // Initializers refer to expressions we haven't copied yet, so fill them
+    // in afterwards.
+    call.locals = callee
+        .local_variables
+        .iter()
+        .map(|(handle, local)| {
+            caller.local_variables.append(
+                LocalVariable {
+                    name: local.name.clone(),
+                    ty: local.ty,
+                    init: None,
+                },
+                callee.local_variables.get_span(handle),
+            )
+        })
+        .collect();
+
+    let expressions = &mut call.expressions;
+    expressions.reserve(callee.expressions.len());
+    for (handle, expr) in callee.expressions.iter() {
+        let new_handle = match *expr {
+            Expression::FunctionArgument(index) => map[call.arguments[index as usize].index()],
+            Expression::LocalVariable(local) => caller.expressions.append(
+                Expression::LocalVariable(call.locals[local.index()]),
+                callee.expressions.get_span(handle),
+            ),
+            _ => {
+                let mut expr = expr.clone();
+                adjust_expression(&mut expr, &mut |h| *h = expressions[h.index()]);
+                caller
+                    .expressions
+                    .append(expr, callee.expressions.get_span(handle))
+            }
+        };
+        expressions.push(new_handle);
+    }
+
+    for ((_, local), &new_local) in callee.local_variables.iter().zip(call.locals.iter()) {
+        caller.local_variables.get_mut(new_local).init =
+            local.init.map(|init| expressions[init.index()]);
+    }
+
+    if let (Some(_), Some(result)) = (call.result, callee.result.as_ref()) {
+        let local = caller.local_variables.append(
+            LocalVariable {
+                name: None,
+                ty: result.ty,
+                init: None,
+            },
+            span,
+        );
+        let pointer = caller
+            .expressions
+            .append(Expression::LocalVariable(local), span);
+        let load = caller
+            .expressions
+            .append(Expression::Load { pointer }, span);
+        call.result_pointer = Some((pointer, load));
+    }
+}
+
+/// What a block being rebuilt belongs to.
+struct Scope<'a> {
+    /// The caller's handles for the expressions the block uses.
+    expressions: &'a [Handle<Expression>],
+
+    /// The caller's handles for the local variables the block uses, or
+    /// `None` if the block is the caller's own.
+    locals: Option<&'a [Handle<LocalVariable>]>,
+
+    /// How to translate `Return` statements, if the block is an inlined
+    /// callee's.
+    return_to: Option<ReturnTo>,
+}
+
+struct ReturnTo {
+    /// Where to store the returned value, if the caller uses it.
+    pointer: Option<Handle<Expression>>,
+
+    /// Whether to break out of a loop wrapped around the callee's body.
+    break_out: bool,
+}
+
+struct Inliner<'a> {
+    module: &'a crate::Module,
+    inline: &'a [bool],
+    expressions: &'a mut Arena<Expression>,
+    calls: Vec<Call>,
+    next_call: usize,
+}
+
+impl Inliner<'_> {
+    fn rebuild_block(&mut self, block: &Block, scope: &Scope, in_loop: bool) -> Block {
+        let mut new = Block::with_capacity(block.len());
+        // Statements may turn into more or fewer, so declare each local
+        // when the statement its scope starts at is reached.
+        let declare_locals = |new: &mut Block, start: usize| {
+            for scoped in block.locals().iter().filter(|scoped| scoped.start == start) {
+                new.declare_local(match scope.locals {
+                    Some(locals) => locals[scoped.local.index()],
+                    None => scoped.local,
+                });
+            }
+        };
+
+        let expr = |handle: Handle<Expression>| scope.expressions[handle.index()];
+        for (index, (statement, &span)) in block.span_iter().enumerate() {
+            declare_locals(&mut new, index);
+            let statement = match *statement {
+                Statement::Emit(ref range) => match range.first_and_last() {
+                    Some((first, last)) => {
+                        Statement::Emit(Range::new_from_bounds(expr(first), expr(last)))
+                    }
+                    None => continue,
+                },
+                Statement::Block(ref body) => {
+                    Statement::Block(self.rebuild_block(body, scope, in_loop))
+                }
+                Statement::If {
+                    condition,
+                    ref accept,
+                    ref reject,
+                } => Statement::If {
+                    condition: expr(condition),
+                    accept: self.rebuild_block(accept, scope, in_loop),
+                    reject: self.rebuild_block(reject, scope, in_loop),
+                },
+                Statement::Switch {
+                    selector,
+                    ref cases,
+                } => Statement::Switch {
+                    selector: expr(selector),
+                    cases: cases
+                        .iter()
+                        .map(|case| crate::SwitchCase {
+                            value: case.value,
+                            body: self.rebuild_block(&case.body, scope, in_loop),
+                            fall_through: case.fall_through,
+                        })
+                        .collect(),
+                },
+                Statement::Loop {
+                    ref body,
+                    ref continuing,
+                    break_if,
+                } => Statement::Loop {
+                    body: self.rebuild_block(body, scope, true),
+                    continuing: self.rebuild_block(continuing, scope, true),
+                    break_if: break_if.map(expr),
+                },
+                Statement::Return { value } => match scope.return_to {
+                    Some(ref return_to) => {
+                        if let (Some(pointer), Some(value)) = (return_to.pointer, value) {
+                            new.push(
+                                Statement::Store {
+                                    pointer,
+                                    value: expr(value),
+                                },
+                                span,
+                            );
+                        }
+                        if !return_to.break_out {
+                            continue;
+                        }
+                        Statement::Break
+                    }
+                    None => Statement::Return {
+                        value: value.map(expr),
+                    },
+                },
+                Statement::Call { function, .. } if self.inline[function.index()] => {
+                    self.inline_call(&mut new, span, in_loop);
+                    continue;
+                }
+                // The rest hold no blocks, so only their handles change.
+                ref other => {
+                    let mut statement = other.clone();
+                    adjust_statement(
+                        &mut statement,
+                        &mut |handle| *handle = expr(*handle),
+                        &mut |_| unreachable!(),
+                    );
+                    statement
+                }
+            };
+            new.push(statement, span);
+        }
+        declare_locals(&mut new, block.len());
+        new
+    }
+
+    /// Push the body of the next call in `self.calls` to `block`.
+    fn inline_call(&mut self, block: &mut Block, span: Span, in_loop: bool) {
+        let index = self.next_call;
+        self.next_call += 1;
+        let module = self.module;
+        let callee = &module.functions[self.calls[index].function];
+
+        let mut body = Block::new();
+        if in_loop {
+            // The callee's locals start out with their initial values on
+            // every call, but the caller's only on entry to the caller.
+            for (handle, local) in callee.local_variables.iter() {
+                let call = &self.calls[index];
+                let new_local = call.locals[handle.index()];
+                let pointer = self
+                    .expressions
+                    .append(Expression::LocalVariable(new_local), span);
+                let value = match local.init {
+                    Some(init) => call.expressions[init.index()],
+                    None => self
+                        .expressions
+                        .append(Expression::ZeroValue(local.ty), span),
+                };
+                body.push(Statement::Store { pointer, value }, span);
+            }
+        }
+
+        let break_out = returns_early(&callee.body);
+        let calls = std::mem::take(&mut self.calls);
+        let call = &calls[index];
+        let scope = Scope {
+            expressions: &call.expressions,
+            locals: Some(&call.locals),
+            return_to: Some(ReturnTo {
+                pointer: call.result_pointer.map(|(pointer, _)| pointer),
+                break_out,
+            }),
+        };
+        let inlined = self.rebuild_block(&callee.body, &scope, false);
+        let result_pointer = call.result_pointer;
+        self.calls = calls;
+        body.extend_block(inlined);
+
+        if break_out {
+            body.push(Statement::Break, span);
+            block.push(
+                Statement::Loop {
+                    body,
+                    continuing: Block::new(),
+                    break_if: None,
+                },
+                span,
+            );
+        } else {
+            block.extend_block(body);
+        }
+        if let Some((_, load)) = result_pointer {
+            block.push(Statement::Emit(Range::new_from_bounds(load, load)), span);
+        }
+    }
+}
+
+/// Return a copy of `block` with each expression handle `h` in it replaced
+/// by `map[h.index()]`.
+pub(super) fn remap_block(
+    module: &crate::Module,
+    expressions: &mut Arena<Expression>,
+    block: &Block,
+    map: &[Handle<Expression>],
+) -> Block {
+    let inline = vec![false; module.functions.len()];
+    let mut inliner = Inliner {
+        module,
+        inline: &inline,
+        expressions,
+        calls: Vec::new(),
+        next_call: 0,
+    };
+    let scope = Scope {
+        expressions: map,
+        locals: None,
+        return_to: None,
+    };
+    inliner.rebuild_block(block, &scope, false)
+}
+
+#[cfg(all(test, feature = "wgsl-in"))]
+mod tests {
+    use super::InlinePolicy;
+
+    const SOURCE: &str = "
+        @group(0) @binding(0) var t: texture_2d<f32>;
+        @group(0) @binding(1) var s: sampler;
+
+        fn sample(t: texture_2d<f32>, s: sampler, uv: vec2<f32>) -> vec4<f32> {
+            return textureSample(t, s, uv);
+        }
+
+        fn clamped(x: f32) -> f32 {
+            var y = x;
+            if y > 1.0 {
+                return 1.0;
+            }
+            y = y * 2.0;
+            return y;
+        }
+
+        @fragment
+        fn main(@location(0) uv: vec2<f32>) -> @location(0) vec4<f32> {
+            var total = vec4(0.0);
+            for (var i = 0; i < 4; i++) {
+                total += sample(t, s, uv) * clamped(uv.x);
+            }
+            return total;
+        }
+        ";
+
+    /// Inline `SOURCE`'s calls with `policy`, and check that the result
+    /// validates.
+    fn inlined(policy: InlinePolicy) -> crate::Module {
+        let mut module = crate::front::wgsl::parse_str(SOURCE).unwrap();
+        super::inline_functions(&mut module, policy);
+        crate::valid::Validator::new(
+            crate::valid::ValidationFlags::all(),
+            crate::valid::Capabilities::default(),
+        )
+        .validate(&module)
+        .unwrap();
+        module
+    }
+
+    /// Inline `SOURCE`'s calls with `policy`, and return the names of the
+    /// functions `main` still calls.
+    fn remaining_calls(policy: InlinePolicy) -> Vec<String> {
+        fn collect(block: &crate::Block, module: &crate::Module, names: &mut Vec<String>) {
+            for statement in block.iter() {
+                match *statement {
+                    crate::Statement::Call { function, .. } => {
+                        names.push(module.functions[function].name.clone().unwrap())
+                    }
+                    crate::Statement::Block(ref body) => collect(body, module, names),
+                    crate::Statement::If {
+                        ref accept,
+                        ref reject,
+                        ..
+                    } => {
+                        collect(accept, module, names);
+                        collect(reject, module, names);
+                    }
+                    crate::Statement::Loop {
+                        ref body,
+                        ref continuing,
+                        ..
+                    } => {
+                        collect(body, module, names);
+                        collect(continuing, module, names);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let module = inlined(policy);
+        let mut names = Vec::new();
+        collect(&module.entry_points[0].function.body, &module, &mut names);
+        names
+    }
+
+    #[test]
+    fn policies() {
+        assert!(remaining_calls(InlinePolicy::Always).is_empty());
+        assert_eq!(remaining_calls(InlinePolicy::RequiredOnly), ["clamped"]);
+        assert_eq!(
+            remaining_calls(InlinePolicy::SizeThreshold(0)),
+            ["sample", "clamped"]
+        );
+    }
+
+    #[test]
+    fn inlined_body() {
+        use crate::{Expression, Statement};
+
+        fn count_loops(block: &crate::Block) -> usize {
+            block
+                .iter()
+                .map(|statement| match *statement {
+                    Statement::Loop {
+                        ref body,
+                        ref continuing,
+                        ..
+                    } => 1 + count_loops(body) + count_loops(continuing),
+                    Statement::Block(ref body) => count_loops(body),
+                    Statement::If {
+                        ref accept,
+                        ref reject,
+                        ..
+                    } => count_loops(accept) + count_loops(reject),
+                    _ => 0,
+                })
+                .sum()
+        }
+
+        let module = inlined(InlinePolicy::Always);
+        let main = &module.entry_points[0].function;
+
+        // `sample`'s arguments are replaced by the values `main` passed.
+        let samples: Vec<_> = main
+            .expressions
+            .iter()
+            .filter_map(|(_, expr)| match *expr {
+                Expression::ImageSample { image, sampler, .. } => Some((image, sampler)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(samples.len(), 1);
+        let (image, sampler) = samples[0];
+        assert!(matches!(
+            main.expressions[image],
+            Expression::GlobalVariable(_)
+        ));
+        assert!(matches!(
+            main.expressions[sampler],
+            Expression::GlobalVariable(_)
+        ));
+        assert!(main
+            .expressions
+            .iter()
+            .all(|(_, expr)| !matches!(*expr, Expression::FunctionArgument(index) if index != 0)));
+
+        // `clamped`'s local moves into `main`.
+        assert!(main
+            .local_variables
+            .iter()
+            .any(|(_, local)| local.name.as_deref() == Some("y")));
+
+        // `clamped` returns from inside an `if`, so its body is wrapped in a
+        // loop to break out of, inside `main`'s own loop.
+        assert_eq!(count_loops(&main.body), 2);
+    }
+}
@@ -0,0 +1,696 @@
+/*!
+Linking several modules into one.
+
+Shader libraries are often split across files, and sometimes across
+languages. Rather than concatenating their source text, front ends can
+translate each file on its own, and [`link`] can combine the results.
+*/
+
+use super::dead_code::{for_each_call, retarget_calls};
+use crate::arena::{Arena, Handle};
+use crate::{
+    Constant, Expression, FastHashMap, Function, GlobalVariable, Module, ShaderStage, Type,
+    TypeInner,
+};
+
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum LinkError {
+    #[error("More than one module defines function {0:?} with the same arguments")]
+    DuplicateFunction(String),
+    #[error("The declarations and definition of function {0:?} return different types")]
+    ResultMismatch(String),
+    #[error("Function {0:?} calls itself through functions declared in other modules")]
+    Recursion(String),
+    #[error("Modules declare global variable {0:?} differently")]
+    GlobalMismatch(String),
+    #[error("Modules give constant {0:?} different types or values")]
+    ConstantMismatch(String),
+    #[error("More than one module has a {0:?} entry point named {1:?}")]
+    DuplicateEntryPoint(ShaderStage, String),
+}
+
+/// Combine `modules` into a single module.
+///
+/// The result holds the types, constants, global variables, functions and
+/// entry points of all the modules, with these merged:
+///
+/// - Types that are the same in each module become one type.
+///
+/// - Named constants and global variables become one item for each name.
+///   Their declarations must otherwise match, or this returns an error.
+///
+/// - A named function with an empty body, like the function a GLSL
+///   prototype produces, is taken to be a declaration. Calls to it resolve
+///   to the function with the same name and argument types that has a
+///   body, in any of the modules, and the declaration itself is dropped.
+///   Only one module may define each such function.
+///
+/// The modules' diagnostic filters apply to the whole result, in the order
+/// of `modules`.
+///
+/// Spans still refer to the source of the module each item came from. The
+/// result should be validated before use.
+///
+/// # Panics
+///
+/// If any of `modules` has not passed validation, this may panic.
+pub fn link(modules: &[Module]) -> Result<Module, LinkError> {
+    let mut linker = Linker {
+        modules,
+        linked: Module::default(),
+        maps: Vec::with_capacity(modules.len()),
+        constants: FastHashMap::default(),
+        globals: FastHashMap::default(),
+        definitions: FastHashMap::default(),
+        functions: FastHashMap::default(),
+        in_progress: Vec::new(),
+    };
+    for module in modules {
+        let map = linker.add_module_items(module)?;
+        linker.maps.push(map);
+        linker
+            .linked
+            .diagnostic_filters
+            .extend_from_slice(&module.diagnostic_filters);
+    }
+    linker.resolve_declarations()?;
+
+    for (index, module) in modules.iter().enumerate() {
+        for (handle, _) in module.functions.iter() {
+            linker.link_function(index, handle)?;
+        }
+    }
+    for (index, module) in modules.iter().enumerate() {
+        for ep in module.entry_points.iter() {
+            let duplicate = linker
+                .linked
+                .entry_points
+                .iter()
+                .any(|other| other.stage == ep.stage && other.name == ep.name);
+            if duplicate {
+                return Err(LinkError::DuplicateEntryPoint(ep.stage, ep.name.clone()));
+            }
+            let function = linker.copy_function(index, &ep.function);
+            linker.linked.entry_points.push(crate::EntryPoint {
+                name: ep.name.clone(),
+                stage: ep.stage,
+                early_depth_test: ep.early_depth_test,
+                workgroup_size: ep.workgroup_size,
+                function,
+            });
+        }
+    }
+    Ok(linker.linked)
+}
+
+/// Where the items of one of the modules being linked ended up.
+///
+/// Each vector is indexed by the items' handles in the module.
+struct ModuleMap {
+    types: Vec<Handle<Type>>,
+    const_expressions: Vec<Handle<Expression>>,
+    /// Constants are added as constant expressions first refer to them, so
+    /// this is filled in out of order.
+    constants: Vec<Option<Handle<Constant>>>,
+    global_variables: Vec<Handle<GlobalVariable>>,
+}
+
+/// The name and linked argument types of a function, which declarations
+/// and definitions must share.
+type Signature<'a> = (&'a str, Vec<Handle<Type>>);
+
+/// A function of one of the modules being linked: the module's index, and
+/// the function's handle in it.
+type FunctionKey = (usize, Handle<Function>);
+
+struct Linker<'a> {
+    modules: &'a [Module],
+    linked: Module,
+    maps: Vec<ModuleMap>,
+    constants: FastHashMap<&'a str, Handle<Constant>>,
+    globals: FastHashMap<&'a str, Handle<GlobalVariable>>,
+    /// The function calls to each signature resolve to, preferring a
+    /// definition to a declaration.
+    definitions: FastHashMap<Signature<'a>, FunctionKey>,
+    /// The functions added to the linked module so far.
+    functions: FastHashMap<FunctionKey, Handle<Function>>,
+    /// The functions being added, whose callees are being added first.
+    in_progress: Vec<FunctionKey>,
+}
+
+impl<'a> Linker<'a> {
+    /// Add the types, constants and global variables of `module`.
+    fn add_module_items(&mut self, module: &'a Module) -> Result<ModuleMap, LinkError> {
+        let mut map = ModuleMap {
+            types: Vec::with_capacity(module.types.len()),
+            const_expressions: Vec::with_capacity(module.const_expressions.len()),
+            constants: vec![None; module.constants.len()],
+            global_variables: Vec::with_capacity(module.global_variables.len()),
+        };
+
+        // Types only refer to types that come before them.
+        for (handle, ty) in module.types.iter() {
+            let ty = Type {
+                name: ty.name.clone(),
+                inner: copy_type_inner(&ty.inner, &map.types),
+            };
+            let linked = self.linked.types.insert(ty, module.types.get_span(handle));
+            map.types.push(linked);
+        }
+
+        let special_types = &mut self.linked.special_types;
+        let adjust_type = |ty: Handle<Type>| map.types[ty.index()];
+        special_types.ray_desc = special_types
+            .ray_desc
+            .or(module.special_types.ray_desc.map(adjust_type));
+        special_types.ray_intersection = special_types
+            .ray_intersection
+            .or(module.special_types.ray_intersection.map(adjust_type));
+        for (predeclared, &ty) in module.special_types.predeclared_types.iter() {
+            special_types
+                .predeclared_types
+                .entry(copy_predeclared_type(predeclared))
+                .or_insert(adjust_type(ty));
+        }
+
+        // A constant's value comes before any constant expression that
+        // refers to the constant.
+        for (handle, expression) in module.const_expressions.iter() {
+            if let Expression::Constant(constant) = *expression {
+                self.add_constant(module, &mut map, constant)?;
+            }
+            let mut expression = expression.clone();
+            adjust_module_handles(&mut expression, &map, &|_| unreachable!());
+            super::scalarize::adjust_expression(&mut expression, &mut |operand| {
+                *operand = map.const_expressions[operand.index()]
+            });
+            let linked = self
+                .linked
+                .const_expressions
+                .append(expression, module.const_expressions.get_span(handle));
+            map.const_expressions.push(linked);
+        }
+        for (handle, _) in module.constants.iter() {
+            self.add_constant(module, &mut map, handle)?;
+        }
+
+        for (handle, global) in module.global_variables.iter() {
+            let mut copy = global.clone();
+            copy.ty = map.types[global.ty.index()];
+            copy.init = global.init.map(|init| map.const_expressions[init.index()]);
+            if let Some(ref name) = global.name {
+                if let Some(&existing) = self.globals.get(name.as_str()) {
+                    let other = &self.linked.global_variables[existing];
+                    let same_init = match (other.init, copy.init) {
+                        (None, None) => true,
+                        (Some(a), Some(b)) => same_value(&self.linked.const_expressions, a, b),
+                        _ => false,
+                    };
+                    if other.space != copy.space
+                        || other.binding != copy.binding
+                        || other.ty != copy.ty
+                        || !same_init
+                    {
+                        return Err(LinkError::GlobalMismatch(name.clone()));
+                    }
+                    map.global_variables.push(existing);
+                    continue;
+                }
+            }
+            let linked = self
+                .linked
+                .global_variables
+                .append(copy, module.global_variables.get_span(handle));
+            if let Some(ref name) = global.name {
+                self.globals.insert(name, linked);
+            }
+            map.global_variables.push(linked);
+        }
+        Ok(map)
+    }
+
+    /// Add the constant `handle` of `module`, unless it has been already.
+    fn add_constant(
+        &mut self,
+        module: &'a Module,
+        map: &mut ModuleMap,
+        handle: Handle<Constant>,
+    ) -> Result<(), LinkError> {
+        if map.constants[handle.index()].is_some() {
+            return Ok(());
+        }
+        let constant = &module.constants[handle];
+        let copy = Constant {
+            name: constant.name.clone(),
+            r#override: match constant.r#override {
+                crate::Override::None => crate::Override::None,
+                crate::Override::ByName => crate::Override::ByName,
+                crate::Override::ByNameOrId(id) => crate::Override::ByNameOrId(id),
+                crate::Override::Derived => crate::Override::Derived,
+            },
+            ty: map.types[constant.ty.index()],
+            init: map.const_expressions[constant.init.index()],
+        };
+        if let Some(ref name) = constant.name {
+            if let Some(&existing) = self.constants.get(name.as_str()) {
+                let other = &self.linked.constants[existing];
+                if other.ty != copy.ty
+                    || other.r#override != copy.r#override
+                    || !same_value(&self.linked.const_expressions, other.init, copy.init)
+                {
+                    return Err(LinkError::ConstantMismatch(name.clone()));
+                }
+                map.constants[handle.index()] = Some(existing);
+                return Ok(());
+            }
+        }
+        let linked = self
+            .linked
+            .constants
+            .append(copy, module.constants.get_span(handle));
+        if let Some(ref name) = constant.name {
+            self.constants.insert(name, linked);
+        }
+        map.constants[handle.index()] = Some(linked);
+        Ok(())
+    }
+
+    /// Return the name and linked argument types of function `handle` of
+    /// module `index`, if it has a name.
+    fn signature(&self, index: usize, handle: Handle<Function>) -> Option<Signature<'a>> {
+        let modules = self.modules;
+        let function = &modules[index].functions[handle];
+        let name = function.name.as_deref()?;
+        let map = &self.maps[index];
+        let arguments = function
+            .arguments
+            .iter()
+            .map(|arg| map.types[arg.ty.index()])
+            .collect();
+        Some((name, arguments))
+    }
+
+    /// Choose the function that calls to each signature resolve to.
+    fn resolve_declarations(&mut self) -> Result<(), LinkError> {
+        let modules = self.modules;
+        for (index, module) in modules.iter().enumerate() {
+            for (handle, function) in module.functions.iter() {
+                let Some(signature) = self.signature(index, handle) else {
+                    continue;
+                };
+                let Some(&(other_index, other_handle)) = self.definitions.get(&signature) else {
+                    self.definitions.insert(signature, (index, handle));
+                    continue;
+                };
+                let other = &modules[other_index].functions[other_handle];
+                let result = |index: usize, function: &Function| {
+                    function.result.as_ref().map(|result| {
+                        let ty = self.maps[index].types[result.ty.index()];
+                        (ty, result.binding.clone())
+                    })
+                };
+                if result(index, function) != result(other_index, other) {
+                    return Err(LinkError::ResultMismatch(signature.0.to_string()));
+                }
+                match (other.body.is_empty(), function.body.is_empty()) {
+                    (true, false) => {
+                        self.definitions.insert(signature, (index, handle));
+                    }
+                    (false, false) => {
+                        return Err(LinkError::DuplicateFunction(signature.0.to_string()));
+                    }
+                    (_, true) => {}
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Return the function calls to function `handle` of module `index`
+    /// resolve to.
+    fn resolve(&self, index: usize, handle: Handle<Function>) -> FunctionKey {
+        self.signature(index, handle)
+            .and_then(|signature| self.definitions.get(&signature).copied())
+            .unwrap_or((index, handle))
+    }
+
+    /// Add the function calls to function `handle` of module `index`
+    /// resolve to, after the functions it calls, and return its handle in
+    /// the linked module.
+    fn link_function(
+        &mut self,
+        index: usize,
+        handle: Handle<Function>,
+    ) -> Result<Handle<Function>, LinkError> {
+        let key = self.resolve(index, handle);
+        if let Some(&linked) = self.functions.get(&key) {
+            return Ok(linked);
+        }
+        let (index, handle) = key;
+        let modules = self.modules;
+        let module = &modules[index];
+        let function = &module.functions[handle];
+        if self.in_progress.contains(&key) {
+            let name = function.name.clone().unwrap_or_default();
+            return Err(LinkError::Recursion(name));
+        }
+
+        self.in_progress.push(key);
+        let mut callees = Vec::new();
+        for_each_call(&function.body, &mut |callee| callees.push(callee));
+        for callee in callees {
+            self.link_function(index, callee)?;
+        }
+        self.in_progress.pop();
+
+        let copy = self.copy_function(index, function);
+        let linked = self
+            .linked
+            .functions
+            .append(copy, module.functions.get_span(handle));
+        self.functions.insert(key, linked);
+        Ok(linked)
+    }
+
+    /// Copy `function` of module `index` for the linked module.
+    ///
+    /// The functions it calls must have been added already.
+    fn copy_function(&self, index: usize, function: &Function) -> Function {
+        let map = &self.maps[index];
+        let adjust_type = |ty: Handle<Type>| map.types[ty.index()];
+        let callee = |callee| self.functions[&self.resolve(index, callee)];
+
+        let mut local_variables = Arena::new();
+        for (handle, local) in function.local_variables.iter() {
+            let mut local = local.clone();
+            local.ty = adjust_type(local.ty);
+            local_variables.append(local, function.local_variables.get_span(handle));
+        }
+        let mut expressions = Arena::new();
+        for (handle, expression) in function.expressions.iter() {
+            let mut expression = expression.clone();
+            adjust_module_handles(&mut expression, map, &callee);
+            expressions.append(expression, function.expressions.get_span(handle));
+        }
+        let mut body = function.body.clone();
+        retarget_calls(&mut body, &callee);
+
+        Function {
+            name: function.name.clone(),
+            arguments: function
+                .arguments
+                .iter()
+                .map(|arg| crate::FunctionArgument {
+                    ty: adjust_type(arg.ty),
+                    ..arg.clone()
+                })
+                .collect(),
+            result: function
+                .result
+                .as_ref()
+                .map(|result| crate::FunctionResult {
+                    ty: adjust_type(result.ty),
+                    binding: result.binding.clone(),
+                }),
+            local_variables,
+            expressions,
+            named_expressions: function.named_expressions.clone(),
+            body,
+        }
+    }
+}
+
+/// Copy `inner`, adjusting the type handles in it with `types`.
+pub(super) fn copy_type_inner(inner: &TypeInner, types: &[Handle<Type>]) -> TypeInner {
+    let adjust = |ty: Handle<Type>| types[ty.index()];
+    match *inner {
+        TypeInner::Scalar { kind, width } => TypeInner::Scalar { kind, width },
+        TypeInner::Vector { size, kind, width } => TypeInner::Vector { size, kind, width },
+        TypeInner::Matrix {
+            columns,
+            rows,
+            width,
+        } => TypeInner::Matrix {
+            columns,
+            rows,
+            width,
+        },
+        TypeInner::Atomic { kind, width } => TypeInner::Atomic { kind, width },
+        TypeInner::Pointer { base, space } => TypeInner::Pointer {
+            base: adjust(base),
+            space,
+        },
+        TypeInner::ValuePointer {
+            size,
+            kind,
+            width,
+            space,
+        } => TypeInner::ValuePointer {
+            size,
+            kind,
+            width,
+            space,
+        },
+        TypeInner::Array { base, size, stride } => TypeInner::Array {
+            base: adjust(base),
+            size,
+            stride,
+        },
+        TypeInner::Struct { ref members, span } => TypeInner::Struct {
+            members: members
+                .iter()
+                .map(|member| crate::StructMember {
+                    ty: adjust(member.ty),
+                    ..member.clone()
+                })
+                .collect(),
+            span,
+        },
+        TypeInner::Image {
+            dim,
+            arrayed,
+            class,
+        } => TypeInner::Image {
+            dim,
+            arrayed,
+            class,
+        },
+        TypeInner::Sampler { comparison } => TypeInner::Sampler { comparison },
+        TypeInner::AccelerationStructure => TypeInner::AccelerationStructure,
+        TypeInner::RayQuery => TypeInner::RayQuery,
+        TypeInner::BindingArray { base, size } => TypeInner::BindingArray {
+            base: adjust(base),
+            size,
+        },
+    }
+}
+
+fn copy_predeclared_type(predeclared: &crate::PredeclaredType) -> crate::PredeclaredType {
+    use crate::PredeclaredType as Pt;
+    match *predeclared {
+        Pt::AtomicCompareExchangeWeakResult { kind, width } => {
+            Pt::AtomicCompareExchangeWeakResult { kind, width }
+        }
+        Pt::ModfResult { size, width } => Pt::ModfResult { size, width },
+        Pt::FrexpResult { size, width } => Pt::FrexpResult { size, width },
+    }
+}
+
+/// Adjust the handles of module-level items in `expression` with `map`,
+/// and the function handles with `callee`.
+///
+/// Handles of other expressions are left as they are.
+fn adjust_module_handles(
+    expression: &mut Expression,
+    map: &ModuleMap,
+    callee: &impl Fn(Handle<Function>) -> Handle<Function>,
+) {
+    match *expression {
+        Expression::Constant(ref mut constant) => {
+            // Every constant was added before any function.
+            *constant = map.constants[constant.index()].unwrap();
+        }
+        Expression::ZeroValue(ref mut ty)
+        | Expression::Compose { ref mut ty, .. }
+        | Expression::Repeat { ref mut ty, .. }
+        | Expression::AtomicResult { ref mut ty, .. }
+        | Expression::WorkGroupUniformLoadResult { ref mut ty } => {
+            *ty = map.types[ty.index()];
+        }
+        Expression::GlobalVariable(ref mut global) => {
+            *global = map.global_variables[global.index()];
+        }
+        Expression::CallResult(ref mut function) => *function = callee(*function),
+        Expression::ImageSample {
+            offset: Some(ref mut offset),
+            ..
+        } => *offset = map.const_expressions[offset.index()],
+        _ => {}
+    }
+}
+
+/// Return true if the constant expressions `a` and `b` in `arena` have the
+/// same value.
+///
+/// Expressions other than constructors are only compared shallowly.
+fn same_value(arena: &Arena<Expression>, a: Handle<Expression>, b: Handle<Expression>) -> bool {
+    if a == b {
+        return true;
+    }
+    match (&arena[a], &arena[b]) {
+        (
+            &Expression::Compose {
+                ty: a_ty,
+                components: ref a_components,
+            },
+            &Expression::Compose {
+                ty: b_ty,
+                components: ref b_components,
+            },
+        ) => {
+            a_ty == b_ty
+                && a_components.len() == b_components.len()
+                && a_components
+                    .iter()
+                    .zip(b_components)
+                    .all(|(&a, &b)| same_value(arena, a, b))
+        }
+        (
+            &Expression::Splat {
+                size: a_size,
+                value: a_value,
+            },
+            &Expression::Splat {
+                size: b_size,
+                value: b_value,
+            },
+        ) => a_size == b_size && same_value(arena, a_value, b_value),
+        (a, b) => a == b,
+    }
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod tests {
+    use super::{link, LinkError};
+    use crate::test_util::Fixture;
+    use crate::valid::Capabilities;
+    use crate::ShaderStage;
+
+    const SHADE: &str = "
+        const scale = 2.0;
+        @group(0) @binding(0) var<uniform> tint: vec4<f32>;
+
+        fn shade(color: vec4<f32>) -> vec4<f32> {
+            return color * scale * tint;
+        }
+
+        @fragment
+        fn fs_main() -> @location(0) vec4<f32> {
+            return shade(vec4(1.0));
+        }
+    ";
+
+    fn parse(source: &str) -> crate::Module {
+        Fixture::wgsl(source).module
+    }
+
+    #[test]
+    fn merge() {
+        let vertex = parse(
+            "
+            const scale = 2.0;
+            @group(0) @binding(0) var<uniform> tint: vec4<f32>;
+
+            @vertex
+            fn vs_main() -> @builtin(position) vec4<f32> {
+                return tint * scale;
+            }
+            ",
+        );
+        let shade = parse(SHADE);
+        let linked =
+            Fixture::module(link(&[shade, vertex]).unwrap(), Capabilities::default()).module;
+        assert_eq!(linked.constants.len(), 1);
+        assert_eq!(linked.global_variables.len(), 1);
+        assert_eq!(linked.functions.len(), 1);
+        let eps: Vec<_> = linked
+            .entry_points
+            .iter()
+            .map(|ep| (ep.stage, ep.name.as_str()))
+            .collect();
+        assert_eq!(
+            eps,
+            [
+                (ShaderStage::Fragment, "fs_main"),
+                (ShaderStage::Vertex, "vs_main")
+            ]
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let link_with_shade = |source: &str| link(&[parse(SHADE), parse(source)]);
+        assert_eq!(
+            link_with_shade(SHADE).err(),
+            Some(LinkError::DuplicateFunction("shade".to_string()))
+        );
+        assert_eq!(
+            link_with_shade("const scale = 3.0;").err(),
+            Some(LinkError::ConstantMismatch("scale".to_string()))
+        );
+        assert_eq!(
+            link_with_shade("@group(0) @binding(1) var<uniform> tint: vec4<f32>;").err(),
+            Some(LinkError::GlobalMismatch("tint".to_string()))
+        );
+        assert_eq!(
+            link_with_shade("fn shade(color: vec4<f32>) -> vec3<f32> { return color.rgb; }").err(),
+            Some(LinkError::ResultMismatch("shade".to_string()))
+        );
+        assert_eq!(
+            link_with_shade(
+                "@fragment fn fs_main() -> @location(0) vec4<f32> { return vec4(0.0); }"
+            )
+            .err(),
+            Some(LinkError::DuplicateEntryPoint(
+                ShaderStage::Fragment,
+                "fs_main".to_string()
+            ))
+        );
+    }
+
+    #[cfg(feature = "glsl-in")]
+    #[test]
+    fn resolve_declaration() {
+        let glsl = crate::front::glsl::Frontend::default()
+            .parse(
+                &crate::front::glsl::Options::from(ShaderStage::Fragment),
+                "
+                #version 450
+                layout(location = 0) out vec4 color;
+
+                float shade(float x);
+
+                void main() {
+                    color = vec4(shade(0.25));
+                }
+                ",
+            )
+            .unwrap();
+        let wgsl = parse(
+            "
+            fn shade(x: f32) -> f32 {
+                return x * 2.0;
+            }
+            ",
+        );
+        let linked = Fixture::module(link(&[glsl, wgsl]).unwrap(), Capabilities::default()).module;
+        let shade: Vec<_> = linked
+            .functions
+            .iter()
+            .filter(|&(_, function)| function.name.as_deref() == Some("shade"))
+            .map(|(_, function)| function)
+            .collect();
+        assert_eq!(shade.len(), 1);
+        assert!(!shade[0].body.is_empty());
+    }
+}
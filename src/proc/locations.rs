@@ -0,0 +1,415 @@
+/*!
+Renumbering `@location` bindings.
+
+Some GL targets only offer a few varying slots, and reject shaders whose
+locations are spread out, even if they use few of them. [`LocationMapping`]
+packs the locations vertex shader inputs and varyings use into `0, 1, 2`...,
+and tells the caller which old location became which new one, so that it
+can lay out its vertex buffers to match.
+*/
+
+use std::collections::BTreeMap;
+
+use super::deduplicate::retarget_types;
+use super::link::copy_type_inner;
+use crate::arena::Handle;
+use crate::{Binding, ShaderStage, Type, TypeInner};
+
+/// New locations for entry point inputs and outputs.
+///
+/// Vertex shader inputs are numbered separately from varyings, since the
+/// former are vertex attributes, and the latter connect vertex shader
+/// outputs with fragment shader inputs. Fragment shader outputs choose color
+/// attachments, so they are left alone.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LocationMapping {
+    /// The new location of each vertex shader input, by old location.
+    pub vertex_inputs: BTreeMap<u32, u32>,
+
+    /// The new location of each vertex shader output and fragment shader
+    /// input, by old location.
+    pub varyings: BTreeMap<u32, u32>,
+}
+
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum LocationError {
+    #[error("Location {0} of a vertex shader input isn't in the mapping")]
+    MissingVertexInput(u32),
+    #[error("Location {0} of a varying isn't in the mapping")]
+    MissingVarying(u32),
+    #[error("Struct {0:?} holds both vertex shader inputs and varyings, which the mapping renumbers differently")]
+    ConflictingStruct(Handle<Type>),
+}
+
+/// The set of locations an entry point input or output belongs to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Role {
+    VertexInput,
+    Varying,
+}
+
+impl LocationMapping {
+    /// Compute a mapping that packs the locations of the vertex shader
+    /// inputs and varyings in `modules` into `0, 1, 2`..., keeping their
+    /// order.
+    ///
+    /// To renumber the stages of a pipeline consistently, pass all the
+    /// modules that provide its entry points, and then [`apply`] the mapping
+    /// to each.
+    ///
+    /// [`apply`]: LocationMapping::apply
+    pub fn compact(modules: &[&crate::Module]) -> Self {
+        let mut mapping = Self::default();
+        for module in modules {
+            for_each_location(module, |role, location| {
+                mapping.map_mut(role).insert(location, 0);
+            });
+        }
+        for map in [&mut mapping.vertex_inputs, &mut mapping.varyings] {
+            for (new, old) in map.values_mut().enumerate() {
+                *old = new as u32;
+            }
+        }
+        mapping
+    }
+
+    /// Renumber the locations of `module`'s vertex shader inputs and
+    /// varyings as `self` says.
+    ///
+    /// If this returns an error, `module` is left unchanged.
+    pub fn apply(&self, module: &mut crate::Module) -> Result<(), LocationError> {
+        let mut error = None;
+        for_each_location(module, |role, location| {
+            if error.is_none() && !self.map(role).contains_key(&location) {
+                error = Some(match role {
+                    Role::VertexInput => LocationError::MissingVertexInput(location),
+                    Role::Varying => LocationError::MissingVarying(location),
+                });
+            }
+        });
+        if let Some(error) = error {
+            return Err(error);
+        }
+
+        // Renumber each struct's members only once, even if several entry
+        // points use it, and make sure they all agree on how.
+        let mut structs: Vec<(Handle<Type>, Role, Type)> = Vec::new();
+        for (role, ty) in bindings(module).filter_map(|(role, binding, ty)| match binding {
+            None => Some((role, ty)),
+            Some(_) => None,
+        }) {
+            let TypeInner::Struct { ref members, span } = module.types[ty].inner else {
+                continue;
+            };
+            let new_type = Type {
+                name: module.types[ty].name.clone(),
+                inner: TypeInner::Struct {
+                    members: members
+                        .iter()
+                        .map(|member| crate::StructMember {
+                            binding: member.binding.as_ref().map(|b| self.rebind(role, b)),
+                            ..member.clone()
+                        })
+                        .collect(),
+                    span,
+                },
+            };
+            match structs.iter().find(|&&(handle, _, _)| handle == ty) {
+                Some(&(_, other, ref existing)) => {
+                    if other != role && *existing != new_type {
+                        return Err(LocationError::ConflictingStruct(ty));
+                    }
+                }
+                None => structs.push((ty, role, new_type)),
+            }
+        }
+
+        if structs
+            .iter()
+            .any(|&(handle, _, ref new_type)| module.types[handle] != *new_type)
+        {
+            // The renumbered struct may be the same as one already in the
+            // arena, so rebuild the arena, and point every use of the old
+            // struct at the new one.
+            let old_types = std::mem::take(&mut module.types);
+            let mut new_handles = Vec::with_capacity(old_types.len());
+            for (handle, ty) in old_types.iter() {
+                let ty = match structs.iter().find(|&&(old, _, _)| old == handle) {
+                    Some(&(_, _, ref new_type)) => new_type,
+                    None => ty,
+                };
+                let ty = Type {
+                    name: ty.name.clone(),
+                    inner: copy_type_inner(&ty.inner, &new_handles),
+                };
+                new_handles.push(module.types.insert(ty, old_types.get_span(handle)));
+            }
+            retarget_types(module, &new_handles);
+        }
+        for ep in module.entry_points.iter_mut() {
+            let (arguments, result) = match ep.stage {
+                ShaderStage::Vertex => (Some(Role::VertexInput), Some(Role::Varying)),
+                ShaderStage::Fragment => (Some(Role::Varying), None),
+                ShaderStage::Compute => (None, None),
+            };
+            if let Some(role) = arguments {
+                for argument in ep.function.arguments.iter_mut() {
+                    if let Some(ref mut binding) = argument.binding {
+                        *binding = self.rebind(role, binding);
+                    }
+                }
+            }
+            if let (Some(role), Some(result)) = (result, ep.function.result.as_mut()) {
+                if let Some(ref mut binding) = result.binding {
+                    *binding = self.rebind(role, binding);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn map(&self, role: Role) -> &BTreeMap<u32, u32> {
+        match role {
+            Role::VertexInput => &self.vertex_inputs,
+            Role::Varying => &self.varyings,
+        }
+    }
+
+    fn map_mut(&mut self, role: Role) -> &mut BTreeMap<u32, u32> {
+        match role {
+            Role::VertexInput => &mut self.vertex_inputs,
+            Role::Varying => &mut self.varyings,
+        }
+    }
+
+    /// Return `binding` with its location renumbered for `role`.
+    ///
+    /// `apply` has checked that the mapping covers it.
+    fn rebind(&self, role: Role, binding: &Binding) -> Binding {
+        match *binding {
+            Binding::Location {
+                location,
+                second_blend_source,
+                interpolation,
+                sampling,
+            } => Binding::Location {
+                location: self.map(role)[&location],
+                second_blend_source,
+                interpolation,
+                sampling,
+            },
+            ref other => other.clone(),
+        }
+    }
+}
+
+/// Renumber the locations of `module`'s vertex shader inputs and varyings
+/// to `0, 1, 2`..., and return the mapping used.
+///
+/// This is [`LocationMapping::compact`] followed by
+/// [`LocationMapping::apply`], for a module that holds all of a pipeline's
+/// entry points.
+pub fn compact_locations(module: &mut crate::Module) -> Result<LocationMapping, LocationError> {
+    let mapping = LocationMapping::compact(&[&*module]);
+    mapping.apply(module)?;
+    Ok(mapping)
+}
+
+/// Return the role, binding and type of each renumbered entry point
+/// argument and result in `module`.
+fn bindings(
+    module: &crate::Module,
+) -> impl Iterator<Item = (Role, Option<&Binding>, Handle<Type>)> + '_ {
+    module.entry_points.iter().flat_map(|ep| {
+        let (arguments, result) = match ep.stage {
+            ShaderStage::Vertex => (Some(Role::VertexInput), Some(Role::Varying)),
+            ShaderStage::Fragment => (Some(Role::Varying), None),
+            ShaderStage::Compute => (None, None),
+        };
+        let arguments = arguments.into_iter().flat_map(|role| {
+            ep.function
+                .arguments
+                .iter()
+                .map(move |argument| (role, argument.binding.as_ref(), argument.ty))
+        });
+        let result = result.and_then(|role| {
+            let result = ep.function.result.as_ref()?;
+            Some((role, result.binding.as_ref(), result.ty))
+        });
+        arguments.chain(result)
+    })
+}
+
+/// Call `f` on each location of a vertex shader input or varying in
+/// `module`.
+fn for_each_location(module: &crate::Module, mut f: impl FnMut(Role, u32)) {
+    for (role, binding, ty) in bindings(module) {
+        match binding {
+            Some(&Binding::Location { location, .. }) => f(role, location),
+            Some(&Binding::BuiltIn(_)) => {}
+            None => {
+                if let TypeInner::Struct { ref members, .. } = module.types[ty].inner {
+                    for member in members {
+                        if let Some(Binding::Location { location, .. }) = member.binding {
+                            f(role, location);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "wgsl-in"))]
+mod tests {
+    use super::{LocationError, LocationMapping};
+
+    const SOURCE: &str = "
+        struct Varyings {
+            @builtin(position) position: vec4<f32>,
+            @location(7) color: vec4<f32>,
+            @location(13) @interpolate(flat) index: u32,
+        }
+
+        @vertex
+        fn vs(@location(3) position: vec4<f32>, @location(9) color: vec4<f32>) -> Varyings {
+            return Varyings(position, color, 0u);
+        }
+
+        @fragment
+        fn fs(@location(0) @interpolate(flat) index: u32, in: Varyings) -> @location(5) vec4<f32> {
+            return in.color;
+        }
+    ";
+
+    fn locations(module: &crate::Module) -> Vec<Vec<u32>> {
+        let locations = |binding: &Option<crate::Binding>| match *binding {
+            Some(crate::Binding::Location { location, .. }) => Some(location),
+            _ => None,
+        };
+        let mut result: Vec<Vec<u32>> = module
+            .entry_points
+            .iter()
+            .map(|ep| {
+                ep.function
+                    .arguments
+                    .iter()
+                    .filter_map(|argument| locations(&argument.binding))
+                    .chain(
+                        ep.function
+                            .result
+                            .iter()
+                            .filter_map(|r| locations(&r.binding)),
+                    )
+                    .collect()
+            })
+            .collect();
+        for (_, ty) in module.types.iter() {
+            if let crate::TypeInner::Struct { ref members, .. } = ty.inner {
+                result.push(
+                    members
+                        .iter()
+                        .filter_map(|m| locations(&m.binding))
+                        .collect(),
+                );
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn compact() {
+        let mut module = crate::front::wgsl::parse_str(SOURCE).unwrap();
+        let mapping = super::compact_locations(&mut module).unwrap();
+        assert_eq!(
+            mapping.vertex_inputs.into_iter().collect::<Vec<_>>(),
+            [(3, 0), (9, 1)]
+        );
+        assert_eq!(
+            mapping.varyings.into_iter().collect::<Vec<_>>(),
+            [(0, 0), (7, 1), (13, 2)]
+        );
+        // The fragment shader's output keeps its location.
+        assert_eq!(locations(&module), [vec![0, 1], vec![0, 5], vec![1, 2]]);
+        crate::valid::Validator::new(
+            crate::valid::ValidationFlags::all(),
+            crate::valid::Capabilities::all(),
+        )
+        .validate(&module)
+        .unwrap();
+    }
+
+    #[test]
+    fn paired_modules() {
+        let vertex = crate::front::wgsl::parse_str(
+            "
+            @vertex
+            fn vs() -> @location(4) vec4<f32> { return vec4(0.0); }
+            ",
+        )
+        .unwrap();
+        let mut fragment = crate::front::wgsl::parse_str(
+            "
+            @fragment
+            fn fs(@location(4) a: vec4<f32>, @location(8) b: vec4<f32>) -> @location(0) vec4<f32> {
+                return a + b;
+            }
+            ",
+        )
+        .unwrap();
+
+        let mapping = LocationMapping::compact(&[&vertex]);
+        assert_eq!(
+            mapping.apply(&mut fragment),
+            Err(LocationError::MissingVarying(8))
+        );
+
+        let mapping = LocationMapping::compact(&[&vertex, &fragment]);
+        mapping.apply(&mut fragment).unwrap();
+        assert_eq!(locations(&fragment), [vec![0, 1, 0]]);
+    }
+
+    #[test]
+    fn renumbered_struct_exists() {
+        let mut module = crate::front::wgsl::parse_str(
+            "
+            struct In { @location(5) color: vec4<f32> }
+            struct Out { @location(0) color: vec4<f32> }
+
+            @vertex
+            fn vs(in: In) -> @builtin(position) vec4<f32> { return in.color; }
+
+            @fragment
+            fn fs() -> Out { return Out(vec4(1.0)); }
+            ",
+        )
+        .unwrap();
+        // Other front ends may leave structs unnamed, so that `In` becomes
+        // the same as `Out` once its location is renumbered.
+        let handles: Vec<_> = module.types.iter().map(|(handle, _)| handle).collect();
+        for &handle in handles.iter() {
+            if !matches!(module.types[handle].inner, crate::TypeInner::Struct { .. }) {
+                continue;
+            }
+            let ty = crate::Type {
+                name: None,
+                inner: crate::proc::link::copy_type_inner(&module.types[handle].inner, &handles),
+            };
+            module.types.replace(handle, ty);
+        }
+
+        LocationMapping::compact(&[&module])
+            .apply(&mut module)
+            .unwrap();
+        crate::valid::Validator::new(
+            crate::valid::ValidationFlags::all(),
+            crate::valid::Capabilities::all(),
+        )
+        .validate(&module)
+        .unwrap();
+        let input = module.entry_points[0].function.arguments[0].ty;
+        let output = module.entry_points[1].function.result.as_ref().unwrap().ty;
+        assert_eq!(input, output);
+        assert_eq!(locations(&module), [vec![], vec![], vec![0]]);
+    }
+}
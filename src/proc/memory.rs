@@ -0,0 +1,143 @@
+/*!
+Reporting and trimming the memory a [`Module`] holds.
+
+[`Module`]: crate::Module
+*/
+
+use crate::arena::ArenaUsage;
+
+/// How much memory each of a module's arenas holds.
+///
+/// See [`Module::memory_usage`](crate::Module::memory_usage).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ModuleUsage {
+    pub types: ArenaUsage,
+    pub constants: ArenaUsage,
+    pub global_variables: ArenaUsage,
+    pub const_expressions: ArenaUsage,
+    pub functions: ArenaUsage,
+    pub entry_points: ArenaUsage,
+    /// The expression arenas of all functions and entry points together.
+    pub expressions: ArenaUsage,
+    /// The local variable arenas of all functions and entry points
+    /// together.
+    pub local_variables: ArenaUsage,
+}
+
+impl ModuleUsage {
+    /// The number of bytes all the arenas hold.
+    pub const fn total_bytes(&self) -> usize {
+        self.types.bytes
+            + self.constants.bytes
+            + self.global_variables.bytes
+            + self.const_expressions.bytes
+            + self.functions.bytes
+            + self.entry_points.bytes
+            + self.expressions.bytes
+            + self.local_variables.bytes
+    }
+}
+
+impl crate::Module {
+    /// Return how much memory the module's arenas hold.
+    ///
+    /// Like [`ArenaUsage`], this counts the arenas themselves, not the
+    /// allocations their items own.
+    pub fn memory_usage(&self) -> ModuleUsage {
+        let mut usage = ModuleUsage {
+            types: self.types.memory_usage(),
+            constants: self.constants.memory_usage(),
+            global_variables: self.global_variables.memory_usage(),
+            const_expressions: self.const_expressions.memory_usage(),
+            functions: self.functions.memory_usage(),
+            entry_points: ArenaUsage::of_vec(&self.entry_points),
+            ..ModuleUsage::default()
+        };
+        let functions = self.functions.iter().map(|(_, function)| function);
+        let entry_points = self.entry_points.iter().map(|ep| &ep.function);
+        for function in functions.chain(entry_points) {
+            usage.expressions += function.expressions.memory_usage();
+            usage.local_variables += function.local_variables.memory_usage();
+        }
+        usage
+    }
+
+    /// Free the memory the module's arenas hold beyond what their items
+    /// need.
+    ///
+    /// [`compact`](crate::compact::compact) does this once it has removed
+    /// the items it doesn't need.
+    pub fn shrink_to_fit(&mut self) {
+        self.types.shrink_to_fit();
+        self.constants.shrink_to_fit();
+        self.global_variables.shrink_to_fit();
+        self.const_expressions.shrink_to_fit();
+        self.functions.shrink_to_fit();
+        self.entry_points.shrink_to_fit();
+        let functions = self.functions.iter_mut().map(|(_, function)| function);
+        let entry_points = self.entry_points.iter_mut().map(|ep| &mut ep.function);
+        for function in functions.chain(entry_points) {
+            function.expressions.shrink_to_fit();
+            function.local_variables.shrink_to_fit();
+            function.named_expressions.shrink_to_fit();
+        }
+    }
+}
+
+#[cfg(all(test, feature = "wgsl-in"))]
+mod tests {
+    #[test]
+    fn shrink_to_fit() {
+        let mut module = crate::front::wgsl::parse_str(
+            "
+            var<private> counter: u32;
+
+            fn bump(amount: u32) -> u32 {
+                counter += amount;
+                return counter;
+            }
+
+            @compute @workgroup_size(1)
+            fn main() {
+                bump(bump(1u));
+            }
+            ",
+        )
+        .unwrap();
+        // Leave some room in an arena the shader doesn't use.
+        assert!(module.const_expressions.is_empty());
+        for _ in 0..64 {
+            module.const_expressions.append(
+                crate::Expression::Literal(crate::Literal::U32(0)),
+                Default::default(),
+            );
+        }
+        module.const_expressions.clear();
+
+        let before = module.memory_usage();
+        assert_eq!(before.const_expressions.len, 0);
+        assert!(before.const_expressions.capacity >= 64);
+        assert_eq!(before.global_variables.len, 1);
+        assert_eq!(before.entry_points.len, 1);
+        assert!(before.expressions.len > 0);
+
+        module.shrink_to_fit();
+        let after = module.memory_usage();
+        assert_eq!(after.const_expressions, crate::ArenaUsage::default());
+        assert_eq!(after.expressions.len, before.expressions.len);
+        assert_eq!(after.expressions.capacity, after.expressions.len);
+        assert!(after.total_bytes() < before.total_bytes());
+
+        let info = crate::valid::Validator::new(
+            crate::valid::ValidationFlags::all(),
+            crate::valid::Capabilities::all(),
+        )
+        .validate(&module)
+        .unwrap();
+        let usage = info.memory_usage();
+        assert_eq!(usage.functions.len, 1);
+        assert_eq!(usage.entry_points.len, 1);
+        assert_eq!(usage.expressions.len, after.expressions.len);
+        assert_eq!(usage.global_uses.len, 2);
+    }
+}
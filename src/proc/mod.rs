@@ -2,23 +2,56 @@
 [`Module`](super::Module) processing functionality.
 */
 
+mod atomics;
+mod bounds;
 mod constant_evaluator;
+#[cfg(all(test, feature = "test-util"))]
+mod cpu;
+mod dead_code;
+mod deduplicate;
 mod emitter;
+mod half;
 pub mod index;
+mod inline;
 mod layouter;
+mod link;
+mod locations;
+mod memory;
 mod namer;
+mod obfuscate;
+mod saturate;
+pub(crate) mod scalarize;
 mod terminator;
 mod typifier;
+mod unroll;
+pub(crate) mod walk;
+mod workgroup_init;
+mod workgroup_size;
 
+pub use atomics::{lower_unshared_atomics, AtomicLoweringError};
+pub use bounds::BoundsCheckLowering;
 pub use constant_evaluator::{
     ConstantEvaluator, ConstantEvaluatorError, ExpressionConstnessTracker,
 };
+pub use dead_code::eliminate_dead_code;
+pub use deduplicate::deduplicate_types;
 pub use emitter::Emitter;
+pub use half::{f16_to_f32, f32_to_f16};
 pub use index::{BoundsCheckPolicies, BoundsCheckPolicy, IndexableLength, IndexableLengthError};
+pub use inline::{inline_functions, InlinePolicy};
 pub use layouter::{Alignment, LayoutError, LayoutErrorInner, Layouter, TypeLayout};
+pub use link::{link, LinkError};
+pub use locations::{compact_locations, LocationError, LocationMapping};
+pub use memory::ModuleUsage;
 pub use namer::{EntryPointIndex, NameKey, Namer};
+pub use obfuscate::obfuscate;
+pub use saturate::saturate_float_to_int;
+pub use scalarize::{scalarize_math, vectorize_math};
 pub use terminator::ensure_block_returns;
 pub use typifier::{ResolveContext, ResolveError, TypeResolution};
+pub use unroll::unroll_loops;
+pub use workgroup_init::zero_initialize_workgroup_memory;
+pub use workgroup_size::{specialize_workgroup_sizes, WorkgroupSizeError};
 
 impl From<super::StorageFormat> for super::ScalarKind {
     fn from(format: super::StorageFormat) -> Self {
@@ -82,8 +115,11 @@ impl PartialEq for crate::Literal {
         match (*self, *other) {
             (Self::F64(a), Self::F64(b)) => a.to_bits() == b.to_bits(),
             (Self::F32(a), Self::F32(b)) => a.to_bits() == b.to_bits(),
+            (Self::F16(a), Self::F16(b)) => a == b,
             (Self::U32(a), Self::U32(b)) => a == b,
             (Self::I32(a), Self::I32(b)) => a == b,
+            (Self::U64(a), Self::U64(b)) => a == b,
+            (Self::I64(a), Self::I64(b)) => a == b,
             (Self::Bool(a), Self::Bool(b)) => a == b,
             _ => false,
         }
@@ -113,6 +149,18 @@ impl std::hash::Hash for crate::Literal {
                 hasher.write_u8(4);
                 v.hash(hasher);
             }
+            Self::F16(v) => {
+                hasher.write_u8(5);
+                v.hash(hasher);
+            }
+            Self::U64(v) => {
+                hasher.write_u8(6);
+                v.hash(hasher);
+            }
+            Self::I64(v) => {
+                hasher.write_u8(7);
+                v.hash(hasher);
+            }
         }
     }
 }
@@ -122,8 +170,11 @@ impl crate::Literal {
         match (value, kind, width) {
             (value, crate::ScalarKind::Float, 8) => Some(Self::F64(value as _)),
             (value, crate::ScalarKind::Float, 4) => Some(Self::F32(value as _)),
+            (value, crate::ScalarKind::Float, 2) => Some(Self::F16(half::u8_to_f16(value))),
             (value, crate::ScalarKind::Uint, 4) => Some(Self::U32(value as _)),
             (value, crate::ScalarKind::Sint, 4) => Some(Self::I32(value as _)),
+            (value, crate::ScalarKind::Uint, 8) => Some(Self::U64(value as _)),
+            (value, crate::ScalarKind::Sint, 8) => Some(Self::I64(value as _)),
             (1, crate::ScalarKind::Bool, 4) => Some(Self::Bool(true)),
             (0, crate::ScalarKind::Bool, 4) => Some(Self::Bool(false)),
             _ => None,
@@ -138,18 +189,35 @@ impl crate::Literal {
         Self::new(1, kind, width)
     }
 
+    /// Return true if this literal is the zero value of its type.
+    ///
+    /// Negative zero is not: its bits differ from the zero value's.
+    pub const fn is_zero(&self) -> bool {
+        match *self {
+            Self::F64(value) => value.to_bits() == 0,
+            Self::F32(value) => value.to_bits() == 0,
+            Self::F16(bits) => bits == 0,
+            Self::U32(value) => value == 0,
+            Self::I32(value) => value == 0,
+            Self::U64(value) => value == 0,
+            Self::I64(value) => value == 0,
+            Self::Bool(value) => !value,
+        }
+    }
+
     pub const fn width(&self) -> crate::Bytes {
         match *self {
-            Self::F64(_) => 8,
+            Self::F64(_) | Self::U64(_) | Self::I64(_) => 8,
             Self::F32(_) | Self::U32(_) | Self::I32(_) => 4,
+            Self::F16(_) => 2,
             Self::Bool(_) => 1,
         }
     }
     pub const fn scalar_kind(&self) -> crate::ScalarKind {
         match *self {
-            Self::F64(_) | Self::F32(_) => crate::ScalarKind::Float,
-            Self::U32(_) => crate::ScalarKind::Uint,
-            Self::I32(_) => crate::ScalarKind::Sint,
+            Self::F64(_) | Self::F32(_) | Self::F16(_) => crate::ScalarKind::Float,
+            Self::U32(_) | Self::U64(_) => crate::ScalarKind::Uint,
+            Self::I32(_) | Self::I64(_) => crate::ScalarKind::Sint,
             Self::Bool(_) => crate::ScalarKind::Bool,
         }
     }
@@ -352,6 +420,46 @@ impl super::AddressSpace {
     }
 }
 
+impl super::SubgroupOperation {
+    /// Return the invocation ID or offset this operation takes, if any.
+    pub const fn index(&self) -> Option<crate::Handle<crate::Expression>> {
+        match *self {
+            Self::Broadcast(index)
+            | Self::Shuffle(index)
+            | Self::ShuffleDown(index)
+            | Self::ShuffleUp(index)
+            | Self::ShuffleXor(index) => Some(index),
+            Self::Ballot
+            | Self::All
+            | Self::Any
+            | Self::Reduce(_)
+            | Self::InclusiveScan(_)
+            | Self::ExclusiveScan(_)
+            | Self::BroadcastFirst => None,
+        }
+    }
+
+    /// Like [`index`], but return a mutable reference to the handle.
+    ///
+    /// [`index`]: crate::SubgroupOperation::index
+    pub(crate) fn index_mut(&mut self) -> Option<&mut crate::Handle<crate::Expression>> {
+        match *self {
+            Self::Broadcast(ref mut index)
+            | Self::Shuffle(ref mut index)
+            | Self::ShuffleDown(ref mut index)
+            | Self::ShuffleUp(ref mut index)
+            | Self::ShuffleXor(ref mut index) => Some(index),
+            Self::Ballot
+            | Self::All
+            | Self::Any
+            | Self::Reduce(_)
+            | Self::InclusiveScan(_)
+            | Self::ExclusiveScan(_)
+            | Self::BroadcastFirst => None,
+        }
+    }
+}
+
 impl super::MathFunction {
     pub const fn argument_count(&self) -> usize {
         match *self {
@@ -436,6 +544,52 @@ impl super::MathFunction {
             Self::Unpack2x16float => 1,
         }
     }
+
+    /// Return true if applying this function to vectors is the same as applying it to
+    /// each component of those vectors, and collecting the results.
+    pub const fn is_componentwise(&self) -> bool {
+        match *self {
+            Self::Abs
+            | Self::Min
+            | Self::Max
+            | Self::Clamp
+            | Self::Saturate
+            | Self::Cos
+            | Self::Cosh
+            | Self::Sin
+            | Self::Sinh
+            | Self::Tan
+            | Self::Tanh
+            | Self::Acos
+            | Self::Asin
+            | Self::Atan
+            | Self::Atan2
+            | Self::Asinh
+            | Self::Acosh
+            | Self::Atanh
+            | Self::Radians
+            | Self::Degrees
+            | Self::Ceil
+            | Self::Floor
+            | Self::Round
+            | Self::Fract
+            | Self::Trunc
+            | Self::Ldexp
+            | Self::Exp
+            | Self::Exp2
+            | Self::Log
+            | Self::Log2
+            | Self::Pow
+            | Self::Sign
+            | Self::Fma
+            | Self::Mix
+            | Self::Step
+            | Self::SmoothStep
+            | Self::Sqrt
+            | Self::InverseSqrt => true,
+            _ => false,
+        }
+    }
 }
 
 impl crate::Expression {
@@ -614,30 +768,89 @@ impl GlobalCtx<'_> {
         self.eval_expr_to_literal_from(handle, self.const_expressions)
     }
 
+    /// Try to evaluate the expression in `arena` using its `handle`.
+    ///
+    /// Besides literals and constants, this folds the unary, binary and
+    /// conversion expressions front ends don't always evaluate themselves,
+    /// so that indices like `2 + 3` can be checked statically. Expressions
+    /// that would overflow or divide by zero are left for run time, as are
+    /// overrides.
+    ///
+    /// The [`ConstantEvaluator`] does the folding, on copies of the
+    /// expressions `handle` depends on in a scratch arena.
     fn eval_expr_to_literal_from(
         &self,
         handle: crate::Handle<crate::Expression>,
         arena: &crate::Arena<crate::Expression>,
     ) -> Option<crate::Literal> {
-        fn get(
-            gctx: GlobalCtx,
-            handle: crate::Handle<crate::Expression>,
-            arena: &crate::Arena<crate::Expression>,
-        ) -> Option<crate::Literal> {
-            match arena[handle] {
-                crate::Expression::Literal(literal) => Some(literal),
-                crate::Expression::ZeroValue(ty) => match gctx.types[ty].inner {
-                    crate::TypeInner::Scalar { kind, width } => crate::Literal::zero(kind, width),
-                    _ => None,
-                },
+        use crate::Expression as E;
+
+        // The literal a scalar value stands for, seeing through constants,
+        // whose initializers have been evaluated already.
+        let value = |expression: &E| match *expression {
+            E::Literal(literal) => Some(literal),
+            E::ZeroValue(ty) => match self.types[ty].inner {
+                crate::TypeInner::Scalar { kind, width } => crate::Literal::zero(kind, width),
                 _ => None,
+            },
+            _ => None,
+        };
+        let literal = |expression: &E| match *expression {
+            E::Constant(c) => {
+                let constant = &self.constants[c];
+                if constant.r#override != crate::Override::None {
+                    return None;
+                }
+                value(&self.const_expressions[constant.init])
             }
-        }
-        match arena[handle] {
-            crate::Expression::Constant(c) => {
-                get(*self, self.constants[c].init, self.const_expressions)
+            ref other => value(other),
+        };
+
+        // Find what `handle` depends on, giving up on anything that can't
+        // be folded. Operands always come before the expressions that use
+        // them, so sorting the handles puts them in an order we can
+        // evaluate them in.
+        let mut needed = crate::FastHashSet::default();
+        let mut pending = vec![handle];
+        while let Some(handle) = pending.pop() {
+            if !needed.insert(handle) {
+                continue;
+            }
+            match arena[handle] {
+                E::Literal(_) | E::ZeroValue(_) | E::Constant(_) => {}
+                E::Unary { expr, .. } | E::As { expr, .. } => pending.push(expr),
+                E::Binary { left, right, .. } => pending.extend([left, right]),
+                _ => return None,
             }
-            _ => get(*self, handle, arena),
+        }
+
+        let mut types = crate::UniqueArena::new();
+        let constants = crate::Arena::new();
+        let special_types = crate::SpecialTypes::default();
+        let mut scratch = crate::Arena::new();
+        let mut evaluator =
+            ConstantEvaluator::for_scratch(&mut types, &constants, &special_types, &mut scratch);
+        let mut needed: Vec<_> = needed.into_iter().collect();
+        needed.sort();
+        let mut copies = crate::FastHashMap::default();
+        for original in needed {
+            let expression = match arena[original] {
+                E::Binary { .. } | E::Unary { .. } | E::As { .. } => {
+                    let mut expression = arena[original].clone();
+                    scalarize::adjust_expression(&mut expression, &mut |h| *h = copies[&*h]);
+                    expression
+                }
+                ref other => E::Literal(literal(other)?),
+            };
+            let copy = evaluator
+                .try_eval_and_append(&expression, crate::Span::UNDEFINED)
+                .ok()?;
+            copies.insert(original, copy);
+        }
+        let result = copies[&handle];
+        match scratch[result] {
+            E::Literal(literal) => Some(literal),
+            _ => None,
         }
     }
 }
@@ -697,6 +910,44 @@ pub fn flatten_compose<'arenas>(
         .cloned()
 }
 
+/// Return the elements of the array built by a `Repeat` expression.
+///
+/// Given `ty` and `value` from an `Expression::Repeat`, return `value` as
+/// many times as the array has elements, for backends that have no shorter
+/// way to write it.
+pub fn repeat_components(
+    ty: crate::Handle<crate::Type>,
+    value: crate::Handle<crate::Expression>,
+    types: &crate::UniqueArena<crate::Type>,
+) -> impl Iterator<Item = crate::Handle<crate::Expression>> {
+    let count = types[ty].inner.components().unwrap_or(0);
+    std::iter::repeat(value).take(count as usize)
+}
+
+/// Return true if `expr` is obviously the zero value of its type.
+///
+/// This recognizes [`ZeroValue`] expressions, zero literals, and splats of
+/// either, so that backends can write a [`Repeat`] of them as the zero
+/// value of the whole array instead of element by element. Other
+/// expressions may still evaluate to zero.
+///
+/// [`ZeroValue`]: crate::Expression::ZeroValue
+/// [`Repeat`]: crate::Expression::Repeat
+pub fn is_zero_value(
+    expr: crate::Handle<crate::Expression>,
+    expressions: &crate::Arena<crate::Expression>,
+) -> bool {
+    let is_zero_scalar = |expr| match expressions[expr] {
+        crate::Expression::ZeroValue(_) => true,
+        crate::Expression::Literal(literal) => literal.is_zero(),
+        _ => false,
+    };
+    match expressions[expr] {
+        crate::Expression::Splat { value, .. } => is_zero_scalar(value),
+        _ => is_zero_scalar(expr),
+    }
+}
+
 #[test]
 fn test_matrix_size() {
     let module = crate::Module::default();
@@ -710,3 +961,94 @@ fn test_matrix_size() {
         48,
     );
 }
+
+#[test]
+fn test_eval_expr_to_u32_folds_arithmetic() {
+    use crate::{BinaryOperator as Bo, Expression as E, Literal as L, Span};
+
+    let module = crate::Module::default();
+    let mut expressions = crate::Arena::new();
+    let two = expressions.append(E::Literal(L::I32(2)), Span::UNDEFINED);
+    let three = expressions.append(E::Literal(L::I32(3)), Span::UNDEFINED);
+    let sum = expressions.append(
+        E::Binary {
+            op: Bo::Add,
+            left: two,
+            right: three,
+        },
+        Span::UNDEFINED,
+    );
+    let as_uint = expressions.append(
+        E::As {
+            expr: sum,
+            kind: crate::ScalarKind::Uint,
+            convert: Some(4),
+        },
+        Span::UNDEFINED,
+    );
+    let negated = expressions.append(
+        E::Unary {
+            op: crate::UnaryOperator::Negate,
+            expr: sum,
+        },
+        Span::UNDEFINED,
+    );
+    let zero = expressions.append(E::Literal(L::I32(0)), Span::UNDEFINED);
+    let quotient = expressions.append(
+        E::Binary {
+            op: Bo::Divide,
+            left: two,
+            right: zero,
+        },
+        Span::UNDEFINED,
+    );
+
+    let ctx = module.to_ctx();
+    assert!(matches!(
+        ctx.eval_expr_to_u32_from(sum, &expressions),
+        Ok(5)
+    ));
+    assert!(matches!(
+        ctx.eval_expr_to_u32_from(as_uint, &expressions),
+        Ok(5)
+    ));
+    assert!(matches!(
+        ctx.eval_expr_to_u32_from(negated, &expressions),
+        Err(U32EvalError::Negative)
+    ));
+    assert!(matches!(
+        ctx.eval_expr_to_u32_from(quotient, &expressions),
+        Err(U32EvalError::NonConst)
+    ));
+
+    // Overflow is left for run time too.
+    let min = expressions.append(E::Literal(L::I32(i32::MIN)), Span::UNDEFINED);
+    let overflowed = expressions.append(
+        E::Unary {
+            op: crate::UnaryOperator::Negate,
+            expr: min,
+        },
+        Span::UNDEFINED,
+    );
+    assert!(matches!(
+        ctx.eval_expr_to_u32_from(overflowed, &expressions),
+        Err(U32EvalError::NonConst)
+    ));
+
+    // Folding long chains doesn't recurse.
+    let mut chain = two;
+    for _ in 0..100_000 {
+        chain = expressions.append(
+            E::Binary {
+                op: Bo::Add,
+                left: chain,
+                right: zero,
+            },
+            Span::UNDEFINED,
+        );
+    }
+    assert!(matches!(
+        ctx.eval_expr_to_u32_from(chain, &expressions),
+        Ok(2)
+    ));
+}
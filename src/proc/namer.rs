@@ -28,6 +28,10 @@ pub struct Namer {
     keywords: FastHashSet<&'static str>,
     keywords_case_insensitive: FastHashSet<AsciiUniCase<&'static str>>,
     reserved_prefixes: Vec<&'static str>,
+    /// Whether [`Namer::call`] ignores its label and hands out short names.
+    short_names: bool,
+    /// The index of the next short name to try.
+    next_short: usize,
 }
 
 impl Namer {
@@ -91,7 +95,18 @@ impl Namer {
     ///
     /// Guarantee uniqueness by applying a numeric suffix when necessary. If `label_raw`
     /// itself ends with digits, separate them from the suffix with an underscore.
+    ///
+    /// If [short names](Namer::set_short_names) are enabled, `label_raw` is
+    /// ignored, and the result is the next unused name in the sequence `a`,
+    /// `b`, ..., `z`, `aa`, `ab`, ....
     pub fn call(&mut self, label_raw: &str) -> String {
+        if self.short_names {
+            return self.short();
+        }
+        self.labelled(label_raw)
+    }
+
+    fn labelled(&mut self, label_raw: &str) -> String {
         use std::fmt::Write as _; // for write!-ing to Strings
 
         let base = self.sanitize(label_raw);
@@ -129,6 +144,33 @@ impl Namer {
         }
     }
 
+    fn short(&mut self) -> String {
+        loop {
+            let name = short_name(self.next_short);
+            self.next_short += 1;
+            if self.keywords.contains(name.as_str())
+                || self
+                    .keywords_case_insensitive
+                    .contains(&AsciiUniCase(name.as_str()))
+                || self.unique.contains_key(&name)
+            {
+                continue;
+            }
+            self.unique.insert(name.clone(), 0);
+            return name;
+        }
+    }
+
+    /// Make [`Namer::call`] hand out short names instead of names based on
+    /// its label, for output whose size matters more than its readability.
+    ///
+    /// Call this before [`Namer::reset`]. Entry points keep their names, since
+    /// the pipeline refers to them by name, and `reset` claims those before
+    /// handing out any short names.
+    pub fn set_short_names(&mut self, short_names: bool) {
+        self.short_names = short_names;
+    }
+
     pub fn call_or(&mut self, label: &Option<String>, fallback: &str) -> String {
         self.call(match *label {
             Some(ref name) => name,
@@ -175,8 +217,18 @@ impl Namer {
                 .map(|string| (AsciiUniCase(*string))),
         );
 
+        self.next_short = 0;
+
         let mut temp = String::new();
 
+        // Claim the entry point names first, so that no short name takes them.
+        if self.short_names {
+            for (ep_index, ep) in module.entry_points.iter().enumerate() {
+                let ep_name = self.labelled(&ep.name);
+                output.insert(NameKey::EntryPoint(ep_index as _), ep_name);
+            }
+        }
+
         for (ty_handle, ty) in module.types.iter() {
             let ty_name = self.call_or(&ty.name, "type");
             output.insert(NameKey::Type(ty_handle), ty_name);
@@ -193,8 +245,10 @@ impl Namer {
         }
 
         for (ep_index, ep) in module.entry_points.iter().enumerate() {
-            let ep_name = self.call(&ep.name);
-            output.insert(NameKey::EntryPoint(ep_index as _), ep_name);
+            if !self.short_names {
+                let ep_name = self.call(&ep.name);
+                output.insert(NameKey::EntryPoint(ep_index as _), ep_name);
+            }
             for (index, arg) in ep.function.arguments.iter().enumerate() {
                 let name = self.call_or(&arg.name, "param");
                 output.insert(
@@ -243,6 +297,21 @@ impl Namer {
     }
 }
 
+/// Return the `index`'th name in the sequence `a`, `b`, ..., `z`, `aa`, `ab`, ....
+pub(super) fn short_name(mut index: usize) -> String {
+    let mut name = Vec::new();
+    loop {
+        name.push(b'a' + (index % 26) as u8);
+        index /= 26;
+        if index == 0 {
+            break;
+        }
+        index -= 1;
+    }
+    name.reverse();
+    String::from_utf8(name).unwrap()
+}
+
 /// A string wrapper type with an ascii case insensitive Eq and Hash impl
 struct AsciiUniCase<S: AsRef<str> + ?Sized>(S);
 
@@ -279,3 +348,16 @@ fn test() {
     assert_eq!(namer.call("__x"), "_x");
     assert_eq!(namer.call("1___x"), "_x_1");
 }
+
+#[test]
+fn short_names() {
+    let mut namer = Namer::default();
+    namer.set_short_names(true);
+    namer.keywords.insert("b");
+    assert_eq!(namer.call("x"), "a");
+    assert_eq!(namer.call("x"), "c");
+    namer.unique.insert("d".to_string(), 0);
+    assert_eq!(namer.call("y"), "e");
+    namer.next_short = 26;
+    assert_eq!(namer.call("z"), "aa");
+}
@@ -0,0 +1,228 @@
+/*!
+Scrubbing identifiers and source locations from a [`Module`].
+
+When shader text has to be shipped to clients, the names and spans
+carried over from the original source can give away more than the
+shader's author would like. [`obfuscate`] replaces them with short
+meaningless names, without changing what the module does.
+
+[`Module`]: crate::Module
+*/
+
+use crate::{Block, Span, Statement, UniqueArena};
+
+/// Rename everything in `module` except its entry points, and forget all
+/// source spans.
+///
+/// Each named type, struct member, constant, global variable, function,
+/// argument and local variable is given a short name like `a`, `b`, ...,
+/// `aa`, assigned in arena order, so the same module always obfuscates the
+/// same way. Names that were absent stay absent. Struct members are
+/// numbered per struct; everything else shares one sequence, so no two
+/// items end up with the same name. [`Function::named_expressions`] are
+/// dropped altogether.
+///
+/// [Entry point](crate::EntryPoint) names are kept, since the pipeline
+/// refers to them, and so are the names of types in [`SpecialTypes`],
+/// which backends and frontends generate themselves.
+///
+/// Handles are left untouched, so a [`ModuleInfo`] for `module` is still
+/// valid afterwards.
+///
+/// [`Function::named_expressions`]: crate::Function::named_expressions
+/// [`SpecialTypes`]: crate::SpecialTypes
+/// [`ModuleInfo`]: crate::valid::ModuleInfo
+pub fn obfuscate(module: &mut crate::Module) {
+    let mut names = ShortNames::default();
+
+    let special_types = &module.special_types;
+    let is_special = |handle| {
+        special_types.ray_desc == Some(handle)
+            || special_types.ray_intersection == Some(handle)
+            || special_types
+                .predeclared_types
+                .values()
+                .any(|&predeclared| predeclared == handle)
+    };
+
+    // Types are hashed by their names, so the arena has to be rebuilt. Each
+    // renamed type gets a fresh name, so the types stay distinct, and keep
+    // their handles.
+    let old_types = std::mem::replace(&mut module.types, UniqueArena::new());
+    for (handle, ty) in old_types.iter() {
+        let mut ty = ty.clone();
+        if !is_special(handle) {
+            names.rename(&mut ty.name);
+            if let crate::TypeInner::Struct {
+                ref mut members, ..
+            } = ty.inner
+            {
+                let mut member_names = ShortNames::default();
+                for member in members.iter_mut() {
+                    member_names.rename(&mut member.name);
+                }
+            }
+        }
+        let new_handle = module.types.insert(ty, Span::default());
+        debug_assert_eq!(new_handle, handle);
+    }
+
+    for (_, constant) in module.constants.iter_mut() {
+        names.rename(&mut constant.name);
+    }
+    module.constants.clear_spans();
+
+    for (_, global) in module.global_variables.iter_mut() {
+        names.rename(&mut global.name);
+    }
+    module.global_variables.clear_spans();
+
+    module.const_expressions.clear_spans();
+
+    for (_, function) in module.functions.iter_mut() {
+        names.rename(&mut function.name);
+        obfuscate_function(function, &mut names);
+    }
+    module.functions.clear_spans();
+
+    for entry_point in module.entry_points.iter_mut() {
+        obfuscate_function(&mut entry_point.function, &mut names);
+    }
+}
+
+fn obfuscate_function(function: &mut crate::Function, names: &mut ShortNames) {
+    for argument in function.arguments.iter_mut() {
+        names.rename(&mut argument.name);
+    }
+    for (_, local) in function.local_variables.iter_mut() {
+        names.rename(&mut local.name);
+    }
+    function.local_variables.clear_spans();
+    function.expressions.clear_spans();
+    function.named_expressions.clear();
+    clear_block_spans(&mut function.body);
+}
+
+fn clear_block_spans(block: &mut Block) {
+    for (statement, span) in block.span_iter_mut() {
+        if let Some(span) = span {
+            *span = Span::default();
+        }
+        match *statement {
+            Statement::Block(ref mut block) => clear_block_spans(block),
+            Statement::If {
+                ref mut accept,
+                ref mut reject,
+                ..
+            } => {
+                clear_block_spans(accept);
+                clear_block_spans(reject);
+            }
+            Statement::Switch { ref mut cases, .. } => {
+                for case in cases.iter_mut() {
+                    clear_block_spans(&mut case.body);
+                }
+            }
+            Statement::Loop {
+                ref mut body,
+                ref mut continuing,
+                ..
+            } => {
+                clear_block_spans(body);
+                clear_block_spans(continuing);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A generator of the names `a` through `z`, then `aa`, `ab`, and so on.
+#[derive(Default)]
+struct ShortNames {
+    next: usize,
+}
+
+impl ShortNames {
+    /// Replace `name` with the next short name, if it is present.
+    fn rename(&mut self, name: &mut Option<String>) {
+        if let Some(name) = name.as_mut() {
+            *name = self.next_name();
+        }
+    }
+
+    fn next_name(&mut self) -> String {
+        let name = super::namer::short_name(self.next);
+        self.next += 1;
+        name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn short_names() {
+        let mut names = super::ShortNames::default();
+        let generated: Vec<_> = (0..704).map(|_| names.next_name()).collect();
+        assert_eq!(generated[0], "a");
+        assert_eq!(generated[25], "z");
+        assert_eq!(generated[26], "aa");
+        assert_eq!(generated[51], "az");
+        assert_eq!(generated[52], "ba");
+        assert_eq!(generated[701], "zz");
+        assert_eq!(generated[702], "aaa");
+    }
+
+    #[cfg(all(feature = "wgsl-in", feature = "wgsl-out"))]
+    #[test]
+    fn obfuscate_wgsl() {
+        let source = "
+            struct Light {
+                color: vec3<f32>,
+                intensity: f32,
+            }
+            @group(0) @binding(0) var<uniform> sun: Light;
+            const ambient_level = 0.25;
+
+            fn shade(normal: vec3<f32>) -> vec3<f32> {
+                var brightness = max(dot(normal, vec3(0.0, 1.0, 0.0)), ambient_level);
+                let lit = sun.color * sun.intensity * brightness;
+                return lit;
+            }
+
+            @fragment
+            fn fs_main(@location(0) surface_normal: vec3<f32>) -> @location(0) vec4<f32> {
+                return vec4(shade(surface_normal), 1.0);
+            }
+        ";
+        let mut module = crate::front::wgsl::parse_str(source).unwrap();
+        super::obfuscate(&mut module);
+        let info = crate::valid::Validator::new(
+            crate::valid::ValidationFlags::all(),
+            crate::valid::Capabilities::all(),
+        )
+        .validate(&module)
+        .unwrap();
+        let output = crate::back::wgsl::write_string(
+            &module,
+            &info,
+            crate::back::wgsl::WriterFlags::empty(),
+        )
+        .unwrap();
+
+        assert!(output.contains("fn fs_main("));
+        for identifier in [
+            "Light",
+            "color",
+            "intensity",
+            "sun",
+            "ambient_level",
+            "shade",
+            "normal",
+            "brightness",
+            "lit",
+            "surface_normal",
+        ] {
+            assert!(!output.contains(identifier), "`{identifier}` in:\n{output}");
+        }
+    }
+}
@@ -0,0 +1,286 @@
+/*!
+Making float-to-integer conversions saturate.
+
+[`Expression::As`] leaves the result of converting an out-of-range float
+to an integer unspecified, because that is what most of our target
+languages do: SPIR-V, GLSL, HLSL and MSL all leave it undefined, and
+drivers disagree in practice. WGSL, however, requires such conversions
+to saturate, and so does the constant evaluator. [`saturate_float_to_int`]
+clamps the operand of each such conversion to the range of the target
+type first, so that every backend emits a conversion whose result is
+defined.
+
+[`Expression::As`]: crate::Expression::As
+*/
+
+use super::scalarize::{adjust_expression, for_each_function, retarget_function, typify};
+use crate::arena::Handle;
+use crate::{Expression, Function, Literal, MathFunction, ScalarKind, Span, TypeInner};
+
+/// Clamp the operand of every float-to-integer [`As`] conversion in
+/// `module` to the range of the target type.
+///
+/// Afterwards, converting a value that is not NaN gives the result
+/// documented on [`As`] on every backend, the same as the constant
+/// evaluator. Converting a NaN remains unspecified.
+///
+/// The module should be validated again afterwards, to get a
+/// [`ModuleInfo`] that matches the new arenas.
+///
+/// # Panics
+///
+/// If `module` has not passed validation, this may panic.
+///
+/// [`As`]: Expression::As
+/// [`ModuleInfo`]: crate::valid::ModuleInfo
+pub fn saturate_float_to_int(module: &mut crate::Module) {
+    for_each_function(module, saturate_function);
+}
+
+/// The smallest and largest floats of `float_width` bytes that convert to
+/// an integer of `kind` and `width` bytes without leaving its range.
+///
+/// These are the range's ends when the float type can represent them, and
+/// otherwise the floats nearest them inside it. Returns `None` if every
+/// float converts in range.
+fn bounds(
+    kind: ScalarKind,
+    width: crate::Bytes,
+    float_width: crate::Bytes,
+) -> Option<(Literal, Literal)> {
+    Some(match (float_width, kind, width) {
+        // Every `f16` is in range for a signed integer, but not the negative
+        // ones for an unsigned one. The upper bound is the largest `f16`.
+        (2, ScalarKind::Sint, _) => return None,
+        (2, ScalarKind::Uint, _) => (Literal::F16(0), Literal::F16(0x7bff)),
+        (4, ScalarKind::Sint, 4) => (Literal::F32(-2147483648.0), Literal::F32(2147483520.0)),
+        (4, ScalarKind::Uint, 4) => (Literal::F32(0.0), Literal::F32(4294967040.0)),
+        (4, ScalarKind::Sint, 8) => (
+            Literal::F32(-9223372036854775808.0),
+            Literal::F32(9223371487098961920.0),
+        ),
+        (4, ScalarKind::Uint, 8) => (Literal::F32(0.0), Literal::F32(18446742974197923840.0)),
+        (8, ScalarKind::Sint, 4) => (Literal::F64(-2147483648.0), Literal::F64(2147483647.0)),
+        (8, ScalarKind::Uint, 4) => (Literal::F64(0.0), Literal::F64(4294967295.0)),
+        (8, ScalarKind::Sint, 8) => (
+            Literal::F64(-9223372036854775808.0),
+            Literal::F64(9223372036854774784.0),
+        ),
+        (8, ScalarKind::Uint, 8) => (Literal::F64(0.0), Literal::F64(18446744073709549568.0)),
+        _ => return None,
+    })
+}
+
+fn saturate_function(module: &mut crate::Module, fun: &mut Function) -> bool {
+    let typifier = typify(module, fun);
+
+    // Find the conversions to clamp, with the vector size of their operands
+    // and the bounds they need.
+    let mut targets = crate::FastHashMap::default();
+    let mut needed_bounds = Vec::new();
+    for (handle, expr) in fun.expressions.iter() {
+        let (expr, kind, width) = match *expr {
+            Expression::As {
+                expr,
+                kind: kind @ (ScalarKind::Sint | ScalarKind::Uint),
+                convert: Some(width),
+            } => (expr, kind, width),
+            _ => continue,
+        };
+        let (size, float_width) = match *typifier.get(expr, &module.types) {
+            TypeInner::Scalar {
+                kind: ScalarKind::Float,
+                width,
+            } => (None, width),
+            TypeInner::Vector {
+                kind: ScalarKind::Float,
+                width,
+                size,
+            } => (Some(size), width),
+            _ => continue,
+        };
+        if let Some(bounds) = bounds(kind, width, float_width) {
+            if !needed_bounds.contains(&bounds) {
+                needed_bounds.push(bounds);
+            }
+            targets.insert(handle, (size, bounds));
+        }
+    }
+    if targets.is_empty() {
+        return false;
+    }
+
+    // Rebuild the arena. The bounds are literals, which are never emitted,
+    // so they go ahead of everything else, where they can't end up inside
+    // an `Emit` range. Each conversion's clamp goes just before it.
+    let old = std::mem::take(&mut fun.expressions);
+    let bound_handles: Vec<_> = needed_bounds
+        .iter()
+        .map(|&(low, high)| {
+            (
+                fun.expressions
+                    .append(Expression::Literal(low), Span::default()),
+                fun.expressions
+                    .append(Expression::Literal(high), Span::default()),
+            )
+        })
+        .collect();
+    let mut first = Vec::with_capacity(old.len());
+    let mut last: Vec<Handle<Expression>> = Vec::with_capacity(old.len());
+    for (handle, expr) in old.iter() {
+        let span = old.get_span(handle);
+        let mut expr = expr.clone();
+        adjust_expression(&mut expr, &mut |h| *h = last[h.index()]);
+
+        if let Some(&(size, bounds)) = targets.get(&handle) {
+            let start = fun.expressions.len();
+            let index = needed_bounds.iter().position(|&b| b == bounds).unwrap();
+            let (mut low, mut high) = bound_handles[index];
+            if let Some(size) = size {
+                low = fun
+                    .expressions
+                    .append(Expression::Splat { size, value: low }, span);
+                high = fun
+                    .expressions
+                    .append(Expression::Splat { size, value: high }, span);
+            }
+            if let Expression::As { ref mut expr, .. } = expr {
+                *expr = fun.expressions.append(
+                    Expression::Math {
+                        fun: MathFunction::Clamp,
+                        arg: *expr,
+                        arg1: Some(low),
+                        arg2: Some(high),
+                        arg3: None,
+                    },
+                    span,
+                );
+            }
+            let new = fun.expressions.append(expr, span);
+            first.push(
+                fun.expressions
+                    .range_from(start)
+                    .first_and_last()
+                    .unwrap()
+                    .0,
+            );
+            last.push(new);
+        } else {
+            let new = fun.expressions.append(expr, span);
+            first.push(new);
+            last.push(new);
+        }
+    }
+
+    retarget_function(fun, &first, &last);
+    true
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod tests {
+    use crate::proc::cpu;
+    use crate::test_util::Fixture;
+    use crate::valid::Capabilities;
+    use crate::{Expression, Literal, ScalarKind};
+
+    /// Each function converts a `vec4` of floats both as a whole and one
+    /// component at a time, and returns the sum.
+    const SOURCE: &str = "
+        fn f32_i32(v: vec4<f32>) -> vec4<i32> {
+            return vec4<i32>(v) + vec4(i32(v.x), i32(v.y), i32(v.z), i32(v.w));
+        }
+        fn f32_u32(v: vec4<f32>) -> vec4<u32> {
+            return vec4<u32>(v) + vec4(u32(v.x), u32(v.y), u32(v.z), u32(v.w));
+        }
+        fn f32_i64(v: vec4<f32>) -> vec4<i64> {
+            return vec4<i64>(v) + vec4(i64(v.x), i64(v.y), i64(v.z), i64(v.w));
+        }
+        fn f32_u64(v: vec4<f32>) -> vec4<u64> {
+            return vec4<u64>(v) + vec4(u64(v.x), u64(v.y), u64(v.z), u64(v.w));
+        }
+        fn f64_i32(v: vec4<f64>) -> vec4<i32> {
+            return vec4<i32>(v) + vec4(i32(v.x), i32(v.y), i32(v.z), i32(v.w));
+        }
+        fn f64_u64(v: vec4<f64>) -> vec4<u64> {
+            return vec4<u64>(v) + vec4(u64(v.x), u64(v.y), u64(v.z), u64(v.w));
+        }
+    ";
+
+    /// Convert `value` to `kind` and `width` with the constant evaluator.
+    fn const_eval(value: f32, kind: ScalarKind, width: crate::Bytes) -> f64 {
+        let mut module = crate::Module::default();
+        let literal = module
+            .const_expressions
+            .append(Expression::Literal(Literal::F32(value)), Default::default());
+        let result = crate::proc::ConstantEvaluator::for_wgsl_module(&mut module)
+            .try_eval_and_append(
+                &Expression::As {
+                    expr: literal,
+                    kind,
+                    convert: Some(width),
+                },
+                Default::default(),
+            )
+            .unwrap();
+        match module.const_expressions[result] {
+            Expression::Literal(Literal::I32(value)) => value.into(),
+            Expression::Literal(Literal::U32(value)) => value.into(),
+            Expression::Literal(Literal::I64(value)) => value as f64,
+            Expression::Literal(Literal::U64(value)) => value as f64,
+            ref other => panic!("expected an integer, found {other:?}"),
+        }
+    }
+
+    #[test]
+    fn saturate_float_to_int() {
+        let capabilities = Capabilities::FLOAT64 | Capabilities::SHADER_INT64;
+        let mut fixture = Fixture::wgsl_with_capabilities(SOURCE, capabilities);
+        super::saturate_float_to_int(&mut fixture.module);
+        fixture.revalidate();
+        let module = fixture.module;
+
+        let check = |name, values: [f64; 4], expected: [f64; 4]| {
+            let expected = expected.map(|value| value * 2.0);
+            assert_eq!(cpu::call(&module, name, &[&values]), expected, "{name}");
+        };
+
+        // Converting `f32`s saturates the same way as the constant evaluator.
+        let batches = [
+            [f32::NEG_INFINITY, -3.0e9, -2147483904.0, -2147483648.0],
+            [-1.5, -0.5, 0.0, 0.75],
+            [1.0e9, 2147483520.0, 2147483648.0, 3.0e9],
+            [4294967040.0, 4294967296.0, 5.0e9, f32::INFINITY],
+            [-1.0e19, -9.3e18, 9.3e18, 1.0e19],
+            [
+                18446742974197923840.0,
+                18446744073709551616.0,
+                1.0e20,
+                1.0e30,
+            ],
+        ];
+        let cases = [
+            ("f32_i32", ScalarKind::Sint, 4),
+            ("f32_u32", ScalarKind::Uint, 4),
+            ("f32_i64", ScalarKind::Sint, 8),
+            ("f32_u64", ScalarKind::Uint, 8),
+        ];
+        for values in batches {
+            for (name, kind, width) in cases {
+                let expected = values.map(|value| const_eval(value, kind, width));
+                check(name, values.map(f64::from), expected);
+            }
+        }
+
+        // The constant evaluator doesn't convert `f64`s.
+        check(
+            "f64_i32",
+            [f64::NEG_INFINITY, -2147483648.5, 2147483647.5, 4294967296.0],
+            [-2147483648.0, -2147483648.0, 2147483647.0, 2147483647.0],
+        );
+        check(
+            "f64_u64",
+            [-1.0, 0.5, 18446744073709549568.0, f64::INFINITY],
+            [0.0, 0.0, 18446744073709549568.0, 18446744073709549568.0],
+        );
+    }
+}
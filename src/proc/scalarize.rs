@@ -0,0 +1,696 @@
+/*!
+Rewriting componentwise math between vector and per-component forms.
+
+Some drivers miscompile particular [`MathFunction`]s when applied to
+vectors, while others generate better code for the vector form than for
+the equivalent sequence of scalar operations. [`scalarize_math`] and
+[`vectorize_math`] let a caller choose, per backend invocation, which form
+the backend sees for a given set of functions.
+
+Both passes only touch functions that are
+[componentwise](MathFunction::is_componentwise), and leave the module's
+meaning unchanged. The module should be validated again afterwards, to get
+a [`ModuleInfo`] that matches the new arenas.
+
+The rewritten math needn't round the same way as the original, so a vertex
+entry point that requests an invariant position may no longer compute the
+same position as other programs that skipped the pass. Both passes log a
+warning when they change a module that has such an entry point.
+
+[`MathFunction`]: crate::MathFunction
+[`ModuleInfo`]: crate::valid::ModuleInfo
+*/
+
+use crate::arena::{Handle, Range};
+use crate::front::Typifier;
+use crate::proc::ResolveContext;
+use crate::{Expression, Function, MathFunction, TypeInner};
+
+/// Rewrite each vector application of `functions` as one scalar
+/// application per component, composed back into a vector.
+///
+/// For example, `sin(v)` for a `vec3<f32>` value `v` becomes
+/// `vec3<f32>(sin(v.x), sin(v.y), sin(v.z))`. Scalar arguments, like the
+/// `t` of `mix(a, b, t)`, are passed to every scalar application as is.
+///
+/// Functions in `functions` that aren't [componentwise] are ignored.
+///
+/// # Panics
+///
+/// If `module` has not passed validation, this may panic.
+///
+/// [componentwise]: MathFunction::is_componentwise
+pub fn scalarize_math(module: &mut crate::Module, functions: &[MathFunction]) {
+    let changed = for_each_function(module, |module, fun| {
+        scalarize_function(module, fun, functions)
+    });
+    if changed {
+        warn_about_invariance(module, "scalarize_math");
+    }
+}
+
+/// Rewrite vectors composed of scalar applications of `functions` to the
+/// components of other vectors as a single vector application.
+///
+/// This is the inverse of [`scalarize_math`]: it recognizes
+/// `vec3<f32>(sin(v.x), sin(v.y), sin(v.z))` and turns it into `sin(v)`,
+/// as long as every component applies the same function to the
+/// corresponding components of the same vectors.
+///
+/// The scalar applications are left in place; if nothing else uses them,
+/// compaction or the backend's own dead code handling will drop them.
+///
+/// # Panics
+///
+/// If `module` has not passed validation, this may panic.
+pub fn vectorize_math(module: &mut crate::Module, functions: &[MathFunction]) {
+    let changed = for_each_function(module, |module, fun| {
+        vectorize_function(module, fun, functions)
+    });
+    if changed {
+        warn_about_invariance(module, "vectorize_math");
+    }
+}
+
+/// Call `f` on every function and entry point in `module`, and return
+/// whether any call returned true.
+///
+/// Each function is moved out of `module` while `f` runs, so that `f` can
+/// also consult and extend the module's other arenas.
+pub(super) fn for_each_function(
+    module: &mut crate::Module,
+    mut f: impl FnMut(&mut crate::Module, &mut Function) -> bool,
+) -> bool {
+    let mut changed = false;
+    let handles: Vec<_> = module.functions.iter().map(|(handle, _)| handle).collect();
+    for handle in handles {
+        let mut fun = std::mem::take(module.functions.get_mut(handle));
+        changed |= f(module, &mut fun);
+        *module.functions.get_mut(handle) = fun;
+    }
+    let mut entry_points = std::mem::take(&mut module.entry_points);
+    for ep in entry_points.iter_mut() {
+        changed |= f(module, &mut ep.function);
+    }
+    module.entry_points = entry_points;
+    changed
+}
+
+fn warn_about_invariance(module: &crate::Module, pass: &str) {
+    for ep in module.entry_points.iter() {
+        if crate::valid::position_invariance(module, &ep.function, true) == Some(true) {
+            log::warn!(
+                "{pass} changed a module whose entry point `{}` requests an invariant \
+                position; the position may no longer be invariant",
+                ep.name
+            );
+        }
+    }
+}
+
+pub(super) fn typify(module: &crate::Module, fun: &Function) -> Typifier {
+    let mut typifier = Typifier::new();
+    if let Some((last, _)) = fun.expressions.iter().next_back() {
+        let ctx = ResolveContext::with_locals(module, &fun.local_variables, &fun.arguments);
+        typifier.grow(last, &fun.expressions, &ctx).unwrap();
+    }
+    typifier
+}
+
+const fn math_arguments(
+    expr: &Expression,
+) -> Option<(MathFunction, [Option<Handle<Expression>>; 4])> {
+    match *expr {
+        Expression::Math {
+            fun,
+            arg,
+            arg1,
+            arg2,
+            arg3,
+        } => Some((fun, [Some(arg), arg1, arg2, arg3])),
+        _ => None,
+    }
+}
+
+fn scalarize_function(
+    module: &mut crate::Module,
+    fun: &mut Function,
+    functions: &[MathFunction],
+) -> bool {
+    let typifier = typify(module, fun);
+
+    // Find the applications to split, and the vector types of their results.
+    let mut targets = Vec::new();
+    for (handle, expr) in fun.expressions.iter() {
+        if let Some((math, _)) = math_arguments(expr) {
+            if !math.is_componentwise() || !functions.contains(&math) {
+                continue;
+            }
+            if let TypeInner::Vector { size, .. } = *typifier.get(handle, &module.types) {
+                targets.push((handle, size as u32));
+            }
+        }
+    }
+    if targets.is_empty() {
+        return false;
+    }
+    let targets: crate::FastHashMap<_, _> = targets
+        .into_iter()
+        .map(|(handle, size)| {
+            (
+                handle,
+                (size, typifier.register_type(handle, &mut module.types)),
+            )
+        })
+        .collect();
+
+    // Rebuild the arena, placing each target's scalar pieces just before
+    // the `Compose` that replaces it. `first[i]` is the first new expression
+    // produced for the old expression with index `i`, and `last[i]` is the
+    // one that takes its place.
+    let old = std::mem::take(&mut fun.expressions);
+    let mut first = Vec::with_capacity(old.len());
+    let mut last: Vec<Handle<Expression>> = Vec::with_capacity(old.len());
+    for (handle, expr) in old.iter() {
+        let span = old.get_span(handle);
+        let mut expr = expr.clone();
+        adjust_expression(&mut expr, &mut |h| *h = last[h.index()]);
+
+        if let Some(&(size, ty)) = targets.get(&handle) {
+            let (math, args) = math_arguments(&expr).unwrap();
+            let (_, old_args) = math_arguments(&old[handle]).unwrap();
+            let start = fun.expressions.len();
+
+            let mut components = Vec::with_capacity(size as usize);
+            for index in 0..size {
+                let mut scalar_args = [None; 4];
+                for (scalar_arg, (arg, old_arg)) in
+                    scalar_args.iter_mut().zip(args.into_iter().zip(old_args))
+                {
+                    if let (Some(arg), Some(old_arg)) = (arg, old_arg) {
+                        *scalar_arg = Some(match *typifier.get(old_arg, &module.types) {
+                            TypeInner::Vector { .. } => fun
+                                .expressions
+                                .append(Expression::AccessIndex { base: arg, index }, span),
+                            _ => arg,
+                        });
+                    }
+                }
+                let [arg, arg1, arg2, arg3] = scalar_args;
+                components.push(fun.expressions.append(
+                    Expression::Math {
+                        fun: math,
+                        arg: arg.unwrap(),
+                        arg1,
+                        arg2,
+                        arg3,
+                    },
+                    span,
+                ));
+            }
+
+            let compose = fun
+                .expressions
+                .append(Expression::Compose { ty, components }, span);
+            first.push(
+                fun.expressions
+                    .range_from(start)
+                    .first_and_last()
+                    .unwrap()
+                    .0,
+            );
+            last.push(compose);
+        } else {
+            let new = fun.expressions.append(expr, span);
+            first.push(new);
+            last.push(new);
+        }
+    }
+
+    retarget_function(fun, &first, &last);
+    true
+}
+
+/// Point everything in `fun` outside its expression arena at the rebuilt
+/// arena's expressions.
+///
+/// `first[i]` is the first new expression produced for the old expression
+/// with index `i`, and `last[i]` is the one that takes its place. An old
+/// `Emit` range covers everything produced for the expressions it covered.
+pub(crate) fn retarget_function(
+    fun: &mut Function,
+    first: &[Handle<Expression>],
+    last: &[Handle<Expression>],
+) {
+    adjust_block(&mut fun.body, &mut |h| *h = last[h.index()], &mut |range| {
+        if let Some((start, end)) = range.first_and_last() {
+            *range = Range::new_from_bounds(first[start.index()], last[end.index()]);
+        }
+    });
+    fun.named_expressions = std::mem::take(&mut fun.named_expressions)
+        .into_iter()
+        .map(|(handle, name)| (last[handle.index()], name))
+        .collect();
+    for (_, local) in fun.local_variables.iter_mut() {
+        if let Some(ref mut init) = local.init {
+            *init = last[init.index()];
+        }
+    }
+}
+
+fn vectorize_function(
+    module: &mut crate::Module,
+    fun: &mut Function,
+    functions: &[MathFunction],
+) -> bool {
+    let typifier = typify(module, fun);
+
+    let mut replacements = Vec::new();
+    for (handle, expr) in fun.expressions.iter() {
+        let components = match *expr {
+            Expression::Compose { ref components, .. } => components,
+            _ => continue,
+        };
+        let size = match *typifier.get(handle, &module.types) {
+            TypeInner::Vector { size, .. } => size as u32,
+            _ => continue,
+        };
+        if components.len() != size as usize {
+            continue;
+        }
+
+        // Every component must apply the same function to the component
+        // with the same index of the same vectors. Scalar arguments are not
+        // matched, since not every function accepts them alongside vectors.
+        let mut found = None;
+        for (index, &component) in components.iter().enumerate() {
+            let vector_args =
+                math_arguments(&fun.expressions[component]).and_then(|(math, args)| {
+                    let mut vector_args = [None; 4];
+                    for (vector_arg, arg) in vector_args.iter_mut().zip(args) {
+                        let arg = match arg {
+                            Some(arg) => arg,
+                            None => continue,
+                        };
+                        *vector_arg = match fun.expressions[arg] {
+                            Expression::AccessIndex { base, index: i } if i == index as u32 => {
+                                match *typifier.get(base, &module.types) {
+                                    TypeInner::Vector { size: s, .. } if s as u32 == size => {
+                                        Some(base)
+                                    }
+                                    _ => return None,
+                                }
+                            }
+                            _ => return None,
+                        };
+                    }
+                    Some((math, vector_args))
+                });
+            match vector_args {
+                Some(current) if *found.get_or_insert(current) == current => {}
+                _ => {
+                    found = None;
+                    break;
+                }
+            }
+        }
+
+        let (math, [arg, arg1, arg2, arg3]) = match found {
+            Some(found) => found,
+            None => continue,
+        };
+        if !math.is_componentwise() || !functions.contains(&math) {
+            continue;
+        }
+        replacements.push((
+            handle,
+            Expression::Math {
+                fun: math,
+                arg: arg.unwrap(),
+                arg1,
+                arg2,
+                arg3,
+            },
+        ));
+    }
+
+    // The arguments are all older than the `Compose` they replace, so
+    // handles don't need to be adjusted.
+    let changed = !replacements.is_empty();
+    for (handle, expr) in replacements {
+        *fun.expressions.get_mut(handle) = expr;
+    }
+    changed
+}
+
+/// Apply `adjust` to every function expression handle in `expr`.
+pub(crate) fn adjust_expression(
+    expr: &mut Expression,
+    adjust: &mut impl FnMut(&mut Handle<Expression>),
+) {
+    fn adjust_opt(
+        handle: &mut Option<Handle<Expression>>,
+        adjust: &mut impl FnMut(&mut Handle<Expression>),
+    ) {
+        if let Some(ref mut handle) = *handle {
+            adjust(handle);
+        }
+    }
+
+    use crate::Expression as Ex;
+    match *expr {
+        Ex::Literal(_)
+        | Ex::Constant(_)
+        | Ex::ZeroValue(_)
+        | Ex::FunctionArgument(_)
+        | Ex::GlobalVariable(_)
+        | Ex::LocalVariable(_)
+        | Ex::CallResult(_)
+        | Ex::AtomicResult { .. }
+        | Ex::WorkGroupUniformLoadResult { .. }
+        | Ex::RayQueryProceedResult => {}
+
+        Ex::Compose {
+            ref mut components, ..
+        } => {
+            for component in components {
+                adjust(component);
+            }
+        }
+        Ex::Access {
+            ref mut base,
+            ref mut index,
+        } => {
+            adjust(base);
+            adjust(index);
+        }
+        Ex::AccessIndex { ref mut base, .. } => adjust(base),
+        Ex::Splat { ref mut value, .. } => adjust(value),
+        Ex::Repeat { ref mut value, .. } => adjust(value),
+        Ex::Swizzle { ref mut vector, .. } => adjust(vector),
+        Ex::Load { ref mut pointer } => adjust(pointer),
+        Ex::ImageSample {
+            ref mut image,
+            ref mut sampler,
+            ref mut coordinate,
+            ref mut array_index,
+            // `offset` refers to `Module::const_expressions`.
+            offset: _,
+            ref mut level,
+            ref mut depth_ref,
+            gather: _,
+        } => {
+            adjust(image);
+            adjust(sampler);
+            adjust(coordinate);
+            adjust_opt(array_index, adjust);
+            match *level {
+                crate::SampleLevel::Auto | crate::SampleLevel::Zero => {}
+                crate::SampleLevel::Exact(ref mut expr)
+                | crate::SampleLevel::Bias(ref mut expr) => adjust(expr),
+                crate::SampleLevel::Gradient {
+                    ref mut x,
+                    ref mut y,
+                } => {
+                    adjust(x);
+                    adjust(y);
+                }
+            }
+            adjust_opt(depth_ref, adjust);
+        }
+        Ex::ImageLoad {
+            ref mut image,
+            ref mut coordinate,
+            ref mut array_index,
+            ref mut sample,
+            ref mut level,
+        } => {
+            adjust(image);
+            adjust(coordinate);
+            adjust_opt(array_index, adjust);
+            adjust_opt(sample, adjust);
+            adjust_opt(level, adjust);
+        }
+        Ex::ImageQuery {
+            ref mut image,
+            ref mut query,
+        } => {
+            adjust(image);
+            if let crate::ImageQuery::Size { ref mut level } = *query {
+                adjust_opt(level, adjust);
+            }
+        }
+        Ex::Unary { ref mut expr, .. } => adjust(expr),
+        Ex::Binary {
+            ref mut left,
+            ref mut right,
+            ..
+        } => {
+            adjust(left);
+            adjust(right);
+        }
+        Ex::Select {
+            ref mut condition,
+            ref mut accept,
+            ref mut reject,
+        } => {
+            adjust(condition);
+            adjust(accept);
+            adjust(reject);
+        }
+        Ex::Derivative { ref mut expr, .. } => adjust(expr),
+        Ex::Relational {
+            ref mut argument, ..
+        } => adjust(argument),
+        Ex::Math {
+            ref mut arg,
+            ref mut arg1,
+            ref mut arg2,
+            ref mut arg3,
+            ..
+        } => {
+            adjust(arg);
+            adjust_opt(arg1, adjust);
+            adjust_opt(arg2, adjust);
+            adjust_opt(arg3, adjust);
+        }
+        Ex::As { ref mut expr, .. } => adjust(expr),
+        Ex::ArrayLength(ref mut expr) => adjust(expr),
+        Ex::RayQueryGetIntersection { ref mut query, .. } => adjust(query),
+        Ex::SubgroupOperation {
+            ref mut op,
+            ref mut argument,
+        } => {
+            adjust(argument);
+            if let Some(index) = op.index_mut() {
+                adjust(index);
+            }
+        }
+        Ex::NonUniform(ref mut value) => adjust(value),
+    }
+}
+
+/// Apply `adjust` to every expression handle in `block`, and
+/// `adjust_range` to every `Emit` range.
+fn adjust_block(
+    block: &mut crate::Block,
+    adjust: &mut impl FnMut(&mut Handle<Expression>),
+    adjust_range: &mut impl FnMut(&mut Range<Expression>),
+) {
+    for stmt in block.iter_mut() {
+        adjust_statement(stmt, adjust, adjust_range);
+    }
+}
+
+/// Apply `adjust` to every expression handle in `stmt` and the blocks it
+/// contains, and `adjust_range` to every `Emit` range.
+pub(crate) fn adjust_statement(
+    stmt: &mut crate::Statement,
+    adjust: &mut impl FnMut(&mut Handle<Expression>),
+    adjust_range: &mut impl FnMut(&mut Range<Expression>),
+) {
+    use crate::Statement as St;
+    match *stmt {
+        St::Emit(ref mut range) => adjust_range(range),
+        St::Block(ref mut block) => adjust_block(block, adjust, adjust_range),
+        St::If {
+            ref mut condition,
+            ref mut accept,
+            ref mut reject,
+        } => {
+            adjust(condition);
+            adjust_block(accept, adjust, adjust_range);
+            adjust_block(reject, adjust, adjust_range);
+        }
+        St::Switch {
+            ref mut selector,
+            ref mut cases,
+        } => {
+            adjust(selector);
+            for case in cases {
+                adjust_block(&mut case.body, adjust, adjust_range);
+            }
+        }
+        St::Loop {
+            ref mut body,
+            ref mut continuing,
+            ref mut break_if,
+        } => {
+            adjust_block(body, adjust, adjust_range);
+            adjust_block(continuing, adjust, adjust_range);
+            if let Some(ref mut break_if) = *break_if {
+                adjust(break_if);
+            }
+        }
+        St::Return {
+            value: Some(ref mut value),
+        } => adjust(value),
+        St::Store {
+            ref mut pointer,
+            ref mut value,
+        } => {
+            adjust(pointer);
+            adjust(value);
+        }
+        St::ImageStore {
+            ref mut image,
+            ref mut coordinate,
+            ref mut array_index,
+            ref mut value,
+        } => {
+            adjust(image);
+            adjust(coordinate);
+            if let Some(ref mut array_index) = *array_index {
+                adjust(array_index);
+            }
+            adjust(value);
+        }
+        St::ImageAtomic {
+            ref mut image,
+            ref mut coordinate,
+            ref mut array_index,
+            fun: _,
+            ref mut value,
+        } => {
+            adjust(image);
+            adjust(coordinate);
+            if let Some(ref mut array_index) = *array_index {
+                adjust(array_index);
+            }
+            adjust(value);
+        }
+        St::Atomic {
+            ref mut pointer,
+            ref mut fun,
+            ref mut value,
+            ref mut result,
+        } => {
+            adjust(pointer);
+            if let crate::AtomicFunction::Exchange {
+                compare: Some(ref mut compare),
+            } = *fun
+            {
+                adjust(compare);
+            }
+            adjust(value);
+            adjust(result);
+        }
+        St::WorkGroupUniformLoad {
+            ref mut pointer,
+            ref mut result,
+        } => {
+            adjust(pointer);
+            adjust(result);
+        }
+        St::Call {
+            ref mut arguments,
+            ref mut result,
+            ..
+        } => {
+            for argument in arguments {
+                adjust(argument);
+            }
+            if let Some(ref mut result) = *result {
+                adjust(result);
+            }
+        }
+        St::RayQuery {
+            ref mut query,
+            ref mut fun,
+        } => {
+            adjust(query);
+            match *fun {
+                crate::RayQueryFunction::Initialize {
+                    ref mut acceleration_structure,
+                    ref mut descriptor,
+                } => {
+                    adjust(acceleration_structure);
+                    adjust(descriptor);
+                }
+                crate::RayQueryFunction::Proceed { ref mut result } => adjust(result),
+                crate::RayQueryFunction::Terminate => {}
+            }
+        }
+        St::Break | St::Continue | St::Kill | St::Barrier(_) | St::Return { value: None } => {}
+    }
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod tests {
+    use crate::proc::cpu;
+    use crate::test_util::Fixture;
+    use crate::valid::Capabilities;
+    use crate::{Expression, MathFunction};
+
+    const SOURCE: &str = "
+        fn f(a: vec3<f32>, b: vec3<f32>, t: f32) -> vec3<f32> {
+            return sin(a) + pow(a, b) * mix(a, b, t);
+        }
+    ";
+    const ARGS: [&[f64]; 3] = [&[0.5, 1.0, 2.0], &[3.0, 0.25, 1.5], &[0.75]];
+
+    /// The math functions applied to whole vectors in the returned sum.
+    fn vector_math(module: &crate::Module) -> Vec<MathFunction> {
+        let fun = cpu::function(module, "f");
+        let mut pending = vec![cpu::returned(fun)];
+        let mut found = Vec::new();
+        while let Some(expr) = pending.pop() {
+            match fun.expressions[expr] {
+                Expression::Binary { left, right, .. } => pending.extend([left, right]),
+                Expression::Math { fun, .. } => found.push(fun),
+                _ => {}
+            }
+        }
+        found.sort_by_key(|&fun| fun as u8);
+        found
+    }
+
+    #[test]
+    fn scalarize_and_vectorize() {
+        let mut fixture = Fixture::wgsl_with_capabilities(SOURCE, Capabilities::empty());
+        let expected = cpu::call(&fixture.module, "f", &ARGS);
+        let check = |fixture: &mut Fixture, math: &[MathFunction]| {
+            fixture.revalidate();
+            assert_eq!(cpu::call(&fixture.module, "f", &ARGS), expected);
+            let mut math = math.to_vec();
+            math.sort_by_key(|&fun| fun as u8);
+            assert_eq!(vector_math(&fixture.module), math);
+        };
+
+        // Only `pow` is split.
+        super::scalarize_math(&mut fixture.module, &[MathFunction::Pow]);
+        check(&mut fixture, &[MathFunction::Sin, MathFunction::Mix]);
+
+        // Splitting `sin` and `mix` too leaves no vector math behind. The
+        // scalar `t` is passed to each `mix` as is.
+        let split = [MathFunction::Sin, MathFunction::Mix];
+        super::scalarize_math(&mut fixture.module, &split);
+        check(&mut fixture, &[]);
+
+        // Vectorizing undoes `sin` and `pow`, but not `mix`, whose scalar
+        // `t` it doesn't match.
+        let all = [MathFunction::Sin, MathFunction::Pow, MathFunction::Mix];
+        super::vectorize_math(&mut fixture.module, &all);
+        check(&mut fixture, &[MathFunction::Sin, MathFunction::Pow]);
+    }
+}
@@ -33,6 +33,7 @@ pub fn ensure_block_returns(block: &mut crate::Block) {
             &mut (S::Loop { .. }
             | S::Store { .. }
             | S::ImageStore { .. }
+            | S::ImageAtomic { .. }
             | S::Call { .. }
             | S::RayQuery { .. }
             | S::Atomic { .. }
@@ -436,7 +436,9 @@ impl<'a> ResolveContext<'a> {
             crate::Expression::Literal(lit) => TypeResolution::Value(lit.ty_inner()),
             crate::Expression::Constant(h) => TypeResolution::Handle(self.constants[h].ty),
             crate::Expression::ZeroValue(ty) => TypeResolution::Handle(ty),
-            crate::Expression::Compose { ty, .. } => TypeResolution::Handle(ty),
+            crate::Expression::Compose { ty, .. } | crate::Expression::Repeat { ty, .. } => {
+                TypeResolution::Handle(ty)
+            }
             crate::Expression::FunctionArgument(index) => {
                 let arg = self
                     .arguments
@@ -905,6 +907,21 @@ impl<'a> ResolveContext<'a> {
                     .ok_or(ResolveError::MissingSpecialType)?;
                 TypeResolution::Handle(result)
             }
+            crate::Expression::SubgroupOperation { op, argument } => match op {
+                crate::SubgroupOperation::Ballot => TypeResolution::Value(Ti::Vector {
+                    size: crate::VectorSize::Quad,
+                    kind: crate::ScalarKind::Uint,
+                    width: 4,
+                }),
+                crate::SubgroupOperation::All | crate::SubgroupOperation::Any => {
+                    TypeResolution::Value(Ti::Scalar {
+                        kind: crate::ScalarKind::Bool,
+                        width: crate::BOOL_WIDTH,
+                    })
+                }
+                _ => past(argument)?.clone(),
+            },
+            crate::Expression::NonUniform(value) => past(value)?.clone(),
         })
     }
 }
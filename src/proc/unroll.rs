@@ -0,0 +1,597 @@
+/*!
+Unrolling loops with a constant trip count.
+
+FXC, the legacy HLSL compiler, chokes on some of the loops the HLSL backend
+writes, particularly ones that index arrays dynamically. When a loop's trip
+count is known before the shader runs, [`unroll_loops`] can replace it with
+that many copies of its body, so the backends never see the loop at all.
+*/
+
+use super::inline::remap_block;
+use super::scalarize::{adjust_expression, for_each_function};
+use crate::arena::{Arena, Handle};
+use crate::{BinaryOperator, Block, Expression, Function, Literal, LocalVariable, Statement};
+
+/// Unroll the loops in `module` that are known to run at most
+/// `max_trip_count` times.
+///
+/// A loop is unrolled when:
+///
+/// - its body starts with an `if` that breaks out of the loop, comparing a
+///   local `i32` or `u32` variable, the induction variable, with a
+///   constant,
+///
+/// - its `continuing` block only adds a constant to the induction variable
+///   or subtracts one from it, and the loop has no `break if`,
+///
+/// - the induction variable holds a constant on entry to the loop, because
+///   the statement before the loop stores one to it, or because it has a
+///   constant initializer, nothing but the loop's `continuing` block stores
+///   to it, and the loop isn't itself inside a loop, and
+///
+/// - the rest of the body doesn't `break` or `continue` the loop, store to
+///   the induction variable, or pass a pointer to it to a function.
+///
+/// This is what the WGSL front end produces for loops like
+/// `for (var i = 0; i < 4; i++) { ... }`.
+///
+/// Each iteration becomes a [`Block`] statement, followed by a `Store` of
+/// the induction variable's next value, so code after the loop still sees
+/// the value the loop left it with. The module should be validated again
+/// afterwards, to get a [`ModuleInfo`] that matches it.
+///
+/// # Panics
+///
+/// If `module` has not passed validation, this may panic.
+///
+/// [`ModuleInfo`]: crate::valid::ModuleInfo
+pub fn unroll_loops(module: &mut crate::Module, max_trip_count: u32) {
+    for_each_function(module, |module, fun| {
+        let mut stores = vec![0; fun.local_variables.len()];
+        count_stores(&fun.expressions, &fun.body, &mut stores);
+        let mut body = std::mem::take(&mut fun.body);
+        Unroller {
+            module,
+            function: fun,
+            stores,
+            max_trip_count,
+        }
+        .unroll_block(&mut body, false);
+        fun.body = body;
+        false
+    });
+}
+
+struct Unroller<'a> {
+    module: &'a crate::Module,
+    function: &'a mut Function,
+
+    /// The number of `Store` statements to each of the function's local
+    /// variables, before unrolling anything.
+    stores: Vec<u32>,
+
+    max_trip_count: u32,
+}
+
+/// How a loop's induction variable changes.
+struct Induction {
+    variable: Handle<LocalVariable>,
+    kind: crate::ScalarKind,
+
+    /// The pointer the loop's `continuing` block stores the variable's
+    /// next value through, and the span of that `Store`.
+    pointer: Handle<Expression>,
+    span: crate::Span,
+
+    /// The amount added to the variable on each iteration.
+    step: i64,
+}
+
+impl Unroller<'_> {
+    /// Unroll the loops in `block` and the blocks nested in it.
+    ///
+    /// If `in_loop` is true, `block` may run more than once.
+    fn unroll_block(&mut self, block: &mut Block, in_loop: bool) {
+        for index in 0..block.len() {
+            match block[index] {
+                Statement::Block(ref mut body) => self.unroll_block(body, in_loop),
+                Statement::If {
+                    ref mut accept,
+                    ref mut reject,
+                    ..
+                } => {
+                    self.unroll_block(accept, in_loop);
+                    self.unroll_block(reject, in_loop);
+                }
+                Statement::Switch { ref mut cases, .. } => {
+                    for case in cases.iter_mut() {
+                        self.unroll_block(&mut case.body, in_loop);
+                    }
+                }
+                Statement::Loop {
+                    ref mut body,
+                    ref mut continuing,
+                    ..
+                } => {
+                    // Unroll inner loops first, so that their copies
+                    // don't need unrolling separately.
+                    self.unroll_block(body, true);
+                    self.unroll_block(continuing, true);
+                    if let Some(unrolled) = self.unroll(block, index, in_loop) {
+                        block[index] = Statement::Block(unrolled);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Return the statements to replace the loop at `block[index]` with,
+    /// if it can be unrolled.
+    fn unroll(&mut self, block: &Block, index: usize, in_loop: bool) -> Option<Block> {
+        let Statement::Loop {
+            ref body,
+            ref continuing,
+            break_if: None,
+        } = block[index]
+        else {
+            return None;
+        };
+        let expressions = &self.function.expressions;
+
+        let guard_index = body
+            .iter()
+            .position(|statement| !matches!(*statement, Statement::Emit(_)))?;
+        let (condition, keep_going) = match body[guard_index] {
+            Statement::If {
+                condition,
+                ref accept,
+                ref reject,
+            } if accept.is_empty() && is_break(reject) => (condition, true),
+            Statement::If {
+                condition,
+                ref accept,
+                ref reject,
+            } if is_break(accept) && reject.is_empty() => (condition, false),
+            _ => return None,
+        };
+        let Expression::Binary { op, left, right } = expressions[condition] else {
+            return None;
+        };
+        if !matches!(
+            op,
+            BinaryOperator::Equal
+                | BinaryOperator::NotEqual
+                | BinaryOperator::Less
+                | BinaryOperator::LessEqual
+                | BinaryOperator::Greater
+                | BinaryOperator::GreaterEqual
+        ) {
+            return None;
+        }
+
+        let induction = self.induction(continuing)?;
+        let (variable_on_left, bound) = if self.loaded(left) == Some(induction.variable) {
+            (true, self.constant(right)?)
+        } else if self.loaded(right) == Some(induction.variable) {
+            (false, self.constant(left)?)
+        } else {
+            return None;
+        };
+
+        let rest = &body[guard_index + 1..];
+        if !self.leaves_alone(rest, induction.variable, false, false) {
+            return None;
+        }
+
+        let preceding = block[..index]
+            .iter()
+            .rev()
+            .find(|statement| !matches!(**statement, Statement::Emit(_)));
+        let initial = match preceding {
+            Some(&Statement::Store { pointer, value })
+                if self.local(pointer) == Some(induction.variable) =>
+            {
+                self.constant(value)?
+            }
+            _ => {
+                let init = self.function.local_variables[induction.variable].init?;
+                if in_loop || self.stores[induction.variable.index()] != 1 {
+                    return None;
+                }
+                self.constant(init)?
+            }
+        };
+
+        let mut values = Vec::new();
+        let mut value = initial;
+        loop {
+            let (left, right) = if variable_on_left {
+                (value, bound)
+            } else {
+                (bound, value)
+            };
+            if compare(op, left, right) != keep_going {
+                break;
+            }
+            if values.len() >= self.max_trip_count as usize {
+                return None;
+            }
+            values.push(value);
+            value = wrap(value + induction.step, induction.kind);
+        }
+
+        let mut unrolled = Block::with_capacity(2 * values.len());
+        for iteration in 0..values.len() {
+            let mut copy = if iteration == 0 {
+                body.clone()
+            } else {
+                self.copy_block(body)
+            };
+            copy.cull(guard_index..=guard_index);
+            unrolled.push(Statement::Block(copy), induction.span);
+
+            let next = values.get(iteration + 1).copied().unwrap_or(value);
+            let literal = match induction.kind {
+                crate::ScalarKind::Sint => Literal::I32(next as i32),
+                _ => Literal::U32(next as u32),
+            };
+            let next = self
+                .function
+                .expressions
+                .append(Expression::Literal(literal), induction.span);
+            unrolled.push(
+                Statement::Store {
+                    pointer: induction.pointer,
+                    value: next,
+                },
+                induction.span,
+            );
+        }
+        Some(unrolled)
+    }
+
+    /// Return how `continuing` changes the loop's induction variable, if it
+    /// does nothing else.
+    fn induction(&self, continuing: &Block) -> Option<Induction> {
+        let (statement, emits) = continuing.split_last()?;
+        if !emits
+            .iter()
+            .all(|statement| matches!(*statement, Statement::Emit(_)))
+        {
+            return None;
+        }
+        let Statement::Store { pointer, value } = *statement else {
+            return None;
+        };
+        let variable = self.local(pointer)?;
+        let kind = match self.module.types[self.function.local_variables[variable].ty].inner {
+            crate::TypeInner::Scalar {
+                kind: kind @ (crate::ScalarKind::Sint | crate::ScalarKind::Uint),
+                width: 4,
+            } => kind,
+            _ => return None,
+        };
+
+        let Expression::Binary { op, left, right } = self.function.expressions[value] else {
+            return None;
+        };
+        let step = match op {
+            BinaryOperator::Add if self.loaded(left) == Some(variable) => self.constant(right)?,
+            BinaryOperator::Add if self.loaded(right) == Some(variable) => self.constant(left)?,
+            BinaryOperator::Subtract if self.loaded(left) == Some(variable) => {
+                -self.constant(right)?
+            }
+            _ => return None,
+        };
+
+        Some(Induction {
+            variable,
+            kind,
+            pointer,
+            span: continuing
+                .span_iter()
+                .last()
+                .map_or_else(crate::Span::default, |(_, &span)| span),
+            step,
+        })
+    }
+
+    /// Return true if `statements` can't leave the loop they're in, except by
+    /// returning, and can't change `variable`.
+    ///
+    /// `in_switch` and `in_loop` say whether `statements` are nested in a
+    /// `switch` or a loop inside that loop.
+    fn leaves_alone(
+        &self,
+        statements: &[Statement],
+        variable: Handle<LocalVariable>,
+        in_switch: bool,
+        in_loop: bool,
+    ) -> bool {
+        statements.iter().all(|statement| match *statement {
+            Statement::Break => in_switch || in_loop,
+            Statement::Continue => in_loop,
+            Statement::Store { pointer, .. } => self.local(pointer) != Some(variable),
+            Statement::Call { ref arguments, .. } => arguments
+                .iter()
+                .all(|&argument| self.local(argument) != Some(variable)),
+            Statement::Block(ref body) => self.leaves_alone(body, variable, in_switch, in_loop),
+            Statement::If {
+                ref accept,
+                ref reject,
+                ..
+            } => {
+                self.leaves_alone(accept, variable, in_switch, in_loop)
+                    && self.leaves_alone(reject, variable, in_switch, in_loop)
+            }
+            Statement::Switch { ref cases, .. } => cases
+                .iter()
+                .all(|case| self.leaves_alone(&case.body, variable, true, in_loop)),
+            Statement::Loop {
+                ref body,
+                ref continuing,
+                ..
+            } => {
+                self.leaves_alone(body, variable, false, true)
+                    && self.leaves_alone(continuing, variable, false, true)
+            }
+            _ => true,
+        })
+    }
+
+    /// Return a copy of `block` that introduces its own copies of the
+    /// expressions `block` introduces.
+    fn copy_block(&mut self, block: &Block) -> Block {
+        let mut introduced = Vec::new();
+        collect_introduced(block, &mut introduced);
+        introduced.sort();
+
+        let expressions = &mut self.function.expressions;
+        let mut map: Vec<_> = expressions.iter().map(|(handle, _)| handle).collect();
+        for handle in introduced {
+            let mut expr = expressions[handle].clone();
+            adjust_expression(&mut expr, &mut |h| *h = map[h.index()]);
+            let span = expressions.get_span(handle);
+            let new_handle = expressions.append(expr, span);
+            map[handle.index()] = new_handle;
+            if let Some(name) = self.function.named_expressions.get(&handle).cloned() {
+                self.function.named_expressions.insert(new_handle, name);
+            }
+        }
+        remap_block(self.module, expressions, block, &map)
+    }
+
+    /// Return the local variable `pointer` refers to, if it's one.
+    fn local(&self, pointer: Handle<Expression>) -> Option<Handle<LocalVariable>> {
+        match self.function.expressions[pointer] {
+            Expression::LocalVariable(local) => Some(local),
+            _ => None,
+        }
+    }
+
+    /// Return the local variable `expr` loads the value of, if it's a `Load`
+    /// of one.
+    fn loaded(&self, expr: Handle<Expression>) -> Option<Handle<LocalVariable>> {
+        match self.function.expressions[expr] {
+            Expression::Load { pointer } => self.local(pointer),
+            _ => None,
+        }
+    }
+
+    /// Return the value of `expr`, if it's an integer known before the
+    /// shader runs.
+    fn constant(&self, expr: Handle<Expression>) -> Option<i64> {
+        let literal = match self.function.expressions[expr] {
+            Expression::Literal(literal) => literal,
+            Expression::Constant(handle) => {
+                let constant = &self.module.constants[handle];
+                if constant.r#override != crate::Override::None {
+                    return None;
+                }
+                match self.module.const_expressions[constant.init] {
+                    Expression::Literal(literal) => literal,
+                    _ => return None,
+                }
+            }
+            _ => return None,
+        };
+        match literal {
+            Literal::I32(value) => Some(value.into()),
+            Literal::U32(value) => Some(value.into()),
+            _ => None,
+        }
+    }
+}
+
+fn is_break(block: &Block) -> bool {
+    matches!(**block, [Statement::Break])
+}
+
+fn compare(op: BinaryOperator, left: i64, right: i64) -> bool {
+    match op {
+        BinaryOperator::Equal => left == right,
+        BinaryOperator::NotEqual => left != right,
+        BinaryOperator::Less => left < right,
+        BinaryOperator::LessEqual => left <= right,
+        BinaryOperator::Greater => left > right,
+        BinaryOperator::GreaterEqual => left >= right,
+        _ => unreachable!(),
+    }
+}
+
+/// Wrap `value` around to the range of a 32-bit integer of `kind`.
+fn wrap(value: i64, kind: crate::ScalarKind) -> i64 {
+    match kind {
+        crate::ScalarKind::Sint => (value as i32).into(),
+        _ => (value as u32).into(),
+    }
+}
+
+/// Count the `Store` statements to each local variable in `block`.
+fn count_stores(expressions: &Arena<Expression>, block: &Block, stores: &mut [u32]) {
+    for statement in block.iter() {
+        match *statement {
+            Statement::Store { pointer, .. } => {
+                if let Expression::LocalVariable(local) = expressions[pointer] {
+                    stores[local.index()] += 1;
+                }
+            }
+            Statement::Block(ref body) => count_stores(expressions, body, stores),
+            Statement::If {
+                ref accept,
+                ref reject,
+                ..
+            } => {
+                count_stores(expressions, accept, stores);
+                count_stores(expressions, reject, stores);
+            }
+            Statement::Switch { ref cases, .. } => {
+                for case in cases.iter() {
+                    count_stores(expressions, &case.body, stores);
+                }
+            }
+            Statement::Loop {
+                ref body,
+                ref continuing,
+                ..
+            } => {
+                count_stores(expressions, body, stores);
+                count_stores(expressions, continuing, stores);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Push the expressions that the statements in `block` introduce: those
+/// they emit, and the results of calls and other operations.
+fn collect_introduced(block: &Block, introduced: &mut Vec<Handle<Expression>>) {
+    for statement in block.iter() {
+        match *statement {
+            Statement::Emit(ref range) => introduced.extend(range.clone()),
+            Statement::Call {
+                result: Some(result),
+                ..
+            }
+            | Statement::Atomic { result, .. }
+            | Statement::WorkGroupUniformLoad { result, .. }
+            | Statement::RayQuery {
+                fun: crate::RayQueryFunction::Proceed { result },
+                ..
+            } => introduced.push(result),
+            Statement::Block(ref body) => collect_introduced(body, introduced),
+            Statement::If {
+                ref accept,
+                ref reject,
+                ..
+            } => {
+                collect_introduced(accept, introduced);
+                collect_introduced(reject, introduced);
+            }
+            Statement::Switch { ref cases, .. } => {
+                for case in cases.iter() {
+                    collect_introduced(&case.body, introduced);
+                }
+            }
+            Statement::Loop {
+                ref body,
+                ref continuing,
+                ..
+            } => {
+                collect_introduced(body, introduced);
+                collect_introduced(continuing, introduced);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(all(test, feature = "wgsl-in"))]
+mod tests {
+    /// Return the number of loops left in the entry point of `source` after
+    /// unrolling those that run at most `max_trip_count` times.
+    fn loops_left(source: &str, max_trip_count: u32) -> usize {
+        fn count(block: &crate::Block) -> usize {
+            block
+                .iter()
+                .map(|statement| match *statement {
+                    crate::Statement::Block(ref body) => count(body),
+                    crate::Statement::If {
+                        ref accept,
+                        ref reject,
+                        ..
+                    } => count(accept) + count(reject),
+                    crate::Statement::Switch { ref cases, .. } => {
+                        cases.iter().map(|case| count(&case.body)).sum()
+                    }
+                    crate::Statement::Loop {
+                        ref body,
+                        ref continuing,
+                        ..
+                    } => 1 + count(body) + count(continuing),
+                    _ => 0,
+                })
+                .sum()
+        }
+
+        let mut module = crate::front::wgsl::parse_str(source).unwrap();
+        super::unroll_loops(&mut module, max_trip_count);
+        crate::valid::Validator::new(
+            crate::valid::ValidationFlags::all(),
+            crate::valid::Capabilities::all(),
+        )
+        .validate(&module)
+        .unwrap();
+        count(&module.entry_points[0].function.body)
+    }
+
+    #[test]
+    fn trip_counts() {
+        let source = "
+            var<private> total: i32;
+            var<private> n: u32;
+
+            @compute @workgroup_size(1)
+            fn main() {
+                // Four iterations.
+                for (var i = 0; i < 4; i++) {
+                    total += i;
+                }
+                // Three iterations, with a local in the body.
+                for (var j = 10u; j >= 3u; j -= 3u) {
+                    var k = j;
+                    total += i32(k);
+                }
+                // Two iterations, starting from the initializer.
+                var x = 0;
+                loop {
+                    if x >= 2 {
+                        break;
+                    }
+                    total += x;
+                    continuing {
+                        x += 1;
+                    }
+                }
+                // Too many iterations.
+                for (var m = 0; m < 100; m++) {
+                    total += m;
+                }
+                // An unknown number of iterations.
+                for (var m = 0u; m < n; m++) {
+                    total += 1;
+                }
+                // Leaves early.
+                for (var m = 0; m < 4; m++) {
+                    if total > 10 {
+                        break;
+                    }
+                }
+            }
+        ";
+        assert_eq!(loops_left(source, 16), 3);
+        assert_eq!(loops_left(source, 3), 4);
+        assert_eq!(loops_left(source, 0), 6);
+    }
+}
@@ -0,0 +1,131 @@
+/*!
+Visiting the nested blocks of a function body.
+
+Many passes only care about some kinds of statements, wherever they are.
+These functions find every block nested in a body, so that the passes
+don't each need their own recursion over [`Statement`].
+*/
+
+use crate::arena::{Handle, Range};
+use crate::{Block, Expression, Statement};
+
+/// Call `f` on every statement in `block`, including those in nested
+/// blocks.
+///
+/// Statements come block by block, in the order of [`for_each_block`].
+pub(crate) fn for_each_statement(block: &Block, f: &mut impl FnMut(&Statement)) {
+    for_each_block(block, &mut |block| {
+        for stmt in block.iter() {
+            f(stmt);
+        }
+    });
+}
+
+/// Call `f` on `block` and on every block nested in it, in the same order
+/// as [`for_each_block_mut`].
+pub(crate) fn for_each_block(block: &Block, f: &mut impl FnMut(&Block)) {
+    for stmt in block.iter() {
+        match *stmt {
+            Statement::Block(ref inner) => for_each_block(inner, f),
+            Statement::If {
+                ref accept,
+                ref reject,
+                ..
+            } => {
+                for_each_block(accept, f);
+                for_each_block(reject, f);
+            }
+            Statement::Switch { ref cases, .. } => {
+                for case in cases.iter() {
+                    for_each_block(&case.body, f);
+                }
+            }
+            Statement::Loop {
+                ref body,
+                ref continuing,
+                ..
+            } => {
+                for_each_block(body, f);
+                for_each_block(continuing, f);
+            }
+            _ => {}
+        }
+    }
+    f(block);
+}
+
+/// Call `f` on `block` and on every block nested in it.
+///
+/// Nested blocks are visited before the blocks that contain them, so `f`
+/// may wrap a block's statements in new blocks without seeing them again.
+pub(crate) fn for_each_block_mut(block: &mut Block, f: &mut impl FnMut(&mut Block)) {
+    for stmt in block.iter_mut() {
+        match *stmt {
+            Statement::Block(ref mut inner) => for_each_block_mut(inner, f),
+            Statement::If {
+                ref mut accept,
+                ref mut reject,
+                ..
+            } => {
+                for_each_block_mut(accept, f);
+                for_each_block_mut(reject, f);
+            }
+            Statement::Switch { ref mut cases, .. } => {
+                for case in cases.iter_mut() {
+                    for_each_block_mut(&mut case.body, f);
+                }
+            }
+            Statement::Loop {
+                ref mut body,
+                ref mut continuing,
+                ..
+            } => {
+                for_each_block_mut(body, f);
+                for_each_block_mut(continuing, f);
+            }
+            _ => {}
+        }
+    }
+    f(block);
+}
+
+/// Split the `Emit` statements in `block` and the blocks nested in it, so
+/// that they no longer cover the expressions `removed` marks.
+///
+/// A pass that replaces emitted expressions with ones that are never
+/// emitted, like [`Expression::Constant`] or [`Expression::Literal`], must
+/// take them out of their `Emit`s.
+pub(crate) fn remove_from_emits(block: &mut Block, removed: &[bool]) {
+    for_each_block_mut(block, &mut |block| {
+        let mut index = 0;
+        while index < block.len() {
+            let Statement::Emit(ref range) = block[index] else {
+                index += 1;
+                continue;
+            };
+            let mut emits = Vec::new();
+            let mut kept: Option<(Handle<Expression>, Handle<Expression>)> = None;
+            for handle in range.clone() {
+                if removed[handle.index()] {
+                    emits.extend(kept.take());
+                } else {
+                    let first = kept.map_or(handle, |(first, _)| first);
+                    kept = Some((first, handle));
+                }
+            }
+            emits.extend(kept);
+
+            let span = block
+                .span_iter()
+                .nth(index)
+                .map_or(crate::Span::UNDEFINED, |(_, &span)| span);
+            let mut replacement = Block::with_capacity(emits.len());
+            for (first, last) in emits {
+                replacement.push(Statement::Emit(Range::new_from_bounds(first, last)), span);
+            }
+            let count = replacement.len();
+            block.splice(index..index + 1, replacement);
+            index += count;
+        }
+    });
+}
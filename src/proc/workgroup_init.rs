@@ -0,0 +1,492 @@
+/*!
+Zero-initializing workgroup memory in the IR.
+
+WGSL requires [`WorkGroup`] variables to start out zeroed, but few target
+languages do it for us. Each backend has an option to emit the code itself,
+at the top of every compute entry point. [`zero_initialize_workgroup_memory`]
+adds that code to the module instead, so that every backend gets the same
+thing, and so do backends that have no such option.
+
+[`WorkGroup`]: crate::AddressSpace::WorkGroup
+*/
+
+use crate::arena::{Handle, Range, UniqueArena};
+use crate::valid::ModuleInfo;
+use crate::{
+    ArraySize, BinaryOperator, Binding, Block, BuiltIn, Expression, Function, FunctionArgument,
+    Literal, LocalVariable, RelationalFunction, ScalarKind, Span, Statement, Type, TypeInner,
+};
+
+/// Zero the [`WorkGroup`] variables each compute entry point in `module`
+/// uses, before anything else it does.
+///
+/// This adds the same code at the start of each entry point that the
+/// backends' `zero_initialize_workgroup_memory` options do: the first
+/// invocation of the workgroup stores zeros to each variable, and then all
+/// of them wait at a workgroup barrier. Entry points that don't take the
+/// [`LocalInvocationId`] built-in get a new argument for it. Atomics are
+/// zeroed one at a time, since they can't be stored as part of a larger
+/// value.
+///
+/// Once a module has been through this, the backends' own options should
+/// be turned off, so that the memory isn't zeroed twice.
+///
+/// `info` must be the result of validating `module`. The module should be
+/// validated again afterwards, to get a [`ModuleInfo`] that matches the new
+/// arenas.
+///
+/// [`WorkGroup`]: crate::AddressSpace::WorkGroup
+/// [`LocalInvocationId`]: BuiltIn::LocalInvocationId
+pub fn zero_initialize_workgroup_memory(module: &mut crate::Module, info: &ModuleInfo) {
+    for (index, ep) in module.entry_points.iter_mut().enumerate() {
+        if ep.stage != crate::ShaderStage::Compute {
+            continue;
+        }
+        let ep_info = info.get_entry_point(index);
+        let globals: Vec<_> = module
+            .global_variables
+            .iter()
+            .filter(|&(handle, var)| {
+                var.space == crate::AddressSpace::WorkGroup && !ep_info[handle].is_empty()
+            })
+            .map(|(handle, var)| (handle, var.ty))
+            .collect();
+        if globals.is_empty() {
+            continue;
+        }
+
+        let mut init = Initializer {
+            types: &mut module.types,
+            function: &mut ep.function,
+        };
+        let mut block = Block::new();
+        let local_id = init.local_invocation_id(&mut block);
+
+        let u32_vec3 = init.u32_vec3();
+        let origin = init.append(Expression::ZeroValue(u32_vec3));
+        let equal = init.emit(
+            Expression::Binary {
+                op: BinaryOperator::Equal,
+                left: local_id,
+                right: origin,
+            },
+            &mut block,
+        );
+        let condition = init.emit(
+            Expression::Relational {
+                fun: RelationalFunction::All,
+                argument: equal,
+            },
+            &mut block,
+        );
+
+        let mut accept = Block::new();
+        for (global, ty) in globals {
+            let pointer = init.append(Expression::GlobalVariable(global));
+            init.store_zero(pointer, ty, &mut accept);
+        }
+        block.push(
+            Statement::If {
+                condition,
+                accept,
+                reject: Block::new(),
+            },
+            Span::UNDEFINED,
+        );
+        block.push(
+            Statement::Barrier(crate::Barrier::WORK_GROUP),
+            Span::UNDEFINED,
+        );
+
+        let body = std::mem::take(&mut ep.function.body);
+        block.extend_block(body);
+        ep.function.body = block;
+    }
+}
+
+struct Initializer<'a> {
+    types: &'a mut UniqueArena<Type>,
+    function: &'a mut Function,
+}
+
+impl Initializer<'_> {
+    fn append(&mut self, expression: Expression) -> Handle<Expression> {
+        self.function
+            .expressions
+            .append(expression, Span::UNDEFINED)
+    }
+
+    fn emit(&mut self, expression: Expression, block: &mut Block) -> Handle<Expression> {
+        let handle = self.append(expression);
+        block.push(
+            Statement::Emit(Range::new_from_bounds(handle, handle)),
+            Span::UNDEFINED,
+        );
+        handle
+    }
+
+    fn scalar(&mut self, kind: ScalarKind, width: crate::Bytes) -> Handle<Type> {
+        self.types.insert(
+            Type {
+                name: None,
+                inner: TypeInner::Scalar { kind, width },
+            },
+            Span::UNDEFINED,
+        )
+    }
+
+    fn u32_vec3(&mut self) -> Handle<Type> {
+        self.types.insert(
+            Type {
+                name: None,
+                inner: TypeInner::Vector {
+                    size: crate::VectorSize::Tri,
+                    kind: ScalarKind::Uint,
+                    width: 4,
+                },
+            },
+            Span::UNDEFINED,
+        )
+    }
+
+    /// Return the entry point's local invocation id, adding an argument for
+    /// it if there is none.
+    fn local_invocation_id(&mut self, block: &mut Block) -> Handle<Expression> {
+        let binding = Some(Binding::BuiltIn(BuiltIn::LocalInvocationId));
+        let found = self
+            .function
+            .arguments
+            .iter()
+            .enumerate()
+            .find_map(|(index, argument)| {
+                if argument.binding == binding {
+                    return Some((index, None));
+                }
+                match self.types[argument.ty].inner {
+                    TypeInner::Struct { ref members, .. } => members
+                        .iter()
+                        .position(|member| member.binding == binding)
+                        .map(|member| (index, Some(member))),
+                    _ => None,
+                }
+            });
+        match found {
+            Some((index, None)) => return self.append(Expression::FunctionArgument(index as u32)),
+            Some((index, Some(member))) => {
+                let base = self.append(Expression::FunctionArgument(index as u32));
+                return self.emit(
+                    Expression::AccessIndex {
+                        base,
+                        index: member as u32,
+                    },
+                    block,
+                );
+            }
+            None => {}
+        }
+
+        let ty = self.u32_vec3();
+        self.function.arguments.push(FunctionArgument {
+            name: Some("local_invocation_id".to_string()),
+            ty,
+            binding,
+        });
+        let index = self.function.arguments.len() - 1;
+        self.append(Expression::FunctionArgument(index as u32))
+    }
+
+    /// Add statements to `block` that store zero to what `pointer` points
+    /// to, a value of type `ty`.
+    fn store_zero(&mut self, pointer: Handle<Expression>, ty: Handle<Type>, block: &mut Block) {
+        if !contains_atomic(self.types, ty) {
+            let value = self.append(Expression::ZeroValue(ty));
+            block.push(Statement::Store { pointer, value }, Span::UNDEFINED);
+            return;
+        }
+
+        match self.types[ty].inner {
+            TypeInner::Atomic { kind, width } => {
+                let scalar = self.scalar(kind, width);
+                let value = self.append(Expression::ZeroValue(scalar));
+                block.push(Statement::Store { pointer, value }, Span::UNDEFINED);
+            }
+            TypeInner::Struct { ref members, .. } => {
+                let members: Vec<_> = members.iter().map(|member| member.ty).collect();
+                for (index, member_ty) in members.into_iter().enumerate() {
+                    let member = self.emit(
+                        Expression::AccessIndex {
+                            base: pointer,
+                            index: index as u32,
+                        },
+                        block,
+                    );
+                    self.store_zero(member, member_ty, block);
+                }
+            }
+            TypeInner::Array {
+                base,
+                size: ArraySize::Constant(count),
+                ..
+            } => {
+                // Visit each element with a loop, rather than an access per
+                // element, which could be a lot of code.
+                let u32_ty = self.scalar(ScalarKind::Uint, 4);
+                let counter = self.function.local_variables.append(
+                    LocalVariable {
+                        name: Some("index".to_string()),
+                        ty: u32_ty,
+                        init: None,
+                    },
+                    Span::UNDEFINED,
+                );
+                // The counter is reset before each run of the loop, since an
+                // inner loop runs once for every element of the outer one.
+                let counter_pointer = self.append(Expression::LocalVariable(counter));
+                let start = self.append(Expression::Literal(Literal::U32(0)));
+                block.push(
+                    Statement::Store {
+                        pointer: counter_pointer,
+                        value: start,
+                    },
+                    Span::UNDEFINED,
+                );
+
+                let mut body = Block::new();
+                let index = self.emit(
+                    Expression::Load {
+                        pointer: counter_pointer,
+                    },
+                    &mut body,
+                );
+                let end = self.append(Expression::Literal(Literal::U32(count.get())));
+                let done = self.emit(
+                    Expression::Binary {
+                        op: BinaryOperator::GreaterEqual,
+                        left: index,
+                        right: end,
+                    },
+                    &mut body,
+                );
+                let mut accept = Block::new();
+                accept.push(Statement::Break, Span::UNDEFINED);
+                body.push(
+                    Statement::If {
+                        condition: done,
+                        accept,
+                        reject: Block::new(),
+                    },
+                    Span::UNDEFINED,
+                );
+                let element = self.emit(
+                    Expression::Access {
+                        base: pointer,
+                        index,
+                    },
+                    &mut body,
+                );
+                self.store_zero(element, base, &mut body);
+
+                let mut continuing = Block::new();
+                let index = self.emit(
+                    Expression::Load {
+                        pointer: counter_pointer,
+                    },
+                    &mut continuing,
+                );
+                let one = self.append(Expression::Literal(Literal::U32(1)));
+                let next = self.emit(
+                    Expression::Binary {
+                        op: BinaryOperator::Add,
+                        left: index,
+                        right: one,
+                    },
+                    &mut continuing,
+                );
+                continuing.push(
+                    Statement::Store {
+                        pointer: counter_pointer,
+                        value: next,
+                    },
+                    Span::UNDEFINED,
+                );
+
+                block.push(
+                    Statement::Loop {
+                        body,
+                        continuing,
+                        break_if: None,
+                    },
+                    Span::UNDEFINED,
+                );
+            }
+            // Workgroup variables can't be runtime-sized, and nothing else
+            // holds atomics.
+            _ => {}
+        }
+    }
+}
+
+fn contains_atomic(types: &UniqueArena<Type>, ty: Handle<Type>) -> bool {
+    match types[ty].inner {
+        TypeInner::Atomic { .. } => true,
+        TypeInner::Array { base, .. } => contains_atomic(types, base),
+        TypeInner::Struct { ref members, .. } => members
+            .iter()
+            .any(|member| contains_atomic(types, member.ty)),
+        _ => false,
+    }
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod tests {
+    use crate::test_util::Fixture;
+
+    fn barriers(block: &crate::Block) -> usize {
+        block
+            .iter()
+            .filter(|stmt| matches!(**stmt, crate::Statement::Barrier(_)))
+            .count()
+    }
+
+    #[test]
+    fn zero_initialize() {
+        let mut fixture = Fixture::wgsl(
+            "
+            struct Bins { counts: array<atomic<u32>, 16>, total: atomic<u32>, scale: f32 }
+            var<workgroup> bins: Bins;
+            var<workgroup> tile: array<vec4<f32>, 64>;
+            var<workgroup> unused: f32;
+
+            @compute @workgroup_size(64)
+            fn main(@builtin(local_invocation_index) index: u32) {
+                tile[index] = vec4(bins.scale);
+                atomicAdd(&bins.counts[index % 16u], 1u);
+            }
+
+            @compute @workgroup_size(1)
+            fn other() {}
+            ",
+        );
+
+        super::zero_initialize_workgroup_memory(&mut fixture.module, &fixture.info);
+        fixture.revalidate();
+        let module = &fixture.module;
+
+        let main = &module.entry_points[0].function;
+        assert_eq!(main.arguments.len(), 2);
+        assert_eq!(
+            main.arguments[1].binding,
+            Some(crate::Binding::BuiltIn(crate::BuiltIn::LocalInvocationId))
+        );
+        assert_eq!(barriers(&main.body), 1);
+        let (unused, _) = module.global_variables.iter().nth(2).unwrap();
+        assert!(!main
+            .expressions
+            .iter()
+            .any(|(_, expr)| *expr == crate::Expression::GlobalVariable(unused)));
+
+        // `other` uses no workgroup memory, so it is left alone.
+        let other = &module.entry_points[1].function;
+        assert!(other.arguments.is_empty());
+        assert_eq!(barriers(&other.body), 0);
+    }
+
+    #[test]
+    fn nested_arrays() {
+        use crate::{Expression, Literal, Statement};
+
+        let mut fixture = Fixture::wgsl(
+            "
+            var<workgroup> grid: array<array<atomic<u32>, 4>, 4>;
+
+            @compute @workgroup_size(16)
+            fn main(@builtin(local_invocation_index) index: u32) {
+                atomicAdd(&grid[index / 4u][index % 4u], 1u);
+            }
+            ",
+        );
+
+        super::zero_initialize_workgroup_memory(&mut fixture.module, &fixture.info);
+        fixture.revalidate();
+        let module = &fixture.module;
+
+        let main = &module.entry_points[0].function;
+        // Whether `stmt` sets a loop counter to zero, and if so, which.
+        let reset = |stmt: &Statement| match *stmt {
+            Statement::Store { pointer, value } => {
+                match (&main.expressions[pointer], &main.expressions[value]) {
+                    (
+                        &Expression::LocalVariable(counter),
+                        &Expression::Literal(Literal::U32(0)),
+                    ) => Some(counter),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        let loop_body = |block: &crate::Block| {
+            let position = block
+                .iter()
+                .position(|stmt| matches!(*stmt, Statement::Loop { .. }))
+                .unwrap();
+            match block[position] {
+                Statement::Loop { ref body, .. } => (reset(&block[position - 1]), body.clone()),
+                _ => unreachable!(),
+            }
+        };
+
+        let accept = match main.body[2] {
+            Statement::If { ref accept, .. } => accept,
+            ref other => panic!("expected the initialization, got {other:?}"),
+        };
+        // Each loop sets its own counter to zero right before it starts, so
+        // the inner loop runs again for every row.
+        let (outer_counter, outer) = loop_body(accept);
+        let (inner_counter, inner) = loop_body(&outer);
+        assert!(outer_counter.is_some());
+        assert!(inner_counter.is_some());
+        assert_ne!(outer_counter, inner_counter);
+        assert!(main
+            .local_variables
+            .iter()
+            .all(|(_, local)| local.init.is_none()));
+
+        // The innermost loop stores a zero `u32` to each atomic.
+        let u32_ty = module
+            .types
+            .get(&crate::Type {
+                name: None,
+                inner: crate::TypeInner::Scalar {
+                    kind: crate::ScalarKind::Uint,
+                    width: 4,
+                },
+            })
+            .unwrap();
+        assert!(inner.iter().any(|stmt| matches!(
+            *stmt,
+            Statement::Store { pointer, value }
+                if matches!(main.expressions[pointer], Expression::Access { .. })
+                    && main.expressions[value] == Expression::ZeroValue(u32_ty)
+        )));
+    }
+
+    #[test]
+    fn existing_local_invocation_id() {
+        let mut fixture = Fixture::wgsl(
+            "
+            struct Input { @builtin(local_invocation_id) id: vec3<u32> }
+            var<workgroup> value: u32;
+
+            @compute @workgroup_size(8)
+            fn main(input: Input) {
+                value = input.id.x;
+            }
+            ",
+        );
+
+        super::zero_initialize_workgroup_memory(&mut fixture.module, &fixture.info);
+        fixture.revalidate();
+        let module = &fixture.module;
+        assert_eq!(module.entry_points[0].function.arguments.len(), 1);
+    }
+}
@@ -0,0 +1,474 @@
+/*!
+Specializing compute entry points for several workgroup sizes.
+
+A compute kernel is often dispatched with different workgroup sizes on
+different hardware. Rather than parsing and validating the same source once
+for each size, [`specialize_workgroup_sizes`] copies a validated entry point
+once per size, within the same module.
+*/
+
+use super::scalarize::adjust_expression;
+use super::walk::remove_from_emits;
+use crate::arena::{Arena, Handle};
+use crate::{Binding, BuiltIn, Expression, Function, GlobalVariable, Span, Type, TypeInner};
+
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum WorkgroupSizeError {
+    #[error("The module has no compute entry point named {0:?}")]
+    MissingEntryPoint(String),
+    #[error("Workgroup size {0:?} has a zero dimension")]
+    ZeroSize([u32; 3]),
+    #[error("The module already has an entry point named {0:?}")]
+    NameTaken(String),
+    #[error("Global {0:?} isn't a workgroup array with a whole number of elements per invocation")]
+    NotPerInvocation(Handle<GlobalVariable>),
+    #[error("Global {0:?} is used by functions other than the entry point")]
+    UsedByFunction(Handle<GlobalVariable>),
+}
+
+/// Add a copy of the compute entry point `entry_point` to `module` for each
+/// of `sizes`, and return the indices of the new entry points.
+///
+/// Each copy is named after the original and its workgroup size, as in
+/// `main_8x8x1`, and starts out with the original's body. Where that reads
+/// the [`WorkGroupSize`] built-in through an argument, the copy uses its own
+/// size instead, and no longer takes the argument: components of the size
+/// become [`Literal`]s, and the arithmetic on them is folded, so that values
+/// derived from the size are constant in the copy too. Whole-vector uses
+/// read a module-scope [`Constant`] holding the size. The original entry
+/// point is left as it is.
+///
+/// Each of `per_invocation` must be a workgroup array with the same number
+/// of elements for each invocation of the original, like a tile with one
+/// element per invocation. Each copy gets its own global in place of it,
+/// named after the original and the size like the entry point, with as
+/// many elements per invocation of the copy. Only the entry point itself
+/// may use these globals, since the functions it calls aren't copied.
+///
+/// If any of `sizes` is invalid, would give a copy a name that is already
+/// taken, or any of `per_invocation` can't be resized, this returns an
+/// error before changing `module`. The module should be validated again
+/// afterwards, to get a [`ModuleInfo`] that covers the new entry points.
+///
+/// [`WorkGroupSize`]: BuiltIn::WorkGroupSize
+/// [`Literal`]: Expression::Literal
+/// [`Constant`]: crate::Constant
+/// [`ModuleInfo`]: crate::valid::ModuleInfo
+pub fn specialize_workgroup_sizes(
+    module: &mut crate::Module,
+    entry_point: &str,
+    sizes: &[[u32; 3]],
+    per_invocation: &[Handle<GlobalVariable>],
+) -> Result<Vec<usize>, WorkgroupSizeError> {
+    let original = module
+        .entry_points
+        .iter()
+        .position(|ep| ep.stage == crate::ShaderStage::Compute && ep.name == entry_point)
+        .ok_or_else(|| WorkgroupSizeError::MissingEntryPoint(entry_point.to_string()))?;
+
+    let mut names = Vec::with_capacity(sizes.len());
+    for &size in sizes {
+        if size.contains(&0) {
+            return Err(WorkgroupSizeError::ZeroSize(size));
+        }
+        let [x, y, z] = size;
+        let name = format!("{entry_point}_{x}x{y}x{z}");
+        let taken = module.entry_points.iter().any(|ep| ep.name == name);
+        if taken || names.contains(&name) {
+            return Err(WorkgroupSizeError::NameTaken(name));
+        }
+        names.push(name);
+    }
+
+    let invocations: u32 = module.entry_points[original]
+        .workgroup_size
+        .iter()
+        .product();
+    let mut arrays = Vec::with_capacity(per_invocation.len());
+    for &global in per_invocation {
+        let var = &module.global_variables[global];
+        let per_invocation_length = match module.types[var.ty].inner {
+            TypeInner::Array {
+                size: crate::ArraySize::Constant(length),
+                ..
+            } if var.space == crate::AddressSpace::WorkGroup && length.get() % invocations == 0 => {
+                length.get() / invocations
+            }
+            _ => return Err(WorkgroupSizeError::NotPerInvocation(global)),
+        };
+        let used = module.functions.iter().any(|(_, function)| {
+            function
+                .expressions
+                .iter()
+                .any(|(_, expression)| *expression == Expression::GlobalVariable(global))
+        });
+        if used {
+            return Err(WorkgroupSizeError::UsedByFunction(global));
+        }
+        arrays.push((global, per_invocation_length));
+    }
+
+    let mut indices = Vec::with_capacity(sizes.len());
+    for (&workgroup_size, name) in sizes.iter().zip(names) {
+        let ep = &module.entry_points[original];
+        let mut function = copy_function(&ep.function);
+        let early_depth_test = ep.early_depth_test;
+        substitute_workgroup_size(module, &mut function, workgroup_size);
+        let [x, y, z] = workgroup_size;
+        for &(global, per_invocation_length) in arrays.iter() {
+            let length = per_invocation_length * x * y * z;
+            let copy = resize_array(module, global, length, &format!("{x}x{y}x{z}"));
+            for (_, expression) in function.expressions.iter_mut() {
+                if *expression == Expression::GlobalVariable(global) {
+                    *expression = Expression::GlobalVariable(copy);
+                }
+            }
+        }
+
+        indices.push(module.entry_points.len());
+        module.entry_points.push(crate::EntryPoint {
+            name,
+            stage: crate::ShaderStage::Compute,
+            early_depth_test,
+            workgroup_size,
+            function,
+        });
+    }
+    Ok(indices)
+}
+
+/// Make a copy of `function`.
+///
+/// `Function` is only `Clone` with the `clone` feature, but everything it
+/// holds is.
+fn copy_function(function: &Function) -> Function {
+    Function {
+        name: function.name.clone(),
+        arguments: function.arguments.clone(),
+        result: function.result.clone(),
+        local_variables: copy_arena(&function.local_variables),
+        expressions: copy_arena(&function.expressions),
+        named_expressions: function.named_expressions.clone(),
+        body: function.body.clone(),
+    }
+}
+
+fn copy_arena<T: Clone>(arena: &Arena<T>) -> Arena<T> {
+    let mut copy = Arena::new();
+    for (handle, item) in arena.iter() {
+        copy.append(item.clone(), arena.get_span(handle));
+    }
+    copy
+}
+
+/// Add a copy of the workgroup array `global` to `module` with `length`
+/// elements, named after the original with `suffix`.
+fn resize_array(
+    module: &mut crate::Module,
+    global: Handle<GlobalVariable>,
+    length: u32,
+    suffix: &str,
+) -> Handle<GlobalVariable> {
+    let var = &module.global_variables[global];
+    let span = module.global_variables.get_span(global);
+    let ty = &module.types[var.ty];
+    let inner = match ty.inner {
+        TypeInner::Array { base, stride, .. } => TypeInner::Array {
+            base,
+            size: crate::ArraySize::Constant(std::num::NonZeroU32::new(length).unwrap()),
+            stride,
+        },
+        _ => unreachable!(),
+    };
+    let name = ty.name.clone();
+    let copy = GlobalVariable {
+        name: var.name.as_ref().map(|name| format!("{name}_{suffix}")),
+        space: var.space,
+        binding: var.binding.clone(),
+        ty: module.types.insert(Type { name, inner }, Span::UNDEFINED),
+        init: var.init,
+    };
+    module.global_variables.append(copy, span)
+}
+
+/// Replace `function`'s [`WorkGroupSize`] arguments with `workgroup_size`,
+/// and fold what's computed from it.
+///
+/// [`WorkGroupSize`]: BuiltIn::WorkGroupSize
+fn substitute_workgroup_size(
+    module: &mut crate::Module,
+    function: &mut Function,
+    workgroup_size: [u32; 3],
+) {
+    let removed: Vec<bool> = function
+        .arguments
+        .iter()
+        .map(|arg| arg.binding == Some(Binding::BuiltIn(BuiltIn::WorkGroupSize)))
+        .collect();
+    if !removed.contains(&true) {
+        return;
+    }
+
+    let ty = module.types.insert(
+        Type {
+            name: None,
+            inner: TypeInner::Vector {
+                size: crate::VectorSize::Tri,
+                kind: crate::ScalarKind::Uint,
+                width: 4,
+            },
+        },
+        Span::UNDEFINED,
+    );
+    let components = workgroup_size
+        .iter()
+        .map(|&size| {
+            let literal = Expression::Literal(crate::Literal::U32(size));
+            module.const_expressions.append(literal, Span::UNDEFINED)
+        })
+        .collect();
+    let init = module
+        .const_expressions
+        .append(Expression::Compose { ty, components }, Span::UNDEFINED);
+    let constant = module.constants.append(
+        crate::Constant {
+            name: None,
+            r#override: crate::Override::None,
+            ty,
+            init,
+        },
+        Span::UNDEFINED,
+    );
+
+    // Components of the size become literals, and so does arithmetic on
+    // them. Literals are never emitted, so these come out of their `Emit`s.
+    let mut replaced = vec![false; function.expressions.len()];
+    let handles: Vec<_> = function
+        .expressions
+        .iter()
+        .map(|(handle, _)| handle)
+        .collect();
+    for handle in handles {
+        let literal = match function.expressions[handle] {
+            Expression::AccessIndex { base, index } => match function.expressions[base] {
+                Expression::FunctionArgument(argument) if removed[argument as usize] => {
+                    workgroup_size
+                        .get(index as usize)
+                        .map(|&size| crate::Literal::U32(size))
+                }
+                _ => None,
+            },
+            ref expression @ (Expression::Unary { .. }
+            | Expression::Binary { .. }
+            | Expression::As { .. }) => {
+                let mut derived = false;
+                adjust_expression(&mut expression.clone(), &mut |operand| {
+                    derived |= replaced[operand.index()]
+                });
+                if derived {
+                    module
+                        .to_ctx()
+                        .eval_expr_to_literal_from(handle, &function.expressions)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+        if let Some(literal) = literal {
+            function.expressions[handle] = Expression::Literal(literal);
+            function.named_expressions.shift_remove(&handle);
+            replaced[handle.index()] = true;
+        }
+    }
+    remove_from_emits(&mut function.body, &replaced);
+
+    // Both argument and constant expressions are outside any `Emit`, so
+    // one can stand in for the other.
+    for (_, expression) in function.expressions.iter_mut() {
+        if let Expression::FunctionArgument(index) = *expression {
+            let index = index as usize;
+            *expression = if removed[index] {
+                Expression::Constant(constant)
+            } else {
+                let before = removed[..index].iter().filter(|&&removed| removed).count();
+                Expression::FunctionArgument((index - before) as u32)
+            };
+        }
+    }
+    let mut removed = removed.into_iter();
+    function
+        .arguments
+        .retain(|_| !removed.next().unwrap_or(false));
+}
+
+#[cfg(all(test, any(feature = "glsl-in", feature = "wgsl-in")))]
+mod tests {
+    use super::{specialize_workgroup_sizes, WorkgroupSizeError};
+    use crate::{Binding, BuiltIn};
+
+    #[cfg(feature = "glsl-in")]
+    #[test]
+    fn specialize() {
+        let mut module = crate::front::glsl::Frontend::default()
+            .parse(
+                &crate::front::glsl::Options::from(crate::ShaderStage::Compute),
+                "
+                #version 450
+                layout(local_size_x = 64) in;
+                layout(std430, binding = 0) buffer Data { uint data[]; };
+
+                void main() {
+                    data[gl_LocalInvocationID.x] = gl_WorkGroupSize.x;
+                }
+                ",
+            )
+            .unwrap();
+        let validate = |module: &crate::Module| {
+            crate::valid::Validator::new(
+                crate::valid::ValidationFlags::all(),
+                crate::valid::Capabilities::default(),
+            )
+            .validate(module)
+            .unwrap()
+        };
+        validate(&module);
+        let takes_workgroup_size = |ep: &crate::EntryPoint| {
+            ep.function
+                .arguments
+                .iter()
+                .any(|arg| arg.binding == Some(Binding::BuiltIn(BuiltIn::WorkGroupSize)))
+        };
+        assert!(takes_workgroup_size(&module.entry_points[0]));
+
+        let indices =
+            specialize_workgroup_sizes(&mut module, "main", &[[32, 1, 1], [8, 8, 1]], &[]).unwrap();
+        assert_eq!(indices, [1, 2]);
+        validate(&module);
+
+        let eps: Vec<_> = module
+            .entry_points
+            .iter()
+            .map(|ep| (ep.name.as_str(), ep.workgroup_size))
+            .collect();
+        assert_eq!(
+            eps,
+            [
+                ("main", [64, 1, 1]),
+                ("main_32x1x1", [32, 1, 1]),
+                ("main_8x8x1", [8, 8, 1]),
+            ]
+        );
+        assert!(takes_workgroup_size(&module.entry_points[0]));
+        assert!(!takes_workgroup_size(&module.entry_points[1]));
+        assert!(!takes_workgroup_size(&module.entry_points[2]));
+        let original = &module.entry_points[0].function;
+        let copy = &module.entry_points[1].function;
+        assert_eq!(copy.arguments.len() + 1, original.arguments.len());
+        assert_eq!(copy.expressions.len(), original.expressions.len());
+
+        assert_eq!(
+            specialize_workgroup_sizes(&mut module, "main", &[[4, 4, 4], [8, 8, 1]], &[]),
+            Err(WorkgroupSizeError::NameTaken("main_8x8x1".to_string()))
+        );
+        assert_eq!(
+            specialize_workgroup_sizes(&mut module, "main", &[[16, 0, 1]], &[]),
+            Err(WorkgroupSizeError::ZeroSize([16, 0, 1]))
+        );
+        assert_eq!(
+            specialize_workgroup_sizes(&mut module, "other", &[[1, 1, 1]], &[]),
+            Err(WorkgroupSizeError::MissingEntryPoint("other".to_string()))
+        );
+        assert_eq!(module.entry_points.len(), 3);
+    }
+
+    #[cfg(feature = "wgsl-in")]
+    #[test]
+    fn fold_and_resize() {
+        use crate::Expression;
+
+        // WGSL has no workgroup size built-in, so this borrows another
+        // argument for it.
+        let mut module = crate::front::wgsl::parse_str(
+            "
+            var<workgroup> tile: array<u32, 128>;
+            @group(0) @binding(0) var<storage, read_write> data: array<u32>;
+
+            @compute @workgroup_size(64)
+            fn main(
+                @builtin(local_invocation_id) id: vec3<u32>,
+                @builtin(num_workgroups) size: vec3<u32>,
+            ) {
+                tile[id.x * 2u] = size.x * 2u;
+                workgroupBarrier();
+                data[id.x] = tile[id.x];
+            }
+            ",
+        )
+        .unwrap();
+        module.entry_points[0].function.arguments[1].binding =
+            Some(Binding::BuiltIn(BuiltIn::WorkGroupSize));
+        let validate = |module: &crate::Module| {
+            crate::valid::Validator::new(
+                crate::valid::ValidationFlags::all(),
+                crate::valid::Capabilities::default(),
+            )
+            .validate(module)
+            .unwrap()
+        };
+        validate(&module);
+        let global = |name| {
+            module
+                .global_variables
+                .iter()
+                .find(|&(_, var)| var.name.as_deref() == Some(name))
+                .unwrap()
+                .0
+        };
+        let (tile, data) = (global("tile"), global("data"));
+        let globals = module.global_variables.len();
+
+        assert_eq!(
+            specialize_workgroup_sizes(&mut module, "main", &[[2, 2, 2]], &[data]),
+            Err(WorkgroupSizeError::NotPerInvocation(data))
+        );
+        specialize_workgroup_sizes(&mut module, "main", &[[32, 1, 1], [8, 8, 1]], &[tile]).unwrap();
+        validate(&module);
+
+        // `size.x * 2u` is folded.
+        let original = &module.entry_points[0].function;
+        let copy = &module.entry_points[1].function;
+        let folded: Vec<_> = original
+            .expressions
+            .iter()
+            .zip(copy.expressions.iter())
+            .filter(|&((_, before), _)| matches!(*before, Expression::Binary { .. }))
+            .map(|(_, (_, after))| after.clone())
+            .collect();
+        assert_eq!(folded.len(), 2);
+        assert!(matches!(folded[0], Expression::Binary { .. }));
+        assert_eq!(folded[1], Expression::Literal(crate::Literal::U32(64)));
+
+        // `tile` keeps two elements per invocation.
+        let lengths: Vec<_> = module
+            .global_variables
+            .iter()
+            .skip(globals)
+            .map(|(_, var)| {
+                let length = match module.types[var.ty].inner {
+                    crate::TypeInner::Array {
+                        size: crate::ArraySize::Constant(length),
+                        ..
+                    } => length.get(),
+                    _ => 0,
+                };
+                (var.name.as_deref().unwrap(), length)
+            })
+            .collect();
+        assert_eq!(lengths, [("tile_32x1x1", 64), ("tile_8x8x1", 128)]);
+        assert!(copy
+            .expressions
+            .iter()
+            .all(|(_, expression)| *expression != Expression::GlobalVariable(tile)));
+    }
+}
@@ -0,0 +1,378 @@
+/*!
+Describing a module's resources and entry point interfaces.
+
+Pipeline builders need to know which resources a shader expects at which
+bindings, what each entry point reads from its vertex buffers, and how
+large its push constants are. [`reflect`] gathers this from a validated
+module, so that callers don't need to walk the IR themselves.
+*/
+
+use crate::arena::Handle;
+use crate::valid::{BindingType, GlobalUse, ModuleInfo};
+use crate::{
+    ArraySize, Binding, Bytes, GlobalVariable, ResourceBinding, ScalarKind, ShaderStage, Type,
+    TypeInner, VectorSize,
+};
+
+/// What a module exposes to the pipeline that uses it.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+pub struct Reflection {
+    /// Every global variable with a resource binding, in declaration order.
+    pub resources: Vec<Resource>,
+
+    /// The interface of each entry point, in the order of
+    /// [`Module::entry_points`].
+    ///
+    /// [`Module::entry_points`]: crate::Module::entry_points
+    pub entry_points: Vec<EntryPointReflection>,
+}
+
+/// A global variable bound to a bind group and binding.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+pub struct Resource {
+    pub global: Handle<GlobalVariable>,
+    pub name: Option<String>,
+    pub binding: ResourceBinding,
+
+    /// The kind of resource the pipeline layout must provide here.
+    ///
+    /// For buffers, `min_size` is the size of the variable's type, or of
+    /// its fixed-size prefix if it ends with a runtime-sized array.
+    pub ty: BindingType,
+
+    /// The number of elements, if the variable is a binding array.
+    pub count: Option<ArraySize>,
+}
+
+/// The interface of one entry point.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+pub struct EntryPointReflection {
+    pub name: String,
+    pub stage: ShaderStage,
+
+    /// The workgroup size of a compute shader, or zeros for other stages.
+    pub workgroup_size: [u32; 3],
+
+    /// The resources this entry point uses, and how.
+    pub resources: Vec<ResourceUse>,
+
+    /// The location-bound inputs of a vertex shader, by increasing
+    /// location. This is empty for other stages.
+    pub vertex_inputs: Vec<VertexInput>,
+
+    /// The push constants this entry point uses, if any.
+    pub push_constants: Option<PushConstantRange>,
+}
+
+/// An entry point's use of a resource.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+pub struct ResourceUse {
+    /// The index of the resource in [`Reflection::resources`].
+    pub resource: usize,
+    pub usage: GlobalUse,
+}
+
+/// A vertex attribute a vertex shader reads.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+pub struct VertexInput {
+    pub location: u32,
+
+    /// The name of the argument or struct member that receives it.
+    pub name: Option<String>,
+
+    /// The format the shader expects: a scalar, or a vector of `size`.
+    pub kind: ScalarKind,
+    pub width: Bytes,
+    pub size: Option<VectorSize>,
+}
+
+/// The push constants an entry point reads.
+///
+/// Naga modules have at most one push constant variable per entry point,
+/// so the range always starts at offset zero.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+pub struct PushConstantRange {
+    pub global: Handle<GlobalVariable>,
+
+    /// The size of the variable's type, in bytes.
+    pub size: u32,
+}
+
+/// Describe the resources and entry point interfaces of `module`.
+///
+/// `info` must be the result of validating `module`.
+pub fn reflect(module: &crate::Module, info: &ModuleInfo) -> Reflection {
+    let mut layouter = crate::proc::Layouter::default();
+    // The module has been validated, so its types can be laid out.
+    layouter.update(module.to_ctx()).unwrap();
+
+    let resources: Vec<Resource> = module
+        .global_variables
+        .iter()
+        .filter_map(|(handle, var)| {
+            let binding = var.binding.clone()?;
+            let (base, count) = match module.types[var.ty].inner {
+                TypeInner::BindingArray { base, size } => (base, Some(size)),
+                _ => (var.ty, None),
+            };
+            Some(Resource {
+                global: handle,
+                name: var.name.clone(),
+                binding,
+                ty: crate::valid::declared_binding_type(module, &layouter, var.space, base),
+                count,
+            })
+        })
+        .collect();
+
+    let entry_points = module
+        .entry_points
+        .iter()
+        .enumerate()
+        .map(|(index, ep)| {
+            let ep_info = info.get_entry_point(index);
+            let used = resources
+                .iter()
+                .enumerate()
+                .filter(|&(_, resource)| !ep_info[resource.global].is_empty())
+                .map(|(index, resource)| ResourceUse {
+                    resource: index,
+                    usage: ep_info[resource.global],
+                })
+                .collect();
+            let push_constants = module
+                .global_variables
+                .iter()
+                .find(|&(handle, var)| {
+                    var.space == crate::AddressSpace::PushConstant && !ep_info[handle].is_empty()
+                })
+                .map(|(handle, var)| PushConstantRange {
+                    global: handle,
+                    size: layouter[var.ty].size,
+                });
+            let vertex_inputs = match ep.stage {
+                ShaderStage::Vertex => vertex_inputs(module, &ep.function),
+                ShaderStage::Fragment | ShaderStage::Compute => Vec::new(),
+            };
+            EntryPointReflection {
+                name: ep.name.clone(),
+                stage: ep.stage,
+                workgroup_size: ep.workgroup_size,
+                resources: used,
+                vertex_inputs,
+                push_constants,
+            }
+        })
+        .collect();
+
+    Reflection {
+        resources,
+        entry_points,
+    }
+}
+
+/// Return the location-bound arguments of `function`, and the
+/// location-bound members of its struct arguments, by increasing location.
+fn vertex_inputs(module: &crate::Module, function: &crate::Function) -> Vec<VertexInput> {
+    let mut inputs = Vec::new();
+    let mut push = |binding: &Option<Binding>, name: &Option<String>, ty: Handle<Type>| {
+        let location = match *binding {
+            Some(Binding::Location { location, .. }) => location,
+            _ => return,
+        };
+        let (kind, width, size) = match module.types[ty].inner {
+            TypeInner::Scalar { kind, width } => (kind, width, None),
+            TypeInner::Vector { size, kind, width } => (kind, width, Some(size)),
+            // The validator only allows scalars and vectors at locations.
+            _ => return,
+        };
+        inputs.push(VertexInput {
+            location,
+            name: name.clone(),
+            kind,
+            width,
+            size,
+        });
+    };
+    for argument in function.arguments.iter() {
+        match argument.binding {
+            Some(_) => push(&argument.binding, &argument.name, argument.ty),
+            None => {
+                if let TypeInner::Struct { ref members, .. } = module.types[argument.ty].inner {
+                    for member in members {
+                        push(&member.binding, &member.name, member.ty);
+                    }
+                }
+            }
+        }
+    }
+    inputs.sort_by_key(|input| input.location);
+    inputs
+}
+
+#[cfg(all(test, feature = "wgsl-in"))]
+mod tests {
+    use super::{PushConstantRange, VertexInput};
+    use crate::valid::{BindingType, GlobalUse};
+
+    fn reflect(source: &str) -> (crate::Module, super::Reflection) {
+        let module = crate::front::wgsl::parse_str(source).unwrap();
+        let info = crate::valid::Validator::new(
+            crate::valid::ValidationFlags::all(),
+            crate::valid::Capabilities::all(),
+        )
+        .validate(&module)
+        .unwrap();
+        let reflection = super::reflect(&module, &info);
+        (module, reflection)
+    }
+
+    #[test]
+    fn resources() {
+        let (_, reflection) = reflect(
+            "
+            struct Globals { scale: vec4<f32>, count: u32 }
+            @group(0) @binding(0) var<uniform> globals: Globals;
+            @group(0) @binding(1) var<storage, read_write> data: array<f32>;
+            @group(1) @binding(3) var tex: texture_2d<f32>;
+            @group(1) @binding(4) var samp: sampler;
+
+            @compute @workgroup_size(8, 4)
+            fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+                data[id.x] = data[id.x] * globals.scale.x;
+            }
+
+            @fragment
+            fn fs() -> @location(0) vec4<f32> {
+                return textureSample(tex, samp, vec2(0.5));
+            }
+            ",
+        );
+
+        let bindings: Vec<_> = reflection
+            .resources
+            .iter()
+            .map(|r| (r.name.as_deref(), r.binding.group, r.binding.binding, r.ty))
+            .collect();
+        assert_eq!(
+            bindings,
+            [
+                (
+                    Some("globals"),
+                    0,
+                    0,
+                    BindingType::UniformBuffer { min_size: Some(32) }
+                ),
+                (
+                    Some("data"),
+                    0,
+                    1,
+                    BindingType::StorageBuffer {
+                        access: crate::StorageAccess::LOAD | crate::StorageAccess::STORE,
+                        min_size: Some(4)
+                    }
+                ),
+                (
+                    Some("tex"),
+                    1,
+                    3,
+                    BindingType::Image {
+                        dim: crate::ImageDimension::D2,
+                        arrayed: false,
+                        class: crate::ImageClass::Sampled {
+                            kind: crate::ScalarKind::Float,
+                            multi: false
+                        }
+                    }
+                ),
+                (
+                    Some("samp"),
+                    1,
+                    4,
+                    BindingType::Sampler { comparison: false }
+                ),
+            ]
+        );
+
+        let compute = &reflection.entry_points[0];
+        assert_eq!(compute.workgroup_size, [8, 4, 1]);
+        let uses: Vec<_> = compute
+            .resources
+            .iter()
+            .map(|u| (u.resource, u.usage))
+            .collect();
+        assert_eq!(
+            uses,
+            [
+                (0, GlobalUse::READ),
+                (1, GlobalUse::READ | GlobalUse::WRITE),
+            ]
+        );
+
+        let fragment = &reflection.entry_points[1];
+        assert_eq!(fragment.workgroup_size, [0; 3]);
+        let used: Vec<_> = fragment.resources.iter().map(|u| u.resource).collect();
+        assert_eq!(used, [2, 3]);
+    }
+
+    #[test]
+    fn vertex_inputs_and_push_constants() {
+        let (module, reflection) = reflect(
+            "
+            struct Instance {
+                @location(5) offset: vec2<f32>,
+                @location(2) index: u32,
+            }
+            struct Constants { transform: mat4x4<f32> }
+            var<push_constant> constants: Constants;
+
+            @vertex
+            fn vs(@location(0) position: vec3<f32>, instance: Instance) -> @builtin(position) vec4<f32> {
+                return constants.transform * vec4(position.xy + instance.offset, position.z, 1.0);
+            }
+            ",
+        );
+
+        let vs = &reflection.entry_points[0];
+        let inputs: Vec<_> = vs
+            .vertex_inputs
+            .iter()
+            .map(|input| (input.location, input.name.as_deref()))
+            .collect();
+        assert_eq!(
+            inputs,
+            [
+                (0, Some("position")),
+                (2, Some("index")),
+                (5, Some("offset"))
+            ]
+        );
+        assert_eq!(
+            vs.vertex_inputs[1],
+            VertexInput {
+                location: 2,
+                name: Some("index".to_string()),
+                kind: crate::ScalarKind::Uint,
+                width: 4,
+                size: None,
+            }
+        );
+        assert_eq!(vs.vertex_inputs[0].size, Some(crate::VectorSize::Tri));
+
+        let (constants, _) = module.global_variables.iter().next().unwrap();
+        assert_eq!(
+            vs.push_constants,
+            Some(PushConstantRange {
+                global: constants,
+                size: 64,
+            })
+        );
+        assert!(reflection.resources.is_empty());
+    }
+}
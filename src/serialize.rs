@@ -0,0 +1,451 @@
+/*!
+A binary encoding of [`Module`]s.
+
+Build systems can use this to cache the output of a front end, and load the
+pre-parsed IR at runtime without the front end itself:
+
+```ignore
+let bytes = naga::serialize::to_bytes(&module)?;
+// ... later, perhaps in another process ...
+let module = naga::serialize::from_bytes(&bytes)?;
+```
+
+The encoding starts with the bytes `NAGA` and the little-endian
+[`FORMAT_VERSION`] the module was written with. [`from_bytes`] rejects
+anything written with a version it doesn't know, so a stale cache shows up
+as [`Error::UnsupportedVersion`] rather than as a garbled module. The
+version changes whenever the IR does, so the encoding is only meant to be
+read by the same Naga release that wrote it.
+
+Unlike the `serialize` feature's serde implementations, which skip spans
+and [`Block::locals`], this keeps both: the spans of all arena entries and
+statements, with the [`SourceId`]s of the files they refer to, so errors
+found in a loaded module still point into the original source, and the
+locals scoped to each block. Version 1 of the encoding predates source ids;
+its spans are read as referring to [`SourceId::PRIMARY`]. Versions 1 and 2
+predate block locals; their blocks are read with none. Version 3 predates
+the statements a local is scoped to; its locals are read as in scope for
+the whole block. Versions 1 to 4 predate [`Module::diagnostic_filters`];
+their modules are read with none. When the `span`
+feature is disabled, spans are written as [`Span::UNDEFINED`] and ignored
+when read.
+*/
+
+use crate::proc::walk::{for_each_block, for_each_block_mut};
+use crate::{Arena, Block, Function, Handle, LocalVariable, Module, ScopedLocal, SourceId, Span};
+
+/// The version of the encoding [`to_bytes`] writes.
+pub const FORMAT_VERSION: u32 = 5;
+
+const MAGIC: &[u8; 4] = b"NAGA";
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("the data is not a serialized Naga module")]
+    BadMagic,
+    #[error("the module was serialized with format version {0}, but this Naga reads versions 1 to {FORMAT_VERSION}")]
+    UnsupportedVersion(u32),
+    #[error("the serialized module is corrupt: {0}")]
+    Encoding(#[from] bincode::Error),
+    #[error("the serialized module has the wrong number of spans")]
+    SpanCountMismatch,
+    #[error("the serialized module has the wrong number of blocks")]
+    BlockCountMismatch,
+}
+
+/// A span's start, end and source id.
+type EncodedSpan = (u32, u32, u32);
+
+/// The [`Block::locals`] of each block.
+type EncodedLocals = Vec<Vec<ScopedLocal>>;
+
+#[derive(serde::Serialize)]
+struct Encoded<'a> {
+    module: &'a Module,
+    spans: Vec<EncodedSpan>,
+    locals: EncodedLocals,
+}
+
+#[derive(serde::Deserialize)]
+struct Decoded<L = ScopedLocal, M = Module> {
+    module: M,
+    spans: Vec<EncodedSpan>,
+    locals: Vec<Vec<L>>,
+}
+
+/// What versions 1 and 2 hold: they predate block locals.
+#[derive(serde::Deserialize)]
+struct DecodedWithoutLocals<S> {
+    module: ModuleWithoutFilters,
+    spans: Vec<S>,
+}
+
+/// A [`Module`] as versions 1 to 4 hold it: they predate
+/// [`Module::diagnostic_filters`].
+#[derive(serde::Deserialize)]
+struct ModuleWithoutFilters {
+    types: crate::UniqueArena<crate::Type>,
+    special_types: crate::SpecialTypes,
+    constants: Arena<crate::Constant>,
+    global_variables: Arena<crate::GlobalVariable>,
+    const_expressions: Arena<crate::Expression>,
+    functions: Arena<Function>,
+    entry_points: Vec<crate::EntryPoint>,
+}
+
+impl From<ModuleWithoutFilters> for Module {
+    fn from(module: ModuleWithoutFilters) -> Self {
+        Module {
+            types: module.types,
+            special_types: module.special_types,
+            constants: module.constants,
+            global_variables: module.global_variables,
+            const_expressions: module.const_expressions,
+            functions: module.functions,
+            entry_points: module.entry_points,
+            diagnostic_filters: Vec::new(),
+        }
+    }
+}
+
+/// Encode `module`, with its spans and the locals of its blocks.
+pub fn to_bytes(module: &Module) -> Result<Vec<u8>, Error> {
+    let mut spans = Vec::new();
+    let mut locals = Vec::new();
+    collect_spans(
+        module,
+        &mut |span| {
+            let (start, end) = match span.to_range() {
+                Some(range) => (range.start as u32, range.end as u32),
+                None => (0, 0),
+            };
+            spans.push((start, end, span.source().index()));
+        },
+        &mut |block| locals.push(block.locals().to_vec()),
+    );
+
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    bincode::serialize_into(
+        &mut bytes,
+        &Encoded {
+            module,
+            spans,
+            locals,
+        },
+    )?;
+    Ok(bytes)
+}
+
+/// Decode a module encoded by [`to_bytes`].
+///
+/// The module is not validated: callers that didn't write `bytes`
+/// themselves should validate it before use. Modules written by versions
+/// before 3 have no [`Block::locals`].
+pub fn from_bytes(bytes: &[u8]) -> Result<Module, Error> {
+    let rest = bytes.strip_prefix(MAGIC).ok_or(Error::BadMagic)?;
+    if rest.len() < 4 {
+        return Err(Error::BadMagic);
+    }
+    let (version, rest) = rest.split_at(4);
+    let version = u32::from_le_bytes(version.try_into().unwrap());
+    let Decoded::<ScopedLocal> {
+        mut module,
+        mut spans,
+        mut locals,
+    } = match version {
+        1 => {
+            let decoded: DecodedWithoutLocals<(u32, u32)> = bincode::deserialize(rest)?;
+            Decoded {
+                module: decoded.module.into(),
+                spans: decoded
+                    .spans
+                    .into_iter()
+                    .map(|(start, end)| (start, end, SourceId::PRIMARY.index()))
+                    .collect(),
+                locals: Vec::new(),
+            }
+        }
+        2 => {
+            let decoded: DecodedWithoutLocals<EncodedSpan> = bincode::deserialize(rest)?;
+            Decoded {
+                module: decoded.module.into(),
+                spans: decoded.spans,
+                locals: Vec::new(),
+            }
+        }
+        3 => {
+            let decoded: Decoded<Handle<LocalVariable>, ModuleWithoutFilters> =
+                bincode::deserialize(rest)?;
+            let scope = |local| ScopedLocal { local, start: 0 };
+            Decoded {
+                module: decoded.module.into(),
+                spans: decoded.spans,
+                locals: decoded
+                    .locals
+                    .into_iter()
+                    .map(|locals| locals.into_iter().map(scope).collect())
+                    .collect(),
+            }
+        }
+        4 => {
+            let decoded: Decoded<ScopedLocal, ModuleWithoutFilters> = bincode::deserialize(rest)?;
+            Decoded {
+                module: decoded.module.into(),
+                spans: decoded.spans,
+                locals: decoded.locals,
+            }
+        }
+        FORMAT_VERSION => bincode::deserialize(rest)?,
+        _ => return Err(Error::UnsupportedVersion(version)),
+    };
+    let mut span_count = 0;
+    let mut block_count = 0;
+    collect_spans(&module, &mut |_| span_count += 1, &mut |_| block_count += 1);
+    if span_count != spans.len() {
+        return Err(Error::SpanCountMismatch);
+    }
+    if version >= 3 && block_count != locals.len() {
+        return Err(Error::BlockCountMismatch);
+    }
+
+    // `restore_spans` visits spans and blocks in the same order as
+    // `collect_spans`.
+    spans.reverse();
+    locals.reverse();
+    restore_spans(
+        &mut module,
+        &mut || {
+            let (start, end, source) = spans.pop().unwrap();
+            Span::new(start, end).with_source(SourceId::new(source))
+        },
+        &mut |block| {
+            for scoped in locals.pop().unwrap_or_default() {
+                block.declare_local_at(scoped.local, scoped.start);
+            }
+        },
+    );
+    Ok(module)
+}
+
+/// Call `f` on every span in `module`, and `block` on every block, in a
+/// fixed order.
+fn collect_spans(module: &Module, f: &mut impl FnMut(Span), block: &mut impl FnMut(&Block)) {
+    for (handle, _) in module.types.iter() {
+        f(module.types.get_span(handle));
+    }
+    for (handle, _) in module.constants.iter() {
+        f(module.constants.get_span(handle));
+    }
+    for (handle, _) in module.global_variables.iter() {
+        f(module.global_variables.get_span(handle));
+    }
+    for (handle, _) in module.const_expressions.iter() {
+        f(module.const_expressions.get_span(handle));
+    }
+    for (handle, function) in module.functions.iter() {
+        f(module.functions.get_span(handle));
+        collect_function_spans(function, f, block);
+    }
+    for ep in module.entry_points.iter() {
+        collect_function_spans(&ep.function, f, block);
+    }
+}
+
+fn collect_function_spans(
+    function: &Function,
+    f: &mut impl FnMut(Span),
+    block: &mut impl FnMut(&Block),
+) {
+    for (handle, _) in function.expressions.iter() {
+        f(function.expressions.get_span(handle));
+    }
+    for (handle, _) in function.local_variables.iter() {
+        f(function.local_variables.get_span(handle));
+    }
+    for_each_block(&function.body, &mut |b: &Block| {
+        for (_, &span) in b.span_iter() {
+            f(span);
+        }
+        block(b);
+    });
+}
+
+/// Set every span in `module` to the next one `next` returns, and pass
+/// `block` every block, in the order [`collect_spans`] visits them.
+fn restore_spans(
+    module: &mut Module,
+    next: &mut impl FnMut() -> Span,
+    block: &mut impl FnMut(&mut Block),
+) {
+    let handles: Vec<_> = module.types.iter().map(|(handle, _)| handle).collect();
+    for handle in handles {
+        module.types.set_span(handle, next());
+    }
+    restore_arena_spans(&mut module.constants, next);
+    restore_arena_spans(&mut module.global_variables, next);
+    restore_arena_spans(&mut module.const_expressions, next);
+    let handles: Vec<_> = module.functions.iter().map(|(handle, _)| handle).collect();
+    for handle in handles {
+        module.functions.set_span(handle, next());
+        restore_function_spans(&mut module.functions[handle], next, block);
+    }
+    for ep in module.entry_points.iter_mut() {
+        restore_function_spans(&mut ep.function, next, block);
+    }
+}
+
+fn restore_arena_spans<T>(arena: &mut Arena<T>, next: &mut impl FnMut() -> Span) {
+    let handles: Vec<_> = arena.iter().map(|(handle, _)| handle).collect();
+    for handle in handles {
+        arena.set_span(handle, next());
+    }
+}
+
+fn restore_function_spans(
+    function: &mut Function,
+    next: &mut impl FnMut() -> Span,
+    block: &mut impl FnMut(&mut Block),
+) {
+    restore_arena_spans(&mut function.expressions, next);
+    restore_arena_spans(&mut function.local_variables, next);
+    for_each_block_mut(&mut function.body, &mut |b: &mut Block| {
+        for (_, span) in b.span_iter_mut() {
+            let new_span = next();
+            if let Some(span) = span {
+                *span = new_span;
+            }
+        }
+        block(b);
+    });
+}
+
+#[cfg(all(test, feature = "wgsl-in"))]
+mod tests {
+    use super::{from_bytes, to_bytes, Error, FORMAT_VERSION};
+
+    const SOURCE: &str = "
+        @group(0) @binding(0) var<storage, read_write> data: array<u32>;
+
+        @compute @workgroup_size(1)
+        fn main() {
+            var i = 0u;
+            loop {
+                if i >= 4u {
+                    break;
+                }
+                var doubled = i * 2u;
+                data[i] = doubled;
+                i++;
+            }
+        }
+    ";
+
+    /// Encode `module` as versions 1 to 4 did, without its diagnostic
+    /// filters.
+    fn module_without_filters(module: &crate::Module) -> Vec<u8> {
+        assert!(module.diagnostic_filters.is_empty());
+        let mut bytes = bincode::serialize(module).unwrap();
+        // The filters are the last field, and an empty `Vec` is just its
+        // length.
+        let filters = bytes.split_off(bytes.len() - 8);
+        assert_eq!(filters, [0; 8]);
+        bytes
+    }
+
+    #[test]
+    fn round_trip() {
+        let filtered = format!("diagnostic(off, derivative_uniformity);\n{SOURCE}");
+        for source in [SOURCE, filtered.as_str()] {
+            let module = crate::front::wgsl::parse_str(source).unwrap();
+            let bytes = to_bytes(&module).unwrap();
+            let decoded = from_bytes(&bytes).unwrap();
+            // `Debug` shows everything, including the spans and block locals
+            // that serde skips.
+            assert_eq!(format!("{decoded:?}"), format!("{module:?}"));
+            assert_eq!(to_bytes(&decoded).unwrap(), bytes);
+        }
+    }
+
+    #[test]
+    fn version_1() {
+        let module = crate::front::wgsl::parse_str(SOURCE).unwrap();
+        let mut spans = Vec::new();
+        super::collect_spans(
+            &module,
+            &mut |span| {
+                let range = span.to_range().unwrap_or(0..0);
+                spans.push((range.start as u32, range.end as u32));
+            },
+            &mut |_| {},
+        );
+        let mut bytes = super::MAGIC.to_vec();
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        bytes.extend(module_without_filters(&module));
+        bincode::serialize_into(&mut bytes, &spans).unwrap();
+
+        // Version 1 has the module and its spans, but no block locals.
+        let decoded = from_bytes(&bytes).unwrap();
+        assert_eq!(
+            bincode::serialize(&decoded).unwrap(),
+            bincode::serialize(&module).unwrap()
+        );
+        let mut decoded_spans = Vec::new();
+        super::collect_spans(
+            &decoded,
+            &mut |span| {
+                let range = span.to_range().unwrap_or(0..0);
+                decoded_spans.push((range.start as u32, range.end as u32));
+            },
+            &mut |block| assert!(block.locals().is_empty()),
+        );
+        assert_eq!(decoded_spans, spans);
+    }
+
+    #[test]
+    fn version_3() {
+        let module = crate::front::wgsl::parse_str(SOURCE).unwrap();
+        let mut spans = Vec::new();
+        let mut locals = Vec::new();
+        super::collect_spans(
+            &module,
+            &mut |span| {
+                let range = span.to_range().unwrap_or(0..0);
+                spans.push((range.start as u32, range.end as u32, span.source().index()));
+            },
+            &mut |block| {
+                // `doubled` is declared after the `if`.
+                assert!(block.locals().iter().all(|scoped| scoped.start > 0));
+                let handles: Vec<_> = block.locals().iter().map(|scoped| scoped.local).collect();
+                locals.push(handles);
+            },
+        );
+        let mut bytes = super::MAGIC.to_vec();
+        bytes.extend_from_slice(&3u32.to_le_bytes());
+        bytes.extend(module_without_filters(&module));
+        bincode::serialize_into(&mut bytes, &(&spans, &locals)).unwrap();
+
+        // Version 3 has the locals of each block, but not where their scopes
+        // start, so they are in scope for the whole block.
+        let decoded = from_bytes(&bytes).unwrap();
+        let mut scoped = Vec::new();
+        super::collect_spans(&decoded, &mut |_| {}, &mut |block| {
+            scoped.extend(block.locals().iter().copied())
+        });
+        assert_eq!(scoped.len(), 1);
+        assert_eq!(scoped[0].start, 0);
+    }
+
+    #[test]
+    fn bad_header() {
+        let module = crate::front::wgsl::parse_str(SOURCE).unwrap();
+        let mut bytes = to_bytes(&module).unwrap();
+        assert!(matches!(from_bytes(&bytes[..6]), Err(Error::BadMagic)));
+
+        bytes[4..8].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        assert!(matches!(
+            from_bytes(&bytes),
+            Err(Error::UnsupportedVersion(version)) if version == FORMAT_VERSION + 1
+        ));
+    }
+}
@@ -1,45 +1,92 @@
 use crate::{Arena, Handle, UniqueArena};
 use std::{error::Error, fmt, ops::Range};
 
+/// Identifies the source file a [`Span`] refers to.
+///
+/// Front ends that read a single file give all their spans
+/// [`SourceId::PRIMARY`]. When a module is built from several files, each
+/// file gets its own id, which a [`SourceMap`] maps back to the file's name
+/// and text for error reporting.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct SourceId(u32);
+
+impl SourceId {
+    /// The source of front ends that read a single file.
+    pub const PRIMARY: Self = Self(0);
+
+    pub const fn new(index: u32) -> Self {
+        Self(index)
+    }
+
+    pub const fn index(self) -> u32 {
+        self.0
+    }
+}
+
 /// A source code span, used for error reporting.
 #[derive(Clone, Copy, Debug, PartialEq, Default)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct Span {
     start: u32,
     end: u32,
+    source: SourceId,
 }
 
 impl Span {
-    pub const UNDEFINED: Self = Self { start: 0, end: 0 };
+    pub const UNDEFINED: Self = Self::new(0, 0);
     /// Creates a new `Span` from a range of byte indices
     ///
     /// Note: end is exclusive, it doesn't belong to the `Span`
     pub const fn new(start: u32, end: u32) -> Self {
-        Span { start, end }
+        Span {
+            start,
+            end,
+            source: SourceId::PRIMARY,
+        }
+    }
+
+    /// The file `self` is a range of bytes in.
+    pub const fn source(&self) -> SourceId {
+        self.source
+    }
+
+    /// Returns `self`, but referring to the file `source`.
+    pub const fn with_source(self, source: SourceId) -> Self {
+        Span { source, ..self }
     }
 
     /// Returns a new `Span` starting at `self` and ending at `other`
+    ///
+    /// If `other` is in a different file, this returns `self`.
     pub const fn until(&self, other: &Self) -> Self {
+        if self.source.0 != other.source.0 {
+            return *self;
+        }
         Span {
             start: self.start,
             end: other.end,
+            source: self.source,
         }
     }
 
     /// Modifies `self` to contain the smallest `Span` possible that
     /// contains both `self` and `other`
+    ///
+    /// If `other` is in a different file, `self` is left unchanged.
     pub fn subsume(&mut self, other: Self) {
         *self = if !self.is_defined() {
             // self isn't defined so use other
             other
-        } else if !other.is_defined() {
-            // other isn't defined so don't try to subsume
+        } else if !other.is_defined() || other.source != self.source {
+            // other isn't defined, or is in another file, so don't try to subsume
             *self
         } else {
             // Both self and other are defined so calculate the span that contains them both
             Span {
                 start: self.start.min(other.start),
                 end: self.end.max(other.end),
+                source: self.source,
             }
         }
     }
@@ -65,7 +112,7 @@ impl Span {
 
     /// Check whether `self` was defined or is a default/unknown span
     pub fn is_defined(&self) -> bool {
-        *self != Self::default()
+        self.start != 0 || self.end != 0
     }
 
     /// Return a [`SourceLocation`] for this span in the provided source.
@@ -86,10 +133,7 @@ impl Span {
 
 impl From<Range<usize>> for Span {
     fn from(range: Range<usize>) -> Self {
-        Span {
-            start: range.start as u32,
-            end: range.end as u32,
-        }
+        Span::new(range.start as u32, range.end as u32)
     }
 }
 
@@ -254,8 +298,13 @@ impl<E> WithSpan<E> {
         None
     }
 
+    /// Describe the error for `codespan_reporting`, using `file` to find
+    /// the file id and text each span refers to.
     #[cfg(feature = "span")]
-    fn diagnostic(&self) -> codespan_reporting::diagnostic::Diagnostic<()>
+    fn diagnostic<'s, F>(
+        &self,
+        file: impl Fn(Span) -> (F, &'s str),
+    ) -> codespan_reporting::diagnostic::Diagnostic<F>
     where
         E: Error,
     {
@@ -265,22 +314,47 @@ impl<E> WithSpan<E> {
             .with_labels(
                 self.spans()
                     .map(|&(span, ref desc)| {
-                        Label::primary((), span.to_range().unwrap()).with_message(desc.to_owned())
+                        let range = span.to_range().unwrap();
+                        let (file_id, source) = file(span);
+                        let message = quote_label(desc, source.get(range.clone()));
+                        Label::primary(file_id, range).with_message(message)
                     })
                     .collect(),
             )
-            .with_notes({
-                let mut notes = Vec::new();
-                let mut source: &dyn Error = &self.inner;
-                while let Some(next) = Error::source(source) {
-                    notes.push(next.to_string());
-                    source = next;
-                }
-                notes
-            });
+            .with_notes(self.source_notes());
         diagnostic
     }
 
+    /// Describe each error in the chain of sources of the inner error.
+    fn source_notes(&self) -> Vec<String>
+    where
+        E: Error,
+    {
+        let mut notes = Vec::new();
+        let mut source: &dyn Error = &self.inner;
+        while let Some(next) = Error::source(source) {
+            notes.push(next.to_string());
+            source = next;
+        }
+        notes
+    }
+
+    /// Describes the error as a [`Diagnostic`], for tools that present errors
+    /// themselves.
+    pub fn to_diagnostic(&self) -> Diagnostic
+    where
+        E: Diagnose,
+    {
+        Diagnostic {
+            severity: self.inner.severity(),
+            code: self.inner.code(),
+            message: self.inner.to_string(),
+            labels: self.spans().cloned().collect(),
+            notes: self.source_notes(),
+            suggestions: Vec::new(),
+        }
+    }
+
     /// Emits a summary of the error to standard error stream.
     #[cfg(feature = "span")]
     pub fn emit_to_stderr(&self, source: &str)
@@ -302,8 +376,36 @@ impl<E> WithSpan<E> {
         let files = files::SimpleFile::new(path, source);
         let config = term::Config::default();
         let writer = StandardStream::stderr(ColorChoice::Auto);
-        term::emit(&mut writer.lock(), &config, &files, &self.diagnostic())
-            .expect("cannot write error");
+        term::emit(
+            &mut writer.lock(),
+            &config,
+            &files,
+            &self.diagnostic(|_| ((), source)),
+        )
+        .expect("cannot write error");
+    }
+
+    /// Emits a summary of the error to standard error stream, quoting the
+    /// files in `sources`.
+    ///
+    /// Every span in the error must refer to a file in `sources`.
+    #[cfg(feature = "span")]
+    pub fn emit_to_stderr_with_sources(&self, sources: &SourceMap)
+    where
+        E: Error,
+    {
+        use codespan_reporting::term;
+        use term::termcolor::{ColorChoice, StandardStream};
+
+        let config = term::Config::default();
+        let writer = StandardStream::stderr(ColorChoice::Auto);
+        term::emit(
+            &mut writer.lock(),
+            &config,
+            &sources.files,
+            &self.diagnostic(|span| sources.file(span.source())),
+        )
+        .expect("cannot write error");
     }
 
     /// Emits a summary of the error to a string.
@@ -327,11 +429,377 @@ impl<E> WithSpan<E> {
         let files = files::SimpleFile::new(path, source);
         let config = codespan_reporting::term::Config::default();
         let mut writer = NoColor::new(Vec::new());
-        term::emit(&mut writer, &config, &files, &self.diagnostic()).expect("cannot write error");
+        term::emit(
+            &mut writer,
+            &config,
+            &files,
+            &self.diagnostic(|_| ((), source)),
+        )
+        .expect("cannot write error");
+        String::from_utf8(writer.into_inner()).unwrap()
+    }
+
+    /// Emits a summary of the error to a string, rendered as `options` say.
+    #[cfg(feature = "span")]
+    pub fn emit_to_string_with_options(
+        &self,
+        source: &str,
+        path: &str,
+        options: &EmitOptions,
+    ) -> String
+    where
+        E: Diagnose,
+    {
+        let mut diagnostic = self.to_diagnostic();
+        for &mut (span, ref mut label) in diagnostic.labels.iter_mut() {
+            let text = span.to_range().and_then(|range| source.get(range));
+            *label = quote_label(label, text);
+        }
+        options.emit_to_string(&[diagnostic], source, path)
+    }
+
+    /// Emits a summary of the error to a string, quoting the files in
+    /// `sources`.
+    ///
+    /// Every span in the error must refer to a file in `sources`.
+    #[cfg(feature = "span")]
+    pub fn emit_to_string_with_sources(&self, sources: &SourceMap) -> String
+    where
+        E: Error,
+    {
+        use codespan_reporting::term;
+        use term::termcolor::NoColor;
+
+        let config = term::Config::default();
+        let mut writer = NoColor::new(Vec::new());
+        term::emit(
+            &mut writer,
+            &config,
+            &sources.files,
+            &self.diagnostic(|span| sources.file(span.source())),
+        )
+        .expect("cannot write error");
         String::from_utf8(writer.into_inner()).unwrap()
     }
 }
 
+/// Describe a label for the source `text` it refers to.
+///
+/// Descriptions often only name a handle, so quote the code it came from,
+/// if that fits on one line.
+#[cfg(feature = "span")]
+fn quote_label(description: &str, text: Option<&str>) -> String {
+    match text {
+        Some(text) if !description.is_empty() && !text.contains('\n') => {
+            format!("{description}: `{text}`")
+        }
+        _ => description.to_owned(),
+    }
+}
+
+/// The names and contents of the files a module's spans refer to.
+///
+/// Pass this to functions like [`WithSpan::emit_to_string_with_sources`] to
+/// report errors in modules built from several files.
+#[cfg(feature = "span")]
+#[derive(Clone, Debug)]
+pub struct SourceMap {
+    files: codespan_reporting::files::SimpleFiles<String, String>,
+}
+
+#[cfg(feature = "span")]
+impl Default for SourceMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "span")]
+impl SourceMap {
+    pub fn new() -> Self {
+        SourceMap {
+            files: codespan_reporting::files::SimpleFiles::new(),
+        }
+    }
+
+    /// Add a file, and return the id its spans should carry.
+    ///
+    /// The first file added gets [`SourceId::PRIMARY`].
+    pub fn add(&mut self, name: impl Into<String>, text: impl Into<String>) -> SourceId {
+        SourceId(self.files.add(name.into(), text.into()) as u32)
+    }
+
+    /// The name of the file `id` refers to, if it is in the map.
+    pub fn name(&self, id: SourceId) -> Option<&str> {
+        let file = self.files.get(id.0 as usize).ok()?;
+        Some(file.name().as_str())
+    }
+
+    /// The text of the file `id` refers to, if it is in the map.
+    pub fn text(&self, id: SourceId) -> Option<&str> {
+        let file = self.files.get(id.0 as usize).ok()?;
+        Some(file.source().as_str())
+    }
+
+    fn file(&self, id: SourceId) -> (usize, &str) {
+        (id.0 as usize, self.text(id).unwrap_or(""))
+    }
+}
+
+/// Make every span in `module` refer to the file `source`.
+///
+/// Front ends that read one file at a time build a module whose spans all
+/// carry [`SourceId::PRIMARY`]; this relabels them once the module is done.
+#[cfg(feature = "span")]
+pub(crate) fn set_module_source(module: &mut crate::Module, source: SourceId) {
+    for_each_module_span(module, &mut |span| span.source = source);
+}
+
+/// Call `f` on every span in `module`.
+#[cfg(feature = "span")]
+pub(crate) fn for_each_module_span<F: FnMut(&mut Span)>(module: &mut crate::Module, f: &mut F) {
+    fn block_spans<F: FnMut(&mut Span)>(block: &mut crate::Block, f: &mut F) {
+        for (statement, span) in block.span_iter_mut() {
+            if let Some(span) = span {
+                f(span);
+            }
+            match *statement {
+                crate::Statement::Block(ref mut block) => block_spans(block, f),
+                crate::Statement::If {
+                    ref mut accept,
+                    ref mut reject,
+                    ..
+                } => {
+                    block_spans(accept, f);
+                    block_spans(reject, f);
+                }
+                crate::Statement::Switch { ref mut cases, .. } => {
+                    for case in cases {
+                        block_spans(&mut case.body, f);
+                    }
+                }
+                crate::Statement::Loop {
+                    ref mut body,
+                    ref mut continuing,
+                    ..
+                } => {
+                    block_spans(body, f);
+                    block_spans(continuing, f);
+                }
+                _ => {}
+            }
+        }
+    }
+    fn function_spans<F: FnMut(&mut Span)>(function: &mut crate::Function, f: &mut F) {
+        for span in function
+            .expressions
+            .spans_mut()
+            .chain(function.local_variables.spans_mut())
+        {
+            f(span);
+        }
+        block_spans(&mut function.body, f);
+    }
+
+    for span in module
+        .types
+        .spans_mut()
+        .chain(module.constants.spans_mut())
+        .chain(module.global_variables.spans_mut())
+        .chain(module.const_expressions.spans_mut())
+        .chain(module.functions.spans_mut())
+    {
+        f(span);
+    }
+    for (_, function) in module.functions.iter_mut() {
+        function_spans(function, f);
+    }
+    for ep in module.entry_points.iter_mut() {
+        function_spans(&mut ep.function, f);
+    }
+}
+
+/// How serious a [`Diagnostic`] is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    /// The input can't be used.
+    Error,
+    /// The input is valid, but may not do what was intended.
+    Warning,
+}
+
+/// A change to the source that would address a [`Diagnostic`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Suggestion {
+    pub message: String,
+    /// The source to replace. This is empty if the fix is an insertion.
+    pub span: Span,
+    pub replacement: String,
+}
+
+/// A machine-readable description of an error or warning.
+///
+/// This holds the same information as the text produced by functions like
+/// [`WithSpan::emit_to_string`], for tools like editors that present it
+/// themselves.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// A short identifier for the kind of problem, which stays the same
+    /// when its message is reworded.
+    pub code: &'static str,
+    pub message: String,
+    /// The parts of the source the problem concerns, with a description of
+    /// each. The first is the main one.
+    pub labels: Vec<SpanContext>,
+    pub notes: Vec<String>,
+    pub suggestions: Vec<Suggestion>,
+}
+
+/// An error or warning that [`WithSpan::to_diagnostic`] can describe.
+pub trait Diagnose: Error {
+    /// See [`Diagnostic::code`].
+    fn code(&self) -> &'static str;
+
+    fn severity(&self) -> Severity {
+        Severity::Error
+    }
+}
+
+/// How to render [`Diagnostic`]s as text.
+///
+/// The `emit_to_string` functions use the defaults, except that they never
+/// use color. Pass this to functions like
+/// [`WithSpan::emit_to_string_with_options`] to choose otherwise, or use
+/// [`EmitOptions::emit_to_string`] to render several diagnostics at once.
+#[cfg(any(feature = "span", feature = "wgsl-in"))]
+#[derive(Clone, Debug)]
+pub struct EmitOptions {
+    /// Color the output with ANSI escape codes. Defaults to `false`.
+    pub color: bool,
+    /// Draw the source snippets with ASCII characters only, instead of
+    /// Unicode box-drawing characters. Defaults to `false`.
+    pub ascii: bool,
+    /// How many columns a tab in the source takes up. Defaults to `4`.
+    pub tab_width: usize,
+    /// The widest a line of a message or note may be, in characters.
+    /// Longer ones are wrapped at spaces. Quoted source lines are never
+    /// wrapped. Defaults to `None`, for no limit.
+    pub max_width: Option<usize>,
+}
+
+#[cfg(any(feature = "span", feature = "wgsl-in"))]
+impl Default for EmitOptions {
+    fn default() -> Self {
+        EmitOptions {
+            color: false,
+            ascii: false,
+            tab_width: 4,
+            max_width: None,
+        }
+    }
+}
+
+#[cfg(any(feature = "span", feature = "wgsl-in"))]
+impl EmitOptions {
+    /// Render `diagnostics`, in order, quoting `source`, which is named
+    /// `path` in the output.
+    ///
+    /// Each diagnostic is tagged with its [`Severity`] and
+    /// [`code`](Diagnostic::code).
+    pub fn emit_to_string(&self, diagnostics: &[Diagnostic], source: &str, path: &str) -> String {
+        use codespan_reporting::{diagnostic as cs, files, term};
+        use term::termcolor::{Ansi, NoColor, WriteColor};
+
+        let files = files::SimpleFile::new(path, source);
+        let config = term::Config {
+            tab_width: self.tab_width,
+            chars: if self.ascii {
+                term::Chars::ascii()
+            } else {
+                term::Chars::box_drawing()
+            },
+            ..Default::default()
+        };
+        let rendered: Vec<_> = diagnostics
+            .iter()
+            .map(|diagnostic| {
+                let (severity, name) = match diagnostic.severity {
+                    Severity::Error => (cs::Severity::Error, "error"),
+                    Severity::Warning => (cs::Severity::Warning, "warning"),
+                };
+                // The header reads `severity[code]: message`.
+                let header = name.len() + diagnostic.code.len() + "[]: ".len();
+                cs::Diagnostic::new(severity)
+                    .with_code(diagnostic.code)
+                    .with_message(self.wrap(&diagnostic.message, header))
+                    .with_labels(
+                        diagnostic
+                            .labels
+                            .iter()
+                            .filter_map(|&(span, ref label)| {
+                                let range = span.to_range()?;
+                                Some(cs::Label::primary((), range).with_message(label))
+                            })
+                            .collect(),
+                    )
+                    .with_notes(
+                        diagnostic
+                            .notes
+                            .iter()
+                            .map(|note| self.wrap(note, "= ".len()))
+                            .collect(),
+                    )
+            })
+            .collect();
+
+        let emit = |writer: &mut dyn WriteColor| {
+            for diagnostic in rendered.iter() {
+                term::emit(writer, &config, &files, diagnostic).expect("cannot write error");
+            }
+        };
+        let output = if self.color {
+            let mut writer = Ansi::new(Vec::new());
+            emit(&mut writer);
+            writer.into_inner()
+        } else {
+            let mut writer = NoColor::new(Vec::new());
+            emit(&mut writer);
+            writer.into_inner()
+        };
+        String::from_utf8(output).unwrap()
+    }
+
+    /// Wrap `text` to [`max_width`](Self::max_width), leaving room for
+    /// `indent` characters before its first line.
+    fn wrap(&self, text: &str, indent: usize) -> String {
+        let max_width = match self.max_width {
+            Some(max_width) => max_width,
+            None => return text.to_string(),
+        };
+        let mut wrapped = String::with_capacity(text.len());
+        let mut width = indent;
+        for (index, word) in text.split(' ').enumerate() {
+            let word_width = word.chars().count();
+            if index != 0 {
+                if width + 1 + word_width > max_width {
+                    wrapped.push('\n');
+                    width = 0;
+                } else {
+                    wrapped.push(' ');
+                    width += 1;
+                }
+            }
+            wrapped.push_str(word);
+            width = match word.rfind('\n') {
+                Some(newline) => word[newline + 1..].chars().count(),
+                None => width + word_width,
+            };
+        }
+        wrapped
+    }
+}
+
 /// Convenience trait for [`Error`] to be able to apply spans to anything.
 pub(crate) trait AddSpan: Sized {
     type Output;
@@ -418,11 +886,30 @@ impl<T, E, E2> MapErrWithSpan<E, E2> for Result<T, WithSpan<E>> {
     }
 }
 
+#[test]
+fn spans_stay_in_their_file() {
+    let other = SourceId::new(1);
+    let first = Span::new(2, 4);
+    let second = Span::new(6, 8).with_source(other);
+    assert_eq!(first.until(&Span::new(6, 8)), Span::new(2, 8));
+    assert_eq!(first.until(&second), first);
+
+    let mut span = first;
+    span.subsume(second);
+    assert_eq!(span, first);
+    span.subsume(Span::new(6, 8));
+    assert_eq!(span, Span::new(2, 8));
+    assert_eq!(
+        Span::total_span([second, Span::new(0, 10).with_source(other)].into_iter()),
+        Span::new(0, 10).with_source(other)
+    );
+}
+
 #[test]
 fn span_location() {
     let source = "12\n45\n\n89\n";
     assert_eq!(
-        Span { start: 0, end: 1 }.location(source),
+        Span::new(0, 1).location(source),
         SourceLocation {
             line_number: 1,
             line_position: 1,
@@ -431,7 +918,7 @@ fn span_location() {
         }
     );
     assert_eq!(
-        Span { start: 1, end: 2 }.location(source),
+        Span::new(1, 2).location(source),
         SourceLocation {
             line_number: 1,
             line_position: 2,
@@ -440,7 +927,7 @@ fn span_location() {
         }
     );
     assert_eq!(
-        Span { start: 2, end: 3 }.location(source),
+        Span::new(2, 3).location(source),
         SourceLocation {
             line_number: 1,
             line_position: 3,
@@ -449,7 +936,7 @@ fn span_location() {
         }
     );
     assert_eq!(
-        Span { start: 3, end: 5 }.location(source),
+        Span::new(3, 5).location(source),
         SourceLocation {
             line_number: 2,
             line_position: 1,
@@ -458,7 +945,7 @@ fn span_location() {
         }
     );
     assert_eq!(
-        Span { start: 4, end: 6 }.location(source),
+        Span::new(4, 6).location(source),
         SourceLocation {
             line_number: 2,
             line_position: 2,
@@ -467,7 +954,7 @@ fn span_location() {
         }
     );
     assert_eq!(
-        Span { start: 5, end: 6 }.location(source),
+        Span::new(5, 6).location(source),
         SourceLocation {
             line_number: 2,
             line_position: 3,
@@ -476,7 +963,7 @@ fn span_location() {
         }
     );
     assert_eq!(
-        Span { start: 6, end: 7 }.location(source),
+        Span::new(6, 7).location(source),
         SourceLocation {
             line_number: 3,
             line_position: 1,
@@ -485,7 +972,7 @@ fn span_location() {
         }
     );
     assert_eq!(
-        Span { start: 7, end: 8 }.location(source),
+        Span::new(7, 8).location(source),
         SourceLocation {
             line_number: 4,
             line_position: 1,
@@ -494,7 +981,7 @@ fn span_location() {
         }
     );
     assert_eq!(
-        Span { start: 8, end: 9 }.location(source),
+        Span::new(8, 9).location(source),
         SourceLocation {
             line_number: 4,
             line_position: 2,
@@ -503,7 +990,7 @@ fn span_location() {
         }
     );
     assert_eq!(
-        Span { start: 9, end: 10 }.location(source),
+        Span::new(9, 10).location(source),
         SourceLocation {
             line_number: 4,
             line_position: 3,
@@ -512,7 +999,7 @@ fn span_location() {
         }
     );
     assert_eq!(
-        Span { start: 10, end: 11 }.location(source),
+        Span::new(10, 11).location(source),
         SourceLocation {
             line_number: 5,
             line_position: 1,
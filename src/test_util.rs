@@ -0,0 +1,258 @@
+/*!
+Helpers for small, focused tests.
+
+Adding a fixture to the snapshot tests is a heavy way to capture a single
+regression. Instead, a test can write its WGSL inline, and check what Naga
+makes of it:
+
+```
+use naga::test_util::Fixture;
+
+let fixture = Fixture::wgsl("fn double(x: f32) -> f32 { return x * 2.0; }");
+# #[cfg(feature = "wgsl-out")]
+fixture
+    .to_wgsl()
+    .assert_contains("fn double(x: f32) -> f32 {")
+    .assert_matches(r"return \(?x \* 2\.0\)?;");
+```
+
+Everything here panics with a readable message when something goes wrong,
+as test assertions should.
+*/
+#![allow(clippy::panic)]
+
+use crate::valid::{Capabilities, ModuleInfo, ValidationError, ValidationFlags, Validator};
+
+/// A module parsed from inline WGSL, and its validation results.
+#[derive(Debug)]
+pub struct Fixture {
+    pub source: String,
+    pub module: crate::Module,
+    pub info: ModuleInfo,
+    /// The capabilities `module` is validated with.
+    pub capabilities: Capabilities,
+}
+
+impl Fixture {
+    /// Parse and validate `source`, with the default capabilities.
+    pub fn wgsl(source: &str) -> Self {
+        Self::wgsl_with_capabilities(source, Capabilities::default())
+    }
+
+    /// Parse and validate `source`, allowing `capabilities`.
+    pub fn wgsl_with_capabilities(source: &str, capabilities: Capabilities) -> Self {
+        let module = parse(source);
+        let info = validate(&module, capabilities, source);
+        Fixture {
+            source: source.to_string(),
+            module,
+            info,
+            capabilities,
+        }
+    }
+
+    /// Validate `module`, which wasn't parsed from any source, allowing
+    /// `capabilities`.
+    ///
+    /// This is for modules built by combining others, or by hand.
+    pub fn module(module: crate::Module, capabilities: Capabilities) -> Self {
+        let info = validate(&module, capabilities, "");
+        Fixture {
+            source: String::new(),
+            module,
+            info,
+            capabilities,
+        }
+    }
+
+    /// Validate `module` again, after a test has changed it, as a pass
+    /// should leave it valid.
+    pub fn revalidate(&mut self) {
+        self.info = validate(&self.module, self.capabilities, &self.source);
+    }
+
+    #[cfg(feature = "wgsl-out")]
+    pub fn to_wgsl(&self) -> Output {
+        self.to_wgsl_with_flags(crate::back::wgsl::WriterFlags::empty())
+    }
+
+    #[cfg(feature = "wgsl-out")]
+    pub fn to_wgsl_with_flags(&self, flags: crate::back::wgsl::WriterFlags) -> Output {
+        match crate::back::wgsl::write_string(&self.module, &self.info, flags) {
+            Ok(text) => Output(text),
+            Err(error) => panic!("WGSL output failed: {error}"),
+        }
+    }
+
+    #[cfg(feature = "msl-out")]
+    pub fn to_msl(
+        &self,
+        options: &crate::back::msl::Options,
+        pipeline_options: &crate::back::msl::PipelineOptions,
+    ) -> Output {
+        match crate::back::msl::write_string(&self.module, &self.info, options, pipeline_options) {
+            Ok((text, _)) => Output(text),
+            Err(error) => panic!("MSL output failed: {error}"),
+        }
+    }
+
+    #[cfg(feature = "hlsl-out")]
+    pub fn to_hlsl(&self, options: &crate::back::hlsl::Options) -> Output {
+        let mut text = String::new();
+        match crate::back::hlsl::Writer::new(&mut text, options).write(&self.module, &self.info) {
+            Ok(_) => Output(text),
+            Err(error) => panic!("HLSL output failed: {error}"),
+        }
+    }
+
+    #[cfg(feature = "glsl-out")]
+    pub fn to_glsl(
+        &self,
+        options: &crate::back::glsl::Options,
+        pipeline_options: &crate::back::glsl::PipelineOptions,
+    ) -> Output {
+        self.to_glsl_with_policies(options, pipeline_options, Default::default())
+    }
+
+    #[cfg(feature = "glsl-out")]
+    pub fn to_glsl_with_policies(
+        &self,
+        options: &crate::back::glsl::Options,
+        pipeline_options: &crate::back::glsl::PipelineOptions,
+        policies: crate::proc::BoundsCheckPolicies,
+    ) -> Output {
+        match self.try_to_glsl(options, pipeline_options, policies) {
+            Ok(output) => output,
+            Err(error) => panic!("GLSL output failed: {error}"),
+        }
+    }
+
+    /// Translate the module to GLSL, for tests of the errors it reports.
+    #[cfg(feature = "glsl-out")]
+    pub fn try_to_glsl(
+        &self,
+        options: &crate::back::glsl::Options,
+        pipeline_options: &crate::back::glsl::PipelineOptions,
+        policies: crate::proc::BoundsCheckPolicies,
+    ) -> Result<Output, crate::back::glsl::Error> {
+        let mut text = String::new();
+        crate::back::glsl::Writer::new(
+            &mut text,
+            &self.module,
+            &self.info,
+            options,
+            pipeline_options,
+            policies,
+        )
+        .and_then(|mut writer| writer.write())?;
+        Ok(Output(text))
+    }
+
+    /// Translate the module to SPIR-V words.
+    ///
+    /// There is no text to check, but the words can be handed to a SPIR-V
+    /// tool, or to the SPIR-V front end.
+    #[cfg(feature = "spv-out")]
+    pub fn to_spv(&self, options: &crate::back::spv::Options) -> Vec<u32> {
+        match crate::back::spv::write_vec(&self.module, &self.info, options, None) {
+            Ok(words) => words,
+            Err(error) => panic!("SPIR-V output failed: {error}"),
+        }
+    }
+}
+
+/// Parse `source`, which must be valid WGSL, but validate it with
+/// `capabilities`, and return the error validation must report.
+pub fn validation_error(source: &str, capabilities: Capabilities) -> ValidationError {
+    module_validation_error(&parse(source), capabilities)
+}
+
+/// Validate `module` with `capabilities`, and return the error validation
+/// must report.
+pub fn module_validation_error(
+    module: &crate::Module,
+    capabilities: Capabilities,
+) -> ValidationError {
+    match Validator::new(ValidationFlags::all(), capabilities).validate(module) {
+        Ok(_) => panic!("expected a validation error"),
+        Err(error) => error.into_inner(),
+    }
+}
+
+/// Assert that `a` and `b` are translated to the same IR, ignoring unused
+/// arena entries.
+///
+/// This is useful to check that two ways of writing something mean the same
+/// thing, like a shorthand and its expansion.
+pub fn assert_ir_equivalent(a: &str, b: &str) {
+    let ir = |source| {
+        let mut module = Fixture::wgsl(source).module;
+        crate::compact::compact(&mut module);
+        format!("{module:#?}")
+    };
+    assert_eq!(ir(a), ir(b), "the sources produce different IR");
+}
+
+/// Validate `module`, which was parsed from `source`, if it isn't empty.
+fn validate(module: &crate::Module, capabilities: Capabilities, source: &str) -> ModuleInfo {
+    match Validator::new(ValidationFlags::all(), capabilities).validate(module) {
+        Ok(info) => info,
+        // Without the source, the spans can't be shown.
+        Err(error) if source.is_empty() => panic!("validation failed: {error:?}"),
+        Err(error) => panic!("validation failed:\n{}", error.emit_to_string(source)),
+    }
+}
+
+/// Parse `source`, which must be valid WGSL, without validating it.
+///
+/// This is for tests of passes that turn modules the validator rejects
+/// into ones it accepts.
+pub fn parse(source: &str) -> crate::Module {
+    match crate::front::wgsl::parse_str(source) {
+        Ok(module) => module,
+        Err(error) => panic!("WGSL parse failed:\n{}", error.emit_to_string(source)),
+    }
+}
+
+/// The text a back end produced.
+#[derive(Clone, Debug)]
+pub struct Output(pub String);
+
+impl Output {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn assert_contains(&self, needle: &str) -> &Self {
+        assert!(
+            self.0.contains(needle),
+            "expected the output to contain `{needle}`:\n{}",
+            self.0
+        );
+        self
+    }
+
+    pub fn assert_not_contains(&self, needle: &str) -> &Self {
+        assert!(
+            !self.0.contains(needle),
+            "expected the output not to contain `{needle}`:\n{}",
+            self.0
+        );
+        self
+    }
+
+    /// Assert that some part of the output matches the regular expression
+    /// `pattern`.
+    pub fn assert_matches(&self, pattern: &str) -> &Self {
+        let regex = match regex::Regex::new(pattern) {
+            Ok(regex) => regex,
+            Err(error) => panic!("bad pattern: {error}"),
+        };
+        assert!(
+            regex.is_match(&self.0),
+            "expected the output to match `{pattern}`:\n{}",
+            self.0
+        );
+        self
+    }
+}
@@ -4,12 +4,16 @@ Figures out the following properties:
   - control flow uniformity
   - texture/sampler pairs
   - expression reference counts
+  - static cost estimates
 !*/
 
-use super::{ExpressionError, FunctionError, ModuleInfo, ShaderStages, ValidationFlags};
+use super::{
+    ExpressionError, FunctionError, ModuleInfo, ShaderStages, ValidationFlags, WarningFlags,
+};
+use crate::diagnostic_filter::{self, FilterableTriggeringRule, Severity};
 use crate::span::{AddSpan as _, WithSpan};
 use crate::{
-    arena::{Arena, Handle},
+    arena::{Arena, ArenaUsage, Handle},
     proc::{ResolveContext, TypeResolution},
 };
 use std::ops;
@@ -201,6 +205,124 @@ struct Sampling {
     sampler: GlobalOrArgument,
 }
 
+/// A static estimate of how expensive a function is to run.
+///
+/// These are counts over the code as written, not over an execution: a loop
+/// body counts once, however many times it runs, and both arms of an `if`
+/// count. Calls count the whole cost of the callee, once per call site. This
+/// is only meant for comparing shaders with each other, for example to pick
+/// cheaper variants of a material.
+///
+/// See [`ModuleInfo::cost`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
+pub struct Cost {
+    /// Arithmetic, logic, comparison, conversion and math function
+    /// expressions.
+    pub alu_ops: u32,
+    /// Texture samples and loads.
+    pub texture_samples: u32,
+    /// `if` and `switch` statements.
+    pub branches: u32,
+    /// How deeply loops nest, counting loops in callees.
+    pub loop_depth: u32,
+}
+
+impl Cost {
+    fn of_block(
+        block: &crate::Block,
+        expressions: &Arena<crate::Expression>,
+        other_functions: &[FunctionInfo],
+        loop_depth: u32,
+    ) -> Self {
+        use crate::{Expression as E, Statement as S};
+
+        let mut cost = Self {
+            loop_depth,
+            ..Self::default()
+        };
+        for statement in block {
+            match *statement {
+                S::Emit(ref range) => {
+                    for handle in range.clone() {
+                        match expressions[handle] {
+                            E::Unary { .. }
+                            | E::Binary { .. }
+                            | E::Select { .. }
+                            | E::Derivative { .. }
+                            | E::Relational { .. }
+                            | E::Math { .. }
+                            | E::As { .. } => cost.alu_ops += 1,
+                            E::ImageSample { .. } | E::ImageLoad { .. } => {
+                                cost.texture_samples += 1
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                S::Block(ref block) => {
+                    cost += Self::of_block(block, expressions, other_functions, loop_depth);
+                }
+                S::If {
+                    ref accept,
+                    ref reject,
+                    ..
+                } => {
+                    cost.branches += 1;
+                    cost += Self::of_block(accept, expressions, other_functions, loop_depth);
+                    cost += Self::of_block(reject, expressions, other_functions, loop_depth);
+                }
+                S::Switch { ref cases, .. } => {
+                    cost.branches += 1;
+                    for case in cases.iter() {
+                        cost +=
+                            Self::of_block(&case.body, expressions, other_functions, loop_depth);
+                    }
+                }
+                S::Loop {
+                    ref body,
+                    ref continuing,
+                    ..
+                } => {
+                    let depth = loop_depth + 1;
+                    cost += Self::of_block(body, expressions, other_functions, depth);
+                    cost += Self::of_block(continuing, expressions, other_functions, depth);
+                }
+                S::Call { function, .. } => {
+                    let callee = other_functions[function.index()].cost;
+                    cost += Self {
+                        loop_depth: loop_depth + callee.loop_depth,
+                        ..callee
+                    };
+                }
+                S::Break
+                | S::Continue
+                | S::Return { .. }
+                | S::Kill
+                | S::Barrier(_)
+                | S::Store { .. }
+                | S::ImageStore { .. }
+                | S::ImageAtomic { .. }
+                | S::Atomic { .. }
+                | S::WorkGroupUniformLoad { .. }
+                | S::RayQuery { .. } => {}
+            }
+        }
+        cost
+    }
+}
+
+/// Counts add up, but loop depths take the deepest.
+impl ops::AddAssign for Cost {
+    fn add_assign(&mut self, other: Self) {
+        self.alu_ops += other.alu_ops;
+        self.texture_samples += other.texture_samples;
+        self.branches += other.branches;
+        self.loop_depth = self.loop_depth.max(other.loop_depth);
+    }
+}
+
 #[derive(Debug)]
 #[cfg_attr(feature = "serialize", derive(serde::Serialize))]
 #[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
@@ -208,6 +330,12 @@ pub struct FunctionInfo {
     /// Validation flags.
     #[allow(dead_code)]
     flags: ValidationFlags,
+    /// How to report sampling with an implicit level of detail in
+    /// non-uniform control flow, from the module's diagnostic filters and
+    /// the validator's [`WarningFlags`].
+    #[allow(dead_code)]
+    #[cfg_attr(any(feature = "serialize", feature = "deserialize"), serde(skip))]
+    derivative_uniformity: Severity,
     /// Set of shader stages where calling this function is valid.
     pub available_stages: ShaderStages,
     /// Uniformity characteristics.
@@ -263,6 +391,33 @@ pub struct FunctionInfo {
 
     /// Indicates that the function is using dual source blending.
     pub dual_source_blending: bool,
+
+    /// Things in this function that are valid, but may not behave as intended.
+    ///
+    /// Only the kinds of warnings chosen with [`Validator::warnings`] are
+    /// reported.
+    ///
+    /// [`Validator::warnings`]: super::Validator::warnings
+    #[cfg_attr(any(feature = "serialize", feature = "deserialize"), serde(skip))]
+    warnings: Vec<WithSpan<ValidationWarning>>,
+
+    /// Indicates that the entry point writes the [`FragDepth`] built-in.
+    ///
+    /// Together with [`may_kill`], this tells pipeline creation whether early
+    /// depth and stencil tests can be used for a fragment entry point. It is
+    /// always `false` for functions that are not entry points.
+    ///
+    /// [`FragDepth`]: crate::BuiltIn::FragDepth
+    /// [`may_kill`]: FunctionInfo::may_kill
+    pub writes_depth: bool,
+
+    /// A static estimate of the cost of this function and its callees.
+    pub cost: Cost,
+
+    /// The shader stages this function was analyzed for.
+    ///
+    /// See [`FunctionInfo::analyzed_stages`].
+    analyzed_stages: ShaderStages,
 }
 
 impl FunctionInfo {
@@ -272,6 +427,39 @@ impl FunctionInfo {
     pub const fn expression_count(&self) -> usize {
         self.expressions.len()
     }
+    /// Returns true if early depth and stencil tests can't change the result
+    /// of running this fragment entry point.
+    ///
+    /// This is the case when the entry point neither discards nor writes
+    /// depth. Note that writes to storage buffers or textures can still be
+    /// observed to happen for fragments that early tests would reject.
+    pub const fn allows_early_depth_test(&self) -> bool {
+        !self.may_kill && !self.writes_depth
+    }
+    /// Returns the warnings validating this function produced.
+    pub fn warnings(&self) -> &[WithSpan<ValidationWarning>] {
+        &self.warnings
+    }
+    /// Returns the shader stages this function was analyzed for.
+    ///
+    /// Analyses that only matter to other stages were skipped, so what they
+    /// would have found is not known. Without [`FRAGMENT`], derivative
+    /// uniformity was not analyzed: [`warnings`] has no derivative
+    /// uniformity warnings whether or not there should be any, expressions
+    /// that need uniform control flow were not checked for it, and the
+    /// [`requirements`] of [`uniformity`] leave out what those expressions
+    /// need.
+    ///
+    /// See [`Validator::stages`].
+    ///
+    /// [`FRAGMENT`]: ShaderStages::FRAGMENT
+    /// [`warnings`]: FunctionInfo::warnings
+    /// [`requirements`]: Uniformity::requirements
+    /// [`uniformity`]: FunctionInfo::uniformity
+    /// [`Validator::stages`]: super::Validator::stages
+    pub const fn analyzed_stages(&self) -> ShaderStages {
+        self.analyzed_stages
+    }
     pub fn dominates_global_use(&self, other: &Self) -> bool {
         for (self_global_uses, other_global_uses) in
             self.global_uses.iter().zip(other.global_uses.iter())
@@ -302,14 +490,91 @@ impl ops::Index<Handle<crate::Expression>> for FunctionInfo {
 #[derive(Clone, Copy, Debug, thiserror::Error)]
 #[cfg_attr(test, derive(PartialEq))]
 pub enum UniformityDisruptor {
-    #[error("Expression {0:?} produced non-uniform result, and control flow depends on it")]
-    Expression(Handle<crate::Expression>),
+    #[error(
+        "Expression {origin:?} produced non-uniform result, and control flow depends on it \
+        through the condition {condition:?}"
+    )]
+    Expression {
+        /// The expression whose result first differs between invocations.
+        origin: Handle<crate::Expression>,
+        /// The condition or selector that depends on `origin`.
+        condition: Handle<crate::Expression>,
+        /// The span of the statement that branches on `condition`.
+        statement: crate::Span,
+    },
     #[error("There is a Return earlier in the control flow of the function")]
     Return,
     #[error("There is a Discard earlier in the entry point across all called functions")]
     Discard,
 }
 
+/// Something valid that may not behave as intended.
+///
+/// See [`FunctionInfo::warnings`].
+#[derive(Clone, Debug, thiserror::Error)]
+#[cfg_attr(test, derive(PartialEq))]
+pub enum ValidationWarning {
+    #[error(
+        "Expression {expression:?} samples with an implicit level of detail in \
+        control flow that may not be uniform, so its result may be undefined: {cause}"
+    )]
+    ImplicitLevelInNonUniformControlFlow {
+        expression: Handle<crate::Expression>,
+        cause: UniformityDisruptor,
+    },
+}
+
+impl UniformityDisruptor {
+    /// Point `error` at each step from the non-uniform value to the control
+    /// flow that depends on it.
+    ///
+    /// The span of the operation that needs uniform control flow should
+    /// already be in `error`.
+    fn add_spans<E>(
+        self,
+        error: WithSpan<E>,
+        expression_arena: &Arena<crate::Expression>,
+    ) -> WithSpan<E> {
+        match self {
+            Self::Expression {
+                origin,
+                condition,
+                statement,
+            } => {
+                let error = error
+                    .with_span(statement, "this control flow is not uniform")
+                    .with_span(
+                        expression_arena.get_span(origin),
+                        "because this value may differ between invocations",
+                    );
+                if condition == origin {
+                    error
+                } else {
+                    error.with_span(
+                        expression_arena.get_span(condition),
+                        "and the condition depends on it",
+                    )
+                }
+            }
+            Self::Return | Self::Discard => error,
+        }
+    }
+}
+
+impl crate::span::Diagnose for ValidationWarning {
+    fn code(&self) -> &'static str {
+        match *self {
+            Self::ImplicitLevelInNonUniformControlFlow { .. } => {
+                "validation::implicit-level-in-non-uniform-control-flow"
+            }
+        }
+    }
+
+    fn severity(&self) -> crate::span::Severity {
+        crate::span::Severity::Warning
+    }
+}
+
 impl FunctionInfo {
     /// Adds a value-type reference to an expression.
     #[must_use]
@@ -354,6 +619,40 @@ impl FunctionInfo {
         info.uniformity.non_uniform_result
     }
 
+    /// Marks the globals that `pointer` may point into with `global_use`.
+    ///
+    /// A [`Select`] between pointers has no single assignable global, so
+    /// [`add_ref_impl`] can't see through it to mark writes. Instead, follow
+    /// the pointer back to every variable it could be based on.
+    ///
+    /// [`Select`]: crate::Expression::Select
+    /// [`add_ref_impl`]: FunctionInfo::add_ref_impl
+    fn add_selected_pointer_use(
+        &mut self,
+        pointer: Handle<crate::Expression>,
+        global_use: GlobalUse,
+        expression_arena: &Arena<crate::Expression>,
+    ) {
+        use crate::Expression as E;
+
+        let mut pending = vec![pointer];
+        let mut selected = false;
+        while let Some(pointer) = pending.pop() {
+            match expression_arena[pointer] {
+                E::Access { base, .. } | E::AccessIndex { base, .. } => pending.push(base),
+                E::Select { accept, reject, .. } => {
+                    selected = true;
+                    pending.push(accept);
+                    pending.push(reject);
+                }
+                E::GlobalVariable(global) if selected => {
+                    self.global_uses[global.index()] |= global_use;
+                }
+                _ => {}
+            }
+        }
+    }
+
     /// Inherit information from a called function.
     fn process_call(
         &mut self,
@@ -527,6 +826,10 @@ impl FunctionInfo {
                 non_uniform_result: self.add_ref(vector),
                 requirements: UniformityRequirements::empty(),
             },
+            E::Repeat { value, .. } => Uniformity {
+                non_uniform_result: self.add_ref(value),
+                requirements: UniformityRequirements::empty(),
+            },
             E::Literal(_) | E::Constant(_) | E::ZeroValue(_) => Uniformity::new(),
             E::Compose { ref components, .. } => {
                 let non_uniform_result = components
@@ -547,7 +850,9 @@ impl FunctionInfo {
                         // per-work-group built-ins are uniform
                         | crate::BuiltIn::WorkGroupId
                         | crate::BuiltIn::WorkGroupSize
-                        | crate::BuiltIn::NumWorkGroups => true,
+                        | crate::BuiltIn::NumWorkGroups
+                        // the same for every invocation of a subgroup
+                        | crate::BuiltIn::SubgroupSize => true,
                         _ => false,
                     },
                     // only flat inputs are uniform
@@ -740,6 +1045,25 @@ impl FunctionInfo {
                 non_uniform_result: self.add_ref(query),
                 requirements: UniformityRequirements::empty(),
             },
+            // Subgroups can differ across the workgroup, so even results that
+            // all invocations of a subgroup agree on aren't uniform.
+            E::SubgroupOperation { op, argument } => {
+                self.add_ref(argument);
+                if let Some(index) = op.index() {
+                    self.add_ref(index);
+                }
+                Uniformity {
+                    non_uniform_result: Some(handle),
+                    requirements: UniformityRequirements::empty(),
+                }
+            }
+            E::NonUniform(value) => {
+                self.add_ref(value);
+                Uniformity {
+                    non_uniform_result: Some(handle),
+                    requirements: UniformityRequirements::empty(),
+                }
+            }
         };
 
         let ty = resolve_context.resolve(expression, |h| Ok(&self[h].ty))?;
@@ -752,7 +1076,48 @@ impl FunctionInfo {
         Ok(())
     }
 
+    /// Warn if `expr`, which is in non-uniform control flow because of
+    /// `cause`, samples with an implicit level of detail.
+    ///
+    /// Such sampling computes derivatives, which are undefined when not
+    /// all invocations of a quad take part. If the module's diagnostic
+    /// filters make `derivative_uniformity` an error, return one instead.
+    #[cfg(feature = "validate")]
+    fn check_derivative_uniformity(
+        &mut self,
+        expr: Handle<crate::Expression>,
+        cause: UniformityDisruptor,
+        expression_arena: &Arena<crate::Expression>,
+    ) -> Result<(), WithSpan<FunctionError>> {
+        if self.derivative_uniformity == Severity::Off {
+            return Ok(());
+        }
+        if let crate::Expression::ImageSample {
+            level: crate::SampleLevel::Auto | crate::SampleLevel::Bias(_),
+            ..
+        } = expression_arena[expr]
+        {
+            if self.derivative_uniformity == Severity::Error {
+                let error = FunctionError::NonUniformDerivative {
+                    expression: expr,
+                    cause,
+                }
+                .with_span_handle(expr, expression_arena);
+                return Err(cause.add_spans(error, expression_arena));
+            }
+            let warning = ValidationWarning::ImplicitLevelInNonUniformControlFlow {
+                expression: expr,
+                cause,
+            }
+            .with_span_handle(expr, expression_arena);
+            self.warnings
+                .push(cause.add_spans(warning, expression_arena));
+        }
+        Ok(())
+    }
+
     /// Analyzes the uniformity requirements of a block (as a sequence of statements).
+    ///
     /// Returns the uniformity characteristics at the *function* level, i.e.
     /// whether or not the function requires to be called in uniform control flow,
     /// and whether the produced result is not disrupting the control flow.
@@ -772,21 +1137,32 @@ impl FunctionInfo {
         use crate::Statement as S;
 
         let mut combined_uniformity = FunctionUniformity::new();
-        for statement in statements {
+        for (statement, &span) in statements.span_iter() {
             let uniformity = match *statement {
+                // Only derivatives and implicit-level sampling make
+                // expressions need uniform control flow, and only fragment
+                // shaders have them, so there's nothing to analyze.
+                S::Emit(_) if !self.analyzed_stages.contains(ShaderStages::FRAGMENT) => {
+                    FunctionUniformity::new()
+                }
                 S::Emit(ref range) => {
                     let mut requirements = UniformityRequirements::empty();
                     for expr in range.clone() {
                         let req = self.expressions[expr.index()].uniformity.requirements;
                         #[cfg(feature = "validate")]
+                        if let Some(cause) = disruptor {
+                            self.check_derivative_uniformity(expr, cause, expression_arena)?;
+                        }
+                        #[cfg(feature = "validate")]
                         if self
                             .flags
                             .contains(super::ValidationFlags::CONTROL_FLOW_UNIFORMITY)
                             && !req.is_empty()
                         {
                             if let Some(cause) = disruptor {
-                                return Err(FunctionError::NonUniformControlFlow(req, expr, cause)
-                                    .with_span_handle(expr, expression_arena));
+                                let error = FunctionError::NonUniformControlFlow(req, expr, cause)
+                                    .with_span_handle(expr, expression_arena);
+                                return Err(cause.add_spans(error, expression_arena));
                             }
                         }
                         requirements |= req;
@@ -830,10 +1206,14 @@ impl FunctionInfo {
                     {
                         let condition_nur = self.add_ref(pointer);
                         let this_disruptor =
-                            disruptor.or(condition_nur.map(UniformityDisruptor::Expression));
+                            disruptor.or(condition_nur.map(|origin| UniformityDisruptor::Expression {
+                                origin,
+                                condition: pointer,
+                                statement: span,
+                            }));
                         if let Some(cause) = this_disruptor {
                             return Err(FunctionError::NonUniformWorkgroupUniformLoad(cause)
-                                .with_span_static(*span, "WorkGroupUniformLoad"));
+                                .with_span_static(span, "WorkGroupUniformLoad"));
                         }
                     } */
                     FunctionUniformity {
@@ -854,7 +1234,11 @@ impl FunctionInfo {
                 } => {
                     let condition_nur = self.add_ref(condition);
                     let branch_disruptor =
-                        disruptor.or(condition_nur.map(UniformityDisruptor::Expression));
+                        disruptor.or(condition_nur.map(|origin| UniformityDisruptor::Expression {
+                            origin,
+                            condition,
+                            statement: span,
+                        }));
                     let accept_uniformity = self.process_block(
                         accept,
                         other_functions,
@@ -875,7 +1259,11 @@ impl FunctionInfo {
                 } => {
                     let selector_nur = self.add_ref(selector);
                     let branch_disruptor =
-                        disruptor.or(selector_nur.map(UniformityDisruptor::Expression));
+                        disruptor.or(selector_nur.map(|origin| UniformityDisruptor::Expression {
+                            origin,
+                            condition: selector,
+                            statement: span,
+                        }));
                     let mut uniformity = FunctionUniformity::new();
                     let mut case_disruptor = branch_disruptor;
                     for case in cases.iter() {
@@ -929,6 +1317,7 @@ impl FunctionInfo {
                 // so we can ignore their non-uniformity.
                 S::Store { pointer, value } => {
                     let _ = self.add_ref_impl(pointer, GlobalUse::WRITE);
+                    self.add_selected_pointer_use(pointer, GlobalUse::WRITE, expression_arena);
                     let _ = self.add_ref(value);
                     FunctionUniformity::new()
                 }
@@ -946,6 +1335,21 @@ impl FunctionInfo {
                     let _ = self.add_ref(value);
                     FunctionUniformity::new()
                 }
+                S::ImageAtomic {
+                    image,
+                    coordinate,
+                    array_index,
+                    fun: _,
+                    value,
+                } => {
+                    let _ = self.add_ref_impl(image, GlobalUse::READ | GlobalUse::WRITE);
+                    if let Some(expr) = array_index {
+                        let _ = self.add_ref(expr);
+                    }
+                    let _ = self.add_ref(coordinate);
+                    let _ = self.add_ref(value);
+                    FunctionUniformity::new()
+                }
                 S::Call {
                     function,
                     ref arguments,
@@ -965,6 +1369,7 @@ impl FunctionInfo {
                     result: _,
                 } => {
                     let _ = self.add_ref_impl(pointer, GlobalUse::WRITE);
+                    self.add_selected_pointer_use(pointer, GlobalUse::WRITE, expression_arena);
                     let _ = self.add_ref(value);
                     if let crate::AtomicFunction::Exchange { compare: Some(cmp) } = *fun {
                         let _ = self.add_ref(cmp);
@@ -1012,10 +1417,28 @@ impl ModuleInfo {
         fun: &crate::Function,
         module: &crate::Module,
         flags: ValidationFlags,
+        warning_flags: WarningFlags,
         capabilities: super::Capabilities,
+        analyzed_stages: ShaderStages,
     ) -> Result<FunctionInfo, WithSpan<FunctionError>> {
+        // A filter can make the rule an error even if the validator was
+        // told not to warn about it, but not bring back a silenced warning.
+        let derivative_uniformity = match diagnostic_filter::severity_of(
+            &module.diagnostic_filters,
+            FilterableTriggeringRule::DerivativeUniformity,
+        )
+        .unwrap_or_default()
+        {
+            Severity::Info | Severity::Warning
+                if !warning_flags.contains(WarningFlags::DERIVATIVE_UNIFORMITY) =>
+            {
+                Severity::Off
+            }
+            severity => severity,
+        };
         let mut info = FunctionInfo {
             flags,
+            derivative_uniformity,
             available_stages: ShaderStages::all(),
             uniformity: Uniformity::new(),
             may_kill: false,
@@ -1024,6 +1447,10 @@ impl ModuleInfo {
             expressions: vec![ExpressionInfo::new(); fun.expressions.len()].into_boxed_slice(),
             sampling: crate::FastHashSet::default(),
             dual_source_blending: false,
+            warnings: Vec::new(),
+            writes_depth: false,
+            cost: Cost::default(),
+            analyzed_stages,
         };
         let resolve_context =
             ResolveContext::with_locals(module, &fun.local_variables, &fun.arguments);
@@ -1047,9 +1474,18 @@ impl ModuleInfo {
             }
         }
 
+        // A global that's only named, as by WGSL's phony assignment `_ = e`,
+        // is still part of the function's interface.
+        for &handle in fun.named_expressions.keys() {
+            if let crate::Expression::GlobalVariable(global) = fun.expressions[handle] {
+                info.global_uses[global.index()] |= GlobalUse::QUERY;
+            }
+        }
+
         let uniformity = info.process_block(&fun.body, &self.functions, None, &fun.expressions)?;
         info.uniformity = uniformity.result;
         info.may_kill = uniformity.exit.contains(ExitFlags::MAY_KILL);
+        info.cost = Cost::of_block(&fun.body, &fun.expressions, &self.functions, 0);
 
         Ok(info)
     }
@@ -1057,6 +1493,72 @@ impl ModuleInfo {
     pub fn get_entry_point(&self, index: usize) -> &FunctionInfo {
         &self.entry_points[index]
     }
+
+    /// Returns a static estimate of the cost of the entry point at `index`,
+    /// including the functions it calls.
+    pub fn cost(&self, index: usize) -> Cost {
+        self.entry_points[index].cost
+    }
+
+    /// Return how much memory the analysis holds.
+    ///
+    /// Like [`ArenaUsage`], this counts the tables themselves, not the
+    /// allocations their entries own.
+    pub fn memory_usage(&self) -> ModuleInfoUsage {
+        let mut usage = ModuleInfoUsage {
+            type_flags: ArenaUsage::of_vec(&self.type_flags),
+            functions: ArenaUsage::of_vec(&self.functions),
+            entry_points: ArenaUsage::of_vec(&self.entry_points),
+            const_expression_types: ArenaUsage::of_slice(&self.const_expression_types),
+            ..ModuleInfoUsage::default()
+        };
+        for info in self.functions.iter().chain(self.entry_points.iter()) {
+            usage.expressions += ArenaUsage::of_slice(&info.expressions);
+            usage.global_uses += ArenaUsage::of_slice(&info.global_uses);
+        }
+        usage
+    }
+
+    /// Free the memory the analysis holds beyond what its tables need.
+    pub fn shrink_to_fit(&mut self) {
+        self.type_flags.shrink_to_fit();
+        self.functions.shrink_to_fit();
+        self.entry_points.shrink_to_fit();
+        for info in self
+            .functions
+            .iter_mut()
+            .chain(self.entry_points.iter_mut())
+        {
+            info.warnings.shrink_to_fit();
+        }
+    }
+}
+
+/// How much memory each of a [`ModuleInfo`]'s tables holds.
+///
+/// See [`ModuleInfo::memory_usage`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ModuleInfoUsage {
+    pub type_flags: ArenaUsage,
+    pub functions: ArenaUsage,
+    pub entry_points: ArenaUsage,
+    pub const_expression_types: ArenaUsage,
+    /// The [`ExpressionInfo`]s of all functions and entry points together.
+    pub expressions: ArenaUsage,
+    /// The [`GlobalUse`]s of all functions and entry points together.
+    pub global_uses: ArenaUsage,
+}
+
+impl ModuleInfoUsage {
+    /// The number of bytes all the tables hold.
+    pub const fn total_bytes(&self) -> usize {
+        self.type_flags.bytes
+            + self.functions.bytes
+            + self.entry_points.bytes
+            + self.const_expression_types.bytes
+            + self.expressions.bytes
+            + self.global_uses.bytes
+    }
 }
 
 #[test]
@@ -1131,6 +1633,7 @@ fn uniform_control_flow() {
 
     let mut info = FunctionInfo {
         flags: ValidationFlags::all(),
+        derivative_uniformity: Severity::Warning,
         available_stages: ShaderStages::all(),
         uniformity: Uniformity::new(),
         may_kill: false,
@@ -1139,6 +1642,10 @@ fn uniform_control_flow() {
         expressions: vec![ExpressionInfo::new(); expressions.len()].into_boxed_slice(),
         sampling: crate::FastHashSet::default(),
         dual_source_blending: false,
+        warnings: Vec::new(),
+        writes_depth: false,
+        cost: Cost::default(),
+        analyzed_stages: ShaderStages::all(),
     };
     let resolve_context = ResolveContext {
         constants: &Arena::new(),
@@ -1225,7 +1732,11 @@ fn uniform_control_flow() {
                 Err(FunctionError::NonUniformControlFlow(
                     UniformityRequirements::DERIVATIVE,
                     derivative_expr,
-                    UniformityDisruptor::Expression(non_uniform_global_expr)
+                    UniformityDisruptor::Expression {
+                        origin: non_uniform_global_expr,
+                        condition: non_uniform_global_expr,
+                        statement: crate::Span::UNDEFINED,
+                    }
                 )
                 .with_span()),
             );
@@ -1282,3 +1793,51 @@ fn uniform_control_flow() {
     );
     assert_eq!(info[non_uniform_global], GlobalUse::READ | GlobalUse::WRITE);
 }
+
+#[test]
+#[cfg(all(feature = "validate", feature = "wgsl-in"))]
+fn cost() {
+    let module = crate::front::wgsl::parse_str(
+        "
+        @group(0) @binding(0) var tex: texture_2d<f32>;
+        @group(0) @binding(1) var samp: sampler;
+
+        fn shade(uv: vec2<f32>) -> vec4<f32> {
+            var color = vec4(0.0);
+            for (var i = 0; i < 4; i++) {
+                color += textureSample(tex, samp, uv * f32(i));
+            }
+            return color;
+        }
+
+        @fragment
+        fn main(@location(0) uv: vec2<f32>) -> @location(0) vec4<f32> {
+            if uv.x > 0.5 {
+                return shade(uv);
+            }
+            loop {
+                for (var j = 0; j < 2; j++) {}
+                break;
+            }
+            return textureSample(tex, samp, uv);
+        }
+        ",
+    )
+    .unwrap();
+    let info = crate::valid::Validator::new(
+        crate::valid::ValidationFlags::all(),
+        crate::valid::Capabilities::default(),
+    )
+    .validate(&module)
+    .unwrap();
+
+    let (shade, _) = module.functions.iter().next().unwrap();
+    assert_eq!(info[shade].cost.texture_samples, 1);
+    assert_eq!(info[shade].cost.loop_depth, 1);
+
+    let cost = info.cost(0);
+    assert_eq!(cost.texture_samples, 2);
+    // The `if`, and the conditions of both `for` loops.
+    assert_eq!(cost.branches, 3);
+    assert_eq!(cost.loop_depth, 2);
+}
@@ -131,3 +131,32 @@ pub fn validate_compose(
 
     Ok(())
 }
+
+/// Validate a [`Repeat`] of `value_resolution` as a `self_ty_handle`.
+///
+/// [`Repeat`]: crate::Expression::Repeat
+#[cfg(feature = "validate")]
+pub fn validate_repeat(
+    self_ty_handle: Handle<crate::Type>,
+    gctx: crate::proc::GlobalCtx,
+    value_resolution: &TypeResolution,
+) -> Result<(), ComposeError> {
+    match gctx.types[self_ty_handle].inner {
+        crate::TypeInner::Array {
+            base,
+            size: crate::ArraySize::Constant(_),
+            stride: _,
+        } => {
+            let value_inner = value_resolution.inner_with(gctx.types);
+            if !gctx.types[base].inner.equivalent(value_inner, gctx.types) {
+                log::error!("Repeated value type {:?}", value_resolution);
+                return Err(ComposeError::ComponentType { index: 0 });
+            }
+            Ok(())
+        }
+        ref other => {
+            log::error!("Repeating as {:?}", other);
+            Err(ComposeError::Type(self_ty_handle))
+        }
+    }
+}
@@ -1,10 +1,12 @@
 #[cfg(feature = "validate")]
 use super::{
-    compose::validate_compose, validate_atomic_compare_exchange_struct, FunctionInfo, ModuleInfo,
-    ShaderStages, TypeFlags,
+    compose::{validate_compose, validate_repeat},
+    validate_atomic_compare_exchange_struct, FunctionInfo, ModuleInfo, ShaderStages, TypeFlags,
 };
 #[cfg(feature = "validate")]
 use crate::arena::UniqueArena;
+#[cfg(feature = "validate")]
+use bit_set::BitSet;
 
 use crate::{
     arena::Handle,
@@ -38,6 +40,8 @@ pub enum ExpressionError {
     InvalidRayQueryType(Handle<crate::Expression>),
     #[error("Splatting {0:?} can't be done")]
     InvalidSplatType(Handle<crate::Expression>),
+    #[error("The operands of the override expression don't match")]
+    InvalidOverrideOperands,
     #[error("Swizzling {0:?} can't be done")]
     InvalidVectorType(Handle<crate::Expression>),
     #[error("Swizzle component {0:?} is outside of vector size {1:?}")]
@@ -74,6 +78,11 @@ pub enum ExpressionError {
     ExpectedSamplerType(Handle<crate::Type>),
     #[error("Unable to operate on image class {0:?}")]
     InvalidImageClass(crate::ImageClass),
+    #[error(
+        "Only float images can be sampled, but this one holds {0:?} texels; \
+        use a load or a gather instead"
+    )]
+    InvalidSampledImageKind(crate::ScalarKind),
     #[error("Derivatives can only be taken from scalar and vector floats")]
     InvalidDerivative,
     #[error("Image array index parameter is misplaced")]
@@ -86,6 +95,12 @@ pub enum ExpressionError {
     InvalidImageOtherIndexType(Handle<crate::Expression>),
     #[error("Image coordinate type of {1:?} does not match dimension {0:?}")]
     InvalidImageCoordinateType(crate::ImageDimension, Handle<crate::Expression>),
+    #[error("Value {value:?} can not be stored to a {format:?} image, which takes 4 components of {expected:?}")]
+    InvalidImageStoreValue {
+        value: Handle<crate::Expression>,
+        format: crate::StorageFormat,
+        expected: crate::ScalarKind,
+    },
     #[error("Comparison sampling mismatch: image has class {image:?}, but the sampler is comparison={sampler}, and the reference was provided={has_ref}")]
     ComparisonSamplingMismatch {
         image: crate::ImageClass,
@@ -122,6 +137,14 @@ pub enum ExpressionError {
         "workgroupUniformLoad result type can't be {0:?}. It can only be a constructible type."
     )]
     InvalidWorkGroupUniformLoadResultType(Handle<crate::Type>),
+    #[error("Subgroup operation {0:?} can't work with {1:?}")]
+    InvalidSubgroupArgument(crate::SubgroupOperation, Handle<crate::Expression>),
+    #[error("Subgroup invocation ID or offset {0:?} is not a u32 scalar")]
+    InvalidSubgroupIndex(Handle<crate::Expression>),
+    #[error("Subgroup broadcast invocation ID {0:?} is not a constant")]
+    SubgroupIndexMustBeConstant(Handle<crate::Expression>),
+    #[error("Non-uniform value {0:?} is not an integer scalar")]
+    InvalidNonUniformValue(Handle<crate::Expression>),
     #[error("Shader requires capability {0:?}")]
     MissingCapabilities(super::Capabilities),
     #[error(transparent)]
@@ -136,6 +159,10 @@ pub enum ConstExpressionError {
     Compose(#[from] super::ComposeError),
     #[error("Splatting {0:?} can't be done")]
     InvalidSplatType(Handle<crate::Expression>),
+    #[error("The operands of the override expression don't match")]
+    InvalidOverrideOperands,
+    #[error("Accessing index {1} is out of {0:?} bounds")]
+    IndexOutOfBounds(Handle<crate::Expression>, u32),
     #[error("Type resolution failed")]
     Type(#[from] ResolveError),
     #[error(transparent)]
@@ -182,14 +209,22 @@ impl<'a> std::ops::Index<Handle<crate::Expression>> for ExpressionTypeResolver<'
 
 #[cfg(feature = "validate")]
 impl super::Validator {
+    /// Validate the constant expression `handle`.
+    ///
+    /// The operations that Naga can't fold are only allowed in [override
+    /// expressions], which `override_expressions` holds the indices of.
+    ///
+    /// [override expressions]: crate#override-expressions
     pub(super) fn validate_const_expression(
         &self,
         handle: Handle<crate::Expression>,
         gctx: crate::proc::GlobalCtx,
         mod_info: &ModuleInfo,
+        override_expressions: &BitSet,
     ) -> Result<(), ConstExpressionError> {
         use crate::Expression as E;
 
+        let is_override = override_expressions.contains(handle.index());
         match gctx.const_expressions[handle] {
             E::Literal(literal) => {
                 self.validate_literal(literal)?;
@@ -202,10 +237,75 @@ impl super::Validator {
                     components.iter().map(|&handle| mod_info[handle].clone()),
                 )?;
             }
+            E::Repeat { ty, value } => validate_repeat(ty, gctx, &mod_info[value])?,
             E::Splat { value, .. } => match *mod_info[value].inner_with(gctx.types) {
                 crate::TypeInner::Scalar { .. } => {}
                 _ => return Err(super::ConstExpressionError::InvalidSplatType(value)),
             },
+            E::Unary { op, expr } if is_override => {
+                use crate::{ScalarKind as Sk, UnaryOperator as Uo};
+                match (op, mod_info[expr].inner_with(gctx.types).scalar_kind()) {
+                    (Uo::Negate, Some(Sk::Float | Sk::Sint))
+                    | (Uo::LogicalNot, Some(Sk::Bool))
+                    | (Uo::BitwiseNot, Some(Sk::Sint | Sk::Uint)) => {}
+                    _ => return Err(ConstExpressionError::InvalidOverrideOperands),
+                }
+            }
+            E::As {
+                expr,
+                kind,
+                convert,
+            } if is_override => {
+                let base_width = match *mod_info[expr].inner_with(gctx.types) {
+                    crate::TypeInner::Scalar { width, .. }
+                    | crate::TypeInner::Vector { width, .. }
+                    | crate::TypeInner::Matrix { width, .. } => width,
+                    _ => return Err(ConstExpressionError::InvalidOverrideOperands),
+                };
+                self.check_width(kind, convert.unwrap_or(base_width))?;
+            }
+            E::AccessIndex { base, index } if is_override => {
+                let limit = match *mod_info[base].inner_with(gctx.types) {
+                    crate::TypeInner::Vector { size, .. } => size as u32,
+                    crate::TypeInner::Matrix { columns, .. } => columns as u32,
+                    crate::TypeInner::Array {
+                        size: crate::ArraySize::Constant(len),
+                        ..
+                    } => len.get(),
+                    crate::TypeInner::Struct { ref members, .. } => members.len() as u32,
+                    _ => return Err(ConstExpressionError::InvalidOverrideOperands),
+                };
+                if index >= limit {
+                    return Err(ConstExpressionError::IndexOutOfBounds(base, limit));
+                }
+            }
+            E::Binary { op, left, right } if is_override => {
+                let left_ty = mod_info[left].inner_with(gctx.types);
+                let right_ty = mod_info[right].inner_with(gctx.types);
+                let valid = match op {
+                    crate::BinaryOperator::ShiftLeft | crate::BinaryOperator::ShiftRight => {
+                        right_ty.scalar_kind() == Some(crate::ScalarKind::Uint)
+                    }
+                    _ => left_ty.equivalent(right_ty, gctx.types),
+                };
+                if !valid {
+                    return Err(ConstExpressionError::InvalidOverrideOperands);
+                }
+            }
+            E::Select {
+                condition,
+                accept,
+                reject,
+            } if is_override => {
+                let condition_ty = mod_info[condition].inner_with(gctx.types);
+                let accept_ty = mod_info[accept].inner_with(gctx.types);
+                let reject_ty = mod_info[reject].inner_with(gctx.types);
+                if condition_ty.scalar_kind() != Some(crate::ScalarKind::Bool)
+                    || !accept_ty.equivalent(reject_ty, gctx.types)
+                {
+                    return Err(ConstExpressionError::InvalidOverrideOperands);
+                }
+            }
             _ => return Err(super::ConstExpressionError::NonConst),
         }
 
@@ -260,6 +360,22 @@ impl super::Validator {
                 {
                     return Err(ExpressionError::IndexMustBeConstant(base));
                 }
+                if let Ti::Pointer {
+                    base: pointee,
+                    space: crate::AddressSpace::Uniform,
+                } = *base_type
+                {
+                    if matches!(module.types[pointee].inner, Ti::Array { .. })
+                        && !self
+                            .capabilities
+                            .contains(super::Capabilities::UNIFORM_BUFFER_DYNAMIC_INDEXING)
+                        && function.expressions[index].is_dynamic_index(module)
+                    {
+                        return Err(ExpressionError::MissingCapabilities(
+                            super::Capabilities::UNIFORM_BUFFER_DYNAMIC_INDEXING,
+                        ));
+                    }
+                }
 
                 // If we know both the length and the index, we can do the
                 // bounds check now.
@@ -359,6 +475,10 @@ impl super::Validator {
                 )?;
                 ShaderStages::all()
             }
+            E::Repeat { ty, value } => {
+                validate_repeat(ty, module.to_ctx(), &info[value].ty)?;
+                ShaderStages::all()
+            }
             E::FunctionArgument(index) => {
                 if index >= function.arguments.len() as u32 {
                     return Err(ExpressionError::FunctionArgumentDoesntExist(index));
@@ -434,6 +554,10 @@ impl super::Validator {
                         kind: crate::ScalarKind::Uint | crate::ScalarKind::Sint,
                         multi: false,
                     } if gather.is_some() => false,
+                    crate::ImageClass::Sampled {
+                        kind: kind @ (crate::ScalarKind::Uint | crate::ScalarKind::Sint),
+                        multi: false,
+                    } => return Err(ExpressionError::InvalidSampledImageKind(kind)),
                     crate::ImageClass::Depth { multi: false } => true,
                     _ => return Err(ExpressionError::InvalidImageClass(class)),
                 };
@@ -486,8 +610,13 @@ impl super::Validator {
                         } => {}
                         _ => return Err(ExpressionError::InvalidDepthReference(expr)),
                     }
+                    // Explicit levels other than zero can't be expressed in
+                    // WGSL or in older HLSL shader models; the backends are
+                    // responsible for rejecting them where needed.
                     match level {
-                        crate::SampleLevel::Auto | crate::SampleLevel::Zero => {}
+                        crate::SampleLevel::Auto
+                        | crate::SampleLevel::Zero
+                        | crate::SampleLevel::Exact(_) => {}
                         _ => return Err(ExpressionError::InvalidDepthSampleLevel),
                     }
                 }
@@ -855,9 +984,20 @@ impl super::Validator {
                         width: _,
                     } => {
                         // When `condition` is a single boolean, `accept` and
-                        // `reject` can be vectors or scalars.
+                        // `reject` can be vectors or scalars, or pointers.
                         match *accept_inner {
                             Ti::Scalar { .. } | Ti::Vector { .. } => true,
+                            Ti::Pointer { .. } | Ti::ValuePointer { .. } => {
+                                if !self
+                                    .capabilities
+                                    .contains(super::Capabilities::POINTER_SELECT)
+                                {
+                                    return Err(ExpressionError::MissingCapabilities(
+                                        super::Capabilities::POINTER_SELECT,
+                                    ));
+                                }
+                                true
+                            }
                             _ => false,
                         }
                     }
@@ -1537,6 +1677,68 @@ impl super::Validator {
                     return Err(ExpressionError::InvalidRayQueryType(query));
                 }
             },
+            E::SubgroupOperation { op, argument } => {
+                use crate::{SubgroupOperation as So, SubgroupReduction as Sr};
+                if !self.capabilities.contains(super::Capabilities::SUBGROUP) {
+                    return Err(ExpressionError::MissingCapabilities(
+                        super::Capabilities::SUBGROUP,
+                    ));
+                }
+                let argument_good = match (op, &resolver[argument]) {
+                    (So::Ballot | So::All | So::Any, &Ti::Scalar { kind, .. }) => kind == Sk::Bool,
+                    (
+                        So::Reduce(reduction)
+                        | So::InclusiveScan(reduction)
+                        | So::ExclusiveScan(reduction),
+                        &(Ti::Scalar { kind, .. } | Ti::Vector { kind, .. }),
+                    ) => match reduction {
+                        Sr::Add | Sr::Mul | Sr::Min | Sr::Max => kind != Sk::Bool,
+                        Sr::And | Sr::Or | Sr::Xor => matches!(kind, Sk::Sint | Sk::Uint),
+                    },
+                    (
+                        So::BroadcastFirst
+                        | So::Broadcast(_)
+                        | So::Shuffle(_)
+                        | So::ShuffleDown(_)
+                        | So::ShuffleUp(_)
+                        | So::ShuffleXor(_),
+                        &(Ti::Scalar { kind, .. } | Ti::Vector { kind, .. }),
+                    ) => kind != Sk::Bool,
+                    _ => false,
+                };
+                if !argument_good {
+                    log::error!("Subgroup operation {:?} of {:?}", op, resolver[argument]);
+                    return Err(ExpressionError::InvalidSubgroupArgument(op, argument));
+                }
+                if let Some(index) = op.index() {
+                    match resolver[index] {
+                        Ti::Scalar {
+                            kind: Sk::Uint,
+                            width: _,
+                        } => {}
+                        ref other => {
+                            log::error!("Subgroup invocation ID of {:?}", other);
+                            return Err(ExpressionError::InvalidSubgroupIndex(index));
+                        }
+                    }
+                    if let So::Broadcast(_) = op {
+                        if function.expressions[index].is_dynamic_index(module) {
+                            return Err(ExpressionError::SubgroupIndexMustBeConstant(index));
+                        }
+                    }
+                }
+                ShaderStages::COMPUTE | ShaderStages::FRAGMENT
+            }
+            E::NonUniform(value) => match resolver[value] {
+                Ti::Scalar {
+                    kind: Sk::Sint | Sk::Uint,
+                    width: _,
+                } => ShaderStages::all(),
+                ref other => {
+                    log::error!("Non-uniform value of {:?}", other);
+                    return Err(ExpressionError::InvalidNonUniformValue(value));
+                }
+            },
         };
         Ok(stages)
     }
@@ -1582,6 +1784,7 @@ pub fn check_literal_value(literal: crate::Literal) -> Result<(), LiteralError>
     let is_nan = match literal {
         crate::Literal::F64(v) => v.is_nan(),
         crate::Literal::F32(v) => v.is_nan(),
+        crate::Literal::F16(v) => crate::proc::f16_to_f32(v).is_nan(),
         _ => false,
     };
     if is_nan {
@@ -1591,6 +1794,7 @@ pub fn check_literal_value(literal: crate::Literal) -> Result<(), LiteralError>
     let is_infinite = match literal {
         crate::Literal::F64(v) => v.is_infinite(),
         crate::Literal::F32(v) => v.is_infinite(),
+        crate::Literal::F16(v) => crate::proc::f16_to_f32(v).is_infinite(),
         _ => false,
     };
     if is_infinite {
@@ -49,6 +49,12 @@ pub enum AtomicError {
     InvalidOperand(Handle<crate::Expression>),
     #[error("Result type for {0:?} doesn't match the statement")]
     ResultTypeMismatch(Handle<crate::Expression>),
+    #[error("Capability {0:?} is required for this atomic function on 64-bit values")]
+    MissingCapability(super::Capabilities),
+    #[error("Image {0:?} for an atomic is invalid.")]
+    InvalidImage(Handle<crate::Expression>),
+    #[error("Atomic function {0:?} is not allowed on images")]
+    InvalidImageFunction(crate::AtomicFunction),
 }
 
 #[derive(Clone, Debug, thiserror::Error)]
@@ -123,6 +129,8 @@ pub enum FunctionError {
     },
     #[error("Image store parameters are invalid")]
     InvalidImageStore(#[source] ExpressionError),
+    #[error("Image atomic parameters are invalid")]
+    InvalidImageAtomic(#[source] ExpressionError),
     #[error("Call to {function:?} is invalid")]
     InvalidCall {
         function: Handle<crate::Function>,
@@ -140,18 +148,27 @@ pub enum FunctionError {
     #[error("Ray Query {0:?} does not have a matching type")]
     InvalidRayQueryType(Handle<crate::Type>),
     #[error(
-        "Required uniformity of control flow for {0:?} in {1:?} is not fulfilled because of {2:?}"
+        "Required uniformity of control flow for {0:?} in {1:?} is not fulfilled because of {2}"
     )]
     NonUniformControlFlow(
         UniformityRequirements,
         Handle<crate::Expression>,
         UniformityDisruptor,
     ),
+    #[error(
+        "Expression {expression:?} samples with an implicit level of detail in control flow \
+        that may not be uniform, which the module's `derivative_uniformity` diagnostic filter \
+        makes an error: {cause}"
+    )]
+    NonUniformDerivative {
+        expression: Handle<crate::Expression>,
+        cause: UniformityDisruptor,
+    },
     #[error("Functions that are not entry points cannot have `@location` or `@builtin` attributes on their arguments: \"{name}\" has attributes")]
     PipelineInputRegularFunction { name: String },
     #[error("Functions that are not entry points cannot have `@location` or `@builtin` attributes on their return value types")]
     PipelineOutputRegularFunction,
-    #[error("Required uniformity for WorkGroupUniformLoad is not fulfilled because of {0:?}")]
+    #[error("Required uniformity for WorkGroupUniformLoad is not fulfilled because of {0}")]
     // The actual load statement will be "pointed to" by the span
     NonUniformWorkgroupUniformLoad(UniformityDisruptor),
     // This is only possible with a misbehaving frontend
@@ -159,6 +176,8 @@ pub enum FunctionError {
     WorkgroupUniformLoadExpressionMismatch(Handle<crate::Expression>),
     #[error("The expression {0:?} is not valid as a WorkGroupUniformLoad argument. It should be a Pointer in Workgroup address space")]
     WorkgroupUniformLoadInvalidPointer(Handle<crate::Expression>),
+    #[error("Calls are nested {depth} deep, exceeding the limit of {max}")]
+    CallsTooDeep { depth: u32, max: u32 },
 }
 
 bitflags::bitflags! {
@@ -345,9 +364,9 @@ impl super::Validator {
         context: &BlockContext,
     ) -> Result<(), WithSpan<FunctionError>> {
         let pointer_inner = context.resolve_type(pointer, &self.valid_expression_set)?;
-        let (ptr_kind, ptr_width) = match *pointer_inner {
-            crate::TypeInner::Pointer { base, .. } => match context.types[base].inner {
-                crate::TypeInner::Atomic { kind, width } => (kind, width),
+        let (ptr_kind, ptr_width, ptr_space) = match *pointer_inner {
+            crate::TypeInner::Pointer { base, space } => match context.types[base].inner {
+                crate::TypeInner::Atomic { kind, width } => (kind, width, space),
                 ref other => {
                     log::error!("Atomic pointer to type {:?}", other);
                     return Err(AtomicError::InvalidPointer(pointer)
@@ -374,6 +393,28 @@ impl super::Validator {
             }
         }
 
+        if ptr_width == 8 {
+            // Some targets can only do `min` and `max` on 64-bit atomics,
+            // and only in storage buffers.
+            let min_max = matches!(
+                *fun,
+                crate::AtomicFunction::Min | crate::AtomicFunction::Max
+            ) && matches!(ptr_space, crate::AddressSpace::Storage { .. });
+            let required = if min_max {
+                super::Capabilities::SHADER_INT64_ATOMIC_MIN_MAX
+                    | super::Capabilities::SHADER_INT64_ATOMIC_ALL_OPS
+            } else {
+                super::Capabilities::SHADER_INT64_ATOMIC_ALL_OPS
+            };
+            if !self.capabilities.intersects(required) {
+                return Err(AtomicError::MissingCapability(
+                    super::Capabilities::SHADER_INT64_ATOMIC_ALL_OPS,
+                )
+                .with_span_handle(pointer, context.expressions)
+                .into_other());
+            }
+        }
+
         if let crate::AtomicFunction::Exchange { compare: Some(cmp) } = *fun {
             if context.resolve_type(cmp, &self.valid_expression_set)? != value_inner {
                 log::error!("Atomic exchange comparison has a different type from the value");
@@ -414,6 +455,100 @@ impl super::Validator {
         Ok(())
     }
 
+    #[cfg(feature = "validate")]
+    fn validate_image_atomic(
+        &mut self,
+        image: Handle<crate::Expression>,
+        coordinate: Handle<crate::Expression>,
+        array_index: Option<Handle<crate::Expression>>,
+        fun: &crate::AtomicFunction,
+        value: Handle<crate::Expression>,
+        context: &BlockContext,
+    ) -> Result<(), WithSpan<FunctionError>> {
+        use crate::TypeInner as Ti;
+
+        // Atomics on images have no result, so there is nothing for a
+        // compare-exchange to report back.
+        if let crate::AtomicFunction::Exchange { compare: Some(_) } = *fun {
+            return Err(AtomicError::InvalidImageFunction(*fun)
+                .with_span_handle(image, context.expressions)
+                .into_other());
+        }
+
+        let (dim, arrayed, class) =
+            match *context.resolve_type(image, &self.valid_expression_set)? {
+                Ti::Image {
+                    dim,
+                    arrayed,
+                    class,
+                } => (dim, arrayed, class),
+                ref other => {
+                    log::error!("Image atomic on type {:?}", other);
+                    return Err(AtomicError::InvalidImage(image)
+                        .with_span_handle(image, context.expressions)
+                        .into_other());
+                }
+            };
+        let format = match class {
+            crate::ImageClass::Storage { format, access }
+                if access.contains(crate::StorageAccess::ATOMIC) =>
+            {
+                format
+            }
+            _ => {
+                return Err(
+                    FunctionError::InvalidImageAtomic(ExpressionError::InvalidImageClass(class))
+                        .with_span_handle(image, context.expressions),
+                )
+            }
+        };
+
+        match context
+            .resolve_type(coordinate, &self.valid_expression_set)?
+            .image_storage_coordinates()
+        {
+            Some(coord_dim) if coord_dim == dim => {}
+            _ => {
+                return Err(FunctionError::InvalidImageAtomic(
+                    ExpressionError::InvalidImageCoordinateType(dim, coordinate),
+                )
+                .with_span_handle(coordinate, context.expressions));
+            }
+        }
+        if arrayed != array_index.is_some() {
+            return Err(
+                FunctionError::InvalidImageAtomic(ExpressionError::InvalidImageArrayIndex)
+                    .with_span_handle(coordinate, context.expressions),
+            );
+        }
+        if let Some(expr) = array_index {
+            match *context.resolve_type(expr, &self.valid_expression_set)? {
+                Ti::Scalar {
+                    kind: crate::ScalarKind::Sint | crate::ScalarKind::Uint,
+                    width: _,
+                } => {}
+                _ => {
+                    return Err(FunctionError::InvalidImageAtomic(
+                        ExpressionError::InvalidImageArrayIndexType(expr),
+                    )
+                    .with_span_handle(expr, context.expressions));
+                }
+            }
+        }
+
+        let value_ty = Ti::Scalar {
+            kind: crate::ScalarKind::from(format),
+            width: 4,
+        };
+        if *context.resolve_type(value, &self.valid_expression_set)? != value_ty {
+            return Err(AtomicError::InvalidOperand(value)
+                .with_span_handle(value, context.expressions)
+                .into_other());
+        }
+
+        Ok(())
+    }
+
     #[cfg(feature = "validate")]
     fn validate_block_impl(
         &mut self,
@@ -632,17 +767,23 @@ impl super::Validator {
                     stages &= super::ShaderStages::COMPUTE;
                 }
                 S::Store { pointer, value } => {
-                    let mut current = pointer;
-                    loop {
+                    // Follow the pointer back to the variables it is based
+                    // on, through both operands of any pointer `Select`.
+                    let mut pending = vec![pointer];
+                    while let Some(current) = pending.pop() {
                         let _ = context
                             .resolve_pointer_type(current)
                             .map_err(|e| e.with_span())?;
                         match context.expressions[current] {
                             crate::Expression::Access { base, .. }
-                            | crate::Expression::AccessIndex { base, .. } => current = base,
+                            | crate::Expression::AccessIndex { base, .. } => pending.push(base),
+                            crate::Expression::Select { accept, reject, .. } => {
+                                pending.push(accept);
+                                pending.push(reject);
+                            }
                             crate::Expression::LocalVariable(_)
                             | crate::Expression::GlobalVariable(_)
-                            | crate::Expression::FunctionArgument(_) => break,
+                            | crate::Expression::FunctionArgument(_) => {}
                             _ => {
                                 return Err(FunctionError::InvalidStorePointer(current)
                                     .with_span_handle(pointer, context.expressions))
@@ -740,7 +881,7 @@ impl super::Validator {
                         ref inner => inner,
                     };
 
-                    let value_ty = match *global_ty {
+                    let format = match *global_ty {
                         Ti::Image {
                             class,
                             arrayed,
@@ -781,13 +922,7 @@ impl super::Validator {
                                 }
                             }
                             match class {
-                                crate::ImageClass::Storage { format, .. } => {
-                                    crate::TypeInner::Vector {
-                                        kind: format.into(),
-                                        size: crate::VectorSize::Quad,
-                                        width: 4,
-                                    }
-                                }
+                                crate::ImageClass::Storage { format, .. } => format,
                                 _ => {
                                     return Err(FunctionError::InvalidImageStore(
                                         ExpressionError::InvalidImageClass(class),
@@ -806,9 +941,26 @@ impl super::Validator {
                         }
                     };
 
+                    // Whatever the texel format, the value is always a
+                    // four-component vector of the format's channel type:
+                    // `f32` for float and normalized formats, and `i32` or
+                    // `u32` for integer formats. Backends are responsible for
+                    // converting it to the texel's actual representation.
+                    let expected = crate::ScalarKind::from(format);
+                    let value_ty = crate::TypeInner::Vector {
+                        kind: expected,
+                        size: crate::VectorSize::Quad,
+                        width: 4,
+                    };
                     if *context.resolve_type(value, &self.valid_expression_set)? != value_ty {
-                        return Err(FunctionError::InvalidStoreValue(value)
-                            .with_span_handle(value, context.expressions));
+                        return Err(FunctionError::InvalidImageStore(
+                            ExpressionError::InvalidImageStoreValue {
+                                value,
+                                format,
+                                expected,
+                            },
+                        )
+                        .with_span_handle(value, context.expressions));
                     }
                 }
                 S::Call {
@@ -832,6 +984,22 @@ impl super::Validator {
                 } => {
                     self.validate_atomic(pointer, fun, value, result, context)?;
                 }
+                S::ImageAtomic {
+                    image,
+                    coordinate,
+                    array_index,
+                    ref fun,
+                    value,
+                } => {
+                    self.validate_image_atomic(
+                        image,
+                        coordinate,
+                        array_index,
+                        fun,
+                        value,
+                        context,
+                    )?;
+                }
                 S::WorkGroupUniformLoad { pointer, result } => {
                     stages &= super::ShaderStages::COMPUTE;
                     let pointer_inner =
@@ -978,7 +1146,14 @@ impl super::Validator {
         #[cfg_attr(not(feature = "validate"), allow(unused))] entry_point: bool,
     ) -> Result<FunctionInfo, WithSpan<FunctionError>> {
         #[cfg_attr(not(feature = "validate"), allow(unused_mut))]
-        let mut info = mod_info.process_function(fun, module, self.flags, self.capabilities)?;
+        let mut info = mod_info.process_function(
+            fun,
+            module,
+            self.flags,
+            self.warnings,
+            self.capabilities,
+            self.analyzed_stages,
+        )?;
 
         #[cfg(feature = "validate")]
         let expression_constness =
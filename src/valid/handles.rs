@@ -4,6 +4,7 @@ use crate::{
     arena::{BadHandle, BadRangeError},
     Handle,
 };
+use std::fmt;
 
 #[cfg(feature = "validate")]
 use crate::{Arena, UniqueArena};
@@ -11,6 +12,9 @@ use crate::{Arena, UniqueArena};
 #[cfg(feature = "validate")]
 use super::ValidationError;
 
+#[cfg(feature = "validate")]
+use crate::span::WithSpan;
+
 #[cfg(feature = "validate")]
 use std::{convert::TryInto, hash::Hash, num::NonZeroU32};
 
@@ -31,8 +35,11 @@ impl super::Validator {
     ///
     /// Errors returned by this method are intentionally sparse, for simplicity of implementation.
     /// It is expected that only buggy frontends or fuzzers should ever emit IR that fails this
-    /// validation pass.
-    pub(super) fn validate_module_handles(module: &crate::Module) -> Result<(), ValidationError> {
+    /// validation pass. They do say which item of the module, and which function, the bad handle
+    /// was found in, with its span when there is one.
+    pub(super) fn validate_module_handles(
+        module: &crate::Module,
+    ) -> Result<(), WithSpan<ValidationError>> {
         let &crate::Module {
             ref constants,
             ref entry_points,
@@ -41,11 +48,12 @@ impl super::Validator {
             ref types,
             ref special_types,
             ref const_expressions,
+            diagnostic_filters: _,
         } = module;
 
         // NOTE: Types being first is important. All other forms of validation depend on this.
         for (this_handle, ty) in types.iter() {
-            match ty.inner {
+            let deps = match ty.inner {
                 crate::TypeInner::Scalar { .. }
                 | crate::TypeInner::Vector { .. }
                 | crate::TypeInner::Matrix { .. }
@@ -54,57 +62,71 @@ impl super::Validator {
                 | crate::TypeInner::Image { .. }
                 | crate::TypeInner::Sampler { .. }
                 | crate::TypeInner::AccelerationStructure
-                | crate::TypeInner::RayQuery => (),
-                crate::TypeInner::Pointer { base, space: _ } => {
-                    this_handle.check_dep(base)?;
-                }
+                | crate::TypeInner::RayQuery => Ok(this_handle),
+                crate::TypeInner::Pointer { base, space: _ } => this_handle.check_dep(base),
                 crate::TypeInner::Array { base, .. }
-                | crate::TypeInner::BindingArray { base, .. } => {
-                    this_handle.check_dep(base)?;
-                }
+                | crate::TypeInner::BindingArray { base, .. } => this_handle.check_dep(base),
                 crate::TypeInner::Struct {
                     ref members,
                     span: _,
-                } => {
-                    this_handle.check_dep_iter(members.iter().map(|m| m.ty))?;
-                }
-            }
+                } => this_handle.check_dep_iter(members.iter().map(|m| m.ty)),
+            };
+            deps.map_err(|source| {
+                let item = describe("type", this_handle, ty.name.as_deref());
+                invalid_handle(item, None, source.into()).with_handle(this_handle, types)
+            })?;
         }
 
         for handle_and_expr in const_expressions.iter() {
-            Self::validate_const_expression_handles(handle_and_expr, constants, types)?;
+            let (handle, _) = handle_and_expr;
+            Self::validate_const_expression_handles(handle_and_expr, constants, types).map_err(
+                |source| {
+                    let item = describe("constant expression", handle, None);
+                    invalid_handle(item, None, source).with_handle(handle, const_expressions)
+                },
+            )?;
         }
 
         let validate_type = |handle| Self::validate_type_handle(handle, types);
         let validate_const_expr =
             |handle| Self::validate_expression_handle(handle, const_expressions);
 
-        for (_handle, constant) in constants.iter() {
+        for (handle, constant) in constants.iter() {
             let &crate::Constant {
-                name: _,
+                ref name,
                 r#override: _,
                 ty,
                 init,
             } = constant;
-            validate_type(ty)?;
-            validate_const_expr(init)?;
+            validate_type(ty)
+                .and_then(|()| validate_const_expr(init))
+                .map_err(|source| {
+                    let item = describe("constant", handle, name.as_deref());
+                    invalid_handle(item, None, source).with_handle(handle, constants)
+                })?;
         }
 
-        for (_handle, global_variable) in global_variables.iter() {
+        for (handle, global_variable) in global_variables.iter() {
             let &crate::GlobalVariable {
-                name: _,
+                ref name,
                 space: _,
                 binding: _,
                 ty,
                 init,
             } = global_variable;
-            validate_type(ty)?;
-            if let Some(init_expr) = init {
-                validate_const_expr(init_expr)?;
-            }
+            validate_type(ty)
+                .and_then(|()| init.map_or(Ok(()), validate_const_expr))
+                .map_err(|source| {
+                    let item = describe("global variable", handle, name.as_deref());
+                    invalid_handle(item, None, source).with_handle(handle, global_variables)
+                })?;
         }
 
-        let validate_function = |function_handle, function: &_| -> Result<_, InvalidHandleError> {
+        // `label` describes the function, for errors in its contents.
+        let validate_function = |function_handle,
+                                 function: &_,
+                                 label: &String|
+         -> Result<(), WithSpan<ValidationError>> {
             let &crate::Function {
                 name: _,
                 ref arguments,
@@ -114,33 +136,48 @@ impl super::Validator {
                 ref named_expressions,
                 ref body,
             } = function;
+            let in_function = |item, source| invalid_handle(item, Some(label.clone()), source);
 
-            for arg in arguments.iter() {
+            for (index, arg) in arguments.iter().enumerate() {
                 let &crate::FunctionArgument {
-                    name: _,
+                    ref name,
                     ty,
                     binding: _,
                 } = arg;
-                validate_type(ty)?;
+                validate_type(ty).map_err(|source| {
+                    let item = match *name {
+                        Some(ref name) => format!("argument '{name}'"),
+                        None => format!("argument {index}"),
+                    };
+                    in_function(item, source)
+                })?;
             }
 
             if let &Some(crate::FunctionResult { ty, binding: _ }) = result {
-                validate_type(ty)?;
+                validate_type(ty).map_err(|source| in_function("result".to_string(), source))?;
             }
 
-            for (_handle, local_variable) in local_variables.iter() {
-                let &crate::LocalVariable { name: _, ty, init } = local_variable;
-                validate_type(ty)?;
-                if let Some(init) = init {
-                    Self::validate_expression_handle(init, expressions)?;
-                }
+            for (handle, local_variable) in local_variables.iter() {
+                let &crate::LocalVariable { ref name, ty, init } = local_variable;
+                validate_type(ty)
+                    .and_then(|()| {
+                        init.map_or(Ok(()), |init| {
+                            Self::validate_expression_handle(init, expressions)
+                        })
+                    })
+                    .map_err(|source| {
+                        let item = describe("local variable", handle, name.as_deref());
+                        in_function(item, source).with_handle(handle, local_variables)
+                    })?;
             }
 
             for handle in named_expressions.keys().copied() {
-                Self::validate_expression_handle(handle, expressions)?;
+                Self::validate_expression_handle(handle, expressions)
+                    .map_err(|source| in_function("named expressions".to_string(), source))?;
             }
 
             for handle_and_expr in expressions.iter() {
+                let (handle, _) = handle_and_expr;
                 Self::validate_expression_handles(
                     handle_and_expr,
                     constants,
@@ -150,27 +187,37 @@ impl super::Validator {
                     global_variables,
                     functions,
                     function_handle,
-                )?;
+                )
+                .map_err(|source| {
+                    let item = describe("expression", handle, None);
+                    in_function(item, source).with_handle(handle, expressions)
+                })?;
             }
 
-            Self::validate_block_handles(body, expressions, functions)?;
-
-            Ok(())
+            Self::validate_block_handles(body, local_variables, expressions, functions)
+                .map_err(|source| in_function("body".to_string(), source))
         };
 
         for entry_point in entry_points.iter() {
-            validate_function(None, &entry_point.function)?;
+            let label = format!("entry point '{}'", entry_point.name);
+            validate_function(None, &entry_point.function, &label)?;
         }
 
         for (function_handle, function) in functions.iter() {
-            validate_function(Some(function_handle), function)?;
+            let label = describe("function", function_handle, function.name.as_deref());
+            validate_function(Some(function_handle), function, &label)
+                .map_err(|error| error.with_handle(function_handle, functions))?;
         }
 
+        let validate_special_type = |ty, name| {
+            validate_type(ty)
+                .map_err(|source| invalid_handle(format!("special type '{name}'"), None, source))
+        };
         if let Some(ty) = special_types.ray_desc {
-            validate_type(ty)?;
+            validate_special_type(ty, "ray_desc")?;
         }
         if let Some(ty) = special_types.ray_intersection {
-            validate_type(ty)?;
+            validate_special_type(ty, "ray_intersection")?;
         }
 
         Ok(())
@@ -225,6 +272,28 @@ impl super::Validator {
                 validate_type(ty)?;
                 handle.check_dep_iter(components.iter().copied())?;
             }
+            crate::Expression::Repeat { ty, value } => {
+                validate_type(ty)?;
+                handle.check_dep(value)?;
+            }
+            crate::Expression::AccessIndex { base: expr, .. }
+            | crate::Expression::Unary { expr, .. }
+            | crate::Expression::As { expr, .. } => {
+                handle.check_dep(expr)?;
+            }
+            crate::Expression::Binary { left, right, .. } => {
+                handle.check_dep(left)?.check_dep(right)?;
+            }
+            crate::Expression::Select {
+                condition,
+                accept,
+                reject,
+            } => {
+                handle
+                    .check_dep(condition)?
+                    .check_dep(accept)?
+                    .check_dep(reject)?;
+            }
             _ => {}
         }
         Ok(())
@@ -271,6 +340,10 @@ impl super::Validator {
                 validate_type(ty)?;
                 handle.check_dep_iter(components.iter().copied())?;
             }
+            crate::Expression::Repeat { ty, value } => {
+                validate_type(ty)?;
+                handle.check_dep(value)?;
+            }
             crate::Expression::FunctionArgument(_arg_idx) => (),
             crate::Expression::GlobalVariable(global_variable) => {
                 global_variable.check_valid_for(global_variables)?;
@@ -404,16 +477,24 @@ impl super::Validator {
             } => {
                 handle.check_dep(query)?;
             }
+            crate::Expression::SubgroupOperation { op, argument } => {
+                handle.check_dep(argument)?.check_dep_opt(op.index())?;
+            }
+            crate::Expression::NonUniform(value) => {
+                handle.check_dep(value)?;
+            }
         }
         Ok(())
     }
 
     fn validate_block_handles(
         block: &crate::Block,
+        local_variables: &Arena<crate::LocalVariable>,
         expressions: &Arena<crate::Expression>,
         functions: &Arena<crate::Function>,
     ) -> Result<(), InvalidHandleError> {
-        let validate_block = |block| Self::validate_block_handles(block, expressions, functions);
+        let validate_block =
+            |block| Self::validate_block_handles(block, local_variables, expressions, functions);
         let validate_expr = |handle| Self::validate_expression_handle(handle, expressions);
         let validate_expr_opt = |handle_opt| {
             if let Some(handle) = handle_opt {
@@ -422,6 +503,10 @@ impl super::Validator {
             Ok(())
         };
 
+        for scoped in block.locals() {
+            scoped.local.check_valid_for(local_variables)?;
+        }
+
         block.iter().try_for_each(|stmt| match *stmt {
             crate::Statement::Emit(ref expr_range) => {
                 expr_range.check_valid_for(expressions)?;
@@ -484,6 +569,22 @@ impl super::Validator {
                 validate_expr(value)?;
                 Ok(())
             }
+            crate::Statement::ImageAtomic {
+                image,
+                coordinate,
+                array_index,
+                fun,
+                value,
+            } => {
+                validate_expr(image)?;
+                validate_expr(coordinate)?;
+                validate_expr_opt(array_index)?;
+                if let crate::AtomicFunction::Exchange { compare } = fun {
+                    validate_expr_opt(compare)?;
+                }
+                validate_expr(value)?;
+                Ok(())
+            }
             crate::Statement::Atomic {
                 pointer,
                 fun,
@@ -547,24 +648,48 @@ impl super::Validator {
     }
 }
 
+/// Return an [`InvalidHandle`] error for `source`, found in `item`.
+///
+/// [`InvalidHandle`]: ValidationError::InvalidHandle
 #[cfg(feature = "validate")]
-impl From<BadHandle> for ValidationError {
-    fn from(source: BadHandle) -> Self {
-        Self::InvalidHandle(source.into())
-    }
+fn invalid_handle(
+    item: String,
+    function: Option<String>,
+    source: InvalidHandleError,
+) -> WithSpan<ValidationError> {
+    WithSpan::new(ValidationError::InvalidHandle {
+        location: HandleDescriptor { item, function },
+        source,
+    })
 }
 
+/// Describe `handle` for error messages: `type 'Light'`, or `type [3]` if it
+/// has no name.
 #[cfg(feature = "validate")]
-impl From<FwdDepError> for ValidationError {
-    fn from(source: FwdDepError) -> Self {
-        Self::InvalidHandle(source.into())
+fn describe<T>(kind: &str, handle: Handle<T>, name: Option<&str>) -> String {
+    match name {
+        Some(name) => format!("{kind} '{name}'"),
+        None => format!("{kind} {handle:?}"),
     }
 }
 
-#[cfg(feature = "validate")]
-impl From<BadRangeError> for ValidationError {
-    fn from(source: BadRangeError) -> Self {
-        Self::InvalidHandle(source.into())
+/// Where in a module an invalid handle was found.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HandleDescriptor {
+    /// The item the handle was found in, like `type 'Light'`,
+    /// `global variable [2]` or `expression [7]`.
+    pub item: String,
+    /// The function or entry point the item is part of, like
+    /// `function 'helper'` or `entry point 'main'`, if any.
+    pub function: Option<String>,
+}
+
+impl fmt::Display for HandleDescriptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.function {
+            Some(ref function) => write!(f, "{} of {}", self.item, function),
+            None => write!(f, "{}", self.item),
+        }
     }
 }
 
@@ -710,3 +835,49 @@ fn constant_deps() {
         .is_err());
     }
 }
+
+#[test]
+#[cfg(feature = "validate")]
+fn forward_dependency_location() {
+    use crate::{Expression, Function, Literal, Span, UnaryOperator};
+
+    let nowhere = Span::default();
+
+    // Make the first expression depend on the second.
+    let mut function = Function {
+        name: Some("helper".to_string()),
+        ..Default::default()
+    };
+    let negate = function
+        .expressions
+        .append(Expression::Literal(Literal::I32(0)), nowhere);
+    let value = function
+        .expressions
+        .append(Expression::Literal(Literal::I32(1)), nowhere);
+    function.expressions[negate] = Expression::Unary {
+        op: UnaryOperator::Negate,
+        expr: value,
+    };
+    let mut module = crate::Module::default();
+    module.functions.append(function, nowhere);
+
+    let error = super::Validator::validate_module_handles(&module)
+        .unwrap_err()
+        .into_inner();
+    match error {
+        ValidationError::InvalidHandle {
+            location,
+            source: InvalidHandleError::ForwardDependency(_),
+        } => {
+            assert_eq!(
+                location,
+                HandleDescriptor {
+                    item: "expression [1]".to_string(),
+                    function: Some("function 'helper'".to_string()),
+                }
+            );
+            assert_eq!(location.to_string(), "expression [1] of function 'helper'");
+        }
+        other => panic!("unexpected error: {other:?}"),
+    }
+}
@@ -37,6 +37,10 @@ pub enum GlobalVariableError {
     InitializerNotAllowed(crate::AddressSpace),
     #[error("Storage address space doesn't support write-only access")]
     StorageAddressSpaceWriteOnlyNotSupported,
+    #[error("Coherent access is only useful for storage that can be written")]
+    CoherentReadOnly,
+    #[error("Push constant of {size} bytes exceeds the limit of {max} bytes")]
+    PushConstantTooLarge { size: u32, max: u32 },
 }
 
 #[derive(Clone, Debug, thiserror::Error)]
@@ -74,6 +78,8 @@ pub enum VaryingError {
         location: u32,
         attribute: &'static str,
     },
+    #[error("Location {location} exceeds the limit of {max}")]
+    LocationTooLarge { location: u32, max: u32 },
 }
 
 #[derive(Clone, Debug, thiserror::Error)]
@@ -88,6 +94,8 @@ pub enum EntryPointError {
     UnexpectedWorkgroupSize,
     #[error("Workgroup size is out of range")]
     OutOfRangeWorkgroupSize,
+    #[error("Workgroup of {product} invocations exceeds the limit of {max}")]
+    WorkgroupTooLarge { product: u64, max: u32 },
     #[error("Uses operations forbidden at this stage")]
     ForbiddenStageOperations,
     #[error("Global variable {0:?} is used incorrectly as {1:?}")]
@@ -110,6 +118,100 @@ pub enum EntryPointError {
     InvalidLocationsWhileDualSourceBlending { location_mask: BitSet },
 }
 
+/// A mismatch in position invariance between a vertex entry point and the
+/// fragment entry point it is paired with.
+///
+/// See [`check_position_invariance`].
+#[derive(Clone, Debug, thiserror::Error)]
+pub enum InvarianceError {
+    #[error("Entry point `{0}` is not a {1:?} shader")]
+    WrongStage(String, crate::ShaderStage),
+    #[error(
+        "Fragment entry point `{fragment}` reads an invariant position, \
+        but vertex entry point `{vertex}` does not write an invariant position"
+    )]
+    FragmentOnly { vertex: String, fragment: String },
+    #[error(
+        "Vertex entry point `{vertex}` writes an invariant position, \
+        but fragment entry point `{fragment}` reads it without `invariant`"
+    )]
+    VertexOnly { vertex: String, fragment: String },
+}
+
+/// Check that `vertex` and `fragment` agree on whether the position built-in
+/// is invariant, as required when they are linked together.
+///
+/// WGSL only gives `@invariant` a meaning on vertex outputs, but GLSL ES
+/// requires `gl_FragCoord` to be declared `invariant` if and only if
+/// `gl_Position` is. A fragment entry point that doesn't read the position
+/// is compatible with any vertex entry point.
+pub fn check_position_invariance(
+    module: &crate::Module,
+    vertex: &crate::EntryPoint,
+    fragment: &crate::EntryPoint,
+) -> Result<(), InvarianceError> {
+    for (ep, stage) in [
+        (vertex, crate::ShaderStage::Vertex),
+        (fragment, crate::ShaderStage::Fragment),
+    ] {
+        if ep.stage != stage {
+            return Err(InvarianceError::WrongStage(ep.name.clone(), stage));
+        }
+    }
+
+    let written = position_invariance(module, &vertex.function, true).unwrap_or(false);
+    let read = match position_invariance(module, &fragment.function, false) {
+        Some(read) => read,
+        None => return Ok(()),
+    };
+    match (written, read) {
+        (false, true) => Err(InvarianceError::FragmentOnly {
+            vertex: vertex.name.clone(),
+            fragment: fragment.name.clone(),
+        }),
+        (true, false) => Err(InvarianceError::VertexOnly {
+            vertex: vertex.name.clone(),
+            fragment: fragment.name.clone(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Return whether the position built-in among `function`'s outputs (if
+/// `output` is true) or inputs is invariant, or `None` if there is no such
+/// built-in.
+pub(crate) fn position_invariance(
+    module: &crate::Module,
+    function: &crate::Function,
+    output: bool,
+) -> Option<bool> {
+    let find = |binding: Option<&crate::Binding>, ty: Handle<crate::Type>| match binding {
+        Some(&crate::Binding::BuiltIn(crate::BuiltIn::Position { invariant })) => Some(invariant),
+        Some(_) => None,
+        None => match module.types[ty].inner {
+            crate::TypeInner::Struct { ref members, .. } => {
+                members.iter().find_map(|member| match member.binding {
+                    Some(crate::Binding::BuiltIn(crate::BuiltIn::Position { invariant })) => {
+                        Some(invariant)
+                    }
+                    _ => None,
+                })
+            }
+            _ => None,
+        },
+    };
+
+    if output {
+        let result = function.result.as_ref()?;
+        find(result.binding.as_ref(), result.ty)
+    } else {
+        function
+            .arguments
+            .iter()
+            .find_map(|arg| find(arg.binding.as_ref(), arg.ty))
+    }
+}
+
 #[cfg(feature = "validate")]
 fn storage_usage(access: crate::StorageAccess) -> GlobalUse {
     let mut storage_usage = GlobalUse::QUERY;
@@ -131,6 +233,7 @@ struct VaryingContext<'a> {
     location_mask: &'a mut BitSet,
     built_ins: &'a mut crate::FastHashSet<crate::BuiltIn>,
     capabilities: Capabilities,
+    max_location: Option<u32>,
 
     #[cfg(feature = "validate")]
     flags: super::ValidationFlags,
@@ -168,6 +271,7 @@ impl VaryingContext<'_> {
                     Bi::PrimitiveIndex => Capabilities::PRIMITIVE_INDEX,
                     Bi::ViewIndex => Capabilities::MULTIVIEW,
                     Bi::SampleIndex => Capabilities::MULTISAMPLED_SHADING,
+                    Bi::SubgroupSize | Bi::SubgroupInvocationId => Capabilities::SUBGROUP,
                     _ => Capabilities::empty(),
                 };
                 if !self.capabilities.contains(required) {
@@ -278,6 +382,14 @@ impl VaryingContext<'_> {
                                 width,
                             },
                     ),
+                    Bi::SubgroupSize | Bi::SubgroupInvocationId => (
+                        matches!(self.stage, St::Compute | St::Fragment) && !self.output,
+                        *ty_inner
+                            == Ti::Scalar {
+                                kind: Sk::Uint,
+                                width,
+                            },
+                    ),
                     Bi::LocalInvocationIndex => (
                         self.stage == St::Compute && !self.output,
                         *ty_inner
@@ -323,6 +435,12 @@ impl VaryingContext<'_> {
                     return Err(VaryingError::NotIOShareableType(ty));
                 }
 
+                if let Some(max) = self.max_location {
+                    if location > max {
+                        return Err(VaryingError::LocationTooLarge { location, max });
+                    }
+                }
+
                 if second_blend_source {
                     if !self
                         .capabilities
@@ -484,9 +602,15 @@ impl super::Validator {
                         ));
                     }
                 }
-                if access == crate::StorageAccess::STORE {
+                if access.difference(crate::StorageAccess::COHERENT) == crate::StorageAccess::STORE
+                {
                     return Err(GlobalVariableError::StorageAddressSpaceWriteOnlyNotSupported);
                 }
+                if access.contains(crate::StorageAccess::COHERENT)
+                    && !access.contains(crate::StorageAccess::STORE)
+                {
+                    return Err(GlobalVariableError::CoherentReadOnly);
+                }
                 (TypeFlags::DATA | TypeFlags::HOST_SHAREABLE, true)
             }
             crate::AddressSpace::Uniform => {
@@ -510,6 +634,12 @@ impl super::Validator {
             crate::AddressSpace::Handle => {
                 match gctx.types[inner_ty].inner {
                     crate::TypeInner::Image { class, .. } => match class {
+                        crate::ImageClass::Storage { access, .. }
+                            if access.contains(crate::StorageAccess::COHERENT)
+                                && !access.contains(crate::StorageAccess::STORE) =>
+                        {
+                            return Err(GlobalVariableError::CoherentReadOnly);
+                        }
                         crate::ImageClass::Storage {
                             format:
                                 crate::StorageFormat::R16Unorm
@@ -549,6 +679,12 @@ impl super::Validator {
                         Capabilities::PUSH_CONSTANT,
                     ));
                 }
+                if let Some(max) = self.limits.max_push_constant_size {
+                    let size = self.layouter[var.ty].size;
+                    if size > max {
+                        return Err(GlobalVariableError::PushConstantTooLarge { size, max });
+                    }
+                }
                 (
                     TypeFlags::DATA
                         | TypeFlags::COPY
@@ -620,11 +756,16 @@ impl super::Validator {
             {
                 return Err(EntryPointError::OutOfRangeWorkgroupSize.with_span());
             }
+            if let Some(max) = self.limits.max_workgroup_size_product {
+                let product = ep.workgroup_size.iter().map(|&s| u64::from(s)).product();
+                if product > u64::from(max) {
+                    return Err(EntryPointError::WorkgroupTooLarge { product, max }.with_span());
+                }
+            }
         } else if ep.workgroup_size != [0; 3] {
             return Err(EntryPointError::UnexpectedWorkgroupSize.with_span());
         }
 
-        #[cfg_attr(not(feature = "validate"), allow(unused_mut))]
         let mut info = self
             .validate_function(&ep.function, module, mod_info, true)
             .map_err(WithSpan::into_other)?;
@@ -657,6 +798,7 @@ impl super::Validator {
                 location_mask: &mut self.location_mask,
                 built_ins: &mut argument_built_ins,
                 capabilities: self.capabilities,
+                max_location: self.limits.max_location,
 
                 #[cfg(feature = "validate")]
                 flags: self.flags,
@@ -677,12 +819,14 @@ impl super::Validator {
                 location_mask: &mut self.location_mask,
                 built_ins: &mut result_built_ins,
                 capabilities: self.capabilities,
+                max_location: self.limits.max_location,
 
                 #[cfg(feature = "validate")]
                 flags: self.flags,
             };
             ctx.validate(fr.ty, fr.binding.as_ref())
                 .map_err_inner(|e| EntryPointError::Result(e).with_span())?;
+            info.writes_depth = result_built_ins.contains(&crate::BuiltIn::FragDepth);
             #[cfg(feature = "validate")]
             if ctx.second_blend_source {
                 // Only the first location may be used whhen dual source blending
@@ -751,7 +895,7 @@ impl super::Validator {
                     _ => GlobalUse::READ | GlobalUse::QUERY,
                 },
                 crate::AddressSpace::Private | crate::AddressSpace::WorkGroup => GlobalUse::all(),
-                crate::AddressSpace::PushConstant => GlobalUse::READ,
+                crate::AddressSpace::PushConstant => GlobalUse::READ | GlobalUse::QUERY,
             };
             if !allowed_usage.contains(usage) {
                 log::warn!("\tUsage error for: {:?}", var);
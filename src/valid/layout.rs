@@ -0,0 +1,258 @@
+//! Checking a module's resource bindings against a pipeline layout.
+
+use super::{ModuleInfo, ShaderStages};
+use crate::arena::Handle;
+
+/// The kind of resource a pipeline layout provides at a binding.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
+pub enum BindingType {
+    /// A buffer in the [`Uniform`] address space.
+    ///
+    /// If `min_size` is given, the layout guarantees that every buffer bound
+    /// here is at least that many bytes long.
+    ///
+    /// [`Uniform`]: crate::AddressSpace::Uniform
+    UniformBuffer {
+        min_size: Option<u32>,
+    },
+    /// A buffer in the [`Storage`] address space, allowing `access`.
+    ///
+    /// [`Storage`]: crate::AddressSpace::Storage
+    StorageBuffer {
+        access: crate::StorageAccess,
+        min_size: Option<u32>,
+    },
+    Sampler {
+        comparison: bool,
+    },
+    /// An image. For storage images, the `access` of the class is the access
+    /// the layout allows.
+    Image {
+        dim: crate::ImageDimension,
+        arrayed: bool,
+        class: crate::ImageClass,
+    },
+    AccelerationStructure,
+}
+
+/// One entry of a pipeline layout.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
+pub struct BindingLayout {
+    pub binding: crate::ResourceBinding,
+    pub ty: BindingType,
+    /// The stages whose entry points may use the binding.
+    pub visibility: ShaderStages,
+    /// The number of elements, if the binding is a binding array.
+    pub count: Option<u32>,
+}
+
+/// A way in which a module doesn't fit a pipeline layout.
+///
+/// See [`check_layout`].
+#[derive(Clone, Debug, thiserror::Error)]
+pub enum PipelineLayoutError {
+    #[error("Entry point `{entry_point}` uses {binding:?}, which is missing from the layout")]
+    MissingBinding {
+        entry_point: String,
+        binding: crate::ResourceBinding,
+    },
+    #[error(
+        "{binding:?} is used by {stage:?} entry point `{entry_point}`, \
+        but isn't visible to that stage"
+    )]
+    NotVisible {
+        entry_point: String,
+        stage: crate::ShaderStage,
+        binding: crate::ResourceBinding,
+    },
+    #[error("{binding:?} is declared as {actual:?}, but the layout provides {expected:?}")]
+    TypeMismatch {
+        binding: crate::ResourceBinding,
+        expected: BindingType,
+        actual: BindingType,
+    },
+    #[error(
+        "{binding:?} needs a buffer of at least {actual} bytes, \
+        but the layout only guarantees {expected}"
+    )]
+    BufferTooSmall {
+        binding: crate::ResourceBinding,
+        expected: u32,
+        actual: u32,
+    },
+    #[error(
+        "{binding:?} is declared with {actual:?} elements, but the layout provides {expected:?}"
+    )]
+    CountMismatch {
+        binding: crate::ResourceBinding,
+        expected: Option<u32>,
+        actual: Option<crate::ArraySize>,
+    },
+}
+
+/// Check that every resource the entry points of `module` use is provided
+/// by `layout`, with a compatible type and count, to the entry point's stage.
+///
+/// `info` must be the result of validating `module`. Resources that no
+/// entry point uses don't need to be in the layout, and the layout may
+/// grant more access, and guarantee larger buffers, than the module needs.
+pub fn check_layout(
+    module: &crate::Module,
+    info: &ModuleInfo,
+    layout: &[BindingLayout],
+) -> Result<(), PipelineLayoutError> {
+    let mut layouter = crate::proc::Layouter::default();
+    // The module has been validated, so its types can be laid out.
+    layouter.update(module.to_ctx()).unwrap();
+
+    for (index, ep) in module.entry_points.iter().enumerate() {
+        let ep_info = info.get_entry_point(index);
+        for (handle, var) in module.global_variables.iter() {
+            let binding = match var.binding {
+                Some(ref binding) if !ep_info[handle].is_empty() => binding,
+                _ => continue,
+            };
+            let entry = layout
+                .iter()
+                .find(|entry| entry.binding == *binding)
+                .ok_or_else(|| PipelineLayoutError::MissingBinding {
+                    entry_point: ep.name.clone(),
+                    binding: binding.clone(),
+                })?;
+            let stage = match ep.stage {
+                crate::ShaderStage::Vertex => ShaderStages::VERTEX,
+                crate::ShaderStage::Fragment => ShaderStages::FRAGMENT,
+                crate::ShaderStage::Compute => ShaderStages::COMPUTE,
+            };
+            if !entry.visibility.contains(stage) {
+                return Err(PipelineLayoutError::NotVisible {
+                    entry_point: ep.name.clone(),
+                    stage: ep.stage,
+                    binding: binding.clone(),
+                });
+            }
+            check_binding(module, &layouter, handle, entry)?;
+        }
+    }
+    Ok(())
+}
+
+fn check_binding(
+    module: &crate::Module,
+    layouter: &crate::proc::Layouter,
+    handle: Handle<crate::GlobalVariable>,
+    entry: &BindingLayout,
+) -> Result<(), PipelineLayoutError> {
+    let var = &module.global_variables[handle];
+    let binding = &entry.binding;
+
+    let (base, size) = match module.types[var.ty].inner {
+        crate::TypeInner::BindingArray { base, size } => (base, Some(size)),
+        _ => (var.ty, None),
+    };
+    let count_fits = match (size, entry.count) {
+        (None, None) | (Some(crate::ArraySize::Dynamic), Some(_)) => true,
+        (Some(crate::ArraySize::Constant(size)), Some(count)) => size.get() <= count,
+        _ => false,
+    };
+    if !count_fits {
+        return Err(PipelineLayoutError::CountMismatch {
+            binding: binding.clone(),
+            expected: entry.count,
+            actual: size,
+        });
+    }
+
+    let actual = declared_binding_type(module, layouter, var.space, base);
+
+    let needed_size = match (entry.ty, actual) {
+        (
+            BindingType::UniformBuffer { min_size },
+            BindingType::UniformBuffer {
+                min_size: Some(needed),
+            },
+        ) => min_size.map(|size| (size, needed)),
+        (
+            BindingType::StorageBuffer { access, min_size },
+            BindingType::StorageBuffer {
+                access: needed_access,
+                min_size: Some(needed),
+            },
+        ) if access.contains(needed_access) => min_size.map(|size| (size, needed)),
+        (
+            BindingType::Image {
+                dim,
+                arrayed,
+                class: crate::ImageClass::Storage { format, access },
+            },
+            BindingType::Image {
+                dim: needed_dim,
+                arrayed: needed_arrayed,
+                class:
+                    crate::ImageClass::Storage {
+                        format: needed_format,
+                        access: needed_access,
+                    },
+            },
+        ) if (dim, arrayed, format) == (needed_dim, needed_arrayed, needed_format)
+            && access.contains(needed_access) =>
+        {
+            None
+        }
+        (expected, actual) if expected == actual => None,
+        _ => {
+            return Err(PipelineLayoutError::TypeMismatch {
+                binding: binding.clone(),
+                expected: entry.ty,
+                actual,
+            })
+        }
+    };
+
+    match needed_size {
+        Some((expected, actual)) if expected < actual => Err(PipelineLayoutError::BufferTooSmall {
+            binding: binding.clone(),
+            expected,
+            actual,
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Return the binding type a global in `space` needs, where `base` is its
+/// type, or its binding array's element type.
+pub(crate) fn declared_binding_type(
+    module: &crate::Module,
+    layouter: &crate::proc::Layouter,
+    space: crate::AddressSpace,
+    base: Handle<crate::Type>,
+) -> BindingType {
+    match (space, &module.types[base].inner) {
+        (crate::AddressSpace::Uniform, _) => BindingType::UniformBuffer {
+            min_size: Some(layouter[base].size),
+        },
+        (crate::AddressSpace::Storage { access }, _) => BindingType::StorageBuffer {
+            access,
+            min_size: Some(layouter[base].size),
+        },
+        (_, &crate::TypeInner::Sampler { comparison }) => BindingType::Sampler { comparison },
+        (
+            _,
+            &crate::TypeInner::Image {
+                dim,
+                arrayed,
+                class,
+            },
+        ) => BindingType::Image {
+            dim,
+            arrayed,
+            class,
+        },
+        // The validator allows nothing else to have a binding.
+        _ => BindingType::AccelerationStructure,
+    }
+}
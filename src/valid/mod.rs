@@ -8,6 +8,7 @@ mod expression;
 mod function;
 mod handles;
 mod interface;
+mod layout;
 mod r#type;
 
 use crate::{
@@ -22,13 +23,22 @@ use std::ops;
 // merge the corresponding matches over expressions and statements.
 
 use crate::span::{AddSpan as _, WithSpan};
-pub use analyzer::{ExpressionInfo, FunctionInfo, GlobalUse, Uniformity, UniformityRequirements};
+pub use analyzer::{
+    Cost, ExpressionInfo, FunctionInfo, GlobalUse, ModuleInfoUsage, Uniformity,
+    UniformityDisruptor, UniformityRequirements, ValidationWarning,
+};
 pub use compose::ComposeError;
 pub use expression::{check_literal_value, LiteralError};
 pub use expression::{ConstExpressionError, ExpressionError};
-pub use function::{CallError, FunctionError, LocalVariableError};
-pub use interface::{EntryPointError, GlobalVariableError, VaryingError};
-pub use r#type::{Disalignment, TypeError, TypeFlags};
+pub use function::{AtomicError, CallError, FunctionError, LocalVariableError};
+pub use handles::HandleDescriptor;
+pub(crate) use interface::position_invariance;
+pub use interface::{
+    check_position_invariance, EntryPointError, GlobalVariableError, InvarianceError, VaryingError,
+};
+pub(crate) use layout::declared_binding_type;
+pub use layout::{check_layout, BindingLayout, BindingType, PipelineLayoutError};
+pub use r#type::{Disalignment, TypeError, TypeFlags, WidthError};
 
 use self::handles::InvalidHandleError;
 
@@ -80,13 +90,42 @@ impl Default for ValidationFlags {
     }
 }
 
+bitflags::bitflags! {
+    /// Warnings to report.
+    ///
+    /// Warnings don't make validation fail. Leaving one out of the flags
+    /// given to [`Validator::warnings`] silences it, like a WGSL diagnostic
+    /// filter with the `off` severity. A filter in the module's
+    /// [`diagnostic_filters`] can still turn it into an error.
+    ///
+    /// [`diagnostic_filters`]: crate::Module::diagnostic_filters
+    ///
+    /// The default value for `WarningFlags` is `WarningFlags::all()`.
+    /// Warnings are only checked for if Naga's `"validate"` feature is
+    /// enabled.
+    #[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+    #[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    pub struct WarningFlags: u8 {
+        /// Sampling with an implicit level of detail in control flow that
+        /// may not be uniform, like WGSL's `derivative_uniformity` rule.
+        const DERIVATIVE_UNIFORMITY = 0x1;
+    }
+}
+
+impl Default for WarningFlags {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
 bitflags::bitflags! {
     /// Allowed IR capabilities.
     #[must_use]
     #[cfg_attr(feature = "serialize", derive(serde::Serialize))]
     #[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
     #[derive(Clone, Copy, Debug, Eq, PartialEq)]
-    pub struct Capabilities: u16 {
+    pub struct Capabilities: u32 {
         /// Support for [`AddressSpace:PushConstant`].
         const PUSH_CONSTANT = 0x1;
         /// Float values with width = 8.
@@ -117,12 +156,50 @@ bitflags::bitflags! {
         const DUAL_SOURCE_BLENDING = 0x2000;
         /// Support for arrayed cube textures.
         const CUBE_ARRAY_TEXTURES = 0x4000;
+        /// Float values with width = 2.
+        const FLOAT16 = 0x8000;
+        /// Support for [`Expression::SubgroupOperation`].
+        ///
+        /// [`Expression::SubgroupOperation`]: crate::Expression::SubgroupOperation
+        const SUBGROUP = 0x10000;
+        /// Support for indexing arrays in the [`Uniform`] address space with
+        /// values that aren't constant.
+        ///
+        /// Some downlevel targets, like WebGL, only allow constant indices.
+        ///
+        /// [`Uniform`]: crate::AddressSpace::Uniform
+        const UNIFORM_BUFFER_DYNAMIC_INDEXING = 0x20000;
+        /// Integer values with width = 8.
+        const SHADER_INT64 = 0x40000;
+        /// Support for [`AtomicFunction::Min`] and [`AtomicFunction::Max`] on
+        /// 64-bit atomics, in the [`Storage`] address space.
+        ///
+        /// [`AtomicFunction::Min`]: crate::AtomicFunction::Min
+        /// [`AtomicFunction::Max`]: crate::AtomicFunction::Max
+        /// [`Storage`]: crate::AddressSpace::Storage
+        const SHADER_INT64_ATOMIC_MIN_MAX = 0x80000;
+        /// Support for all atomic functions on 64-bit atomics.
+        const SHADER_INT64_ATOMIC_ALL_OPS = 0x100000;
+        /// Support for [`Statement::ImageAtomic`], and storage images with
+        /// [`StorageAccess::ATOMIC`] access.
+        ///
+        /// [`Statement::ImageAtomic`]: crate::Statement::ImageAtomic
+        /// [`StorageAccess::ATOMIC`]: crate::StorageAccess::ATOMIC
+        const TEXTURE_ATOMIC = 0x200000;
+        /// Support for [`Expression::Select`] choosing between two pointers.
+        ///
+        /// Only the SPIR-V backend supports this.
+        ///
+        /// [`Expression::Select`]: crate::Expression::Select
+        const POINTER_SELECT = 0x400000;
     }
 }
 
 impl Default for Capabilities {
     fn default() -> Self {
-        Self::MULTISAMPLED_SHADING | Self::CUBE_ARRAY_TEXTURES
+        Self::MULTISAMPLED_SHADING
+            | Self::CUBE_ARRAY_TEXTURES
+            | Self::UNIFORM_BUFFER_DYNAMIC_INDEXING
     }
 }
 
@@ -138,6 +215,141 @@ bitflags::bitflags! {
     }
 }
 
+/// Limits on a module's size and shape, beyond what the IR itself requires.
+///
+/// These are for devices and target languages with limits of their own, so
+/// that modules that would exceed them are rejected during validation,
+/// rather than failing later in a backend or the driver. Every limit is
+/// `None`, meaning unlimited, by default.
+///
+/// See [`Validator::limits`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
+pub struct Limits {
+    /// How many arrays and structs deep a type may be. A struct of scalars
+    /// is one deep, and an array of those is two.
+    pub max_struct_nesting: Option<u32>,
+    /// How many elements a fixed-size array may have.
+    pub max_array_size: Option<u32>,
+    /// How long a chain of function calls may be. A function that calls
+    /// nothing has depth zero, and one that calls it has depth one.
+    pub max_call_depth: Option<u32>,
+    /// How many invocations a compute workgroup may have: the product of
+    /// the three [`workgroup_size`] dimensions.
+    ///
+    /// [`workgroup_size`]: crate::EntryPoint::workgroup_size
+    pub max_workgroup_size_product: Option<u32>,
+    /// The largest [`Location`] an entry point's inputs and outputs may use.
+    ///
+    /// [`Location`]: crate::Binding::Location
+    pub max_location: Option<u32>,
+    /// How many bytes [`AddressSpace::PushConstant`] globals may take up.
+    ///
+    /// This should match the `maxPushConstantsSize` (or equivalent) of the
+    /// device the module is for.
+    ///
+    /// [`AddressSpace::PushConstant`]: crate::AddressSpace::PushConstant
+    pub max_push_constant_size: Option<u32>,
+}
+
+/// A set of rules for what modules may contain, chosen by who provides
+/// them.
+///
+/// Rather than assembling [`Capabilities`], [`Limits`] and front end
+/// settings that fit together, callers can pick a profile: see
+/// [`Validator::with_profile`], and the WGSL front end's
+/// `Frontend::with_profile`.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
+pub enum Profile {
+    /// Follow the WebGPU specification strictly, as untrusted web content
+    /// must.
+    ///
+    /// Only the capabilities WebGPU requires of every device are allowed,
+    /// modules must stay within the WGSL specification's limits on
+    /// nesting, workgroup size and inter-stage locations, and front ends
+    /// reject syntax that only Naga accepts.
+    WebGpu,
+    /// Allow everything Naga supports, as trusted native applications may.
+    ///
+    /// Every capability is allowed and there are no limits. Backends may
+    /// still reject what their target languages can't express. The names
+    /// of the types only Naga provides are reserved, like WGSL's own.
+    #[default]
+    Native,
+}
+
+bitflags::bitflags! {
+    /// Optional WebGPU features a device may have, which let modules use
+    /// more under [`Profile::WebGpu`].
+    #[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+    #[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
+    #[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+    pub struct WebGpuFeatures: u8 {
+        /// The `shader-f16` feature, which allows [`Capabilities::FLOAT16`].
+        const SHADER_F16 = 0x1;
+        /// The `subgroups` feature, which allows [`Capabilities::SUBGROUP`].
+        const SUBGROUPS = 0x2;
+    }
+}
+
+impl Profile {
+    /// The capabilities modules may use under this profile, on a device
+    /// with the optional WebGPU `features`.
+    ///
+    /// [`Native`] allows every capability whatever `features` are.
+    ///
+    /// [`Native`]: Profile::Native
+    pub fn capabilities(self, features: WebGpuFeatures) -> Capabilities {
+        match self {
+            Self::WebGpu => {
+                let mut capabilities = Capabilities::default();
+                capabilities.set(
+                    Capabilities::FLOAT16,
+                    features.contains(WebGpuFeatures::SHADER_F16),
+                );
+                capabilities.set(
+                    Capabilities::SUBGROUP,
+                    features.contains(WebGpuFeatures::SUBGROUPS),
+                );
+                capabilities
+            }
+            Self::Native => Capabilities::all(),
+        }
+    }
+
+    /// The limits modules must stay within under this profile.
+    pub const fn limits(self) -> Limits {
+        match self {
+            Self::WebGpu => Limits {
+                max_struct_nesting: Some(15),
+                max_array_size: None,
+                max_call_depth: None,
+                max_workgroup_size_product: Some(256),
+                // `maxInterStageShaderVariables` is at least 16.
+                max_location: Some(15),
+                // WebGPU has no push constants.
+                max_push_constant_size: None,
+            },
+            Self::Native => Limits {
+                max_struct_nesting: None,
+                max_array_size: None,
+                max_call_depth: None,
+                max_workgroup_size_product: None,
+                max_location: None,
+                max_push_constant_size: None,
+            },
+        }
+    }
+}
+
+/// The results of validating a module, which the back ends need.
+///
+/// With the `serialize` and `deserialize` features, this can be cached
+/// alongside its module, and accepted again with
+/// [`Validator::assume_validated`].
 #[derive(Debug)]
 #[cfg_attr(feature = "serialize", derive(serde::Serialize))]
 #[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
@@ -172,6 +384,7 @@ impl ops::Index<Handle<crate::Expression>> for ModuleInfo {
 #[derive(Debug)]
 pub struct Validator {
     flags: ValidationFlags,
+    warnings: WarningFlags,
     capabilities: Capabilities,
     types: Vec<r#type::TypeInfo>,
     layouter: Layouter,
@@ -181,6 +394,11 @@ pub struct Validator {
     switch_values: FastHashSet<crate::SwitchValue>,
     valid_expression_list: Vec<Handle<crate::Expression>>,
     valid_expression_set: BitSet,
+    limits: Limits,
+    stages: ShaderStages,
+    /// The stages the module being validated is analyzed for: `stages`,
+    /// narrowed to those it has entry points for.
+    analyzed_stages: ShaderStages,
 }
 
 #[derive(Clone, Debug, thiserror::Error)]
@@ -189,12 +407,17 @@ pub enum ConstantError {
     InvalidType,
     #[error("The type is not constructible")]
     NonConstructibleType,
+    #[error("The value depends on overridable constants")]
+    OverrideDependent,
 }
 
 #[derive(Clone, Debug, thiserror::Error)]
 pub enum ValidationError {
-    #[error(transparent)]
-    InvalidHandle(#[from] InvalidHandleError),
+    #[error("Invalid handle in {location}")]
+    InvalidHandle {
+        location: HandleDescriptor,
+        source: InvalidHandleError,
+    },
     #[error(transparent)]
     Layouter(#[from] LayoutError),
     #[error("Type {handle:?} '{name}' is invalid")]
@@ -234,6 +457,25 @@ pub enum ValidationError {
     },
     #[error("Module is corrupted")]
     Corrupted,
+    #[error("The module info doesn't describe this module")]
+    InfoMismatch,
+}
+
+impl crate::span::Diagnose for ValidationError {
+    fn code(&self) -> &'static str {
+        match *self {
+            Self::InvalidHandle { .. } => "validation::invalid-handle",
+            Self::Layouter(_) => "validation::layout",
+            Self::Type { .. } => "validation::type",
+            Self::ConstExpression { .. } => "validation::const-expression",
+            Self::Constant { .. } => "validation::constant",
+            Self::GlobalVariable { .. } => "validation::global-variable",
+            Self::Function { .. } => "validation::function",
+            Self::EntryPoint { .. } => "validation::entry-point",
+            Self::Corrupted => "validation::corrupted",
+            Self::InfoMismatch => "validation::info-mismatch",
+        }
+    }
 }
 
 impl crate::TypeInner {
@@ -288,6 +530,7 @@ impl Validator {
     pub fn new(flags: ValidationFlags, capabilities: Capabilities) -> Self {
         Validator {
             flags,
+            warnings: WarningFlags::default(),
             capabilities,
             types: Vec::new(),
             layouter: Layouter::default(),
@@ -296,9 +539,56 @@ impl Validator {
             switch_values: FastHashSet::default(),
             valid_expression_list: Vec::new(),
             valid_expression_set: BitSet::new(),
+            limits: Limits::default(),
+            stages: ShaderStages::all(),
+            analyzed_stages: ShaderStages::all(),
         }
     }
 
+    /// Construct a validator that checks every rule of `profile`, for a
+    /// device with the optional WebGPU `features`.
+    ///
+    /// This validates fully, with the profile's [`capabilities`] and
+    /// [`limits`]. The limits, like the other settings, can still be
+    /// changed afterwards.
+    ///
+    /// [`capabilities`]: Profile::capabilities
+    /// [`limits`]: Profile::limits
+    pub fn with_profile(profile: Profile, features: WebGpuFeatures) -> Self {
+        let mut validator = Self::new(ValidationFlags::all(), profile.capabilities(features));
+        validator.limits(profile.limits());
+        validator
+    }
+
+    /// Reject modules that exceed `limits`. There are no limits by default.
+    pub fn limits(&mut self, limits: Limits) -> &mut Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Only run the analyses that shaders for `stages` need.
+    ///
+    /// Some analyses only matter to certain stages: derivative uniformity,
+    /// for example, only matters to fragment shaders. Those are skipped for
+    /// stages not in `stages`, and for stages the module has no entry points
+    /// for. Modules with no entry points at all are analyzed for all of
+    /// `stages`, since they may be used from any of them. All stages are
+    /// analyzed by default.
+    ///
+    /// See [`FunctionInfo::analyzed_stages`] for what is left out.
+    pub fn stages(&mut self, stages: ShaderStages) -> &mut Self {
+        self.stages = stages;
+        self
+    }
+
+    /// Choose which warnings to report. All of them are by default.
+    ///
+    /// See [`FunctionInfo::warnings`].
+    pub fn warnings(&mut self, warnings: WarningFlags) -> &mut Self {
+        self.warnings = warnings;
+        self
+    }
+
     /// Reset the validator internals
     pub fn reset(&mut self) {
         self.types.clear();
@@ -316,6 +606,7 @@ impl Validator {
         handle: Handle<crate::Constant>,
         gctx: crate::proc::GlobalCtx,
         mod_info: &ModuleInfo,
+        override_expressions: &BitSet,
     ) -> Result<(), ConstantError> {
         let con = &gctx.constants[handle];
 
@@ -330,6 +621,12 @@ impl Validator {
             return Err(ConstantError::InvalidType);
         }
 
+        if con.r#override == crate::Override::None
+            && override_expressions.contains(con.init.index())
+        {
+            return Err(ConstantError::OverrideDependent);
+        }
+
         Ok(())
     }
 
@@ -340,9 +637,10 @@ impl Validator {
     ) -> Result<ModuleInfo, WithSpan<ValidationError>> {
         self.reset();
         self.reset_types(module.types.len());
+        self.analyzed_stages = self.stages_to_analyze(module);
 
         #[cfg(feature = "validate")]
-        Self::validate_module_handles(module).map_err(|e| e.with_span())?;
+        Self::validate_module_handles(module)?;
 
         self.layouter.update(module.to_ctx()).map_err(|e| {
             let handle = e.ty;
@@ -392,16 +690,22 @@ impl Validator {
 
         #[cfg(feature = "validate")]
         if self.flags.contains(ValidationFlags::CONSTANTS) {
+            let override_expressions = override_expressions(module.to_ctx());
             for (handle, _) in module.const_expressions.iter() {
-                self.validate_const_expression(handle, module.to_ctx(), &mod_info)
-                    .map_err(|source| {
-                        ValidationError::ConstExpression { handle, source }
-                            .with_span_handle(handle, &module.const_expressions)
-                    })?
+                self.validate_const_expression(
+                    handle,
+                    module.to_ctx(),
+                    &mod_info,
+                    &override_expressions,
+                )
+                .map_err(|source| {
+                    ValidationError::ConstExpression { handle, source }
+                        .with_span_handle(handle, &module.const_expressions)
+                })?
             }
 
             for (handle, constant) in module.constants.iter() {
-                self.validate_constant(handle, module.to_ctx(), &mod_info)
+                self.validate_constant(handle, module.to_ctx(), &mod_info, &override_expressions)
                     .map_err(|source| {
                         ValidationError::Constant {
                             handle,
@@ -426,6 +730,8 @@ impl Validator {
                 })?;
         }
 
+        #[cfg(feature = "validate")]
+        let mut call_depths = Vec::with_capacity(module.functions.len());
         for (handle, fun) in module.functions.iter() {
             match self.validate_function(fun, module, &mod_info, false) {
                 Ok(info) => mod_info.functions.push(info),
@@ -440,6 +746,19 @@ impl Validator {
                     }))
                 }
             }
+            #[cfg(feature = "validate")]
+            {
+                let depth = call_depth(&fun.body, &call_depths);
+                self.check_call_depth(depth).map_err(|source| {
+                    ValidationError::Function {
+                        handle,
+                        name: fun.name.clone().unwrap_or_default(),
+                        source,
+                    }
+                    .with_span_handle(handle, &module.functions)
+                })?;
+                call_depths.push(depth);
+            }
         }
 
         let mut ep_map = FastHashSet::default();
@@ -466,10 +785,106 @@ impl Validator {
                     }));
                 }
             }
+            #[cfg(feature = "validate")]
+            self.check_call_depth(call_depth(&ep.function.body, &call_depths))
+                .map_err(|source| {
+                    ValidationError::EntryPoint {
+                        stage: ep.stage,
+                        name: ep.name.clone(),
+                        source: EntryPointError::Function(source),
+                    }
+                    .with_span()
+                })?;
         }
 
         Ok(mod_info)
     }
+
+    /// Return the stages to analyze `module` for: those chosen with
+    /// [`Validator::stages`] that the module has entry points for, or all of
+    /// them if it has none.
+    fn stages_to_analyze(&self, module: &crate::Module) -> ShaderStages {
+        if module.entry_points.is_empty() {
+            return self.stages;
+        }
+        let present = module
+            .entry_points
+            .iter()
+            .fold(ShaderStages::empty(), |stages, ep| {
+                stages
+                    | match ep.stage {
+                        crate::ShaderStage::Vertex => ShaderStages::VERTEX,
+                        crate::ShaderStage::Fragment => ShaderStages::FRAGMENT,
+                        crate::ShaderStage::Compute => ShaderStages::COMPUTE,
+                    }
+            });
+        self.stages & present
+    }
+
+    #[cfg(feature = "validate")]
+    const fn check_call_depth(&self, depth: u32) -> Result<(), FunctionError> {
+        match self.limits.max_call_depth {
+            Some(max) if depth > max => Err(FunctionError::CallsTooDeep { depth, max }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Accept `info` as the result of validating `module`, without
+    /// validating it again.
+    ///
+    /// This is for `ModuleInfo`s that were serialized alongside their
+    /// module, so that a module validated at build time needn't be validated
+    /// again when it is loaded.
+    ///
+    /// Only the shape of `info` is checked: that it has an entry for every
+    /// type, constant expression, function, entry point, expression and
+    /// global variable in `module`, and that it was produced with at least
+    /// this validator's [`ValidationFlags`] and analyzed for at least the
+    /// stages it would analyze. The module's handles are checked
+    /// as well, when the `validate` feature is enabled.
+    ///
+    /// The caller must ensure that `info` really is what [`validate`]
+    /// returned for this same `module`, with at least this validator's
+    /// capabilities. Back ends trust the `ModuleInfo` they are given: with
+    /// one that doesn't describe the module, they may panic or generate
+    /// invalid shaders.
+    ///
+    /// [`validate`]: Validator::validate
+    pub fn assume_validated(
+        &self,
+        module: &crate::Module,
+        info: ModuleInfo,
+    ) -> Result<ModuleInfo, WithSpan<ValidationError>> {
+        #[cfg(feature = "validate")]
+        Self::validate_module_handles(module)?;
+
+        let stages = self.stages_to_analyze(module);
+        let function_matches = |fun_info: &FunctionInfo, fun: &crate::Function| {
+            fun_info.flags.contains(self.flags)
+                && fun_info.analyzed_stages().contains(stages)
+                && fun_info.expression_count() == fun.expressions.len()
+                && fun_info.global_variable_count() == module.global_variables.len()
+        };
+        let matches = info.type_flags.len() == module.types.len()
+            && info.const_expression_types.len() == module.const_expressions.len()
+            && info.functions.len() == module.functions.len()
+            && info.entry_points.len() == module.entry_points.len()
+            && info
+                .functions
+                .iter()
+                .zip(module.functions.iter())
+                .all(|(fun_info, (_, fun))| function_matches(fun_info, fun))
+            && info
+                .entry_points
+                .iter()
+                .zip(module.entry_points.iter())
+                .all(|(fun_info, ep)| function_matches(fun_info, &ep.function));
+        if !matches {
+            return Err(ValidationError::InfoMismatch.with_span());
+        }
+
+        Ok(info)
+    }
 }
 
 #[cfg(feature = "validate")]
@@ -488,3 +903,159 @@ fn validate_atomic_compare_exchange_struct(
                 width: crate::BOOL_WIDTH,
             }
 }
+
+/// Return how long the longest chain of calls starting in `block` is, given
+/// the depths of the functions it may call, indexed by handle.
+#[cfg(feature = "validate")]
+fn call_depth(block: &crate::Block, call_depths: &[u32]) -> u32 {
+    let mut depth = 0;
+    crate::proc::walk::for_each_statement(block, &mut |statement| {
+        if let crate::Statement::Call { function, .. } = *statement {
+            // Calls to functions not validated yet are caught elsewhere.
+            if let Some(&callee_depth) = call_depths.get(function.index()) {
+                depth = depth.max(callee_depth + 1);
+            }
+        }
+    });
+    depth
+}
+
+/// Return the indices of the constant expressions in `gctx` that refer to
+/// overridable constants, directly or through their operands, and so are
+/// [override expressions].
+///
+/// [override expressions]: crate#override-expressions
+#[cfg(feature = "validate")]
+fn override_expressions(gctx: crate::proc::GlobalCtx) -> BitSet {
+    use crate::Expression as E;
+
+    let mut set = BitSet::new();
+    for (handle, expression) in gctx.const_expressions.iter() {
+        let depends = |operand: Handle<crate::Expression>| set.contains(operand.index());
+        let is_override = match *expression {
+            E::Constant(constant) => {
+                let constant = &gctx.constants[constant];
+                constant.r#override != crate::Override::None || depends(constant.init)
+            }
+            E::Compose { ref components, .. } => components.iter().copied().any(depends),
+            E::Repeat { value, .. }
+            | E::Splat { value, .. }
+            | E::AccessIndex { base: value, .. }
+            | E::Unary { expr: value, .. }
+            | E::As { expr: value, .. } => depends(value),
+            E::Binary { left, right, .. } => depends(left) || depends(right),
+            E::Select {
+                condition,
+                accept,
+                reject,
+            } => depends(condition) || depends(accept) || depends(reject),
+            _ => false,
+        };
+        if is_override {
+            set.insert(handle.index());
+        }
+    }
+    set
+}
+
+#[cfg(all(
+    test,
+    feature = "test-util",
+    feature = "serialize",
+    feature = "deserialize"
+))]
+mod tests {
+    use super::{
+        Capabilities, GlobalUse, Handle, ModuleInfo, ValidationError, ValidationFlags, Validator,
+    };
+
+    #[test]
+    fn assume_validated() {
+        let fixture = crate::test_util::Fixture::wgsl(
+            "
+            @group(0) @binding(0) var<storage, read_write> data: array<u32>;
+
+            @compute @workgroup_size(64)
+            fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+                data[id.x] *= 2u;
+            }
+            ",
+        );
+        let text = ron::ser::to_string(&fixture.info).unwrap();
+        let reload = || ron::de::from_str::<ModuleInfo>(&text).unwrap();
+
+        let validator = Validator::new(ValidationFlags::all(), Capabilities::default());
+        let info = validator
+            .assume_validated(&fixture.module, reload())
+            .unwrap();
+        let (data, _) = fixture.module.global_variables.iter().next().unwrap();
+        assert_eq!(
+            info.get_entry_point(0)[data],
+            GlobalUse::READ | GlobalUse::WRITE
+        );
+
+        // An info for some other module is rejected.
+        let other = crate::test_util::Fixture::wgsl("fn f() {}");
+        let error = validator
+            .assume_validated(&other.module, reload())
+            .unwrap_err()
+            .into_inner();
+        assert!(matches!(error, ValidationError::InfoMismatch), "{error:?}");
+    }
+
+    #[test]
+    fn override_operands() {
+        use super::ConstExpressionError;
+        use crate::{Expression as E, Span};
+
+        // `op(c)`, for an overridable `i32` constant `c`.
+        let error = |op: &dyn Fn(Handle<crate::Expression>) -> crate::Expression| {
+            let mut module = crate::Module::default();
+            let ty = module.types.insert(
+                crate::Type {
+                    name: None,
+                    inner: crate::TypeInner::Scalar {
+                        kind: crate::ScalarKind::Sint,
+                        width: 4,
+                    },
+                },
+                Span::UNDEFINED,
+            );
+            let init = module
+                .const_expressions
+                .append(E::Literal(crate::Literal::I32(1)), Span::UNDEFINED);
+            let constant = module.constants.append(
+                crate::Constant {
+                    name: Some("c".to_string()),
+                    r#override: crate::Override::ByNameOrId(0),
+                    ty,
+                    init,
+                },
+                Span::UNDEFINED,
+            );
+            let c = module
+                .const_expressions
+                .append(E::Constant(constant), Span::UNDEFINED);
+            module.const_expressions.append(op(c), Span::UNDEFINED);
+            match crate::test_util::module_validation_error(&module, Capabilities::default()) {
+                ValidationError::ConstExpression { source, .. } => source,
+                other => panic!("{other:?}"),
+            }
+        };
+
+        let not = error(&|expr| E::Unary {
+            op: crate::UnaryOperator::LogicalNot,
+            expr,
+        });
+        assert!(
+            matches!(not, ConstExpressionError::InvalidOverrideOperands),
+            "{not:?}"
+        );
+        let cast = error(&|expr| E::As {
+            expr,
+            kind: crate::ScalarKind::Float,
+            convert: Some(3),
+        });
+        assert!(matches!(cast, ConstExpressionError::Width(_)), "{cast:?}");
+    }
+}
@@ -92,6 +92,10 @@ pub enum TypeError {
     MissingCapability(Capabilities),
     #[error("The {0:?} scalar width {1} is not supported for an atomic")]
     InvalidAtomicWidth(crate::ScalarKind, crate::Bytes),
+    #[error(
+        "Storage images with atomic access must have an `R32Uint` or `R32Sint` format, not {0:?}"
+    )]
+    InvalidAtomicStorageFormat(crate::StorageFormat),
     #[error("Invalid type for pointer target {0:?}")]
     InvalidPointerBase(Handle<crate::Type>),
     #[error("Unsized types like {base:?} must be in the `Storage` address space, not `{space:?}`")]
@@ -124,6 +128,14 @@ pub enum TypeError {
     },
     #[error("Structure types must have at least one member")]
     EmptyStruct,
+    #[error(
+        "Structure member[{index}] has type {ty:?}, which is a resource handle: images, samplers, acceleration structures and binding arrays can only be global variables or function arguments"
+    )]
+    HandleInStruct { index: u32, ty: Handle<crate::Type> },
+    #[error("Type is nested {depth} arrays and structs deep, exceeding the limit of {max}")]
+    NestingTooDeep { depth: u32, max: u32 },
+    #[error("Array of {size} elements exceeds the limit of {max}")]
+    ArrayTooLarge { size: u32, max: u32 },
     #[error(transparent)]
     WidthError(#[from] WidthError),
 }
@@ -138,9 +150,6 @@ pub enum WidthError {
         name: &'static str,
         flag: &'static str,
     },
-
-    #[error("64-bit integers are not yet supported")]
-    Unsupported64Bit,
 }
 
 // Only makes sense if `flags.contains(HOST_SHAREABLE)`
@@ -195,6 +204,9 @@ pub(super) struct TypeInfo {
     pub flags: TypeFlags,
     pub uniform_layout: LayoutCompatibility,
     pub storage_layout: LayoutCompatibility,
+    /// How many arrays and structs deep this type is: zero for anything
+    /// else, or one more than the deepest array element or struct member.
+    pub nesting: u32,
 }
 
 impl TypeInfo {
@@ -203,6 +215,7 @@ impl TypeInfo {
             flags: TypeFlags::empty(),
             uniform_layout: Ok(Alignment::ONE),
             storage_layout: Ok(Alignment::ONE),
+            nesting: 0,
         }
     }
 
@@ -211,6 +224,7 @@ impl TypeInfo {
             flags,
             uniform_layout: Ok(alignment),
             storage_layout: Ok(alignment),
+            nesting: 0,
         }
     }
 }
@@ -240,11 +254,34 @@ impl super::Validator {
                         });
                     }
                     true
+                } else if width == 2 {
+                    if !self.capabilities.contains(Capabilities::FLOAT16) {
+                        return Err(WidthError::MissingCapability {
+                            name: "f16",
+                            flag: "FLOAT16",
+                        });
+                    }
+                    true
+                } else {
+                    width == 4
+                }
+            }
+            crate::ScalarKind::Sint | crate::ScalarKind::Uint => {
+                if width == 8 {
+                    if !self.capabilities.contains(Capabilities::SHADER_INT64) {
+                        return Err(WidthError::MissingCapability {
+                            name: match kind {
+                                crate::ScalarKind::Sint => "i64",
+                                _ => "u64",
+                            },
+                            flag: "SHADER_INT64",
+                        });
+                    }
+                    true
                 } else {
                     width == 4
                 }
             }
-            crate::ScalarKind::Sint | crate::ScalarKind::Uint => width == 4,
         };
         if good {
             Ok(())
@@ -265,7 +302,32 @@ impl super::Validator {
         gctx: crate::proc::GlobalCtx,
     ) -> Result<TypeInfo, TypeError> {
         use crate::TypeInner as Ti;
-        Ok(match gctx.types[handle].inner {
+
+        // Handles to base and member types that aren't validated yet are
+        // caught below; their `TypeInfo` is still the dummy here.
+        let nesting = match gctx.types[handle].inner {
+            Ti::Array { base, .. } => self.types[base.index()].nesting + 1,
+            Ti::Struct { ref members, .. } => {
+                members
+                    .iter()
+                    .map(|member| self.types[member.ty.index()].nesting)
+                    .max()
+                    .unwrap_or(0)
+                    + 1
+            }
+            _ => 0,
+        };
+        #[cfg(feature = "validate")]
+        if let Some(max) = self.limits.max_struct_nesting {
+            if nesting > max {
+                return Err(TypeError::NestingTooDeep {
+                    depth: nesting,
+                    max,
+                });
+            }
+        }
+
+        let mut info = match gctx.types[handle].inner {
             Ti::Scalar { kind, width } => {
                 self.check_width(kind, width)?;
                 let shareable = if kind.is_numeric() {
@@ -320,11 +382,22 @@ impl super::Validator {
             Ti::Atomic { kind, width } => {
                 let good = match kind {
                     crate::ScalarKind::Bool | crate::ScalarKind::Float => false,
-                    crate::ScalarKind::Sint | crate::ScalarKind::Uint => width == 4,
+                    crate::ScalarKind::Sint | crate::ScalarKind::Uint => width == 4 || width == 8,
                 };
                 if !good {
                     return Err(TypeError::InvalidAtomicWidth(kind, width));
                 }
+                if width == 8 {
+                    self.check_width(kind, width)?;
+                    if !self.capabilities.intersects(
+                        Capabilities::SHADER_INT64_ATOMIC_MIN_MAX
+                            | Capabilities::SHADER_INT64_ATOMIC_ALL_OPS,
+                    ) {
+                        return Err(TypeError::MissingCapability(
+                            Capabilities::SHADER_INT64_ATOMIC_ALL_OPS,
+                        ));
+                    }
+                }
                 TypeInfo::new(
                     TypeFlags::DATA | TypeFlags::SIZED | TypeFlags::HOST_SHAREABLE,
                     Alignment::from_width(width),
@@ -432,6 +505,18 @@ impl super::Validator {
                     Err(e) => Err(e),
                 };
 
+                #[cfg(feature = "validate")]
+                if let (crate::ArraySize::Constant(len), Some(max)) =
+                    (size, self.limits.max_array_size)
+                {
+                    if len.get() > max {
+                        return Err(TypeError::ArrayTooLarge {
+                            size: len.get(),
+                            max,
+                        });
+                    }
+                }
+
                 let type_info_mask = match size {
                     crate::ArraySize::Constant(_) => {
                         TypeFlags::DATA
@@ -453,6 +538,7 @@ impl super::Validator {
                     flags: base_info.flags & type_info_mask,
                     uniform_layout,
                     storage_layout,
+                    nesting: 0,
                 }
             }
             Ti::Struct { ref members, span } => {
@@ -477,6 +563,22 @@ impl super::Validator {
                 let mut prev_struct_data: Option<(u32, u32)> = None;
 
                 for (i, member) in members.iter().enumerate() {
+                    // Backends have no way to store a resource handle in
+                    // memory, so reject these explicitly rather than as
+                    // just another non-data type.
+                    match gctx.types[member.ty].inner {
+                        Ti::Image { .. }
+                        | Ti::Sampler { .. }
+                        | Ti::AccelerationStructure
+                        | Ti::BindingArray { .. } => {
+                            return Err(TypeError::HandleInStruct {
+                                index: i as u32,
+                                ty: member.ty,
+                            });
+                        }
+                        _ => {}
+                    }
+
                     let base_info = &self.types[member.ty.index()];
                     if !base_info.flags.contains(TypeFlags::DATA) {
                         return Err(TypeError::InvalidData(member.ty));
@@ -582,11 +684,20 @@ impl super::Validator {
             Ti::Image {
                 dim,
                 arrayed,
-                class: _,
+                class,
             } => {
                 if arrayed && matches!(dim, crate::ImageDimension::Cube) {
                     self.require_type_capability(Capabilities::CUBE_ARRAY_TEXTURES)?;
                 }
+                if let crate::ImageClass::Storage { format, access } = class {
+                    if access.contains(crate::StorageAccess::ATOMIC) {
+                        self.require_type_capability(Capabilities::TEXTURE_ATOMIC)?;
+                        match format {
+                            crate::StorageFormat::R32Uint | crate::StorageFormat::R32Sint => {}
+                            _ => return Err(TypeError::InvalidAtomicStorageFormat(format)),
+                        }
+                    }
+                }
                 TypeInfo::new(TypeFlags::ARGUMENT, Alignment::ONE)
             }
             Ti::Sampler { .. } => TypeInfo::new(TypeFlags::ARGUMENT, Alignment::ONE),
@@ -624,6 +735,8 @@ impl super::Validator {
 
                 TypeInfo::new(base_info.flags & type_info_mask, Alignment::ONE)
             }
-        })
+        };
+        info.nesting = nesting;
+        Ok(info)
     }
 }
@@ -0,0 +1,16 @@
+// 64-bit atomic min and max on storage buffers, the only 64-bit atomics
+// Metal has. Their results must go unused.
+
+@group(0) @binding(0)
+var<storage, read_write> storage_atomic_scalar: atomic<u64>;
+@group(0) @binding(1)
+var<storage, read_write> storage_atomic_arr: array<atomic<i64>, 2>;
+
+@compute
+@workgroup_size(1)
+fn cs_main() {
+    atomicMax(&storage_atomic_scalar, 1lu);
+    atomicMax(&storage_atomic_arr[1], 1li);
+    atomicMin(&storage_atomic_scalar, 1lu);
+    atomicMin(&storage_atomic_arr[1], 1li);
+}
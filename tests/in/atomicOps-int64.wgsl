@@ -0,0 +1,17 @@
+// 64-bit atomics on storage buffers, which HLSL supports in full from
+// Shader Model 6.6.
+
+@group(0) @binding(0)
+var<storage, read_write> storage_atomic_scalar: atomic<u64>;
+@group(0) @binding(1)
+var<storage, read_write> storage_atomic_arr: array<atomic<i64>, 2>;
+
+@compute
+@workgroup_size(1)
+fn cs_main() {
+    atomicAdd(&storage_atomic_scalar, 1lu);
+    atomicAdd(&storage_atomic_arr[1], 1li);
+    atomicMax(&storage_atomic_scalar, 1lu);
+    atomicMin(&storage_atomic_arr[1], 1li);
+    atomicExchange(&storage_atomic_scalar, 1lu);
+}
@@ -0,0 +1,7 @@
+// Offsets and counts past the width of the value, which WGSL clamps.
+@compute @workgroup_size(1)
+fn main() {
+    var u = 0u;
+    u = extractBits(u, 33u, 40u);
+    u = insertBits(u, 1u, 40u, 33u);
+}
@@ -0,0 +1,7 @@
+#version 450
+
+const double tenth = 0.1lf;
+
+void main() {
+    double a = 0.5lf;
+}
@@ -0,0 +1,8 @@
+#version 450
+
+struct LightScatteringParams {
+    float BetaRay, BetaMie[3], HGg, DistanceMul[4], BlendCoeff;
+    vec3 SunDirection, SunColor;
+};
+
+void main() {}
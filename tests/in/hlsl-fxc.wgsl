@@ -0,0 +1,12 @@
+// Run through `hlsl::lower_for_fxc` before the HLSL backend sees it.
+
+@fragment
+fn main(@location(0) @interpolate(flat) index: i32, @location(1) column: vec2<f32>) -> @location(0) vec4<f32> {
+    // FXC may reject arrays built in the function body, so this becomes a
+    // `static const`.
+    let weights = array(1.0, 2.0, 3.0);
+    // FXC rejects some matrix constructors that take vectors, so this is
+    // written with the columns' scalars.
+    let m = mat2x2(column, vec2(weights[index], 1.0));
+    return vec4(m[0], m[1]);
+}
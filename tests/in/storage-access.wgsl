@@ -0,0 +1,16 @@
+// Storage buffer and image access modes, which the GLSL backend writes as
+// `readonly`, `writeonly` and `coherent` qualifiers.
+
+@group(0) @binding(0) var<storage, read> values: array<u32>;
+@group(0) @binding(1) var<storage, read_write> results: array<u32>;
+@coherent @group(0) @binding(2) var<storage, read_write> partial: array<u32>;
+@group(1) @binding(0) var src: texture_storage_2d<r32uint, read>;
+@group(1) @binding(1) var dst: texture_storage_2d<r32uint, write>;
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let texel = textureLoad(src, vec2<i32>(id.xy));
+    textureStore(dst, vec2<i32>(id.xy), texel);
+    results[id.x] = values[id.x];
+    partial[id.x] = results[id.x];
+}
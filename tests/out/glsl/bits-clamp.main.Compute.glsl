@@ -0,0 +1,17 @@
+#version 310 es
+
+precision highp float;
+precision highp int;
+
+layout(local_size_x = 1, local_size_y = 1, local_size_z = 1) in;
+
+
+void main() {
+    uint u = 0u;
+    uint _e2 = u;
+    u = bitfieldExtract(_e2, int(min(33u, 32u)), int(min(40u, 32u - min(33u, 32u))));
+    uint _e6 = u;
+    u = bitfieldInsert(_e6, 1u, int(min(40u, 32u)), int(min(33u, 32u - min(40u, 32u))));
+    return;
+}
+
@@ -39,44 +39,44 @@ void main() {
     f2_ = unpackHalf2x16(_e46);
     int _e48 = i;
     int _e49 = i;
-    i = bitfieldInsert(_e48, _e49, int(5u), int(10u));
+    i = bitfieldInsert(_e48, _e49, int(min(5u, 32u)), int(min(10u, 32u - min(5u, 32u))));
     ivec2 _e53 = i2_;
     ivec2 _e54 = i2_;
-    i2_ = bitfieldInsert(_e53, _e54, int(5u), int(10u));
+    i2_ = bitfieldInsert(_e53, _e54, int(min(5u, 32u)), int(min(10u, 32u - min(5u, 32u))));
     ivec3 _e58 = i3_;
     ivec3 _e59 = i3_;
-    i3_ = bitfieldInsert(_e58, _e59, int(5u), int(10u));
+    i3_ = bitfieldInsert(_e58, _e59, int(min(5u, 32u)), int(min(10u, 32u - min(5u, 32u))));
     ivec4 _e63 = i4_;
     ivec4 _e64 = i4_;
-    i4_ = bitfieldInsert(_e63, _e64, int(5u), int(10u));
+    i4_ = bitfieldInsert(_e63, _e64, int(min(5u, 32u)), int(min(10u, 32u - min(5u, 32u))));
     uint _e68 = u;
     uint _e69 = u;
-    u = bitfieldInsert(_e68, _e69, int(5u), int(10u));
+    u = bitfieldInsert(_e68, _e69, int(min(5u, 32u)), int(min(10u, 32u - min(5u, 32u))));
     uvec2 _e73 = u2_;
     uvec2 _e74 = u2_;
-    u2_ = bitfieldInsert(_e73, _e74, int(5u), int(10u));
+    u2_ = bitfieldInsert(_e73, _e74, int(min(5u, 32u)), int(min(10u, 32u - min(5u, 32u))));
     uvec3 _e78 = u3_;
     uvec3 _e79 = u3_;
-    u3_ = bitfieldInsert(_e78, _e79, int(5u), int(10u));
+    u3_ = bitfieldInsert(_e78, _e79, int(min(5u, 32u)), int(min(10u, 32u - min(5u, 32u))));
     uvec4 _e83 = u4_;
     uvec4 _e84 = u4_;
-    u4_ = bitfieldInsert(_e83, _e84, int(5u), int(10u));
+    u4_ = bitfieldInsert(_e83, _e84, int(min(5u, 32u)), int(min(10u, 32u - min(5u, 32u))));
     int _e88 = i;
-    i = bitfieldExtract(_e88, int(5u), int(10u));
+    i = bitfieldExtract(_e88, int(min(5u, 32u)), int(min(10u, 32u - min(5u, 32u))));
     ivec2 _e92 = i2_;
-    i2_ = bitfieldExtract(_e92, int(5u), int(10u));
+    i2_ = bitfieldExtract(_e92, int(min(5u, 32u)), int(min(10u, 32u - min(5u, 32u))));
     ivec3 _e96 = i3_;
-    i3_ = bitfieldExtract(_e96, int(5u), int(10u));
+    i3_ = bitfieldExtract(_e96, int(min(5u, 32u)), int(min(10u, 32u - min(5u, 32u))));
     ivec4 _e100 = i4_;
-    i4_ = bitfieldExtract(_e100, int(5u), int(10u));
+    i4_ = bitfieldExtract(_e100, int(min(5u, 32u)), int(min(10u, 32u - min(5u, 32u))));
     uint _e104 = u;
-    u = bitfieldExtract(_e104, int(5u), int(10u));
+    u = bitfieldExtract(_e104, int(min(5u, 32u)), int(min(10u, 32u - min(5u, 32u))));
     uvec2 _e108 = u2_;
-    u2_ = bitfieldExtract(_e108, int(5u), int(10u));
+    u2_ = bitfieldExtract(_e108, int(min(5u, 32u)), int(min(10u, 32u - min(5u, 32u))));
     uvec3 _e112 = u3_;
-    u3_ = bitfieldExtract(_e112, int(5u), int(10u));
+    u3_ = bitfieldExtract(_e112, int(min(5u, 32u)), int(min(10u, 32u - min(5u, 32u))));
     uvec4 _e116 = u4_;
-    u4_ = bitfieldExtract(_e116, int(5u), int(10u));
+    u4_ = bitfieldExtract(_e116, int(min(5u, 32u)), int(min(10u, 32u - min(5u, 32u))));
     int _e120 = i;
     i = findLSB(_e120);
     uvec2 _e122 = u2_;
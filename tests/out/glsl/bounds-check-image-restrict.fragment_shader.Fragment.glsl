@@ -51,8 +51,7 @@ vec4 test_textureLoad_3d(ivec3 coords_4, int level_4) {
 }
 
 vec4 test_textureLoad_multisampled_2d(ivec2 coords_5, int _sample) {
-    vec4 _e3 = texelFetch(_group_0_binding_4_fs, clamp(coords_5, ivec2(0), textureSize(_group_0_binding_4_fs) - ivec2(1)), clamp(_sample, 0, textureSamples(_group_0_binding_4_fs) - 1)
-);
+    vec4 _e3 = texelFetch(_group_0_binding_4_fs, clamp(coords_5, ivec2(0), textureSize(_group_0_binding_4_fs) - ivec2(1)), clamp(_sample, 0, textureSamples(_group_0_binding_4_fs) - 1));
     return _e3;
 }
 
@@ -21,32 +21,32 @@ layout(rgba8) writeonly uniform image3D _group_0_binding_11_fs;
 layout(location = 0) out vec4 _fs2p_location0;
 
 vec4 test_textureLoad_1d(int coords, int level) {
-    vec4 _e3 = (level < textureQueryLevels(_group_0_binding_0_fs) && coords < textureSize(_group_0_binding_0_fs, level) ? texelFetch(_group_0_binding_0_fs, coords, level) : vec4(0.0));
+    vec4 _e3 = (uint(level) < uint(textureQueryLevels(_group_0_binding_0_fs)) && uint(coords) < uint(textureSize(_group_0_binding_0_fs, level)) ? texelFetch(_group_0_binding_0_fs, coords, level) : vec4(0.0));
     return _e3;
 }
 
 vec4 test_textureLoad_2d(ivec2 coords_1, int level_1) {
-    vec4 _e3 = (level_1 < textureQueryLevels(_group_0_binding_1_fs) && all(lessThan(coords_1, textureSize(_group_0_binding_1_fs, level_1))) ? texelFetch(_group_0_binding_1_fs, coords_1, level_1) : vec4(0.0));
+    vec4 _e3 = (uint(level_1) < uint(textureQueryLevels(_group_0_binding_1_fs)) && all(lessThan(uvec2(coords_1), uvec2(textureSize(_group_0_binding_1_fs, level_1)))) ? texelFetch(_group_0_binding_1_fs, coords_1, level_1) : vec4(0.0));
     return _e3;
 }
 
 vec4 test_textureLoad_2d_array_u(ivec2 coords_2, uint index, int level_2) {
-    vec4 _e4 = (level_2 < textureQueryLevels(_group_0_binding_2_fs) && all(lessThan(ivec3(coords_2, index), textureSize(_group_0_binding_2_fs, level_2))) ? texelFetch(_group_0_binding_2_fs, ivec3(coords_2, index), level_2) : vec4(0.0));
+    vec4 _e4 = (uint(level_2) < uint(textureQueryLevels(_group_0_binding_2_fs)) && all(lessThan(uvec3(ivec3(coords_2, index)), uvec3(textureSize(_group_0_binding_2_fs, level_2)))) ? texelFetch(_group_0_binding_2_fs, ivec3(coords_2, index), level_2) : vec4(0.0));
     return _e4;
 }
 
 vec4 test_textureLoad_2d_array_s(ivec2 coords_3, int index_1, int level_3) {
-    vec4 _e4 = (level_3 < textureQueryLevels(_group_0_binding_2_fs) && all(lessThan(ivec3(coords_3, index_1), textureSize(_group_0_binding_2_fs, level_3))) ? texelFetch(_group_0_binding_2_fs, ivec3(coords_3, index_1), level_3) : vec4(0.0));
+    vec4 _e4 = (uint(level_3) < uint(textureQueryLevels(_group_0_binding_2_fs)) && all(lessThan(uvec3(ivec3(coords_3, index_1)), uvec3(textureSize(_group_0_binding_2_fs, level_3)))) ? texelFetch(_group_0_binding_2_fs, ivec3(coords_3, index_1), level_3) : vec4(0.0));
     return _e4;
 }
 
 vec4 test_textureLoad_3d(ivec3 coords_4, int level_4) {
-    vec4 _e3 = (level_4 < textureQueryLevels(_group_0_binding_3_fs) && all(lessThan(coords_4, textureSize(_group_0_binding_3_fs, level_4))) ? texelFetch(_group_0_binding_3_fs, coords_4, level_4) : vec4(0.0));
+    vec4 _e3 = (uint(level_4) < uint(textureQueryLevels(_group_0_binding_3_fs)) && all(lessThan(uvec3(coords_4), uvec3(textureSize(_group_0_binding_3_fs, level_4)))) ? texelFetch(_group_0_binding_3_fs, coords_4, level_4) : vec4(0.0));
     return _e3;
 }
 
 vec4 test_textureLoad_multisampled_2d(ivec2 coords_5, int _sample) {
-    vec4 _e3 = (_sample < textureSamples(_group_0_binding_4_fs) && all(lessThan(coords_5, textureSize(_group_0_binding_4_fs))) ? texelFetch(_group_0_binding_4_fs, coords_5, _sample) : vec4(0.0));
+    vec4 _e3 = (uint(_sample) < uint(textureSamples(_group_0_binding_4_fs)) && all(lessThan(uvec2(coords_5), uvec2(textureSize(_group_0_binding_4_fs)))) ? texelFetch(_group_0_binding_4_fs, coords_5, _sample) : vec4(0.0));
     return _e3;
 }
 
@@ -24,9 +24,9 @@ struct Light {
 const vec3 c_ambient = vec3(0.05, 0.05, 0.05);
 const uint c_max_lights = 10u;
 
-uniform Globals_block_0Vertex { Globals _group_0_binding_0_vs; };
+layout(std140) uniform Globals_block_0Vertex { Globals _group_0_binding_0_vs; };
 
-uniform Entity_block_1Vertex { Entity _group_1_binding_0_vs; };
+layout(std140) uniform Entity_block_1Vertex { Entity _group_1_binding_0_vs; };
 
 layout(location = 0) in ivec4 _p2vs_location0;
 layout(location = 1) in ivec4 _p2vs_location1;
@@ -0,0 +1,29 @@
+#version 310 es
+
+precision highp float;
+precision highp int;
+
+layout(local_size_x = 64, local_size_y = 1, local_size_z = 1) in;
+
+layout(std430) readonly buffer type_1_block_0Compute { uint _group_0_binding_0_cs[]; };
+
+layout(std430) buffer type_1_block_1Compute { uint _group_0_binding_1_cs[]; };
+
+layout(std430) coherent buffer type_1_block_2Compute { uint _group_0_binding_2_cs[]; };
+
+layout(r32ui) readonly uniform highp uimage2D _group_1_binding_0_cs;
+
+layout(r32ui) writeonly uniform highp uimage2D _group_1_binding_1_cs;
+
+
+void main() {
+    uvec3 id = gl_GlobalInvocationID;
+    uvec4 texel = imageLoad(_group_1_binding_0_cs, ivec2(id.xy));
+    imageStore(_group_1_binding_1_cs, ivec2(id.xy), texel);
+    uint _e14 = _group_0_binding_0_cs[id.x];
+    _group_0_binding_1_cs[id.x] = _e14;
+    uint _e21 = _group_0_binding_1_cs[id.x];
+    _group_0_binding_2_cs[id.x] = _e21;
+    return;
+}
+
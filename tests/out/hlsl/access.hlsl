@@ -253,10 +253,10 @@ float4 foo_vert(uint vi : SV_VertexID) : SV_Position
     foo = 1.0;
     test_matrix_within_struct_accesses();
     test_matrix_within_array_within_struct_accesses();
-    float4x3 _matrix = float4x3(asfloat(bar.Load3(0+0)), asfloat(bar.Load3(0+16)), asfloat(bar.Load3(0+32)), asfloat(bar.Load3(0+48)));
-    uint2 arr_1[2] = Constructarray2_uint2_(asuint(bar.Load2(144+0)), asuint(bar.Load2(144+8)));
-    float b = asfloat(bar.Load(0+3u*16+0));
-    int a_1 = asint(bar.Load(0+(((NagaBufferLengthRW(bar) - 160) / 8) - 2u)*8+160));
+    float4x3 _matrix = float4x3(asfloat(bar.Load3(0)), asfloat(bar.Load3(16)), asfloat(bar.Load3(32)), asfloat(bar.Load3(48)));
+    uint2 arr_1[2] = Constructarray2_uint2_(asuint(bar.Load2(144)), asuint(bar.Load2(152)));
+    float b = asfloat(bar.Load(3u*16));
+    int a_1 = asint(bar.Load((((NagaBufferLengthRW(bar) - 160) / 8) - 2u)*8+160));
     int2 c = asint(qux.Load2(0));
     const float _e33 = read_from_private(foo);
     c2_ = Constructarray5_int_(a_1, int(b), 3, 4, 5);
@@ -268,20 +268,20 @@ float4 foo_vert(uint vi : SV_VertexID) : SV_Position
 
 float4 foo_frag() : SV_Target0
 {
-    bar.Store(8+16+0, asuint(1.0));
+    bar.Store(24, asuint(1.0));
     {
         float4x3 _value2 = float4x3((0.0).xxx, (1.0).xxx, (2.0).xxx, (3.0).xxx);
-        bar.Store3(0+0, asuint(_value2[0]));
-        bar.Store3(0+16, asuint(_value2[1]));
-        bar.Store3(0+32, asuint(_value2[2]));
-        bar.Store3(0+48, asuint(_value2[3]));
+        bar.Store3(0, asuint(_value2[0]));
+        bar.Store3(16, asuint(_value2[1]));
+        bar.Store3(32, asuint(_value2[2]));
+        bar.Store3(48, asuint(_value2[3]));
     }
     {
         uint2 _value2[2] = Constructarray2_uint2_((0u).xx, (1u).xx);
-        bar.Store2(144+0, asuint(_value2[0]));
-        bar.Store2(144+8, asuint(_value2[1]));
+        bar.Store2(144, asuint(_value2[0]));
+        bar.Store2(152, asuint(_value2[1]));
     }
-    bar.Store(0+8+160, asuint(1));
+    bar.Store(168, asuint(1));
     qux.Store2(0, asuint((int2)0));
     return (0.0).xxxx;
 }
@@ -0,0 +1,13 @@
+RWByteAddressBuffer storage_atomic_scalar : register(u0);
+RWByteAddressBuffer storage_atomic_arr : register(u1);
+
+[numthreads(1, 1, 1)]
+void cs_main()
+{
+    uint64_t _e2; storage_atomic_scalar.InterlockedAdd64(0, 1uL, _e2);
+    int64_t _e6; storage_atomic_arr.InterlockedAdd64(8, 1L, _e6);
+    uint64_t _e9; storage_atomic_scalar.InterlockedMax64(0, 1uL, _e9);
+    int64_t _e13; storage_atomic_arr.InterlockedMin64(8, 1L, _e13);
+    uint64_t _e16; storage_atomic_scalar.InterlockedExchange64(0, 1uL, _e16);
+    return;
+}
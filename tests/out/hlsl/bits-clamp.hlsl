@@ -0,0 +1,11 @@
+[numthreads(1, 1, 1)]
+void main()
+{
+    uint u = 0u;
+
+    uint _expr2 = u;
+    u = (min(40u, 32u - min(33u, 32u)) == 0 ? 0 : (_expr2 << (32 - min(40u, 32u - min(33u, 32u)) - min(33u, 32u))) >> (32 - min(40u, 32u - min(33u, 32u))));
+    uint _expr6 = u;
+    u = (min(33u, 32u - min(40u, 32u)) == 0 ? _expr6 : (_expr6 & ~((4294967295u >> (32u - min(33u, 32u - min(40u, 32u)))) << min(40u, 32u))) | ((1u << min(40u, 32u)) & ((4294967295u >> (32u - min(33u, 32u - min(40u, 32u)))) << min(40u, 32u))));
+    return;
+}
@@ -34,44 +34,44 @@ void main()
     f2_ = float2(f16tof32(_expr46), f16tof32((_expr46) >> 16));
     int _expr48 = i;
     int _expr49 = i;
-    i = (10u == 0 ? _expr48 : (_expr48 & ~((4294967295u >> (32u - 10u)) << 5u)) | ((_expr49 << 5u) & ((4294967295u >> (32u - 10u)) << 5u)));
+    i = (min(10u, 32u - min(5u, 32u)) == 0 ? _expr48 : (_expr48 & ~((4294967295u >> (32u - min(10u, 32u - min(5u, 32u)))) << min(5u, 32u))) | ((_expr49 << min(5u, 32u)) & ((4294967295u >> (32u - min(10u, 32u - min(5u, 32u)))) << min(5u, 32u))));
     int2 _expr53 = i2_;
     int2 _expr54 = i2_;
-    i2_ = (10u == 0 ? _expr53 : (_expr53 & ~((4294967295u >> (32u - 10u)) << 5u)) | ((_expr54 << 5u) & ((4294967295u >> (32u - 10u)) << 5u)));
+    i2_ = (min(10u, 32u - min(5u, 32u)) == 0 ? _expr53 : (_expr53 & ~((4294967295u >> (32u - min(10u, 32u - min(5u, 32u)))) << min(5u, 32u))) | ((_expr54 << min(5u, 32u)) & ((4294967295u >> (32u - min(10u, 32u - min(5u, 32u)))) << min(5u, 32u))));
     int3 _expr58 = i3_;
     int3 _expr59 = i3_;
-    i3_ = (10u == 0 ? _expr58 : (_expr58 & ~((4294967295u >> (32u - 10u)) << 5u)) | ((_expr59 << 5u) & ((4294967295u >> (32u - 10u)) << 5u)));
+    i3_ = (min(10u, 32u - min(5u, 32u)) == 0 ? _expr58 : (_expr58 & ~((4294967295u >> (32u - min(10u, 32u - min(5u, 32u)))) << min(5u, 32u))) | ((_expr59 << min(5u, 32u)) & ((4294967295u >> (32u - min(10u, 32u - min(5u, 32u)))) << min(5u, 32u))));
     int4 _expr63 = i4_;
     int4 _expr64 = i4_;
-    i4_ = (10u == 0 ? _expr63 : (_expr63 & ~((4294967295u >> (32u - 10u)) << 5u)) | ((_expr64 << 5u) & ((4294967295u >> (32u - 10u)) << 5u)));
+    i4_ = (min(10u, 32u - min(5u, 32u)) == 0 ? _expr63 : (_expr63 & ~((4294967295u >> (32u - min(10u, 32u - min(5u, 32u)))) << min(5u, 32u))) | ((_expr64 << min(5u, 32u)) & ((4294967295u >> (32u - min(10u, 32u - min(5u, 32u)))) << min(5u, 32u))));
     uint _expr68 = u;
     uint _expr69 = u;
-    u = (10u == 0 ? _expr68 : (_expr68 & ~((4294967295u >> (32u - 10u)) << 5u)) | ((_expr69 << 5u) & ((4294967295u >> (32u - 10u)) << 5u)));
+    u = (min(10u, 32u - min(5u, 32u)) == 0 ? _expr68 : (_expr68 & ~((4294967295u >> (32u - min(10u, 32u - min(5u, 32u)))) << min(5u, 32u))) | ((_expr69 << min(5u, 32u)) & ((4294967295u >> (32u - min(10u, 32u - min(5u, 32u)))) << min(5u, 32u))));
     uint2 _expr73 = u2_;
     uint2 _expr74 = u2_;
-    u2_ = (10u == 0 ? _expr73 : (_expr73 & ~((4294967295u >> (32u - 10u)) << 5u)) | ((_expr74 << 5u) & ((4294967295u >> (32u - 10u)) << 5u)));
+    u2_ = (min(10u, 32u - min(5u, 32u)) == 0 ? _expr73 : (_expr73 & ~((4294967295u >> (32u - min(10u, 32u - min(5u, 32u)))) << min(5u, 32u))) | ((_expr74 << min(5u, 32u)) & ((4294967295u >> (32u - min(10u, 32u - min(5u, 32u)))) << min(5u, 32u))));
     uint3 _expr78 = u3_;
     uint3 _expr79 = u3_;
-    u3_ = (10u == 0 ? _expr78 : (_expr78 & ~((4294967295u >> (32u - 10u)) << 5u)) | ((_expr79 << 5u) & ((4294967295u >> (32u - 10u)) << 5u)));
+    u3_ = (min(10u, 32u - min(5u, 32u)) == 0 ? _expr78 : (_expr78 & ~((4294967295u >> (32u - min(10u, 32u - min(5u, 32u)))) << min(5u, 32u))) | ((_expr79 << min(5u, 32u)) & ((4294967295u >> (32u - min(10u, 32u - min(5u, 32u)))) << min(5u, 32u))));
     uint4 _expr83 = u4_;
     uint4 _expr84 = u4_;
-    u4_ = (10u == 0 ? _expr83 : (_expr83 & ~((4294967295u >> (32u - 10u)) << 5u)) | ((_expr84 << 5u) & ((4294967295u >> (32u - 10u)) << 5u)));
+    u4_ = (min(10u, 32u - min(5u, 32u)) == 0 ? _expr83 : (_expr83 & ~((4294967295u >> (32u - min(10u, 32u - min(5u, 32u)))) << min(5u, 32u))) | ((_expr84 << min(5u, 32u)) & ((4294967295u >> (32u - min(10u, 32u - min(5u, 32u)))) << min(5u, 32u))));
     int _expr88 = i;
-    i = (10u == 0 ? 0 : (_expr88 << (32 - 10u - 5u)) >> (32 - 10u));
+    i = (min(10u, 32u - min(5u, 32u)) == 0 ? 0 : (_expr88 << (32 - min(10u, 32u - min(5u, 32u)) - min(5u, 32u))) >> (32 - min(10u, 32u - min(5u, 32u))));
     int2 _expr92 = i2_;
-    i2_ = (10u == 0 ? 0 : (_expr92 << (32 - 10u - 5u)) >> (32 - 10u));
+    i2_ = (min(10u, 32u - min(5u, 32u)) == 0 ? 0 : (_expr92 << (32 - min(10u, 32u - min(5u, 32u)) - min(5u, 32u))) >> (32 - min(10u, 32u - min(5u, 32u))));
     int3 _expr96 = i3_;
-    i3_ = (10u == 0 ? 0 : (_expr96 << (32 - 10u - 5u)) >> (32 - 10u));
+    i3_ = (min(10u, 32u - min(5u, 32u)) == 0 ? 0 : (_expr96 << (32 - min(10u, 32u - min(5u, 32u)) - min(5u, 32u))) >> (32 - min(10u, 32u - min(5u, 32u))));
     int4 _expr100 = i4_;
-    i4_ = (10u == 0 ? 0 : (_expr100 << (32 - 10u - 5u)) >> (32 - 10u));
+    i4_ = (min(10u, 32u - min(5u, 32u)) == 0 ? 0 : (_expr100 << (32 - min(10u, 32u - min(5u, 32u)) - min(5u, 32u))) >> (32 - min(10u, 32u - min(5u, 32u))));
     uint _expr104 = u;
-    u = (10u == 0 ? 0 : (_expr104 << (32 - 10u - 5u)) >> (32 - 10u));
+    u = (min(10u, 32u - min(5u, 32u)) == 0 ? 0 : (_expr104 << (32 - min(10u, 32u - min(5u, 32u)) - min(5u, 32u))) >> (32 - min(10u, 32u - min(5u, 32u))));
     uint2 _expr108 = u2_;
-    u2_ = (10u == 0 ? 0 : (_expr108 << (32 - 10u - 5u)) >> (32 - 10u));
+    u2_ = (min(10u, 32u - min(5u, 32u)) == 0 ? 0 : (_expr108 << (32 - min(10u, 32u - min(5u, 32u)) - min(5u, 32u))) >> (32 - min(10u, 32u - min(5u, 32u))));
     uint3 _expr112 = u3_;
-    u3_ = (10u == 0 ? 0 : (_expr112 << (32 - 10u - 5u)) >> (32 - 10u));
+    u3_ = (min(10u, 32u - min(5u, 32u)) == 0 ? 0 : (_expr112 << (32 - min(10u, 32u - min(5u, 32u)) - min(5u, 32u))) >> (32 - min(10u, 32u - min(5u, 32u))));
     uint4 _expr116 = u4_;
-    u4_ = (10u == 0 ? 0 : (_expr116 << (32 - 10u - 5u)) >> (32 - 10u));
+    u4_ = (min(10u, 32u - min(5u, 32u)) == 0 ? 0 : (_expr116 << (32 - min(10u, 32u - min(5u, 32u)) - min(5u, 32u))) >> (32 - min(10u, 32u - min(5u, 32u))));
     int _expr120 = i;
     i = asint(firstbitlow(_expr120));
     uint2 _expr122 = u2_;
@@ -37,9 +37,9 @@ void main(uint3 global_invocation_id : SV_DispatchThreadID)
     if ((index >= NUM_PARTICLES)) {
         return;
     }
-    float2 _expr8 = asfloat(particlesSrc.Load2(0+index*16+0));
+    float2 _expr8 = asfloat(particlesSrc.Load2(index*16));
     vPos = _expr8;
-    float2 _expr14 = asfloat(particlesSrc.Load2(8+index*16+0));
+    float2 _expr14 = asfloat(particlesSrc.Load2(index*16+8));
     vVel = _expr14;
     bool loop_init = true;
     while(true) {
@@ -57,10 +57,10 @@ void main(uint3 global_invocation_id : SV_DispatchThreadID)
             continue;
         }
         uint _expr43 = i;
-        float2 _expr46 = asfloat(particlesSrc.Load2(0+_expr43*16+0));
+        float2 _expr46 = asfloat(particlesSrc.Load2(_expr43*16));
         pos = _expr46;
         uint _expr49 = i;
-        float2 _expr52 = asfloat(particlesSrc.Load2(8+_expr49*16+0));
+        float2 _expr52 = asfloat(particlesSrc.Load2(_expr49*16+8));
         vel = _expr52;
         float2 _expr53 = pos;
         float2 _expr54 = vPos;
@@ -137,8 +137,8 @@ void main(uint3 global_invocation_id : SV_DispatchThreadID)
         vPos.y = -1.0;
     }
     float2 _expr174 = vPos;
-    particlesDst.Store2(0+index*16+0, asuint(_expr174));
+    particlesDst.Store2(index*16, asuint(_expr174));
     float2 _expr179 = vVel;
-    particlesDst.Store2(8+index*16+0, asuint(_expr179));
+    particlesDst.Store2(index*16+8, asuint(_expr179));
     return;
 }
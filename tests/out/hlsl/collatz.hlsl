@@ -32,8 +32,8 @@ uint collatz_iterations(uint n_base)
 [numthreads(1, 1, 1)]
 void main(uint3 global_id : SV_DispatchThreadID)
 {
-    uint _expr9 = asuint(v_indices.Load(global_id.x*4+0));
+    uint _expr9 = asuint(v_indices.Load(global_id.x*4));
     const uint _e10 = collatz_iterations(_expr9);
-    v_indices.Store(global_id.x*4+0, asuint(_e10));
+    v_indices.Store(global_id.x*4, asuint(_e10));
     return;
 }
@@ -83,10 +83,10 @@ void test_msl_packed_vec3_()
     int idx = 1;
 
     alignment.Store3(0, asuint((1.0).xxx));
-    alignment.Store(0+0, asuint(1.0));
-    alignment.Store(0+0, asuint(2.0));
+    alignment.Store(0, asuint(1.0));
+    alignment.Store(0, asuint(2.0));
     int _expr16 = idx;
-    alignment.Store(_expr16*4+0, asuint(3.0));
+    alignment.Store(_expr16*4, asuint(3.0));
     FooStruct data = ConstructFooStruct(asfloat(alignment.Load3(0)), asfloat(alignment.Load(12)));
     float3 l0_ = data.v3_;
     float2 l1_ = data.v3_.zx;
@@ -122,13 +122,13 @@ void main(uint3 __local_invocation_id : SV_GroupThreadID)
     float3x2 _expr16 = ((float3x2)global_mat);
     float3 _expr18 = global_vec;
     wg[6] = mul(_expr18, _expr16).x;
-    float _expr26 = asfloat(dummy.Load(4+8));
+    float _expr26 = asfloat(dummy.Load(12));
     wg[5] = _expr26;
     float _expr32 = float_vecs[0].w;
     wg[4] = _expr32;
     float _expr37 = asfloat(alignment.Load(12));
     wg[3] = _expr37;
-    float _expr43 = asfloat(alignment.Load(0+0));
+    float _expr43 = asfloat(alignment.Load(0));
     wg[2] = _expr43;
     alignment.Store(12, asuint(4.0));
     wg[1] = float(((NagaBufferLength(dummy) - 0) / 8));
@@ -0,0 +1,20 @@
+typedef float ret_Constructarray3_float_[3];
+ret_Constructarray3_float_ Constructarray3_float_(float arg0, float arg1, float arg2) {
+    float ret[3] = { arg0, arg1, arg2 };
+    return ret;
+}
+
+static const float weights[3] = Constructarray3_float_(1.0, 2.0, 3.0);
+
+struct FragmentInput_main {
+    nointerpolation int index_1 : LOC0;
+    float2 column_1 : LOC1;
+};
+
+float4 main(FragmentInput_main fragmentinput_main) : SV_Target0
+{
+    int index = fragmentinput_main.index_1;
+    float2 column = fragmentinput_main.column_1;
+    float2x2 m = float2x2(column.x, column.y, weights[index], 1.0);
+    return float4(m[0], m[1]);
+}
@@ -107,7 +107,7 @@ float4 fs_main(FragmentInput_fs_main fragmentinput_fs_main) : SV_Target0
         }
         {
             uint _expr16 = i;
-            Light light = ConstructLight(float4x4(asfloat(s_lights.Load4(_expr16*96+0+0)), asfloat(s_lights.Load4(_expr16*96+0+16)), asfloat(s_lights.Load4(_expr16*96+0+32)), asfloat(s_lights.Load4(_expr16*96+0+48))), asfloat(s_lights.Load4(_expr16*96+64)), asfloat(s_lights.Load4(_expr16*96+80)));
+            Light light = ConstructLight(float4x4(asfloat(s_lights.Load4(_expr16*96)), asfloat(s_lights.Load4(_expr16*96+16)), asfloat(s_lights.Load4(_expr16*96+32)), asfloat(s_lights.Load4(_expr16*96+48))), asfloat(s_lights.Load4(_expr16*96+64)), asfloat(s_lights.Load4(_expr16*96+80)));
             uint _expr19 = i;
             const float _e23 = fetch_shadow(_expr19, mul(in_.world_position, light.proj));
             float3 light_dir = normalize((light.pos.xyz - in_.world_position.xyz));
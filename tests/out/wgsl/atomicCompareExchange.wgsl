@@ -8,8 +8,6 @@ var<storage, read_write> arr_u32_: array<atomic<u32>, 128>;
 @compute @workgroup_size(1, 1, 1) 
 fn test_atomic_compare_exchange_i32_() {
     var i: u32 = 0u;
-    var old: i32;
-    var exchanged: bool;
 
     loop {
         let _e2 = i;
@@ -18,9 +16,12 @@ fn test_atomic_compare_exchange_i32_() {
             break;
         }
         {
+            var old: i32;
+
             let _e6 = i;
             let _e8 = atomicLoad((&arr_i32_[_e6]));
             old = _e8;
+            var exchanged: bool;
             exchanged = false;
             loop {
                 let _e12 = exchanged;
@@ -50,8 +51,6 @@ fn test_atomic_compare_exchange_i32_() {
 @compute @workgroup_size(1, 1, 1) 
 fn test_atomic_compare_exchange_u32_() {
     var i_1: u32 = 0u;
-    var old_1: u32;
-    var exchanged_1: bool;
 
     loop {
         let _e2 = i_1;
@@ -60,9 +59,12 @@ fn test_atomic_compare_exchange_u32_() {
             break;
         }
         {
+            var old_1: u32;
+
             let _e6 = i_1;
             let _e8 = atomicLoad((&arr_u32_[_e6]));
             old_1 = _e8;
+            var exchanged_1: bool;
             exchanged_1 = false;
             loop {
                 let _e12 = exchanged_1;
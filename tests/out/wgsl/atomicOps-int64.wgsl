@@ -0,0 +1,14 @@
+@group(0) @binding(0) 
+var<storage, read_write> storage_atomic_scalar: atomic<u64>;
+@group(0) @binding(1) 
+var<storage, read_write> storage_atomic_arr: array<atomic<i64>, 2>;
+
+@compute @workgroup_size(1, 1, 1) 
+fn cs_main() {
+    let _e2 = atomicAdd((&storage_atomic_scalar), 1lu);
+    let _e6 = atomicAdd((&storage_atomic_arr[1]), 1li);
+    let _e9 = atomicMax((&storage_atomic_scalar), 1lu);
+    let _e13 = atomicMin((&storage_atomic_arr[1]), 1li);
+    let _e16 = atomicExchange((&storage_atomic_scalar), 1lu);
+    return;
+}
@@ -0,0 +1,10 @@
+@compute @workgroup_size(1, 1, 1) 
+fn main() {
+    var u: u32 = 0u;
+
+    let _e2 = u;
+    u = extractBits(_e2, 33u, 40u);
+    let _e6 = u;
+    u = insertBits(_e6, 1u, 40u, 33u);
+    return;
+}
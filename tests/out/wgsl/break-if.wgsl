@@ -8,12 +8,12 @@ fn breakIfEmpty() {
 }
 
 fn breakIfEmptyBody(a: bool) {
-    var b: bool;
-    var c: bool;
-
     loop {
         continuing {
+            var b: bool;
+
             b = a;
+            var c: bool;
             let _e2 = b;
             c = (a != _e2);
             let _e5 = c;
@@ -24,11 +24,11 @@ fn breakIfEmptyBody(a: bool) {
 }
 
 fn breakIf(a_1: bool) {
-    var d: bool;
-    var e: bool;
-
     loop {
+        var d: bool;
+
         d = a_1;
+        var e: bool;
         let _e2 = d;
         e = (a_1 != _e2);
         continuing {
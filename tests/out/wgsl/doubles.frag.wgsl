@@ -0,0 +1,13 @@
+const tenth: f64 = f64(0.1);
+
+fn main_1() {
+    var a: f64 = f64(0.5);
+
+    return;
+}
+
+@fragment 
+fn main() {
+    main_1();
+    return;
+}
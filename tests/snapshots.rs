@@ -47,6 +47,8 @@ struct SpirvOutParameters {
     #[serde(default)]
     clamp_frag_depth: bool,
     #[serde(default)]
+    restrict_storage_buffers: bool,
+    #[serde(default)]
     separate_entry_points: bool,
     #[serde(default)]
     #[cfg(all(feature = "deserialize", feature = "spv-out"))]
@@ -87,6 +89,22 @@ struct Parameters {
     #[cfg(all(feature = "deserialize", feature = "glsl-out"))]
     #[serde(default)]
     glsl_multiview: Option<std::num::NonZeroU32>,
+    /// Option sets to generate backend outputs with, in place of the ones
+    /// above. See [`Variant`].
+    #[serde(default)]
+    variants: Vec<Variant>,
+}
+
+/// One of several option sets to run the same input through.
+///
+/// Each variant's backend outputs are named after the input with `suffix`
+/// appended, so `variants: [(suffix: "-restrict", params: (...))]` for
+/// `foo.wgsl` produces `foo-restrict.msl` and so on. IR and analysis outputs
+/// don't depend on the options, and are only produced once.
+#[derive(serde::Deserialize)]
+struct Variant {
+    suffix: String,
+    params: Parameters,
 }
 
 /// Information about a shader input file.
@@ -108,6 +126,9 @@ struct Input {
     /// like `210-bevy-2d-shader.frag` and just add `.wgsl` to it, producing
     /// `210-bevy-2d-shader.frag.wgsl`.
     keep_input_extension: bool,
+
+    /// Text to append to output file names, for a parameter [`Variant`].
+    output_suffix: String,
 }
 
 impl Input {
@@ -126,6 +147,18 @@ impl Input {
             // `with_extension` would do.
             file_name: PathBuf::from(format!("{name}.{extension}")),
             keep_input_extension: false,
+            output_suffix: String::new(),
+        }
+    }
+
+    /// Return an `Input` for the same file, whose output file names have
+    /// `suffix` appended.
+    fn with_output_suffix(&self, suffix: &str) -> Input {
+        Input {
+            subdirectory: self.subdirectory.clone(),
+            file_name: self.file_name.clone(),
+            keep_input_extension: self.keep_input_extension,
+            output_suffix: format!("{}{suffix}", self.output_suffix),
         }
     }
 
@@ -183,15 +216,15 @@ impl Input {
 
     fn output_path(&self, subdirectory: &str, extension: &str) -> PathBuf {
         let mut output = self.output_directory(subdirectory);
-        if self.keep_input_extension {
-            let mut file_name = self.file_name.as_os_str().to_owned();
-            file_name.push(".");
-            file_name.push(extension);
-            output.push(&file_name);
+        let mut file_name = if self.keep_input_extension {
+            self.file_name.as_os_str().to_owned()
         } else {
-            output.push(&self.file_name);
-            output.set_extension(extension);
-        }
+            self.file_name.file_stem().unwrap().to_owned()
+        };
+        file_name.push(&self.output_suffix);
+        file_name.push(".");
+        file_name.push(extension);
+        output.push(&file_name);
         output
     }
 
@@ -275,6 +308,19 @@ fn check_targets(
         }
     }
 
+    #[cfg(feature = "binary")]
+    {
+        let bytes = naga::serialize::to_bytes(module).unwrap();
+        let decoded = naga::serialize::from_bytes(&bytes).unwrap();
+        // `Debug` shows the spans and block locals too.
+        assert_eq!(
+            format!("{decoded:?}"),
+            format!("{module:?}"),
+            "binary round trip changed test '{}'",
+            name.display()
+        );
+    }
+
     let info = naga::valid::Validator::new(naga::valid::ValidationFlags::all(), capabilities)
         .validate(module)
         .expect(&format!(
@@ -312,6 +358,59 @@ fn check_targets(
         }
     }
 
+    // Shaders meant for FXC go through its lowering pass before any backend
+    // sees them. That needs no capabilities the module didn't already have.
+    #[cfg(all(feature = "deserialize", feature = "hlsl-out"))]
+    let info = if params.hlsl.fxc_compat {
+        naga::back::hlsl::lower_for_fxc(module);
+        naga::valid::Validator::new(naga::valid::ValidationFlags::all(), capabilities)
+            .validate(module)
+            .expect(&format!(
+                "FXC-lowered module validation failed on test '{}'",
+                name.display()
+            ))
+    } else {
+        info
+    };
+
+    if params.variants.is_empty() {
+        write_backend_outputs(input, module, &info, &params, targets, source_code);
+    }
+    for variant in params.variants.iter() {
+        let capabilities = if variant.params.god_mode {
+            naga::valid::Capabilities::all()
+        } else {
+            naga::valid::Capabilities::default()
+        };
+        let info = naga::valid::Validator::new(naga::valid::ValidationFlags::all(), capabilities)
+            .validate(module)
+            .expect(&format!(
+                "Naga module validation failed on test '{}' with variant '{}'",
+                name.display(),
+                variant.suffix
+            ));
+        write_backend_outputs(
+            &input.with_output_suffix(&variant.suffix),
+            module,
+            &info,
+            &variant.params,
+            targets,
+            source_code,
+        );
+    }
+}
+
+#[allow(unused_variables)]
+fn write_backend_outputs(
+    input: &Input,
+    module: &naga::Module,
+    info: &naga::valid::ModuleInfo,
+    params: &Parameters,
+    targets: Targets,
+    source_code: Option<&str>,
+) {
+    let name = &input.file_name;
+
     #[cfg(all(feature = "deserialize", feature = "spv-out"))]
     {
         let debug_info = if cfg!(feature = "span") {
@@ -327,7 +426,7 @@ fn check_targets(
             write_output_spv(
                 input,
                 module,
-                &info,
+                info,
                 debug_info,
                 &params.spv,
                 params.bounds_check_policies,
@@ -340,7 +439,7 @@ fn check_targets(
             write_output_msl(
                 input,
                 module,
-                &info,
+                info,
                 &params.msl,
                 &params.msl_pipeline,
                 params.bounds_check_policies,
@@ -357,7 +456,7 @@ fn check_targets(
                 write_output_glsl(
                     input,
                     module,
-                    &info,
+                    info,
                     ep.stage,
                     &ep.name,
                     &params.glsl,
@@ -370,20 +469,26 @@ fn check_targets(
     #[cfg(feature = "dot-out")]
     {
         if targets.contains(Targets::DOT) {
-            let string = naga::back::dot::write(module, Some(&info), Default::default()).unwrap();
+            let string = naga::back::dot::write(module, Some(info), Default::default()).unwrap();
             input.write_output_file("dot", "dot", string);
         }
     }
     #[cfg(all(feature = "deserialize", feature = "hlsl-out"))]
     {
         if targets.contains(Targets::HLSL) {
-            write_output_hlsl(input, module, &info, &params.hlsl);
+            write_output_hlsl(
+                input,
+                module,
+                info,
+                &params.hlsl,
+                params.bounds_check_policies,
+            );
         }
     }
     #[cfg(all(feature = "deserialize", feature = "wgsl-out"))]
     {
         if targets.contains(Targets::WGSL) {
-            write_output_wgsl(input, module, &info, &params.wgsl);
+            write_output_wgsl(input, module, info, &params.wgsl);
         }
     }
 }
@@ -408,6 +513,10 @@ fn write_output_spv(
     );
     flags.set(spv::WriterFlags::FORCE_POINT_SIZE, params.force_point_size);
     flags.set(spv::WriterFlags::CLAMP_FRAG_DEPTH, params.clamp_frag_depth);
+    flags.set(
+        spv::WriterFlags::RESTRICT_STORAGE_BUFFERS,
+        params.restrict_storage_buffers,
+    );
 
     let options = spv::Options {
         lang_version: (params.version.0, params.version.1),
@@ -421,6 +530,7 @@ fn write_output_spv(
         binding_map: params.binding_map.clone(),
         zero_initialize_workgroup_memory: spv::ZeroInitializeWorkgroupMemoryMode::Polyfill,
         debug_info,
+        target_environment: spv::TargetEnvironment::Vulkan,
     };
 
     if params.separate_entry_points {
@@ -493,6 +603,23 @@ fn write_output_msl(
         }
     }
 
+    // Every reflected slot must match an attribute in the generated code.
+    for (ep, slots) in module.entry_points.iter().zip(tr_info.entry_point_slots) {
+        for slot in slots {
+            let attribute = format!(
+                "{} [[{}({})]]",
+                slot.name,
+                slot.kind.to_msl_name(),
+                slot.index
+            );
+            assert!(
+                string.contains(&attribute),
+                "Slot {slot:?} of '{}' has no `{attribute}` argument",
+                ep.name
+            );
+        }
+    }
+
     input.write_output_file("msl", "msl", string);
 }
 
@@ -540,14 +667,18 @@ fn write_output_hlsl(
     module: &naga::Module,
     info: &naga::valid::ModuleInfo,
     options: &naga::back::hlsl::Options,
+    bounds_check_policies: naga::proc::BoundsCheckPolicies,
 ) {
     use naga::back::hlsl;
     use std::fmt::Write as _;
 
     println!("generating HLSL");
 
+    let mut options = options.clone();
+    options.bounds_check_policies = bounds_check_policies;
+
     let mut buffer = String::new();
-    let mut writer = hlsl::Writer::new(&mut buffer, options);
+    let mut writer = hlsl::Writer::new(&mut buffer, &options);
     let reflection_info = writer.write(module, info).expect("HLSL write failed");
 
     input.write_output_file("hlsl", "hlsl", buffer);
@@ -561,20 +692,16 @@ fn write_output_hlsl(
             Ok(ref name) => name,
             Err(_) => continue,
         };
-        match ep.stage {
-            naga::ShaderStage::Vertex => &mut config.vertex,
-            naga::ShaderStage::Fragment => &mut config.fragment,
-            naga::ShaderStage::Compute => &mut config.compute,
-        }
-        .push(hlsl_snapshots::ConfigItem {
-            entry_point: name.clone(),
-            target_profile: format!(
-                "{}_{}",
-                ep.stage.to_hlsl_str(),
-                options.shader_model.to_str()
-            ),
-        });
+        let stage = match ep.stage {
+            naga::ShaderStage::Vertex => hlsl_snapshots::ShaderStage::Vertex,
+            naga::ShaderStage::Fragment => hlsl_snapshots::ShaderStage::Fragment,
+            naga::ShaderStage::Compute => hlsl_snapshots::ShaderStage::Compute,
+        };
+        config
+            .push(stage, name.clone(), options.shader_model.to_str())
+            .unwrap();
     }
+    config.fxc_compatible = options.fxc_compat.then_some(true);
 
     config.to_file(&input.output_path("hlsl", "ron")).unwrap();
 }
@@ -630,6 +757,10 @@ fn convert_wgsl() {
             "bits",
             Targets::SPIRV | Targets::METAL | Targets::GLSL | Targets::HLSL | Targets::WGSL,
         ),
+        (
+            "bits-clamp",
+            Targets::SPIRV | Targets::METAL | Targets::GLSL | Targets::HLSL | Targets::WGSL,
+        ),
         (
             "bitcast",
             Targets::SPIRV | Targets::METAL | Targets::GLSL | Targets::HLSL | Targets::WGSL,
@@ -697,6 +828,8 @@ fn convert_wgsl() {
             Targets::SPIRV | Targets::METAL | Targets::GLSL | Targets::HLSL | Targets::WGSL,
         ),
         ("atomicCompareExchange", Targets::SPIRV | Targets::WGSL),
+        ("atomicOps-int64", Targets::HLSL | Targets::WGSL),
+        ("atomicOps-int64-min-max", Targets::METAL | Targets::WGSL),
         (
             "padding",
             Targets::SPIRV | Targets::METAL | Targets::GLSL | Targets::HLSL | Targets::WGSL,
@@ -723,11 +856,7 @@ fn convert_wgsl() {
         ("bounds-check-zero-atomic", Targets::METAL),
         ("bounds-check-restrict", Targets::SPIRV | Targets::METAL),
         (
-            "bounds-check-image-restrict",
-            Targets::SPIRV | Targets::METAL | Targets::GLSL,
-        ),
-        (
-            "bounds-check-image-rzsw",
+            "bounds-check-image",
             Targets::SPIRV | Targets::METAL | Targets::GLSL,
         ),
         ("policy-mix", Targets::SPIRV | Targets::METAL),
@@ -736,6 +865,7 @@ fn convert_wgsl() {
             Targets::SPIRV | Targets::METAL | Targets::GLSL | Targets::HLSL | Targets::WGSL,
         ),
         ("cubeArrayShadow", Targets::GLSL),
+        ("storage-access", Targets::GLSL),
         (
             "math-functions",
             Targets::SPIRV | Targets::METAL | Targets::GLSL | Targets::HLSL | Targets::WGSL,
@@ -772,6 +902,7 @@ fn convert_wgsl() {
         ("invariant", Targets::GLSL),
         ("ray-query", Targets::SPIRV | Targets::METAL),
         ("hlsl-keyword", Targets::HLSL),
+        ("hlsl-fxc", Targets::HLSL),
         (
             "constructors",
             Targets::SPIRV | Targets::METAL | Targets::GLSL | Targets::HLSL | Targets::WGSL,
@@ -823,6 +954,8 @@ fn convert_spv(name: &str, adjust_coordinate_space: bool, targets: Targets) {
             adjust_coordinate_space,
             strict_capabilities: false,
             block_ctx_dump_prefix: None,
+            input_attachments_as_textures: false,
+            ignore_non_semantic_instructions: false,
         },
     )
     .unwrap();
@@ -858,6 +991,56 @@ fn convert_spv_all() {
     );
 }
 
+#[cfg(feature = "spv-in")]
+#[test]
+fn convert_spv_input_attachments() {
+    let _ = env_logger::try_init();
+
+    let input = Input::new(Some("spv"), "subpass-input", "spv");
+    let bytes = input.read_bytes();
+    let mut options = naga::front::spv::Options {
+        strict_capabilities: true,
+        ..Default::default()
+    };
+    assert!(matches!(
+        naga::front::spv::parse_u8_slice(&bytes, &options),
+        Err(naga::front::spv::Error::UnsupportedCapability(
+            spirv::Capability::InputAttachment
+        ))
+    ));
+    options.strict_capabilities = false;
+    assert!(matches!(
+        naga::front::spv::parse_u8_slice(&bytes, &options),
+        Err(naga::front::spv::Error::UnsupportedInputAttachment(_))
+    ));
+
+    options.input_attachments_as_textures = true;
+    let mut module = naga::front::spv::parse_u8_slice(&bytes, &options).unwrap();
+    let (_, global) = module
+        .global_variables
+        .iter()
+        .find(|(_, var)| var.name.as_deref() == Some("albedo"))
+        .unwrap();
+    assert_eq!(
+        module.types[global.ty].inner,
+        naga::TypeInner::Image {
+            dim: naga::ImageDimension::D2,
+            arrayed: false,
+            class: naga::ImageClass::Sampled {
+                kind: naga::ScalarKind::Float,
+                multi: false,
+            },
+        }
+    );
+    assert!(module.entry_points[0].function.arguments.iter().any(|arg| {
+        arg.binding
+            == Some(naga::Binding::BuiltIn(naga::BuiltIn::Position {
+                invariant: false,
+            }))
+    }));
+    check_targets(&input, &mut module, Targets::empty(), None);
+}
+
 #[cfg(feature = "glsl-in")]
 #[test]
 fn convert_glsl_variations_check() {
@@ -869,6 +1052,7 @@ fn convert_glsl_variations_check() {
             &naga::front::glsl::Options {
                 stage: naga::ShaderStage::Fragment,
                 defines: Default::default(),
+                include_resolver: None,
             },
             &source,
         )
@@ -904,6 +1088,7 @@ fn convert_glsl_folder() {
                         ext => panic!("Unknown extension for glsl file {ext}"),
                     },
                     defines: Default::default(),
+                    include_resolver: None,
                 },
                 &input.read_source(),
             )
@@ -918,6 +1103,25 @@ fn convert_glsl_folder() {
 
         #[cfg(feature = "wgsl-out")]
         {
+            // std140 gives the `float` arrays in `LightScatteringParams` a
+            // stride of 16, which WGSL can't express.
+            if file_name.to_str() == Some("std140-float-arrays.frag") {
+                let error = naga::back::wgsl::write_string(
+                    &module,
+                    &info,
+                    naga::back::wgsl::WriterFlags::empty(),
+                )
+                .unwrap_err();
+                assert!(
+                    matches!(
+                        error,
+                        naga::back::wgsl::Error::UnrepresentableStructLayout { ref member, .. }
+                            if member == "BetaMie"
+                    ),
+                    "{error}"
+                );
+                continue;
+            }
             write_output_wgsl(&input, &module, &info, &WgslOutParameters::default());
         }
     }
@@ -176,3 +176,448 @@ fn storage_image_formats() {
         "#,
     );
 }
+
+#[test]
+fn reused_writer() {
+    use naga::back::spv;
+    use naga::valid;
+
+    let translate = |writer: &mut spv::Writer, source: &str| {
+        let module = naga::front::wgsl::parse_str(source).unwrap();
+        let info = valid::Validator::new(valid::ValidationFlags::all(), valid::Capabilities::all())
+            .validate(&module)
+            .expect("validation failed");
+        let mut words = vec![];
+        writer
+            .write(&module, &info, None, &None, &mut words)
+            .unwrap();
+        words
+    };
+    let float64 = "var<private> x: f64;";
+    let plain = "var<private> x: f32;";
+
+    // A reused writer must produce the same words as a fresh one, and not
+    // carry capabilities over from the last module.
+    let mut writer = spv::Writer::new(&spv::Options::default()).unwrap();
+    translate(&mut writer, float64);
+    let reused = translate(&mut writer, plain);
+    assert!(!writer.get_capabilities_used().contains(&Ca::Float64));
+    let fresh = translate(
+        &mut spv::Writer::new(&spv::Options::default()).unwrap(),
+        plain,
+    );
+    assert_eq!(reused, fresh);
+
+    translate(&mut writer, float64);
+    writer.reset();
+    assert_eq!(
+        writer.get_capabilities_used().iter().collect::<Vec<_>>(),
+        [&Ca::Shader]
+    );
+}
+
+/// Build a compute shader that chooses between two storage buffers of four
+/// `element`s with a pointer `Select`, which WGSL can't express.
+///
+/// `body` adds the statements that use the select. It is given the entry
+/// point, and the expressions for the invocation index and the select.
+fn pointer_select_module(
+    element: naga::TypeInner,
+    body: impl FnOnce(
+        &mut naga::Function,
+        naga::Handle<naga::Expression>,
+        naga::Handle<naga::Expression>,
+    ),
+) -> naga::Module {
+    use naga::{Expression as E, Span};
+
+    let mut module = naga::Module::default();
+    let u32_ty = module.types.insert(
+        naga::Type {
+            name: None,
+            inner: naga::TypeInner::Scalar {
+                kind: naga::ScalarKind::Uint,
+                width: 4,
+            },
+        },
+        Span::UNDEFINED,
+    );
+    let element_ty = module.types.insert(
+        naga::Type {
+            name: None,
+            inner: element,
+        },
+        Span::UNDEFINED,
+    );
+    let array_ty = module.types.insert(
+        naga::Type {
+            name: None,
+            inner: naga::TypeInner::Array {
+                base: element_ty,
+                size: naga::ArraySize::Constant(std::num::NonZeroU32::new(4).unwrap()),
+                stride: 4,
+            },
+        },
+        Span::UNDEFINED,
+    );
+    let mut buffer = |name: &str, binding| {
+        module.global_variables.append(
+            naga::GlobalVariable {
+                name: Some(name.to_string()),
+                space: naga::AddressSpace::Storage {
+                    access: naga::StorageAccess::LOAD | naga::StorageAccess::STORE,
+                },
+                binding: Some(naga::ResourceBinding { group: 0, binding }),
+                ty: array_ty,
+                init: None,
+            },
+            Span::UNDEFINED,
+        )
+    };
+    let a = buffer("a", 0);
+    let b = buffer("b", 1);
+
+    let mut function = naga::Function {
+        arguments: vec![naga::FunctionArgument {
+            name: Some("index".to_string()),
+            ty: u32_ty,
+            binding: Some(naga::Binding::BuiltIn(naga::BuiltIn::LocalInvocationIndex)),
+        }],
+        ..Default::default()
+    };
+    let exprs = &mut function.expressions;
+    let index = exprs.append(E::FunctionArgument(0), Span::UNDEFINED);
+    let a = exprs.append(E::GlobalVariable(a), Span::UNDEFINED);
+    let b = exprs.append(E::GlobalVariable(b), Span::UNDEFINED);
+    let zero = exprs.append(E::Literal(naga::Literal::U32(0)), Span::UNDEFINED);
+    let condition = exprs.append(
+        E::Binary {
+            op: naga::BinaryOperator::Equal,
+            left: index,
+            right: zero,
+        },
+        Span::UNDEFINED,
+    );
+    let select = exprs.append(
+        E::Select {
+            condition,
+            accept: a,
+            reject: b,
+        },
+        Span::UNDEFINED,
+    );
+    function.body.push(
+        naga::Statement::Emit(naga::Range::new_from_bounds(condition, select)),
+        Span::UNDEFINED,
+    );
+    body(&mut function, index, select);
+
+    module.entry_points.push(naga::EntryPoint {
+        name: "main".to_string(),
+        stage: naga::ShaderStage::Compute,
+        early_depth_test: None,
+        workgroup_size: [1, 1, 1],
+        function,
+    });
+    module
+}
+
+/// Validate `module`, which uses pointer selects, and translate it to
+/// SPIR-V, using only `capabilities` if given.
+///
+/// Check that the result passes `spirv-val`, if it is installed, and that
+/// there are `OpSelect`s between pointers only if variable pointers were
+/// used. Return the capabilities used.
+fn write_pointer_select(
+    module: &naga::Module,
+    capabilities: Option<naga::FastHashSet<Ca>>,
+) -> naga::FastIndexSet<Ca> {
+    use naga::back::spv;
+    use naga::valid;
+    use rspirv::spirv::Op;
+
+    let validate = |capabilities| {
+        valid::Validator::new(valid::ValidationFlags::all(), capabilities).validate(module)
+    };
+    assert!(validate(valid::Capabilities::default()).is_err());
+    let info = validate(valid::Capabilities::POINTER_SELECT).expect("validation failed");
+
+    let options = spv::Options {
+        capabilities,
+        ..Default::default()
+    };
+    let mut writer = spv::Writer::new(&options).unwrap();
+    let mut words = vec![];
+    writer
+        .write(module, &info, None, &None, &mut words)
+        .unwrap();
+    let capabilities_used = writer.get_capabilities_used().clone();
+    spirv_val(&words);
+
+    let parsed = rspirv::dr::load_words(&words).expect("produced invalid SPIR-V");
+    let pointer_types: Vec<_> = parsed
+        .types_global_values
+        .iter()
+        .filter(|inst| inst.class.opcode == Op::TypePointer)
+        .filter_map(|inst| inst.result_id)
+        .collect();
+    let body = || {
+        parsed
+            .functions
+            .iter()
+            .flat_map(|fun| &fun.blocks)
+            .flat_map(|block| &block.instructions)
+    };
+    let pointer_selects = body()
+        .filter(|inst| inst.class.opcode == Op::Select)
+        .filter(|inst| {
+            inst.result_type
+                .map_or(false, |ty| pointer_types.contains(&ty))
+        })
+        .count();
+    let variable_pointers = capabilities_used.contains(&Ca::VariablePointersStorageBuffer)
+        || capabilities_used.contains(&Ca::VariablePointers);
+    if variable_pointers {
+        assert_ne!(pointer_selects, 0, "expected a pointer `OpSelect`");
+    } else {
+        assert_eq!(
+            pointer_selects, 0,
+            "pointer `OpSelect` without variable pointers"
+        );
+        assert!(
+            body().any(|inst| inst.class.opcode == Op::Phi),
+            "expected the lowered select's results to be merged with `OpPhi`"
+        );
+    }
+    capabilities_used
+}
+
+/// Check `words` with `spirv-val`, unless it isn't installed.
+fn spirv_val(words: &[u32]) {
+    use std::io::Write as _;
+    use std::process::{Command, Stdio};
+
+    let mut child = match Command::new("spirv-val")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+            eprintln!("`spirv-val` not found; skipping SPIR-V validation");
+            return;
+        }
+        Err(error) => panic!("failed to run `spirv-val`: {error}"),
+    };
+    let bytes: Vec<u8> = words.iter().flat_map(|word| word.to_le_bytes()).collect();
+    child.stdin.take().unwrap().write_all(&bytes).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(
+        output.status.success(),
+        "`spirv-val` rejected the module:\n{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr),
+    );
+}
+
+/// Only allow the `Shader` capability, so variable pointers aren't available.
+fn shader_only() -> Option<naga::FastHashSet<Ca>> {
+    Some([Ca::Shader].into_iter().collect())
+}
+
+#[test]
+fn pointer_select() {
+    use naga::{Expression as E, Span};
+
+    // Increment an element of one of the buffers.
+    let u32_inner = naga::TypeInner::Scalar {
+        kind: naga::ScalarKind::Uint,
+        width: 4,
+    };
+    let module = pointer_select_module(u32_inner, |function, index, select| {
+        let exprs = &mut function.expressions;
+        let one = exprs.append(E::Literal(naga::Literal::U32(1)), Span::UNDEFINED);
+        let element = exprs.append(
+            E::Access {
+                base: select,
+                index,
+            },
+            Span::UNDEFINED,
+        );
+        let value = exprs.append(E::Load { pointer: element }, Span::UNDEFINED);
+        let sum = exprs.append(
+            E::Binary {
+                op: naga::BinaryOperator::Add,
+                left: value,
+                right: one,
+            },
+            Span::UNDEFINED,
+        );
+        function.body.push(
+            naga::Statement::Emit(naga::Range::new_from_bounds(element, sum)),
+            Span::UNDEFINED,
+        );
+        function.body.push(
+            naga::Statement::Store {
+                pointer: element,
+                value: sum,
+            },
+            Span::UNDEFINED,
+        );
+    });
+
+    // With no allow-list, `OpSelect` chooses between the pointers.
+    assert!(write_pointer_select(&module, None).contains(&Ca::VariablePointersStorageBuffer));
+
+    // Without variable pointers, the load and store are duplicated instead.
+    let lowered = write_pointer_select(&module, shader_only());
+    assert!(!lowered.contains(&Ca::VariablePointersStorageBuffer));
+    assert!(!lowered.contains(&Ca::VariablePointers));
+}
+
+#[test]
+fn pointer_select_atomic() {
+    use naga::{Expression as E, Span};
+
+    // Atomically increment an element of one of the buffers.
+    let atomic_inner = naga::TypeInner::Atomic {
+        kind: naga::ScalarKind::Uint,
+        width: 4,
+    };
+    let module = pointer_select_module(atomic_inner, |function, index, select| {
+        let u32_ty = function.arguments[0].ty;
+        let exprs = &mut function.expressions;
+        let one = exprs.append(E::Literal(naga::Literal::U32(1)), Span::UNDEFINED);
+        let element = exprs.append(
+            E::Access {
+                base: select,
+                index,
+            },
+            Span::UNDEFINED,
+        );
+        let result = exprs.append(
+            E::AtomicResult {
+                ty: u32_ty,
+                comparison: false,
+            },
+            Span::UNDEFINED,
+        );
+        function.body.push(
+            naga::Statement::Emit(naga::Range::new_from_bounds(element, element)),
+            Span::UNDEFINED,
+        );
+        function.body.push(
+            naga::Statement::Atomic {
+                pointer: element,
+                fun: naga::AtomicFunction::Add,
+                value: one,
+                result,
+            },
+            Span::UNDEFINED,
+        );
+    });
+
+    assert!(write_pointer_select(&module, None).contains(&Ca::VariablePointersStorageBuffer));
+
+    // Without variable pointers, the atomic is performed on one buffer or
+    // the other, and its results are merged.
+    let lowered = write_pointer_select(&module, shader_only());
+    assert!(!lowered.contains(&Ca::VariablePointersStorageBuffer));
+    assert!(!lowered.contains(&Ca::VariablePointers));
+}
+
+#[test]
+fn pointer_select_call_argument() {
+    use naga::{Expression as E, Span};
+
+    // Pass a select between two local variables to a function that loads
+    // through its pointer argument. WGSL can't select pointers, so patch the
+    // call's argument in after parsing.
+    let source = "
+        fn read(p: ptr<function, u32>) -> u32 {
+            return *p;
+        }
+
+        @compute @workgroup_size(1)
+        fn main(@builtin(local_invocation_index) index: u32) {
+            var x: u32;
+            var y: u32;
+            x = read(&x);
+        }
+    ";
+    let mut module = naga::front::wgsl::parse_str(source).unwrap();
+    let function = &mut module.entry_points[0].function;
+    let exprs = &mut function.expressions;
+    let find = |exprs: &naga::Arena<E>, wanted: &dyn Fn(&E) -> bool| {
+        exprs
+            .iter()
+            .find_map(|(handle, expr)| wanted(expr).then_some(handle))
+            .unwrap()
+    };
+    let local = |name| {
+        let variable = function
+            .local_variables
+            .iter()
+            .find_map(|(handle, var)| (var.name.as_deref() == Some(name)).then_some(handle))
+            .unwrap();
+        find(exprs, &|expr| *expr == E::LocalVariable(variable))
+    };
+    let x = local("x");
+    let y = local("y");
+    let index = find(exprs, &|expr| *expr == E::FunctionArgument(0));
+    let zero = exprs.append(E::Literal(naga::Literal::U32(0)), Span::UNDEFINED);
+    let condition = exprs.append(
+        E::Binary {
+            op: naga::BinaryOperator::Equal,
+            left: index,
+            right: zero,
+        },
+        Span::UNDEFINED,
+    );
+    let select = exprs.append(
+        E::Select {
+            condition,
+            accept: x,
+            reject: y,
+        },
+        Span::UNDEFINED,
+    );
+
+    let call = function
+        .body
+        .iter()
+        .position(|statement| matches!(*statement, naga::Statement::Call { .. }))
+        .unwrap();
+    if let naga::Statement::Call {
+        ref mut arguments, ..
+    } = function.body[call]
+    {
+        arguments[0] = select;
+    }
+    let mut emit = naga::Block::new();
+    emit.push(
+        naga::Statement::Emit(naga::Range::new_from_bounds(condition, select)),
+        Span::UNDEFINED,
+    );
+    function.body.splice(call..call, emit);
+
+    // Function arguments can't point into storage buffers or workgroup
+    // memory, so even with no allow-list, the call is written once for each
+    // variable.
+    write_pointer_select(&module, None);
+}
+
+#[test]
+fn float64() {
+    require(
+        &[Ca::Float64],
+        r#"
+        @fragment
+        fn f() -> @location(0) vec4<f32> {
+            let half = f64(0.5);
+            return vec4(f32(half * f64(2.0)));
+        }
+        "#,
+    );
+}
@@ -354,7 +354,7 @@ fn unknown_scalar_type() {
 2 │             const a: vec2<something>;
   │                           ^^^^^^^^^ unknown scalar type
   │
-  = note: Valid scalar types are f32, f64, i32, u32, bool
+  = note: Valid scalar types are f16, f32, f64, i32, u32, bool
 
 "#,
     );
@@ -1006,9 +1006,11 @@ fn main() -> @builtin(position) vec4<f32> {
 fn invalid_structs() {
     check_validation! {
         "struct Bad { data: sampler }",
-        "struct Bad { data: texture_2d<f32> }":
+        "struct Bad { data: texture_2d<f32> }",
+        "struct Bad { data: binding_array<texture_2d<f32>, 4> }",
+        "struct Bad { other: f32, data: binding_array<sampler> }":
         Err(naga::valid::ValidationError::Type {
-            source: naga::valid::TypeError::InvalidData(_),
+            source: naga::valid::TypeError::HandleInStruct { .. },
             ..
         })
     }
@@ -1307,6 +1309,75 @@ fn invalid_access() {
             ..
         })
     }
+
+    check_validation! {
+        "
+        fn vector_by_value(v: vec4<f32>) -> f32 {
+            return v[4];
+        }
+        ",
+        "
+        fn matrix_by_pointer() -> f32 {
+            var m: mat2x2<f32>;
+            return m[1][2];
+        }
+        ",
+        "
+        struct Inner { values: array<u32, 4> }
+        struct Outer { inner: Inner }
+        fn struct_chain() -> u32 {
+            var o: Outer;
+            return o.inner.values[2 * 2];
+        }
+        ":
+        Err(naga::valid::ValidationError::Function {
+            source: naga::valid::FunctionError::Expression {
+                source: naga::valid::ExpressionError::IndexOutOfBounds(_, _),
+                ..
+            },
+            ..
+        })
+    }
+}
+
+#[cfg(feature = "span")]
+#[test]
+fn out_of_bounds_index_span() {
+    let source = "
+        struct Inner { values: array<u32, 4> }
+        struct Outer { inner: Inner }
+        fn struct_chain() -> u32 {
+            var o: Outer;
+            return o.inner.values[2 * 2];
+        }
+    ";
+    let module = naga::front::wgsl::parse_str(source).unwrap();
+    let error = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::empty(),
+    )
+    .validate(&module)
+    .unwrap_err();
+    assert!(matches!(
+        *error.as_inner(),
+        naga::valid::ValidationError::Function {
+            source: naga::valid::FunctionError::Expression {
+                source: naga::valid::ExpressionError::IndexOutOfBounds(_, 4),
+                ..
+            },
+            ..
+        }
+    ));
+    let labels: Vec<_> = error
+        .spans()
+        .map(|&(span, ref label)| (&source[span], label.as_str()))
+        .collect();
+    assert!(
+        labels
+            .iter()
+            .any(|&(text, _)| text == "o.inner.values[2 * 2]"),
+        "{labels:?}"
+    );
 }
 
 #[test]
@@ -1501,39 +1572,133 @@ fn missing_default_case() {
 fn wrong_access_mode() {
     // The assignments to `global.i` should be forbidden, because they are in
     // variables whose access mode is `read`, not `read_write`.
-    check_validation! {
+    check(
         "
-            struct Globals {
-                i: i32
-            }
+        struct Globals { i: i32 }
+        @group(0) @binding(0) var<storage> globals: Globals;
+        fn store(v: i32) {
+            globals.i = v;
+        }
+        ",
+        r###"error: cannot assign to read-only memory in the `storage` address space
+  ┌─ wgsl:5:13
+  │
+5 │             globals.i = v;
+  │             ^^^^^^^^^ declared with `read` access, but assignment requires `read_write`
+  │
+  = note: `globals` is declared as `var<storage, read> globals: Globals`
+  = note: consider declaring it as `var<storage, read_write> globals: Globals`
 
-            @group(0) @binding(0)
-            var<storage> globals: Globals;
+"###,
+    );
 
-            fn store(v: i32) {
-                globals.i = v;
-            }
+    check(
+        "
+        struct Globals { i: i32 }
+        @group(0) @binding(0) var<uniform> globals: Globals;
+        fn store() {
+            globals.i++;
+        }
+        ",
+        r###"error: cannot assign to read-only memory in the `uniform` address space
+  ┌─ wgsl:5:13
+  │
+5 │             globals.i++;
+  │             ^^^^^^^^^ the `uniform` address space is read-only
+  │
+  = note: `globals` is declared as `var<uniform> globals: Globals`
+  = note: consider declaring it as `var<storage, read_write> globals: Globals`
+
+"###,
+    );
+}
+
+#[test]
+fn access_mode_not_allowed() {
+    check(
+        "
+        @group(0) @binding(0) var<uniform, read> u: f32;
+        ",
+        r###"error: access mode `read` cannot be specified for the `uniform` address space
+  ┌─ wgsl:2:44
+  │
+2 │         @group(0) @binding(0) var<uniform, read> u: f32;
+  │                                            ^^^^ access mode not allowed here
+  │
+  = note: only variables in the `storage` address space take an access mode
+  = note: consider declaring it as `var<uniform>`
+
+"###,
+    );
+}
+
+#[test]
+fn coherent_not_allowed() {
+    check(
+        "
+        @coherent @group(0) @binding(0) var<uniform> u: f32;
         ",
+        r###"error: the `@coherent` attribute cannot be used in the `uniform` address space
+  ┌─ wgsl:2:10
+  │
+2 │         @coherent @group(0) @binding(0) var<uniform> u: f32;
+  │          ^^^^^^^^ attribute not allowed here
+  │
+  = note: only storage buffers and storage textures can be coherent
+
+"###,
+    );
+}
+
+#[test]
+fn image_store_value_type() {
+    // Normalized formats take `vec4<f32>`, not the integer type of their
+    // underlying storage.
+    check_validation! {
         "
-            struct Globals {
-                i: i32
+            @group(0) @binding(0)
+            var image: texture_storage_2d<rgba8unorm, write>;
+
+            fn store() {
+                textureStore(image, vec2<i32>(0, 0), vec4<u32>(255u));
             }
+        ":
+        Err(
+            naga::valid::ValidationError::Function {
+                source: naga::valid::FunctionError::InvalidImageStore(
+                    naga::valid::ExpressionError::InvalidImageStoreValue {
+                        format: naga::StorageFormat::Rgba8Unorm,
+                        expected: naga::ScalarKind::Float,
+                        ..
+                    },
+                ),
+                ..
+            },
+        )
+    }
 
+    // Formats with fewer than four channels still take a four-component value.
+    check_validation! {
+        "
             @group(0) @binding(0)
-            var<uniform> globals: Globals;
+            var image: texture_storage_2d<r32uint, write>;
 
-            fn store(v: i32) {
-                globals.i = v;
+            fn store() {
+                textureStore(image, vec2<i32>(0, 0), 255u);
             }
         ":
         Err(
             naga::valid::ValidationError::Function {
-                name,
-                source: naga::valid::FunctionError::InvalidStorePointer(_),
+                source: naga::valid::FunctionError::InvalidImageStore(
+                    naga::valid::ExpressionError::InvalidImageStoreValue {
+                        format: naga::StorageFormat::R32Uint,
+                        expected: naga::ScalarKind::Uint,
+                        ..
+                    },
+                ),
                 ..
             },
         )
-            if name == "store"
     }
 }
 
@@ -1636,6 +1801,24 @@ fn host_shareable_types() {
     }
 }
 
+#[test]
+fn coherent_read_only() {
+    check_validation! {
+        "
+        @coherent @group(0) @binding(0) var<storage, read> data: array<u32>;
+        ",
+        "
+        @coherent @group(0) @binding(0) var image: texture_storage_2d<r32uint, read>;
+        ":
+        Err(
+            naga::valid::ValidationError::GlobalVariable {
+                source: naga::valid::GlobalVariableError::CoherentReadOnly,
+                ..
+            },
+        )
+    }
+}
+
 #[test]
 fn var_init() {
     check_validation! {
@@ -1692,6 +1875,35 @@ fn break_if_bad_condition() {
     }
 }
 
+#[test]
+fn continue_bypasses_declaration() {
+    check(
+        "
+        fn f() {
+            var i = 0;
+            loop {
+                if i > 4 { continue; }
+                let x = i * 2;
+                continuing { i += x; }
+            }
+        }
+    ",
+        r###"error: `continue` bypasses the declaration of `x`, which is used in the `continuing` block
+  ┌─ wgsl:5:28
+  │
+5 │                 if i > 4 { continue; }
+  │                            ^^^^^^^^^ this `continue` skips the declaration
+6 │                 let x = i * 2;
+  │                     ^ `x` is declared here
+7 │                 continuing { i += x; }
+  │                                   ^ and used in the `continuing` block here
+  │
+  = note: move the declaration of `x` before the `continue`, or out of the loop body
+
+"###,
+    );
+}
+
 #[test]
 fn swizzle_assignment() {
     check(
@@ -1992,3 +2204,1319 @@ fn binding_array_non_struct() {
         })
     }
 }
+
+#[test]
+fn position_invariance_pairing() {
+    use naga::valid::{check_position_invariance, InvarianceError};
+
+    let module = naga::front::wgsl::parse_str(
+        "
+        @vertex
+        fn vs_invariant() -> @builtin(position) @invariant vec4<f32> {
+            return vec4(0.0);
+        }
+
+        @vertex
+        fn vs_plain() -> @builtin(position) vec4<f32> {
+            return vec4(0.0);
+        }
+
+        @fragment
+        fn fs_invariant(@builtin(position) @invariant pos: vec4<f32>) -> @location(0) vec4<f32> {
+            return pos;
+        }
+
+        struct FragmentIn {
+            @builtin(position) pos: vec4<f32>,
+        }
+
+        @fragment
+        fn fs_plain(in: FragmentIn) -> @location(0) vec4<f32> {
+            return in.pos;
+        }
+
+        @fragment
+        fn fs_no_position() -> @location(0) vec4<f32> {
+            return vec4(1.0);
+        }
+        ",
+    )
+    .unwrap();
+    let ep = |name: &str| {
+        module
+            .entry_points
+            .iter()
+            .find(|ep| ep.name == name)
+            .unwrap()
+    };
+    let check = |vs, fs| check_position_invariance(&module, ep(vs), ep(fs));
+
+    assert!(check("vs_invariant", "fs_invariant").is_ok());
+    assert!(check("vs_plain", "fs_plain").is_ok());
+    assert!(check("vs_invariant", "fs_no_position").is_ok());
+    assert!(check("vs_plain", "fs_no_position").is_ok());
+    assert!(matches!(
+        check("vs_plain", "fs_invariant"),
+        Err(InvarianceError::FragmentOnly { .. })
+    ));
+    assert!(matches!(
+        check("vs_invariant", "fs_plain"),
+        Err(InvarianceError::VertexOnly { .. })
+    ));
+    assert!(matches!(
+        check("fs_plain", "fs_plain"),
+        Err(InvarianceError::WrongStage(_, naga::ShaderStage::Vertex))
+    ));
+}
+
+#[test]
+fn push_constant_limits() {
+    let source = "
+        struct PushConstants {
+            data: array<vec4<f32>, 9>,
+        }
+        var<push_constant> pc: PushConstants;
+
+        @fragment
+        fn main() -> @location(0) vec4<f32> {
+            return pc.data[0];
+        }
+    ";
+    let module = naga::front::wgsl::parse_str(source).unwrap();
+    let validate = |capabilities, max_size| {
+        naga::valid::Validator::new(naga::valid::ValidationFlags::all(), capabilities)
+            .limits(naga::valid::Limits {
+                max_push_constant_size: max_size,
+                ..naga::valid::Limits::default()
+            })
+            .validate(&module)
+            .map_err(|e| e.into_inner())
+    };
+
+    assert!(matches!(
+        validate(naga::valid::Capabilities::empty(), None),
+        Err(naga::valid::ValidationError::GlobalVariable {
+            source: naga::valid::GlobalVariableError::UnsupportedCapability(
+                naga::valid::Capabilities::PUSH_CONSTANT
+            ),
+            ..
+        })
+    ));
+    assert!(matches!(
+        validate(naga::valid::Capabilities::PUSH_CONSTANT, Some(128)),
+        Err(naga::valid::ValidationError::GlobalVariable {
+            source: naga::valid::GlobalVariableError::PushConstantTooLarge {
+                size: 144,
+                max: 128,
+            },
+            ..
+        })
+    ));
+    assert!(validate(naga::valid::Capabilities::PUSH_CONSTANT, Some(144)).is_ok());
+    assert!(validate(naga::valid::Capabilities::PUSH_CONSTANT, None).is_ok());
+}
+
+#[test]
+fn limits() {
+    use naga::valid::{EntryPointError, FunctionError, Limits, TypeError, ValidationError};
+
+    let source = "
+        struct Inner { values: array<f32, 8> }
+        struct Outer { inner: Inner }
+
+        fn leaf() -> f32 { return 1.0; }
+        fn middle() -> f32 { return leaf(); }
+
+        @fragment
+        fn main(@location(3) x: f32) -> @location(0) vec4<f32> {
+            var o: Outer;
+            return vec4(middle() + o.inner.values[0] + x);
+        }
+
+        @compute @workgroup_size(8, 8, 2)
+        fn cs() {}
+    ";
+    let module = naga::front::wgsl::parse_str(source).unwrap();
+    let validate = |limits| {
+        naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::default(),
+        )
+        .limits(limits)
+        .validate(&module)
+        .map_err(|e| e.into_inner())
+    };
+
+    assert!(matches!(
+        validate(Limits {
+            max_struct_nesting: Some(2),
+            ..Limits::default()
+        }),
+        Err(ValidationError::Type {
+            source: TypeError::NestingTooDeep { depth: 3, max: 2 },
+            ..
+        })
+    ));
+    assert!(matches!(
+        validate(Limits {
+            max_array_size: Some(4),
+            ..Limits::default()
+        }),
+        Err(ValidationError::Type {
+            source: TypeError::ArrayTooLarge { size: 8, max: 4 },
+            ..
+        })
+    ));
+    assert!(matches!(
+        validate(Limits {
+            max_call_depth: Some(0),
+            ..Limits::default()
+        }),
+        Err(ValidationError::Function {
+            source: FunctionError::CallsTooDeep { depth: 1, max: 0 },
+            ..
+        })
+    ));
+    assert!(matches!(
+        validate(Limits {
+            max_call_depth: Some(1),
+            ..Limits::default()
+        }),
+        Err(ValidationError::EntryPoint {
+            source: EntryPointError::Function(FunctionError::CallsTooDeep { depth: 2, max: 1 }),
+            ..
+        })
+    ));
+    assert!(matches!(
+        validate(Limits {
+            max_workgroup_size_product: Some(64),
+            ..Limits::default()
+        }),
+        Err(ValidationError::EntryPoint {
+            source: EntryPointError::WorkgroupTooLarge {
+                product: 128,
+                max: 64,
+            },
+            ..
+        })
+    ));
+    assert!(matches!(
+        validate(Limits {
+            max_location: Some(2),
+            ..Limits::default()
+        }),
+        Err(ValidationError::EntryPoint {
+            source: EntryPointError::Argument(
+                0,
+                naga::valid::VaryingError::LocationTooLarge {
+                    location: 3,
+                    max: 2
+                }
+            ),
+            ..
+        })
+    ));
+    assert!(validate(Limits {
+        max_struct_nesting: Some(3),
+        max_array_size: Some(8),
+        max_call_depth: Some(2),
+        max_workgroup_size_product: Some(128),
+        max_location: Some(3),
+    })
+    .is_ok());
+}
+
+#[test]
+fn profiles() {
+    use naga::valid::{
+        Capabilities, GlobalVariableError, Profile, ValidationError, WebGpuFeatures,
+    };
+
+    let source = "
+        var<push_constant> pc: vec4<f32>;
+
+        @fragment
+        fn main() -> @location(0) vec4<f32> {
+            return pc;
+        }
+    ";
+    let output = naga::front::wgsl::Frontend::with_profile(Profile::WebGpu)
+        .parse(source)
+        .expect_err("expected parser error")
+        .emit_to_string(source);
+    assert_eq!(
+        output,
+        r###"error: `push_constant` is not part of WebGPU
+  ┌─ wgsl:2:13
+  │
+2 │         var<push_constant> pc: vec4<f32>;
+  │             ^^^^^^^^^^^^^ only native applications may use this
+  │
+  = note: this shader is being parsed under the WebGPU profile
+
+"###
+    );
+
+    let module = naga::front::wgsl::Frontend::with_profile(Profile::Native)
+        .parse(source)
+        .unwrap();
+    assert!(matches!(
+        naga::valid::Validator::with_profile(Profile::WebGpu, WebGpuFeatures::empty())
+            .validate(&module)
+            .map_err(|e| e.into_inner()),
+        Err(ValidationError::GlobalVariable {
+            source: GlobalVariableError::UnsupportedCapability(Capabilities::PUSH_CONSTANT),
+            ..
+        })
+    ));
+    assert!(
+        naga::valid::Validator::with_profile(Profile::Native, WebGpuFeatures::empty())
+            .validate(&module)
+            .is_ok()
+    );
+
+    for source in [
+        "var<private> x: f64;",
+        "@group(0) @binding(0) var t: binding_array<texture_2d<f32>, 2>;",
+        "@group(0) @binding(0) var a: acceleration_structure;",
+        "@coherent @group(0) @binding(0) var<storage, read_write> b: u32;",
+        "enable nonuniform_indexing; fn f() {}",
+        "@fragment @early_depth_test fn main() {}",
+    ] {
+        let mut webgpu = naga::front::wgsl::Frontend::with_profile(Profile::WebGpu);
+        assert!(webgpu.parse(source).is_err(), "{source}");
+        let mut native = naga::front::wgsl::Frontend::with_profile(Profile::Native);
+        assert!(native.parse(source).is_ok(), "{source}");
+    }
+
+    // The names of Naga's own types are only reserved where those types exist.
+    for source in ["fn f() { let f64 = 1.0; }", "struct RayDesc { x: f32 }"] {
+        let mut webgpu = naga::front::wgsl::Frontend::with_profile(Profile::WebGpu);
+        assert!(webgpu.parse(source).is_ok(), "{source}");
+        let mut native = naga::front::wgsl::Frontend::with_profile(Profile::Native);
+        assert!(
+            matches!(native.parse(source), Err(error) if error.message().contains("reserved keyword")),
+            "{source}"
+        );
+    }
+
+    // Optional WebGPU features allow more capabilities.
+    let module = naga::front::wgsl::parse_str(
+        "
+        enable f16;
+        var<private> x: f16;
+        ",
+    )
+    .unwrap();
+    assert!(
+        naga::valid::Validator::with_profile(Profile::WebGpu, WebGpuFeatures::empty())
+            .validate(&module)
+            .is_err()
+    );
+    assert!(
+        naga::valid::Validator::with_profile(Profile::WebGpu, WebGpuFeatures::SHADER_F16)
+            .validate(&module)
+            .is_ok()
+    );
+    assert!(Profile::WebGpu
+        .capabilities(WebGpuFeatures::SUBGROUPS)
+        .contains(Capabilities::SUBGROUP));
+
+    let module = naga::front::wgsl::parse_str(
+        "
+        @compute @workgroup_size(16, 16, 2)
+        fn main() {}
+        ",
+    )
+    .unwrap();
+    assert!(
+        naga::valid::Validator::with_profile(Profile::WebGpu, WebGpuFeatures::empty())
+            .validate(&module)
+            .is_err()
+    );
+    assert!(
+        naga::valid::Validator::with_profile(Profile::Native, WebGpuFeatures::empty())
+            .validate(&module)
+            .is_ok()
+    );
+}
+
+#[test]
+fn pipeline_layout_compatibility() {
+    use naga::valid::{check_layout, BindingLayout, BindingType, PipelineLayoutError};
+    use naga::ResourceBinding;
+
+    let module = naga::front::wgsl::parse_str(
+        "
+        struct Params {
+            scale: vec4<f32>,
+        }
+        @group(0) @binding(0) var<uniform> params: Params;
+        @group(0) @binding(1) var<storage> data: array<f32>;
+        @group(1) @binding(0) var tex: texture_2d<f32>;
+        @group(1) @binding(1) var samp: sampler;
+        @group(2) @binding(0) var unused: sampler;
+
+        @fragment
+        fn main() -> @location(0) vec4<f32> {
+            return textureSample(tex, samp, vec2(data[0])) * params.scale;
+        }
+        ",
+    )
+    .unwrap();
+    let info = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::empty(),
+    )
+    .validate(&module)
+    .unwrap();
+
+    let entry = |group, binding, ty| BindingLayout {
+        binding: ResourceBinding { group, binding },
+        ty,
+        visibility: naga::valid::ShaderStages::FRAGMENT,
+        count: None,
+    };
+    let tex_ty = BindingType::Image {
+        dim: naga::ImageDimension::D2,
+        arrayed: false,
+        class: naga::ImageClass::Sampled {
+            kind: naga::ScalarKind::Float,
+            multi: false,
+        },
+    };
+    let layout = || {
+        vec![
+            entry(0, 0, BindingType::UniformBuffer { min_size: None }),
+            entry(
+                0,
+                1,
+                BindingType::StorageBuffer {
+                    access: naga::StorageAccess::LOAD | naga::StorageAccess::STORE,
+                    min_size: Some(64),
+                },
+            ),
+            entry(1, 0, tex_ty),
+            entry(1, 1, BindingType::Sampler { comparison: false }),
+        ]
+    };
+    let binding = |group, binding| ResourceBinding { group, binding };
+
+    assert!(check_layout(&module, &info, &layout()).is_ok());
+
+    let mut missing = layout();
+    missing.remove(3);
+    assert!(matches!(
+        check_layout(&module, &info, &missing),
+        Err(PipelineLayoutError::MissingBinding { binding: b, .. }) if b == binding(1, 1)
+    ));
+
+    let mut invisible = layout();
+    invisible[2].visibility = naga::valid::ShaderStages::VERTEX;
+    assert!(matches!(
+        check_layout(&module, &info, &invisible),
+        Err(PipelineLayoutError::NotVisible {
+            stage: naga::ShaderStage::Fragment,
+            ..
+        })
+    ));
+
+    let mut comparison = layout();
+    comparison[3].ty = BindingType::Sampler { comparison: true };
+    assert!(matches!(
+        check_layout(&module, &info, &comparison),
+        Err(PipelineLayoutError::TypeMismatch {
+            expected: BindingType::Sampler { comparison: true },
+            actual: BindingType::Sampler { comparison: false },
+            ..
+        })
+    ));
+
+    let mut too_small = layout();
+    too_small[0].ty = BindingType::UniformBuffer { min_size: Some(8) };
+    assert!(matches!(
+        check_layout(&module, &info, &too_small),
+        Err(PipelineLayoutError::BufferTooSmall {
+            expected: 8,
+            actual: 16,
+            ..
+        })
+    ));
+
+    let mut read_only = layout();
+    read_only[1].ty = BindingType::StorageBuffer {
+        access: naga::StorageAccess::LOAD,
+        min_size: None,
+    };
+    assert!(check_layout(&module, &info, &read_only).is_ok());
+
+    let mut arrayed = layout();
+    arrayed[2].count = Some(4);
+    assert!(matches!(
+        check_layout(&module, &info, &arrayed),
+        Err(PipelineLayoutError::CountMismatch {
+            expected: Some(4),
+            actual: None,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn validation_error_quotes_source() {
+    let source = "
+        fn matrix_by_value(m: mat4x4<f32>, i: i32) -> vec4<f32> {
+            return m[i];
+        }
+    ";
+    let module = naga::front::wgsl::parse_str(source).unwrap();
+    let error = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::default(),
+    )
+    .validate(&module)
+    .unwrap_err();
+
+    let output = error.emit_to_string(source);
+    assert!(output.contains("]: `m[i]`"), "{output}");
+}
+
+#[test]
+fn uniform_buffer_dynamic_indexing() {
+    let source = "
+        struct Lights { colors: array<vec4<f32>, 8> }
+        @group(0) @binding(0) var<uniform> lights: Lights;
+
+        fn color(i: u32) -> vec4<f32> {
+            return lights.colors[i] + lights.colors[3];
+        }
+    ";
+    let module = naga::front::wgsl::parse_str(source).unwrap();
+    let validate = |capabilities| {
+        naga::valid::Validator::new(naga::valid::ValidationFlags::all(), capabilities)
+            .validate(&module)
+    };
+
+    assert!(validate(naga::valid::Capabilities::default()).is_ok());
+    let error = validate(
+        naga::valid::Capabilities::default()
+            - naga::valid::Capabilities::UNIFORM_BUFFER_DYNAMIC_INDEXING,
+    )
+    .unwrap_err();
+    assert!(matches!(
+        error.as_inner(),
+        naga::valid::ValidationError::Function {
+            source: naga::valid::FunctionError::Expression {
+                source: naga::valid::ExpressionError::MissingCapabilities(
+                    naga::valid::Capabilities::UNIFORM_BUFFER_DYNAMIC_INDEXING
+                ),
+                ..
+            },
+            ..
+        }
+    ));
+    let output = error.emit_to_string(source);
+    assert!(output.contains("`lights.colors[i]`"), "{output}");
+}
+
+#[test]
+fn implicit_level_sampling_in_non_uniform_control_flow() {
+    use naga::valid::{ValidationWarning, WarningFlags};
+
+    let source = "
+        @group(0) @binding(0) var tex: texture_2d<f32>;
+        @group(0) @binding(1) var samp: sampler;
+
+        @fragment
+        fn main(@location(0) uv: vec2<f32>) -> @location(0) vec4<f32> {
+            var color = textureSample(tex, samp, uv);
+            if uv.x > 0.5 {
+                color = textureSample(tex, samp, uv * 2.0);
+            }
+            return color;
+        }
+    ";
+    let module = naga::front::wgsl::parse_str(source).unwrap();
+    let mut validator = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::empty(),
+    );
+
+    let info = validator.validate(&module).unwrap();
+    let warnings = info.get_entry_point(0).warnings();
+    // Only the sample inside the `if` is in non-uniform control flow.
+    assert_eq!(warnings.len(), 1);
+    assert!(matches!(
+        warnings[0].as_inner(),
+        ValidationWarning::ImplicitLevelInNonUniformControlFlow { .. }
+    ));
+    assert!(warnings[0]
+        .emit_to_string(source)
+        .contains("textureSample(tex, samp, uv * 2.0)"));
+
+    let info = validator
+        .warnings(WarningFlags::empty())
+        .validate(&module)
+        .unwrap();
+    assert!(info.get_entry_point(0).warnings().is_empty());
+}
+
+#[test]
+fn derivative_uniformity_diagnostic_filters() {
+    use naga::valid::{FunctionError, ValidationError, WarningFlags};
+
+    let shader = |directive: &str| {
+        format!(
+            "
+            {directive}
+            @group(0) @binding(0) var tex: texture_2d<f32>;
+            @group(0) @binding(1) var samp: sampler;
+
+            @fragment
+            fn main(@location(0) uv: vec2<f32>) -> @location(0) vec4<f32> {{
+                if uv.x > 0.5 {{
+                    return textureSample(tex, samp, uv);
+                }}
+                return vec4(0.0);
+            }}
+            "
+        )
+    };
+    let mut validator = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::empty(),
+    );
+    let mut warnings = |directive: &str, flags: WarningFlags| {
+        let module = naga::front::wgsl::parse_str(&shader(directive)).unwrap();
+        validator
+            .warnings(flags)
+            .validate(&module)
+            .map(|info| info.get_entry_point(0).warnings().len())
+    };
+
+    assert_eq!(warnings("", WarningFlags::all()).unwrap(), 1);
+    assert_eq!(
+        warnings(
+            "diagnostic(off, derivative_uniformity);",
+            WarningFlags::all()
+        )
+        .unwrap(),
+        0
+    );
+    assert_eq!(
+        warnings(
+            "diagnostic(info, derivative_uniformity);",
+            WarningFlags::all()
+        )
+        .unwrap(),
+        1
+    );
+    // Rules Naga doesn't know are ignored.
+    assert_eq!(
+        warnings(
+            "diagnostic(off, chromium.unreachable_code);",
+            WarningFlags::all()
+        )
+        .unwrap(),
+        1
+    );
+    // Turning the warning off in the validator wins over a filter that
+    // only asks for a warning, but not over one that asks for an error.
+    assert_eq!(
+        warnings(
+            "diagnostic(warning, derivative_uniformity);",
+            WarningFlags::empty()
+        )
+        .unwrap(),
+        0
+    );
+    let error = warnings(
+        "diagnostic(error, derivative_uniformity);",
+        WarningFlags::empty(),
+    )
+    .unwrap_err();
+    assert!(matches!(
+        error.into_inner(),
+        ValidationError::EntryPoint {
+            source: naga::valid::EntryPointError::Function(
+                FunctionError::NonUniformDerivative { .. }
+            ),
+            ..
+        }
+    ));
+
+    // The directives survive a round trip through WGSL.
+    #[cfg(feature = "wgsl-out")]
+    {
+        let module =
+            naga::front::wgsl::parse_str(&shader("diagnostic(off, derivative_uniformity);"))
+                .unwrap();
+        let info = validator.validate(&module).unwrap();
+        let output =
+            naga::back::wgsl::write_string(&module, &info, naga::back::wgsl::WriterFlags::empty())
+                .unwrap();
+        assert!(output.starts_with("diagnostic(off, derivative_uniformity);\n"));
+    }
+}
+
+#[test]
+fn bad_diagnostic_directives() {
+    check(
+        "diagnostic(loud, derivative_uniformity);",
+        r###"error: unknown diagnostic severity `loud`
+  ┌─ wgsl:1:12
+  │
+1 │ diagnostic(loud, derivative_uniformity);
+  │            ^^^^ unknown severity
+  │
+  = note: the severities are `error`, `warning`, `info` and `off`
+
+"###,
+    );
+
+    check(
+        r#"
+            diagnostic(off, derivative_uniformity);
+            diagnostic(error, derivative_uniformity);
+        "#,
+        r###"error: conflicting diagnostic filters for `derivative_uniformity`
+  ┌─ wgsl:2:23
+  │
+2 │             diagnostic(off, derivative_uniformity);
+  │                       ^^^^^^^^^^^^^^^^^^^^^^^^^^^^ this gives `derivative_uniformity` one severity
+3 │             diagnostic(error, derivative_uniformity);
+  │                       ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^ but this gives it another
+
+"###,
+    );
+}
+
+#[test]
+fn analyzed_stages() {
+    use naga::valid::ShaderStages;
+
+    let source = "
+        @group(0) @binding(0) var tex: texture_2d<f32>;
+        @group(0) @binding(1) var samp: sampler;
+
+        fn helper(uv: vec2<f32>) -> vec4<f32> {
+            if uv.x > 0.5 {
+                return textureSample(tex, samp, uv);
+            }
+            return vec4(0.0);
+        }
+
+        @fragment
+        fn main(@location(0) uv: vec2<f32>) -> @location(0) vec4<f32> {
+            return helper(uv);
+        }
+    ";
+    let module = naga::front::wgsl::parse_str(source).unwrap();
+    let (helper, _) = module.functions.iter().next().unwrap();
+    let mut validator = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::empty(),
+    );
+
+    let info = validator.validate(&module).unwrap();
+    assert_eq!(info[helper].warnings().len(), 1);
+    assert_eq!(
+        info.get_entry_point(0).analyzed_stages(),
+        ShaderStages::FRAGMENT
+    );
+
+    // Leaving out the fragment stage skips derivative uniformity.
+    let info = validator
+        .stages(ShaderStages::COMPUTE)
+        .validate(&module)
+        .unwrap();
+    assert!(info[helper].warnings().is_empty());
+    assert_eq!(info[helper].analyzed_stages(), ShaderStages::empty());
+
+    // A module with no entry points may be used from any stage.
+    let library = naga::front::wgsl::parse_str("fn f() {}").unwrap();
+    let info = validator
+        .stages(ShaderStages::all())
+        .validate(&library)
+        .unwrap();
+    let (f, _) = library.functions.iter().next().unwrap();
+    assert_eq!(info[f].analyzed_stages(), ShaderStages::all());
+}
+
+#[test]
+fn writes_depth() {
+    let module = naga::front::wgsl::parse_str(
+        "
+        struct Output {
+            @builtin(frag_depth) depth: f32,
+            @location(0) color: vec4<f32>,
+        }
+
+        @fragment
+        fn direct(@builtin(position) position: vec4<f32>) -> @builtin(frag_depth) f32 {
+            return position.z;
+        }
+
+        @fragment
+        fn structured(@builtin(position) position: vec4<f32>) -> Output {
+            return Output(position.z, vec4(1.0));
+        }
+
+        @fragment
+        fn color() -> @location(0) vec4<f32> {
+            return vec4(1.0);
+        }
+
+        @fragment
+        fn discards(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+            if position.x > 1.0 {
+                discard;
+            }
+            return vec4(1.0);
+        }
+        ",
+    )
+    .unwrap();
+    let info = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::empty(),
+    )
+    .validate(&module)
+    .unwrap();
+
+    let flags: Vec<_> = (0..module.entry_points.len())
+        .map(|index| {
+            let info = info.get_entry_point(index);
+            (info.writes_depth, info.allows_early_depth_test())
+        })
+        .collect();
+    assert_eq!(
+        flags,
+        [(true, false), (true, false), (false, true), (false, false)]
+    );
+}
+
+#[test]
+fn non_uniform_control_flow_cause() {
+    let source = "
+        @group(0) @binding(0) var tex: texture_2d<f32>;
+        @group(0) @binding(1) var samp: sampler;
+
+        @fragment
+        fn main(@location(0) uv: vec2<f32>) -> @location(0) vec4<f32> {
+            var color = vec4(0.0);
+            if uv.x > 0.5 {
+                color = textureSample(tex, samp, uv);
+            }
+            return color;
+        }
+    ";
+    let module = naga::front::wgsl::parse_str(source).unwrap();
+    let info = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::empty(),
+    )
+    .validate(&module)
+    .unwrap();
+
+    // The warning leads from the sample, through the `if`, back to the
+    // argument that makes it non-uniform.
+    let warning = &info.get_entry_point(0).warnings()[0];
+    let labels: Vec<_> = warning
+        .spans()
+        .map(|&(span, ref label)| (&source[span], label.as_str()))
+        .collect();
+    assert_eq!(labels[0].0, "textureSample(tex, samp, uv)");
+    assert!(labels[1].0.starts_with("if uv.x > 0.5"), "{labels:?}");
+    assert_eq!(labels[1].1, "this control flow is not uniform");
+    assert_eq!(
+        labels[2],
+        ("uv", "because this value may differ between invocations")
+    );
+    assert_eq!(labels[3], ("uv.x > 0.5", "and the condition depends on it"));
+}
+
+#[test]
+fn structured_diagnostics() {
+    let source = "const a = 1\nconst b = 2;";
+    let diagnostic = naga::front::wgsl::parse_str(source)
+        .unwrap_err()
+        .to_diagnostic();
+    assert_eq!(diagnostic.severity, naga::Severity::Error);
+    assert_eq!(diagnostic.code, "wgsl::unexpected");
+    assert_eq!(diagnostic.labels[0].0.location(source).line_number, 2);
+    assert_eq!(
+        diagnostic.suggestions,
+        [naga::Suggestion {
+            message: "insert ';'".to_string(),
+            span: naga::Span::new(12, 12),
+            replacement: ";".to_string(),
+        }]
+    );
+
+    let source = "
+        @group(0) @binding(0) var tex: texture_2d<f32>;
+        @group(0) @binding(1) var samp: sampler;
+
+        @fragment
+        fn main(@location(0) uv: vec2<f32>) -> @location(0) vec4<f32> {
+            if uv.x > 0.5 {
+                return textureSample(tex, samp, uv);
+            }
+            return vec4(0.0);
+        }
+    ";
+    let module = naga::front::wgsl::parse_str(source).unwrap();
+    let info = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::empty(),
+    )
+    .validate(&module)
+    .unwrap();
+    let diagnostic = info.get_entry_point(0).warnings()[0].to_diagnostic();
+    assert_eq!(diagnostic.severity, naga::Severity::Warning);
+    assert_eq!(
+        diagnostic.code,
+        "validation::implicit-level-in-non-uniform-control-flow"
+    );
+}
+
+#[cfg(feature = "span")]
+#[test]
+fn emit_options() {
+    let error_source = "const a = 1\nconst b = 2;";
+    let error = naga::front::wgsl::parse_str(error_source).unwrap_err();
+
+    let plain = naga::EmitOptions {
+        ascii: true,
+        ..Default::default()
+    };
+    let output = error.emit_to_string_with_options(error_source, "a.wgsl", &plain);
+    assert!(output.starts_with("error[wgsl::unexpected]: "), "{output}");
+    assert!(output.contains("--> a.wgsl:2:1"), "{output}");
+    assert!(!output.contains('\u{1b}'), "{output}");
+
+    let colored = naga::EmitOptions {
+        color: true,
+        ..Default::default()
+    };
+    let output = error.emit_to_string_with_options(error_source, "a.wgsl", &colored);
+    assert!(output.contains('\u{1b}'), "{output}");
+
+    let narrow = naga::EmitOptions {
+        ascii: true,
+        max_width: Some(20),
+        ..Default::default()
+    };
+    let output = error.emit_to_string_with_options(error_source, "a.wgsl", &narrow);
+    let header: Vec<_> = output
+        .lines()
+        .take_while(|line| !line.contains("-->"))
+        .collect();
+    assert!(header.len() > 1, "{output}");
+
+    // Warnings are tagged as such, and several diagnostics can be rendered
+    // together.
+    let source = "
+        @group(0) @binding(0) var tex: texture_2d<f32>;
+        @group(0) @binding(1) var samp: sampler;
+
+        @fragment
+        fn main(@location(0) uv: vec2<f32>) -> @location(0) vec4<f32> {
+            if uv.x > 0.5 {
+                return textureSample(tex, samp, uv);
+            }
+            return vec4(0.0);
+        }
+    ";
+    let module = naga::front::wgsl::parse_str(source).unwrap();
+    let info = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::empty(),
+    )
+    .validate(&module)
+    .unwrap();
+    let warning = &info.get_entry_point(0).warnings()[0];
+    let output = warning.emit_to_string_with_options(source, "b.wgsl", &plain);
+    assert!(
+        output.starts_with("warning[validation::implicit-level-in-non-uniform-control-flow]: "),
+        "{output}"
+    );
+    assert!(
+        output.contains("`textureSample(tex, samp, uv)`"),
+        "{output}"
+    );
+
+    let diagnostics = [warning.to_diagnostic(), warning.to_diagnostic()];
+    let output = plain.emit_to_string(&diagnostics, source, "b.wgsl");
+    assert_eq!(output.matches("warning[").count(), 2, "{output}");
+}
+
+#[test]
+fn sample_integer_image() {
+    check_validation! {
+        "
+            @group(0) @binding(0) var image: texture_2d<u32>;
+            @group(0) @binding(1) var samp: sampler;
+
+            fn sample() -> vec4<u32> {
+                return textureSampleLevel(image, samp, vec2(0.5), 0.0);
+            }
+        ":
+        Err(naga::valid::ValidationError::Function {
+            source: naga::valid::FunctionError::Expression {
+                source: naga::valid::ExpressionError::InvalidSampledImageKind(
+                    naga::ScalarKind::Uint
+                ),
+                ..
+            },
+            ..
+        })
+    }
+
+    // Gathering from integer images is fine.
+    check_validation! {
+        "
+            @group(0) @binding(0) var image: texture_2d<i32>;
+            @group(0) @binding(1) var samp: sampler;
+
+            fn gather() -> vec4<i32> {
+                return textureGather(0, image, samp, vec2(0.5));
+            }
+        ":
+        Ok(_)
+    }
+}
+
+#[test]
+fn f16_needs_enable() {
+    check(
+        "var<private> x: f16;",
+        r###"error: the `f16` enable-extension is not enabled
+  ┌─ wgsl:1:17
+  │
+1 │ var<private> x: f16;
+  │                 ^^^ this needs the `f16` enable-extension
+  │
+  = note: add `enable f16;` at the start of the shader
+
+"###,
+    );
+
+    check(
+        "const x = 1h;",
+        r###"error: the `f16` enable-extension is not enabled
+  ┌─ wgsl:1:11
+  │
+1 │ const x = 1h;
+  │           ^^ this needs the `f16` enable-extension
+  │
+  = note: add `enable f16;` at the start of the shader
+
+"###,
+    );
+
+    check(
+        "enable f32;",
+        r###"error: unknown enable-extension `f32`
+  ┌─ wgsl:1:8
+  │
+1 │ enable f32;
+  │        ^^^ unknown enable-extension
+  │
+  = note: Naga supports the `f16`, `subgroups` and `nonuniform_indexing` enable-extensions
+
+"###,
+    );
+}
+
+#[test]
+fn f16_needs_capability() {
+    check_validation! {
+        "enable f16; var<private> x: vec2h;":
+        Err(naga::valid::ValidationError::Type {
+            source: naga::valid::TypeError::WidthError(
+                naga::valid::WidthError::MissingCapability { name: "f16", .. }
+            ),
+            ..
+        })
+    }
+}
+
+#[test]
+fn int64_needs_capability() {
+    check_validation! {
+        "var<private> x: i64;",
+        "var<private> x: vec2<u64>;":
+        Err(naga::valid::ValidationError::Type {
+            source: naga::valid::TypeError::WidthError(
+                naga::valid::WidthError::MissingCapability { name: "i64" | "u64", .. }
+            ),
+            ..
+        })
+    }
+}
+
+#[test]
+fn int64_atomic_min_max() {
+    use naga::valid::{Capabilities, ValidationFlags, Validator};
+
+    let validate = |source: &str| {
+        let module = naga::front::wgsl::parse_str(source).unwrap();
+        Validator::new(
+            ValidationFlags::all(),
+            Capabilities::SHADER_INT64 | Capabilities::SHADER_INT64_ATOMIC_MIN_MAX,
+        )
+        .validate(&module)
+    };
+
+    validate(
+        "
+        @group(0) @binding(0) var<storage, read_write> x: atomic<u64>;
+        @compute @workgroup_size(1)
+        fn main() { atomicMax(&x, 1lu); }
+        ",
+    )
+    .unwrap();
+
+    // Other functions, and other address spaces, need all the operations.
+    for source in [
+        "
+        @group(0) @binding(0) var<storage, read_write> x: atomic<u64>;
+        @compute @workgroup_size(1)
+        fn main() { atomicAdd(&x, 1lu); }
+        ",
+        "
+        var<workgroup> x: atomic<u64>;
+        @compute @workgroup_size(1)
+        fn main() { atomicMax(&x, 1lu); }
+        ",
+    ] {
+        let error = validate(source).unwrap_err().into_inner();
+        assert!(
+            matches!(
+                error,
+                naga::valid::ValidationError::EntryPoint {
+                    source: naga::valid::EntryPointError::Function(
+                        naga::valid::FunctionError::InvalidAtomic(
+                            naga::valid::AtomicError::MissingCapability(
+                                Capabilities::SHADER_INT64_ATOMIC_ALL_OPS
+                            )
+                        )
+                    ),
+                    ..
+                }
+            ),
+            "{error:?}"
+        );
+    }
+}
+
+#[test]
+fn image_atomics_need_capability() {
+    check_validation! {
+        "@group(0) @binding(0) var image: texture_storage_2d<r32uint, atomic>;":
+        Err(naga::valid::ValidationError::Type {
+            source: naga::valid::TypeError::MissingCapability(
+                naga::valid::Capabilities::TEXTURE_ATOMIC
+            ),
+            ..
+        })
+    }
+}
+
+#[test]
+fn subgroups_need_enable() {
+    check(
+        "fn f(x: u32) -> u32 { return subgroupAdd(x); }",
+        r###"error: the `subgroups` enable-extension is not enabled
+  ┌─ wgsl:1:30
+  │
+1 │ fn f(x: u32) -> u32 { return subgroupAdd(x); }
+  │                              ^^^^^^^^^^^ this needs the `subgroups` enable-extension
+  │
+  = note: add `enable subgroups;` at the start of the shader
+
+"###,
+    );
+}
+
+#[test]
+fn subgroups_need_capability() {
+    check_validation! {
+        "enable subgroups; fn f(x: u32) -> u32 { return subgroupAdd(x); }":
+        Err(naga::valid::ValidationError::Function {
+            source: naga::valid::FunctionError::Expression {
+                source: naga::valid::ExpressionError::MissingCapabilities(
+                    naga::valid::Capabilities::SUBGROUP
+                ),
+                ..
+            },
+            ..
+        })
+    }
+}
+
+#[test]
+fn non_uniform_indexing_needs_capability() {
+    check_validation! {
+        "
+        enable nonuniform_indexing;
+
+        @group(0) @binding(0) var textures: binding_array<texture_2d<f32>, 4>;
+        @group(0) @binding(1) var samp: sampler;
+        @group(0) @binding(2) var<uniform> which: u32;
+
+        @fragment
+        fn main(@location(0) uv: vec2<f32>) -> @location(0) vec4<f32> {
+            return textureSample(textures[nonUniform(which)], samp, uv);
+        }
+        ":
+        Err(naga::valid::ValidationError::Function {
+            source: naga::valid::FunctionError::Expression {
+                source: naga::valid::ExpressionError::MissingCapabilities(
+                    naga::valid::Capabilities::SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING
+                ),
+                ..
+            },
+            ..
+        })
+    }
+}
+
+#[test]
+fn subgroup_built_ins_need_capability() {
+    check_validation! {
+        "@compute @workgroup_size(1) fn main(@builtin(subgroup_size) size: u32) {}",
+        "@fragment fn main(@builtin(subgroup_invocation_id) lane: u32) {}":
+        Err(naga::valid::ValidationError::EntryPoint {
+            source: naga::valid::EntryPointError::Argument(
+                0,
+                naga::valid::VaryingError::UnsupportedCapability(
+                    naga::valid::Capabilities::SUBGROUP
+                ),
+            ),
+            ..
+        })
+    }
+}
+
+#[test]
+fn non_uniform_needs_enable() {
+    check(
+        "fn f(x: u32) -> u32 { return nonUniform(x); }",
+        r###"error: the `nonuniform_indexing` enable-extension is not enabled
+  ┌─ wgsl:1:30
+  │
+1 │ fn f(x: u32) -> u32 { return nonUniform(x); }
+  │                              ^^^^^^^^^^ this needs the `nonuniform_indexing` enable-extension
+  │
+  = note: add `enable nonuniform_indexing;` at the start of the shader
+
+"###,
+    );
+}
+
+#[test]
+fn non_uniform_needs_integer() {
+    check_validation! {
+        "enable nonuniform_indexing; fn f(x: f32) -> f32 { return nonUniform(x); }":
+        Err(naga::valid::ValidationError::Function {
+            source: naga::valid::FunctionError::Expression {
+                source: naga::valid::ExpressionError::InvalidNonUniformValue(_),
+                ..
+            },
+            ..
+        })
+    }
+}
+
+#[test]
+fn invalid_subgroup_operations() {
+    let validate = |source: &str| {
+        let module = naga::front::wgsl::parse_str(source).unwrap();
+        naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::SUBGROUP,
+        )
+        .validate(&module)
+        .map_err(|e| e.into_inner())
+    };
+
+    // Bitwise reductions only take integers.
+    let result = validate("enable subgroups; fn f(x: f32) -> f32 { return subgroupXor(x); }");
+    assert!(
+        matches!(
+            result,
+            Err(naga::valid::ValidationError::Function {
+                source: naga::valid::FunctionError::Expression {
+                    source: naga::valid::ExpressionError::InvalidSubgroupArgument(..),
+                    ..
+                },
+                ..
+            })
+        ),
+        "{result:?}"
+    );
+
+    // Broadcasts must read from the same invocation everywhere.
+    let result = validate(
+        "enable subgroups; fn f(x: f32, i: u32) -> f32 { return subgroupBroadcast(x, i); }",
+    );
+    assert!(
+        matches!(
+            result,
+            Err(naga::valid::ValidationError::Function {
+                source: naga::valid::FunctionError::Expression {
+                    source: naga::valid::ExpressionError::SubgroupIndexMustBeConstant(_),
+                    ..
+                },
+                ..
+            })
+        ),
+        "{result:?}"
+    );
+
+    assert!(validate(
+        "enable subgroups; fn f(x: f32, i: u32) -> f32 { return subgroupShuffle(x, i) + subgroupBroadcast(x, 3); }"
+    )
+    .is_ok());
+}
+
+#[cfg(feature = "span")]
+#[test]
+fn errors_name_their_source_file() {
+    let mut sources = naga::SourceMap::new();
+    let first = "@compute @workgroup_size(1) fn main() {}";
+    let second = "\nvar<private> x: f64;\n";
+    assert_eq!(sources.add("first.wgsl", first), naga::SourceId::PRIMARY);
+    let id = sources.add("second.wgsl", second);
+    assert_eq!(sources.name(id), Some("second.wgsl"));
+
+    let module = naga::front::wgsl::Frontend::new()
+        .parse_with_source_id(second, id)
+        .unwrap();
+    let (handle, _) = module.global_variables.iter().next().unwrap();
+    assert_eq!(module.global_variables.get_span(handle).source(), id);
+
+    let error = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::default(),
+    )
+    .validate(&module)
+    .unwrap_err();
+    let output = error.emit_to_string_with_sources(&sources);
+    assert!(output.contains("┌─ second.wgsl:2:"), "{output}");
+}
+
+#[test]
+fn parse_errors_name_their_source_file() {
+    let id = naga::SourceId::new(1);
+    let error = naga::front::wgsl::Frontend::new()
+        .parse_with_source_id("fn main() { let x = ; }", id)
+        .unwrap_err();
+    assert_ne!(error.labels().len(), 0);
+    for (span, _) in error.labels() {
+        assert_eq!(span.source(), id);
+    }
+}
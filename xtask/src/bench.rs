@@ -0,0 +1,124 @@
+use std::{path::Path, time::SystemTime};
+
+use anyhow::{bail, ensure, Context};
+
+use crate::{
+    fs::{read_to_string, remove_dir_all},
+    glob::visit_files,
+    path::join_path,
+    process::EasyCommand,
+    result::ErrorStatus,
+};
+
+/// What `bench` should do with criterion's baselines.
+#[derive(Debug)]
+pub(crate) enum Baseline {
+    /// Save the results as the baseline with this name.
+    Save(String),
+
+    /// Compare the results with the baseline named `name`, and fail if any
+    /// benchmark's mean time grew by more than `threshold` percent.
+    Compare { name: String, threshold: f64 },
+}
+
+pub(crate) fn run(clean: bool, baseline: Option<Baseline>) -> anyhow::Result<()> {
+    let criterion_artifact_dir = join_path(["target", "criterion"]);
+    if clean {
+        log::info!("removing {}", criterion_artifact_dir.display());
+        remove_dir_all(&criterion_artifact_dir)
+            .with_context(|| format!("failed to remove {criterion_artifact_dir:?}"))?;
+    }
+
+    let started = SystemTime::now();
+    // Only the criterion harness understands the baseline options, so
+    // don't pass them to the library's own test harness.
+    EasyCommand::new("cargo", |cmd| match baseline {
+        None => cmd.arg("bench"),
+        Some(Baseline::Save(ref name)) => cmd
+            .args(["bench", "--bench", "criterion", "--", "--save-baseline"])
+            .arg(name),
+        Some(Baseline::Compare { ref name, .. }) => cmd
+            .args(["bench", "--bench", "criterion", "--", "--baseline"])
+            .arg(name),
+    })
+    .success()?;
+
+    let Some(Baseline::Compare { name, threshold }) = baseline else {
+        return Ok(());
+    };
+
+    let mut changes = Vec::new();
+    let status = visit_files(
+        &criterion_artifact_dir,
+        "**/change/estimates.json",
+        |path| {
+            let modified = path
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .with_context(|| format!("failed to fetch modification time for {path:?}"))?;
+            if modified < started {
+                // Left over from an earlier run, for a benchmark that no longer
+                // exists or didn't run this time.
+                return Ok(());
+            }
+            let bench_name = path
+                .parent()
+                .and_then(Path::parent)
+                .and_then(|dir| dir.strip_prefix(&criterion_artifact_dir).ok())
+                .with_context(|| format!("unexpected criterion output path {path:?}"))?;
+            let estimates =
+                read_to_string(path).with_context(|| format!("failed to read {path:?}"))?;
+            let change = mean_point_estimate(&estimates)
+                .with_context(|| format!("failed to find the mean change in {path:?}"))?;
+            changes.push((bench_name.display().to_string(), change * 100.0));
+            Ok(())
+        },
+    );
+    if let ErrorStatus::OneOrMoreFailuresFound = status {
+        bail!("failed to read one or more benchmark comparisons, see above output");
+    }
+    ensure!(
+        !changes.is_empty(),
+        "no benchmarks were compared with baseline {name:?}"
+    );
+
+    changes.sort_by(|a, b| a.0.cmp(&b.0));
+    let heading = "benchmark";
+    let width = changes
+        .iter()
+        .map(|(bench_name, _)| bench_name.len())
+        .chain([heading.len()])
+        .max()
+        .unwrap();
+    println!("{heading:width$}  {:>8}", "change");
+    let mut regressions = 0;
+    for (bench_name, change) in changes.iter() {
+        let note = if *change > threshold {
+            regressions += 1;
+            "  regressed"
+        } else {
+            ""
+        };
+        println!("{bench_name:width$}  {change:>+7.2}%{note}");
+    }
+
+    ensure!(
+        regressions == 0,
+        "{regressions} benchmark(s) slowed down by more than {threshold}% compared with baseline \
+        {name:?}"
+    );
+    Ok(())
+}
+
+/// Find `mean.point_estimate` in the contents of a criterion
+/// `estimates.json` file, which is the relative change in the mean time for
+/// a `change` directory.
+///
+/// The file's layout is simple and stable enough not to need a JSON parser.
+fn mean_point_estimate(estimates: &str) -> Option<f64> {
+    let key = "\"point_estimate\":";
+    let mean = &estimates[estimates.find("\"mean\"")?..];
+    let value = &mean[mean.find(key)? + key.len()..];
+    let end = value.find([',', '}'])?;
+    value[..end].trim().parse().ok()
+}
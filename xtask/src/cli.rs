@@ -3,12 +3,16 @@ use std::process::exit;
 use anyhow::{anyhow, bail, ensure, Context};
 use pico_args::Arguments;
 
+use crate::bench::Baseline;
+
 const HELP: &str = "\
 Usage: xtask <COMMAND>
 
 Commands:
   all
-  bench [--clean]
+  bench [--clean] [--save-baseline NAME | --compare NAME [--threshold PERCENT]]
+  hlsl-configs [--check]
+  install-tools
   validate
     dot
     glsl
@@ -23,6 +27,10 @@ Options:
   -h, --help  Print help
 ";
 
+/// How many percent slower than the baseline `bench --compare` lets a
+/// benchmark get before failing.
+const DEFAULT_BENCH_THRESHOLD: f64 = 5.0;
+
 #[derive(Debug)]
 pub(crate) struct Args {
     pub subcommand: Subcommand,
@@ -49,7 +57,14 @@ impl Args {
 #[derive(Debug)]
 pub(crate) enum Subcommand {
     All,
-    Bench { clean: bool },
+    Bench {
+        clean: bool,
+        baseline: Option<Baseline>,
+    },
+    HlslConfigs {
+        check: bool,
+    },
+    InstallTools,
     Validate(ValidateSubcommand),
 }
 
@@ -66,8 +81,34 @@ impl Subcommand {
             }
             "bench" => {
                 let clean = args.contains("--clean");
+                let save_baseline = args.opt_value_from_str("--save-baseline")?;
+                let compare = args.opt_value_from_str("--compare")?;
+                let threshold = args.opt_value_from_str("--threshold")?;
+                ensure_remaining_args_empty(args)?;
+                let baseline = match (save_baseline, compare, threshold) {
+                    (Some(_), Some(_), _) => {
+                        bail!("`--save-baseline` and `--compare` can't be used together")
+                    }
+                    (_, None, Some(_)) => {
+                        bail!("`--threshold` can only be used with `--compare`")
+                    }
+                    (Some(name), None, None) => Some(Baseline::Save(name)),
+                    (None, Some(name), threshold) => Some(Baseline::Compare {
+                        name,
+                        threshold: threshold.unwrap_or(DEFAULT_BENCH_THRESHOLD),
+                    }),
+                    (None, None, None) => None,
+                };
+                Ok(Self::Bench { clean, baseline })
+            }
+            "hlsl-configs" => {
+                let check = args.contains("--check");
+                ensure_remaining_args_empty(args)?;
+                Ok(Self::HlslConfigs { check })
+            }
+            "install-tools" => {
                 ensure_remaining_args_empty(args)?;
-                Ok(Self::Bench { clean })
+                Ok(Self::InstallTools)
             }
             "validate" => Ok(Self::Validate(ValidateSubcommand::parse(args)?)),
             other => {
@@ -4,24 +4,26 @@ use std::{
     process::{ExitCode, Stdio},
 };
 
-use anyhow::{bail, Context};
+use anyhow::{bail, ensure, Context};
 use cli::Args;
 
 use crate::{
     cli::{Subcommand, ValidateHlslCommand, ValidateSubcommand},
-    fs::{open_file, remove_dir_all},
+    fs::open_file,
     glob::visit_files,
     path::join_path,
     process::{which, EasyCommand},
     result::{ErrorStatus, LogIfError},
 };
 
+mod bench;
 mod cli;
 mod fs;
 mod glob;
 mod path;
 mod process;
 mod result;
+mod tools;
 
 fn main() -> ExitCode {
     env_logger::builder()
@@ -66,24 +68,43 @@ fn run(args: Args) -> anyhow::Result<()> {
             .success()?;
             Ok(())
         }
-        Subcommand::Bench { clean } => {
-            if clean {
-                let criterion_artifact_dir = join_path(["target", "criterion"]);
-                log::info!("removing {}", criterion_artifact_dir.display());
-                remove_dir_all(&criterion_artifact_dir)
-                    .with_context(|| format!("failed to remove {criterion_artifact_dir:?}"))?;
+        Subcommand::Bench { clean, baseline } => bench::run(clean, baseline),
+        Subcommand::InstallTools => tools::install(),
+        Subcommand::HlslConfigs { check } => {
+            // The snapshot tests write each HLSL snapshot's config from the
+            // entry points of the module it was generated from.
+            EasyCommand::simple("cargo", ["test", "--all-features", "--test", "snapshots"])
+                .success()?;
+
+            let hlsl_out = snapshots_base_out.join("hlsl");
+            let stale_status = visit_files(&hlsl_out, "*.ron", |path| {
+                ensure!(
+                    path.with_extension("hlsl").is_file(),
+                    "{} has no matching HLSL snapshot",
+                    path.display()
+                );
+                Ok(())
+            });
+            if let ErrorStatus::OneOrMoreFailuresFound = stale_status {
+                bail!("found configs for HLSL snapshots that no longer exist, see above output");
+            }
+
+            if check {
+                EasyCommand::new("git", |cmd| {
+                    cmd.args(["diff", "--exit-code", "--"])
+                        .arg(hlsl_out.join("*.ron"))
+                })
+                .success()
+                .context("HLSL snapshot configs are out of date")?;
             }
-            EasyCommand::simple("cargo", ["bench"]).success()
+            Ok(())
         }
         Subcommand::Validate(cmd) => {
             let ack_visiting = |path: &Path| log::info!("Validating {}", path.display());
             let err_status = match cmd {
                 ValidateSubcommand::Spirv => {
-                    let spirv_as = "spirv-as";
-                    which(spirv_as)?;
-
-                    let spirv_val = "spirv-val";
-                    which(spirv_val)?;
+                    let spirv_as = which("spirv-as")?;
+                    let spirv_val = which("spirv-val")?;
 
                     visit_files(snapshots_base_out, "spv/*.spvasm", |path| {
                         ack_visiting(path);
@@ -107,7 +128,7 @@ fn run(args: Args) -> anyhow::Result<()> {
                                 );
                             };
                         let file = open_file(path)?;
-                        let mut spirv_as_cmd = EasyCommand::new(spirv_as, |cmd| {
+                        let mut spirv_as_cmd = EasyCommand::new(&spirv_as, |cmd| {
                             cmd.stdin(Stdio::from(file))
                                 .stdout(Stdio::piped())
                                 .arg("--target-env")
@@ -117,7 +138,7 @@ fn run(args: Args) -> anyhow::Result<()> {
                         let child = spirv_as_cmd
                             .spawn()
                             .with_context(|| format!("failed to spawn {cmd:?}"))?;
-                        EasyCommand::new(spirv_val, |cmd| cmd.stdin(child.stdout.unwrap()))
+                        EasyCommand::new(&spirv_val, |cmd| cmd.stdin(child.stdout.unwrap()))
                             .success()
                     })
                 }
@@ -153,8 +174,7 @@ fn run(args: Args) -> anyhow::Result<()> {
                     })
                 }
                 ValidateSubcommand::Glsl => {
-                    let glslang_validator = "glslangValidator";
-                    which(glslang_validator)?;
+                    let glslang_validator = which("glslangValidator")?;
                     let mut err_status = ErrorStatus::NoFailuresFound;
                     for (glob, type_arg) in [
                         ("glsl/*.Vertex.glsl", "vert"),
@@ -164,7 +184,7 @@ fn run(args: Args) -> anyhow::Result<()> {
                         let type_err_status = visit_files(&snapshots_base_out, glob, |path| {
                             ack_visiting(path);
                             let file = open_file(path)?;
-                            EasyCommand::new(glslang_validator, |cmd| {
+                            EasyCommand::new(&glslang_validator, |cmd| {
                                 cmd.stdin(Stdio::from(file))
                                     .args(["--stdin", "-S"])
                                     .arg(type_arg)
@@ -197,21 +217,27 @@ fn run(args: Args) -> anyhow::Result<()> {
                     let visit_hlsl = |consume_config_item: &mut dyn FnMut(
                         &Path,
                         hlsl_snapshots::ConfigItem,
+                        bool,
                     )
                         -> anyhow::Result<()>| {
                         visit_files(snapshots_base_out, "hlsl/*.hlsl", |path| {
                             ack_visiting(path);
+                            let config =
+                                hlsl_snapshots::Config::from_path(path.with_extension("ron"))?;
+                            let fxc_compatible = config.is_fxc_compatible();
                             let hlsl_snapshots::Config {
                                 vertex,
                                 fragment,
                                 compute,
-                            } = hlsl_snapshots::Config::from_path(path.with_extension("ron"))?;
+                                fxc_compatible: _,
+                            } = config;
                             let mut status = ErrorStatus::NoFailuresFound;
                             [vertex, fragment, compute]
                                 .into_iter()
                                 .flatten()
                                 .for_each(|shader| {
-                                    consume_config_item(path, shader).log_if_err_found(&mut status);
+                                    consume_config_item(path, shader, fxc_compatible)
+                                        .log_if_err_found(&mut status);
                                 });
                             match status {
                                 ErrorStatus::NoFailuresFound => Ok(()),
@@ -246,35 +272,31 @@ fn run(args: Args) -> anyhow::Result<()> {
                     };
                     match cmd {
                         ValidateHlslCommand::Dxc => {
-                            let bin = "dxc";
-                            which(bin)?;
-                            visit_hlsl(&mut |file, config_item| {
+                            let bin = which("dxc")?;
+                            visit_hlsl(&mut |file, config_item, _| {
                                 // Reference:
                                 // <https://github.com/microsoft/DirectXShaderCompiler/blob/6ee4074a4b43fa23bf5ad27e4f6cafc6b835e437/tools/clang/docs/UsingDxc.rst>.
-                                validate(
-                                    bin,
-                                    file,
-                                    config_item,
-                                    &[
-                                        "-Wno-parentheses-equality",
-                                        "-Zi",
-                                        "-Qembed_debug",
-                                        "-Od",
-                                        "-HV",
-                                        "2018",
-                                    ],
-                                )
+                                let mut params = vec![
+                                    "-Wno-parentheses-equality",
+                                    "-Zi",
+                                    "-Qembed_debug",
+                                    "-Od",
+                                    "-HV",
+                                    "2018",
+                                ];
+                                // Naga writes 16-bit types as `float16_t` from shader
+                                // model 6.2, which needs this flag.
+                                if config_item.shader_model_version() >= Some((6, 2)) {
+                                    params.push("-enable-16bit-types");
+                                }
+                                validate(&bin, file, config_item, params.as_slice())
                             })
                         }
                         ValidateHlslCommand::Fxc => {
-                            let bin = "fxc";
-                            which(bin)?;
-                            visit_hlsl(&mut |file, config_item| {
-                                let Some(Ok(shader_model_major_version)) = config_item
-                                    .target_profile
-                                    .split('_')
-                                    .nth(1)
-                                    .map(|segment| segment.parse::<u8>()) else {
+                            let bin = which("fxc")?;
+                            visit_hlsl(&mut |file, config_item, fxc_compatible| {
+                                let Some(shader_model_major_version) =
+                                    config_item.shader_model_major_version() else {
                                         bail!(
                                             "expected target profile of the form \
                                             `{{model}}_{{major}}_{{minor}}`, found invalid target \
@@ -288,7 +310,13 @@ fn run(args: Args) -> anyhow::Result<()> {
                                 if shader_model_major_version < 6 {
                                     // Reference:
                                     // <https://learn.microsoft.com/en-us/windows/win32/direct3dtools/dx-graphics-tools-fxc-syntax>.
-                                    validate(bin, file, config_item, &["-Zi", "-Od"])
+                                    validate(&bin, file, config_item, &["-Zi", "-Od"])
+                                } else if fxc_compatible {
+                                    bail!(
+                                        "{} is marked as FXC-compatible, but config. item \
+                                        {config_item:?} targets shader model 6 or later",
+                                        file.display()
+                                    )
                                 } else {
                                     log::debug!(
                                         "skipping config. item {config_item:?} because the \
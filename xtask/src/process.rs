@@ -45,6 +45,21 @@ impl EasyCommand {
         );
         Ok(())
     }
+
+    /// Run the command, and return what it wrote to its standard output.
+    pub fn output_string(&mut self) -> anyhow::Result<String> {
+        let Self { inner } = self;
+        log::debug!("running {inner:?}");
+        let output = inner
+            .output()
+            .with_context(|| format!("failed to run {self}"))?;
+        ensure!(
+            output.status.success(),
+            "{self} failed to run; exit code: {:?}",
+            output.status.code()
+        );
+        String::from_utf8(output.stdout).with_context(|| format!("{self} printed invalid UTF-8"))
+    }
 }
 
 impl Deref for EasyCommand {
@@ -61,7 +76,12 @@ impl DerefMut for EasyCommand {
     }
 }
 
+/// Find `binary_name`, preferring a copy installed by `xtask install-tools`
+/// over one in the `PATH`.
 pub(crate) fn which(binary_name: &str) -> anyhow::Result<OsString> {
+    if let Some(path) = crate::tools::find_installed(binary_name) {
+        return Ok(path);
+    }
     ::which::which(binary_name)
         .with_context(|| format!("unable to find `{binary_name}` binary"))
         .map(|buf| buf.file_name().unwrap().to_owned())
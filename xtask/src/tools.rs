@@ -0,0 +1,170 @@
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context};
+
+use crate::{
+    fs::{create_dir_all, remove_file},
+    path::join_path,
+    process::EasyCommand,
+};
+
+/// An archive of validation tools for the host platform, and where in it
+/// the tools' binaries end up once it is extracted.
+struct Download {
+    url: &'static str,
+    file_name: &'static str,
+    /// The SHA-256 of the archive, in lowercase hex. `install` refuses
+    /// archives that don't match.
+    sha256: &'static str,
+    bin_dir: &'static [&'static str],
+    tools: &'static [&'static str],
+}
+
+// Bump these together with the URLs below, checksums included. When a pin
+// doesn't match, `install` prints the checksum of what it downloaded.
+//
+// The Vulkan SDK for Linux bundles all of the validators we use there.
+const LINUX_DOWNLOADS: &[Download] = &[Download {
+    url: "https://sdk.lunarg.com/sdk/download/1.3.268.0/linux/vulkansdk-linux-x86_64-1.3.268.0.tar.xz",
+    file_name: "vulkansdk-linux-x86_64-1.3.268.0.tar.xz",
+    sha256: "",
+    bin_dir: &["1.3.268.0", "x86_64", "bin"],
+    tools: &["dxc", "glslangValidator", "spirv-as", "spirv-val"],
+}];
+
+const WINDOWS_DOWNLOADS: &[Download] = &[Download {
+    url: "https://github.com/microsoft/DirectXShaderCompiler/releases/download/v1.7.2308/dxc_2023_08_14.zip",
+    file_name: "dxc_2023_08_14.zip",
+    sha256: "",
+    bin_dir: &["bin", "x64"],
+    tools: &["dxc"],
+}];
+
+fn host_downloads() -> &'static [Download] {
+    match (std::env::consts::OS, std::env::consts::ARCH) {
+        ("linux", "x86_64") => LINUX_DOWNLOADS,
+        ("windows", "x86_64") => WINDOWS_DOWNLOADS,
+        _ => &[],
+    }
+}
+
+/// The workspace-local directory that `install-tools` extracts into.
+fn tools_dir() -> PathBuf {
+    join_path(["target", "xtask-tools"])
+}
+
+/// Find `binary_name` among the tools installed by `install-tools`, if it
+/// is there.
+pub(crate) fn find_installed(binary_name: &str) -> Option<OsString> {
+    host_downloads()
+        .iter()
+        .filter(|download| download.tools.contains(&binary_name))
+        .map(|download| {
+            tools_dir()
+                .join(join_path(download.bin_dir))
+                .join(format!("{binary_name}{}", std::env::consts::EXE_SUFFIX))
+        })
+        .find(|path| path.is_file())
+        .and_then(|path| path.canonicalize().ok())
+        .map(PathBuf::into_os_string)
+}
+
+/// The SHA-256 of the file at `path`, in lowercase hex.
+fn sha256_of(path: &Path) -> anyhow::Result<String> {
+    let hash = if cfg!(windows) {
+        // `certutil` prints the hash on its second line. Older versions
+        // separate its bytes with spaces.
+        let output = EasyCommand::new("certutil", |cmd| {
+            cmd.arg("-hashfile").arg(path).arg("SHA256")
+        })
+        .output_string()?;
+        output
+            .lines()
+            .nth(1)
+            .map(|line| line.split_whitespace().collect::<String>())
+    } else {
+        let output = EasyCommand::new("sha256sum", |cmd| cmd.arg(path)).output_string()?;
+        output.split_whitespace().next().map(str::to_owned)
+    };
+    hash.map(|hash| hash.to_ascii_lowercase())
+        .with_context(|| format!("failed to hash {}", path.display()))
+}
+
+pub(crate) fn install() -> anyhow::Result<()> {
+    let downloads = host_downloads();
+    if downloads.is_empty() {
+        bail!(
+            "no pinned validation tools are available for {}-{}; install them manually",
+            std::env::consts::OS,
+            std::env::consts::ARCH
+        );
+    }
+
+    // Don't download anything that `install` would refuse anyway.
+    let unpinned: Vec<_> = downloads
+        .iter()
+        .filter(|download| download.sha256.is_empty())
+        .map(|download| download.url)
+        .collect();
+    if !unpinned.is_empty() {
+        bail!(
+            "no SHA-256 is pinned for {}; pin the checksums of the archives in \
+            `xtask/src/tools.rs`",
+            unpinned.join(", ")
+        );
+    }
+
+    let tools_dir = tools_dir();
+    create_dir_all(&tools_dir)
+        .with_context(|| format!("failed to create {}", tools_dir.display()))?;
+
+    for download in downloads {
+        let Download {
+            url,
+            file_name,
+            sha256,
+            bin_dir: _,
+            tools,
+        } = *download;
+        log::info!("downloading {url}");
+        let archive = tools_dir.join(file_name);
+        EasyCommand::new("curl", |cmd| {
+            cmd.args(["--fail", "--location", "--retry", "5", "--output"])
+                .arg(&archive)
+                .arg(url)
+        })
+        .success()?;
+
+        let actual = sha256_of(&archive)?;
+        if actual != sha256 {
+            remove_file(&archive).with_context(|| format!("failed to remove {archive:?}"))?;
+            bail!(
+                "{url} has SHA-256 {actual}, but {sha256:?} is pinned; if the archive \
+                is trustworthy, update the pin in `xtask/src/tools.rs`"
+            );
+        }
+
+        log::info!("extracting {}", archive.display());
+        EasyCommand::new("tar", |cmd| {
+            cmd.arg("-xf").arg(&archive).arg("-C").arg(&tools_dir)
+        })
+        .success()?;
+        remove_file(&archive).with_context(|| format!("failed to remove {archive:?}"))?;
+
+        for &tool in tools {
+            if find_installed(tool).is_none() {
+                bail!("{url} did not provide `{tool}` where expected");
+            }
+        }
+        log::info!("installed {}", tools.join(", "));
+    }
+
+    log::info!(
+        "`validate` subcommands will now prefer the tools in {}",
+        tools_dir.display()
+    );
+    Ok(())
+}